@@ -0,0 +1,31 @@
+-- Your SQL goes here
+create table prompt_templates (
+    id uuid primary key default gen_random_uuid(),
+    name text not null,
+    version integer not null,
+    template text not null,
+    organization_id uuid references organizations(id) on delete cascade,
+    experiment_name text,
+    variant text,
+    weight integer not null default 0,
+    active boolean not null default true,
+    created_by uuid references users(id),
+    created_at timestamp with time zone not null default now(),
+    updated_at timestamp with time zone not null default now(),
+    deleted_at timestamp with time zone
+);
+
+create index prompt_templates_name_idx on prompt_templates(name);
+create index prompt_templates_organization_id_idx on prompt_templates(organization_id);
+create index prompt_templates_deleted_at_idx on prompt_templates(deleted_at);
+
+-- One row per prompt, organization, version and variant. The nullable columns are coalesced so
+-- defaults and base templates can't be duplicated either.
+create unique index prompt_templates_name_organization_version_variant_idx on prompt_templates(
+    name,
+    coalesce(organization_id, '00000000-0000-0000-0000-000000000000'::uuid),
+    version,
+    coalesce(variant, '')
+) where deleted_at is null;
+
+alter table prompt_templates enable row level security;
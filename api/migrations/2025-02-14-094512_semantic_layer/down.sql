@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+alter table entity_relationship
+    drop column primary_key,
+    drop column foreign_key;
+
+drop table semantic_objects;
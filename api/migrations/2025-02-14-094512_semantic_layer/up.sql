@@ -0,0 +1,23 @@
+-- Your SQL goes here
+create table semantic_objects (
+    id uuid primary key default gen_random_uuid(),
+    dataset_id uuid not null references datasets(id) on delete cascade,
+    name text not null,
+    object_type text not null,
+    expr text not null,
+    agg text,
+    description text,
+    created_at timestamp with time zone not null default now(),
+    updated_at timestamp with time zone not null default now(),
+    deleted_at timestamp with time zone,
+    unique (dataset_id, name)
+);
+
+create index semantic_objects_dataset_id_idx on semantic_objects(dataset_id);
+create index semantic_objects_deleted_at_idx on semantic_objects(deleted_at);
+
+alter table semantic_objects enable row level security;
+
+alter table entity_relationship
+    add column primary_key text,
+    add column foreign_key text;
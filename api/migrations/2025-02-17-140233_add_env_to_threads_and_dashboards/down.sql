@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+drop index if exists threads_env_idx;
+drop index if exists dashboards_env_idx;
+
+alter table threads drop column env;
+
+alter table dashboards drop column env;
@@ -0,0 +1,9 @@
+-- Your SQL goes here
+alter table threads
+    add column env varchar not null default 'dev';
+
+alter table dashboards
+    add column env varchar not null default 'dev';
+
+create index threads_env_idx on threads(env);
+create index dashboards_env_idx on dashboards(env);
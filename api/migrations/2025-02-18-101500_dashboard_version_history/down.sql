@@ -0,0 +1,11 @@
+-- This file should undo anything in `up.sql`
+alter table dashboard_versions
+    drop constraint if exists dashboard_versions_dashboard_id_version_number_key;
+
+alter table dashboard_versions
+    drop column restored_from,
+    drop column created_by,
+    drop column thread_ids,
+    drop column description,
+    drop column name,
+    drop column version_number;
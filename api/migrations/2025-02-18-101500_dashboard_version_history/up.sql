@@ -0,0 +1,20 @@
+-- Your SQL goes here
+alter table dashboard_versions
+    add column version_number integer not null default 1,
+    add column name text not null default '',
+    add column description text,
+    add column thread_ids uuid[] not null default '{}',
+    add column created_by uuid references users(id),
+    add column restored_from uuid references dashboard_versions(id) on delete set null;
+
+-- Number any existing versions in the order they were created
+update dashboard_versions
+set version_number = numbered.version_number
+from (
+    select id, row_number() over (partition by dashboard_id order by created_at, id) as version_number
+    from dashboard_versions
+) numbered
+where dashboard_versions.id = numbered.id;
+
+alter table dashboard_versions
+    add constraint dashboard_versions_dashboard_id_version_number_key unique (dashboard_id, version_number);
@@ -0,0 +1,22 @@
+-- Your SQL goes here
+create table report_subscriptions (
+    id uuid primary key default gen_random_uuid(),
+    asset_id uuid not null,
+    asset_type asset_type_enum not null,
+    user_id uuid not null references users(id) on delete cascade,
+    organization_id uuid not null references organizations(id) on delete cascade,
+    cron text not null,
+    timezone text not null default 'UTC',
+    enabled boolean not null default true,
+    last_sent_at timestamp with time zone,
+    last_error text,
+    created_at timestamp with time zone not null default now(),
+    updated_at timestamp with time zone not null default now(),
+    deleted_at timestamp with time zone
+);
+
+create index report_subscriptions_asset_idx on report_subscriptions(asset_id, asset_type);
+create index report_subscriptions_user_id_idx on report_subscriptions(user_id);
+create index report_subscriptions_enabled_idx on report_subscriptions(enabled) where deleted_at is null;
+
+alter table report_subscriptions enable row level security;
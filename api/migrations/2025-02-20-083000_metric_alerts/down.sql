@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+drop table metric_alert_history;
+drop table metric_alerts;
@@ -0,0 +1,42 @@
+-- Your SQL goes here
+create table metric_alerts (
+    id uuid primary key default gen_random_uuid(),
+    thread_id uuid not null references threads(id) on delete cascade,
+    user_id uuid not null references users(id) on delete cascade,
+    organization_id uuid not null references organizations(id) on delete cascade,
+    name text not null,
+    column_name text not null,
+    condition jsonb not null,
+    cron text not null,
+    timezone text not null default 'UTC',
+    notify_email boolean not null default true,
+    webhook_url text,
+    enabled boolean not null default true,
+    state text not null default 'ok',
+    last_value double precision,
+    last_evaluated_at timestamp with time zone,
+    last_error text,
+    created_at timestamp with time zone not null default now(),
+    updated_at timestamp with time zone not null default now(),
+    deleted_at timestamp with time zone
+);
+
+create index metric_alerts_thread_id_idx on metric_alerts(thread_id);
+create index metric_alerts_user_id_idx on metric_alerts(user_id);
+create index metric_alerts_enabled_idx on metric_alerts(enabled) where deleted_at is null;
+
+create table metric_alert_history (
+    id uuid primary key default gen_random_uuid(),
+    alert_id uuid not null references metric_alerts(id) on delete cascade,
+    state text not null,
+    value double precision not null,
+    baseline_mean double precision,
+    baseline_std_dev double precision,
+    message text not null,
+    created_at timestamp with time zone not null default now()
+);
+
+create index metric_alert_history_alert_id_created_at_idx on metric_alert_history(alert_id, created_at desc);
+
+alter table metric_alerts enable row level security;
+alter table metric_alert_history enable row level security;
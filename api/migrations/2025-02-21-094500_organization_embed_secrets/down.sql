@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+alter table organizations drop column embed_secret_id;
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+alter table organizations add column embed_secret_id uuid;
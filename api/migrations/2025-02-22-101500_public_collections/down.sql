@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+alter table collections
+    drop column publicly_accessible,
+    drop column publicly_enabled_by,
+    drop column public_expiry_date,
+    drop column password_secret_id;
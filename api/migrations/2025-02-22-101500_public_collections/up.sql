@@ -0,0 +1,6 @@
+-- Your SQL goes here
+alter table collections
+    add column publicly_accessible boolean not null default false,
+    add column publicly_enabled_by uuid references users(id) on update cascade,
+    add column public_expiry_date timestamptz,
+    add column password_secret_id uuid;
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE datasets ADD COLUMN verification verification_enum NOT NULL DEFAULT 'notRequested';
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE threads ADD COLUMN draft_state JSONB;
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+alter table metric_alerts drop column time_column_name;
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+alter table metric_alerts add column time_column_name text;
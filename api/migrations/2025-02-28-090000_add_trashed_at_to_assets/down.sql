@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+alter table dashboards drop column trashed_at;
+alter table threads drop column trashed_at;
+alter table collections drop column trashed_at;
+alter table datasets drop column trashed_at;
+alter table terms drop column trashed_at;
+alter table data_sources drop column trashed_at;
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+alter table dashboards add column trashed_at timestamptz;
+alter table threads add column trashed_at timestamptz;
+alter table collections add column trashed_at timestamptz;
+alter table datasets add column trashed_at timestamptz;
+alter table terms add column trashed_at timestamptz;
+alter table data_sources add column trashed_at timestamptz;
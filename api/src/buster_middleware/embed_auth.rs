@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+
+use axum::{extract::Request, http::StatusCode, middleware::Next, response::Response};
+
+use crate::utils::embed::tokens::{decode_embed_token, embed_token_organization, embed_viewer};
+
+use super::auth::auth;
+
+/// Routes for embedded dashboards only accept embed tokens. The verified `EmbedClaims` are added to
+/// the request extensions.
+pub async fn embed_auth(mut req: Request, next: Next) -> Result<Response, StatusCode> {
+    let token = match request_token(&req) {
+        Some(token) => token,
+        None => return Err(StatusCode::UNAUTHORIZED),
+    };
+
+    let claims = match decode_embed_token(&token).await {
+        Ok(claims) => claims,
+        Err(e) => {
+            tracing::error!("Embed authorization error: {}", e);
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+    };
+
+    req.extensions_mut().insert(claims);
+    Ok(next.run(req).await)
+}
+
+/// The WebSocket accepts embed tokens as well as the usual ones. An embedded connection runs as a
+/// throwaway viewer and carries its `EmbedClaims`, which limit it to the token's dashboard.
+pub async fn ws_auth(mut req: Request, next: Next) -> Result<Response, StatusCode> {
+    let token = match request_token(&req) {
+        Some(token) if embed_token_organization(&token).is_some() => token,
+        _ => return auth(req, next).await,
+    };
+
+    let claims = match decode_embed_token(&token).await {
+        Ok(claims) => claims,
+        Err(e) => {
+            tracing::error!("Embed authorization error: {}", e);
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+    };
+
+    req.extensions_mut().insert(embed_viewer(&claims));
+    req.extensions_mut().insert(claims);
+    Ok(next.run(req).await)
+}
+
+// Tokens come in the Authorization header or, for WebSockets, the `authentication` query param.
+fn request_token(req: &Request) -> Option<String> {
+    let bearer_token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| match value.strip_prefix("Bearer ") {
+            Some(token) => token.split_whitespace().next(),
+            None => Some(value),
+        });
+
+    match bearer_token {
+        Some(token) => Some(token.to_string()),
+        None => req
+            .uri()
+            .query()
+            .and_then(|query| serde_urlencoded::from_str::<HashMap<String, String>>(query).ok())
+            .and_then(|params| params.get("authentication").cloned()),
+    }
+}
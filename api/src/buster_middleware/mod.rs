@@ -1,2 +1,3 @@
 pub mod auth;
-pub mod cors;
\ No newline at end of file
+pub mod cors;
+pub mod embed_auth;
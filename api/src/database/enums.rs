@@ -4,7 +4,9 @@ use diesel::pg::{Pg, PgValue};
 use diesel::serialize::{self, IsNull, Output, ToSql};
 use diesel::sql_types::Text;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::io::Write;
+use std::str::FromStr;
 
 #[derive(
     Debug,
@@ -392,6 +394,7 @@ impl FromSql<sql_types::DatasetTypeEnum, Pg> for DatasetType {
     Copy,
     PartialEq,
     Eq,
+    Hash,
     diesel::AsExpression,
     diesel::FromSqlRow,
 )]
@@ -524,6 +527,77 @@ impl FromSql<Text, Pg> for DataSourceType {
     }
 }
 
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    diesel::AsExpression,
+    diesel::FromSqlRow,
+)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "lowercase")]
+pub enum SemanticObjectType {
+    Dimension,
+    Measure,
+    Metric,
+    Segment,
+}
+
+impl SemanticObjectType {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            SemanticObjectType::Dimension => "dimension",
+            SemanticObjectType::Measure => "measure",
+            SemanticObjectType::Metric => "metric",
+            SemanticObjectType::Segment => "segment",
+        }
+    }
+}
+
+impl FromStr for SemanticObjectType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dimension" => Ok(SemanticObjectType::Dimension),
+            "measure" => Ok(SemanticObjectType::Measure),
+            "metric" => Ok(SemanticObjectType::Metric),
+            "segment" => Ok(SemanticObjectType::Segment),
+            _ => Err(format!("Unknown semantic object type '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for SemanticObjectType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl ToSql<Text, Pg> for SemanticObjectType {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for SemanticObjectType {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"dimension" => Ok(SemanticObjectType::Dimension),
+            b"measure" => Ok(SemanticObjectType::Measure),
+            b"metric" => Ok(SemanticObjectType::Metric),
+            b"segment" => Ok(SemanticObjectType::Segment),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
+
 impl ToSql<sql_types::AssetTypeEnum, Pg> for AssetType {
     fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
         match *self {
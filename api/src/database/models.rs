@@ -47,7 +47,9 @@ pub struct ApiKey {
     pub deleted_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Queryable, Identifiable, Associations, Debug)]
+#[derive(
+    Serialize, Queryable, Selectable, Insertable, Identifiable, Associations, Debug, Clone,
+)]
 #[diesel(belongs_to(Dashboard, foreign_key = dashboard_id))]
 #[diesel(table_name = dashboard_versions)]
 pub struct DashboardVersion {
@@ -57,6 +59,12 @@ pub struct DashboardVersion {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
+    pub version_number: i32,
+    pub name: String,
+    pub description: Option<String>,
+    pub thread_ids: Vec<Uuid>,
+    pub created_by: Option<Uuid>,
+    pub restored_from: Option<Uuid>,
 }
 
 #[derive(
@@ -89,6 +97,9 @@ pub struct Dashboard {
     pub updated_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
     pub organization_id: Uuid,
+    pub env: String,
+    #[serde(skip_serializing)]
+    pub trashed_at: Option<DateTime<Utc>>,
 }
 
 #[derive(
@@ -113,6 +124,8 @@ pub struct DataSource {
     pub updated_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
     pub env: String,
+    #[serde(skip_serializing)]
+    pub trashed_at: Option<DateTime<Utc>>,
 }
 
 #[derive(
@@ -190,6 +203,9 @@ pub struct Dataset {
     pub model: Option<String>,
     pub yml_file: Option<String>,
     pub database_identifier: Option<String>,
+    pub verification: Verification,
+    #[serde(skip_serializing)]
+    pub trashed_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Insertable, Queryable, Associations, Debug)]
@@ -219,6 +235,13 @@ pub struct Collection {
     pub updated_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
     pub organization_id: Uuid,
+    pub publicly_accessible: bool,
+    pub publicly_enabled_by: Option<Uuid>,
+    pub public_expiry_date: Option<DateTime<Utc>>,
+    #[serde(skip_serializing)]
+    pub password_secret_id: Option<Uuid>,
+    #[serde(skip_serializing)]
+    pub trashed_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Queryable, Insertable, Identifiable, Debug, Clone, Serialize, Deserialize)]
@@ -230,6 +253,8 @@ pub struct Organization {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing, default)]
+    pub embed_secret_id: Option<Uuid>,
 }
 
 #[derive(
@@ -325,6 +350,7 @@ pub struct Message {
     #[serde(rename = "description")]
     pub summary_question: Option<String>,
     pub sql_evaluation_id: Option<Uuid>,
+    pub follow_up_suggestions: Option<serde_json::Value>,
 }
 
 #[derive(Selectable, Queryable, Insertable, Identifiable, Associations, Debug, Serialize)]
@@ -367,6 +393,8 @@ pub struct Term {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing)]
+    pub trashed_at: Option<DateTime<Utc>>,
 }
 
 #[derive(
@@ -397,6 +425,11 @@ pub struct Thread {
     pub updated_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
     pub organization_id: Uuid,
+    pub env: String,
+    #[serde(skip_serializing)]
+    pub draft_state: Option<serde_json::Value>,
+    #[serde(skip_serializing)]
+    pub trashed_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Queryable, Insertable, Associations, Debug)]
@@ -502,6 +535,95 @@ pub struct TermToDataset {
     pub deleted_at: Option<DateTime<Utc>>,
 }
 
+#[derive(Queryable, Insertable, Selectable, Debug, Clone, Serialize, Deserialize)]
+#[diesel(table_name = prompt_templates)]
+pub struct PromptTemplate {
+    pub id: Uuid,
+    pub name: String,
+    pub version: i32,
+    pub template: String,
+    pub organization_id: Option<Uuid>,
+    pub experiment_name: Option<String>,
+    pub variant: Option<String>,
+    pub weight: i32,
+    pub active: bool,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Queryable, Insertable, Selectable, Debug, Clone, Serialize, Deserialize)]
+#[diesel(table_name = metric_alerts)]
+pub struct MetricAlert {
+    pub id: Uuid,
+    pub thread_id: Uuid,
+    pub user_id: Uuid,
+    pub organization_id: Uuid,
+    pub name: String,
+    pub column_name: String,
+    pub condition: Value,
+    pub cron: String,
+    pub timezone: String,
+    pub notify_email: bool,
+    pub webhook_url: Option<String>,
+    pub enabled: bool,
+    pub state: String,
+    pub last_value: Option<f64>,
+    pub last_evaluated_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub time_column_name: Option<String>,
+}
+
+#[derive(Queryable, Insertable, Selectable, Debug, Clone, Serialize, Deserialize)]
+#[diesel(table_name = metric_alert_history)]
+pub struct MetricAlertHistory {
+    pub id: Uuid,
+    pub alert_id: Uuid,
+    pub state: String,
+    pub value: f64,
+    pub baseline_mean: Option<f64>,
+    pub baseline_std_dev: Option<f64>,
+    pub message: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Queryable, Insertable, Selectable, Debug, Clone, Serialize, Deserialize)]
+#[diesel(table_name = report_subscriptions)]
+pub struct ReportSubscription {
+    pub id: Uuid,
+    pub asset_id: Uuid,
+    pub asset_type: AssetType,
+    pub user_id: Uuid,
+    pub organization_id: Uuid,
+    pub cron: String,
+    pub timezone: String,
+    pub enabled: bool,
+    pub last_sent_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Queryable, Insertable, Selectable, Debug, Clone, Serialize, Deserialize)]
+#[diesel(table_name = semantic_objects)]
+pub struct SemanticObject {
+    pub id: Uuid,
+    pub dataset_id: Uuid,
+    pub name: String,
+    pub object_type: SemanticObjectType,
+    pub expr: String,
+    pub agg: Option<String>,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
 #[derive(Queryable, Insertable, Debug, Serialize, Deserialize)]
 #[diesel(table_name = sql_evaluations)]
 pub struct SqlEvaluation {
@@ -521,6 +643,8 @@ pub struct EntityRelationship {
     pub foreign_dataset_id: Uuid,
     pub relationship_type: String,
     pub created_at: DateTime<Utc>,
+    pub primary_key: Option<String>,
+    pub foreign_key: Option<String>,
 }
 
 #[derive(Queryable, Insertable, Debug)]
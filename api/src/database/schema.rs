@@ -93,6 +93,11 @@ diesel::table! {
         updated_at -> Timestamptz,
         deleted_at -> Nullable<Timestamptz>,
         organization_id -> Uuid,
+        publicly_accessible -> Bool,
+        publicly_enabled_by -> Nullable<Uuid>,
+        public_expiry_date -> Nullable<Timestamptz>,
+        password_secret_id -> Nullable<Uuid>,
+        trashed_at -> Nullable<Timestamptz>,
     }
 }
 
@@ -120,6 +125,12 @@ diesel::table! {
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
         deleted_at -> Nullable<Timestamptz>,
+        version_number -> Int4,
+        name -> Text,
+        description -> Nullable<Text>,
+        thread_ids -> Array<Uuid>,
+        created_by -> Nullable<Uuid>,
+        restored_from -> Nullable<Uuid>,
     }
 }
 
@@ -139,6 +150,8 @@ diesel::table! {
         updated_at -> Timestamptz,
         deleted_at -> Nullable<Timestamptz>,
         organization_id -> Uuid,
+        env -> Varchar,
+        trashed_at -> Nullable<Timestamptz>,
     }
 }
 
@@ -161,6 +174,7 @@ diesel::table! {
         updated_at -> Timestamptz,
         deleted_at -> Nullable<Timestamptz>,
         env -> Varchar,
+        trashed_at -> Nullable<Timestamptz>,
     }
 }
 
@@ -230,6 +244,7 @@ diesel::table! {
 diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::DatasetTypeEnum;
+    use super::sql_types::VerificationEnum;
 
     datasets (id) {
         id -> Uuid,
@@ -253,6 +268,8 @@ diesel::table! {
         model -> Nullable<Text>,
         yml_file -> Nullable<Text>,
         database_identifier -> Nullable<Text>,
+        verification -> VerificationEnum,
+        trashed_at -> Nullable<Timestamptz>,
     }
 }
 
@@ -282,6 +299,8 @@ diesel::table! {
         foreign_dataset_id -> Uuid,
         relationship_type -> Text,
         created_at -> Timestamptz,
+        primary_key -> Nullable<Text>,
+        foreign_key -> Nullable<Text>,
     }
 }
 
@@ -313,6 +332,45 @@ diesel::table! {
         draft_state -> Nullable<Jsonb>,
         summary_question -> Nullable<Text>,
         sql_evaluation_id -> Nullable<Uuid>,
+        follow_up_suggestions -> Nullable<Jsonb>,
+    }
+}
+
+diesel::table! {
+    metric_alert_history (id) {
+        id -> Uuid,
+        alert_id -> Uuid,
+        state -> Text,
+        value -> Float8,
+        baseline_mean -> Nullable<Float8>,
+        baseline_std_dev -> Nullable<Float8>,
+        message -> Text,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    metric_alerts (id) {
+        id -> Uuid,
+        thread_id -> Uuid,
+        user_id -> Uuid,
+        organization_id -> Uuid,
+        name -> Text,
+        column_name -> Text,
+        condition -> Jsonb,
+        cron -> Text,
+        timezone -> Text,
+        notify_email -> Bool,
+        webhook_url -> Nullable<Text>,
+        enabled -> Bool,
+        state -> Text,
+        last_value -> Nullable<Float8>,
+        last_evaluated_at -> Nullable<Timestamptz>,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        deleted_at -> Nullable<Timestamptz>,
+        time_column_name -> Nullable<Text>,
     }
 }
 
@@ -324,6 +382,7 @@ diesel::table! {
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
         deleted_at -> Nullable<Timestamptz>,
+        embed_secret_id -> Nullable<Uuid>,
     }
 }
 
@@ -364,6 +423,60 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    prompt_templates (id) {
+        id -> Uuid,
+        name -> Text,
+        version -> Int4,
+        template -> Text,
+        organization_id -> Nullable<Uuid>,
+        experiment_name -> Nullable<Text>,
+        variant -> Nullable<Text>,
+        weight -> Int4,
+        active -> Bool,
+        created_by -> Nullable<Uuid>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        deleted_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::AssetTypeEnum;
+
+    report_subscriptions (id) {
+        id -> Uuid,
+        asset_id -> Uuid,
+        asset_type -> AssetTypeEnum,
+        user_id -> Uuid,
+        organization_id -> Uuid,
+        cron -> Text,
+        timezone -> Text,
+        enabled -> Bool,
+        last_sent_at -> Nullable<Timestamptz>,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        deleted_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    semantic_objects (id) {
+        id -> Uuid,
+        dataset_id -> Uuid,
+        name -> Text,
+        object_type -> Text,
+        expr -> Text,
+        agg -> Nullable<Text>,
+        description -> Nullable<Text>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        deleted_at -> Nullable<Timestamptz>,
+    }
+}
+
 diesel::table! {
     sql_evaluations (id) {
         id -> Uuid,
@@ -422,6 +535,7 @@ diesel::table! {
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
         deleted_at -> Nullable<Timestamptz>,
+        trashed_at -> Nullable<Timestamptz>,
     }
 }
 
@@ -450,6 +564,9 @@ diesel::table! {
         updated_at -> Timestamptz,
         deleted_at -> Nullable<Timestamptz>,
         organization_id -> Uuid,
+        env -> Varchar,
+        draft_state -> Nullable<Jsonb>,
+        trashed_at -> Nullable<Timestamptz>,
     }
 }
 
@@ -519,6 +636,7 @@ diesel::joinable!(api_keys -> organizations (organization_id));
 diesel::joinable!(api_keys -> users (owner_id));
 diesel::joinable!(collections -> organizations (organization_id));
 diesel::joinable!(dashboard_versions -> dashboards (dashboard_id));
+diesel::joinable!(dashboard_versions -> users (created_by));
 diesel::joinable!(dashboards -> organizations (organization_id));
 diesel::joinable!(data_sources -> organizations (organization_id));
 diesel::joinable!(dataset_groups -> organizations (organization_id));
@@ -538,6 +656,15 @@ diesel::joinable!(messages -> users (sent_by));
 diesel::joinable!(permission_groups -> organizations (organization_id));
 diesel::joinable!(permission_groups_to_users -> permission_groups (permission_group_id));
 diesel::joinable!(permission_groups_to_users -> users (user_id));
+diesel::joinable!(metric_alert_history -> metric_alerts (alert_id));
+diesel::joinable!(metric_alerts -> organizations (organization_id));
+diesel::joinable!(metric_alerts -> threads (thread_id));
+diesel::joinable!(metric_alerts -> users (user_id));
+diesel::joinable!(prompt_templates -> organizations (organization_id));
+diesel::joinable!(prompt_templates -> users (created_by));
+diesel::joinable!(report_subscriptions -> organizations (organization_id));
+diesel::joinable!(report_subscriptions -> users (user_id));
+diesel::joinable!(semantic_objects -> datasets (dataset_id));
 diesel::joinable!(teams -> organizations (organization_id));
 diesel::joinable!(teams -> users (created_by));
 diesel::joinable!(teams_to_users -> teams (team_id));
@@ -569,10 +696,15 @@ diesel::allow_tables_to_appear_in_same_query!(
     datasets_to_permission_groups,
     entity_relationship,
     messages,
+    metric_alert_history,
+    metric_alerts,
     organizations,
     permission_groups,
     permission_groups_to_identities,
     permission_groups_to_users,
+    prompt_templates,
+    report_subscriptions,
+    semantic_objects,
     sql_evaluations,
     teams,
     teams_to_users,
@@ -93,6 +93,7 @@ impl DataSource {
                 data_sources::updated_at,
                 data_sources::deleted_at,
                 data_sources::env,
+                data_sources::trashed_at,
             ))
             .first::<DataSource>(&mut conn)
             .await
@@ -134,6 +135,7 @@ impl DataSource {
                 data_sources::updated_at,
                 data_sources::deleted_at,
                 data_sources::env,
+                data_sources::trashed_at,
             ))
             .first::<DataSource>(&mut conn)
             .await
@@ -173,6 +175,7 @@ impl Term {
                 terms::created_at,
                 terms::updated_at,
                 terms::deleted_at,
+                terms::trashed_at,
             ))
             .first::<Term>(&mut conn)
             .await
@@ -229,6 +232,8 @@ impl Dashboard {
                     dashboards::updated_at,
                     dashboards::deleted_at,
                     dashboards::organization_id,
+                    dashboards::env,
+                    dashboards::trashed_at,
                 ),
                 asset_permissions::role,
             ))
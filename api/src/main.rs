@@ -60,6 +60,10 @@ async fn main() {
 
     tracing::info!("Successfully ran database migrations");
 
+    utils::reports::scheduler::start_report_scheduler();
+    utils::alerts::evaluator::start_alert_scheduler();
+    utils::trash::retention::start_trash_retention_job();
+
     let protected_router = Router::new().nest("/api/v1", routes::protected_router());
     let public_router = Router::new().route("/health", axum::routing::get(|| async { "OK" }));
 
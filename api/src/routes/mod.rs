@@ -3,7 +3,7 @@ pub mod ws;
 
 use axum::{middleware, routing::get, Router};
 
-use crate::buster_middleware::auth::auth;
+use crate::buster_middleware::embed_auth::ws_auth;
 
 pub fn protected_router() -> Router {
     Router::new()
@@ -12,7 +12,7 @@ pub fn protected_router() -> Router {
         .merge(
             Router::new()
                 .nest("/ws", ws::router())
-                .route_layer(middleware::from_fn(auth)),
+                .route_layer(middleware::from_fn(ws_auth)),
         )
 }
 
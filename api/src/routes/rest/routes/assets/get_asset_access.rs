@@ -14,10 +14,11 @@ use crate::database::enums::{AssetPermissionRole, AssetType, UserOrganizationRol
 use crate::database::lib::{get_pg_pool, PgPool};
 use crate::database::models::User;
 use crate::database::schema::{
-    asset_permissions, collections_to_assets, dashboards, teams_to_users, threads,
+    asset_permissions, collections, collections_to_assets, dashboards, teams_to_users, threads,
     threads_to_dashboards, users_to_organizations,
 };
 use crate::routes::rest::ApiResponse;
+use crate::routes::ws::collections::collection_utils::get_user_collection_permission;
 use crate::utils::user::user_info::get_user_organization_id;
 
 pub async fn get_asset_access(
@@ -55,9 +56,25 @@ async fn get_asset_access_handler(
 
     let (asset_info, user_permission) = match asset_type {
         AssetType::Collection => {
-            return Err(anyhow!(
-                "Public access is not supported for collections yet"
-            ))
+            let mut conn = pg_pool.get().await?;
+
+            let collection_info = collections::table
+                .select((
+                    collections::id,
+                    collections::publicly_accessible,
+                    collections::password_secret_id.is_not_null(),
+                    collections::public_expiry_date,
+                ))
+                .filter(collections::id.eq(&asset_id))
+                .filter(collections::deleted_at.is_null())
+                .first::<(Uuid, bool, bool, Option<DateTime<Utc>>)>(&mut conn)
+                .await?;
+
+            let user_permission = get_user_collection_permission(&user.id, &asset_id)
+                .await
+                .ok(); // The collection has no permissions for users it isn't shared with
+
+            (collection_info, user_permission)
         }
         AssetType::Dashboard => {
             let mut conn = pg_pool.get().await?;
@@ -0,0 +1,46 @@
+use axum::{
+    extract::{Path, Query},
+    Extension,
+};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    database::models::User,
+    routes::{
+        rest::ApiResponse, ws::dashboards::dashboard_versions::check_dashboard_version_access,
+    },
+    utils::dashboards::versions::{
+        diff_dashboard_versions as diff_versions, get_dashboard_version, DashboardVersionDiff,
+    },
+};
+
+#[derive(Debug, Deserialize)]
+pub struct DiffDashboardVersionsQuery {
+    pub from: Uuid,
+    pub to: Uuid,
+}
+
+pub async fn diff_dashboard_versions(
+    Extension(user): Extension<User>,
+    Path(dashboard_id): Path<Uuid>,
+    Query(query): Query<DiffDashboardVersionsQuery>,
+) -> Result<ApiResponse<DashboardVersionDiff>, (StatusCode, String)> {
+    if let Err(e) = check_dashboard_version_access(&user.id, &dashboard_id, false).await {
+        tracing::error!("Error checking dashboard permissions: {:?}", e);
+        return Err((StatusCode::FORBIDDEN, e.to_string()));
+    }
+
+    let from = match get_dashboard_version(&dashboard_id, &query.from).await {
+        Ok(version) => version,
+        Err(e) => return Err((StatusCode::NOT_FOUND, e.to_string())),
+    };
+
+    let to = match get_dashboard_version(&dashboard_id, &query.to).await {
+        Ok(version) => version,
+        Err(e) => return Err((StatusCode::NOT_FOUND, e.to_string())),
+    };
+
+    Ok(ApiResponse::JsonData(diff_versions(&from, &to)))
+}
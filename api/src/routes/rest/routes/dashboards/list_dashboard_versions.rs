@@ -0,0 +1,31 @@
+use axum::{extract::Path, Extension};
+use reqwest::StatusCode;
+use uuid::Uuid;
+
+use crate::{
+    database::models::User,
+    routes::{
+        rest::ApiResponse, ws::dashboards::dashboard_versions::check_dashboard_version_access,
+    },
+    utils::dashboards::versions::{
+        list_dashboard_versions as list_versions, DashboardVersionSummary,
+    },
+};
+
+pub async fn list_dashboard_versions(
+    Extension(user): Extension<User>,
+    Path(dashboard_id): Path<Uuid>,
+) -> Result<ApiResponse<Vec<DashboardVersionSummary>>, (StatusCode, String)> {
+    if let Err(e) = check_dashboard_version_access(&user.id, &dashboard_id, false).await {
+        tracing::error!("Error checking dashboard permissions: {:?}", e);
+        return Err((StatusCode::FORBIDDEN, e.to_string()));
+    }
+
+    match list_versions(&dashboard_id).await {
+        Ok(versions) => Ok(ApiResponse::JsonData(versions)),
+        Err(e) => {
+            tracing::error!("Error listing dashboard versions: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
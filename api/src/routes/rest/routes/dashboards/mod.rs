@@ -0,0 +1,24 @@
+mod diff_dashboard_versions;
+mod list_dashboard_versions;
+mod restore_dashboard_version;
+
+use axum::{
+    routing::{get, post},
+    Router,
+};
+
+pub fn router() -> Router {
+    Router::new()
+        .route(
+            "/:dashboard_id/versions",
+            get(list_dashboard_versions::list_dashboard_versions),
+        )
+        .route(
+            "/:dashboard_id/versions/diff",
+            get(diff_dashboard_versions::diff_dashboard_versions),
+        )
+        .route(
+            "/:dashboard_id/versions/:version_id/restore",
+            post(restore_dashboard_version::restore_dashboard_version),
+        )
+}
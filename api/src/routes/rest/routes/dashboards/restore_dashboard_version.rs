@@ -0,0 +1,39 @@
+use axum::{extract::Path, Extension};
+use reqwest::StatusCode;
+use uuid::Uuid;
+
+use crate::{
+    database::models::{DashboardVersion, User},
+    routes::{
+        rest::ApiResponse,
+        ws::dashboards::dashboard_versions::{
+            broadcast_restored_dashboard, check_dashboard_version_access,
+        },
+    },
+    utils::dashboards::versions::restore_dashboard_version as restore_version,
+};
+
+/// Restores a dashboard version and sends the result to everyone viewing the dashboard.
+pub async fn restore_dashboard_version(
+    Extension(user): Extension<User>,
+    Path((dashboard_id, version_id)): Path<(Uuid, Uuid)>,
+) -> Result<ApiResponse<DashboardVersion>, (StatusCode, String)> {
+    if let Err(e) = check_dashboard_version_access(&user.id, &dashboard_id, true).await {
+        tracing::error!("Error checking dashboard permissions: {:?}", e);
+        return Err((StatusCode::FORBIDDEN, e.to_string()));
+    }
+
+    let version = match restore_version(&dashboard_id, &version_id, &user.id).await {
+        Ok(version) => version,
+        Err(e) => {
+            tracing::error!("Error restoring dashboard version: {:?}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+    };
+
+    if let Err(e) = broadcast_restored_dashboard(&user, &dashboard_id).await {
+        tracing::error!("Error broadcasting restored dashboard: {:?}", e);
+    }
+
+    Ok(ApiResponse::JsonData(version))
+}
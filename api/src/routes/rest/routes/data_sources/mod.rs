@@ -1,8 +1,10 @@
 mod post_data_sources;
+mod test_data_source;
 
 use axum::{routing::post, Router};
 
 pub fn router() -> Router {
     Router::new()
         .route("/", post(post_data_sources::post_data_sources))
+        .route("/test", post(test_data_source::test_data_source))
 }
@@ -143,6 +143,7 @@ async fn post_data_sources_handler(
                 onboarding_status: DataSourceOnboardingStatus::NotStarted,
                 onboarding_error: None,
                 env: request.env.clone(),
+                trashed_at: None,
             }
         })
         .collect::<Vec<DataSource>>();
@@ -161,6 +162,7 @@ async fn post_data_sources_handler(
             data_sources::secret_id.eq(excluded(data_sources::secret_id)),
             data_sources::updated_at.eq(chrono::Utc::now()),
             data_sources::deleted_at.eq(Option::<DateTime<Utc>>::None),
+            data_sources::trashed_at.eq(Option::<DateTime<Utc>>::None),
             data_sources::env.eq(excluded(data_sources::env)),
         ))
         .execute(&mut conn)
@@ -0,0 +1,60 @@
+use axum::{extract::Json, Extension};
+use reqwest::StatusCode;
+use serde::Deserialize;
+
+use crate::{
+    database::models::User,
+    routes::rest::ApiResponse,
+    utils::{
+        query_engine::{
+            credentials::Credential, test_data_source_connections::test_data_source_connection,
+        },
+        security::checks::is_user_workspace_admin_or_data_admin,
+        user::user_info::get_user_organization_id,
+    },
+};
+
+#[derive(Debug, Deserialize)]
+pub struct TestDataSourceRequest {
+    #[serde(flatten)]
+    pub credential: Credential,
+}
+
+/// Checks that the server can connect with the given credentials, without saving them.
+pub async fn test_data_source(
+    Extension(user): Extension<User>,
+    Json(request): Json<TestDataSourceRequest>,
+) -> Result<ApiResponse<()>, (StatusCode, String)> {
+    let organization_id = match get_user_organization_id(&user.id).await {
+        Ok(id) => id,
+        Err(e) => {
+            tracing::error!("Error getting user organization id: {:?}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error getting user organization id".to_string(),
+            ));
+        }
+    };
+
+    match is_user_workspace_admin_or_data_admin(&user, &organization_id).await {
+        Ok(true) => (),
+        Ok(false) => {
+            return Err((
+                StatusCode::FORBIDDEN,
+                "Insufficient permissions".to_string(),
+            ))
+        }
+        Err(e) => {
+            tracing::error!("Error checking user permissions: {:?}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+    }
+
+    match test_data_source_connection(&request.credential.get_type(), &request.credential).await {
+        Ok(_) => Ok(ApiResponse::OK),
+        Err(e) => Err((
+            StatusCode::BAD_REQUEST,
+            format!("Unable to connect to the data source: {}", e),
+        )),
+    }
+}
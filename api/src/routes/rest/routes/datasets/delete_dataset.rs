@@ -70,9 +70,14 @@ async fn delete_dataset_handler(user: &User, dataset_id: Uuid) -> Result<()> {
     }
 
     // Soft delete the dataset
+    let now = Utc::now();
+
     update(datasets::table)
         .filter(datasets::id.eq(dataset_id))
-        .set(datasets::deleted_at.eq(Some(Utc::now())))
+        .set((
+            datasets::deleted_at.eq(Some(now)),
+            datasets::trashed_at.eq(Some(now)),
+        ))
         .execute(&mut conn)
         .await
         .map_err(|e| anyhow!("Error updating dataset: {}", e))?;
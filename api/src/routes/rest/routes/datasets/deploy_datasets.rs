@@ -1,7 +1,15 @@
 use anyhow::{anyhow, Result};
-use axum::{extract::Json, Extension};
+use axum::{
+    extract::{Json, Query},
+    Extension,
+};
 use chrono::{DateTime, Utc};
-use diesel::{upsert::excluded, ExpressionMethods, QueryDsl, SelectableHelper};
+use diesel::{
+    dsl::sql,
+    sql_types::{Nullable, Text},
+    upsert::excluded,
+    ExpressionMethods, QueryDsl, SelectableHelper,
+};
 use diesel_async::RunQueryDsl;
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
@@ -11,23 +19,32 @@ use uuid::Uuid;
 
 use crate::{
     database::{
-        enums::DatasetType,
+        enums::{DatasetType, SemanticObjectType, Verification},
         lib::get_pg_pool,
-        models::{DataSource, Dataset, DatasetColumn, EntityRelationship, User},
-        schema::{data_sources, dataset_columns, datasets, entity_relationship},
+        models::{DataSource, Dataset, DatasetColumn, EntityRelationship, SemanticObject, User},
+        schema::{data_sources, dataset_columns, datasets, entity_relationship, semantic_objects},
     },
     routes::rest::ApiResponse,
     utils::{
-        dataset::column_management::{get_column_types, update_dataset_columns},
+        dataset::{
+            column_management::{get_column_types, update_dataset_columns},
+            prune::{prune_datasets, PrunedDataset},
+        },
         query_engine::{
             credentials::get_data_source_credentials,
-            import_dataset_columns::{retrieve_dataset_columns, retrieve_dataset_columns_batch},
+            import_dataset_columns::{
+                retrieve_dataset_columns, retrieve_dataset_columns_batch, DatasetColumnRecord,
+            },
             write_query_engine::write_query_engine,
         },
         security::checks::is_user_workspace_admin_or_data_admin,
+        semantic_layer::semantic_model::{SemanticDataset, SemanticField},
         stored_values::{process_stored_values_background, store_column_values, StoredValueColumn},
         user::user_info::get_user_organization_id,
-        validation::{dataset_validation::validate_model, ValidationError, ValidationResult},
+        validation::{
+            dataset_validation::validate_model, expression_validation::validate_expressions,
+            ValidationError, ValidationResult,
+        },
         ColumnUpdate, ValidationErrorType,
     },
 };
@@ -38,6 +55,19 @@ pub struct BusterConfig {
     pub schema: Option<String>,
 }
 
+#[derive(Debug, Default, Deserialize)]
+pub struct DeployDatasetsQuery {
+    /// Delete datasets that aren't part of the request from the data sources it deploys to.
+    #[serde(default)]
+    pub prune: bool,
+    /// Validate and report what would change without writing anything.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Prune datasets even when threads or dashboards still reference them.
+    #[serde(default)]
+    pub force: bool,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct DeployDatasetsRequest {
     pub id: Option<Uuid>,
@@ -82,6 +112,7 @@ pub struct DeployDatasetsEntityRelationshipsRequest {
 pub struct DeployDatasetsResponse {
     pub results: Vec<ValidationResult>,
     pub summary: DeploymentSummary,
+    pub pruned: Vec<PrunedDataset>,
 }
 
 #[derive(Serialize)]
@@ -196,6 +227,7 @@ pub struct DatasetValidationFailure {
 // Main API endpoint function
 pub async fn deploy_datasets(
     Extension(user): Extension<User>,
+    Query(query): Query<DeployDatasetsQuery>,
     Json(request): Json<Vec<DeployDatasetsRequest>>,
 ) -> Result<ApiResponse<DeployDatasetsResponse>, (StatusCode, String)> {
     let organization_id = match get_user_organization_id(&user.id).await {
@@ -225,7 +257,7 @@ pub async fn deploy_datasets(
     }
 
     // Call handler function
-    match handle_deploy_datasets(&user.id, request).await {
+    match handle_deploy_datasets(&user.id, request, &query).await {
         Ok(result) => Ok(ApiResponse::JsonData(result)),
         Err(e) => {
             tracing::error!("Error in deploy_datasets: {:?}", e);
@@ -238,8 +270,55 @@ pub async fn deploy_datasets(
 async fn handle_deploy_datasets(
     user_id: &Uuid,
     requests: Vec<DeployDatasetsRequest>,
+    query: &DeployDatasetsQuery,
 ) -> Result<DeployDatasetsResponse> {
-    let results = deploy_datasets_handler(user_id, requests, false).await?;
+    // The request is the full desired set of each data source it touches, including models that
+    // fail validation, so a broken model is never pruned.
+    let mut desired_datasets: HashMap<(String, String), HashSet<String>> = HashMap::new();
+    if query.prune {
+        for req in &requests {
+            desired_datasets
+                .entry((req.data_source_name.clone(), req.env.clone()))
+                .or_default()
+                .insert(req.name.clone());
+        }
+    }
+
+    let results =
+        deploy_datasets_handler(user_id, requests, false, query.dry_run, query.prune).await?;
+
+    let mut pruned = Vec::new();
+    if !desired_datasets.is_empty() {
+        let organization_id = get_user_organization_id(user_id).await?;
+        let mut conn = get_pg_pool().get().await?;
+
+        for ((data_source_name, env), keep) in desired_datasets {
+            // Missing data sources were already reported as failures.
+            let data_source_id = match data_sources::table
+                .filter(data_sources::name.eq(&data_source_name))
+                .filter(data_sources::env.eq(&env))
+                .filter(data_sources::organization_id.eq(&organization_id))
+                .filter(data_sources::deleted_at.is_null())
+                .select(data_sources::id)
+                .first::<Uuid>(&mut conn)
+                .await
+            {
+                Ok(id) => id,
+                Err(_) => continue,
+            };
+
+            pruned.extend(
+                prune_datasets(
+                    &data_source_id,
+                    &data_source_name,
+                    &keep,
+                    query.dry_run,
+                    query.force,
+                )
+                .await?,
+            );
+        }
+    }
 
     let successful_models = results.iter().filter(|r| r.success).count();
     let failed_models = results.iter().filter(|r| !r.success).count();
@@ -269,7 +348,11 @@ async fn handle_deploy_datasets(
             .collect(),
     };
 
-    Ok(DeployDatasetsResponse { results, summary })
+    Ok(DeployDatasetsResponse {
+        results,
+        summary,
+        pruned,
+    })
 }
 
 // Handler function that contains all the business logic
@@ -277,6 +360,8 @@ async fn deploy_datasets_handler(
     user_id: &Uuid,
     requests: Vec<DeployDatasetsRequest>,
     is_simple: bool,
+    dry_run: bool,
+    prune: bool,
 ) -> Result<Vec<ValidationResult>> {
     let organization_id = get_user_organization_id(user_id).await?;
     let mut conn = get_pg_pool().get().await?;
@@ -458,57 +543,30 @@ async fn deploy_datasets_handler(
                     req.schema,
                     req.name
                 );
-                validation.success = true;
-                valid_datasets.push(req);
-                dataset_columns_map.insert(req.name.clone(), columns);
+
+                // Compile every expression against the data source before anything is written
+                let semantic_dataset = semantic_dataset_from_request(req, &columns);
+                let expression_errors = validate_expressions(&data_source, &semantic_dataset).await;
+
+                if expression_errors.is_empty() {
+                    validation.success = true;
+                    valid_datasets.push(req);
+                    dataset_columns_map.insert(req.name.clone(), columns);
+                } else {
+                    for error in expression_errors {
+                        validation.add_error(error);
+                    }
+                    validation.success = false;
+                }
             }
 
             results.push(validation);
         }
 
-        // Bulk upsert valid datasets
-        if !valid_datasets.is_empty() {
+        // Bulk upsert valid datasets. Datasets missing from the request are only removed when
+        // pruning, after every group has been deployed.
+        if !valid_datasets.is_empty() && !dry_run {
             let now = Utc::now();
-            
-            // Get existing dataset IDs for this data source
-            let existing_datasets: HashSet<String> = datasets::table
-                .filter(datasets::data_source_id.eq(&data_source.id))
-                .filter(datasets::deleted_at.is_null())
-                .select(datasets::name)
-                .load::<String>(&mut conn)
-                .await?
-                .into_iter()
-                .collect();
-
-            // Get new dataset names from the request
-            let new_dataset_names: HashSet<String> = valid_datasets
-                .iter()
-                .map(|req| req.name.clone())
-                .collect();
-
-            // Find datasets that exist but aren't in the request
-            let datasets_to_delete: Vec<String> = existing_datasets
-                .difference(&new_dataset_names)
-                .cloned()
-                .collect();
-
-            // Mark datasets as deleted if they're not in the request
-            if !datasets_to_delete.is_empty() {
-                tracing::info!(
-                    "Marking {} datasets as deleted for data source '{}': {:?}",
-                    datasets_to_delete.len(),
-                    data_source_name,
-                    datasets_to_delete
-                );
-                
-                diesel::update(datasets::table)
-                    .filter(datasets::data_source_id.eq(&data_source.id))
-                    .filter(datasets::name.eq_any(&datasets_to_delete))
-                    .filter(datasets::deleted_at.is_null())
-                    .set(datasets::deleted_at.eq(now))
-                    .execute(&mut conn)
-                    .await?;
-            }
 
             // Prepare datasets for upsert
             let mut datasets_to_upsert: Vec<Dataset> = valid_datasets
@@ -534,6 +592,8 @@ async fn deploy_datasets_handler(
                     model: req.model.clone(),
                     yml_file: req.yml_file.clone(),
                     database_identifier: req.database.clone(),
+                    verification: Verification::NotRequested,
+                    trashed_at: None,
                 })
                 .collect();
 
@@ -559,6 +619,7 @@ async fn deploy_datasets_handler(
                     datasets::schema.eq(excluded(datasets::schema)),
                     datasets::name.eq(excluded(datasets::name)),
                     datasets::deleted_at.eq(None::<DateTime<Utc>>),
+                    datasets::trashed_at.eq(None::<DateTime<Utc>>),
                 ))
                 .execute(&mut conn)
                 .await?;
@@ -575,7 +636,7 @@ async fn deploy_datasets_handler(
                 .collect();
 
             // Bulk upsert columns for each dataset
-            for req in valid_datasets {
+            for req in &valid_datasets {
                 let dataset_id = match dataset_ids.get(&req.name) {
                     Some(id) => *id,
                     None => {
@@ -692,6 +753,173 @@ async fn deploy_datasets_handler(
                         .execute(&mut conn)
                         .await?;
                 }
+
+                // Measures, metrics and segments are kept as semantic objects, along with any
+                // dimension that is computed rather than a physical column.
+                let mut unique_semantic_objects = HashMap::new();
+                for col in &req.columns {
+                    let object_type = match col.semantic_type.as_deref().and_then(|semantic_type| semantic_type.parse().ok()) {
+                        Some(SemanticObjectType::Dimension) if ds_column_types.contains_key(&col.name.to_lowercase()) => continue,
+                        Some(object_type) => object_type,
+                        None => continue,
+                    };
+
+                    unique_semantic_objects.insert(col.name.clone(), SemanticObject {
+                        id: Uuid::new_v4(),
+                        dataset_id,
+                        name: col.name.clone(),
+                        object_type,
+                        expr: col.expr.clone().unwrap_or_else(|| col.name.clone()),
+                        agg: col.agg.clone(),
+                        description: Some(col.description.clone()),
+                        created_at: now,
+                        updated_at: now,
+                        deleted_at: None,
+                    });
+                }
+                let semantic_objects_to_upsert: Vec<SemanticObject> = unique_semantic_objects.into_values().collect();
+
+                if !semantic_objects_to_upsert.is_empty() {
+                    diesel::insert_into(semantic_objects::table)
+                        .values(&semantic_objects_to_upsert)
+                        .on_conflict((semantic_objects::dataset_id, semantic_objects::name))
+                        .do_update()
+                        .set((
+                            semantic_objects::object_type.eq(excluded(semantic_objects::object_type)),
+                            semantic_objects::expr.eq(excluded(semantic_objects::expr)),
+                            semantic_objects::agg.eq(excluded(semantic_objects::agg)),
+                            semantic_objects::description.eq(excluded(semantic_objects::description)),
+                            semantic_objects::updated_at.eq(now),
+                            semantic_objects::deleted_at.eq(None::<DateTime<Utc>>),
+                        ))
+                        .execute(&mut conn)
+                        .await?;
+                }
+
+                // Soft delete semantic objects that were removed from the model
+                diesel::update(semantic_objects::table)
+                    .filter(semantic_objects::dataset_id.eq(dataset_id))
+                    .filter(
+                        semantic_objects::name
+                            .ne_all(semantic_objects_to_upsert.iter().map(|o| o.name.clone()).collect::<Vec<String>>()),
+                    )
+                    .filter(semantic_objects::deleted_at.is_null())
+                    .set(semantic_objects::deleted_at.eq(now))
+                    .execute(&mut conn)
+                    .await?;
+            }
+
+            // Persist the relationships declared by foreign and unique entities. The key on the
+            // primary side comes from the referenced model's primary entity, which may be deployed
+            // separately. Relationships the request doesn't declare are left alone unless pruning.
+            let related_dataset_ids: HashMap<String, Uuid> = datasets::table
+                .filter(datasets::data_source_id.eq(&data_source.id))
+                .filter(datasets::deleted_at.is_null())
+                .select((datasets::name, datasets::id))
+                .load::<(String, Uuid)>(&mut conn)
+                .await?
+                .into_iter()
+                .collect();
+
+            let primary_keys: HashMap<&String, &String> = requests
+                .iter()
+                .filter_map(|req| {
+                    req.entity_relationships
+                        .as_ref()?
+                        .iter()
+                        .find(|entity| entity.type_ == "primary")
+                        .map(|entity| (&req.name, &entity.expr))
+                })
+                .collect();
+
+            for req in &valid_datasets {
+                let dataset_id = match dataset_ids.get(&req.name) {
+                    Some(id) => *id,
+                    None => continue,
+                };
+
+                let relationships: Vec<EntityRelationship> = req
+                    .entity_relationships
+                    .iter()
+                    .flatten()
+                    .filter(|entity| entity.name != req.name)
+                    .filter_map(|entity| {
+                        // A foreign entity repeats the referenced key across many rows, a unique
+                        // one appears once. Types are stored from the primary dataset's side.
+                        let relationship_type = match entity.type_.as_str() {
+                            "foreign" => "one_to_many",
+                            "unique" => "one_to_one",
+                            _ => return None,
+                        };
+
+                        let primary_dataset_id = match related_dataset_ids.get(&entity.name) {
+                            Some(id) => *id,
+                            None => {
+                                tracing::warn!(
+                                    "Model '{}' references '{}', which is not deployed to data source '{}'",
+                                    req.name,
+                                    entity.name,
+                                    data_source_name
+                                );
+                                return None;
+                            }
+                        };
+
+                        Some(EntityRelationship {
+                            primary_dataset_id,
+                            foreign_dataset_id: dataset_id,
+                            relationship_type: relationship_type.to_string(),
+                            created_at: now,
+                            primary_key: primary_keys.get(&entity.name).map(|key| key.to_string()),
+                            foreign_key: Some(entity.expr.clone()),
+                        })
+                    })
+                    .collect();
+
+                if !relationships.is_empty() {
+                    diesel::insert_into(entity_relationship::table)
+                        .values(&relationships)
+                        .on_conflict((
+                            entity_relationship::primary_dataset_id,
+                            entity_relationship::foreign_dataset_id,
+                        ))
+                        .do_update()
+                        .set((
+                            entity_relationship::relationship_type
+                                .eq(excluded(entity_relationship::relationship_type)),
+                            entity_relationship::foreign_key
+                                .eq(excluded(entity_relationship::foreign_key)),
+                            // Keep the stored key when the referenced model isn't in this deploy.
+                            entity_relationship::primary_key.eq(sql::<Nullable<Text>>(
+                                "COALESCE(excluded.primary_key, entity_relationship.primary_key)",
+                            )),
+                        ))
+                        .execute(&mut conn)
+                        .await?;
+                }
+
+                if prune {
+                    let declared_primary_ids: Vec<Uuid> = relationships
+                        .iter()
+                        .map(|relationship| relationship.primary_dataset_id)
+                        .collect();
+
+                    diesel::delete(entity_relationship::table)
+                        .filter(entity_relationship::foreign_dataset_id.eq(dataset_id))
+                        .filter(
+                            entity_relationship::primary_dataset_id.ne_all(&declared_primary_ids),
+                        )
+                        .execute(&mut conn)
+                        .await?;
+                }
+
+                if let Some(primary_key) = primary_keys.get(&req.name) {
+                    diesel::update(entity_relationship::table)
+                        .filter(entity_relationship::primary_dataset_id.eq(dataset_id))
+                        .set(entity_relationship::primary_key.eq(Some(primary_key.to_string())))
+                        .execute(&mut conn)
+                        .await?;
+                }
             }
         }
     }
@@ -699,6 +927,48 @@ async fn deploy_datasets_handler(
     Ok(results)
 }
 
+// The fields of a deploy request that are computed from an expression, shaped for the compiler.
+fn semantic_dataset_from_request(
+    req: &DeployDatasetsRequest,
+    ds_columns: &[&DatasetColumnRecord],
+) -> SemanticDataset {
+    let table = match &req.database {
+        Some(database) => format!("{}.{}.{}", database, req.schema, req.name),
+        None => format!("{}.{}", req.schema, req.name),
+    };
+
+    let mut dataset = SemanticDataset {
+        id: req.id.unwrap_or_else(Uuid::new_v4),
+        name: req.name.clone(),
+        table,
+        columns: ds_columns.iter().map(|c| c.name.clone()).collect(),
+        dimensions: vec![],
+        measures: vec![],
+        metrics: vec![],
+        segments: vec![],
+    };
+
+    for col in &req.columns {
+        let field = SemanticField {
+            name: col.name.clone(),
+            expr: col.expr.clone().unwrap_or_else(|| col.name.clone()),
+            agg: col.agg.clone(),
+            description: None,
+        };
+
+        match col.semantic_type.as_deref().and_then(|semantic_type| semantic_type.parse().ok()) {
+            Some(SemanticObjectType::Measure) => dataset.measures.push(field),
+            Some(SemanticObjectType::Metric) => dataset.metrics.push(field),
+            Some(SemanticObjectType::Segment) => dataset.segments.push(field),
+            // Physical columns were already checked against the data source
+            _ if field.expr == field.name => continue,
+            _ => dataset.dimensions.push(field),
+        }
+    }
+
+    dataset
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,131 @@
+use axum::{extract::Json, Extension};
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    database::models::User,
+    routes::rest::ApiResponse,
+    utils::{
+        environments::{promotion::load_env_snapshots, DEFAULT_ENV},
+        security::checks::is_user_workspace_admin_or_data_admin,
+        user::user_info::get_user_organization_id,
+    },
+};
+
+fn default_env() -> String {
+    DEFAULT_ENV.to_string()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportDatasetsRequest {
+    #[serde(default = "default_env")]
+    pub env: String,
+    pub data_source_name: Option<String>,
+    pub dataset_names: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportedDataset {
+    pub id: Uuid,
+    pub name: String,
+    pub data_source_name: String,
+    pub env: String,
+    pub schema: String,
+    pub database: Option<String>,
+    pub description: Option<String>,
+    /// The model file as it was last deployed.
+    pub yml_file: Option<String>,
+    pub columns: Vec<ExportedColumn>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportedColumn {
+    pub name: String,
+    pub description: Option<String>,
+    pub semantic_type: Option<String>,
+    pub expr: Option<String>,
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub stored_values: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Returns deployed dataset definitions with their live metadata, so edits made in the app can be
+/// pulled back into model files.
+pub async fn export_datasets(
+    Extension(user): Extension<User>,
+    Json(request): Json<ExportDatasetsRequest>,
+) -> Result<ApiResponse<Vec<ExportedDataset>>, (StatusCode, String)> {
+    let organization_id = match get_user_organization_id(&user.id).await {
+        Ok(id) => id,
+        Err(e) => {
+            tracing::error!("Error getting user organization id: {:?}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error getting user organization id".to_string(),
+            ));
+        }
+    };
+
+    match is_user_workspace_admin_or_data_admin(&user, &organization_id).await {
+        Ok(true) => (),
+        Ok(false) => {
+            return Err((
+                StatusCode::FORBIDDEN,
+                "Insufficient permissions".to_string(),
+            ))
+        }
+        Err(e) => {
+            tracing::error!("Error checking user permissions: {:?}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+    }
+
+    let snapshots = match load_env_snapshots(
+        &organization_id,
+        &request.env,
+        request.data_source_name.as_ref(),
+        request.dataset_names.as_ref(),
+    )
+    .await
+    {
+        Ok(snapshots) => snapshots,
+        Err(e) => {
+            tracing::error!("Error exporting datasets: {:?}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+    };
+
+    let datasets = snapshots
+        .into_iter()
+        .map(|snapshot| ExportedDataset {
+            id: snapshot.dataset.id,
+            name: snapshot.dataset.name,
+            data_source_name: snapshot.data_source_name,
+            env: request.env.clone(),
+            schema: snapshot.dataset.schema,
+            database: snapshot.dataset.database_identifier,
+            description: snapshot.dataset.when_to_use,
+            yml_file: snapshot.dataset.yml_file,
+            columns: snapshot
+                .columns
+                .into_iter()
+                .map(|column| ExportedColumn {
+                    name: column.name,
+                    description: column.description,
+                    semantic_type: column.semantic_type,
+                    expr: column.expr,
+                    type_: column.type_,
+                    stored_values: column.stored_values.unwrap_or(false),
+                    updated_at: column.updated_at,
+                })
+                .collect(),
+            updated_at: snapshot.dataset.updated_at,
+        })
+        .collect();
+
+    Ok(ApiResponse::JsonData(datasets))
+}
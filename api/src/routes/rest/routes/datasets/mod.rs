@@ -1,13 +1,17 @@
 mod assets;
 mod delete_dataset;
 mod deploy_datasets;
+mod export_datasets;
 mod generate_datasets;
 mod get_dataset;
 mod get_dataset_data_sample;
 mod list_datasets;
 mod post_dataset;
+mod promote_datasets;
+mod upload_csv;
 
 use axum::{
+    extract::DefaultBodyLimit,
     routing::{get, post, delete},
     Router,
 };
@@ -17,7 +21,13 @@ pub fn router() -> Router {
         .route("/", get(list_datasets::list_datasets))
         .route("/", post(post_dataset::post_dataset))
         .route("/deploy", post(deploy_datasets::deploy_datasets))
+        .route("/export", post(export_datasets::export_datasets))
         .route("/generate", post(generate_datasets::generate_datasets))
+        .route("/promote", post(promote_datasets::promote_datasets))
+        .route(
+            "/upload",
+            post(upload_csv::upload_csv).layer(DefaultBodyLimit::max(upload_csv::UPLOAD_MAX_BYTES)),
+        )
         .route("/:dataset_id", get(get_dataset::get_dataset))
         .route("/:dataset_id", delete(delete_dataset::delete_dataset))
         .route(
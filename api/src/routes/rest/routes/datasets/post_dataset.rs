@@ -8,7 +8,7 @@ use uuid::Uuid;
 
 use crate::{
     database::{
-        enums::{DatasetType, UserOrganizationRole},
+        enums::{DatasetType, UserOrganizationRole, Verification},
         lib::get_pg_pool,
         models::{DataSource, Dataset, User},
         schema::{data_sources, datasets, users_to_organizations},
@@ -128,6 +128,8 @@ async fn post_dataset_handler(
         model: None,
         yml_file: None,
         database_identifier: None,
+        verification: Verification::NotRequested,
+        trashed_at: None,
     };
 
     diesel::insert_into(datasets::table)
@@ -0,0 +1,73 @@
+use axum::{extract::Json, Extension};
+use reqwest::StatusCode;
+use serde::Deserialize;
+
+use crate::{
+    database::models::User,
+    routes::rest::ApiResponse,
+    utils::{
+        environments::promotion::{promote_datasets as promote_datasets_handler, PromotionPlan},
+        security::checks::is_user_workspace_admin_or_data_admin,
+        user::user_info::get_user_organization_id,
+    },
+};
+
+#[derive(Debug, Deserialize)]
+pub struct PromoteDatasetsRequest {
+    pub from_env: String,
+    pub to_env: String,
+    // Limits the promotion to the datasets of one data source.
+    pub data_source_name: Option<String>,
+    pub dataset_names: Option<Vec<String>>,
+    // Returns the diff without writing anything.
+    #[serde(default)]
+    pub preview: bool,
+}
+
+pub async fn promote_datasets(
+    Extension(user): Extension<User>,
+    Json(request): Json<PromoteDatasetsRequest>,
+) -> Result<ApiResponse<PromotionPlan>, (StatusCode, String)> {
+    let organization_id = match get_user_organization_id(&user.id).await {
+        Ok(id) => id,
+        Err(e) => {
+            tracing::error!("Error getting user organization id: {:?}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error getting user organization id".to_string(),
+            ));
+        }
+    };
+
+    match is_user_workspace_admin_or_data_admin(&user, &organization_id).await {
+        Ok(true) => (),
+        Ok(false) => {
+            return Err((
+                StatusCode::FORBIDDEN,
+                "Insufficient permissions".to_string(),
+            ))
+        }
+        Err(e) => {
+            tracing::error!("Error checking user permissions: {:?}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+    }
+
+    match promote_datasets_handler(
+        &organization_id,
+        &user.id,
+        &request.from_env,
+        &request.to_env,
+        request.data_source_name.as_ref(),
+        request.dataset_names.as_ref(),
+        request.preview,
+    )
+    .await
+    {
+        Ok(plan) => Ok(ApiResponse::JsonData(plan)),
+        Err(e) => {
+            tracing::error!("Error promoting datasets: {:?}", e);
+            Err((StatusCode::BAD_REQUEST, e.to_string()))
+        }
+    }
+}
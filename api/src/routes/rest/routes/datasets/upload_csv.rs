@@ -0,0 +1,296 @@
+use axum::{
+    body::Bytes,
+    extract::{multipart::MultipartError, Multipart},
+    Extension,
+};
+use diesel::{ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+use reqwest::StatusCode;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        lib::get_pg_pool,
+        models::{DataSource, Dataset, DatasetColumn, User},
+        schema::data_sources,
+    },
+    routes::rest::ApiResponse,
+    utils::{
+        uploads::{
+            csv_file::{parse_csv, sql_identifier},
+            register::{can_grant_permission_groups, register_upload, UploadDataset},
+            warehouse::{drop_upload, supports_uploads, write_upload},
+        },
+        security::{
+            checks::is_user_workspace_admin_or_data_admin,
+            permission_flags::{has_permission_flag, PermissionFlag},
+        },
+        user::user_info::get_user_organization_id,
+    },
+};
+
+/// The largest request body the upload route accepts.
+pub const UPLOAD_MAX_BYTES: usize = 100 * 1024 * 1024;
+
+#[derive(Serialize)]
+pub struct UploadCsvResponse {
+    pub dataset: Dataset,
+    pub columns: Vec<DatasetColumn>,
+    pub row_count: usize,
+}
+
+#[derive(Default)]
+struct UploadForm {
+    file: Option<Bytes>,
+    file_name: Option<String>,
+    data_source_id: Option<String>,
+    name: Option<String>,
+    description: Option<String>,
+    permission_group_ids: Vec<Uuid>,
+}
+
+/// Takes a `multipart/form-data` body with a `file` CSV, a `data_source_id`, and optionally a
+/// `name`, a `description` and `permission_group_ids` (repeated or comma separated). The CSV is
+/// written to the data source's upload schema and registered as a dataset.
+///
+/// Postgres, Supabase, Snowflake and BigQuery data sources take uploads. There is no local DuckDB
+/// store to write to, since the query engine has no DuckDB data source to register it against.
+pub async fn upload_csv(
+    Extension(user): Extension<User>,
+    multipart: Multipart,
+) -> Result<ApiResponse<UploadCsvResponse>, (StatusCode, String)> {
+    match has_permission_flag(&user.id, PermissionFlag::UploadCsv).await {
+        Ok(true) => (),
+        Ok(false) => {
+            return Err((
+                StatusCode::FORBIDDEN,
+                "CSV uploads are not enabled for this user".to_string(),
+            ))
+        }
+        Err(e) => {
+            tracing::error!("Error checking upload access: {:?}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+    }
+
+    let form = match read_form(multipart).await {
+        Ok(form) => form,
+        Err(e) => return Err((StatusCode::BAD_REQUEST, e)),
+    };
+
+    let file = match &form.file {
+        Some(file) => file,
+        None => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "A CSV file is required".to_string(),
+            ))
+        }
+    };
+
+    let data_source_id = match form
+        .data_source_id
+        .as_deref()
+        .and_then(|id| Uuid::parse_str(id).ok())
+    {
+        Some(data_source_id) => data_source_id,
+        None => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "A valid data_source_id is required".to_string(),
+            ))
+        }
+    };
+
+    let name = match form.name {
+        Some(name) => name,
+        None => form
+            .file_name
+            .as_deref()
+            .map(|file_name| file_name.trim_end_matches(".csv").to_string())
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| "Upload".to_string()),
+    };
+
+    let description = form.description;
+    let permission_group_ids = form.permission_group_ids;
+
+    let upload = match parse_csv(file) {
+        Ok(upload) => upload,
+        Err(e) => return Err((StatusCode::BAD_REQUEST, e.to_string())),
+    };
+
+    if upload.rows.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "The CSV has no rows".to_string()));
+    }
+
+    let organization_id = match get_user_organization_id(&user.id).await {
+        Ok(organization_id) => organization_id,
+        Err(e) => {
+            tracing::error!("Error getting user organization id: {:?}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error getting user organization id".to_string(),
+            ));
+        }
+    };
+
+    let data_source = match get_data_source(&data_source_id, &organization_id).await {
+        Ok(Some(data_source)) => data_source,
+        Ok(None) => return Err((StatusCode::NOT_FOUND, "Data source not found".to_string())),
+        Err(e) => {
+            tracing::error!("Error getting data source: {:?}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+    };
+
+    if !supports_uploads(&data_source.type_) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "CSV uploads are not supported for {} data sources",
+                data_source.type_.to_string()
+            ),
+        ));
+    }
+
+    if !permission_group_ids.is_empty() {
+        let is_admin = match is_user_workspace_admin_or_data_admin(&user, &organization_id).await {
+            Ok(is_admin) => is_admin,
+            Err(e) => {
+                tracing::error!("Error checking user role: {:?}", e);
+                return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+            }
+        };
+
+        match can_grant_permission_groups(
+            &user.id,
+            &organization_id,
+            &permission_group_ids,
+            is_admin,
+        )
+        .await
+        {
+            Ok(true) => (),
+            Ok(false) => {
+                return Err((
+                    StatusCode::FORBIDDEN,
+                    "Uploads can only be shared with permission groups the user belongs to"
+                        .to_string(),
+                ))
+            }
+            Err(e) => {
+                tracing::error!("Error checking permission groups: {:?}", e);
+                return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+            }
+        }
+    }
+
+    // The id suffix keeps uploads with the same name from colliding.
+    let dataset_id = Uuid::new_v4();
+    let table = match sql_identifier(&name) {
+        table if table.is_empty() => format!("upload_{}", &dataset_id.simple().to_string()[..8]),
+        table => format!("{}_{}", table, &dataset_id.simple().to_string()[..8]),
+    };
+
+    let target = match write_upload(&data_source, &table, &upload).await {
+        Ok(target) => target,
+        Err(e) => {
+            tracing::error!("Error writing upload: {:?}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+    };
+
+    let (dataset, columns) = match register_upload(
+        &user.id,
+        &organization_id,
+        UploadDataset {
+            id: dataset_id,
+            name: name.trim(),
+            description,
+            data_source: &data_source,
+            target: &target,
+            upload: &upload,
+        },
+        permission_group_ids,
+    )
+    .await
+    {
+        Ok(registered) => registered,
+        Err(e) => {
+            tracing::error!("Error registering upload: {:?}", e);
+            if let Err(e) = drop_upload(&data_source, &target).await {
+                tracing::error!("Error dropping unregistered upload: {:?}", e);
+            }
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+    };
+
+    Ok(ApiResponse::JsonData(UploadCsvResponse {
+        dataset,
+        columns,
+        row_count: upload.rows.len(),
+    }))
+}
+
+async fn read_form(mut multipart: Multipart) -> Result<UploadForm, String> {
+    let mut form = UploadForm::default();
+
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => return Ok(form),
+            Err(e) => return Err(e.body_text()),
+        };
+
+        match field.name().unwrap_or_default() {
+            "file" => {
+                form.file_name = field.file_name().map(|file_name| file_name.to_string());
+                form.file = Some(field.bytes().await.map_err(|e| e.body_text())?);
+            }
+            "data_source_id" => form.data_source_id = text_field(field.text().await)?,
+            "name" => form.name = text_field(field.text().await)?,
+            "description" => form.description = text_field(field.text().await)?,
+            "permission_group_ids" => {
+                let ids = field.text().await.map_err(|e| e.body_text())?;
+
+                for id in ids.split(',').map(|id| id.trim()).filter(|id| !id.is_empty()) {
+                    match Uuid::parse_str(id) {
+                        Ok(id) => form.permission_group_ids.push(id),
+                        Err(_) => return Err(format!("Invalid permission group id: {}", id)),
+                    }
+                }
+            }
+            _ => continue,
+        }
+    }
+}
+
+fn text_field(value: Result<String, MultipartError>) -> Result<Option<String>, String> {
+    match value {
+        Ok(value) if value.trim().is_empty() => Ok(None),
+        Ok(value) => Ok(Some(value.trim().to_string())),
+        Err(e) => Err(e.body_text()),
+    }
+}
+
+async fn get_data_source(
+    data_source_id: &Uuid,
+    organization_id: &Uuid,
+) -> anyhow::Result<Option<DataSource>> {
+    let mut conn = get_pg_pool().get().await?;
+
+    match data_sources::table
+        .filter(data_sources::id.eq(data_source_id))
+        .filter(data_sources::organization_id.eq(organization_id))
+        .filter(data_sources::deleted_at.is_null())
+        .select(data_sources::all_columns)
+        .first::<DataSource>(&mut conn)
+        .await
+    {
+        Ok(data_source) => Ok(Some(data_source)),
+        Err(diesel::NotFound) => Ok(None),
+        Err(e) => Err(anyhow::anyhow!("Error getting data source: {}", e)),
+    }
+}
@@ -0,0 +1,78 @@
+use axum::Extension;
+use reqwest::StatusCode;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    database::models::User,
+    routes::rest::ApiResponse,
+    utils::{
+        embed::tokens::{
+            revoke_embed_secret as revoke_secret, rotate_embed_secret as rotate_secret,
+        },
+        security::checks::is_user_workspace_admin_or_data_admin,
+        user::user_info::get_user_organization_id,
+    },
+};
+
+#[derive(Serialize)]
+pub struct EmbedSecretResponse {
+    pub secret: String,
+}
+
+/// Generates a new embed secret for the user's organization. The secret is only returned here, so
+/// the embedding application has to store it. Tokens signed with the previous secret stop working.
+pub async fn rotate_embed_secret(
+    Extension(user): Extension<User>,
+) -> Result<ApiResponse<EmbedSecretResponse>, (StatusCode, String)> {
+    let organization_id = embed_admin_organization(&user).await?;
+
+    match rotate_secret(&organization_id).await {
+        Ok(secret) => Ok(ApiResponse::JsonData(EmbedSecretResponse { secret })),
+        Err(e) => {
+            tracing::error!("Error rotating embed secret: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
+
+/// Turns embedding off for the user's organization.
+pub async fn revoke_embed_secret(
+    Extension(user): Extension<User>,
+) -> Result<ApiResponse<()>, (StatusCode, String)> {
+    let organization_id = embed_admin_organization(&user).await?;
+
+    match revoke_secret(&organization_id).await {
+        Ok(_) => Ok(ApiResponse::NoContent),
+        Err(e) => {
+            tracing::error!("Error revoking embed secret: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
+
+/// The user's organization, if they can manage its embedding.
+pub(super) async fn embed_admin_organization(user: &User) -> Result<Uuid, (StatusCode, String)> {
+    let organization_id = match get_user_organization_id(&user.id).await {
+        Ok(organization_id) => organization_id,
+        Err(e) => {
+            tracing::error!("Error getting user organization id: {:?}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error getting user organization id".to_string(),
+            ));
+        }
+    };
+
+    match is_user_workspace_admin_or_data_admin(user, &organization_id).await {
+        Ok(true) => Ok(organization_id),
+        Ok(false) => Err((
+            StatusCode::FORBIDDEN,
+            "Only workspace and data admins can manage embedding".to_string(),
+        )),
+        Err(e) => {
+            tracing::error!("Error checking user permissions: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+
+use axum::{extract::Query, Extension};
+use futures::future::join_all;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    routes::{
+        rest::ApiResponse,
+        ws::dashboards::dashboard_utils::{get_embedded_dashboard_state, DashboardState},
+    },
+    utils::{embed::tokens::EmbedClaims, query_engine::query_engine::query_engine},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct GetEmbeddedDashboardQuery {
+    /// Dashboard filter values as a JSON object. Filters locked by the token can't be changed.
+    pub filters: Option<String>,
+}
+
+/// The dashboard an embed token grants, with each metric's data. Metrics the locked filters can't
+/// be applied to are returned without data.
+pub async fn get_embedded_dashboard(
+    Extension(claims): Extension<EmbedClaims>,
+    Query(query): Query<GetEmbeddedDashboardQuery>,
+) -> Result<ApiResponse<DashboardState>, (StatusCode, String)> {
+    let filter_values = match &query.filters {
+        Some(filters) => match serde_json::from_str::<HashMap<String, Value>>(filters) {
+            Ok(filter_values) => Some(filter_values),
+            Err(e) => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    format!("Invalid dashboard filters: {}", e),
+                ))
+            }
+        },
+        None => None,
+    };
+
+    let mut dashboard_state =
+        match get_embedded_dashboard_state(&claims, filter_values.as_ref()).await {
+            Ok(dashboard_state) => dashboard_state,
+            Err(e) => {
+                tracing::error!("Error getting embedded dashboard: {:?}", e);
+                return Err((StatusCode::BAD_REQUEST, e.to_string()));
+            }
+        };
+
+    let data = join_all(dashboard_state.metrics.iter().map(|metric| async move {
+        if let Some(filter_error) = &metric.filter_error {
+            tracing::error!("Unable to apply dashboard filters: {}", filter_error);
+            return None;
+        }
+
+        match query_engine(&metric.dataset_id, &metric.sql).await {
+            Ok(data) => Some(data),
+            Err(e) => {
+                tracing::error!("Error querying metric {}: {:?}", metric.id, e);
+                None
+            }
+        }
+    }))
+    .await;
+
+    for (metric, data) in dashboard_state.metrics.iter_mut().zip(data) {
+        metric.data = data;
+    }
+
+    Ok(ApiResponse::JsonData(dashboard_state))
+}
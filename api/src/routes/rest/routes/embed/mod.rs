@@ -0,0 +1,33 @@
+mod embed_secret;
+mod get_embedded_dashboard;
+mod post_embed_token;
+
+use axum::{
+    middleware,
+    routing::{get, post},
+    Router,
+};
+
+use crate::buster_middleware::{auth::auth, embed_auth::embed_auth};
+
+pub fn router() -> Router {
+    Router::new()
+        .merge(
+            Router::new()
+                .route(
+                    "/dashboard",
+                    get(get_embedded_dashboard::get_embedded_dashboard),
+                )
+                .route_layer(middleware::from_fn(embed_auth)),
+        )
+        .merge(
+            Router::new()
+                .route(
+                    "/secret",
+                    post(embed_secret::rotate_embed_secret)
+                        .delete(embed_secret::revoke_embed_secret),
+                )
+                .route("/tokens", post(post_embed_token::post_embed_token))
+                .route_layer(middleware::from_fn(auth)),
+        )
+}
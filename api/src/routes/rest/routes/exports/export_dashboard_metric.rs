@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, Query},
+    response::Response,
+    Extension,
+};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    database::models::User,
+    routes::ws::dashboards::dashboard_utils::{
+        get_dashboard_state_by_id, get_user_dashboard_permission,
+    },
+    utils::exports::exporter::ExportFormat,
+};
+
+use super::export_response::export_metric_response;
+
+#[derive(Debug, Deserialize)]
+pub struct ExportDashboardMetricQuery {
+    pub format: ExportFormat,
+    /// Dashboard filter values as a JSON object, applied the same way as when the dashboard is
+    /// viewed.
+    pub filters: Option<String>,
+}
+
+pub async fn export_dashboard_metric(
+    Extension(user): Extension<User>,
+    Path((dashboard_id, metric_id)): Path<(Uuid, Uuid)>,
+    Query(query): Query<ExportDashboardMetricQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    let filter_values = match &query.filters {
+        Some(filters) => match serde_json::from_str::<HashMap<String, Value>>(filters) {
+            Ok(filter_values) => Some(filter_values),
+            Err(e) => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    format!("Invalid dashboard filters: {}", e),
+                ))
+            }
+        },
+        None => None,
+    };
+
+    match get_user_dashboard_permission(&user.id, &dashboard_id).await {
+        Ok(Some(_)) => (),
+        Ok(None) => {
+            return Err((
+                StatusCode::FORBIDDEN,
+                "User does not have access to this dashboard".to_string(),
+            ))
+        }
+        Err(e) => {
+            tracing::error!("Error checking dashboard permission: {:?}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+    }
+
+    let dashboard_state =
+        match get_dashboard_state_by_id(&user.id, &dashboard_id, filter_values.as_ref()).await {
+            Ok(dashboard_state) => dashboard_state,
+            Err(e) => {
+                tracing::error!("Error getting dashboard: {:?}", e);
+                return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+            }
+        };
+
+    let metric = match dashboard_state
+        .metrics
+        .into_iter()
+        .find(|metric| metric.id == metric_id)
+    {
+        Some(metric) => metric,
+        None => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                "Metric not found on this dashboard".to_string(),
+            ))
+        }
+    };
+
+    if let Some(filter_error) = metric.filter_error {
+        return Err((StatusCode::BAD_REQUEST, filter_error));
+    }
+
+    export_metric_response(
+        &user.id,
+        &metric.name,
+        &metric.dataset_id,
+        &metric.sql,
+        Some(&metric.chart_config),
+        query.format,
+    )
+    .await
+}
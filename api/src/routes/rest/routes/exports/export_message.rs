@@ -0,0 +1,100 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use axum::{
+    extract::{Path, Query},
+    response::Response,
+    Extension,
+};
+use diesel::{ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        lib::get_pg_pool,
+        models::{Message, User},
+        schema::messages,
+    },
+    routes::ws::threads_and_messages::thread_utils::get_user_thread_permission,
+    utils::exports::exporter::ExportFormat,
+};
+
+use super::export_response::export_metric_response;
+
+#[derive(Debug, Deserialize)]
+pub struct ExportMessageQuery {
+    pub format: ExportFormat,
+}
+
+pub async fn export_message(
+    Extension(user): Extension<User>,
+    Path(message_id): Path<Uuid>,
+    Query(query): Query<ExportMessageQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    let message = match get_message(&message_id).await {
+        Ok(Some(message)) => message,
+        Ok(None) => return Err((StatusCode::NOT_FOUND, "Message not found".to_string())),
+        Err(e) => {
+            tracing::error!("Error getting message: {:?}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+    };
+
+    match get_user_thread_permission(Arc::new(user.id), Arc::new(message.thread_id)).await {
+        Ok(Some(_)) => (),
+        Ok(None) => {
+            return Err((
+                StatusCode::FORBIDDEN,
+                "User does not have access to this message".to_string(),
+            ))
+        }
+        Err(e) => {
+            tracing::error!("Error checking thread permission: {:?}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+    }
+
+    let (dataset_id, sql) = match (message.dataset_id, message.code) {
+        (Some(dataset_id), Some(sql)) => (dataset_id, sql),
+        _ => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "This message has no query to export".to_string(),
+            ))
+        }
+    };
+
+    let name = message.title.unwrap_or_else(|| "export".to_string());
+
+    export_metric_response(
+        &user.id,
+        &name,
+        &dataset_id,
+        &sql,
+        message.chart_config.as_ref(),
+        query.format,
+    )
+    .await
+}
+
+async fn get_message(message_id: &Uuid) -> Result<Option<Message>> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    match messages::table
+        .filter(messages::id.eq(message_id))
+        .filter(messages::deleted_at.is_null())
+        .select(messages::all_columns)
+        .first::<Message>(&mut conn)
+        .await
+    {
+        Ok(message) => Ok(Some(message)),
+        Err(diesel::result::Error::NotFound) => Ok(None),
+        Err(e) => Err(anyhow!("Error querying message: {}", e)),
+    }
+}
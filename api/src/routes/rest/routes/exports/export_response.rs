@@ -0,0 +1,89 @@
+use axum::{
+    body::Body,
+    http::{header, HeaderValue},
+    response::{IntoResponse, Response},
+};
+use reqwest::StatusCode;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::utils::{
+    exports::exporter::{export_metric, ExportFormat},
+    security::{
+        dataset_security::has_dataset_access,
+        permission_flags::{has_permission_flag, PermissionFlag},
+    },
+};
+
+/// Checks the user may export the metric's data, then re-runs its query and streams the file back
+/// as an attachment. `X-Export-Truncated` is set when rows past the export limit were left out.
+pub async fn export_metric_response(
+    user_id: &Uuid,
+    name: &str,
+    dataset_id: &Uuid,
+    sql: &String,
+    chart_config: Option<&Value>,
+    format: ExportFormat,
+) -> Result<Response, (StatusCode, String)> {
+    match has_permission_flag(user_id, PermissionFlag::ExportAssets).await {
+        Ok(true) => (),
+        Ok(false) => {
+            return Err((
+                StatusCode::FORBIDDEN,
+                "Exports are not enabled for this user".to_string(),
+            ))
+        }
+        Err(e) => {
+            tracing::error!("Error checking export access: {:?}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+    }
+
+    match has_dataset_access(user_id, dataset_id).await {
+        Ok(true) => (),
+        Ok(false) => {
+            return Err((
+                StatusCode::FORBIDDEN,
+                "User does not have access to this dataset".to_string(),
+            ))
+        }
+        Err(e) => {
+            tracing::error!("Error checking dataset access: {:?}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+    }
+
+    let file = match export_metric(name, dataset_id, sql, chart_config, format).await {
+        Ok(file) => file,
+        Err(e) => {
+            tracing::error!("Error exporting metric: {:?}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+    };
+
+    let content_disposition =
+        match HeaderValue::from_str(&format!("attachment; filename=\"{}\"", file.file_name)) {
+            Ok(value) => value,
+            Err(_) => HeaderValue::from_static("attachment"),
+        };
+
+    let mut response = (
+        [
+            (
+                header::CONTENT_TYPE,
+                HeaderValue::from_static(file.content_type),
+            ),
+            (header::CONTENT_DISPOSITION, content_disposition),
+        ],
+        Body::from_stream(file.contents),
+    )
+        .into_response();
+
+    if file.truncated {
+        response
+            .headers_mut()
+            .insert("x-export-truncated", HeaderValue::from_static("true"));
+    }
+
+    Ok(response)
+}
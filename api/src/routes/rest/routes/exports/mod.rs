@@ -0,0 +1,14 @@
+mod export_dashboard_metric;
+mod export_message;
+mod export_response;
+
+use axum::{routing::get, Router};
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/messages/:message_id", get(export_message::export_message))
+        .route(
+            "/dashboards/:dashboard_id/metrics/:metric_id",
+            get(export_dashboard_metric::export_dashboard_metric),
+        )
+}
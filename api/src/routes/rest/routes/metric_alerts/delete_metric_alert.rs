@@ -0,0 +1,45 @@
+use anyhow::{anyhow, Result};
+use axum::{extract::Path, Extension};
+use chrono::Utc;
+use diesel::{update, ExpressionMethods};
+use diesel_async::RunQueryDsl;
+use reqwest::StatusCode;
+use uuid::Uuid;
+
+use crate::{
+    database::{lib::get_pg_pool, models::User, schema::metric_alerts},
+    routes::rest::ApiResponse,
+};
+
+pub async fn delete_metric_alert(
+    Extension(user): Extension<User>,
+    Path(alert_id): Path<Uuid>,
+) -> Result<ApiResponse<()>, (StatusCode, String)> {
+    match delete_metric_alert_handler(&user.id, &alert_id).await {
+        Ok(true) => Ok(ApiResponse::NoContent),
+        Ok(false) => Err((StatusCode::NOT_FOUND, "Metric alert not found".to_string())),
+        Err(e) => {
+            tracing::error!("Error deleting metric alert: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
+
+async fn delete_metric_alert_handler(user_id: &Uuid, alert_id: &Uuid) -> Result<bool> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    match update(metric_alerts::table)
+        .filter(metric_alerts::id.eq(alert_id))
+        .filter(metric_alerts::user_id.eq(user_id))
+        .filter(metric_alerts::deleted_at.is_null())
+        .set(metric_alerts::deleted_at.eq(Some(Utc::now())))
+        .execute(&mut conn)
+        .await
+    {
+        Ok(rows_affected) => Ok(rows_affected > 0),
+        Err(e) => Err(anyhow!("Error deleting metric alert: {}", e)),
+    }
+}
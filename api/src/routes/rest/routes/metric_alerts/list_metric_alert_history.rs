@@ -0,0 +1,58 @@
+use anyhow::{anyhow, Result};
+use axum::{extract::Path, Extension};
+use diesel::{ExpressionMethods, QueryDsl, SelectableHelper};
+use diesel_async::RunQueryDsl;
+use reqwest::StatusCode;
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        lib::get_pg_pool,
+        models::{MetricAlertHistory, User},
+        schema::metric_alert_history,
+    },
+    routes::rest::ApiResponse,
+};
+
+use super::update_metric_alert::get_metric_alert;
+
+pub async fn list_metric_alert_history(
+    Extension(user): Extension<User>,
+    Path(alert_id): Path<Uuid>,
+) -> Result<ApiResponse<Vec<MetricAlertHistory>>, (StatusCode, String)> {
+    match get_metric_alert(&user.id, &alert_id).await {
+        Ok(Some(_)) => (),
+        Ok(None) => return Err((StatusCode::NOT_FOUND, "Metric alert not found".to_string())),
+        Err(e) => {
+            tracing::error!("Error getting metric alert: {:?}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+    }
+
+    match list_metric_alert_history_handler(&alert_id).await {
+        Ok(history) => Ok(ApiResponse::JsonData(history)),
+        Err(e) => {
+            tracing::error!("Error listing metric alert history: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
+
+async fn list_metric_alert_history_handler(alert_id: &Uuid) -> Result<Vec<MetricAlertHistory>> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    match metric_alert_history::table
+        .select(MetricAlertHistory::as_select())
+        .filter(metric_alert_history::alert_id.eq(alert_id))
+        .order(metric_alert_history::created_at.desc())
+        .limit(200)
+        .load::<MetricAlertHistory>(&mut conn)
+        .await
+    {
+        Ok(history) => Ok(history),
+        Err(e) => Err(anyhow!("Error querying metric alert history: {}", e)),
+    }
+}
@@ -0,0 +1,60 @@
+use anyhow::{anyhow, Result};
+use axum::{extract::Query, Extension};
+use diesel::{ExpressionMethods, QueryDsl, SelectableHelper};
+use diesel_async::RunQueryDsl;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        lib::get_pg_pool,
+        models::{MetricAlert, User},
+        schema::metric_alerts,
+    },
+    routes::rest::ApiResponse,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct ListMetricAlertsQuery {
+    pub thread_id: Option<Uuid>,
+}
+
+pub async fn list_metric_alerts(
+    Extension(user): Extension<User>,
+    Query(query): Query<ListMetricAlertsQuery>,
+) -> Result<ApiResponse<Vec<MetricAlert>>, (StatusCode, String)> {
+    match list_metric_alerts_handler(&user.id, query.thread_id).await {
+        Ok(alerts) => Ok(ApiResponse::JsonData(alerts)),
+        Err(e) => {
+            tracing::error!("Error listing metric alerts: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
+
+async fn list_metric_alerts_handler(
+    user_id: &Uuid,
+    thread_id: Option<Uuid>,
+) -> Result<Vec<MetricAlert>> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    let mut query = metric_alerts::table
+        .select(MetricAlert::as_select())
+        .filter(metric_alerts::user_id.eq(user_id))
+        .filter(metric_alerts::deleted_at.is_null())
+        .order(metric_alerts::created_at.desc())
+        .into_boxed();
+
+    if let Some(thread_id) = thread_id {
+        query = query.filter(metric_alerts::thread_id.eq(thread_id));
+    }
+
+    match query.load::<MetricAlert>(&mut conn).await {
+        Ok(alerts) => Ok(alerts),
+        Err(e) => Err(anyhow!("Error querying metric alerts: {}", e)),
+    }
+}
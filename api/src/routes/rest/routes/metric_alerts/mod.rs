@@ -0,0 +1,25 @@
+mod delete_metric_alert;
+mod list_metric_alert_history;
+mod list_metric_alerts;
+mod post_metric_alert;
+mod update_metric_alert;
+
+use axum::{
+    routing::{delete, get, post, put},
+    Router,
+};
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/", get(list_metric_alerts::list_metric_alerts))
+        .route("/", post(post_metric_alert::post_metric_alert))
+        .route("/:alert_id", put(update_metric_alert::update_metric_alert))
+        .route(
+            "/:alert_id",
+            delete(delete_metric_alert::delete_metric_alert),
+        )
+        .route(
+            "/:alert_id/history",
+            get(list_metric_alert_history::list_metric_alert_history),
+        )
+}
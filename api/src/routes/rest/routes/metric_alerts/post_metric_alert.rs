@@ -0,0 +1,168 @@
+use anyhow::{anyhow, Result};
+use axum::{Extension, Json};
+use chrono::Utc;
+use diesel::insert_into;
+use diesel_async::RunQueryDsl;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        enums::AssetType,
+        lib::get_pg_pool,
+        models::{MetricAlert, User},
+        schema::metric_alerts,
+    },
+    routes::rest::ApiResponse,
+    utils::{
+        alerts::{conditions::AlertState, rules::validate_alert_rule},
+        reports::subscriptions::check_report_asset_access,
+        security::{
+            checks::is_user_workspace_admin_or_data_admin,
+            permission_flags::{has_permission_flag, PermissionFlag},
+        },
+        user::user_info::get_user_organization_id,
+    },
+};
+
+#[derive(Debug, Deserialize)]
+pub struct PostMetricAlertRequest {
+    pub thread_id: Uuid,
+    pub name: String,
+    pub column_name: String,
+    pub time_column_name: Option<String>,
+    pub condition: Value,
+    pub cron: String,
+    pub timezone: Option<String>,
+    pub notify_email: Option<bool>,
+    pub webhook_url: Option<String>,
+}
+
+pub async fn post_metric_alert(
+    Extension(user): Extension<User>,
+    Json(req): Json<PostMetricAlertRequest>,
+) -> Result<ApiResponse<MetricAlert>, (StatusCode, String)> {
+    if req.notify_email.unwrap_or(true) {
+        check_alert_email_access(&user).await?;
+    }
+
+    if let Err(e) = check_report_asset_access(&user.id, &req.thread_id, &AssetType::Thread).await {
+        tracing::error!("Error checking metric access: {:?}", e);
+        return Err((StatusCode::FORBIDDEN, e.to_string()));
+    }
+
+    if req.webhook_url.is_some() {
+        check_alert_webhook_access(&user).await?;
+    }
+
+    let timezone = req.timezone.clone().unwrap_or_else(|| "UTC".to_string());
+
+    if let Err(e) = validate_alert_rule(
+        &req.column_name,
+        req.time_column_name.as_deref(),
+        &req.condition,
+        &req.cron,
+        &timezone,
+        req.webhook_url.as_deref(),
+    )
+    .await
+    {
+        return Err((StatusCode::BAD_REQUEST, e.to_string()));
+    }
+
+    match post_metric_alert_handler(&user, req, timezone).await {
+        Ok(alert) => Ok(ApiResponse::JsonData(alert)),
+        Err(e) => {
+            tracing::error!("Error creating metric alert: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
+
+/// Alert emails go through the same switch as emailed reports.
+pub(super) async fn check_alert_email_access(user: &User) -> Result<(), (StatusCode, String)> {
+    match has_permission_flag(&user.id, PermissionFlag::EmailSlackEnabled).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err((
+            StatusCode::FORBIDDEN,
+            "Email alerts are not enabled for this user".to_string(),
+        )),
+        Err(e) => {
+            tracing::error!("Error checking alert email access: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
+
+/// Alert webhooks post to arbitrary URLs from inside the network, so only workspace and data admins
+/// can set them.
+pub(super) async fn check_alert_webhook_access(user: &User) -> Result<(), (StatusCode, String)> {
+    let organization_id = match get_user_organization_id(&user.id).await {
+        Ok(organization_id) => organization_id,
+        Err(e) => {
+            tracing::error!("Error getting user organization: {:?}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+    };
+
+    match is_user_workspace_admin_or_data_admin(user, &organization_id).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err((
+            StatusCode::FORBIDDEN,
+            "Only admins can send alerts to webhooks".to_string(),
+        )),
+        Err(e) => {
+            tracing::error!("Error checking alert webhook access: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
+
+async fn post_metric_alert_handler(
+    user: &User,
+    req: PostMetricAlertRequest,
+    timezone: String,
+) -> Result<MetricAlert> {
+    let organization_id = get_user_organization_id(&user.id).await?;
+
+    let alert = MetricAlert {
+        id: Uuid::new_v4(),
+        thread_id: req.thread_id,
+        user_id: user.id,
+        organization_id,
+        name: req.name,
+        column_name: req.column_name.trim().to_string(),
+        time_column_name: req
+            .time_column_name
+            .map(|time_column_name| time_column_name.trim().to_string()),
+        condition: req.condition,
+        cron: req.cron.trim().to_string(),
+        timezone,
+        notify_email: req.notify_email.unwrap_or(true),
+        webhook_url: req.webhook_url,
+        enabled: true,
+        state: AlertState::Ok.as_str().to_string(),
+        last_value: None,
+        last_evaluated_at: None,
+        last_error: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        deleted_at: None,
+    };
+
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    match insert_into(metric_alerts::table)
+        .values(&alert)
+        .execute(&mut conn)
+        .await
+    {
+        Ok(_) => Ok(alert),
+        Err(e) => Err(anyhow!("Error inserting metric alert: {}", e)),
+    }
+}
@@ -0,0 +1,159 @@
+use anyhow::{anyhow, Result};
+use axum::{extract::Path, Extension, Json};
+use chrono::Utc;
+use diesel::{update, ExpressionMethods, QueryDsl, SelectableHelper};
+use diesel_async::RunQueryDsl;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        lib::get_pg_pool,
+        models::{MetricAlert, User},
+        schema::metric_alerts,
+    },
+    routes::rest::ApiResponse,
+    utils::{
+        alerts::rules::validate_alert_rule,
+        serde_helpers::deserialization_helpers::deserialize_double_option,
+    },
+};
+
+use super::post_metric_alert::{check_alert_email_access, check_alert_webhook_access};
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateMetricAlertRequest {
+    pub name: Option<String>,
+    pub column_name: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_double_option")]
+    pub time_column_name: Option<Option<String>>,
+    pub condition: Option<Value>,
+    pub cron: Option<String>,
+    pub timezone: Option<String>,
+    pub notify_email: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_double_option")]
+    pub webhook_url: Option<Option<String>>,
+    pub enabled: Option<bool>,
+}
+
+pub async fn update_metric_alert(
+    Extension(user): Extension<User>,
+    Path(alert_id): Path<Uuid>,
+    Json(req): Json<UpdateMetricAlertRequest>,
+) -> Result<ApiResponse<MetricAlert>, (StatusCode, String)> {
+    let mut alert = match get_metric_alert(&user.id, &alert_id).await {
+        Ok(Some(alert)) => alert,
+        Ok(None) => return Err((StatusCode::NOT_FOUND, "Metric alert not found".to_string())),
+        Err(e) => {
+            tracing::error!("Error getting metric alert: {:?}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+    };
+
+    if let Some(name) = req.name {
+        alert.name = name;
+    }
+    if let Some(column_name) = req.column_name {
+        alert.column_name = column_name.trim().to_string();
+    }
+    if let Some(time_column_name) = req.time_column_name {
+        alert.time_column_name =
+            time_column_name.map(|time_column_name| time_column_name.trim().to_string());
+    }
+    if let Some(condition) = req.condition {
+        alert.condition = condition;
+    }
+    if let Some(cron) = req.cron {
+        alert.cron = cron.trim().to_string();
+    }
+    if let Some(timezone) = req.timezone {
+        alert.timezone = timezone;
+    }
+    if let Some(notify_email) = req.notify_email {
+        if notify_email && !alert.notify_email {
+            check_alert_email_access(&user).await?;
+        }
+        alert.notify_email = notify_email;
+    }
+    if let Some(webhook_url) = req.webhook_url {
+        if webhook_url.is_some() && webhook_url != alert.webhook_url {
+            check_alert_webhook_access(&user).await?;
+        }
+        alert.webhook_url = webhook_url;
+    }
+    if let Some(enabled) = req.enabled {
+        alert.enabled = enabled;
+    }
+
+    if let Err(e) = validate_alert_rule(
+        &alert.column_name,
+        alert.time_column_name.as_deref(),
+        &alert.condition,
+        &alert.cron,
+        &alert.timezone,
+        alert.webhook_url.as_deref(),
+    )
+    .await
+    {
+        return Err((StatusCode::BAD_REQUEST, e.to_string()));
+    }
+
+    match update_metric_alert_handler(alert).await {
+        Ok(alert) => Ok(ApiResponse::JsonData(alert)),
+        Err(e) => {
+            tracing::error!("Error updating metric alert: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
+
+pub async fn get_metric_alert(user_id: &Uuid, alert_id: &Uuid) -> Result<Option<MetricAlert>> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    match metric_alerts::table
+        .select(MetricAlert::as_select())
+        .filter(metric_alerts::id.eq(alert_id))
+        .filter(metric_alerts::user_id.eq(user_id))
+        .filter(metric_alerts::deleted_at.is_null())
+        .first::<MetricAlert>(&mut conn)
+        .await
+    {
+        Ok(alert) => Ok(Some(alert)),
+        Err(diesel::result::Error::NotFound) => Ok(None),
+        Err(e) => Err(anyhow!("Error querying metric alert: {}", e)),
+    }
+}
+
+async fn update_metric_alert_handler(alert: MetricAlert) -> Result<MetricAlert> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    match update(metric_alerts::table)
+        .filter(metric_alerts::id.eq(alert.id))
+        .set((
+            metric_alerts::name.eq(alert.name),
+            metric_alerts::column_name.eq(alert.column_name),
+            metric_alerts::time_column_name.eq(alert.time_column_name),
+            metric_alerts::condition.eq(alert.condition),
+            metric_alerts::cron.eq(alert.cron),
+            metric_alerts::timezone.eq(alert.timezone),
+            metric_alerts::notify_email.eq(alert.notify_email),
+            metric_alerts::webhook_url.eq(alert.webhook_url),
+            metric_alerts::enabled.eq(alert.enabled),
+            metric_alerts::updated_at.eq(Utc::now()),
+        ))
+        .returning(MetricAlert::as_returning())
+        .get_result::<MetricAlert>(&mut conn)
+        .await
+    {
+        Ok(alert) => Ok(alert),
+        Err(e) => Err(anyhow!("Error updating metric alert: {}", e)),
+    }
+}
@@ -1,11 +1,17 @@
 mod api_keys;
 mod assets;
+mod dashboards;
 mod data_sources;
 mod dataset_groups;
 mod datasets;
+mod embed;
+mod exports;
+mod metric_alerts;
 mod organizations;
 mod permission_groups;
+mod report_subscriptions;
 mod sql;
+mod trash;
 mod users;
 
 use axum::{middleware, Router};
@@ -13,16 +19,24 @@ use axum::{middleware, Router};
 use crate::buster_middleware::auth::auth;
 
 pub fn router() -> Router {
-    Router::new().nest("/api_keys", api_keys::router()).merge(
-        Router::new()
-            .nest("/assets", assets::router())
-            .nest("/datasets", datasets::router())
-            .nest("/data_sources", data_sources::router())
-            .nest("/permission_groups", permission_groups::router())
-            .nest("/dataset_groups", dataset_groups::router())
-            .nest("/sql", sql::router())
-            .nest("/organizations", organizations::router())
-            .nest("/users", users::router())
-            .route_layer(middleware::from_fn(auth)),
-    )
+    Router::new()
+        .nest("/api_keys", api_keys::router())
+        .nest("/embed", embed::router())
+        .merge(
+            Router::new()
+                .nest("/assets", assets::router())
+                .nest("/dashboards", dashboards::router())
+                .nest("/datasets", datasets::router())
+                .nest("/data_sources", data_sources::router())
+                .nest("/exports", exports::router())
+                .nest("/metric_alerts", metric_alerts::router())
+                .nest("/permission_groups", permission_groups::router())
+                .nest("/dataset_groups", dataset_groups::router())
+                .nest("/sql", sql::router())
+                .nest("/organizations", organizations::router())
+                .nest("/report_subscriptions", report_subscriptions::router())
+                .nest("/trash", trash::router())
+                .nest("/users", users::router())
+                .route_layer(middleware::from_fn(auth)),
+        )
 }
@@ -0,0 +1,51 @@
+use anyhow::{anyhow, Result};
+use axum::{extract::Path, Extension};
+use chrono::Utc;
+use diesel::{update, ExpressionMethods};
+use diesel_async::RunQueryDsl;
+use reqwest::StatusCode;
+use uuid::Uuid;
+
+use crate::{
+    database::{lib::get_pg_pool, models::User, schema::report_subscriptions},
+    routes::rest::ApiResponse,
+};
+
+pub async fn delete_report_subscription(
+    Extension(user): Extension<User>,
+    Path(subscription_id): Path<Uuid>,
+) -> Result<ApiResponse<()>, (StatusCode, String)> {
+    match delete_report_subscription_handler(&user.id, &subscription_id).await {
+        Ok(true) => Ok(ApiResponse::NoContent),
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            "Report subscription not found".to_string(),
+        )),
+        Err(e) => {
+            tracing::error!("Error deleting report subscription: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
+
+async fn delete_report_subscription_handler(
+    user_id: &Uuid,
+    subscription_id: &Uuid,
+) -> Result<bool> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    match update(report_subscriptions::table)
+        .filter(report_subscriptions::id.eq(subscription_id))
+        .filter(report_subscriptions::user_id.eq(user_id))
+        .filter(report_subscriptions::deleted_at.is_null())
+        .set(report_subscriptions::deleted_at.eq(Some(Utc::now())))
+        .execute(&mut conn)
+        .await
+    {
+        Ok(rows_affected) => Ok(rows_affected > 0),
+        Err(e) => Err(anyhow!("Error deleting report subscription: {}", e)),
+    }
+}
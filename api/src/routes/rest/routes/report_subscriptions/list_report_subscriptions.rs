@@ -0,0 +1,46 @@
+use anyhow::{anyhow, Result};
+use axum::Extension;
+use diesel::{ExpressionMethods, QueryDsl, SelectableHelper};
+use diesel_async::RunQueryDsl;
+use reqwest::StatusCode;
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        lib::get_pg_pool,
+        models::{ReportSubscription, User},
+        schema::report_subscriptions,
+    },
+    routes::rest::ApiResponse,
+};
+
+pub async fn list_report_subscriptions(
+    Extension(user): Extension<User>,
+) -> Result<ApiResponse<Vec<ReportSubscription>>, (StatusCode, String)> {
+    match list_report_subscriptions_handler(&user.id).await {
+        Ok(subscriptions) => Ok(ApiResponse::JsonData(subscriptions)),
+        Err(e) => {
+            tracing::error!("Error listing report subscriptions: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
+
+async fn list_report_subscriptions_handler(user_id: &Uuid) -> Result<Vec<ReportSubscription>> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    match report_subscriptions::table
+        .select(ReportSubscription::as_select())
+        .filter(report_subscriptions::user_id.eq(user_id))
+        .filter(report_subscriptions::deleted_at.is_null())
+        .order(report_subscriptions::created_at.desc())
+        .load::<ReportSubscription>(&mut conn)
+        .await
+    {
+        Ok(subscriptions) => Ok(subscriptions),
+        Err(e) => Err(anyhow!("Error querying report subscriptions: {}", e)),
+    }
+}
@@ -0,0 +1,29 @@
+mod delete_report_subscription;
+mod list_report_subscriptions;
+mod post_report_subscription;
+mod update_report_subscription;
+
+use axum::{
+    routing::{delete, get, post, put},
+    Router,
+};
+
+pub fn router() -> Router {
+    Router::new()
+        .route(
+            "/",
+            get(list_report_subscriptions::list_report_subscriptions),
+        )
+        .route(
+            "/",
+            post(post_report_subscription::post_report_subscription),
+        )
+        .route(
+            "/:subscription_id",
+            put(update_report_subscription::update_report_subscription),
+        )
+        .route(
+            "/:subscription_id",
+            delete(delete_report_subscription::delete_report_subscription),
+        )
+}
@@ -0,0 +1,107 @@
+use anyhow::{anyhow, Result};
+use axum::{Extension, Json};
+use chrono::Utc;
+use diesel::insert_into;
+use diesel_async::RunQueryDsl;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        enums::AssetType,
+        lib::get_pg_pool,
+        models::{ReportSubscription, User},
+        schema::report_subscriptions,
+    },
+    routes::rest::ApiResponse,
+    utils::{
+        reports::subscriptions::{check_report_asset_access, validate_report_schedule},
+        security::permission_flags::{has_permission_flag, PermissionFlag},
+        user::user_info::get_user_organization_id,
+    },
+};
+
+#[derive(Debug, Deserialize)]
+pub struct PostReportSubscriptionRequest {
+    pub asset_id: Uuid,
+    pub asset_type: AssetType,
+    pub cron: String,
+    pub timezone: Option<String>,
+}
+
+pub async fn post_report_subscription(
+    Extension(user): Extension<User>,
+    Json(req): Json<PostReportSubscriptionRequest>,
+) -> Result<ApiResponse<ReportSubscription>, (StatusCode, String)> {
+    match has_permission_flag(&user.id, PermissionFlag::EmailSlackEnabled).await {
+        Ok(true) => (),
+        Ok(false) => {
+            return Err((
+                StatusCode::FORBIDDEN,
+                "Email reports are not enabled for this user".to_string(),
+            ))
+        }
+        Err(e) => {
+            tracing::error!("Error checking email report access: {:?}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+    }
+
+    if let Err(e) = check_report_asset_access(&user.id, &req.asset_id, &req.asset_type).await {
+        tracing::error!("Error checking report asset access: {:?}", e);
+        return Err((StatusCode::FORBIDDEN, e.to_string()));
+    }
+
+    let timezone = req.timezone.clone().unwrap_or_else(|| "UTC".to_string());
+
+    if let Err(e) = validate_report_schedule(&req.cron, &timezone).await {
+        return Err((StatusCode::BAD_REQUEST, e.to_string()));
+    }
+
+    match post_report_subscription_handler(&user, req, timezone).await {
+        Ok(subscription) => Ok(ApiResponse::JsonData(subscription)),
+        Err(e) => {
+            tracing::error!("Error creating report subscription: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
+
+async fn post_report_subscription_handler(
+    user: &User,
+    req: PostReportSubscriptionRequest,
+    timezone: String,
+) -> Result<ReportSubscription> {
+    let organization_id = get_user_organization_id(&user.id).await?;
+
+    let subscription = ReportSubscription {
+        id: Uuid::new_v4(),
+        asset_id: req.asset_id,
+        asset_type: req.asset_type,
+        user_id: user.id,
+        organization_id,
+        cron: req.cron.trim().to_string(),
+        timezone,
+        enabled: true,
+        last_sent_at: None,
+        last_error: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        deleted_at: None,
+    };
+
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    match insert_into(report_subscriptions::table)
+        .values(&subscription)
+        .execute(&mut conn)
+        .await
+    {
+        Ok(_) => Ok(subscription),
+        Err(e) => Err(anyhow!("Error inserting report subscription: {}", e)),
+    }
+}
@@ -0,0 +1,112 @@
+use anyhow::{anyhow, Result};
+use axum::{extract::Path, Extension, Json};
+use chrono::Utc;
+use diesel::{update, ExpressionMethods, QueryDsl, SelectableHelper};
+use diesel_async::RunQueryDsl;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        lib::get_pg_pool,
+        models::{ReportSubscription, User},
+        schema::report_subscriptions,
+    },
+    routes::rest::ApiResponse,
+    utils::reports::subscriptions::validate_report_schedule,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateReportSubscriptionRequest {
+    pub cron: Option<String>,
+    pub timezone: Option<String>,
+    pub enabled: Option<bool>,
+}
+
+pub async fn update_report_subscription(
+    Extension(user): Extension<User>,
+    Path(subscription_id): Path<Uuid>,
+    Json(req): Json<UpdateReportSubscriptionRequest>,
+) -> Result<ApiResponse<ReportSubscription>, (StatusCode, String)> {
+    let subscription = match get_report_subscription(&user.id, &subscription_id).await {
+        Ok(Some(subscription)) => subscription,
+        Ok(None) => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                "Report subscription not found".to_string(),
+            ))
+        }
+        Err(e) => {
+            tracing::error!("Error getting report subscription: {:?}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+    };
+
+    let cron = req.cron.unwrap_or(subscription.cron).trim().to_string();
+    let timezone = req.timezone.unwrap_or(subscription.timezone);
+    let enabled = req.enabled.unwrap_or(subscription.enabled);
+
+    if let Err(e) = validate_report_schedule(&cron, &timezone).await {
+        return Err((StatusCode::BAD_REQUEST, e.to_string()));
+    }
+
+    match update_report_subscription_handler(&subscription_id, cron, timezone, enabled).await {
+        Ok(subscription) => Ok(ApiResponse::JsonData(subscription)),
+        Err(e) => {
+            tracing::error!("Error updating report subscription: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
+
+async fn get_report_subscription(
+    user_id: &Uuid,
+    subscription_id: &Uuid,
+) -> Result<Option<ReportSubscription>> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    match report_subscriptions::table
+        .select(ReportSubscription::as_select())
+        .filter(report_subscriptions::id.eq(subscription_id))
+        .filter(report_subscriptions::user_id.eq(user_id))
+        .filter(report_subscriptions::deleted_at.is_null())
+        .first::<ReportSubscription>(&mut conn)
+        .await
+    {
+        Ok(subscription) => Ok(Some(subscription)),
+        Err(diesel::result::Error::NotFound) => Ok(None),
+        Err(e) => Err(anyhow!("Error querying report subscription: {}", e)),
+    }
+}
+
+async fn update_report_subscription_handler(
+    subscription_id: &Uuid,
+    cron: String,
+    timezone: String,
+    enabled: bool,
+) -> Result<ReportSubscription> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    match update(report_subscriptions::table)
+        .filter(report_subscriptions::id.eq(subscription_id))
+        .set((
+            report_subscriptions::cron.eq(cron),
+            report_subscriptions::timezone.eq(timezone),
+            report_subscriptions::enabled.eq(enabled),
+            report_subscriptions::updated_at.eq(Utc::now()),
+        ))
+        .returning(ReportSubscription::as_returning())
+        .get_result::<ReportSubscription>(&mut conn)
+        .await
+    {
+        Ok(subscription) => Ok(subscription),
+        Err(e) => Err(anyhow!("Error updating report subscription: {}", e)),
+    }
+}
@@ -0,0 +1,50 @@
+use axum::Extension;
+use reqwest::StatusCode;
+use uuid::Uuid;
+
+use crate::{
+    database::models::User,
+    routes::rest::ApiResponse,
+    utils::{
+        security::checks::is_user_workspace_admin_or_data_admin,
+        trash::items::{list_trash_items, TrashItem},
+        user::user_info::get_user_organization_id,
+    },
+};
+
+/// Lists the deleted assets the user can restore, newest first.
+pub async fn list_trash(
+    Extension(user): Extension<User>,
+) -> Result<ApiResponse<Vec<TrashItem>>, (StatusCode, String)> {
+    let (organization_id, is_admin) = trash_access(&user).await?;
+
+    match list_trash_items(&user.id, &organization_id, is_admin).await {
+        Ok(items) => Ok(ApiResponse::JsonData(items)),
+        Err(e) => {
+            tracing::error!("Error listing trash: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
+
+/// The user's organization and whether they are one of its admins, who see its whole trash.
+pub(super) async fn trash_access(user: &User) -> Result<(Uuid, bool), (StatusCode, String)> {
+    let organization_id = match get_user_organization_id(&user.id).await {
+        Ok(organization_id) => organization_id,
+        Err(e) => {
+            tracing::error!("Error getting user organization id: {:?}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error getting user organization id".to_string(),
+            ));
+        }
+    };
+
+    match is_user_workspace_admin_or_data_admin(user, &organization_id).await {
+        Ok(is_admin) => Ok((organization_id, is_admin)),
+        Err(e) => {
+            tracing::error!("Error checking user permissions: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
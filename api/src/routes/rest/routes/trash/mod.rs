@@ -0,0 +1,21 @@
+mod list_trash;
+mod purge_trash_item;
+mod restore_trash_item;
+
+use axum::{
+    routing::{delete, get, post},
+    Router,
+};
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/", get(list_trash::list_trash))
+        .route(
+            "/:asset_type/:id/restore",
+            post(restore_trash_item::restore_trash_item),
+        )
+        .route(
+            "/:asset_type/:id",
+            delete(purge_trash_item::purge_trash_item),
+        )
+}
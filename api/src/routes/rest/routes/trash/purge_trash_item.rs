@@ -0,0 +1,55 @@
+use axum::{extract::Path, Extension};
+use reqwest::StatusCode;
+use uuid::Uuid;
+
+use crate::{
+    database::models::User,
+    routes::rest::ApiResponse,
+    utils::trash::{
+        items::{get_trash_item, TrashAssetType},
+        purge::purge_trash_item as purge_item,
+    },
+};
+
+use super::list_trash::trash_access;
+
+/// Permanently deletes an asset in the trash instead of waiting for the retention period.
+pub async fn purge_trash_item(
+    Extension(user): Extension<User>,
+    Path((asset_type, id)): Path<(TrashAssetType, Uuid)>,
+) -> Result<ApiResponse<()>, (StatusCode, String)> {
+    let (organization_id, is_admin) = trash_access(&user).await?;
+
+    if !is_admin {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Only workspace and data admins can permanently delete assets".to_string(),
+        ));
+    }
+
+    match get_trash_item(&user.id, &organization_id, is_admin, asset_type, &id).await {
+        Ok(Some(_)) => (),
+        Ok(None) => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                "Asset not found in trash".to_string(),
+            ))
+        }
+        Err(e) => {
+            tracing::error!("Error getting trash item: {:?}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+    };
+
+    match purge_item(asset_type, &id).await {
+        Ok(true) => Ok(ApiResponse::NoContent),
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            "Asset not found in trash".to_string(),
+        )),
+        Err(e) => {
+            tracing::error!("Error purging trash item: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use axum::{extract::Path, Extension};
+use reqwest::StatusCode;
+use uuid::Uuid;
+
+use crate::{
+    database::models::User,
+    routes::rest::ApiResponse,
+    utils::trash::items::{get_trash_item, restore_trash_item as restore_item, TrashAssetType},
+};
+
+use super::list_trash::trash_access;
+
+pub async fn restore_trash_item(
+    Extension(user): Extension<User>,
+    Path((asset_type, id)): Path<(TrashAssetType, Uuid)>,
+) -> Result<ApiResponse<()>, (StatusCode, String)> {
+    let (organization_id, is_admin) = trash_access(&user).await?;
+
+    match get_trash_item(&user.id, &organization_id, is_admin, asset_type, &id).await {
+        Ok(Some(_)) => (),
+        Ok(None) => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                "Asset not found in trash".to_string(),
+            ))
+        }
+        Err(e) => {
+            tracing::error!("Error getting trash item: {:?}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+    };
+
+    match restore_item(&user.id, asset_type, &id).await {
+        Ok(_) => Ok(ApiResponse::NoContent),
+        Err(e) => {
+            tracing::error!("Error restoring trash item: {:?}", e);
+            Err((StatusCode::CONFLICT, e.to_string()))
+        }
+    }
+}
@@ -21,7 +21,7 @@ use crate::{
         },
     },
     utils::{
-        clients::sentry_utils::send_sentry_error,
+        clients::{sentry_utils::send_sentry_error, supabase_vault::read_secret},
         sharing::asset_sharing::{get_asset_sharing_info, IndividualPermission, TeamPermissions},
     },
 };
@@ -51,6 +51,7 @@ pub struct CollectionState {
     pub team_permissions: Option<Vec<TeamPermissions>>,
     pub organization_permissions: bool,
     pub assets: Option<Vec<CollectionAsset>>,
+    pub public_password: Option<String>,
 }
 
 pub async fn get_collection_by_id(user_id: &Uuid, collection_id: &Uuid) -> Result<CollectionState> {
@@ -204,6 +205,20 @@ pub async fn get_collection_by_id(user_id: &Uuid, collection_id: &Uuid) -> Resul
         }
     };
 
+    let public_password = match (
+        collection.publicly_accessible,
+        collection.password_secret_id,
+    ) {
+        (true, Some(secret_id)) => match read_secret(&secret_id).await {
+            Ok(password) => Some(password),
+            Err(e) => {
+                tracing::error!("Error getting collection password: {}", e);
+                None
+            }
+        },
+        _ => None,
+    };
+
     Ok(CollectionState {
         collection,
         permission,
@@ -211,9 +226,131 @@ pub async fn get_collection_by_id(user_id: &Uuid, collection_id: &Uuid) -> Resul
         team_permissions: collection_sharing_info.team_permissions,
         organization_permissions: collection_sharing_info.organization_permissions,
         assets: collection_assets,
+        public_password,
     })
 }
 
+/// The collection as seen through its public link: read-only, without sharing details.
+pub async fn get_public_collection_by_id(
+    collection_id: &Uuid,
+    password: Option<&String>,
+) -> Result<CollectionState> {
+    let collection = get_public_collection(collection_id, password).await?;
+
+    let assets = match get_collection_assets(Arc::new(*collection_id)).await {
+        Ok(assets) => assets,
+        Err(e) => return Err(anyhow!("Error getting collection assets: {}", e)),
+    };
+
+    Ok(CollectionState {
+        collection,
+        permission: AssetPermissionRole::Viewer,
+        individual_permissions: None,
+        team_permissions: None,
+        organization_permissions: false,
+        assets,
+        public_password: None,
+    })
+}
+
+/// Checks that an asset can be opened through a collection's public link: the asset is in the
+/// collection, which is public, unexpired, and unlocked by `password` if it has one.
+pub async fn check_public_collection_access(
+    collection_id: &Uuid,
+    asset_id: &Uuid,
+    asset_type: AssetType,
+    password: Option<&String>,
+) -> Result<()> {
+    get_public_collection(collection_id, password).await?;
+
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    match collections_to_assets::table
+        .select(collections_to_assets::asset_id)
+        .filter(collections_to_assets::collection_id.eq(collection_id))
+        .filter(collections_to_assets::asset_id.eq(asset_id))
+        .filter(collections_to_assets::asset_type.eq(asset_type))
+        .filter(collections_to_assets::deleted_at.is_null())
+        .first::<Uuid>(&mut conn)
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(diesel::result::Error::NotFound) => Err(anyhow!("Asset is not in this collection")),
+        Err(e) => Err(anyhow!("Error checking collection assets: {}", e)),
+    }
+}
+
+// Loads a collection for a public viewer, failing unless its public link is usable.
+async fn get_public_collection(
+    collection_id: &Uuid,
+    password: Option<&String>,
+) -> Result<Collection> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    let collection = match collections::table
+        .filter(collections::id.eq(collection_id))
+        .filter(collections::deleted_at.is_null())
+        .select(collections::all_columns)
+        .first::<Collection>(&mut conn)
+        .await
+    {
+        Ok(collection) => collection,
+        Err(diesel::result::Error::NotFound) => return Err(anyhow!("Collection not found")),
+        Err(e) => return Err(anyhow!("Error querying collection by ID: {}", e)),
+    };
+
+    let collection_password = match (
+        collection.publicly_accessible,
+        collection.password_secret_id,
+    ) {
+        (true, Some(secret_id)) => match read_secret(&secret_id).await {
+            Ok(collection_password) => Some(collection_password),
+            Err(e) => return Err(anyhow!("Error getting collection password: {}", e)),
+        },
+        _ => None,
+    };
+
+    check_public_link(
+        &collection,
+        collection_password.as_deref(),
+        password,
+        Utc::now(),
+    )?;
+
+    Ok(collection)
+}
+
+fn check_public_link(
+    collection: &Collection,
+    collection_password: Option<&str>,
+    password: Option<&String>,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    if !collection.publicly_accessible {
+        return Err(anyhow!("Collection not found"));
+    }
+
+    if let Some(expiry) = collection.public_expiry_date {
+        if expiry <= now {
+            return Err(anyhow!("Public access to this collection has expired"));
+        }
+    }
+
+    if let Some(collection_password) = collection_password {
+        if password.map(String::as_str) != Some(collection_password) {
+            return Err(anyhow!("Invalid or missing password"));
+        }
+    }
+
+    Ok(())
+}
+
 pub async fn get_user_collection_permission(
     user_id: &Uuid,
     collection_id: &Uuid,
@@ -346,6 +483,11 @@ async fn get_collection_and_check_permissions(
                 collections::updated_at,
                 collections::deleted_at,
                 collections::organization_id,
+                collections::publicly_accessible,
+                collections::publicly_enabled_by,
+                collections::public_expiry_date,
+                collections::password_secret_id,
+                collections::trashed_at,
             ),
             asset_permissions::role,
         ))
@@ -558,3 +700,70 @@ async fn get_thread_assets(collection_id: Arc<Uuid>) -> Result<Option<Vec<Collec
 
     Ok(Some(thread_assets))
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    fn public_collection(public_expiry_date: Option<DateTime<Utc>>) -> Collection {
+        Collection {
+            id: Uuid::new_v4(),
+            name: "Finance".to_string(),
+            description: None,
+            created_by: Uuid::new_v4(),
+            updated_by: Uuid::new_v4(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            deleted_at: None,
+            organization_id: Uuid::new_v4(),
+            publicly_accessible: true,
+            publicly_enabled_by: None,
+            public_expiry_date,
+            password_secret_id: None,
+            trashed_at: None,
+        }
+    }
+
+    #[test]
+    fn test_check_public_link_password() {
+        let collection = public_collection(None);
+        let now = Utc::now();
+
+        assert!(check_public_link(&collection, None, None, now).is_ok());
+        assert!(check_public_link(&collection, Some("hunter2"), None, now).is_err());
+        assert!(check_public_link(
+            &collection,
+            Some("hunter2"),
+            Some(&"wrong".to_string()),
+            now
+        )
+        .is_err());
+        assert!(check_public_link(
+            &collection,
+            Some("hunter2"),
+            Some(&"hunter2".to_string()),
+            now
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_check_public_link_expiry() {
+        let now = Utc::now();
+
+        let expired = public_collection(Some(now - Duration::hours(1)));
+        assert!(check_public_link(&expired, None, None, now).is_err());
+
+        let expiring_now = public_collection(Some(now));
+        assert!(check_public_link(&expiring_now, None, None, now).is_err());
+
+        let active = public_collection(Some(now + Duration::hours(1)));
+        assert!(check_public_link(&active, None, None, now).is_ok());
+
+        let mut private = public_collection(None);
+        private.publicly_accessible = false;
+        assert!(check_public_link(&private, None, None, now).is_err());
+    }
+}
@@ -92,9 +92,14 @@ async fn delete_collection_handler(
         }
     };
 
+    let now = Utc::now();
+
     let _ = match update(collections::table)
         .filter(collections::id.eq_any(&filtered_ids_to_delete))
-        .set(collections::deleted_at.eq(Some(Utc::now())))
+        .set((
+            collections::deleted_at.eq(Some(now)),
+            collections::trashed_at.eq(Some(now)),
+        ))
         .execute(&mut conn)
         .await
     {
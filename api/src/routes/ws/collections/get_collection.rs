@@ -15,13 +15,16 @@ use crate::{
 };
 
 use super::{
-    collection_utils::get_collection_by_id,
+    collection_utils::{
+        get_bulk_user_collection_permission, get_collection_by_id, get_public_collection_by_id,
+    },
     collections_router::{CollectionEvent, CollectionRoute},
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GetCollectionRequest {
     pub id: Uuid,
+    pub password: Option<String>,
 }
 
 pub async fn get_collection(
@@ -48,24 +51,52 @@ pub async fn get_collection(
         }
     };
 
-    let collection = match get_collection_by_id(&user.id, &req.id).await {
-        Ok(collection) => collection,
+    let has_permission = match get_bulk_user_collection_permission(&user.id, &vec![req.id]).await {
+        Ok(permissions) => permissions.contains_key(&req.id),
         Err(e) => {
-            tracing::error!("Error getting collection: {}", e);
+            tracing::error!("Error getting collection permission: {}", e);
             send_sentry_error(&e.to_string(), Some(&user.id));
-            send_error_message(
-                &user.id.to_string(),
-                WsRoutes::Collections(CollectionRoute::Get),
-                WsEvent::Collections(CollectionEvent::CollectionState),
-                WsErrorCode::InternalServerError,
-                e.to_string(),
-                user,
-            )
-            .await?;
             return Err(e);
         }
     };
 
+    // Users without access to the collection can still open it through its public link.
+    let collection = if has_permission {
+        match get_collection_by_id(&user.id, &req.id).await {
+            Ok(collection) => collection,
+            Err(e) => {
+                tracing::error!("Error getting collection: {}", e);
+                send_error_message(
+                    &user.id.to_string(),
+                    WsRoutes::Collections(CollectionRoute::Get),
+                    WsEvent::Collections(CollectionEvent::CollectionState),
+                    WsErrorCode::InternalServerError,
+                    "Failed to get collection.".to_string(),
+                    user,
+                )
+                .await?;
+                return Err(e);
+            }
+        }
+    } else {
+        match get_public_collection_by_id(&req.id, req.password.as_ref()).await {
+            Ok(collection) => collection,
+            Err(e) => {
+                tracing::error!("Error getting public collection: {}", e);
+                send_error_message(
+                    &user.id.to_string(),
+                    WsRoutes::Collections(CollectionRoute::Get),
+                    WsEvent::Collections(CollectionEvent::CollectionState),
+                    WsErrorCode::Unauthorized,
+                    e.to_string(),
+                    user,
+                )
+                .await?;
+                return Err(e);
+            }
+        }
+    };
+
     let post_collection_message = WsResponseMessage::new(
         WsRoutes::Collections(CollectionRoute::Get),
         WsEvent::Collections(CollectionEvent::CollectionState),
@@ -1,4 +1,4 @@
-mod collection_utils;
+pub mod collection_utils;
 pub mod collections_router;
 mod delete_collection;
 mod get_collection;
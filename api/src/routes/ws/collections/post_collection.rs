@@ -115,6 +115,11 @@ async fn post_collection_handler(
         updated_by: user_id.clone(),
         deleted_at: None,
         organization_id,
+        publicly_accessible: false,
+        publicly_enabled_by: None,
+        public_expiry_date: None,
+        password_secret_id: None,
+        trashed_at: None,
     };
 
     let insert_task_user_id = user_id.clone();
@@ -214,5 +219,6 @@ async fn post_collection_handler(
         individual_permissions: None,
         team_permissions: None,
         organization_permissions: false,
+        public_password: None,
     })
 }
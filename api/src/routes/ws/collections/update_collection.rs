@@ -21,7 +21,8 @@ use crate::{
         ws_utils::{send_error_message, send_ws_message, subscribe_to_stream},
     },
     utils::{
-        clients::sentry_utils::send_sentry_error,
+        clients::{sentry_utils::send_sentry_error, supabase_vault::create_secret},
+        serde_helpers::deserialization_helpers::deserialize_double_option,
         sharing::asset_sharing::{
             update_asset_permissions, ShareWithTeamsReqObject, ShareWithUsersReqObject,
         },
@@ -53,6 +54,13 @@ pub struct UpdateCollectionRequest {
     #[serde(flatten)]
     pub collection: Option<UpdateCollectionObject>,
     pub assets: Option<Vec<UpdateCollectionAssetsRequest>>,
+    pub publicly_accessible: Option<bool>,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_double_option")]
+    pub public_password: Option<Option<String>>,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_double_option")]
+    pub public_expiry_date: Option<Option<chrono::NaiveDateTime>>,
     pub team_permissions: Option<Vec<ShareWithTeamsReqObject>>,
     pub user_permissions: Option<Vec<ShareWithUsersReqObject>>,
     pub remove_teams: Option<Vec<Uuid>>,
@@ -147,6 +155,26 @@ pub async fn update_collection(
         None
     };
 
+    let update_collection_public_access_handle = if req.publicly_accessible.is_some()
+        || req.public_password.is_some()
+        || req.public_expiry_date.is_some()
+    {
+        let user_id = Arc::clone(&user_id);
+        let collection_id = Arc::clone(&collection_id);
+        Some(tokio::spawn(async move {
+            update_collection_public_access(
+                user_id,
+                collection_id,
+                req.publicly_accessible,
+                req.public_password,
+                req.public_expiry_date,
+            )
+            .await
+        }))
+    } else {
+        None
+    };
+
     let update_collection_permissions_handle = if req.team_permissions.is_some()
         || req.user_permissions.is_some()
         || req.remove_teams.is_some()
@@ -198,6 +226,22 @@ pub async fn update_collection(
         }
     }
 
+    if let Some(update_collection_public_access_handle) = update_collection_public_access_handle {
+        match update_collection_public_access_handle.await {
+            Ok(Ok(_)) => (),
+            Ok(Err(e)) => {
+                tracing::error!("Error updating collection public access: {}", e);
+                send_sentry_error(&e.to_string(), None);
+                return Err(anyhow!("Error updating collection public access: {}", e));
+            }
+            Err(e) => {
+                tracing::error!("Error updating collection public access: {}", e);
+                send_sentry_error(&e.to_string(), None);
+                return Err(anyhow!("Error updating collection public access: {}", e));
+            }
+        }
+    }
+
     if let Some(update_collection_assets_handle) = update_collection_assets_handle {
         match update_collection_assets_handle.await {
             Ok(_) => (),
@@ -332,6 +376,72 @@ async fn update_collection_record(
     Ok(())
 }
 
+#[derive(AsChangeset)]
+#[diesel(table_name = collections)]
+pub struct CollectionPublicAccessChangeset {
+    pub updated_at: DateTime<Utc>,
+    pub updated_by: Uuid,
+    pub publicly_accessible: Option<bool>,
+    pub publicly_enabled_by: Option<Uuid>,
+    pub password_secret_id: Option<Option<Uuid>>,
+    pub public_expiry_date: Option<Option<chrono::NaiveDateTime>>,
+}
+
+async fn update_collection_public_access(
+    user_id: Arc<Uuid>,
+    collection_id: Arc<Uuid>,
+    publicly_accessible: Option<bool>,
+    public_password: Option<Option<String>>,
+    public_expiry_date: Option<Option<chrono::NaiveDateTime>>,
+) -> Result<()> {
+    let password_secret_id = match public_password {
+        Some(Some(password)) => match create_secret(&password).await {
+            Ok(secret_id) => Some(Some(secret_id)),
+            Err(e) => {
+                tracing::error!("Error creating secret: {}", e);
+                return Err(anyhow!("Error creating secret: {}", e));
+            }
+        },
+        Some(None) => Some(None),
+        None => None,
+    };
+
+    let publicly_enabled_by = match publicly_accessible {
+        Some(true) => Some(*user_id),
+        _ => None,
+    };
+
+    let changeset = CollectionPublicAccessChangeset {
+        updated_at: Utc::now(),
+        updated_by: *user_id,
+        publicly_accessible,
+        publicly_enabled_by,
+        password_secret_id,
+        public_expiry_date,
+    };
+
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            tracing::error!("Error getting pg connection: {}", e);
+            return Err(anyhow!("Error getting pg connection: {}", e));
+        }
+    };
+
+    match update(collections::table)
+        .filter(collections::id.eq(collection_id.as_ref()))
+        .set(&changeset)
+        .execute(&mut conn)
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            tracing::error!("Error updating collection public access: {}", e);
+            Err(anyhow!("Error updating collection public access: {}", e))
+        }
+    }
+}
+
 async fn update_collection_assets(
     user_id: Arc<Uuid>,
     collection_id: Arc<Uuid>,
@@ -21,6 +21,12 @@ use crate::{
     },
     utils::{
         clients::{sentry_utils::send_sentry_error, supabase_vault::read_secret},
+        dashboards::filters::{
+            apply_dashboard_filters, filter_cache_key, get_dataset_data_source_types,
+            get_dataset_table_names, lock_filter_values, parse_dashboard_filters,
+            resolve_active_filters, DashboardFilter,
+        },
+        embed::tokens::EmbedClaims,
         query_engine::data_types::DataType,
         sharing::asset_sharing::{
             get_asset_collections, get_asset_sharing_info, CollectionNameAndId,
@@ -44,6 +50,10 @@ pub struct Metric {
     pub chart_config: Value,
     pub data: Option<Vec<IndexMap<String, DataType>>>,
     pub data_metadata: Option<Value>,
+    #[serde(default)]
+    pub filter_key: Option<String>, // Identifies the dashboard filter values applied to the sql
+    #[serde(skip_serializing, default)]
+    pub filter_error: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -61,9 +71,22 @@ pub struct DashboardState {
 pub async fn get_dashboard_state_by_id(
     user_id: &Uuid,
     dashboard_id: &Uuid,
+    filter_values: Option<&HashMap<String, Value>>,
+) -> Result<DashboardState> {
+    get_dashboard_state_with_access(user_id, dashboard_id, filter_values, false).await
+}
+
+/// Like `get_dashboard_state_by_id`, but with `collection_access` a user the dashboard isn't shared
+/// with can load it, having opened it through a public collection that contains it.
+pub async fn get_dashboard_state_with_access(
+    user_id: &Uuid,
+    dashboard_id: &Uuid,
+    filter_values: Option<&HashMap<String, Value>>,
+    collection_access: bool,
 ) -> Result<DashboardState> {
     let dashboard_id = Arc::new(dashboard_id.clone());
     let user_id = Arc::new(user_id.clone());
+    let filter_values = filter_values.cloned();
 
     let dashboard_and_permission = {
         let dashboard_id = Arc::clone(&dashboard_id);
@@ -82,7 +105,7 @@ pub async fn get_dashboard_state_by_id(
 
     let dashboard_metrics = {
         let dashboard_id = Arc::clone(&dashboard_id);
-        tokio::spawn(async move { get_dashboard_metrics(dashboard_id).await })
+        tokio::spawn(async move { get_dashboard_metrics(dashboard_id, filter_values).await })
     };
 
     let dashboard_collections = {
@@ -184,7 +207,11 @@ pub async fn get_dashboard_state_by_id(
             .unwrap_or(false);
 
         // If no direct or team access, return unauthorized
-        if !has_direct_access && !has_team_access && !dashboard.publicly_accessible {
+        if !has_direct_access
+            && !has_team_access
+            && !dashboard.publicly_accessible
+            && !collection_access
+        {
             return Err(anyhow!("Unauthorized access to dashboard"));
         }
     }
@@ -217,6 +244,74 @@ pub async fn get_dashboard_state_by_id(
     })
 }
 
+/// The dashboard an embed token grants, read-only and without any sharing details. Only the filters
+/// the token leaves open can be set through `filter_values`, and a metric a locked filter doesn't
+/// map to gets a `filter_error` so it is never queried unfiltered.
+pub async fn get_embedded_dashboard_state(
+    claims: &EmbedClaims,
+    filter_values: Option<&HashMap<String, Value>>,
+) -> Result<DashboardState> {
+    let dashboard_id = Arc::new(claims.sub);
+
+    let dashboard = get_dashboard_by_id(Arc::clone(&dashboard_id)).await?;
+
+    if dashboard.organization_id != claims.iss {
+        return Err(anyhow!("dashboard not found"));
+    }
+
+    let dashboard_filters = parse_dashboard_filters(&dashboard.config)?;
+
+    let (filter_values, locked_ids) = lock_filter_values(
+        &dashboard_filters,
+        filter_values,
+        &claims.filters,
+        &claims.open_filters,
+        &claims.user_attributes,
+    )?;
+
+    let mut metrics = get_dashboard_metrics(dashboard_id, Some(filter_values)).await?;
+
+    lock_embedded_metrics(&dashboard_filters, &locked_ids, &mut metrics);
+
+    Ok(DashboardState {
+        dashboard,
+        metrics,
+        collections: vec![],
+        permission: Some(AssetPermissionRole::Viewer),
+        individual_permissions: None,
+        team_permissions: None,
+        organization_permissions: false,
+        public_password: None,
+    })
+}
+
+// A metric that a locked filter isn't mapped to would show every viewer's data, so it is never
+// queried.
+fn lock_embedded_metrics(
+    dashboard_filters: &[DashboardFilter],
+    locked_ids: &[String],
+    metrics: &mut [Metric],
+) {
+    for metric in metrics.iter_mut() {
+        let unmapped_filter = dashboard_filters
+            .iter()
+            .filter(|filter| locked_ids.contains(&filter.id))
+            .find(|filter| {
+                !filter
+                    .columns
+                    .iter()
+                    .any(|column| column.dataset_id == metric.dataset_id)
+            });
+
+        if let Some(filter) = unmapped_filter {
+            metric.filter_error = Some(format!(
+                "Locked dashboard filter '{}' does not apply to this metric",
+                filter.id
+            ));
+        }
+    }
+}
+
 pub async fn get_user_dashboard_permission(
     user_id: &Uuid,
     dashboard_id: &Uuid,
@@ -415,6 +510,8 @@ async fn get_dashboard_by_id(dashboard_id: Arc<Uuid>) -> Result<Dashboard> {
             dashboards::updated_at,
             dashboards::deleted_at,
             dashboards::organization_id,
+            dashboards::env,
+            dashboards::trashed_at,
         ))
         .first::<Dashboard>(&mut conn)
         .await
@@ -431,7 +528,10 @@ async fn get_dashboard_by_id(dashboard_id: Arc<Uuid>) -> Result<Dashboard> {
     Ok(dashboard)
 }
 
-async fn get_dashboard_metrics(dashboard_id: Arc<Uuid>) -> Result<Vec<Metric>> {
+async fn get_dashboard_metrics(
+    dashboard_id: Arc<Uuid>,
+    filter_values: Option<HashMap<String, Value>>,
+) -> Result<Vec<Metric>> {
     let mut conn = match get_pg_pool().get().await {
         Ok(conn) => conn,
         Err(e) => {
@@ -486,9 +586,225 @@ async fn get_dashboard_metrics(dashboard_id: Arc<Uuid>) -> Result<Vec<Metric>> {
             chart_config: message.chart_config.unwrap_or_default(),
             data: None,
             data_metadata: message.data_metadata,
+            filter_key: None,
+            filter_error: None,
         };
         metrics.push(metric);
     }
 
+    match apply_dashboard_filters_to_metrics(&dashboard_id, &mut metrics, filter_values.as_ref())
+        .await
+    {
+        Ok(_) => (),
+        Err(e) => return Err(anyhow!("Error applying dashboard filters: {}", e)),
+    };
+
     Ok(metrics)
 }
+
+// Rewrites each metric's sql with the dashboard's filters. A metric the filters can't be applied to
+// keeps the error instead of being queried unfiltered.
+async fn apply_dashboard_filters_to_metrics(
+    dashboard_id: &Uuid,
+    metrics: &mut Vec<Metric>,
+    filter_values: Option<&HashMap<String, Value>>,
+) -> Result<()> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            tracing::error!("Error getting pg connection: {}", e);
+            return Err(anyhow!("Error getting pg connection: {}", e));
+        }
+    };
+
+    let dashboard_config = match dashboards::table
+        .select(dashboards::config)
+        .filter(dashboards::id.eq(dashboard_id))
+        .first::<Value>(&mut conn)
+        .await
+    {
+        Ok(config) => config,
+        Err(diesel::result::Error::NotFound) => return Ok(()),
+        Err(e) => return Err(anyhow!("Error querying dashboard config: {}", e)),
+    };
+
+    let active_filters = match parse_dashboard_filters(&dashboard_config)
+        .and_then(|filters| resolve_active_filters(&filters, filter_values))
+    {
+        Ok(active_filters) => active_filters,
+        Err(e) => {
+            tracing::error!("Error resolving dashboard filters: {}", e);
+            for metric in metrics.iter_mut() {
+                metric.filter_error = Some(e.to_string());
+            }
+            return Ok(());
+        }
+    };
+
+    if active_filters.is_empty() {
+        return Ok(());
+    }
+
+    let filter_key = filter_cache_key(&active_filters);
+
+    let dataset_ids: Vec<Uuid> = metrics.iter().map(|metric| metric.dataset_id).collect();
+    let table_names = get_dataset_table_names(&dataset_ids).await?;
+    let data_source_types = get_dataset_data_source_types(&dataset_ids).await?;
+
+    for metric in metrics.iter_mut() {
+        let metric_table_names = table_names
+            .get(&metric.dataset_id)
+            .cloned()
+            .unwrap_or_default();
+
+        metric.filter_key = filter_key.clone();
+
+        let data_source_type = match data_source_types.get(&metric.dataset_id) {
+            Some(data_source_type) => data_source_type,
+            None => {
+                // Metrics without a dataset have nothing the filters can be mapped to.
+                let is_filtered = active_filters.iter().any(|filter| {
+                    filter
+                        .columns
+                        .iter()
+                        .any(|column| column.dataset_id == metric.dataset_id)
+                });
+
+                if is_filtered {
+                    metric.filter_error = Some("Metric dataset no longer exists".to_string());
+                }
+                continue;
+            }
+        };
+
+        match apply_dashboard_filters(
+            &metric.sql,
+            &metric.dataset_id,
+            &metric_table_names,
+            data_source_type,
+            &active_filters,
+        ) {
+            Ok(sql) => metric.sql = sql,
+            Err(e) => {
+                tracing::error!("Error applying dashboard filters to metric {}: {}", metric.id, e);
+                metric.filter_error = Some(e.to_string());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use serde_json::json;
+
+    use crate::{
+        database::enums::DataSourceType,
+        utils::embed::tokens::{sign_embed_token, verify_embed_token, EMBED_AUDIENCE},
+    };
+
+    fn metric(dataset_id: Uuid, sql: &str) -> Metric {
+        Metric {
+            id: Uuid::new_v4(),
+            message_id: Uuid::new_v4(),
+            name: "Revenue".to_string(),
+            time_frame: "all time".to_string(),
+            sql: sql.to_string(),
+            dataset_id,
+            chart_config: json!({}),
+            data: None,
+            data_metadata: None,
+            filter_key: None,
+            filter_error: None,
+        }
+    }
+
+    // Follows an embed token from signing to the SQL its metrics run, minus the database reads.
+    #[test]
+    fn test_embedded_dashboard_filters() {
+        let organization_id = Uuid::new_v4();
+        let orders_id = Uuid::new_v4();
+        let invoices_id = Uuid::new_v4();
+
+        let dashboard_filters = parse_dashboard_filters(&json!({
+            "filters": [
+                {
+                    "id": "customer",
+                    "type": "select",
+                    "user_attribute": "customer_id",
+                    "columns": [{ "dataset_id": orders_id, "column": "customer_id" }]
+                },
+                {
+                    "id": "status",
+                    "type": "select",
+                    "columns": [{ "dataset_id": orders_id, "column": "status" }]
+                }
+            ]
+        }))
+        .unwrap();
+
+        let claims = EmbedClaims {
+            aud: EMBED_AUDIENCE.to_string(),
+            iss: organization_id,
+            sub: Uuid::new_v4(),
+            iat: Utc::now().timestamp(),
+            exp: Utc::now().timestamp() + 600,
+            filters: HashMap::new(),
+            open_filters: vec!["status".to_string()],
+            user_attributes: HashMap::from([("customer_id".to_string(), json!("acme"))]),
+        };
+        let token = sign_embed_token("secret", &claims).unwrap();
+        let claims = verify_embed_token(&token, "secret", &organization_id).unwrap();
+
+        let embedded_sql = |requested: HashMap<String, Value>| -> Result<Vec<Metric>> {
+            let (filter_values, locked_ids) = lock_filter_values(
+                &dashboard_filters,
+                Some(&requested),
+                &claims.filters,
+                &claims.open_filters,
+                &claims.user_attributes,
+            )?;
+            let active_filters = resolve_active_filters(&dashboard_filters, Some(&filter_values))?;
+
+            let mut metrics = vec![
+                metric(orders_id, "SELECT SUM(amount) FROM orders"),
+                metric(invoices_id, "SELECT SUM(total) FROM invoices"),
+            ];
+            metrics[0].sql = apply_dashboard_filters(
+                &metrics[0].sql,
+                &orders_id,
+                &["orders".to_string()],
+                &DataSourceType::MySql,
+                &active_filters,
+            )?;
+            lock_embedded_metrics(&dashboard_filters, &locked_ids, &mut metrics);
+
+            Ok(metrics)
+        };
+
+        let metrics = embedded_sql(HashMap::from([(
+            "status".to_string(),
+            json!(["paid", "refunded"]),
+        )]))
+        .unwrap();
+        assert_eq!(
+            metrics[0].sql,
+            "SELECT SUM(amount) FROM orders WHERE orders.customer_id = 'acme' \
+             AND orders.status IN ('paid', 'refunded')"
+        );
+        assert_eq!(metrics[0].filter_error, None);
+        // The invoices metric can't be limited to the viewer's customer, so it isn't queried.
+        assert!(metrics[1].filter_error.is_some());
+
+        // The viewer can't widen the locked filter, nor break out of the open one.
+        assert!(embedded_sql(HashMap::from([("customer".to_string(), json!("globex"))])).is_err());
+        assert!(embedded_sql(HashMap::from([(
+            "status".to_string(),
+            json!("paid\\' OR 1=1 -- ")
+        )]))
+        .is_err());
+    }
+}
@@ -0,0 +1,230 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    database::{enums::AssetPermissionRole, models::User},
+    routes::ws::{
+        dashboards::dashboards_router::{DashboardEvent, DashboardRoute},
+        ws::{WsErrorCode, WsEvent, WsResponseMessage, WsSendMethod},
+        ws_router::WsRoutes,
+        ws_utils::{send_error_message, send_ws_message},
+    },
+    utils::{
+        clients::sentry_utils::send_sentry_error,
+        dashboards::versions::{
+            diff_dashboard_versions, get_dashboard_version, list_dashboard_versions,
+            restore_dashboard_version, DashboardVersionDiff, DashboardVersionSummary,
+        },
+    },
+};
+
+use super::dashboard_utils::{get_dashboard_state_by_id, get_user_dashboard_permission};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ListDashboardVersionsRequest {
+    pub id: Uuid,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ListDashboardVersionsResponse {
+    pub dashboard_id: Uuid,
+    pub versions: Vec<DashboardVersionSummary>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DiffDashboardVersionsRequest {
+    pub id: Uuid,
+    pub from_version_id: Uuid,
+    pub to_version_id: Uuid,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RestoreDashboardVersionRequest {
+    pub id: Uuid,
+    pub version_id: Uuid,
+}
+
+/// Returns an error unless the user can see the dashboard, and when `edit` is set, change it.
+pub async fn check_dashboard_version_access(
+    user_id: &Uuid,
+    dashboard_id: &Uuid,
+    edit: bool,
+) -> Result<()> {
+    let permission = match get_user_dashboard_permission(user_id, dashboard_id).await {
+        Ok(Some(permission)) => permission,
+        Ok(None) => return Err(anyhow!("No dashboard permission found")),
+        Err(e) => return Err(anyhow!("Error getting dashboard permission: {}", e)),
+    };
+
+    if edit && permission == AssetPermissionRole::Viewer {
+        return Err(anyhow!(
+            "User does not have permission to restore dashboard versions"
+        ));
+    }
+
+    Ok(())
+}
+
+pub async fn list_versions(user: &User, req: ListDashboardVersionsRequest) -> Result<()> {
+    let versions = match list_versions_handler(&user.id, &req.id).await {
+        Ok(versions) => versions,
+        Err(e) => {
+            tracing::error!("Error listing dashboard versions: {}", e);
+            send_sentry_error(&e.to_string(), Some(&user.id));
+            send_error_message(
+                &user.id.to_string(),
+                WsRoutes::Dashboards(DashboardRoute::ListVersions),
+                WsEvent::Dashboards(DashboardEvent::ListDashboardVersions),
+                WsErrorCode::InternalServerError,
+                "Failed to list dashboard versions.".to_string(),
+                user,
+            )
+            .await?;
+            return Err(e);
+        }
+    };
+
+    let list_versions_message = WsResponseMessage::new(
+        WsRoutes::Dashboards(DashboardRoute::ListVersions),
+        WsEvent::Dashboards(DashboardEvent::ListDashboardVersions),
+        ListDashboardVersionsResponse {
+            dashboard_id: req.id,
+            versions,
+        },
+        None,
+        user,
+        WsSendMethod::SenderOnly,
+    );
+
+    match send_ws_message(&user.id.to_string(), &list_versions_message).await {
+        Ok(_) => (),
+        Err(e) => {
+            tracing::error!("Error sending ws message: {}", e);
+            send_sentry_error(&e.to_string(), Some(&user.id));
+            return Err(anyhow!("Error sending ws message: {}", e));
+        }
+    }
+
+    Ok(())
+}
+
+async fn list_versions_handler(
+    user_id: &Uuid,
+    dashboard_id: &Uuid,
+) -> Result<Vec<DashboardVersionSummary>> {
+    check_dashboard_version_access(user_id, dashboard_id, false).await?;
+    list_dashboard_versions(dashboard_id).await
+}
+
+pub async fn diff_versions(user: &User, req: DiffDashboardVersionsRequest) -> Result<()> {
+    let diff = match diff_versions_handler(&user.id, &req).await {
+        Ok(diff) => diff,
+        Err(e) => {
+            tracing::error!("Error diffing dashboard versions: {}", e);
+            send_sentry_error(&e.to_string(), Some(&user.id));
+            send_error_message(
+                &user.id.to_string(),
+                WsRoutes::Dashboards(DashboardRoute::DiffVersions),
+                WsEvent::Dashboards(DashboardEvent::DiffDashboardVersions),
+                WsErrorCode::InternalServerError,
+                "Failed to compare dashboard versions.".to_string(),
+                user,
+            )
+            .await?;
+            return Err(e);
+        }
+    };
+
+    let diff_versions_message = WsResponseMessage::new(
+        WsRoutes::Dashboards(DashboardRoute::DiffVersions),
+        WsEvent::Dashboards(DashboardEvent::DiffDashboardVersions),
+        diff,
+        None,
+        user,
+        WsSendMethod::SenderOnly,
+    );
+
+    match send_ws_message(&user.id.to_string(), &diff_versions_message).await {
+        Ok(_) => (),
+        Err(e) => {
+            tracing::error!("Error sending ws message: {}", e);
+            send_sentry_error(&e.to_string(), Some(&user.id));
+            return Err(anyhow!("Error sending ws message: {}", e));
+        }
+    }
+
+    Ok(())
+}
+
+async fn diff_versions_handler(
+    user_id: &Uuid,
+    req: &DiffDashboardVersionsRequest,
+) -> Result<DashboardVersionDiff> {
+    check_dashboard_version_access(user_id, &req.id, false).await?;
+
+    let from = get_dashboard_version(&req.id, &req.from_version_id).await?;
+    let to = get_dashboard_version(&req.id, &req.to_version_id).await?;
+
+    Ok(diff_dashboard_versions(&from, &to))
+}
+
+pub async fn restore_version(user: &User, req: RestoreDashboardVersionRequest) -> Result<()> {
+    let restore_result = match check_dashboard_version_access(&user.id, &req.id, true).await {
+        Ok(_) => restore_dashboard_version(&req.id, &req.version_id, &user.id).await,
+        Err(e) => Err(e),
+    };
+
+    if let Err(e) = restore_result {
+        tracing::error!("Error restoring dashboard version: {}", e);
+        send_sentry_error(&e.to_string(), Some(&user.id));
+        send_error_message(
+            &user.id.to_string(),
+            WsRoutes::Dashboards(DashboardRoute::RestoreVersion),
+            WsEvent::Dashboards(DashboardEvent::UpdateDashboard),
+            WsErrorCode::InternalServerError,
+            "Failed to restore dashboard version.".to_string(),
+            user,
+        )
+        .await?;
+        return Err(e);
+    }
+
+    broadcast_restored_dashboard(user, &req.id).await
+}
+
+/// Sends the restored dashboard to everyone subscribed to it, the same way updates are sent.
+pub async fn broadcast_restored_dashboard(user: &User, dashboard_id: &Uuid) -> Result<()> {
+    let dashboard = match get_dashboard_state_by_id(&user.id, dashboard_id, None).await {
+        Ok(dashboard) => dashboard,
+        Err(e) => {
+            tracing::error!("Error getting dashboard: {}", e);
+            send_sentry_error(&e.to_string(), Some(&user.id));
+            return Err(e);
+        }
+    };
+
+    let restored_dashboard_message = WsResponseMessage::new(
+        WsRoutes::Dashboards(DashboardRoute::RestoreVersion),
+        WsEvent::Dashboards(DashboardEvent::UpdateDashboard),
+        dashboard,
+        None,
+        user,
+        WsSendMethod::All,
+    );
+
+    match send_ws_message(
+        &format!("dashboard:{}", dashboard_id),
+        &restored_dashboard_message,
+    )
+    .await
+    {
+        Ok(_) => (),
+        Err(e) => {
+            tracing::error!("Error sending message to pubsub: {}", e);
+            return Err(anyhow!("Error sending message to pubsub: {}", e));
+        }
+    }
+
+    Ok(())
+}
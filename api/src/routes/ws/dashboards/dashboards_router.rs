@@ -6,10 +6,20 @@ use serde_json::Value;
 
 use uuid::Uuid;
 
-use crate::{database::models::User, routes::ws::ws::SubscriptionRwLock};
+use crate::{
+    database::models::User,
+    routes::ws::{
+        ws::{SubscriptionRwLock, WsErrorCode, WsEvent},
+        ws_router::WsRoutes,
+        ws_utils::send_error_message,
+    },
+    utils::embed::tokens::EmbedClaims,
+};
 
 use super::{
-    delete_dashboard::delete_dashboard, get_dashboard::get_dashboard,
+    dashboard_versions::{diff_versions, list_versions, restore_version},
+    delete_dashboard::delete_dashboard, duplicate_dashboard::duplicate_dashboard,
+    get_dashboard::{get_dashboard, get_embedded_dashboard},
     list_dashboards::list_dashboards, post_dashboard::post_dashboard, unsubscribe::unsubscribe,
     update_dashboard::update_dashboard,
 };
@@ -28,6 +38,14 @@ pub enum DashboardRoute {
     Update,
     #[serde(rename = "/dashboards/delete")]
     Delete,
+    #[serde(rename = "/dashboards/versions/list")]
+    ListVersions,
+    #[serde(rename = "/dashboards/versions/diff")]
+    DiffVersions,
+    #[serde(rename = "/dashboards/versions/restore")]
+    RestoreVersion,
+    #[serde(rename = "/dashboards/duplicate")]
+    Duplicate,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -42,6 +60,9 @@ pub enum DashboardEvent {
     UpdateDashboard,
     JoinedDashboard,
     DeleteDashboard,
+    ListDashboardVersions,
+    DiffDashboardVersions,
+    DuplicateDashboard,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -117,6 +138,72 @@ pub async fn dashboards_router(
 
             delete_dashboard(user, req).await?;
         }
+        DashboardRoute::ListVersions => {
+            let req = match serde_json::from_value(data) {
+                Ok(req) => req,
+                Err(e) => return Err(anyhow!("Error parsing request: {}", e)),
+            };
+
+            list_versions(user, req).await?;
+        }
+        DashboardRoute::DiffVersions => {
+            let req = match serde_json::from_value(data) {
+                Ok(req) => req,
+                Err(e) => return Err(anyhow!("Error parsing request: {}", e)),
+            };
+
+            diff_versions(user, req).await?;
+        }
+        DashboardRoute::RestoreVersion => {
+            let req = match serde_json::from_value(data) {
+                Ok(req) => req,
+                Err(e) => return Err(anyhow!("Error parsing request: {}", e)),
+            };
+
+            restore_version(user, req).await?;
+        }
+        DashboardRoute::Duplicate => {
+            let req = match serde_json::from_value(data) {
+                Ok(req) => req,
+                Err(e) => return Err(anyhow!("Error parsing request: {}", e)),
+            };
+
+            duplicate_dashboard(subscriptions, user_group, user, req).await?;
+        }
+    };
+
+    Ok(())
+}
+
+/// Embedded sessions can only read the dashboard their token grants.
+pub async fn embedded_dashboards_router(
+    route: DashboardRoute,
+    data: Value,
+    subscriptions: &Arc<SubscriptionRwLock>,
+    user_group: &String,
+    user: &User,
+    claims: &EmbedClaims,
+) -> Result<()> {
+    match route {
+        DashboardRoute::Get => {
+            let req = match serde_json::from_value(data) {
+                Ok(req) => req,
+                Err(e) => return Err(anyhow!("Error parsing request: {}", e)),
+            };
+
+            get_embedded_dashboard(subscriptions, user_group, user, claims, req).await?;
+        }
+        _ => {
+            send_error_message(
+                &user.id.to_string(),
+                WsRoutes::Dashboards(route.clone()),
+                WsEvent::Dashboards(DashboardEvent::GetDashboardState),
+                WsErrorCode::Unauthorized,
+                "Embedded dashboards are read-only".to_string(),
+                user,
+            )
+            .await?;
+        }
     };
 
     Ok(())
@@ -131,6 +218,10 @@ impl DashboardRoute {
             "/dashboards/unsubscribe" => Ok(Self::Unsubscribe),
             "/dashboards/update" => Ok(Self::Update),
             "/dashboards/delete" => Ok(Self::Delete),
+            "/dashboards/versions/list" => Ok(Self::ListVersions),
+            "/dashboards/versions/diff" => Ok(Self::DiffVersions),
+            "/dashboards/versions/restore" => Ok(Self::RestoreVersion),
+            "/dashboards/duplicate" => Ok(Self::Duplicate),
             _ => Err(anyhow!("Invalid path")),
         }
     }
@@ -93,9 +93,14 @@ async fn delete_dashboard_handler(
                 }
             };
 
+            let now = Utc::now();
+
             let _ = match update(dashboards::table)
                 .filter(dashboards::id.eq_any(&ids))
-                .set(dashboards::deleted_at.eq(Some(Utc::now())))
+                .set((
+                    dashboards::deleted_at.eq(Some(now)),
+                    dashboards::trashed_at.eq(Some(now)),
+                ))
                 .execute(&mut conn)
                 .await
             {
@@ -0,0 +1,491 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use diesel::{insert_into, ExpressionMethods};
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        enums::{AssetPermissionRole, AssetType, IdentityType},
+        lib::get_pg_pool,
+        models::{AssetPermission, CollectionToAsset, Dashboard, ThreadToDashboard, User},
+        schema::{asset_permissions, collections_to_assets, dashboards, threads_to_dashboards},
+    },
+    routes::ws::{
+        collections::collection_utils::get_bulk_user_collection_permission,
+        threads_and_messages::{
+            duplicate_thread::{insert_thread_duplicate, prepare_thread_duplicate},
+            thread_utils::{get_thread_state_by_id, ThreadState},
+        },
+        ws::{SubscriptionRwLock, WsErrorCode, WsEvent, WsResponseMessage, WsSendMethod},
+        ws_router::WsRoutes,
+        ws_utils::{send_error_message, send_ws_message, subscribe_to_stream},
+    },
+    utils::{
+        clients::sentry_utils::send_sentry_error,
+        dashboards::duplication::{load_dataset_remaps, remap_dashboard_config, remap_dataset_sql},
+        user::user_info::get_user_organization_id,
+    },
+};
+
+use super::{
+    dashboard_utils::{get_dashboard_state_by_id, DashboardState},
+    dashboards_router::{DashboardEvent, DashboardRoute, JoinedDashboard},
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct DuplicateDashboardRequest {
+    pub id: Uuid,
+    /// Defaults to the original name with " (Copy)" appended.
+    pub name: Option<String>,
+    /// Duplicate every metric thread instead of pointing the copy at the same threads.
+    pub deep_copy: Option<bool>,
+    /// Source dataset id to the dataset the copied metrics should read instead. Targets need every
+    /// column of their source. Only allowed with `deep_copy`.
+    pub dataset_remap: Option<HashMap<Uuid, Uuid>>,
+    pub share_with_same_people: Option<bool>,
+}
+
+pub async fn duplicate_dashboard(
+    subscriptions: &Arc<SubscriptionRwLock>,
+    user_group: &String,
+    user: &User,
+    req: DuplicateDashboardRequest,
+) -> Result<()> {
+    let dashboard_state = match get_dashboard_state_by_id(&user.id, &req.id, None).await {
+        Ok(dashboard_state) => dashboard_state,
+        Err(e) => {
+            tracing::error!("Error getting dashboard: {}", e);
+            send_error_message(
+                &user.id.to_string(),
+                WsRoutes::Dashboards(DashboardRoute::Duplicate),
+                WsEvent::Dashboards(DashboardEvent::DuplicateDashboard),
+                WsErrorCode::NotFound,
+                "Dashboard not found.".to_string(),
+                user,
+            )
+            .await?;
+            return Err(anyhow!("Error getting dashboard: {}", e));
+        }
+    };
+
+    // Threads to deep copy are loaded and remapped first so a bad remapping fails before anything
+    // is written.
+    let copied_threads = match prepare_thread_copies(user, &dashboard_state, &req).await {
+        Ok(copied_threads) => copied_threads,
+        Err(e) => {
+            tracing::error!("Error preparing dashboard duplicate: {}", e);
+            send_error_message(
+                &user.id.to_string(),
+                WsRoutes::Dashboards(DashboardRoute::Duplicate),
+                WsEvent::Dashboards(DashboardEvent::DuplicateDashboard),
+                WsErrorCode::BadRequest,
+                e.to_string(),
+                user,
+            )
+            .await?;
+            return Err(e);
+        }
+    };
+
+    let dashboard =
+        match duplicate_dashboard_handler(user, &dashboard_state, copied_threads, &req).await {
+            Ok(dashboard) => dashboard,
+            Err(e) => {
+                tracing::error!("Error duplicating dashboard: {}", e);
+                send_sentry_error(&e.to_string(), Some(&user.id));
+                send_error_message(
+                    &user.id.to_string(),
+                    WsRoutes::Dashboards(DashboardRoute::Duplicate),
+                    WsEvent::Dashboards(DashboardEvent::DuplicateDashboard),
+                    WsErrorCode::InternalServerError,
+                    "Failed to duplicate dashboard.".to_string(),
+                    user,
+                )
+                .await?;
+                return Err(anyhow!("Error duplicating dashboard: {}", e));
+            }
+        };
+
+    let dashboard_subscription = format!("dashboard:{}", dashboard.id);
+
+    match subscribe_to_stream(subscriptions, &dashboard_subscription, user_group, &user.id).await {
+        Ok(_) => (),
+        Err(e) => return Err(anyhow!("Error subscribing to dashboard: {}", e)),
+    };
+
+    let new_dashboard_state = match get_dashboard_state_by_id(&user.id, &dashboard.id, None).await {
+        Ok(dashboard_state) => dashboard_state,
+        Err(e) => {
+            tracing::error!("Error getting duplicated dashboard: {}", e);
+            send_sentry_error(&e.to_string(), Some(&user.id));
+            return Err(anyhow!("Error getting duplicated dashboard: {}", e));
+        }
+    };
+
+    let join_dashboard_response = JoinedDashboard {
+        id: user.id,
+        email: user.email.clone(),
+        name: user.name.clone(),
+        dashboard_id: dashboard.id,
+    };
+
+    let join_dashboard_ws_message = WsResponseMessage::new(
+        WsRoutes::Dashboards(DashboardRoute::Duplicate),
+        WsEvent::Dashboards(DashboardEvent::JoinedDashboard),
+        vec![join_dashboard_response],
+        None,
+        user,
+        WsSendMethod::All,
+    );
+
+    match send_ws_message(&dashboard_subscription, &join_dashboard_ws_message).await {
+        Ok(_) => {}
+        Err(e) => {
+            tracing::error!("Error sending message to pubsub: {}", e);
+            send_sentry_error(&e.to_string(), Some(&user.id));
+            return Err(anyhow!("Error sending message to pubsub: {}", e));
+        }
+    }
+
+    let duplicate_dashboard_ws_message = WsResponseMessage::new(
+        WsRoutes::Dashboards(DashboardRoute::Duplicate),
+        WsEvent::Dashboards(DashboardEvent::DuplicateDashboard),
+        new_dashboard_state,
+        None,
+        user,
+        WsSendMethod::All,
+    );
+
+    match send_ws_message(&dashboard_subscription, &duplicate_dashboard_ws_message).await {
+        Ok(_) => {}
+        Err(e) => {
+            tracing::error!("Error sending message to pubsub: {}", e);
+            send_sentry_error(&e.to_string(), Some(&user.id));
+            return Err(anyhow!("Error sending message to pubsub: {}", e));
+        }
+    }
+
+    Ok(())
+}
+
+/// Loads the state of every metric thread to deep copy, with messages reading a remapped dataset
+/// pointed at its target. Returns nothing when the copy should share the original threads.
+async fn prepare_thread_copies(
+    user: &User,
+    dashboard_state: &DashboardState,
+    req: &DuplicateDashboardRequest,
+) -> Result<Option<Vec<ThreadState>>> {
+    let dataset_remap = req.dataset_remap.clone().unwrap_or_default();
+
+    if !req.deep_copy.unwrap_or(false) {
+        if !dataset_remap.is_empty() {
+            return Err(anyhow!("Datasets can only be remapped on a deep copy"));
+        }
+
+        return Ok(None);
+    }
+
+    let remaps = match dataset_remap.is_empty() {
+        true => HashMap::new(),
+        false => {
+            let organization_id = get_user_organization_id(&user.id).await?;
+            load_dataset_remaps(&user.id, &organization_id, &dataset_remap).await?
+        }
+    };
+
+    let mut thread_states = Vec::with_capacity(dashboard_state.metrics.len());
+
+    for metric in &dashboard_state.metrics {
+        let mut thread_state = match get_thread_state_by_id(&user.id, &metric.id, &None).await {
+            Ok(thread_state) => thread_state,
+            Err(e) => return Err(anyhow!("Unable to copy metric '{}': {}", metric.name, e)),
+        };
+
+        for message in &mut thread_state.messages {
+            let remap = match message.message.dataset_id.and_then(|id| remaps.get(&id)) {
+                Some(remap) => remap,
+                None => continue,
+            };
+
+            if let Some(sql) = &message.message.code {
+                message.message.code = match remap_dataset_sql(sql, remap) {
+                    Ok(sql) => Some(sql),
+                    Err(e) => {
+                        return Err(anyhow!("Unable to remap metric '{}': {}", metric.name, e))
+                    }
+                };
+            }
+
+            message.message.dataset_id = Some(remap.dataset_id);
+        }
+
+        if let Some(remap) = thread_state.dataset_id.and_then(|id| remaps.get(&id)) {
+            thread_state.dataset_id = Some(remap.dataset_id);
+        }
+
+        thread_states.push(thread_state);
+    }
+
+    Ok(Some(thread_states))
+}
+
+async fn duplicate_dashboard_handler(
+    user: &User,
+    dashboard_state: &DashboardState,
+    copied_threads: Option<Vec<ThreadState>>,
+    req: &DuplicateDashboardRequest,
+) -> Result<Dashboard> {
+    let original = &dashboard_state.dashboard;
+
+    let mut thread_ids = HashMap::new();
+    let mut thread_duplicates = Vec::new();
+
+    match copied_threads {
+        Some(thread_states) => {
+            for thread_state in thread_states {
+                let message_id = match thread_state
+                    .thread
+                    .state_message_id
+                    .or_else(|| thread_state.messages.last().map(|m| m.message.id))
+                {
+                    Some(message_id) => message_id,
+                    None => continue,
+                };
+
+                let thread_duplicate = prepare_thread_duplicate(
+                    &user.id,
+                    &thread_state,
+                    &message_id,
+                    &req.share_with_same_people,
+                )?;
+
+                thread_ids.insert(
+                    thread_state.thread.id,
+                    thread_duplicate.thread_state.thread.id,
+                );
+                thread_duplicates.push(thread_duplicate);
+            }
+        }
+        None => {
+            for metric in &dashboard_state.metrics {
+                thread_ids.insert(metric.id, metric.id);
+            }
+        }
+    };
+
+    let dashboard = Dashboard {
+        id: Uuid::new_v4(),
+        name: req
+            .name
+            .clone()
+            .unwrap_or_else(|| format!("{} (Copy)", original.name)),
+        description: original.description.clone(),
+        config: remap_dashboard_config(
+            &original.config,
+            &thread_ids,
+            &req.dataset_remap.clone().unwrap_or_default(),
+        ),
+        created_by: user.id,
+        updated_by: user.id,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        deleted_at: None,
+        publicly_accessible: false,
+        publicly_enabled_by: None,
+        public_expiry_date: None,
+        password_secret_id: None,
+        organization_id: original.organization_id,
+        env: original.env.clone(),
+        trashed_at: None,
+    };
+
+    let mut permissions = vec![AssetPermission {
+        identity_id: user.id,
+        identity_type: IdentityType::User,
+        asset_id: dashboard.id,
+        asset_type: AssetType::Dashboard,
+        role: AssetPermissionRole::Owner,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        deleted_at: None,
+        created_by: user.id,
+        updated_by: user.id,
+    }];
+
+    if req.share_with_same_people.unwrap_or(false) {
+        let shared_with = dashboard_state
+            .individual_permissions
+            .iter()
+            .flatten()
+            .filter(|p| p.id != user.id)
+            .map(|p| (p.id, IdentityType::User, p.role))
+            .chain(
+                dashboard_state
+                    .team_permissions
+                    .iter()
+                    .flatten()
+                    .map(|p| (p.id, IdentityType::Team, p.role)),
+            );
+
+        for (identity_id, identity_type, role) in shared_with {
+            permissions.push(AssetPermission {
+                identity_id,
+                identity_type,
+                asset_id: dashboard.id,
+                asset_type: AssetType::Dashboard,
+                role,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                deleted_at: None,
+                created_by: user.id,
+                updated_by: user.id,
+            });
+        }
+    }
+
+    let thread_records: Vec<ThreadToDashboard> = thread_ids
+        .values()
+        .map(|thread_id| ThreadToDashboard {
+            thread_id: *thread_id,
+            dashboard_id: dashboard.id,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            deleted_at: None,
+            added_by: user.id,
+        })
+        .collect();
+
+    let collection_records: Vec<CollectionToAsset> =
+        get_editable_collection_ids(&user.id, dashboard_state)
+            .await?
+            .into_iter()
+            .map(|collection_id| CollectionToAsset {
+                collection_id,
+                asset_id: dashboard.id,
+                asset_type: AssetType::Dashboard,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                deleted_at: None,
+                created_by: user.id,
+                updated_by: user.id,
+            })
+            .collect();
+
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    // The copied threads, the dashboard and its links are written together, so a failure part-way
+    // leaves no orphaned copies behind.
+    conn.transaction::<_, anyhow::Error, _>(|conn| {
+        async {
+            for thread_duplicate in &thread_duplicates {
+                insert_thread_duplicate(conn, thread_duplicate).await?;
+            }
+
+            match insert_into(dashboards::table)
+                .values(&dashboard)
+                .execute(conn)
+                .await
+            {
+                Ok(_) => (),
+                Err(e) => return Err(anyhow!("Error inserting dashboard: {}", e)),
+            }
+
+            match insert_into(asset_permissions::table)
+                .values(&permissions)
+                .execute(conn)
+                .await
+            {
+                Ok(_) => (),
+                Err(e) => return Err(anyhow!("Error inserting dashboard permissions: {}", e)),
+            }
+
+            if !thread_records.is_empty() {
+                match insert_into(threads_to_dashboards::table)
+                    .values(&thread_records)
+                    .execute(conn)
+                    .await
+                {
+                    Ok(_) => (),
+                    Err(e) => return Err(anyhow!("Error adding threads to dashboard: {}", e)),
+                }
+            }
+
+            if !collection_records.is_empty() {
+                match insert_into(collections_to_assets::table)
+                    .values(&collection_records)
+                    .on_conflict((
+                        collections_to_assets::collection_id,
+                        collections_to_assets::asset_id,
+                        collections_to_assets::asset_type,
+                    ))
+                    .do_update()
+                    .set((
+                        collections_to_assets::updated_at.eq(Utc::now()),
+                        collections_to_assets::updated_by.eq(user.id),
+                        collections_to_assets::deleted_at.eq(None::<DateTime<Utc>>),
+                    ))
+                    .execute(conn)
+                    .await
+                {
+                    Ok(_) => (),
+                    Err(e) => return Err(anyhow!("Error adding dashboard to collections: {}", e)),
+                }
+            }
+
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await?;
+
+    let query = diesel::sql_query(
+        "INSERT INTO asset_search (asset_id, asset_type, content, organization_id)
+        VALUES ($1, 'dashboard', $2, $3)
+        ON CONFLICT (asset_id, asset_type)
+        DO UPDATE SET
+            content = EXCLUDED.content,
+            updated_at = NOW()",
+    )
+    .bind::<diesel::sql_types::Uuid, _>(dashboard.id)
+    .bind::<diesel::sql_types::Text, _>(dashboard.name.clone())
+    .bind::<diesel::sql_types::Uuid, _>(dashboard.organization_id);
+
+    if let Err(e) = query.execute(&mut conn).await {
+        tracing::error!("Failed to update asset search: {:?}", e);
+        send_sentry_error(&e.to_string(), None);
+    }
+
+    drop(conn);
+    Ok(dashboard)
+}
+
+/// The original dashboard's collections the user can add to. Viewers of a collection keep the copy
+/// out of it, like they can't add assets to it themselves.
+async fn get_editable_collection_ids(
+    user_id: &Uuid,
+    dashboard_state: &DashboardState,
+) -> Result<Vec<Uuid>> {
+    if dashboard_state.collections.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let collection_ids: Vec<Uuid> = dashboard_state.collections.iter().map(|c| c.id).collect();
+    let roles = get_bulk_user_collection_permission(user_id, &collection_ids).await?;
+
+    Ok(collection_ids
+        .into_iter()
+        .filter(|collection_id| {
+            matches!(
+                roles.get(collection_id),
+                Some(AssetPermissionRole::Owner) | Some(AssetPermissionRole::Editor)
+            )
+        })
+        .collect())
+}
@@ -1,17 +1,19 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use anyhow::{anyhow, Result};
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use uuid::Uuid;
 
 use crate::{
     database::{
-        enums::AssetPermissionRole,
+        enums::{AssetPermissionRole, AssetType},
         lib::StepProgress,
         models::User,
     },
     routes::ws::{
+        collections::collection_utils::check_public_collection_access,
         ws::{SubscriptionRwLock, WsErrorCode, WsEvent, WsResponseMessage, WsSendMethod},
         ws_router::WsRoutes,
         ws_utils::{send_error_message, send_ws_message, subscribe_to_stream},
@@ -24,12 +26,15 @@ use crate::{
             },
             sentry_utils::send_sentry_error,
         },
+        embed::tokens::EmbedClaims,
         query_engine::{data_types::DataType, query_engine::query_engine},
     },
 };
 
 use super::{
-    dashboard_utils::{get_dashboard_state_by_id, DashboardState, Metric},
+    dashboard_utils::{
+        get_dashboard_state_with_access, get_embedded_dashboard_state, DashboardState, Metric,
+    },
     dashboards_router::{DashboardEvent, DashboardRoute},
 };
 
@@ -37,6 +42,10 @@ use super::{
 pub struct GetDashboardRequest {
     pub id: Uuid,
     pub password: Option<String>,
+    pub filters: Option<HashMap<String, Value>>, // Dashboard filter id to its active value
+    /// Set when the dashboard is opened through a public collection. `password` is then the
+    /// collection's.
+    pub collection_id: Option<Uuid>,
 }
 
 pub async fn get_dashboard(
@@ -53,7 +62,40 @@ pub async fn get_dashboard(
         Err(e) => return Err(anyhow!("Error subscribing to dashboard: {}", e)),
     };
 
-    let mut dashboard_with_metrics = match get_dashboard_state_by_id(&user.id, &req.id).await {
+    let collection_access = match req.collection_id {
+        Some(collection_id) => match check_public_collection_access(
+            &collection_id,
+            &req.id,
+            AssetType::Dashboard,
+            req.password.as_ref(),
+        )
+        .await
+        {
+            Ok(_) => true,
+            Err(e) => {
+                send_error_message(
+                    &user.id.to_string(),
+                    WsRoutes::Dashboards(DashboardRoute::Get),
+                    WsEvent::Dashboards(DashboardEvent::GetDashboardState),
+                    WsErrorCode::Unauthorized,
+                    e.to_string(),
+                    user,
+                )
+                .await?;
+                return Ok(());
+            }
+        },
+        None => false,
+    };
+
+    let mut dashboard_with_metrics = match get_dashboard_state_with_access(
+        &user.id,
+        &req.id,
+        req.filters.as_ref(),
+        collection_access,
+    )
+    .await
+    {
         Ok(dashboard_with_metrics) => dashboard_with_metrics,
         Err(e) => {
             tracing::error!("Error getting dashboard with metrics: {}", e);
@@ -76,6 +118,15 @@ pub async fn get_dashboard(
         Some(_) => {
             // User has explicit permission, proceed
         }
+        None if collection_access => {
+            // Opened through a public collection, so read-only like any public viewer
+            dashboard_with_metrics.permission = Some(AssetPermissionRole::Viewer);
+            dashboard_with_metrics.public_password = None;
+            dashboard_with_metrics.organization_permissions = false;
+            dashboard_with_metrics.individual_permissions = None;
+            dashboard_with_metrics.team_permissions = None;
+            dashboard_with_metrics.collections = vec![];
+        }
         None => {
             // No explicit permission, check if public access is allowed
             if !dashboard_with_metrics.dashboard.publicly_accessible {
@@ -170,6 +221,68 @@ pub async fn get_dashboard(
     Ok(())
 }
 
+/// Serves the dashboard of an embedded session. The session gets its own subscription so it never
+/// receives the updates and sharing details broadcast to the dashboard's regular viewers.
+pub async fn get_embedded_dashboard(
+    subscriptions: &Arc<SubscriptionRwLock>,
+    user_group: &String,
+    user: &User,
+    claims: &EmbedClaims,
+    req: GetDashboardRequest,
+) -> Result<()> {
+    if req.id != claims.sub {
+        send_error_message(
+            &user.id.to_string(),
+            WsRoutes::Dashboards(DashboardRoute::Get),
+            WsEvent::Dashboards(DashboardEvent::GetDashboardState),
+            WsErrorCode::Unauthorized,
+            "You don't have permission to access this dashboard".to_string(),
+            user,
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let dashboard_subscription = format!("dashboard:{}:embed:{}", req.id, user.id);
+
+    match subscribe_to_stream(subscriptions, &dashboard_subscription, user_group, &user.id).await {
+        Ok(_) => (),
+        Err(e) => return Err(anyhow!("Error subscribing to dashboard: {}", e)),
+    };
+
+    let dashboard_with_metrics = match get_embedded_dashboard_state(claims, req.filters.as_ref()).await
+    {
+        Ok(dashboard_with_metrics) => dashboard_with_metrics,
+        Err(e) => {
+            tracing::error!("Error getting embedded dashboard: {}", e);
+            send_error_message(
+                &dashboard_subscription,
+                WsRoutes::Dashboards(DashboardRoute::Get),
+                WsEvent::Dashboards(DashboardEvent::GetDashboardState),
+                WsErrorCode::BadRequest,
+                "Failed to fetch dashboard.".to_string(),
+                user,
+            )
+            .await?;
+            return Err(anyhow!("Error getting embedded dashboard: {}", e));
+        }
+    };
+
+    match send_dashboard_skeleton_message(user, &dashboard_subscription, &dashboard_with_metrics).await {
+        Ok(_) => (),
+        Err(e) => return Err(anyhow!("Error sending dashboard skeleton message: {}", e)),
+    };
+
+    for metric in &dashboard_with_metrics.metrics {
+        match fetch_data_handler(&dashboard_subscription, metric, user).await {
+            Ok(_) => (),
+            Err(e) => return Err(anyhow!("Error fetching data: {}", e)),
+        };
+    }
+
+    Ok(())
+}
+
 async fn send_dashboard_skeleton_message(
     user: &User,
     dashboard_subscription: &String,
@@ -200,6 +313,7 @@ pub struct FetchingData {
     pub progress: StepProgress,
     pub data: Option<Vec<IndexMap<String, DataType>>>,
     pub metric_id: Uuid,
+    pub filter_key: Option<String>,
 }
 
 async fn fetch_data_handler(subscription: &String, metric: &Metric, user: &User) -> Result<()> {
@@ -208,6 +322,23 @@ async fn fetch_data_handler(subscription: &String, metric: &Metric, user: &User)
     let user = user.clone();
 
     tokio::spawn(async move {
+        if let Some(filter_error) = &metric.filter_error {
+            tracing::error!("Unable to apply dashboard filters: {}", filter_error);
+            send_error_message(
+                &subscription,
+                WsRoutes::Dashboards(DashboardRoute::Get),
+                WsEvent::Dashboards(DashboardEvent::FetchingData),
+                WsErrorCode::BadRequest,
+                format!(
+                    "Dashboard filters could not be applied to metric {}.",
+                    metric.id
+                ),
+                &user,
+            )
+            .await?;
+            return Err(anyhow!("Unable to apply dashboard filters: {}", filter_error));
+        }
+
         let data = match query_engine(&metric.dataset_id, &metric.sql).await {
             Ok(data) => data,
             Err(e) => {
@@ -234,6 +365,7 @@ async fn fetch_data_handler(subscription: &String, metric: &Metric, user: &User)
                 Some(data)
             },
             metric_id: metric.id,
+            filter_key: metric.filter_key.clone(),
         };
 
         let fetching_data_ws_response = WsResponseMessage::new(
@@ -1,8 +1,10 @@
+pub mod dashboard_versions;
 pub mod dashboards_router;
 mod delete_dashboard;
+mod duplicate_dashboard;
 mod get_dashboard;
 mod list_dashboards;
 mod post_dashboard;
 mod unsubscribe;
 mod update_dashboard;
-mod dashboard_utils;
+pub mod dashboard_utils;
@@ -21,7 +21,10 @@ use crate::{
         ws_router::WsRoutes,
         ws_utils::{send_error_message, send_ws_message, subscribe_to_stream},
     },
-    utils::{clients::sentry_utils::send_sentry_error, user::user_info::get_user_organization_id},
+    utils::{
+        clients::sentry_utils::send_sentry_error, environments::DEFAULT_ENV,
+        user::user_info::get_user_organization_id,
+    },
 };
 
 use super::dashboards_router::JoinedDashboard;
@@ -30,6 +33,8 @@ use super::dashboards_router::JoinedDashboard;
 pub struct PostDashboardRequest {
     pub name: String,
     pub description: Option<String>,
+    // Only threads from this environment can be added to the dashboard.
+    pub env: Option<String>,
 }
 
 pub async fn post_dashboard(
@@ -133,6 +138,8 @@ async fn create_dashboad_handler(
         public_expiry_date: None,
         password_secret_id: None,
         organization_id,
+        env: req.env.unwrap_or_else(|| DEFAULT_ENV.to_string()),
+        trashed_at: None,
     };
 
     let user_to_dashboard = AssetPermission {
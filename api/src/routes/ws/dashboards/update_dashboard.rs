@@ -23,6 +23,8 @@ use crate::{
     },
     utils::{
         clients::{sentry_utils::send_sentry_error, supabase_vault::create_secret},
+        dashboards::versions::{ensure_dashboard_has_version, record_dashboard_version},
+        environments::check_threads_match_dashboard_env,
         sharing::asset_sharing::{
             create_asset_collection_association, delete_asset_collection_association,
             update_asset_permissions, ShareWithTeamsReqObject, ShareWithUsersReqObject,
@@ -93,6 +95,20 @@ pub async fn update_dashboard(
         }
     };
 
+    // Name, description, config and thread changes are versioned. The state before the first
+    // versioned change is saved too, so it can be restored.
+    let is_versioned_change = req.name.is_some()
+        || req.description.is_some()
+        || req.config.is_some()
+        || req.threads.is_some();
+
+    if is_versioned_change {
+        if let Err(e) = ensure_dashboard_has_version(&dashboard_id).await {
+            tracing::error!("Error saving dashboard version: {}", e);
+            send_sentry_error(&e.to_string(), Some(&user.id));
+        }
+    }
+
     let user_id = Arc::new(user.id.clone());
     let dashboard_id = Arc::new(dashboard_id.clone());
 
@@ -227,7 +243,14 @@ pub async fn update_dashboard(
         }
     }
 
-    let dashboard = match get_dashboard_state_by_id(&user.id, &req.id).await {
+    if is_versioned_change {
+        if let Err(e) = record_dashboard_version(&req.id, Some(user.id), None).await {
+            tracing::error!("Error saving dashboard version: {}", e);
+            send_sentry_error(&e.to_string(), Some(&user.id));
+        }
+    }
+
+    let dashboard = match get_dashboard_state_by_id(&user.id, &req.id, None).await {
         Ok(dashboard) => dashboard,
         Err(e) => {
             tracing::error!("Error getting dashboard: {}", e);
@@ -463,6 +486,8 @@ async fn update_dashboard_threads(
     user_id: Arc<Uuid>,
     threads: Vec<Uuid>,
 ) -> Result<()> {
+    check_threads_match_dashboard_env(&dashboard_id, &threads).await?;
+
     let threads = Arc::new(threads);
 
     let upsert_handle = {
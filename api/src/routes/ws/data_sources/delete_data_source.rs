@@ -19,7 +19,7 @@ use crate::{
         ws_router::WsRoutes,
         ws_utils::{send_error_message, send_ws_message},
     },
-    utils::clients::{sentry_utils::send_sentry_error, supabase_vault::delete_secret},
+    utils::clients::sentry_utils::send_sentry_error,
 };
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -84,12 +84,14 @@ async fn delete_data_source_handler(user_id: &Uuid, id: Uuid) -> Result<()> {
         }
     };
 
-    let secret_id = match data_sources::table
+    // The credentials stay in the vault until the data source is purged from the trash.
+    match data_sources::table
         .inner_join(
             users_to_organizations::table
                 .on(data_sources::organization_id.eq(users_to_organizations::organization_id)),
         )
-        .select(data_sources::secret_id)
+        .select(data_sources::id)
+        .filter(data_sources::id.eq(&id))
         .filter(data_sources::deleted_at.is_null())
         .filter(
             users_to_organizations::role
@@ -100,7 +102,7 @@ async fn delete_data_source_handler(user_id: &Uuid, id: Uuid) -> Result<()> {
         .first::<Uuid>(&mut conn)
         .await
     {
-        Ok(secret_id) => secret_id,
+        Ok(_) => (),
         Err(diesel::NotFound) => {
             return Err(anyhow!("User does not have appropriate permissions"));
         }
@@ -109,39 +111,18 @@ async fn delete_data_source_handler(user_id: &Uuid, id: Uuid) -> Result<()> {
         }
     };
 
-    let delete_secret_handle = { tokio::spawn(async move { delete_secret(&secret_id).await }) };
-
-    let delete_data_source_handle = {
-        let user_id = user_id.clone();
-        let id = id.clone();
-        tokio::spawn(async move {
-            let mut conn = match get_pg_pool().get().await {
-                Ok(conn) => conn,
-                Err(e) => {
-                    return Err(anyhow!("Error getting postgres connection: {}", e));
-                }
-            };
+    let now = Utc::now();
 
-            match update(data_sources::table)
-                .set((
-                    data_sources::updated_by.eq(&user_id),
-                    data_sources::deleted_at.eq(Utc::now()),
-                ))
-                .filter(data_sources::id.eq(&id))
-                .execute(&mut conn)
-                .await
-            {
-                Ok(_) => (),
-                Err(e) => {
-                    return Err(anyhow!("Error updating data source: {}", e));
-                }
-            };
-
-            Ok(())
-        })
-    };
-
-    match tokio::try_join!(delete_secret_handle, delete_data_source_handle) {
+    match update(data_sources::table)
+        .set((
+            data_sources::updated_by.eq(user_id),
+            data_sources::deleted_at.eq(now),
+            data_sources::trashed_at.eq(now),
+        ))
+        .filter(data_sources::id.eq(&id))
+        .execute(&mut conn)
+        .await
+    {
         Ok(_) => (),
         Err(e) => {
             return Err(anyhow!("Error updating data source: {}", e));
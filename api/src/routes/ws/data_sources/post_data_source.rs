@@ -163,6 +163,7 @@ async fn post_data_source_handler(
         onboarding_status: DataSourceOnboardingStatus::NotStarted,
         onboarding_error: None,
         env: "dev".to_string(),
+        trashed_at: None,
     };
 
     match insert_into(data_sources::table)
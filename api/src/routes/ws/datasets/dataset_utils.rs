@@ -205,6 +205,7 @@ async fn get_dataset_and_columns(
                 data_sources::updated_at,
                 data_sources::deleted_at.nullable(),
                 data_sources::env,
+                data_sources::trashed_at,
             ),
             users::name.nullable(),
             users::email,
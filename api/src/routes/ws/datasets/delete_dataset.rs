@@ -70,9 +70,14 @@ async fn delete_dataset_handler(ids: Vec<Uuid>) -> Result<DeleteDatasetResponse>
         }
     };
 
+    let now = Utc::now();
+
     match update(datasets::table)
         .filter(datasets::id.eq_any(&ids))
-        .set(datasets::deleted_at.eq(Some(Utc::now())))
+        .set((
+            datasets::deleted_at.eq(Some(now)),
+            datasets::trashed_at.eq(Some(now)),
+        ))
         .execute(&mut conn)
         .await
     {
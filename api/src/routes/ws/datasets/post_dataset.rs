@@ -10,7 +10,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     database::{
-        enums::DatasetType,
+        enums::{DatasetType, Verification},
         lib::get_pg_pool,
         models::{Dataset, User},
         schema::{dataset_columns, datasets},
@@ -237,6 +237,8 @@ async fn create_dataset(user_id: &Uuid, name: &String, data_source_id: &Uuid) ->
         yml_file: None,
         model: None,
         database_identifier: None,
+        verification: Verification::NotRequested,
+        trashed_at: None,
     };
 
     let mut conn = match get_pg_pool().get().await {
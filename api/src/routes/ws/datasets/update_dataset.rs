@@ -9,7 +9,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     database::{
-        enums::DatasetType,
+        enums::{DatasetType, Verification},
         lib::get_pg_pool,
         models::User,
         schema::{dataset_columns, datasets},
@@ -52,6 +52,8 @@ pub struct UpdateDatasetReq {
     pub when_not_to_use: Option<String>,
     pub data_source_id: Option<Uuid>,
     pub dataset_definition: Option<UpdateDatasetDefReq>,
+    /// Only verified datasets are promoted to other environments.
+    pub verification: Option<Verification>,
 }
 
 pub async fn update_dataset(user: &User, req: UpdateDatasetReq) -> Result<()> {
@@ -64,6 +66,7 @@ pub async fn update_dataset(user: &User, req: UpdateDatasetReq) -> Result<()> {
         req.when_not_to_use,
         req.dataset_definition,
         req.data_source_id,
+        req.verification,
     )
     .await
     {
@@ -124,6 +127,7 @@ pub struct DatasetChangeset {
     pub type_: Option<DatasetType>,
     pub schema: Option<String>,
     pub data_source_id: Option<Uuid>,
+    pub verification: Option<Verification>,
 }
 
 async fn update_dataset_handler(
@@ -135,6 +139,7 @@ async fn update_dataset_handler(
     when_not_to_use: Option<String>,
     dataset_def: Option<UpdateDatasetDefReq>,
     data_source_id: Option<Uuid>,
+    verification: Option<Verification>,
 ) -> Result<()> {
     let mut dataset_changeset = DatasetChangeset {
         database_name: None,
@@ -146,6 +151,7 @@ async fn update_dataset_handler(
         schema: None,
         name,
         data_source_id,
+        verification,
     };
 
     let dataset_state = match get_dataset_state(id, user_id).await {
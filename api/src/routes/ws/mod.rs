@@ -1,5 +1,5 @@
-mod collections;
-mod dashboards;
+pub mod collections;
+pub mod dashboards;
 mod data_sources;
 mod datasets;
 mod organizations;
@@ -81,6 +81,7 @@ async fn post_organization_handler(user: &User, name: String) -> Result<UserInfo
         updated_at: chrono::Utc::now(),
         deleted_at: None,
         domain,
+        embed_secret_id: None,
     };
 
     let organization_user = UserToOrganization {
@@ -94,9 +94,14 @@ async fn delete_term_handler(term_ids: Vec<Uuid>) -> Result<Vec<Uuid>> {
 async fn mark_term_as_deleted(term_ids: Arc<Vec<Uuid>>) -> Result<()> {
     let mut conn = get_pg_pool().get().await?;
 
+    let now = Utc::now();
+
     match diesel::update(terms::table)
         .filter(terms::id.eq_any(term_ids.as_ref()))
-        .set(terms::deleted_at.eq(Some(Utc::now())))
+        .set((
+            terms::deleted_at.eq(Some(now)),
+            terms::trashed_at.eq(Some(now)),
+        ))
         .execute(&mut conn)
         .await
     {
@@ -113,6 +113,7 @@ async fn list_terms_handler(user_id: &Uuid, req: ListTermsRequest) -> Result<Vec
                     terms::created_at,
                     terms::updated_at,
                     terms::deleted_at,
+                    terms::trashed_at,
                 ),
                 users::name.nullable(),
                 users::email,
@@ -93,6 +93,7 @@ async fn post_term_handler(user_id: &Uuid, req: PostTermRequest) -> Result<TermS
         updated_at: Utc::now(),
         deleted_at: None,
         organization_id,
+        trashed_at: None,
     };
 
     let search_term = term.clone();
@@ -118,6 +118,7 @@ async fn get_term_datasets_and_creator(
                 terms::created_at,
                 terms::updated_at,
                 terms::deleted_at,
+                terms::trashed_at,
             ),
             datasets::id,
             datasets::name,
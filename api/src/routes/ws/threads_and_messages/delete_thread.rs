@@ -141,9 +141,11 @@ async fn delete_thread_handler(
     let id = id.clone();
 
     let delete_thread_task = tokio::task::spawn(async move {
+        let now = chrono::Utc::now();
+
         match update(threads::table)
             .filter(threads::id.eq(&id))
-            .set(threads::deleted_at.eq(chrono::Utc::now()))
+            .set((threads::deleted_at.eq(now), threads::trashed_at.eq(now)))
             .execute(&mut conn)
             .await
         {
@@ -1,6 +1,6 @@
 use anyhow::{anyhow, Result};
 use diesel::insert_into;
-use diesel_async::RunQueryDsl;
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
@@ -323,12 +323,42 @@ async fn fetch_data_handler(
     Ok(())
 }
 
-async fn duplicate_thread_handler(
+/// A copy of a thread up to one of its messages, with the permissions to create for it.
+pub struct ThreadDuplicate {
+    pub thread_state: ThreadState,
+    permissions: Vec<AssetPermission>,
+}
+
+pub async fn duplicate_thread_handler(
     user_id: &Uuid,
     thread_state: &ThreadState,
     message_id: &Uuid,
     share_with_same_people: &Option<bool>,
 ) -> Result<ThreadState> {
+    let duplicate =
+        prepare_thread_duplicate(user_id, thread_state, message_id, share_with_same_people)?;
+
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    conn.transaction::<_, anyhow::Error, _>(|conn| {
+        async { insert_thread_duplicate(conn, &duplicate).await }.scope_boxed()
+    })
+    .await?;
+
+    drop(conn);
+    Ok(duplicate.thread_state)
+}
+
+/// Builds the copy of a thread up to `message_id` without writing anything.
+pub fn prepare_thread_duplicate(
+    user_id: &Uuid,
+    thread_state: &ThreadState,
+    message_id: &Uuid,
+    share_with_same_people: &Option<bool>,
+) -> Result<ThreadDuplicate> {
     let mut thread_state = thread_state.clone();
 
     let new_thread_id = Uuid::new_v4();
@@ -336,6 +366,7 @@ async fn duplicate_thread_handler(
 
     thread_state.thread.id = new_thread_id;
     thread_state.thread.parent_thread_id = Some(old_thread_id);
+    thread_state.thread.draft_state = None;
 
     let target_message = thread_state
         .messages
@@ -368,174 +399,95 @@ async fn duplicate_thread_handler(
         thread_state.thread.state_message_id = None;
     }
 
-    let mut conn = match get_pg_pool().get().await {
-        Ok(conn) => conn,
-        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
-    };
+    let mut permissions = vec![AssetPermission {
+        asset_id: new_thread_id,
+        identity_id: *user_id,
+        identity_type: IdentityType::User,
+        asset_type: AssetType::Thread,
+        role: AssetPermissionRole::Owner,
+        created_at: chrono::Utc::now(),
+        updated_at: chrono::Utc::now(),
+        deleted_at: None,
+        created_by: *user_id,
+        updated_by: *user_id,
+    }];
+
+    if share_with_same_people.unwrap_or(false) {
+        let shared_with = thread_state
+            .individual_permissions
+            .iter()
+            .flatten()
+            .filter(|p| p.id != *user_id)
+            .map(|p| (p.id, IdentityType::User, p.role))
+            .chain(
+                thread_state
+                    .team_permissions
+                    .iter()
+                    .flatten()
+                    .map(|p| (p.id, IdentityType::Team, p.role)),
+            );
+
+        for (identity_id, identity_type, role) in shared_with {
+            permissions.push(AssetPermission {
+                asset_id: new_thread_id,
+                identity_id,
+                identity_type,
+                asset_type: AssetType::Thread,
+                role,
+                created_at: chrono::Utc::now(),
+                updated_at: chrono::Utc::now(),
+                deleted_at: None,
+                created_by: *user_id,
+                updated_by: *user_id,
+            });
+        }
+    }
+
+    Ok(ThreadDuplicate {
+        thread_state,
+        permissions,
+    })
+}
 
+/// Inserts a prepared thread copy with its messages and permissions. Callers run it inside a
+/// transaction so a failure leaves no partial copy behind.
+pub async fn insert_thread_duplicate(
+    conn: &mut AsyncPgConnection,
+    duplicate: &ThreadDuplicate,
+) -> Result<()> {
     match insert_into(threads::table)
-        .values(&thread_state.thread)
-        .execute(&mut conn)
+        .values(&duplicate.thread_state.thread)
+        .execute(conn)
         .await
     {
         Ok(_) => (),
         Err(e) => return Err(anyhow!("Error inserting thread: {}", e)),
     }
 
-    let bulk_messages: Vec<Message> = thread_state
+    let bulk_messages: Vec<&Message> = duplicate
+        .thread_state
         .messages
         .iter()
-        .map(|m| m.message.clone())
+        .map(|m| &m.message)
         .collect();
 
-    let insert_messages_handle = {
-        let mut conn = match get_pg_pool().get().await {
-            Ok(conn) => conn,
-            Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
-        };
-        tokio::spawn(async move {
-            match insert_into(messages::table)
-                .values(bulk_messages)
-                .execute(&mut conn)
-                .await
-            {
-                Ok(_) => Ok(()),
-                Err(e) => Err(anyhow!("Error inserting messages: {}", e)),
-            }
-        })
-    };
-
-    let asset_permission = AssetPermission {
-        asset_id: thread_state.thread.id.clone(),
-        identity_id: user_id.clone(),
-        identity_type: IdentityType::User,
-        asset_type: AssetType::Thread,
-        role: AssetPermissionRole::Owner,
-        created_at: chrono::Utc::now(),
-        updated_at: chrono::Utc::now(),
-        deleted_at: None,
-        created_by: user_id.clone(),
-        updated_by: user_id.clone(),
-    };
-
-    let insert_asset_permission_handle = {
-        let mut conn = match get_pg_pool().get().await {
-            Ok(conn) => conn,
-            Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
-        };
-        tokio::spawn(async move {
-            match insert_into(asset_permissions::table)
-                .values(asset_permission)
-                .execute(&mut conn)
-                .await
-            {
-                Ok(_) => Ok(()),
-                Err(e) => Err(anyhow!("Error inserting asset permission: {}", e)),
-            }
-        })
-    };
-
-    let insert_previous_permissions_handle =
-        if let Some(share_with_same_people) = share_with_same_people {
-            if *share_with_same_people {
-                let user_id = user_id.clone();
-                let thread_state = thread_state.clone();
-                let mut conn = match get_pg_pool().get().await {
-                    Ok(conn) => conn,
-                    Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
-                };
-                tokio::spawn(async move {
-                    let individual_permissions =
-                        if let Some(individual_permissions) = thread_state.individual_permissions {
-                            individual_permissions
-                                .iter()
-                                .map(|p| AssetPermission {
-                                    asset_id: new_thread_id.clone(),
-                                    identity_id: p.id,
-                                    identity_type: IdentityType::User,
-                                    asset_type: AssetType::Thread,
-                                    role: p.role,
-                                    created_at: chrono::Utc::now(),
-                                    updated_at: chrono::Utc::now(),
-                                    deleted_at: None,
-                                    created_by: user_id.clone(),
-                                    updated_by: user_id.clone(),
-                                })
-                                .collect::<Vec<AssetPermission>>()
-                        } else {
-                            vec![]
-                        };
-
-                    let team_permissions =
-                        if let Some(team_permissions) = thread_state.team_permissions {
-                            team_permissions
-                                .iter()
-                                .map(|p| AssetPermission {
-                                    asset_id: new_thread_id.clone(),
-                                    identity_id: p.id,
-                                    identity_type: IdentityType::Team,
-                                    asset_type: AssetType::Thread,
-                                    role: p.role,
-                                    created_at: chrono::Utc::now(),
-                                    updated_at: chrono::Utc::now(),
-                                    deleted_at: None,
-                                    created_by: user_id.clone(),
-                                    updated_by: user_id.clone(),
-                                })
-                                .collect::<Vec<AssetPermission>>()
-                        } else {
-                            vec![]
-                        };
-
-                    let mut combined_permissions = vec![];
-                    combined_permissions.extend(individual_permissions);
-                    combined_permissions.extend(team_permissions);
-
-                    match insert_into(asset_permissions::table)
-                        .values(combined_permissions)
-                        .execute(&mut conn)
-                        .await
-                    {
-                        Ok(_) => (),
-                        Err(e) => return Err(anyhow!("Error inserting asset permissions: {}", e)),
-                    };
-
-                    Ok(())
-                })
-            } else {
-                tokio::spawn(async move { Ok(()) })
-            }
-        } else {
-            tokio::spawn(async move { Ok(()) })
-        };
-
-    match insert_messages_handle.await {
-        Ok(Ok(_)) => (),
-        Ok(Err(e)) => return Err(anyhow!("Error inserting messages: {}", e)),
-        Err(e) => return Err(anyhow!("Error spawning insert messages task: {}", e)),
-    };
-
-    match insert_previous_permissions_handle.await {
-        Ok(Ok(_)) => (),
-        Ok(Err(e)) => return Err(anyhow!("Error inserting previous permissions: {}", e)),
-        Err(e) => {
-            return Err(anyhow!(
-                "Error spawning insert previous permissions task: {}",
-                e
-            ))
+    if !bulk_messages.is_empty() {
+        match insert_into(messages::table)
+            .values(bulk_messages)
+            .execute(conn)
+            .await
+        {
+            Ok(_) => (),
+            Err(e) => return Err(anyhow!("Error inserting messages: {}", e)),
         }
-    };
-
-    match insert_asset_permission_handle.await {
-        Ok(Ok(_)) => (),
-        Ok(Err(e)) => return Err(anyhow!("Error inserting asset permission: {}", e)),
-        Err(e) => {
-            return Err(anyhow!(
-                "Error spawning insert asset permission task: {}",
-                e
-            ))
-        }
-    };
+    }
 
-    Ok(thread_state)
+    match insert_into(asset_permissions::table)
+        .values(&duplicate.permissions)
+        .execute(conn)
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!("Error inserting asset permissions: {}", e)),
+    }
 }
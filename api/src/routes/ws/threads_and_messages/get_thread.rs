@@ -6,11 +6,12 @@ use uuid::Uuid;
 
 use crate::{
     database::{
-        enums::AssetPermissionRole,
+        enums::{AssetPermissionRole, AssetType},
         lib::{FetchingData, StepProgress},
         models::User,
     },
     routes::ws::{
+        collections::collection_utils::check_public_collection_access,
         ws::{SubscriptionRwLock, WsErrorCode, WsEvent, WsResponseMessage, WsSendMethod},
         ws_router::WsRoutes,
         ws_utils::{get_key_value, send_error_message, send_ws_message, subscribe_to_stream},
@@ -28,7 +29,7 @@ use crate::{
 };
 
 use super::{
-    thread_utils::get_thread_state_by_id,
+    thread_utils::get_thread_state_with_access,
     threads_router::{ThreadEvent, ThreadRoute},
 };
 
@@ -36,6 +37,9 @@ use super::{
 pub struct GetThreadRequest {
     pub id: Uuid,
     pub password: Option<String>,
+    /// Set when the thread is opened through a public collection. `password` is then the
+    /// collection's.
+    pub collection_id: Option<Uuid>,
 }
 
 #[derive(Serialize, Debug)]
@@ -67,7 +71,39 @@ pub async fn get_thread(
         Err(e) => return Err(anyhow!("Error subscribing to thread: {}", e)),
     };
 
-    let mut thread_state = match get_thread_state_by_id(&user.id, &req.id, &draft_session_id).await
+    let collection_access = match req.collection_id {
+        Some(collection_id) => match check_public_collection_access(
+            &collection_id,
+            &req.id,
+            AssetType::Thread,
+            req.password.as_ref(),
+        )
+        .await
+        {
+            Ok(_) => true,
+            Err(e) => {
+                send_error_message(
+                    &user.id.to_string(),
+                    WsRoutes::Threads(ThreadRoute::Get),
+                    WsEvent::Threads(ThreadEvent::GetThreadState),
+                    WsErrorCode::Unauthorized,
+                    e.to_string(),
+                    user,
+                )
+                .await?;
+                return Ok(());
+            }
+        },
+        None => false,
+    };
+
+    let mut thread_state = match get_thread_state_with_access(
+        &user.id,
+        &req.id,
+        &draft_session_id,
+        collection_access,
+    )
+    .await
     {
         Ok(res) => res,
         Err(e) => {
@@ -78,8 +114,10 @@ pub async fn get_thread(
     };
 
     if thread_state.permission.is_none() {
+        // Threads opened through a public collection were unlocked with the collection's password.
         if let Some(password) = &thread_state.public_password {
-            if thread_state.thread.publicly_accessible
+            if !collection_access
+                && thread_state.thread.publicly_accessible
                 && (thread_state.thread.public_expiry_date.is_none()
                     || thread_state.thread.public_expiry_date > Some(chrono::Utc::now()))
             {
@@ -1,11 +1,11 @@
 mod delete_thread;
-mod duplicate_thread;
+pub mod duplicate_thread;
 mod get_message_data;
 mod get_thread;
 mod list_threads;
 mod messages_utils;
 mod post_thread;
-mod thread_utils;
+pub mod thread_utils;
 pub mod threads_router;
 mod unsubscribe;
 mod update_message;
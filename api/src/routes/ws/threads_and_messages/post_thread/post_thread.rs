@@ -21,15 +21,24 @@ use crate::{
         ws_utils::{get_key_value, set_key_value, subscribe_to_stream},
     },
     utils::{
-        agents::data_analyst_agent::{
-            data_analyst_agent, DataAnalystAgentOptions, DatasetWithMetadata, RelevantTerm,
-            Thoughts,
+        agents::{
+            conversation_memory_agent::{
+                conversation_memory_agent, ConversationMemoryAgentOptions,
+            },
+            data_analyst_agent::{
+                data_analyst_agent, DataAnalystAgentOptions, DatasetWithMetadata, RelevantTerm,
+                Thoughts,
+            },
+            follow_up_suggestions_agent::{
+                follow_up_suggestions_agent, FollowUpSuggestionsAgentOptions,
+            },
         },
         clients::{
             ai::embedding_router::embedding_router,
             sentry_utils::send_sentry_error,
             typesense::{self, CollectionName, SearchRequestObject},
         },
+        environments::DEFAULT_ENV,
         user::user_info::get_user_organization_id,
     },
 };
@@ -83,6 +92,8 @@ pub struct PostThreadRequest {
     pub thread_id: Option<Uuid>,
     // message_id is only applicable after the first message.  It indicates that a message is going to be redone.
     pub message_id: Option<Uuid>,
+    // env is only applicable on the first message. Follow ups stay in the environment the thread was created in.
+    pub env: Option<String>,
 }
 
 pub struct PostThreadMessage {
@@ -116,6 +127,7 @@ pub async fn post_thread(
         &req.prompt,
         &req.message_id,
         &organization_id,
+        &req.env,
     )
     .await
     {
@@ -185,12 +197,13 @@ pub async fn post_thread(
         })
     };
 
-    let datasets_with_metadata = match get_user_datasets_with_metadata(&user.id).await {
-        Ok(datasets_with_metadata) => datasets_with_metadata,
-        Err(e) => {
-            return Err(anyhow!("Error fetching user datasets: {}", e));
-        }
-    };
+    let datasets_with_metadata =
+        match get_user_datasets_with_metadata(&user.id, &thread.thread.env).await {
+            Ok(datasets_with_metadata) => datasets_with_metadata,
+            Err(e) => {
+                return Err(anyhow!("Error fetching user datasets: {}", e));
+            }
+        };
 
     let reranked_datasets_with_metadata =
         match rerank_datasets(&req.prompt, datasets_with_metadata).await {
@@ -228,9 +241,39 @@ pub async fn post_thread(
         }
     };
 
+    let conversation_memory_options = ConversationMemoryAgentOptions {
+        message_history: assemble_message_history(&thread),
+        datasets: reranked_datasets_with_metadata
+            .iter()
+            .map(|dataset| dataset.dataset_ddl.clone())
+            .collect::<Vec<String>>()
+            .join("\n\n"),
+        terms: terms
+            .iter()
+            .map(|term| format!("{}: {}", term.name, term.definition))
+            .collect::<Vec<String>>()
+            .join("\n\n"),
+        // The orchestrator and sql agents run on the default prompt node model.
+        model: String::new(),
+        thread_id: thread.thread.id,
+        user_id: user.id,
+    };
+
+    let message_history = match conversation_memory_agent(conversation_memory_options).await {
+        Ok(message_history) => message_history,
+        Err(e) => {
+            tracing::error!("Unable to fit message history into context: {:?}", e);
+            assemble_message_history(&thread)
+        }
+    };
+
+    // The follow-up suggestions are checked against the same datasets the analyst could use.
+    let suggestion_datasets = reranked_datasets_with_metadata.clone();
+    let suggestion_terms = terms.clone();
+
     let data_analyst_options = DataAnalystAgentOptions {
         input: req.prompt.clone(),
-        message_history: assemble_message_history(&thread),
+        message_history,
         output_sender: thread_tx.clone(),
         datasets: reranked_datasets_with_metadata,
         terms,
@@ -238,6 +281,7 @@ pub async fn post_thread(
         thread_id: thread.thread.id,
         message_id: message.id,
         user_id: user.id,
+        organization_id,
     };
 
     let result = match data_analyst_agent(data_analyst_options).await {
@@ -325,6 +369,37 @@ pub async fn post_thread(
 
     let _ = send_completed_state_to_sub(&subscription, &thread_state, user).await;
 
+    // Suggestions are generated after the thread is completed so they don't hold up the answer.
+    if let Some(sql) = message.code.clone() {
+        let follow_up_suggestions_options = FollowUpSuggestionsAgentOptions {
+            input: req.prompt.clone(),
+            sql,
+            data_metadata: message.data_metadata.clone().unwrap_or(Value::Null),
+            dataset_id: message.dataset_id,
+            datasets: suggestion_datasets,
+            terms: suggestion_terms,
+            thread_id: thread.thread.id,
+            user_id: user.id,
+        };
+
+        let subscription = subscription.clone();
+        let user = user.clone();
+        let message_id = message.id;
+
+        tokio::spawn(async move {
+            if let Err(e) = send_follow_up_suggestions(
+                &subscription,
+                follow_up_suggestions_options,
+                &user,
+                &message_id,
+            )
+            .await
+            {
+                tracing::error!("Unable to generate follow-up suggestions: {:?}", e);
+            }
+        });
+    }
+
     Ok(())
 }
 
@@ -334,6 +409,7 @@ fn assemble_message_history(thread: &ThreadState) -> Vec<Value> {
     for message in thread.messages.clone() {
         if let Some(mut context) = message.message.context {
             context["chart_config"] = message.message.chart_config.clone().unwrap_or(Value::Null);
+            context["message_id"] = Value::String(message.message.id.to_string());
             message_history.push(context.clone());
         }
     }
@@ -606,7 +682,11 @@ fn get_current_chart_config(chart_config: Value) -> Option<Value> {
     }
 }
 
-pub async fn get_user_datasets_with_metadata(user_id: &Uuid) -> Result<Vec<DatasetWithMetadata>> {
+/// The datasets the user can query in an environment.
+pub async fn get_user_datasets_with_metadata(
+    user_id: &Uuid,
+    env: &String,
+) -> Result<Vec<DatasetWithMetadata>> {
     let mut conn = match get_pg_pool().get().await {
         Ok(conn) => conn,
         Err(e) => return Err(anyhow!("Unable to get connection from pool: {}", e)),
@@ -628,10 +708,10 @@ pub async fn get_user_datasets_with_metadata(user_id: &Uuid) -> Result<Vec<Datas
         UserOrganizationRole::WorkspaceAdmin
         | UserOrganizationRole::DataAdmin
         | UserOrganizationRole::Querier => {
-            get_org_datasets_with_metadata(&user_organization_record.organization_id).await?
+            get_org_datasets_with_metadata(&user_organization_record.organization_id, env).await?
         }
         UserOrganizationRole::RestrictedQuerier => {
-            get_restricted_user_datasets_with_metadata(user_id).await?
+            get_restricted_user_datasets_with_metadata(user_id, env).await?
         }
         UserOrganizationRole::Viewer => Vec::new(),
     };
@@ -641,6 +721,7 @@ pub async fn get_user_datasets_with_metadata(user_id: &Uuid) -> Result<Vec<Datas
 
 async fn get_org_datasets_with_metadata(
     organization_id: &Uuid,
+    env: &String,
 ) -> Result<Vec<DatasetWithMetadata>> {
     let mut conn = match get_pg_pool().get().await {
         Ok(conn) => conn,
@@ -652,6 +733,7 @@ async fn get_org_datasets_with_metadata(
         .filter(datasets::organization_id.eq(organization_id))
         .filter(datasets::deleted_at.is_null())
         .filter(datasets::enabled.eq(true))
+        .filter(data_sources::env.eq(env))
         .select((Dataset::as_select(), DataSource::as_select()))
         .load::<(Dataset, DataSource)>(&mut conn)
         .await
@@ -665,6 +747,7 @@ async fn get_org_datasets_with_metadata(
 
 async fn get_restricted_user_datasets_with_metadata(
     user_id: &Uuid,
+    env: &String,
 ) -> Result<Vec<DatasetWithMetadata>> {
     // Direct dataset access
     let direct_user_permissioned_datasets_handle = {
@@ -869,6 +952,8 @@ async fn get_restricted_user_datasets_with_metadata(
     all_datasets.sort_by_key(|k| k.0.id);
     all_datasets.dedup_by_key(|k| k.0.id);
 
+    all_datasets.retain(|(_, data_source)| &data_source.env == env);
+
     process_dataset_records(all_datasets).await
 }
 
@@ -974,6 +1059,7 @@ async fn initialize_thread(
     prompt: &String,
     message_id: &Option<Uuid>,
     organization_id: &Uuid,
+    env: &Option<String>,
 ) -> Result<(ThreadState, Message)> {
     let (thread, message) = if let Some(thread_id) = thread_id {
         match follow_up_thread(user, &thread_id, prompt, message_id).await {
@@ -984,7 +1070,7 @@ async fn initialize_thread(
             }
         }
     } else {
-        match create_thread(&user, prompt, &organization_id).await {
+        match create_thread(&user, prompt, &organization_id, env).await {
             Ok(thread) => thread,
             Err(e) => {
                 tracing::error!("Failed to create a new thread: {:?}", e);
@@ -1077,6 +1163,7 @@ async fn follow_up_thread(
                 draft_state: None,
                 summary_question: None,
                 sql_evaluation_id: None,
+                follow_up_suggestions: None,
             };
 
             new_message
@@ -1105,6 +1192,7 @@ async fn follow_up_thread(
                 draft_state: None,
                 summary_question: None,
                 sql_evaluation_id: None,
+                follow_up_suggestions: None,
             };
 
             new_message
@@ -1309,6 +1397,7 @@ async fn create_thread(
     user: &User,
     prompt: &String,
     organization_id: &Uuid,
+    env: &Option<String>,
 ) -> Result<(ThreadState, Message)> {
     let message_uuid = Uuid::new_v4();
 
@@ -1326,6 +1415,9 @@ async fn create_thread(
         public_expiry_date: None,
         password_secret_id: None,
         organization_id: organization_id.clone(),
+        env: env.clone().unwrap_or_else(|| DEFAULT_ENV.to_string()),
+        draft_state: None,
+        trashed_at: None,
     };
 
     let message_context = ContextJsonBody { steps: vec![] };
@@ -1354,6 +1446,7 @@ async fn create_thread(
         draft_state: None,
         summary_question: None,
         sql_evaluation_id: None,
+        follow_up_suggestions: None,
     };
 
     let thread_insert_body = thread.clone();
@@ -1509,6 +1602,65 @@ async fn send_completed_state_to_sub(
     Ok(())
 }
 
+async fn send_follow_up_suggestions(
+    subscription: &String,
+    options: FollowUpSuggestionsAgentOptions,
+    user: &User,
+    message_id: &Uuid,
+) -> Result<()> {
+    let thread_id = options.thread_id;
+
+    let follow_up_suggestions = match follow_up_suggestions_agent(options).await {
+        Ok(follow_up_suggestions) => follow_up_suggestions,
+        Err(e) => {
+            return Err(anyhow!(
+                "Error in follow-up suggestions agent: {}",
+                e.error_message
+            ))
+        }
+    };
+
+    if follow_up_suggestions.is_empty() {
+        return Ok(());
+    }
+
+    let follow_up_suggestions = json!(follow_up_suggestions);
+
+    let thread_ws_response = WsResponseMessage::new(
+        WsRoutes::Threads(ThreadRoute::Post),
+        WsEvent::Threads(ThreadEvent::FollowUpSuggestions),
+        Some(json!({
+            "thread_id": thread_id,
+            "message_id": message_id,
+            "follow_up_suggestions": follow_up_suggestions,
+        })),
+        None,
+        user,
+        WsSendMethod::All,
+    );
+
+    if let Err(e) = send_ws_message(subscription, &thread_ws_response).await {
+        tracing::error!("Unable to send follow-up suggestions: {:?}", e);
+    }
+
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Unable to get connection from pool: {}", e)),
+    };
+
+    match update(messages::table)
+        .filter(messages::id.eq(message_id))
+        .set(messages::follow_up_suggestions.eq(Some(follow_up_suggestions)))
+        .execute(&mut conn)
+        .await
+    {
+        Ok(_) => (),
+        Err(e) => return Err(anyhow!("Unable to save follow-up suggestions: {}", e)),
+    }
+
+    Ok(())
+}
+
 async fn send_initial_thread_to_sub(
     subscription: &String,
     thread: &ThreadState,
@@ -1598,7 +1750,10 @@ async fn update_thread_and_message(
         thread_message.error = error;
     }
 
-    let update_thread = thread.thread.clone();
+    let mut update_thread = thread.thread.clone();
+    // The conversation memory agent writes the draft state on its own during the turn, so the
+    // copy loaded before it ran is left out of the changeset.
+    update_thread.draft_state = None;
 
     let thread_handle = tokio::spawn(async move {
         let mut conn = match get_pg_pool().get().await {
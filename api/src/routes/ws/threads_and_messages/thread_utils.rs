@@ -81,6 +81,17 @@ pub async fn get_thread_state_by_id(
     user_id: &Uuid,
     thread_id: &Uuid,
     draft_session_id: &Option<Uuid>,
+) -> Result<ThreadState> {
+    get_thread_state_with_access(user_id, thread_id, draft_session_id, false).await
+}
+
+/// Like `get_thread_state_by_id`, but with `collection_access` a user the thread isn't shared with
+/// can load it, having opened it through a public collection that contains it.
+pub async fn get_thread_state_with_access(
+    user_id: &Uuid,
+    thread_id: &Uuid,
+    draft_session_id: &Option<Uuid>,
+    collection_access: bool,
 ) -> Result<ThreadState> {
     let thread_id = Arc::new(thread_id.clone());
     let user_id = Arc::new(user_id.clone());
@@ -88,7 +99,9 @@ pub async fn get_thread_state_by_id(
     let thread_and_permission = {
         let thread_id = Arc::clone(&thread_id);
         let user_id = Arc::clone(&user_id);
-        tokio::spawn(async move { get_thread_and_check_permissions(user_id, thread_id).await })
+        tokio::spawn(async move {
+            get_thread_and_check_permissions(user_id, thread_id, collection_access).await
+        })
     };
 
     let thread_sharing_info = {
@@ -587,6 +600,7 @@ pub async fn get_bulk_user_thread_permission(
 async fn get_thread_and_check_permissions(
     user_id: Arc<Uuid>,
     thread_id: Arc<Uuid>,
+    collection_access: bool,
 ) -> Result<(Thread, Option<AssetPermissionRole>)> {
     let thread_handler = {
         let id = Arc::clone(&thread_id);
@@ -615,7 +629,7 @@ async fn get_thread_and_check_permissions(
         Err(e) => return Err(anyhow!("Error getting permission: {}", e)),
     };
 
-    if permission.is_none() && !thread.publicly_accessible {
+    if permission.is_none() && !thread.publicly_accessible && !collection_access {
         return Err(anyhow!("Thread not found"));
     };
 
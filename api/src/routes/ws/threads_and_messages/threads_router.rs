@@ -70,6 +70,7 @@ pub enum ThreadEvent {
     Unsubscribed,
     DuplicateThread,
     SqlEvaluation,
+    FollowUpSuggestions,
 }
 
 pub async fn threads_router(
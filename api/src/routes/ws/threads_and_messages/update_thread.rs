@@ -24,6 +24,7 @@ use crate::{
     },
     utils::{
         clients::{sentry_utils::send_sentry_error, supabase_vault::create_secret},
+        environments::check_threads_match_dashboard_env,
         sharing::asset_sharing::{
             create_asset_collection_association, delete_asset_collection_association,
             update_asset_permissions, ShareWithTeamsReqObject, ShareWithUsersReqObject,
@@ -408,6 +409,8 @@ async fn save_to_dashboard_handler(
     dashboard_id: Uuid,
     thread_id: Arc<Uuid>,
 ) -> Result<()> {
+    check_threads_match_dashboard_env(&dashboard_id, &[*thread_id]).await?;
+
     let mut conn = match get_pg_pool().get().await {
         Ok(conn) => conn,
         Err(e) => {
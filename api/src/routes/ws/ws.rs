@@ -5,7 +5,10 @@ use std::{
     time::{Duration, Instant},
 };
 
-use crate::database::{lib::get_redis_pool, models::User};
+use crate::{
+    database::{lib::get_redis_pool, models::User},
+    utils::embed::tokens::EmbedClaims,
+};
 use async_compression::tokio::bufread::GzipDecoder;
 use axum::{
     extract::{
@@ -43,7 +46,7 @@ use super::{
     terms::terms_router::TermEvent,
     threads_and_messages::threads_router::ThreadEvent,
     users::users_router::UserEvent,
-    ws_router::{ws_router, WsRoutes},
+    ws_router::{embed_ws_router, ws_router, WsRoutes},
     ws_utils::{subscribe_to_stream, unsubscribe_from_stream},
 };
 
@@ -224,13 +227,21 @@ pub async fn ws(
     ws: WebSocketUpgrade,
     Extension(user): Extension<User>,
     Extension(shutdown_tx): Extension<Arc<broadcast::Sender<()>>>,
+    embed: Option<Extension<EmbedClaims>>,
 ) -> impl IntoResponse {
+    let embed = embed.map(|Extension(claims)| claims);
+
     ws.on_upgrade(|ws| async move {
-        ws_handler(ws, user, shutdown_tx).await;
+        ws_handler(ws, user, shutdown_tx, embed).await;
     })
 }
 
-async fn ws_handler(stream: WebSocket, user: User, shutdown_tx: Arc<broadcast::Sender<()>>) {
+async fn ws_handler(
+    stream: WebSocket,
+    user: User,
+    shutdown_tx: Arc<broadcast::Sender<()>>,
+    embed: Option<EmbedClaims>,
+) {
     let mut shutdown_rx = shutdown_tx.subscribe();
 
     let (sender, mut receiver) = stream.split();
@@ -319,9 +330,15 @@ async fn ws_handler(stream: WebSocket, user: User, shutdown_tx: Arc<broadcast::S
                             let subscriptions = subscriptions.clone();
                             let user_group = user_group.clone();
                             let user = user.clone();
+                            let embed = embed.clone();
 
                             tasks.spawn(async move {
-                                if let Err(e) = ws_router(message.route, message.payload, &subscriptions, &user_group, &user).await {
+                                let result = match &embed {
+                                    Some(claims) => embed_ws_router(message.route, message.payload, &subscriptions, &user_group, &user, claims).await,
+                                    None => ws_router(message.route, message.payload, &subscriptions, &user_group, &user).await,
+                                };
+
+                                if let Err(e) = result {
                                     tracing::error!("Error processing websocket message: {:?}", e);
                                 }
                             });
@@ -1,15 +1,17 @@
 use std::sync::Arc;
 
 use anyhow::{anyhow, Result};
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::{
     database::models::User,
     routes::ws::{
-        dashboards::dashboards_router::dashboards_router,
+        dashboards::dashboards_router::{dashboards_router, embedded_dashboards_router},
         datasets::datasets_router::datasets_router,
     },
+    utils::embed::tokens::EmbedClaims,
 };
 
 use super::{
@@ -117,3 +119,45 @@ pub async fn ws_router(
 
     Ok(())
 }
+
+/// Routes messages from an embedded session, which can only reach its token's dashboard.
+pub async fn embed_ws_router(
+    route: String,
+    payload: Value,
+    subscriptions: &Arc<SubscriptionRwLock>,
+    user_group: &String,
+    user: &User,
+    claims: &EmbedClaims,
+) -> Result<()> {
+    if claims.exp <= Utc::now().timestamp() {
+        return Err(anyhow!("Embed token expired"));
+    }
+
+    let parsed_route: WsRoutes = match WsRoutes::from_str(&route) {
+        Ok(parsed_route) => parsed_route,
+        Err(e) => {
+            return Err(anyhow!("Error parsing route: {:?}", e));
+        }
+    };
+
+    let result = match parsed_route {
+        WsRoutes::Dashboards(dashboards_route) => {
+            embedded_dashboards_router(
+                dashboards_route,
+                payload,
+                subscriptions,
+                user_group,
+                user,
+                claims,
+            )
+            .await
+        }
+        _ => Err(anyhow!("Embedded sessions can't use {}", route)),
+    };
+
+    if let Err(e) = result {
+        tracing::error!("Error: {}", e);
+    }
+
+    Ok(())
+}
@@ -2,3 +2,4 @@ pub mod error_node;
 pub mod merge_node;
 pub mod output_node;
 pub mod prompt_node;
+pub mod tool_node;
@@ -5,6 +5,7 @@ use tokio::sync::mpsc::Sender;
 use uuid::Uuid;
 
 use crate::utils::clients::ai::{
+    anthropic::AnthropicChatModel,
     langfuse::PromptName,
     llm_router::{llm_chat, llm_chat_stream, LlmMessage, LlmModel},
     openai::OpenAiChatModel,
@@ -30,6 +31,8 @@ pub struct PromptNodeSettings {
     pub user_id: Uuid,
     pub session_id: Uuid,
     pub prompt_name: String,
+    // Tags from the prompt registry describing which template variant was used.
+    pub prompt_tags: Vec<String>,
 }
 
 impl Default for PromptNodeSettings {
@@ -47,6 +50,7 @@ impl Default for PromptNodeSettings {
             user_id: Uuid::new_v4(),
             session_id: Uuid::new_v4(),
             prompt_name: String::from("Unknown Prompt"),
+            prompt_tags: vec![],
         }
     }
 }
@@ -63,11 +67,26 @@ impl fmt::Display for PromptNodeError {
     }
 }
 
-pub async fn prompt_node(settings: PromptNodeSettings) -> Result<Value, ErrorNode> {
-    let model = match settings.model.as_str() {
+/// Resolves the model name in `PromptNodeSettings` to the model that will be called.
+pub fn prompt_node_model(model: &str) -> LlmModel {
+    match model {
         "gpt-4o" => LlmModel::OpenAi(OpenAiChatModel::Gpt4o),
         "gpt-3.5-turbo" => LlmModel::OpenAi(OpenAiChatModel::Gpt35Turbo),
+        "claude-3-opus-20240229" => LlmModel::Anthropic(AnthropicChatModel::Claude3Opus20240229),
         _ => LlmModel::OpenAi(OpenAiChatModel::O3Mini),
+    }
+}
+
+pub async fn prompt_node(settings: PromptNodeSettings) -> Result<Value, ErrorNode> {
+    let model = prompt_node_model(&settings.model);
+
+    let prompt_name = if settings.prompt_tags.is_empty() {
+        PromptName::CustomPrompt(settings.prompt_name.clone())
+    } else {
+        PromptName::RegisteredPrompt {
+            name: settings.prompt_name.clone(),
+            tags: settings.prompt_tags.clone(),
+        }
     };
 
     let llm_response = if let Some(stream) = settings.stream {
@@ -84,7 +103,7 @@ pub async fn prompt_node(settings: PromptNodeSettings) -> Result<Value, ErrorNod
             settings.stop,
             &settings.session_id,
             &settings.user_id,
-            PromptName::CustomPrompt(settings.prompt_name.clone()),
+            prompt_name,
         )
         .await
         {
@@ -157,7 +176,7 @@ pub async fn prompt_node(settings: PromptNodeSettings) -> Result<Value, ErrorNod
             settings.json_schema.clone(),
             &settings.session_id,
             &settings.user_id,
-            PromptName::CustomPrompt(settings.prompt_name.clone()),
+            prompt_name,
         )
         .await
         {
@@ -0,0 +1,86 @@
+use std::fmt;
+
+use uuid::Uuid;
+
+use crate::utils::clients::ai::{
+    langfuse::PromptName,
+    llm_router::{llm_chat_with_tools, LlmTool, LlmToolMessage, LlmToolResponse},
+};
+
+use super::{error_node::ErrorNode, prompt_node::prompt_node_model};
+
+pub struct ToolNodeSettings {
+    pub messages: Vec<LlmToolMessage>,
+    pub tools: Vec<LlmTool>,
+    pub model: String,
+    pub temperature: f32,
+    pub max_tokens: u32,
+    pub user_id: Uuid,
+    pub session_id: Uuid,
+    pub prompt_name: String,
+    // Tags from the prompt registry describing which template variant was used.
+    pub prompt_tags: Vec<String>,
+}
+
+impl Default for ToolNodeSettings {
+    fn default() -> Self {
+        Self {
+            messages: vec![],
+            tools: vec![],
+            model: String::new(),
+            temperature: 0.0,
+            max_tokens: 2048,
+            user_id: Uuid::new_v4(),
+            session_id: Uuid::new_v4(),
+            prompt_name: String::from("Unknown Prompt"),
+            prompt_tags: vec![],
+        }
+    }
+}
+
+pub enum ToolNodeError {
+    LlmError,
+}
+
+impl fmt::Display for ToolNodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LlmError => write!(f, "llm_error"),
+        }
+    }
+}
+
+/// Runs a single turn of a conversation where the model can call tools. The caller executes
+/// the returned tool calls and passes their results back in the next turn's messages.
+pub async fn tool_node(settings: ToolNodeSettings) -> Result<LlmToolResponse, ErrorNode> {
+    let model = prompt_node_model(&settings.model);
+
+    let prompt_name = if settings.prompt_tags.is_empty() {
+        PromptName::CustomPrompt(settings.prompt_name.clone())
+    } else {
+        PromptName::RegisteredPrompt {
+            name: settings.prompt_name.clone(),
+            tags: settings.prompt_tags.clone(),
+        }
+    };
+
+    match llm_chat_with_tools(
+        model,
+        &settings.messages,
+        &settings.tools,
+        settings.temperature,
+        settings.max_tokens,
+        30,
+        &settings.session_id,
+        &settings.user_id,
+        prompt_name,
+    )
+    .await
+    {
+        Ok(response) => Ok(response),
+        Err(e) => Err(ErrorNode::new(
+            ToolNodeError::LlmError.to_string(),
+            e.to_string(),
+        )),
+    }
+}
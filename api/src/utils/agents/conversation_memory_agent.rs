@@ -0,0 +1,447 @@
+use anyhow::{anyhow, Result};
+use diesel::{update, ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt;
+use tiktoken_rs::o200k_base;
+use uuid::Uuid;
+
+use crate::{
+    database::{lib::get_pg_pool, schema::threads},
+    utils::{
+        agent_builder::nodes::{
+            error_node::ErrorNode,
+            prompt_node::{prompt_node, prompt_node_model, PromptNodeMessage, PromptNodeSettings},
+        },
+        prompts::analyst_chat_prompts::conversation_summary_prompt::{
+            conversation_summary_system_prompt, conversation_summary_user_prompt,
+        },
+    },
+};
+
+// Share of the model's context window that the message history, dataset DDL and terms can use.
+// The rest is left for the system prompts, the current request and the model's output.
+const CONTEXT_BUDGET_RATIO: f64 = 0.6;
+
+// Tokens set aside for the summary itself when deciding how many messages to keep.
+const SUMMARY_TOKEN_RESERVE: usize = 1_000;
+
+// The current message and the one before it are never summarized. The agents read the previous
+// SQL, data metadata and chart config from the second to last entry of the history.
+const MIN_RECENT_MESSAGES: usize = 2;
+
+pub enum ConversationMemoryAgentError {
+    ObjectNotJson,
+    TokenizerError,
+}
+
+impl fmt::Display for ConversationMemoryAgentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ObjectNotJson => write!(f, "object_not_json"),
+            Self::TokenizerError => write!(f, "tokenizer_error"),
+        }
+    }
+}
+
+pub struct ConversationMemoryAgentOptions {
+    pub message_history: Vec<Value>,
+    // This is a string of all the dataset DDLs.
+    pub datasets: String,
+    pub terms: String,
+    // The model the history is replayed into, as passed to `PromptNodeSettings`.
+    pub model: String,
+    pub thread_id: Uuid,
+    pub user_id: Uuid,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedConversationSummary {
+    last_summarized_message_id: Uuid,
+    summary: String,
+}
+
+/// Fits the message history of a thread into the context window of the target model.
+///
+/// If the history, dataset DDL and terms fit in the budget the history is returned as is.
+/// Otherwise the oldest messages are replaced by a single entry holding a summary of them.
+/// The summary is cached in the thread's draft state with the id of the last message it covers,
+/// so later turns only extend it with the messages after that one.
+pub async fn conversation_memory_agent(
+    options: ConversationMemoryAgentOptions,
+) -> Result<Vec<Value>, ErrorNode> {
+    let bpe = match o200k_base() {
+        Ok(bpe) => bpe,
+        Err(e) => {
+            return Err(ErrorNode::new(
+                ConversationMemoryAgentError::TokenizerError.to_string(),
+                e.to_string(),
+            ))
+        }
+    };
+
+    let count_tokens = |text: &str| bpe.encode_with_special_tokens(text).len();
+
+    let budget =
+        (prompt_node_model(&options.model).context_window() as f64 * CONTEXT_BUDGET_RATIO) as usize;
+
+    let fixed_tokens = count_tokens(&options.datasets) + count_tokens(&options.terms);
+
+    let message_tokens = options
+        .message_history
+        .iter()
+        .map(|message| count_tokens(&message.to_string()))
+        .collect::<Vec<usize>>();
+
+    let summarized_message_count = summarized_message_count(&message_tokens, fixed_tokens, budget);
+
+    if summarized_message_count == 0 {
+        return Ok(options.message_history);
+    }
+
+    let (older_messages, recent_messages) =
+        options.message_history.split_at(summarized_message_count);
+
+    let summary = get_or_create_summary(older_messages, &options).await?;
+
+    let mut message_history = vec![json!({
+        "conversation_summary": summary,
+        "summarized_message_count": summarized_message_count,
+    })];
+
+    message_history.extend_from_slice(recent_messages);
+
+    Ok(message_history)
+}
+
+/// The prompt message replaying a summary entry created by the conversation memory agent, if the
+/// history entry is one.
+pub fn conversation_summary_message(message: &Value) -> Option<PromptNodeMessage> {
+    get_conversation_summary(message).map(|summary| PromptNodeMessage {
+        role: "assistant".to_string(),
+        content: format!("## SUMMARY OF THE EARLIER CONVERSATION\n{}", summary),
+    })
+}
+
+fn get_conversation_summary(message: &Value) -> Option<&str> {
+    message.get("conversation_summary").and_then(|v| v.as_str())
+}
+
+/// How many of the oldest messages to replace with a summary. Zero when the history fits in the
+/// budget, otherwise everything but the newest messages that fit next to the summary, always
+/// keeping the last `MIN_RECENT_MESSAGES`.
+fn summarized_message_count(message_tokens: &[usize], fixed_tokens: usize, budget: usize) -> usize {
+    if fixed_tokens + message_tokens.iter().sum::<usize>() <= budget {
+        return 0;
+    }
+
+    let available_tokens = budget
+        .saturating_sub(fixed_tokens)
+        .saturating_sub(SUMMARY_TOKEN_RESERVE);
+
+    let mut kept_messages = 0;
+    let mut kept_tokens = 0;
+
+    for tokens in message_tokens.iter().rev() {
+        if kept_messages >= MIN_RECENT_MESSAGES && kept_tokens + tokens > available_tokens {
+            break;
+        }
+
+        kept_tokens += tokens;
+        kept_messages += 1;
+    }
+
+    message_tokens.len() - kept_messages
+}
+
+fn get_message_id(message: &Value) -> Option<Uuid> {
+    message
+        .get("message_id")
+        .and_then(|v| v.as_str())
+        .and_then(|id| Uuid::parse_str(id).ok())
+}
+
+#[derive(Debug, PartialEq)]
+enum SummaryPlan<'a> {
+    /// The cached summary covers exactly the messages being summarized.
+    Reuse(String),
+    /// The cached summary covers the oldest messages and is extended with the newer ones.
+    Extend {
+        previous_summary: String,
+        new_messages: &'a [Value],
+    },
+    /// There is no usable cached summary, so every message is summarized from scratch.
+    Summarize(&'a [Value]),
+}
+
+/// A cached summary can only be reused or extended if the last message it covers is still one of
+/// the messages being summarized. Redoing or editing a message replaces the messages after it, so
+/// a summary of the old ones is dropped.
+fn summary_plan(
+    older_messages: &[Value],
+    cached_summary: Option<CachedConversationSummary>,
+) -> SummaryPlan<'_> {
+    let cached = match cached_summary {
+        Some(cached) => cached,
+        None => return SummaryPlan::Summarize(older_messages),
+    };
+
+    match older_messages
+        .iter()
+        .position(|message| get_message_id(message) == Some(cached.last_summarized_message_id))
+    {
+        Some(position) if position + 1 == older_messages.len() => {
+            SummaryPlan::Reuse(cached.summary)
+        }
+        Some(position) => SummaryPlan::Extend {
+            previous_summary: cached.summary,
+            new_messages: &older_messages[position + 1..],
+        },
+        None => SummaryPlan::Summarize(older_messages),
+    }
+}
+
+async fn get_or_create_summary(
+    older_messages: &[Value],
+    options: &ConversationMemoryAgentOptions,
+) -> Result<String, ErrorNode> {
+    let cached_summary = match get_cached_summary(&options.thread_id).await {
+        Ok(cached_summary) => cached_summary,
+        Err(e) => {
+            tracing::warn!("Unable to read cached conversation summary: {:?}", e);
+            None
+        }
+    };
+
+    let (previous_summary, new_messages) = match summary_plan(older_messages, cached_summary) {
+        SummaryPlan::Reuse(summary) => return Ok(summary),
+        SummaryPlan::Extend {
+            previous_summary,
+            new_messages,
+        } => (Some(previous_summary), new_messages),
+        SummaryPlan::Summarize(new_messages) => (None, new_messages),
+    };
+
+    let summary_prompt_settings = PromptNodeSettings {
+        messages: vec![
+            PromptNodeMessage {
+                role: "system".to_string(),
+                content: conversation_summary_system_prompt(),
+            },
+            PromptNodeMessage {
+                role: "user".to_string(),
+                content: conversation_summary_user_prompt(
+                    previous_summary.as_deref(),
+                    &render_transcript(new_messages),
+                ),
+            },
+        ],
+        session_id: options.thread_id,
+        user_id: options.user_id,
+        prompt_name: "conversation_summary".to_string(),
+        ..Default::default()
+    };
+
+    let summary = match prompt_node(summary_prompt_settings).await {
+        Ok(Value::String(summary)) => summary,
+        Ok(_) => {
+            return Err(ErrorNode::new(
+                ConversationMemoryAgentError::ObjectNotJson.to_string(),
+                "Conversation summary is not a string".to_string(),
+            ));
+        }
+        Err(e) => return Err(e),
+    };
+
+    match older_messages.last().and_then(get_message_id) {
+        Some(last_summarized_message_id) => {
+            let cached_summary = CachedConversationSummary {
+                last_summarized_message_id,
+                summary: summary.clone(),
+            };
+
+            if let Err(e) = set_cached_summary(&options.thread_id, &cached_summary).await {
+                tracing::warn!("Unable to cache conversation summary: {:?}", e);
+            }
+        }
+        None => tracing::warn!("Unable to cache conversation summary: last message has no id"),
+    }
+
+    Ok(summary)
+}
+
+async fn get_cached_summary(thread_id: &Uuid) -> Result<Option<CachedConversationSummary>> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    let draft_state = match threads::table
+        .filter(threads::id.eq(thread_id))
+        .select(threads::draft_state)
+        .first::<Option<Value>>(&mut conn)
+        .await
+    {
+        Ok(draft_state) => draft_state,
+        Err(e) => return Err(anyhow!("Error getting thread draft state: {}", e)),
+    };
+
+    Ok(draft_state
+        .and_then(|draft_state| draft_state.get("conversation_summary").cloned())
+        .and_then(|cached| serde_json::from_value(cached).ok()))
+}
+
+async fn set_cached_summary(
+    thread_id: &Uuid,
+    cached_summary: &CachedConversationSummary,
+) -> Result<()> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    let draft_state = match threads::table
+        .filter(threads::id.eq(thread_id))
+        .select(threads::draft_state)
+        .first::<Option<Value>>(&mut conn)
+        .await
+    {
+        Ok(draft_state) => draft_state,
+        Err(e) => return Err(anyhow!("Error getting thread draft state: {}", e)),
+    };
+
+    let mut draft_state = match draft_state {
+        Some(Value::Object(draft_state)) => draft_state,
+        _ => serde_json::Map::new(),
+    };
+
+    draft_state.insert(
+        "conversation_summary".to_string(),
+        serde_json::to_value(cached_summary)?,
+    );
+
+    match update(threads::table)
+        .filter(threads::id.eq(thread_id))
+        .set(threads::draft_state.eq(Value::Object(draft_state)))
+        .execute(&mut conn)
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!("Error updating thread draft state: {}", e)),
+    }
+}
+
+fn render_transcript(messages: &[Value]) -> String {
+    let mut transcript = String::new();
+
+    for message in messages {
+        if let Some(summary) = get_conversation_summary(message) {
+            transcript.push_str(&format!("### EARLIER SUMMARY\n{}\n\n", summary));
+            continue;
+        }
+
+        if let Some(input) = message.get("input").and_then(|v| v.as_str()) {
+            transcript.push_str(&format!("### USER\n{}\n", input));
+        }
+
+        if let Some(dataset_name) = message.get("dataset_name").and_then(|v| v.as_str()) {
+            transcript.push_str(&format!("### DATASET\n{}\n", dataset_name));
+        }
+
+        if let Some(sql) = message.get("sql").and_then(|v| v.as_str()) {
+            transcript.push_str(&format!("### SQL\n{}\n", sql));
+        }
+
+        if let Some(response) = message.get("master_response").and_then(|v| v.as_str()) {
+            transcript.push_str(&format!("### ASSISTANT\n{}\n", response));
+        }
+
+        transcript.push('\n');
+    }
+
+    transcript
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: Uuid) -> Value {
+        json!({ "message_id": id.to_string(), "input": "How many orders?" })
+    }
+
+    fn cached(last_summarized_message_id: Uuid) -> Option<CachedConversationSummary> {
+        Some(CachedConversationSummary {
+            last_summarized_message_id,
+            summary: "Earlier orders questions".to_string(),
+        })
+    }
+
+    #[test]
+    fn test_history_that_fits_is_not_summarized() {
+        assert_eq!(summarized_message_count(&[100, 200, 300], 400, 1_000), 0);
+        assert_eq!(summarized_message_count(&[], 400, 1_000), 0);
+    }
+
+    #[test]
+    fn test_newest_messages_that_fit_are_kept() {
+        // 3,000 tokens are left next to the summary: the newest three messages fit.
+        let message_tokens = [2_000, 1_000, 1_000, 1_000, 500];
+
+        assert_eq!(
+            summarized_message_count(&message_tokens, 1_000, 4_000 + SUMMARY_TOKEN_RESERVE),
+            2
+        );
+    }
+
+    #[test]
+    fn test_last_two_messages_are_always_kept() {
+        let message_tokens = [1_000, 1_000, 5_000, 5_000];
+
+        assert_eq!(
+            summarized_message_count(&message_tokens, 1_000, 2_000),
+            message_tokens.len() - MIN_RECENT_MESSAGES
+        );
+    }
+
+    #[test]
+    fn test_cached_summary_covering_older_messages_is_reused() {
+        let ids = [Uuid::new_v4(), Uuid::new_v4()];
+        let older_messages = ids.map(message);
+
+        assert_eq!(
+            summary_plan(&older_messages, cached(ids[1])),
+            SummaryPlan::Reuse("Earlier orders questions".to_string())
+        );
+    }
+
+    #[test]
+    fn test_cached_summary_is_extended_with_newer_messages() {
+        let ids = [Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4()];
+        let older_messages = ids.map(message);
+
+        assert_eq!(
+            summary_plan(&older_messages, cached(ids[0])),
+            SummaryPlan::Extend {
+                previous_summary: "Earlier orders questions".to_string(),
+                new_messages: &older_messages[1..],
+            }
+        );
+    }
+
+    #[test]
+    fn test_cached_summary_is_dropped_after_redo() {
+        // The message the summary ended at was replaced by redoing an earlier one.
+        let older_messages = [message(Uuid::new_v4()), message(Uuid::new_v4())];
+
+        assert_eq!(
+            summary_plan(&older_messages, cached(Uuid::new_v4())),
+            SummaryPlan::Summarize(&older_messages)
+        );
+        assert_eq!(
+            summary_plan(&older_messages, None),
+            SummaryPlan::Summarize(&older_messages)
+        );
+    }
+}
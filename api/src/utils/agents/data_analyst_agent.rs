@@ -1,7 +1,7 @@
 use anyhow::{anyhow, Error};
-use diesel::insert_into;
+use diesel::{insert_into, ExpressionMethods, JoinOnDsl, PgTextExpressionMethods, QueryDsl};
 use diesel_async::RunQueryDsl;
-use std::{fmt, time::Instant};
+use std::{env, fmt, time::Instant};
 
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
@@ -13,7 +13,7 @@ use crate::{
     database::{
         lib::get_pg_pool,
         models::{DataSource, Dataset, DatasetColumn, SqlEvaluation},
-        schema::sql_evaluations,
+        schema::{sql_evaluations, terms, terms_to_datasets},
     },
     routes::ws::{
         threads_and_messages::threads_router::{ThreadEvent, ThreadRoute},
@@ -25,20 +25,28 @@ use crate::{
         agent_builder::nodes::{
             error_node::ErrorNode,
             merge_node::{merge_node, MergeNodeSettings},
-            prompt_node::{prompt_node, PromptNodeMessage, PromptNodeSettings},
+            prompt_node::PromptNodeMessage,
+            tool_node::{tool_node, ToolNodeSettings},
         },
         agents::{
+            conversation_memory_agent::conversation_summary_message,
             failed_to_fix_sql_agent::{failed_to_fix_sql_agent, FailedToFixSqlAgentOptions},
             metadata_prompts_agent::{metadata_prompts_agent, MetadataPromptsAgentOptions},
             multiple_datasets_response_agent::handle_multiple_datasets_agent,
             sql_evaluation_agent::{sql_evaluation_agent, SqlEvaluationAgentOptions},
+            statistical_analysis_agent::{
+                statistical_analysis_agent, StatisticalAnalysisAgentOptions,
+            },
         },
-        clients::typesense::StoredValueDocument,
-        prompts::analyst_chat_prompts::orchestrator_prompt::{
-            orchestrator_prompt_schema, orchestrator_system_prompt,
+        clients::ai::llm_router::{LlmMessage, LlmTool, LlmToolCall, LlmToolMessage},
+        prompts::{
+            analyst_chat_prompts::orchestrator_prompt::{
+                orchestrator_system_prompt, orchestrator_tools,
+            },
+            prompt_registry::resolve_prompt,
         },
+        statistics::analysis::rows_from_json,
         stored_values::search::{search_values_for_dataset, StoredValue},
-        user::user_info::get_user_organization_id,
     },
 };
 
@@ -50,6 +58,9 @@ use super::{
     multiple_datasets_response_agent::MultipleDatasetAgentOptions,
 };
 
+// The orchestrator stops after this many turns even if it keeps calling tools.
+const MAX_ORCHESTRATOR_ITERATIONS: usize = 6;
+
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct RelevantTerm {
     pub id: Uuid,
@@ -87,6 +98,7 @@ pub struct DataAnalystAgentOptions {
     pub thread_id: Uuid,
     pub message_id: Uuid,
     pub user_id: Uuid,
+    pub organization_id: Uuid,
 }
 
 pub enum DataAnalystAgentError {
@@ -125,7 +137,7 @@ pub struct Thought {
     pub error: Option<String>,
 }
 
-pub async fn data_analyst_agent(options: DataAnalystAgentOptions) -> Result<Value, ErrorNode> {
+pub async fn data_analyst_agent(mut options: DataAnalystAgentOptions) -> Result<Value, ErrorNode> {
     let start_time = Instant::now();
 
     let mut thoughts = Thoughts {
@@ -140,21 +152,43 @@ pub async fn data_analyst_agent(options: DataAnalystAgentOptions) -> Result<Valu
     )
     .await?;
 
-    let orchestrator_prompt_settings = PromptNodeSettings {
-        messages: create_orchestrator_messages(options.input.clone(), &options.message_history),
-        json_schema: Some(orchestrator_prompt_schema()),
-        prompt_name: "orchestrator".to_string(),
-        ..Default::default()
-    };
+    // The code prompt takes no inputs, but registered templates can use the request, the datasets
+    // the user can query and the matched terms.
+    let orchestrator_datasets = options
+        .datasets
+        .iter()
+        .map(|d| d.dataset.name.clone())
+        .collect::<Vec<String>>()
+        .join("\n");
 
-    let orchestrator_response = match prompt_node(orchestrator_prompt_settings).await {
-        Ok(Value::Object(obj)) => obj,
-        Ok(_) => {
-            return Err(ErrorNode::new(
-                DataAnalystAgentError::ObjectNotJson.to_string(),
-                "Orchestrator response is not a JSON object".to_string(),
-            ));
-        }
+    let orchestrator_terms = options
+        .terms
+        .iter()
+        .map(|t| format!("{}: {}", t.name, t.definition))
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let orchestrator_prompt = resolve_prompt(
+        "orchestrator",
+        &options.organization_id,
+        &options.user_id,
+        &[
+            ("input", options.input.as_str()),
+            ("datasets", orchestrator_datasets.as_str()),
+            ("terms", orchestrator_terms.as_str()),
+        ],
+        orchestrator_system_prompt(),
+    )
+    .await;
+
+    let orchestrator_response = match run_orchestrator(
+        &mut options,
+        orchestrator_prompt.content.clone(),
+        orchestrator_prompt.trace_tags(),
+    )
+    .await
+    {
+        Ok(response) => response,
         Err(e) => {
             return Err(e);
         }
@@ -282,13 +316,14 @@ pub async fn data_analyst_agent(options: DataAnalystAgentOptions) -> Result<Valu
 
     let mut merge_list: Vec<JoinHandle<Result<Value, ErrorNode>>> = vec![];
 
-    let generate_sql_action = get_generate_sql_action(&actions);
-    let modify_visualization_action = get_modify_visualization_action(&actions);
+    let generate_sql_action = get_action(&actions, "generate_sql");
+    let modify_visualization_action = get_action(&actions, "modify_visualization");
     let chart_requested_but_not_compatible_action =
-        get_chart_requested_but_not_compatible_action(&actions);
-    let explain_something_general_action = get_explain_something_general_action(&actions);
-    let explain_sql_data_action = get_explain_sql_data_action(&actions);
-    let cannot_do_requested_action = get_cannot_do_requested_action(&actions);
+        get_action(&actions, "chart_requested_but_not_compatible");
+    let explain_something_general_action = get_action(&actions, "explain_something_general");
+    let explain_sql_data_action = get_action(&actions, "explain_sql_data");
+    let cannot_do_requested_action = get_action(&actions, "cannot_do_requested_action_response");
+    let analyze_data_action = get_action(&actions, "analyze_data");
 
     if let Some(chart_requested_but_not_compatible_action) =
         chart_requested_but_not_compatible_action
@@ -534,16 +569,6 @@ pub async fn data_analyst_agent(options: DataAnalystAgentOptions) -> Result<Valu
 
     // Get generate_sql action if it exists
     if let Some(generate_sql_action) = &generate_sql_action {
-        let organization_id = match get_user_organization_id(&options.user_id).await {
-            Ok(id) => id,
-            Err(e) => {
-                return Err(ErrorNode::new(
-                    DataAnalystAgentError::GenericError.to_string(),
-                    format!("Error getting organization id: {}", e),
-                ));
-            }
-        };
-
         let generate_sql_options = GenerateSqlAgentOptions {
             sql_gen_action: generate_sql_action.clone(),
             datasets: options.datasets.clone(),
@@ -552,8 +577,9 @@ pub async fn data_analyst_agent(options: DataAnalystAgentOptions) -> Result<Valu
             output_sender: options.output_sender.clone(),
             message_history: options.message_history.clone(),
             start_time,
-            organization_id,
-            relevant_values: vec![], // We'll get these in generate_sql_agent
+            organization_id: options.organization_id,
+            user_id: options.user_id,
+            relevant_values: options.relevant_values.clone(),
         };
 
         let future = tokio::spawn(async move { generate_sql_agent(generate_sql_options).await });
@@ -1489,12 +1515,41 @@ pub async fn data_analyst_agent(options: DataAnalystAgentOptions) -> Result<Valu
         merge_with_previous_message(&mut outputs, &previous_message);
     };
 
+    // Set after the merge so an analysis of a previous message's data is never carried over.
+    outputs["statistical_analysis"] = match &analyze_data_action {
+        Some(analyze_data_action) if !data.is_empty() => {
+            let statistical_analysis_options = StatisticalAnalysisAgentOptions {
+                data_analyst_ticket: analyze_data_action
+                    .get("data_analyst_ticket")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or(&options.input)
+                    .to_string(),
+                sql: get_sql(&sql_gen_results).unwrap_or_default(),
+                data_metadata: Value::Object(data_metadata_obj.clone()),
+                data: rows_from_json(data),
+                thread_id: options.thread_id,
+                user_id: options.user_id,
+            };
+
+            match statistical_analysis_agent(statistical_analysis_options).await {
+                Ok(statistical_analysis) => statistical_analysis,
+                Err(e) => {
+                    tracing::error!("Statistical analysis failed: {:?}", e);
+                    Value::Null
+                }
+            }
+        }
+        _ => Value::Null,
+    };
+
     let master_response_options = MasterResponseAgentOptions {
         outputs: outputs.clone(),
         message_history: options.message_history.clone(),
         datasets: datasets_string.clone(),
         input: options.input.clone(),
         output_sender: options.output_sender.clone(),
+        organization_id: options.organization_id,
+        user_id: options.user_id,
     };
 
     let master_response_handle =
@@ -1570,6 +1625,272 @@ fn merge_with_previous_message(current: &mut Value, previous: &Value) {
     }
 }
 
+/// Runs the orchestrator until it stops looking things up. Lookup tools are executed and their
+/// results passed back to the model. Action tools are collected as the actions to take.
+async fn run_orchestrator(
+    options: &mut DataAnalystAgentOptions,
+    system_prompt: String,
+    prompt_tags: Vec<String>,
+) -> Result<Value, ErrorNode> {
+    let mut messages = create_orchestrator_messages(
+        system_prompt,
+        options.input.clone(),
+        &options.message_history,
+    )
+    .into_iter()
+    .map(|m| LlmToolMessage::Message(LlmMessage::new(m.role, m.content)))
+    .collect::<Vec<LlmToolMessage>>();
+
+    let mut actions: Vec<Value> = Vec::new();
+    let tools = orchestrator_tools();
+    let mut finished = false;
+
+    for _ in 0..MAX_ORCHESTRATOR_ITERATIONS {
+        let orchestrator_settings = ToolNodeSettings {
+            messages: messages.clone(),
+            tools: tools.clone(),
+            model: env::var("ORCHESTRATOR_MODEL").unwrap_or_default(),
+            session_id: options.thread_id,
+            user_id: options.user_id,
+            prompt_name: "orchestrator".to_string(),
+            prompt_tags: prompt_tags.clone(),
+            ..Default::default()
+        };
+
+        let response = match tool_node(orchestrator_settings).await {
+            Ok(response) => response,
+            Err(e) => return Err(e),
+        };
+
+        if response.tool_calls.is_empty() {
+            finished = true;
+            break;
+        }
+
+        // The model only needs another turn to read the results of its lookups.
+        let looked_up = response
+            .tool_calls
+            .iter()
+            .any(|tool_call| is_lookup_tool(&tool_call.name));
+
+        messages.push(LlmToolMessage::ToolCalls {
+            content: response.content.clone(),
+            tool_calls: response.tool_calls.clone(),
+        });
+
+        for tool_call in response.tool_calls {
+            let result = match tool_call.name.as_str() {
+                "search_values" => search_values_tool(options, &tool_call.arguments).await,
+                "look_up_term" => look_up_term_tool(options, &tool_call.arguments).await,
+                _ => record_action(&tools, &mut actions, &tool_call),
+            };
+
+            messages.push(LlmToolMessage::ToolResult {
+                tool_call_id: tool_call.id,
+                name: tool_call.name,
+                content: result,
+            });
+        }
+
+        if !looked_up {
+            finished = true;
+            break;
+        }
+    }
+
+    if !finished {
+        tracing::warn!(
+            "Orchestrator was still looking things up after {} iterations, using the {} actions recorded so far",
+            MAX_ORCHESTRATOR_ITERATIONS,
+            actions.len()
+        );
+    }
+
+    Ok(json!({ "actions": actions }))
+}
+
+fn is_lookup_tool(name: &str) -> bool {
+    matches!(name, "search_values" | "look_up_term")
+}
+
+/// Records a call to an action tool and returns the result passed back to the model.
+fn record_action(tools: &[LlmTool], actions: &mut Vec<Value>, tool_call: &LlmToolCall) -> String {
+    // Anything the model makes up would otherwise be recorded as an action.
+    if !tools.iter().any(|tool| tool.name == tool_call.name) {
+        return format!("There is no tool named '{}'.", tool_call.name);
+    }
+
+    match tool_call.arguments.get("data_analyst_ticket") {
+        Some(Value::String(ticket)) => {
+            // Only the first call of each action is used downstream.
+            if get_action(actions, &tool_call.name).is_none() {
+                actions.push(json!({
+                    "name": tool_call.name,
+                    "data_analyst_ticket": ticket,
+                }));
+            }
+
+            format!("Recorded the {} action.", tool_call.name)
+        }
+        _ => "Missing 'data_analyst_ticket'.".to_string(),
+    }
+}
+
+async fn search_values_tool(options: &mut DataAnalystAgentOptions, arguments: &Value) -> String {
+    let search_terms = match arguments.get("search_terms") {
+        Some(Value::Array(terms)) => terms
+            .iter()
+            .filter_map(|term| term.as_str().map(String::from))
+            .collect::<Vec<String>>(),
+        _ => return "Missing 'search_terms'.".to_string(),
+    };
+
+    let mut found_values: Vec<StoredValue> = Vec::new();
+
+    for search_term in search_terms {
+        for dataset in &options.datasets {
+            match search_values_for_dataset(
+                &options.organization_id,
+                &dataset.dataset.id,
+                search_term.clone(),
+            )
+            .await
+            {
+                Ok(values) => found_values.extend(values),
+                Err(e) => {
+                    tracing::error!(
+                        "Error searching stored values for dataset {}: {:?}",
+                        dataset.dataset.id,
+                        e
+                    );
+                }
+            }
+        }
+    }
+
+    if found_values.is_empty() {
+        return "No matching values were found.".to_string();
+    }
+
+    let result = found_values
+        .iter()
+        .map(|v| {
+            let dataset_name = options
+                .datasets
+                .iter()
+                .find(|d| d.dataset.id == v.dataset_id)
+                .map(|d| d.dataset.name.clone())
+                .unwrap_or_default();
+
+            format!("{}.{}: {}", dataset_name, v.column_name, v.value)
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    for value in found_values {
+        if !options
+            .relevant_values
+            .iter()
+            .any(|v| v.column_id == value.column_id && v.value == value.value)
+        {
+            options.relevant_values.push(value);
+        }
+    }
+
+    result
+}
+
+// `%` and `_` in a term name are matched literally rather than as wildcards.
+fn escape_like_pattern(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+async fn look_up_term_tool(options: &mut DataAnalystAgentOptions, arguments: &Value) -> String {
+    let term = match arguments.get("term") {
+        Some(Value::String(term)) => term.clone(),
+        _ => return "Missing 'term'.".to_string(),
+    };
+
+    let format_term = |term: &RelevantTerm| match &term.sql_snippet {
+        Some(sql_snippet) => format!("{}: {}\nSQL: {}", term.name, term.definition, sql_snippet),
+        None => format!("{}: {}", term.name, term.definition),
+    };
+
+    if let Some(known_term) = options
+        .terms
+        .iter()
+        .find(|t| t.name.to_lowercase() == term.to_lowercase())
+    {
+        return format_term(known_term);
+    }
+
+    let dataset_ids = options
+        .datasets
+        .iter()
+        .map(|d| d.dataset.id)
+        .collect::<Vec<Uuid>>();
+
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            tracing::error!("Unable to get connection from pool: {:?}", e);
+            return "Unable to look up the term.".to_string();
+        }
+    };
+
+    let matched_terms = match terms::table
+        .inner_join(terms_to_datasets::table.on(terms::id.eq(terms_to_datasets::term_id)))
+        .select((
+            terms::id,
+            terms::name,
+            terms::definition,
+            terms::sql_snippet,
+            terms_to_datasets::dataset_id,
+        ))
+        .filter(terms::organization_id.eq(options.organization_id))
+        .filter(terms::name.ilike(format!("%{}%", escape_like_pattern(&term))))
+        .filter(terms_to_datasets::dataset_id.eq_any(&dataset_ids))
+        .filter(terms::deleted_at.is_null())
+        .filter(terms_to_datasets::deleted_at.is_null())
+        .limit(5)
+        .load::<(Uuid, String, Option<String>, Option<String>, Uuid)>(&mut conn)
+        .await
+    {
+        Ok(terms) => terms,
+        Err(e) => {
+            tracing::error!("Error looking up term {}: {:?}", term, e);
+            return "Unable to look up the term.".to_string();
+        }
+    };
+
+    if matched_terms.is_empty() {
+        return format!("No definition was found for '{}'.", term);
+    }
+
+    let mut results = Vec::new();
+
+    for (id, name, definition, sql_snippet, dataset_id) in matched_terms {
+        let relevant_term = RelevantTerm {
+            id,
+            name,
+            definition: definition.unwrap_or_default(),
+            sql_snippet,
+            dataset_id,
+        };
+
+        results.push(format_term(&relevant_term));
+
+        if !options.terms.iter().any(|t| t.id == relevant_term.id) {
+            options.terms.push(relevant_term);
+        }
+    }
+
+    results.join("\n\n")
+}
+
 fn assemble_orchestrator_thoughts(actions: &Vec<Value>) -> Vec<Thought> {
     let mut thoughts = Vec::new();
     for action in actions {
@@ -1590,6 +1911,7 @@ fn assemble_orchestrator_thoughts(actions: &Vec<Value>) -> Vec<Thought> {
                         "User requested action that cannot be done"
                     }
                     "explain_sql_data" => "User asked me to explain the results",
+                    "analyze_data" => "User asked for a statistical analysis",
                     "explain_something_general" => "User had a unique request",
                     "chart_requested_but_not_compatible" => {
                         "User asked for a chart that we don't support"
@@ -1612,16 +1934,22 @@ fn assemble_orchestrator_thoughts(actions: &Vec<Value>) -> Vec<Thought> {
 }
 
 fn create_orchestrator_messages(
+    system_prompt: String,
     input: String,
     message_history: &Vec<Value>,
 ) -> Vec<PromptNodeMessage> {
     let mut messages = vec![PromptNodeMessage {
         role: "system".to_string(),
-        content: orchestrator_system_prompt(),
+        content: system_prompt,
     }];
 
     // Add message history
     for message in message_history {
+        if let Some(summary_message) = conversation_summary_message(message) {
+            messages.push(summary_message);
+            continue;
+        }
+
         // Add user message with input
         if let Some(input) = message.get("input").and_then(|v| v.as_str()) {
             messages.push(PromptNodeMessage {
@@ -1792,58 +2120,11 @@ fn get_sql(results: &Value) -> Option<String> {
         .map(|s| s.to_string())
 }
 
-fn get_generate_sql_action(actions: &Vec<Value>) -> Option<Value> {
-    let sql_gen = actions
+fn get_action(actions: &Vec<Value>, name: &str) -> Option<Value> {
+    actions
         .iter()
-        .find(|action| action.get("name") == Some(&Value::String("generate_sql".to_string())));
-
-    sql_gen.cloned()
-}
-
-fn get_modify_visualization_action(actions: &Vec<Value>) -> Option<Value> {
-    let modify_visualization = actions.iter().find(|action| {
-        action.get("name") == Some(&Value::String("modify_visualization".to_string()))
-    });
-
-    modify_visualization.cloned()
-}
-
-fn get_explain_something_general_action(actions: &Vec<Value>) -> Option<Value> {
-    let explain_something_general = actions.iter().find(|action| {
-        action.get("name") == Some(&Value::String("explain_something_general".to_string()))
-    });
-
-    explain_something_general.cloned()
-}
-
-fn get_explain_sql_data_action(actions: &Vec<Value>) -> Option<Value> {
-    let explain_sql_data = actions
-        .iter()
-        .find(|action| action.get("name") == Some(&Value::String("explain_sql_data".to_string())));
-
-    explain_sql_data.cloned()
-}
-
-fn get_cannot_do_requested_action(actions: &Vec<Value>) -> Option<Value> {
-    let cannot_do_requested = actions.iter().find(|action| {
-        action.get("name")
-            == Some(&Value::String(
-                "cannot_do_requested_action_response".to_string(),
-            ))
-    });
-
-    cannot_do_requested.cloned()
-}
-
-fn get_chart_requested_but_not_compatible_action(actions: &Vec<Value>) -> Option<Value> {
-    let chart_requested_but_not_compatible = actions.iter().find(|action| {
-        action.get("name")
-            == Some(&Value::String(
-                "chart_requested_but_not_compatible".to_string(),
-            ))
-    });
-
-    chart_requested_but_not_compatible.cloned()
+        .find(|action| action.get("name").and_then(|v| v.as_str()) == Some(name))
+        .cloned()
 }
 
 async fn send_message(
@@ -1896,3 +2177,94 @@ async fn send_sql_evaluation_to_sub(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool_call(name: &str, arguments: Value) -> LlmToolCall {
+        LlmToolCall {
+            id: format!("call_{}", name),
+            name: name.to_string(),
+            arguments,
+        }
+    }
+
+    #[test]
+    fn test_escape_like_pattern() {
+        assert_eq!(escape_like_pattern("revenue"), "revenue");
+        assert_eq!(escape_like_pattern("100%"), "100\\%");
+        assert_eq!(escape_like_pattern("net_revenue"), "net\\_revenue");
+        assert_eq!(escape_like_pattern("a\\b"), "a\\\\b");
+        assert_eq!(escape_like_pattern("%_\\"), "\\%\\_\\\\");
+    }
+
+    #[test]
+    fn test_get_action() {
+        let actions = vec![
+            json!({ "name": "generate_sql", "data_analyst_ticket": "first" }),
+            json!({ "name": "explain_sql_data", "data_analyst_ticket": "second" }),
+        ];
+
+        assert_eq!(
+            get_action(&actions, "generate_sql").unwrap()["data_analyst_ticket"],
+            "first"
+        );
+        assert!(get_action(&actions, "analyze_data").is_none());
+    }
+
+    #[test]
+    fn test_record_action_keeps_first_call() {
+        let tools = orchestrator_tools();
+        let mut actions = Vec::new();
+
+        let result = record_action(
+            &tools,
+            &mut actions,
+            &tool_call("generate_sql", json!({ "data_analyst_ticket": "first" })),
+        );
+        assert_eq!(result, "Recorded the generate_sql action.");
+
+        record_action(
+            &tools,
+            &mut actions,
+            &tool_call("generate_sql", json!({ "data_analyst_ticket": "second" })),
+        );
+
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0]["data_analyst_ticket"], "first");
+
+        let result = record_action(&tools, &mut actions, &tool_call("analyze_data", json!({})));
+        assert_eq!(result, "Missing 'data_analyst_ticket'.");
+        assert_eq!(actions.len(), 1);
+    }
+
+    #[test]
+    fn test_record_action_rejects_unknown_tools() {
+        let tools = orchestrator_tools();
+        let mut actions = Vec::new();
+
+        let result = record_action(
+            &tools,
+            &mut actions,
+            &tool_call("drop_tables", json!({ "data_analyst_ticket": "all" })),
+        );
+
+        assert_eq!(result, "There is no tool named 'drop_tables'.");
+        assert!(actions.is_empty());
+    }
+
+    #[test]
+    fn test_is_lookup_tool() {
+        assert!(is_lookup_tool("search_values"));
+        assert!(is_lookup_tool("look_up_term"));
+        assert!(!is_lookup_tool("generate_sql"));
+
+        // Every lookup tool is offered to the model.
+        let lookup_tools = orchestrator_tools()
+            .into_iter()
+            .filter(|tool| is_lookup_tool(&tool.name))
+            .count();
+        assert_eq!(lookup_tools, 2);
+    }
+}
@@ -0,0 +1,238 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::fmt;
+use uuid::Uuid;
+
+use crate::utils::{
+    agent_builder::nodes::{
+        error_node::ErrorNode,
+        prompt_node::{prompt_node, PromptNodeMessage, PromptNodeSettings},
+    },
+    prompts::analyst_chat_prompts::follow_up_suggestions_prompt::{
+        follow_up_suggestions_system_prompt, follow_up_suggestions_user_prompt,
+    },
+};
+
+use super::data_analyst_agent::{DatasetWithMetadata, RelevantTerm};
+
+// The selected dataset plus the next best ranked datasets the user can access.
+const MAX_SUGGESTION_DATASETS: usize = 5;
+
+const MAX_SUGGESTIONS: usize = 5;
+
+pub enum FollowUpSuggestionsAgentError {
+    ObjectNotJson,
+}
+
+impl fmt::Display for FollowUpSuggestionsAgentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ObjectNotJson => write!(f, "object_not_json"),
+        }
+    }
+}
+
+pub struct FollowUpSuggestionsAgentOptions {
+    pub input: String,
+    pub sql: String,
+    pub data_metadata: Value,
+    pub dataset_id: Option<Uuid>,
+    // The datasets the user has access to, in ranked order.
+    pub datasets: Vec<DatasetWithMetadata>,
+    pub terms: Vec<RelevantTerm>,
+    pub thread_id: Uuid,
+    pub user_id: Uuid,
+}
+
+#[derive(Deserialize)]
+struct FollowUpSuggestion {
+    question: String,
+    dataset: String,
+    columns: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct FollowUpSuggestionsResponse {
+    suggestions: Vec<FollowUpSuggestion>,
+}
+
+/// Suggests follow-up questions to a completed answer.
+///
+/// Only suggestions whose dataset and columns exist in the datasets the user can access are
+/// returned, so every suggestion can be answered. Returns at most five questions.
+pub async fn follow_up_suggestions_agent(
+    options: FollowUpSuggestionsAgentOptions,
+) -> Result<Vec<String>, ErrorNode> {
+    let mut datasets = options
+        .datasets
+        .iter()
+        .collect::<Vec<&DatasetWithMetadata>>();
+
+    // The dataset that was just queried is listed first.
+    if let Some(dataset_id) = options.dataset_id {
+        datasets.sort_by_key(|d| d.dataset.id != dataset_id);
+    }
+
+    datasets.truncate(MAX_SUGGESTION_DATASETS);
+
+    let dataset_columns = datasets
+        .iter()
+        .map(|d| {
+            (
+                d.dataset.name.clone(),
+                d.columns
+                    .iter()
+                    .map(|c| c.name.clone())
+                    .collect::<Vec<String>>(),
+            )
+        })
+        .collect::<Vec<(String, Vec<String>)>>();
+
+    let datasets_string = datasets
+        .iter()
+        .map(|d| {
+            let columns = d
+                .columns
+                .iter()
+                .map(|c| match &c.description {
+                    Some(description) => format!("- {} ({}): {}", c.name, c.type_, description),
+                    None => format!("- {} ({})", c.name, c.type_),
+                })
+                .collect::<Vec<String>>()
+                .join("\n");
+
+            format!("### {}\n{}", d.dataset.name, columns)
+        })
+        .collect::<Vec<String>>()
+        .join("\n\n");
+
+    let terms_string = options
+        .terms
+        .iter()
+        .map(|term| format!("{}: {}", term.name, term.definition))
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let follow_up_suggestions_prompt_settings = PromptNodeSettings {
+        messages: vec![
+            PromptNodeMessage {
+                role: "system".to_string(),
+                content: follow_up_suggestions_system_prompt(),
+            },
+            PromptNodeMessage {
+                role: "user".to_string(),
+                content: follow_up_suggestions_user_prompt(
+                    &options.input,
+                    &options.sql,
+                    &options.data_metadata.to_string(),
+                    &datasets_string,
+                    &terms_string,
+                ),
+            },
+        ],
+        json_mode: true,
+        session_id: options.thread_id,
+        user_id: options.user_id,
+        prompt_name: "follow_up_suggestions".to_string(),
+        ..Default::default()
+    };
+
+    let response = match prompt_node(follow_up_suggestions_prompt_settings).await {
+        Ok(response) => match serde_json::from_value::<FollowUpSuggestionsResponse>(response) {
+            Ok(response) => response,
+            Err(e) => {
+                return Err(ErrorNode::new(
+                    FollowUpSuggestionsAgentError::ObjectNotJson.to_string(),
+                    format!("Follow-up suggestions are not valid: {}", e),
+                ))
+            }
+        },
+        Err(e) => return Err(e),
+    };
+
+    Ok(validate_suggestions(
+        response.suggestions,
+        &dataset_columns,
+        &options.input,
+    ))
+}
+
+fn validate_suggestions(
+    suggestions: Vec<FollowUpSuggestion>,
+    dataset_columns: &[(String, Vec<String>)],
+    input: &str,
+) -> Vec<String> {
+    let mut questions: Vec<String> = Vec::new();
+
+    for suggestion in suggestions {
+        let question = suggestion.question.trim().to_string();
+
+        if question.is_empty()
+            || question.eq_ignore_ascii_case(input.trim())
+            || questions.iter().any(|q| q.eq_ignore_ascii_case(&question))
+        {
+            continue;
+        }
+
+        let columns = match dataset_columns
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(&suggestion.dataset))
+        {
+            Some((_, columns)) => columns,
+            None => continue,
+        };
+
+        let columns_exist = !suggestion.columns.is_empty()
+            && suggestion
+                .columns
+                .iter()
+                .all(|column| columns.iter().any(|c| c.eq_ignore_ascii_case(column)));
+
+        if !columns_exist {
+            continue;
+        }
+
+        questions.push(question);
+
+        if questions.len() == MAX_SUGGESTIONS {
+            break;
+        }
+    }
+
+    questions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suggestion(question: &str, dataset: &str, columns: &[&str]) -> FollowUpSuggestion {
+        FollowUpSuggestion {
+            question: question.to_string(),
+            dataset: dataset.to_string(),
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_validate_suggestions_drops_unknown_columns_and_datasets() {
+        let dataset_columns = vec![(
+            "orders".to_string(),
+            vec!["region".to_string(), "revenue".to_string()],
+        )];
+
+        let questions = validate_suggestions(
+            vec![
+                suggestion("Revenue by region?", "orders", &["REGION", "revenue"]),
+                suggestion("Revenue by channel?", "orders", &["channel", "revenue"]),
+                suggestion("Top customers?", "customers", &["name"]),
+                suggestion("revenue by region?", "orders", &["region", "revenue"]),
+                suggestion("Total revenue", "orders", &["revenue"]),
+            ],
+            &dataset_columns,
+            "Total revenue",
+        );
+
+        assert_eq!(questions, vec!["Revenue by region?".to_string()]);
+    }
+}
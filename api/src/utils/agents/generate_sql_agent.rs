@@ -1,6 +1,6 @@
 use std::{collections::HashMap, fmt, time::Instant};
 
-use diesel::{BoolExpressionMethods, ExpressionMethods, QueryDsl};
+use diesel::{ExpressionMethods, QueryDsl};
 use diesel_async::RunQueryDsl;
 use regex::Regex;
 use serde_json::{json, Value};
@@ -9,9 +9,9 @@ use uuid::Uuid;
 
 use crate::{
     database::{
+        enums::DataSourceType,
         lib::get_pg_pool,
-        models::EntityRelationship,
-        schema::{data_sources, datasets::data_source_id, entity_relationship},
+        schema::{data_sources, datasets::data_source_id},
     },
     utils::{
         agent_builder::nodes::{
@@ -24,14 +24,22 @@ use crate::{
                 dataset_selector_prompt_schema, dataset_selector_system_prompt,
                 dataset_selector_user_prompt,
             },
+            semantic_query_prompt::{semantic_query_system_prompt, semantic_query_user_prompt},
             sql_gen_prompt::{sql_gen_system_prompt, sql_gen_user_prompt},
             sql_gen_thought_prompt::{sql_gen_thought_system_prompt, sql_gen_thought_user_prompt},
         },
+        prompts::prompt_registry::resolve_prompt,
+        semantic_layer::{
+            compiler::{compile_semantic_query, SemanticQuery},
+            join_planner::plan_joins,
+            semantic_model::{get_semantic_model, SemanticModel},
+        },
         stored_values::search::{search_values_for_dataset, StoredValue},
     },
 };
 
 use super::{
+    conversation_memory_agent::conversation_summary_message,
     data_analyst_agent::{DatasetWithMetadata, RelevantTerm, Thought, Thoughts},
     run_and_fix_sql_agent::{run_and_fix_sql_agent, RunAndFixSqlAgentOptions},
 };
@@ -70,11 +78,12 @@ pub struct GenerateSqlAgentOptions {
     pub message_history: Vec<Value>,
     pub start_time: Instant,
     pub organization_id: Uuid,
+    pub user_id: Uuid,
     pub relevant_values: Vec<StoredValue>,
 }
 
 pub async fn generate_sql_agent(options: GenerateSqlAgentOptions) -> Result<Value, ErrorNode> {
-    let mut thoughts = options.thoughts;
+    let mut thoughts = options.thoughts.clone();
 
     // Validate the input
     let input = match options.sql_gen_action.get("data_analyst_ticket") {
@@ -95,6 +104,37 @@ pub async fn generate_sql_agent(options: GenerateSqlAgentOptions) -> Result<Valu
 
     let dataset_selector_json_schema = dataset_selector_prompt_schema(&datasets_enum);
 
+    // The relationships between every dataset the user can query, used to plan joins.
+    let semantic_model = match get_semantic_model(
+        &options
+            .datasets
+            .iter()
+            .map(|d| d.dataset.id)
+            .collect::<Vec<Uuid>>(),
+    )
+    .await
+    {
+        Ok(semantic_model) => semantic_model,
+        Err(e) => {
+            tracing::error!("Unable to load the semantic model: {:?}", e);
+            SemanticModel::default()
+        }
+    };
+
+    let dataset_schemas = dataset_schemas_string(
+        &options.datasets,
+        &semantic_model.relationships_prompt_string(),
+    );
+
+    let dataset_selector_prompt = resolve_prompt(
+        "dataset_selector",
+        &options.organization_id,
+        &options.user_id,
+        &[("datasets", dataset_schemas.as_str())],
+        dataset_selector_system_prompt(&dataset_schemas),
+    )
+    .await;
+
     // Assemble the options for the prompt node from the generate sql agent inputs
     let dataset_selector_prompt_settings = PromptNodeSettings {
         messages: create_dataset_selector_messages(
@@ -102,10 +142,12 @@ pub async fn generate_sql_agent(options: GenerateSqlAgentOptions) -> Result<Valu
             &options.datasets,
             &options.terms,
             &options.relevant_values,
+            dataset_selector_prompt.content.clone(),
             &options.message_history,
         ),
         json_schema: Some(dataset_selector_json_schema),
         prompt_name: "dataset_selector".to_string(),
+        prompt_tags: dataset_selector_prompt.trace_tags(),
         ..Default::default()
     };
 
@@ -240,13 +282,65 @@ pub async fn generate_sql_agent(options: GenerateSqlAgentOptions) -> Result<Valu
         .map(|(dataset, _)| dataset.dataset.id)
         .collect::<Vec<Uuid>>();
 
+    // Plan how the selected datasets join instead of leaving the joins to the LLM. Datasets that
+    // are only needed to connect the selected ones are added to the context.
+    let mut datasets = datasets;
+    let mut join_plan_string = String::new();
+    let mut planned_dataset_ids = dataset_ids.clone();
+
+    if dataset_ids.len() > 1 {
+        match plan_joins(&semantic_model, &dataset_ids[0], &dataset_ids[1..]) {
+            Ok(plan) => {
+                for dataset_id in plan.dataset_ids() {
+                    if dataset_ids.contains(&dataset_id) {
+                        continue;
+                    }
+
+                    if let Some(dataset) =
+                        options.datasets.iter().find(|d| d.dataset.id == dataset_id)
+                    {
+                        datasets.push((
+                            dataset.clone(),
+                            "Used to join the selected datasets.".to_string(),
+                        ));
+                        planned_dataset_ids.push(dataset_id);
+                    }
+                }
+
+                join_plan_string = plan.to_prompt_string(&semantic_model);
+            }
+            Err(e) => {
+                join_plan_string = format!(
+                    "## JOIN PLAN\n{}. Do not join these datasets, answer with the dataset that covers most of the request.\n",
+                    e
+                );
+            }
+        }
+    }
+
+    // Values the orchestrator already found with the search_values tool come first.
+    let mut stored_values = options
+        .relevant_values
+        .iter()
+        .filter(|v| dataset_ids.contains(&v.dataset_id))
+        .cloned()
+        .collect::<Vec<StoredValue>>();
+
     // Search for relevant stored values
-    let mut stored_values = Vec::new();
     for dataset_id in &dataset_ids {
         match search_values_for_dataset(&options.organization_id, dataset_id, input.to_string())
             .await
         {
-            Ok(values) => stored_values.extend(values),
+            Ok(values) => {
+                for value in values {
+                    if !stored_values
+                        .iter()
+                        .any(|v| v.column_id == value.column_id && v.value == value.value)
+                    {
+                        stored_values.push(value);
+                    }
+                }
+            }
             Err(e) => {
                 tracing::error!(
                     "Error searching stored values for dataset {}: {:?}",
@@ -310,24 +404,6 @@ pub async fn generate_sql_agent(options: GenerateSqlAgentOptions) -> Result<Valu
         }
     };
 
-    let entity_relationships = match entity_relationship::table
-        .filter(
-            entity_relationship::primary_dataset_id
-                .eq_any(&dataset_ids)
-                .or(entity_relationship::foreign_dataset_id.eq_any(&dataset_ids)),
-        )
-        .load::<EntityRelationship>(&mut conn)
-        .await
-    {
-        Ok(entity_relationships) => entity_relationships,
-        Err(e) => {
-            return Err(ErrorNode::new(
-                GenerateSqlAgentError::GenericError.to_string(),
-                e.to_string(),
-            ))
-        }
-    };
-
     let mut terms_string = String::new();
     let mut terms_map: std::collections::HashMap<String, (String, String, Vec<String>)> =
         std::collections::HashMap::new();
@@ -387,7 +463,7 @@ pub async fn generate_sql_agent(options: GenerateSqlAgentOptions) -> Result<Valu
 
     let (thought_tx, mut thought_rx) = mpsc::channel::<Value>(100);
 
-    let dataset_ddls = datasets
+    let mut dataset_ddls = datasets
         .iter()
         .map(|(dataset, _)| {
             format!(
@@ -399,24 +475,90 @@ pub async fn generate_sql_agent(options: GenerateSqlAgentOptions) -> Result<Valu
         .collect::<Vec<String>>()
         .join("\n\n");
 
+    if !join_plan_string.is_empty() {
+        dataset_ddls.push_str("\n\n");
+        dataset_ddls.push_str(&join_plan_string);
+    }
+
     let dataset_explanations = datasets
         .iter()
         .map(|(_, explanation)| explanation.clone())
         .collect::<Vec<String>>()
         .join("\n\n");
 
+    // Requests the semantic layer can express are compiled instead of written from scratch.
+    if let Some((semantic_sql, semantic_explanation)) = semantic_layer_sql(
+        input,
+        &semantic_model.subset(&planned_dataset_ids),
+        &data_source_type,
+        &terms_string,
+        &relevant_values_string,
+        &options,
+    )
+    .await
+    {
+        thoughts.thoughts.push(Thought {
+            type_: "thoughtBlock".to_string(),
+            title: "Built the query from the semantic layer".to_string(),
+            content: Some(semantic_explanation.clone()),
+            code: Some(semantic_sql.clone()),
+            error: None,
+        });
+
+        let duration = Instant::now().duration_since(options.start_time);
+
+        thoughts.title = format!("Thought for {} seconds", duration.as_secs());
+
+        send_message(
+            "thought_finished".to_string(),
+            serde_json::to_value(&thoughts).unwrap(),
+            options.output_sender.clone(),
+        )
+        .await?;
+
+        return run_generated_sql(
+            input,
+            &dataset_selector_response,
+            &datasets,
+            &dataset_ddls,
+            semantic_sql,
+            semantic_explanation,
+            thoughts,
+            &options,
+        )
+        .await;
+    }
+
+    let sql_gen_thought_prompt = resolve_prompt(
+        "sql_gen_thought",
+        &options.organization_id,
+        &options.user_id,
+        &[
+            ("datasets", dataset_ddls.as_str()),
+            ("explanation", dataset_explanations.as_str()),
+            ("terms", terms_string.as_str()),
+            ("relevant_values", relevant_values_string.as_str()),
+            ("data_source_type", data_source_type.as_str()),
+        ],
+        sql_gen_thought_system_prompt(
+            &dataset_ddls,
+            &dataset_explanations,
+            &terms_string,
+            &relevant_values_string,
+            &data_source_type,
+        ),
+    )
+    .await;
+
     let sql_gen_thought_prompt_settings = PromptNodeSettings {
         messages: create_sql_gen_thought_messages(
             &input,
             &previous_sql,
-            &dataset_ddls,
-            &data_source_type,
-            &terms_string,
-            &dataset_explanations,
+            sql_gen_thought_prompt.content.clone(),
             &options.message_history,
-            &relevant_values_string,
         ),
         prompt_name: "sql_gen_thought".to_string(),
+        prompt_tags: sql_gen_thought_prompt.trace_tags(),
         stream: Some(thought_tx.clone()),
         stream_name: Some("generating_sql_thought".to_string()),
         ..Default::default()
@@ -586,21 +728,39 @@ pub async fn generate_sql_agent(options: GenerateSqlAgentOptions) -> Result<Valu
     )
     .await?;
 
+    let sql_gen_prompt = resolve_prompt(
+        "sql_gen",
+        &options.organization_id,
+        &options.user_id,
+        &[
+            ("datasets", dataset_ddls.as_str()),
+            ("explanation", dataset_explanations.as_str()),
+            ("terms", terms_string.as_str()),
+            ("relevant_values", relevant_values_string.as_str()),
+            ("data_source_type", data_source_type.as_str()),
+        ],
+        sql_gen_system_prompt(
+            &dataset_ddls,
+            &dataset_explanations,
+            &terms_string,
+            &relevant_values_string,
+            &data_source_type,
+        ),
+    )
+    .await;
+
     // Assemble the options for the prompt node from the generate sql agent inputs
     let sql_gen_prompt_settings = PromptNodeSettings {
         messages: create_sql_gen_messages(
             input,
             &sql_gen_thought_response,
-            &dataset_ddls,
-            &terms_string,
-            &dataset_explanations,
+            sql_gen_prompt.content.clone(),
             &options.message_history,
-            &relevant_values_string,
-            &data_source_type,
         ),
         stream: Some(options.output_sender.clone()),
         stream_name: Some("generating_sql".to_string()),
         prompt_name: "sql_gen".to_string(),
+        prompt_tags: sql_gen_prompt.trace_tags(),
         ..Default::default()
     };
 
@@ -618,6 +778,30 @@ pub async fn generate_sql_agent(options: GenerateSqlAgentOptions) -> Result<Valu
         }
     };
 
+    run_generated_sql(
+        input,
+        &dataset_selector_response,
+        &datasets,
+        &dataset_ddls,
+        sql_gen_response,
+        sql_gen_thought_response,
+        thoughts,
+        &options,
+    )
+    .await
+}
+
+// Runs the generated SQL, fixing it if needed, and assembles the result of the agent.
+async fn run_generated_sql(
+    input: &String,
+    dataset_selector_response: &serde_json::Map<String, Value>,
+    datasets: &[(DatasetWithMetadata, String)],
+    dataset_ddls: &str,
+    sql_gen_response: String,
+    sql_gen_thought_response: String,
+    thoughts: Thoughts,
+    options: &GenerateSqlAgentOptions,
+) -> Result<Value, ErrorNode> {
     let (dataset, _) = datasets[0].clone();
     let dataset_id = dataset.dataset.id;
     let dataset_name = dataset.dataset.name.clone();
@@ -625,7 +809,7 @@ pub async fn generate_sql_agent(options: GenerateSqlAgentOptions) -> Result<Valu
     let run_and_fix_sql_agent_options = RunAndFixSqlAgentOptions {
         sql_input: sql_gen_response.clone(),
         dataset_id: dataset_id.clone(),
-        dataset: dataset_ddls.clone(),
+        dataset: dataset_ddls.to_string(),
         output_sender: options.output_sender.clone(),
         thoughts: thoughts.clone(),
         start_time: options.start_time,
@@ -679,11 +863,116 @@ pub async fn generate_sql_agent(options: GenerateSqlAgentOptions) -> Result<Valu
     Ok(final_sql_agent_object)
 }
 
+/// Asks the LLM for a semantic request and compiles it. Returns `None` when the datasets have
+/// no measures, the request can't be expressed with the semantic layer, or compilation fails,
+/// in which case the SQL is written from scratch.
+async fn semantic_layer_sql(
+    input: &String,
+    semantic_model: &SemanticModel,
+    data_source_type: &str,
+    terms: &String,
+    relevant_values: &String,
+    options: &GenerateSqlAgentOptions,
+) -> Option<(String, String)> {
+    let dialect = DataSourceType::from_str(data_source_type)?;
+
+    if !semantic_model.has_measures() {
+        return None;
+    }
+
+    let semantic_model_string = semantic_model.to_prompt_string();
+
+    let semantic_query_prompt = resolve_prompt(
+        "semantic_query",
+        &options.organization_id,
+        &options.user_id,
+        &[("semantic_model", semantic_model_string.as_str())],
+        semantic_query_system_prompt(&semantic_model_string),
+    )
+    .await;
+
+    let semantic_query_prompt_settings = PromptNodeSettings {
+        messages: create_semantic_query_messages(
+            input,
+            semantic_query_prompt.content.clone(),
+            terms,
+            relevant_values,
+            &options.message_history,
+        ),
+        json_mode: true,
+        prompt_name: "semantic_query".to_string(),
+        prompt_tags: semantic_query_prompt.trace_tags(),
+        user_id: options.user_id,
+        ..Default::default()
+    };
+
+    let response = match prompt_node(semantic_query_prompt_settings).await {
+        Ok(Value::Object(response)) => response,
+        Ok(_) => return None,
+        Err(e) => {
+            tracing::error!("Error in semantic query prompt: {}", e.error_message);
+            return None;
+        }
+    };
+
+    if response.get("use_semantic_layer").and_then(|v| v.as_bool()) != Some(true) {
+        return None;
+    }
+
+    let explanation = response
+        .get("explanation")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string();
+
+    let query = match response
+        .get("query")
+        .cloned()
+        .map(serde_json::from_value::<SemanticQuery>)
+    {
+        Some(Ok(query)) => query,
+        Some(Err(e)) => {
+            tracing::warn!("Semantic query is not valid: {}", e);
+            return None;
+        }
+        None => return None,
+    };
+
+    match compile_semantic_query(semantic_model, &query, &dialect) {
+        Ok(sql) => Some((sql, explanation)),
+        Err(e) => {
+            tracing::warn!("Unable to compile semantic query: {}", e);
+            None
+        }
+    }
+}
+
+fn dataset_schemas_string(datasets: &[DatasetWithMetadata], relationships: &str) -> String {
+    let mut dataset_schemas = String::new();
+
+    for dataset in datasets {
+        dataset_schemas.push_str(&format!(
+            "{}\n{}",
+            dataset.dataset.yml_file.clone().unwrap_or("".to_string()),
+            dataset.dataset_ddl.clone(),
+        ));
+        dataset_schemas.push_str("\n\n");
+    }
+
+    if !relationships.is_empty() {
+        dataset_schemas.push_str("### RELATIONSHIPS\n");
+        dataset_schemas.push_str(relationships);
+    }
+
+    dataset_schemas
+}
+
 fn create_dataset_selector_messages(
     input: &String,
     datasets: &Vec<DatasetWithMetadata>,
     terms: &Vec<RelevantTerm>,
     relevant_values: &Vec<StoredValue>,
+    system_prompt: String,
     message_history: &Vec<Value>,
 ) -> Vec<PromptNodeMessage> {
     let mut terms_string = String::new();
@@ -723,24 +1012,18 @@ fn create_dataset_selector_messages(
         }
     }
 
-    let mut dataset_schemas = String::new();
-
-    for dataset in datasets {
-        dataset_schemas.push_str(&format!(
-            "{}\n{}",
-            dataset.dataset.yml_file.clone().unwrap_or("".to_string()),
-            dataset.dataset_ddl.clone(),
-        ));
-        dataset_schemas.push_str("\n\n");
-    }
-
     let mut messages = vec![PromptNodeMessage {
         role: "system".to_string(),
-        content: dataset_selector_system_prompt(&dataset_schemas),
+        content: system_prompt,
     }];
 
     // Add message history
     for message in message_history {
+        if let Some(summary_message) = conversation_summary_message(message) {
+            messages.push(summary_message);
+            continue;
+        }
+
         // Add user message with input
         let input = match message.get("input") {
             Some(Value::String(input)) => input,
@@ -778,26 +1061,21 @@ fn create_dataset_selector_messages(
 fn create_sql_gen_messages(
     input: &String,
     thought_process: &String,
-    dataset: &String,
-    terms: &String,
-    explanation: &String,
+    system_prompt: String,
     message_history: &Vec<Value>,
-    relevant_values: &String,
-    data_source_type: &String,
 ) -> Vec<PromptNodeMessage> {
     let mut messages = vec![PromptNodeMessage {
         role: "system".to_string(),
-        content: sql_gen_system_prompt(
-            dataset,
-            explanation,
-            terms,
-            relevant_values,
-            data_source_type,
-        ),
+        content: system_prompt,
     }];
 
     // Add message history
     for message in message_history {
+        if let Some(summary_message) = conversation_summary_message(message) {
+            messages.push(summary_message);
+            continue;
+        }
+
         // Add user message with input
         let input = match message.get("input") {
             Some(Value::String(input)) => input,
@@ -834,29 +1112,73 @@ fn create_sql_gen_messages(
     messages
 }
 
+fn create_semantic_query_messages(
+    input: &String,
+    system_prompt: String,
+    terms: &String,
+    relevant_values: &String,
+    message_history: &Vec<Value>,
+) -> Vec<PromptNodeMessage> {
+    let mut messages = vec![PromptNodeMessage {
+        role: "system".to_string(),
+        content: system_prompt,
+    }];
+
+    // Add message history so follow-up requests can refer to earlier ones
+    for message in message_history {
+        if let Some(summary_message) = conversation_summary_message(message) {
+            messages.push(summary_message);
+            continue;
+        }
+
+        let input = match message.get("input") {
+            Some(Value::String(input)) => input,
+            _ => continue,
+        };
+
+        let sql_response = match message.get("first_part_of_response") {
+            Some(Value::String(sql_response)) => sql_response,
+            _ => &"".to_string(),
+        };
+
+        messages.push(PromptNodeMessage {
+            role: "user".to_string(),
+            content: input.to_string(),
+        });
+
+        messages.push(PromptNodeMessage {
+            role: "assistant".to_string(),
+            content: sql_response.to_string(),
+        });
+    }
+
+    // Add the current input as the final user message
+    messages.push(PromptNodeMessage {
+        role: "user".to_string(),
+        content: semantic_query_user_prompt(input, terms, relevant_values),
+    });
+
+    messages
+}
+
 fn create_sql_gen_thought_messages(
     input: &String,
     sql: &Option<String>,
-    dataset: &String,
-    data_source_type: &String,
-    terms: &String,
-    explanation: &String,
+    system_prompt: String,
     message_history: &Vec<Value>,
-    relevant_values: &String,
 ) -> Vec<PromptNodeMessage> {
     let mut messages = vec![PromptNodeMessage {
         role: "system".to_string(),
-        content: sql_gen_thought_system_prompt(
-            dataset,
-            explanation,
-            terms,
-            relevant_values,
-            data_source_type,
-        ),
+        content: system_prompt,
     }];
 
     // Add message history
     for message in message_history {
+        if let Some(summary_message) = conversation_summary_message(message) {
+            messages.push(summary_message);
+            continue;
+        }
+
         // Add user message with input
         let input = match message.get("input") {
             Some(Value::String(input)) => input,
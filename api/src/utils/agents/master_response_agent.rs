@@ -1,14 +1,18 @@
 use serde_json::Value;
 use std::fmt;
 use tokio::sync::mpsc;
+use uuid::Uuid;
 
 use crate::utils::{
     agent_builder::nodes::{
         error_node::ErrorNode,
         prompt_node::{prompt_node, PromptNodeMessage, PromptNodeSettings},
     },
-    prompts::analyst_chat_prompts::master_response_prompt::{
-        master_response_system_prompt, master_response_user_prompt,
+    prompts::{
+        analyst_chat_prompts::master_response_prompt::{
+            master_response_system_prompt, master_response_user_prompt,
+        },
+        prompt_registry::resolve_prompt,
     },
 };
 
@@ -34,6 +38,8 @@ pub struct MasterResponseAgentOptions {
     pub datasets: String,
     pub input: String,
     pub output_sender: mpsc::Sender<Value>,
+    pub organization_id: Uuid,
+    pub user_id: Uuid,
 }
 
 pub async fn master_response_agent(
@@ -75,12 +81,27 @@ pub async fn master_response_agent(
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
 
+    let statistical_analysis = options
+        .outputs
+        .get("statistical_analysis")
+        .and_then(|v| v.as_object())
+        .and_then(|obj| serde_json::to_string(obj).ok());
+
+    let system_prompt = resolve_prompt(
+        "master_response",
+        &options.organization_id,
+        &options.user_id,
+        &[("datasets", options.datasets.as_str())],
+        master_response_system_prompt(&options.datasets),
+    )
+    .await;
+
     // Create prompt settings
     let master_response_prompt_settings = PromptNodeSettings {
         messages: vec![
             PromptNodeMessage {
                 role: "system".to_string(),
-                content: master_response_system_prompt(&options.datasets),
+                content: system_prompt.content.clone(),
             },
             PromptNodeMessage {
                 role: "user".to_string(),
@@ -92,12 +113,14 @@ pub async fn master_response_agent(
                     &data_metadata,
                     &chart_generated,
                     &chart_requirements,
+                    &statistical_analysis,
                 ),
             },
         ],
         stream: Some(options.output_sender),
         stream_name: Some("master_response".to_string()),
         prompt_name: "master_response".to_string(),
+        prompt_tags: system_prompt.trace_tags(),
         ..Default::default()
     };
 
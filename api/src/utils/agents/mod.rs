@@ -1,8 +1,10 @@
 pub mod column_styling_agent;
 pub mod configure_charts_agent;
+pub mod conversation_memory_agent;
 pub mod custom_response_agent;
 pub mod data_analyst_agent;
 pub mod failed_to_fix_sql_agent;
+pub mod follow_up_suggestions_agent;
 pub mod format_labels_agent;
 pub mod generate_sql_agent;
 pub mod master_response_agent;
@@ -11,4 +13,5 @@ pub mod modify_visualization_agent;
 pub mod multiple_datasets_response_agent;
 pub mod run_and_fix_sql_agent;
 pub mod sql_evaluation_agent;
+pub mod statistical_analysis_agent;
 pub mod global_styling_agent;
\ No newline at end of file
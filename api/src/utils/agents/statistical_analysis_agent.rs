@@ -0,0 +1,115 @@
+use indexmap::IndexMap;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::fmt;
+use uuid::Uuid;
+
+use crate::utils::{
+    agent_builder::nodes::{
+        error_node::ErrorNode,
+        prompt_node::{prompt_node, PromptNodeMessage, PromptNodeSettings},
+    },
+    prompts::analyst_chat_prompts::statistical_analysis_prompt::{
+        statistical_analysis_system_prompt, statistical_analysis_user_prompt,
+    },
+    query_engine::data_types::DataType,
+    statistics::analysis::{run_analysis, AnalysisRequest},
+};
+
+pub enum StatisticalAnalysisAgentError {
+    ObjectNotJson,
+}
+
+impl fmt::Display for StatisticalAnalysisAgentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ObjectNotJson => write!(f, "object_not_json"),
+        }
+    }
+}
+
+pub struct StatisticalAnalysisAgentOptions {
+    pub data_analyst_ticket: String,
+    pub sql: String,
+    pub data_metadata: Value,
+    pub data: Vec<IndexMap<String, DataType>>,
+    pub thread_id: Uuid,
+    pub user_id: Uuid,
+}
+
+#[derive(Deserialize)]
+struct StatisticalAnalysisPlan {
+    analyses: Vec<Value>,
+}
+
+/// Picks the analyses that answer the ticket and computes them over the query results.
+///
+/// The model only chooses the analyses and columns. Every number in the output is computed by
+/// `run_analysis`, so the master response can quote them instead of estimating.
+pub async fn statistical_analysis_agent(
+    options: StatisticalAnalysisAgentOptions,
+) -> Result<Value, ErrorNode> {
+    let statistical_analysis_prompt_settings = PromptNodeSettings {
+        messages: vec![
+            PromptNodeMessage {
+                role: "system".to_string(),
+                content: statistical_analysis_system_prompt(),
+            },
+            PromptNodeMessage {
+                role: "user".to_string(),
+                content: statistical_analysis_user_prompt(
+                    &options.data_analyst_ticket,
+                    &options.sql,
+                    &options.data_metadata.to_string(),
+                ),
+            },
+        ],
+        json_mode: true,
+        session_id: options.thread_id,
+        user_id: options.user_id,
+        prompt_name: "statistical_analysis".to_string(),
+        ..Default::default()
+    };
+
+    let plan = match prompt_node(statistical_analysis_prompt_settings).await {
+        Ok(response) => match serde_json::from_value::<StatisticalAnalysisPlan>(response) {
+            Ok(plan) => plan,
+            Err(e) => {
+                return Err(ErrorNode::new(
+                    StatisticalAnalysisAgentError::ObjectNotJson.to_string(),
+                    format!("Statistical analysis plan is not valid: {}", e),
+                ))
+            }
+        },
+        Err(e) => return Err(e),
+    };
+
+    let mut analyses = Vec::new();
+
+    // An analysis the model got wrong is reported back instead of failing the whole response.
+    for analysis in plan.analyses {
+        let request = match serde_json::from_value::<AnalysisRequest>(analysis.clone()) {
+            Ok(request) => request,
+            Err(e) => {
+                analyses.push(json!({
+                    "request": analysis,
+                    "error": format!("Unknown analysis: {}", e),
+                }));
+                continue;
+            }
+        };
+
+        match run_analysis(&options.data, &request) {
+            Ok(result) => analyses.push(json!({
+                "request": request,
+                "result": result,
+            })),
+            Err(e) => analyses.push(json!({
+                "request": request,
+                "error": e.to_string(),
+            })),
+        }
+    }
+
+    Ok(json!({ "analyses": analyses }))
+}
@@ -0,0 +1,421 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::utils::{
+    query_engine::data_types::DataType,
+    statistics::analysis::{mean, numeric_value, std_dev},
+};
+
+const DEFAULT_ANOMALY_WINDOW: usize = 30;
+const MIN_BASELINE_POINTS: usize = 3;
+
+/// When an alert fires. Stored as JSON in `metric_alerts.condition`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// Fires when the latest value compares true against a fixed threshold.
+    Threshold {
+        comparator: ThresholdComparator,
+        value: f64,
+    },
+    /// Fires when the latest value is more than `sigmas` standard deviations from the mean of the
+    /// `window` values before it.
+    Anomaly {
+        #[serde(default)]
+        direction: AnomalyDirection,
+        sigmas: f64,
+        #[serde(default = "default_anomaly_window")]
+        window: usize,
+    },
+}
+
+fn default_anomaly_window() -> usize {
+    DEFAULT_ANOMALY_WINDOW
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ThresholdComparator {
+    GreaterThan,
+    GreaterThanOrEqual,
+    LessThan,
+    LessThanOrEqual,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyDirection {
+    Above,
+    Below,
+    #[default]
+    Either,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertState {
+    Ok,
+    Firing,
+    Resolved,
+}
+
+impl AlertState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertState::Ok => "ok",
+            AlertState::Firing => "firing",
+            AlertState::Resolved => "resolved",
+        }
+    }
+
+    /// The state recorded for an evaluation, given whether the alert was firing before it.
+    pub fn after_evaluation(was_firing: bool, is_firing: bool) -> Self {
+        match (was_firing, is_firing) {
+            (_, true) => AlertState::Firing,
+            (true, false) => AlertState::Resolved,
+            (false, false) => AlertState::Ok,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConditionResult {
+    pub firing: bool,
+    pub value: f64,
+    pub baseline_mean: Option<f64>,
+    pub baseline_std_dev: Option<f64>,
+    pub message: String,
+}
+
+impl AlertCondition {
+    pub fn from_value(condition: &Value) -> Result<Self> {
+        let condition: AlertCondition = match serde_json::from_value(condition.clone()) {
+            Ok(condition) => condition,
+            Err(e) => return Err(anyhow!("Invalid alert condition: {}", e)),
+        };
+
+        match &condition {
+            AlertCondition::Threshold { value, .. } if !value.is_finite() => {
+                Err(anyhow!("Alert threshold must be a number"))
+            }
+            AlertCondition::Anomaly { sigmas, .. } if !(sigmas.is_finite() && *sigmas > 0.0) => {
+                Err(anyhow!("Anomaly sigmas must be greater than zero"))
+            }
+            AlertCondition::Anomaly { window, .. } if *window < MIN_BASELINE_POINTS => Err(
+                anyhow!("Anomaly window must be at least {}", MIN_BASELINE_POINTS),
+            ),
+            _ => Ok(condition),
+        }
+    }
+
+    /// Evaluates the condition against a metric's values, oldest first. The last value is the one
+    /// tested. Anomalies use the values before it as the baseline, or `history` (earlier
+    /// evaluations, oldest first) when the metric returns a single value.
+    pub fn evaluate(&self, values: &[f64], history: &[f64]) -> Result<ConditionResult> {
+        let (value, previous) = match values.split_last() {
+            Some((value, previous)) => (*value, previous),
+            None => return Err(anyhow!("The metric returned no values to evaluate")),
+        };
+
+        match self {
+            AlertCondition::Threshold {
+                comparator,
+                value: threshold,
+            } => {
+                let (firing, symbol) = match comparator {
+                    ThresholdComparator::GreaterThan => (value > *threshold, ">"),
+                    ThresholdComparator::GreaterThanOrEqual => (value >= *threshold, ">="),
+                    ThresholdComparator::LessThan => (value < *threshold, "<"),
+                    ThresholdComparator::LessThanOrEqual => (value <= *threshold, "<="),
+                };
+
+                let message = match firing {
+                    true => format!(
+                        "{} is {} {}",
+                        format_number(value),
+                        symbol,
+                        format_number(*threshold)
+                    ),
+                    false => format!(
+                        "{} is no longer {} {}",
+                        format_number(value),
+                        symbol,
+                        format_number(*threshold)
+                    ),
+                };
+
+                Ok(ConditionResult {
+                    firing,
+                    value,
+                    baseline_mean: None,
+                    baseline_std_dev: None,
+                    message,
+                })
+            }
+            AlertCondition::Anomaly {
+                direction,
+                sigmas,
+                window,
+            } => {
+                let baseline_source = match previous.is_empty() {
+                    true => history,
+                    false => previous,
+                };
+                let baseline = &baseline_source[baseline_source.len().saturating_sub(*window)..];
+
+                let (baseline_mean, baseline_std_dev) = match (
+                    baseline.len() >= MIN_BASELINE_POINTS,
+                    mean(baseline),
+                    std_dev(baseline),
+                ) {
+                    (true, Some(mean), Some(std_dev)) => (mean, std_dev),
+                    _ => {
+                        return Ok(ConditionResult {
+                            firing: false,
+                            value,
+                            baseline_mean: None,
+                            baseline_std_dev: None,
+                            message: format!(
+                                "Waiting for at least {} values to build a baseline",
+                                MIN_BASELINE_POINTS
+                            ),
+                        })
+                    }
+                };
+
+                let deviations = match baseline_std_dev == 0.0 {
+                    true if value == baseline_mean => 0.0,
+                    true => f64::INFINITY.copysign(value - baseline_mean),
+                    false => (value - baseline_mean) / baseline_std_dev,
+                };
+
+                let firing = match direction {
+                    AnomalyDirection::Above => deviations > *sigmas,
+                    AnomalyDirection::Below => deviations < -*sigmas,
+                    AnomalyDirection::Either => deviations.abs() > *sigmas,
+                };
+
+                let message = format!(
+                    "{} is {} standard deviations {} the trailing average of {}",
+                    format_number(value),
+                    format_number(deviations.abs()),
+                    if deviations >= 0.0 { "above" } else { "below" },
+                    format_number(baseline_mean)
+                );
+
+                Ok(ConditionResult {
+                    firing,
+                    value,
+                    baseline_mean: Some(baseline_mean),
+                    baseline_std_dev: Some(baseline_std_dev),
+                    message,
+                })
+            }
+        }
+    }
+}
+
+/// Reads a numeric column from query results, oldest first. Rows are ordered by the time column,
+/// which is required once the metric returns more than one row, since the warehouse's row order
+/// says nothing about which value is the latest.
+pub fn column_values(
+    rows: &[IndexMap<String, DataType>],
+    column: &str,
+    time_column: Option<&str>,
+) -> Result<Vec<f64>> {
+    let column_key = match find_column(rows, column) {
+        Some(key) => key,
+        None if rows.is_empty() => return Ok(Vec::new()),
+        None => return Err(anyhow!("The metric has no column named '{}'", column)),
+    };
+
+    if rows.len() == 1 {
+        return Ok(rows[0]
+            .get(&column_key)
+            .and_then(numeric_value)
+            .into_iter()
+            .collect());
+    }
+
+    let time_column = match time_column {
+        Some(time_column) => time_column,
+        None => {
+            return Err(anyhow!(
+                "The metric returns more than one row, so the alert needs a time column to order them by"
+            ))
+        }
+    };
+
+    let time_key = match find_column(rows, time_column) {
+        Some(key) => key,
+        None => return Err(anyhow!("The metric has no column named '{}'", time_column)),
+    };
+
+    let mut points = Vec::with_capacity(rows.len());
+
+    for row in rows {
+        let time = match row.get(&time_key).and_then(time_value) {
+            Some(time) => time,
+            None => {
+                return Err(anyhow!(
+                    "The metric's '{}' column has a value that is not a date or time",
+                    time_column
+                ))
+            }
+        };
+
+        if let Some(value) = row.get(&column_key).and_then(numeric_value) {
+            points.push((time, value));
+        }
+    }
+
+    points.sort_by_key(|(time, _)| *time);
+
+    Ok(points.into_iter().map(|(_, value)| value).collect())
+}
+
+fn find_column(rows: &[IndexMap<String, DataType>], column: &str) -> Option<String> {
+    rows.first().and_then(|row| {
+        row.keys()
+            .find(|key| key.eq_ignore_ascii_case(column))
+            .cloned()
+    })
+}
+
+fn time_value(value: &DataType) -> Option<NaiveDateTime> {
+    match value {
+        DataType::Date(Some(v)) => v.and_hms_opt(0, 0, 0),
+        DataType::Timestamp(Some(v)) => Some(*v),
+        DataType::Timestamptz(Some(v)) => Some(v.naive_utc()),
+        DataType::Text(Some(v)) | DataType::Char(Some(v)) | DataType::Unknown(Some(v)) => {
+            parse_time(v.trim())
+        }
+        _ => None,
+    }
+}
+
+fn parse_time(value: &str) -> Option<NaiveDateTime> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return date.and_hms_opt(0, 0, 0);
+    }
+
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(value) {
+        return Some(date_time.naive_utc());
+    }
+
+    ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+}
+
+fn format_number(value: f64) -> String {
+    if value.is_infinite() {
+        "infinitely many".to_string()
+    } else if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_evaluate_alert_conditions() {
+        let below_500 = AlertCondition::from_value(&json!({
+            "type": "threshold",
+            "comparator": "less_than",
+            "value": 500
+        }))
+        .unwrap();
+
+        let result = below_500.evaluate(&[620.0, 480.0], &[]).unwrap();
+        assert!(result.firing);
+        assert_eq!(result.message, "480 is < 500");
+        assert!(!below_500.evaluate(&[510.0], &[]).unwrap().firing);
+
+        let three_sigmas = AlertCondition::from_value(&json!({
+            "type": "anomaly",
+            "sigmas": 3,
+            "window": 5
+        }))
+        .unwrap();
+        assert_eq!(
+            three_sigmas,
+            AlertCondition::Anomaly {
+                direction: AnomalyDirection::Either,
+                sigmas: 3.0,
+                window: 5
+            }
+        );
+
+        // Baseline comes from the rows before the latest one, limited to the window.
+        let series = [1000.0, 98.0, 102.0, 100.0, 99.0, 101.0, 60.0];
+        let result = three_sigmas.evaluate(&series, &[]).unwrap();
+        assert!(result.firing);
+        assert_eq!(result.baseline_mean, Some(100.0));
+
+        // A single value falls back to the history of earlier evaluations.
+        let result = three_sigmas
+            .evaluate(&[101.0], &[98.0, 102.0, 100.0, 99.0, 101.0])
+            .unwrap();
+        assert!(!result.firing);
+
+        let result = three_sigmas.evaluate(&[40.0], &[98.0, 102.0]).unwrap();
+        assert!(!result.firing);
+        assert_eq!(result.baseline_mean, None);
+
+        assert!(AlertCondition::from_value(&json!({ "type": "anomaly", "sigmas": 0 })).is_err());
+        assert!(three_sigmas.evaluate(&[], &[]).is_err());
+
+        assert_eq!(
+            AlertState::after_evaluation(false, true),
+            AlertState::Firing
+        );
+        assert_eq!(AlertState::after_evaluation(true, true), AlertState::Firing);
+        assert_eq!(
+            AlertState::after_evaluation(true, false),
+            AlertState::Resolved
+        );
+        assert_eq!(AlertState::after_evaluation(false, false), AlertState::Ok);
+    }
+
+    #[test]
+    fn test_column_values_ordered_by_time_column() {
+        let row = |day: &str, revenue: f64| {
+            IndexMap::from([
+                ("day".to_string(), DataType::Text(Some(day.to_string()))),
+                ("Revenue".to_string(), DataType::Float8(Some(revenue))),
+            ])
+        };
+
+        // Newest first, as a metric sorted for display would return them.
+        let rows = vec![
+            row("2024-01-03", 300.0),
+            row("2024-01-01", 100.0),
+            row("2024-01-02", 200.0),
+        ];
+
+        assert_eq!(
+            column_values(&rows, "revenue", Some("day")).unwrap(),
+            vec![100.0, 200.0, 300.0]
+        );
+        assert!(column_values(&rows, "revenue", None).is_err());
+        assert!(column_values(&rows, "revenue", Some("week")).is_err());
+
+        assert_eq!(
+            column_values(&rows[..1], "revenue", None).unwrap(),
+            vec![300.0]
+        );
+        assert_eq!(
+            column_values(&[], "revenue", None).unwrap(),
+            Vec::<f64>::new()
+        );
+    }
+}
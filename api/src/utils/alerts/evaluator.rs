@@ -0,0 +1,440 @@
+use std::{env, sync::Arc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, DurationRound, NaiveDateTime, TimeDelta, Utc};
+use diesel::{
+    dsl::sql,
+    insert_into,
+    sql_types::{Timestamp, Timestamptz},
+    update, BoolExpressionMethods, ExpressionMethods, QueryDsl, SelectableHelper,
+};
+use diesel_async::RunQueryDsl;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        enums::AssetType,
+        lib::get_pg_pool,
+        models::{MetricAlert, MetricAlertHistory, User},
+        schema::{metric_alert_history, metric_alerts, users},
+    },
+    utils::{
+        clients::{
+            email::sender::{EmailBackend, EmailSender, OutgoingEmail},
+            sentry_utils::send_sentry_error,
+        },
+        query_engine::query_engine::query_engine,
+        reports::{
+            cron::CronSchedule,
+            render::render_notification,
+            scheduler::get_latest_metric_message,
+            subscriptions::check_report_asset_access,
+        },
+        security::{
+            checks::is_user_workspace_admin_or_data_admin,
+            dataset_security::has_dataset_access,
+            permission_flags::{has_permission_flag, PermissionFlag},
+        },
+    },
+};
+
+use super::{
+    conditions::{column_values, AlertCondition, AlertState, ConditionResult},
+    webhooks::{validate_webhook_url, webhook_client},
+};
+
+/// Starts the loop that evaluates metric alerts on their cron schedules, alongside the report
+/// scheduler. Email notifications are skipped when no email backend is configured.
+pub fn start_alert_scheduler() {
+    let sender = match EmailBackend::from_env() {
+        Ok(sender) => Some(Arc::new(sender)),
+        Err(e) => {
+            tracing::warn!("Alert emails are disabled: {}", e);
+            None
+        }
+    };
+
+    tokio::spawn(async move {
+        loop {
+            let millis_into_minute = (Utc::now().timestamp_millis() % 60_000) as u64;
+            tokio::time::sleep(Duration::from_millis(60_000 - millis_into_minute)).await;
+
+            let minute = match Utc::now().duration_trunc(TimeDelta::minutes(1)) {
+                Ok(minute) => minute,
+                Err(e) => {
+                    tracing::error!("Error truncating alert schedule time: {}", e);
+                    continue;
+                }
+            };
+
+            if let Err(e) = evaluate_due_alerts(&sender, minute).await {
+                tracing::error!("Error evaluating metric alerts: {}", e);
+                send_sentry_error(&format!("Error evaluating metric alerts: {}", e), None);
+            }
+        }
+    });
+}
+
+async fn evaluate_due_alerts(
+    sender: &Option<Arc<EmailBackend>>,
+    minute: DateTime<Utc>,
+) -> Result<()> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    let alerts = match metric_alerts::table
+        .select((
+            MetricAlert::as_select(),
+            sql::<Timestamp>("timezone(metric_alerts.timezone, ")
+                .bind::<Timestamptz, _>(minute)
+                .sql(")"),
+        ))
+        .filter(metric_alerts::enabled.eq(true))
+        .filter(metric_alerts::deleted_at.is_null())
+        .load::<(MetricAlert, NaiveDateTime)>(&mut conn)
+        .await
+    {
+        Ok(alerts) => alerts,
+        Err(e) => return Err(anyhow!("Error querying metric alerts: {}", e)),
+    };
+
+    for (alert, local_time) in alerts {
+        match CronSchedule::parse(&alert.cron) {
+            Ok(schedule) if schedule.matches(&local_time) => (),
+            Ok(_) => continue,
+            Err(e) => {
+                tracing::error!("Invalid schedule on metric alert {}: {}", alert.id, e);
+                continue;
+            }
+        }
+
+        // Claiming the minute keeps an alert from being evaluated twice when several API
+        // processes run.
+        let claimed = match update(metric_alerts::table)
+            .filter(metric_alerts::id.eq(alert.id))
+            .filter(
+                metric_alerts::last_evaluated_at
+                    .is_null()
+                    .or(metric_alerts::last_evaluated_at.lt(minute)),
+            )
+            .set(metric_alerts::last_evaluated_at.eq(minute))
+            .execute(&mut conn)
+            .await
+        {
+            Ok(claimed) => claimed > 0,
+            Err(e) => {
+                tracing::error!("Error claiming metric alert: {}", e);
+                continue;
+            }
+        };
+
+        if !claimed {
+            continue;
+        }
+
+        let sender = sender.clone();
+        tokio::spawn(async move {
+            if let Err(e) = evaluate_alert(&alert, sender.as_deref()).await {
+                tracing::error!("Error evaluating metric alert {}: {}", alert.id, e);
+                send_sentry_error(&e.to_string(), Some(&alert.user_id));
+
+                if let Err(e) = record_alert_error(&alert.id, &e.to_string()).await {
+                    tracing::error!("Error recording metric alert error: {}", e);
+                }
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// Runs an alert's metric as the alert's owner, records the evaluation in its history and sends
+/// notifications when it starts firing or resolves.
+pub async fn evaluate_alert<S: EmailSender>(alert: &MetricAlert, sender: Option<&S>) -> Result<()> {
+    let condition = AlertCondition::from_value(&alert.condition)?;
+
+    check_report_asset_access(&alert.user_id, &alert.thread_id, &AssetType::Thread).await?;
+
+    let message = get_latest_metric_message(&alert.thread_id).await?;
+
+    let (dataset_id, metric_sql) = match (message.dataset_id, message.code) {
+        (Some(dataset_id), Some(metric_sql)) => (dataset_id, metric_sql),
+        _ => return Err(anyhow!("The metric has no query to run")),
+    };
+
+    if !has_dataset_access(&alert.user_id, &dataset_id).await? {
+        return Err(anyhow!(
+            "The alert owner no longer has access to the metric's dataset"
+        ));
+    }
+
+    let rows = query_engine(&dataset_id, &metric_sql).await?;
+    let values = column_values(&rows, &alert.column_name, alert.time_column_name.as_deref())?;
+    let history = get_alert_history_values(&alert.id).await?;
+
+    let result = condition.evaluate(&values, &history)?;
+
+    let was_firing = alert.state == AlertState::Firing.as_str();
+    let state = AlertState::after_evaluation(was_firing, result.firing);
+
+    record_alert_evaluation(alert, &state, &result).await?;
+
+    if state == AlertState::Ok || (state == AlertState::Firing && was_firing) {
+        return Ok(());
+    }
+
+    let metric_name = message.title.unwrap_or_else(|| "Metric".to_string());
+    let notification = AlertNotification::new(alert, &metric_name, &state, &result);
+
+    let mut errors = Vec::new();
+
+    if alert.notify_email {
+        if let Some(sender) = sender {
+            if let Err(e) = send_alert_email(alert, &notification, sender).await {
+                errors.push(e.to_string());
+            }
+        }
+    }
+
+    if let Some(webhook_url) = &alert.webhook_url {
+        if let Err(e) = send_alert_webhook(alert, webhook_url, &notification).await {
+            errors.push(e.to_string());
+        }
+    }
+
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(anyhow!(
+            "Error sending alert notifications: {}",
+            errors.join("; ")
+        )),
+    }
+}
+
+// Values from earlier evaluations, oldest first, used as the baseline for single-value metrics.
+async fn get_alert_history_values(alert_id: &Uuid) -> Result<Vec<f64>> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    let mut values = match metric_alert_history::table
+        .select(metric_alert_history::value)
+        .filter(metric_alert_history::alert_id.eq(alert_id))
+        .order(metric_alert_history::created_at.desc())
+        .limit(500)
+        .load::<f64>(&mut conn)
+        .await
+    {
+        Ok(values) => values,
+        Err(e) => return Err(anyhow!("Error querying metric alert history: {}", e)),
+    };
+
+    values.reverse();
+    Ok(values)
+}
+
+async fn record_alert_evaluation(
+    alert: &MetricAlert,
+    state: &AlertState,
+    result: &ConditionResult,
+) -> Result<()> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    let history = MetricAlertHistory {
+        id: Uuid::new_v4(),
+        alert_id: alert.id,
+        state: state.as_str().to_string(),
+        value: result.value,
+        baseline_mean: result.baseline_mean,
+        baseline_std_dev: result.baseline_std_dev,
+        message: result.message.clone(),
+        created_at: Utc::now(),
+    };
+
+    if let Err(e) = insert_into(metric_alert_history::table)
+        .values(&history)
+        .execute(&mut conn)
+        .await
+    {
+        return Err(anyhow!("Error inserting metric alert history: {}", e));
+    }
+
+    let current_state = match state {
+        AlertState::Firing => AlertState::Firing,
+        AlertState::Ok | AlertState::Resolved => AlertState::Ok,
+    };
+
+    match update(metric_alerts::table)
+        .filter(metric_alerts::id.eq(alert.id))
+        .set((
+            metric_alerts::state.eq(current_state.as_str()),
+            metric_alerts::last_value.eq(Some(result.value)),
+            metric_alerts::last_error.eq(None::<String>),
+        ))
+        .execute(&mut conn)
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!("Error updating metric alert: {}", e)),
+    }
+}
+
+async fn record_alert_error(alert_id: &Uuid, error: &str) -> Result<()> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    match update(metric_alerts::table)
+        .filter(metric_alerts::id.eq(alert_id))
+        .set(metric_alerts::last_error.eq(Some(error)))
+        .execute(&mut conn)
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!("Error updating metric alert: {}", e)),
+    }
+}
+
+/// The payload posted to an alert's webhook.
+#[derive(Serialize, Debug, Clone)]
+pub struct AlertNotification {
+    pub alert_id: Uuid,
+    pub alert_name: String,
+    pub thread_id: Uuid,
+    pub metric_name: String,
+    pub column: String,
+    pub state: AlertState,
+    pub value: f64,
+    pub baseline_mean: Option<f64>,
+    pub baseline_std_dev: Option<f64>,
+    pub message: String,
+    pub link: String,
+    pub evaluated_at: DateTime<Utc>,
+}
+
+impl AlertNotification {
+    fn new(
+        alert: &MetricAlert,
+        metric_name: &str,
+        state: &AlertState,
+        result: &ConditionResult,
+    ) -> Self {
+        AlertNotification {
+            alert_id: alert.id,
+            alert_name: alert.name.clone(),
+            thread_id: alert.thread_id,
+            metric_name: metric_name.to_string(),
+            column: alert.column_name.clone(),
+            state: *state,
+            value: result.value,
+            baseline_mean: result.baseline_mean,
+            baseline_std_dev: result.baseline_std_dev,
+            message: result.message.clone(),
+            link: format!(
+                "{}/app/metrics/{}",
+                env::var("BUSTER_URL").unwrap_or_default(),
+                alert.thread_id
+            ),
+            evaluated_at: Utc::now(),
+        }
+    }
+}
+
+async fn send_alert_email<S: EmailSender>(
+    alert: &MetricAlert,
+    notification: &AlertNotification,
+    sender: &S,
+) -> Result<()> {
+    if !has_permission_flag(&alert.user_id, PermissionFlag::EmailSlackEnabled).await? {
+        return Ok(());
+    }
+
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    let user = match users::table
+        .filter(users::id.eq(alert.user_id))
+        .select(User::as_select())
+        .first::<User>(&mut conn)
+        .await
+    {
+        Ok(user) => user,
+        Err(e) => return Err(anyhow!("Error getting alert owner: {}", e)),
+    };
+
+    let title = match notification.state {
+        AlertState::Resolved => format!("Resolved: {}", notification.alert_name),
+        _ => format!("Alert: {}", notification.alert_name),
+    };
+    let subtitle = format!("{} · {}", notification.metric_name, notification.column);
+
+    let rendered =
+        render_notification(&title, &subtitle, &notification.message, &notification.link);
+
+    sender
+        .send(&OutgoingEmail {
+            to: user.email,
+            subject: title,
+            html: rendered.html,
+            text: rendered.text,
+        })
+        .await
+}
+
+// Webhooks are limited to admins, so one set up by someone who has since lost the role stops firing.
+async fn send_alert_webhook(
+    alert: &MetricAlert,
+    webhook_url: &str,
+    notification: &AlertNotification,
+) -> Result<()> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    let owner = match users::table
+        .filter(users::id.eq(alert.user_id))
+        .select(User::as_select())
+        .first::<User>(&mut conn)
+        .await
+    {
+        Ok(owner) => owner,
+        Err(e) => return Err(anyhow!("Error getting alert owner: {}", e)),
+    };
+
+    if !is_user_workspace_admin_or_data_admin(&owner, &alert.organization_id).await? {
+        return Err(anyhow!("Only admins can send alerts to webhooks"));
+    }
+
+    // The host is checked again in case it changed since the alert was saved. Hosts are also
+    // filtered when the client resolves them, but IP addresses never go through the resolver.
+    let webhook_url = validate_webhook_url(webhook_url).await?;
+
+    let response = match webhook_client()
+        .post(webhook_url)
+        .json(notification)
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => return Err(anyhow!("Error calling alert webhook: {}", e)),
+    };
+
+    match response.status().is_success() {
+        true => Ok(()),
+        false => Err(anyhow!(
+            "Alert webhook responded with {}",
+            response.status()
+        )),
+    }
+}
@@ -0,0 +1,4 @@
+pub mod conditions;
+pub mod evaluator;
+pub mod rules;
+pub mod webhooks;
@@ -0,0 +1,33 @@
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+use crate::utils::reports::subscriptions::validate_report_schedule;
+
+use super::{conditions::AlertCondition, webhooks::validate_webhook_url};
+
+/// Validates an alert's settings before they are saved.
+pub async fn validate_alert_rule(
+    column_name: &str,
+    time_column_name: Option<&str>,
+    condition: &Value,
+    cron: &str,
+    timezone: &str,
+    webhook_url: Option<&str>,
+) -> Result<()> {
+    if column_name.trim().is_empty() {
+        return Err(anyhow!("Alert column is required"));
+    }
+
+    if time_column_name.is_some_and(|time_column_name| time_column_name.trim().is_empty()) {
+        return Err(anyhow!("Alert time column can't be empty"));
+    }
+
+    AlertCondition::from_value(condition)?;
+    validate_report_schedule(cron, timezone).await?;
+
+    if let Some(webhook_url) = webhook_url {
+        validate_webhook_url(webhook_url).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,172 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    redirect, Client,
+};
+use url::{Host, Url};
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+lazy_static! {
+    // Shared by every alert so connections are pooled. Redirects aren't followed, since a public
+    // webhook could otherwise bounce the request to an internal address.
+    static ref WEBHOOK_CLIENT: Client = Client::builder()
+        .timeout(WEBHOOK_TIMEOUT)
+        .redirect(redirect::Policy::none())
+        .dns_resolver(Arc::new(PublicResolver))
+        .build()
+        .expect("Failed to build the alert webhook client");
+}
+
+/// The client alert webhooks are posted with. It only connects to public addresses.
+pub fn webhook_client() -> &'static Client {
+    &WEBHOOK_CLIENT
+}
+
+/// Checks that a webhook URL is http(s) and only resolves to public addresses, so alerts can't be
+/// used to reach the API's own network or the cloud metadata service.
+pub async fn validate_webhook_url(webhook_url: &str) -> Result<Url> {
+    let url = match Url::parse(webhook_url) {
+        Ok(url) if url.scheme() == "https" || url.scheme() == "http" => url,
+        Ok(_) => return Err(anyhow!("Alert webhooks must use http or https")),
+        Err(e) => return Err(anyhow!("Invalid alert webhook URL: {}", e)),
+    };
+
+    let port = url.port_or_known_default().unwrap_or(443);
+
+    let addresses: Vec<IpAddr> = match url.host() {
+        Some(Host::Ipv4(ip)) => vec![IpAddr::V4(ip)],
+        Some(Host::Ipv6(ip)) => vec![IpAddr::V6(ip)],
+        Some(Host::Domain(domain)) => match tokio::net::lookup_host((domain, port)).await {
+            Ok(addresses) => addresses.map(|address| address.ip()).collect(),
+            Err(e) => return Err(anyhow!("Unable to resolve alert webhook host: {}", e)),
+        },
+        None => return Err(anyhow!("Alert webhook URL has no host")),
+    };
+
+    if addresses.is_empty() {
+        return Err(anyhow!("Unable to resolve alert webhook host"));
+    }
+
+    if addresses.iter().any(|ip| !is_public_address(ip)) {
+        return Err(anyhow!("Alert webhooks can't be sent to private addresses"));
+    }
+
+    Ok(url)
+}
+
+// Resolves webhook hosts and drops every non-public address, so a host that passed validation
+// can't later be pointed at an internal one.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addresses: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|address| is_public_address(&address.ip()))
+                .collect();
+
+            if addresses.is_empty() {
+                return Err(
+                    anyhow!("{} does not resolve to a public address", name.as_str()).into(),
+                );
+            }
+
+            let addresses: Addrs = Box::new(addresses.into_iter());
+            Ok(addresses)
+        })
+    }
+}
+
+fn is_public_address(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ipv4(&ip),
+            None => is_public_ipv6(ip),
+        },
+    }
+}
+
+fn is_public_ipv4(ip: &Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+
+    !(ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local() // Includes the 169.254.169.254 metadata service
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        || a == 0
+        || (a == 100 && (64..128).contains(&b)) // Carrier-grade NAT
+        || (a == 192 && b == 0 && c == 0) // IETF protocol assignments
+        || (a == 198 && (b == 18 || b == 19)) // Benchmarking
+        || a >= 240)
+}
+
+fn is_public_ipv6(ip: &Ipv6Addr) -> bool {
+    let first_segment = ip.segments()[0];
+
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || (first_segment & 0xfe00) == 0xfc00 // Unique local, including fd00:ec2::254
+        || (first_segment & 0xffc0) == 0xfe80 // Link local
+        || first_segment == 0x2001 && ip.segments()[1] == 0x0db8) // Documentation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_public_address() {
+        for ip in [
+            "127.0.0.1",
+            "10.0.0.8",
+            "172.16.4.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00:ec2::254",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_address(&ip.parse().unwrap()), "{}", ip);
+        }
+
+        for ip in ["8.8.8.8", "52.1.2.3", "2606:4700:4700::1111"] {
+            assert!(is_public_address(&ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_validate_webhook_url() {
+        assert!(validate_webhook_url("https://8.8.8.8/hooks/alerts")
+            .await
+            .is_ok());
+
+        assert!(validate_webhook_url("ftp://8.8.8.8/hooks").await.is_err());
+        assert!(validate_webhook_url("http://127.0.0.1:8080/internal")
+            .await
+            .is_err());
+        assert!(
+            validate_webhook_url("http://169.254.169.254/latest/meta-data")
+                .await
+                .is_err()
+        );
+        assert!(validate_webhook_url("http://[::1]/").await.is_err());
+        assert!(validate_webhook_url("http://localhost/").await.is_err());
+    }
+}
@@ -233,6 +233,7 @@ pub struct DotColumnSettings {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ColumnLabelFormat {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub style: Option<String>,
@@ -256,7 +257,7 @@ pub struct ColumnLabelFormat {
     pub replace_missing_data_with: Option<serde_json::Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub use_relative_time: Option<bool>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "isUTC", skip_serializing_if = "Option::is_none")]
     pub is_utc: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub make_label_human_readable: Option<bool>,
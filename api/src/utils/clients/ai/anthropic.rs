@@ -5,6 +5,7 @@ use tokio::sync::mpsc::{self, Receiver, Sender};
 use tokio_stream::wrappers::ReceiverStream;
 
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 use crate::utils::clients::sentry_utils::send_sentry_error;
 
@@ -144,6 +145,116 @@ pub async fn anthropic_chat(
     Ok(content)
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnthropicToolContent {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+    },
+}
+
+#[derive(Serialize, Clone)]
+pub struct AnthropicToolChatMessage {
+    pub role: AnthropicChatRole,
+    pub content: Vec<AnthropicToolContent>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct AnthropicTool {
+    pub name: String,
+    pub description: String,
+    pub input_schema: Value,
+}
+
+#[derive(Serialize, Clone)]
+pub struct AnthropicToolChatRequest {
+    pub model: AnthropicChatModel,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    pub messages: Vec<AnthropicToolChatMessage>,
+    pub tools: Vec<AnthropicTool>,
+    pub temperature: f32,
+    pub max_tokens: u32,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AnthropicToolChatResponse {
+    pub content: Vec<AnthropicToolContent>,
+}
+
+/// Sends a chat request with tools the model can call. Returns the content blocks of the
+/// response, which hold text and `tool_use` blocks.
+pub async fn anthropic_chat_with_tools(
+    model: &AnthropicChatModel,
+    system: Option<String>,
+    messages: Vec<AnthropicToolChatMessage>,
+    tools: Vec<AnthropicTool>,
+    temperature: f32,
+    max_tokens: u32,
+    timeout: u64,
+) -> Result<Vec<AnthropicToolContent>> {
+    let chat_request = AnthropicToolChatRequest {
+        model: model.clone(),
+        system,
+        messages,
+        tools,
+        temperature,
+        max_tokens,
+    };
+
+    let client = reqwest::Client::new();
+
+    let headers = {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            "x-api-key",
+            format!("{}", ANTHROPIC_API_KEY.to_string())
+                .parse()
+                .unwrap(),
+        );
+        headers.insert("anthropic-version", "2023-06-01".parse().unwrap());
+        headers
+    };
+
+    let response = match client
+        .post(ANTHROPIC_CHAT_URL)
+        .headers(headers)
+        .json(&chat_request)
+        .timeout(Duration::from_secs(timeout))
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            tracing::error!("Unable to send request to Anthropic: {:?}", e);
+            let err = anyhow!("Unable to send request to Anthropic: {}", e);
+            send_sentry_error(&err.to_string(), None);
+            return Err(err);
+        }
+    };
+
+    let completion_res = match response.json::<AnthropicToolChatResponse>().await {
+        Ok(res) => res,
+        Err(e) => {
+            tracing::error!("Unable to parse response from Anthropic: {:?}", e);
+            let err = anyhow!("Unable to parse response from Anthropic: {}", e);
+            send_sentry_error(&err.to_string(), None);
+            return Err(err);
+        }
+    };
+
+    Ok(completion_res.content)
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AnthropicChatDelta {
     #[serde(rename = "type")]
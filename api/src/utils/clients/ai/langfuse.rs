@@ -93,6 +93,8 @@ pub enum PromptName {
     GenerateDatasetDescription,
     SummaryQuestion,
     CustomPrompt(String),
+    // A prompt resolved through the prompt registry, tagged with the template that was used.
+    RegisteredPrompt { name: String, tags: Vec<String> },
 }
 
 impl PromptName {
@@ -121,6 +123,14 @@ impl PromptName {
             PromptName::GenerateDatasetDescription => "generate_dataset_description".to_string(),
             PromptName::SummaryQuestion => "summary_question".to_string(),
             PromptName::CustomPrompt(prompt) => prompt.clone(),
+            PromptName::RegisteredPrompt { name, .. } => name.clone(),
+        }
+    }
+
+    fn tags(&self) -> Vec<String> {
+        match self {
+            PromptName::RegisteredPrompt { tags, .. } => tags.clone(),
+            _ => vec![],
         }
     }
 }
@@ -296,7 +306,7 @@ async fn langfuse_handler(
             release: "1.0.0".to_string(),
             version: "1.0.0".to_string(),
             metadata: Metadata {},
-            tags: vec![],
+            tags: prompt_name.tags(),
             public: false,
         }),
         timestamp: Utc::now(),
@@ -13,13 +13,15 @@ use uuid::Uuid;
 
 use super::{
     anthropic::{
-        anthropic_chat, anthropic_chat_stream, AnthropicChatMessage, AnthropicChatModel,
-        AnthropicChatRole, AnthropicContent, AnthropicContentType,
+        anthropic_chat, anthropic_chat_stream, anthropic_chat_with_tools, AnthropicChatMessage,
+        AnthropicChatModel, AnthropicChatRole, AnthropicContent, AnthropicContentType,
+        AnthropicTool, AnthropicToolChatMessage, AnthropicToolContent,
     },
     langfuse::{send_langfuse_request, PromptName},
     openai::{
-        openai_chat, openai_chat_stream, OpenAiChatContent, OpenAiChatMessage, OpenAiChatModel,
-        OpenAiChatRole,
+        openai_chat, openai_chat_stream, openai_chat_with_tools, OpenAiChatContent,
+        OpenAiChatMessage, OpenAiChatModel, OpenAiChatRole, OpenAiFunction, OpenAiTool,
+        OpenAiToolCall, OpenAiToolCallFunction, OpenAiToolChatMessage,
     },
 };
 use lazy_static::lazy_static;
@@ -38,6 +40,18 @@ pub enum LlmModel {
     OpenAi(OpenAiChatModel),
 }
 
+impl LlmModel {
+    /// The number of tokens the model accepts across the prompt and its output.
+    pub fn context_window(&self) -> usize {
+        match self {
+            LlmModel::OpenAi(OpenAiChatModel::O3Mini) => 200_000,
+            LlmModel::OpenAi(OpenAiChatModel::Gpt4o) => 128_000,
+            LlmModel::OpenAi(OpenAiChatModel::Gpt35Turbo) => 16_385,
+            LlmModel::Anthropic(AnthropicChatModel::Claude3Opus20240229) => 200_000,
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum LlmRole {
     System,
@@ -63,6 +77,42 @@ impl LlmMessage {
     }
 }
 
+/// A tool the model can call. `parameters` is the JSON schema of the tool's arguments.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LlmTool {
+    pub name: String,
+    pub description: String,
+    pub parameters: Value,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LlmToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: Value,
+}
+
+/// A message in a conversation where the model can call tools.
+#[derive(Serialize, Deserialize, Clone)]
+pub enum LlmToolMessage {
+    Message(LlmMessage),
+    ToolCalls {
+        content: Option<String>,
+        tool_calls: Vec<LlmToolCall>,
+    },
+    ToolResult {
+        tool_call_id: String,
+        name: String,
+        content: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LlmToolResponse {
+    pub content: Option<String>,
+    pub tool_calls: Vec<LlmToolCall>,
+}
+
 pub async fn llm_chat(
     model: LlmModel,
     messages: &Vec<LlmMessage>,
@@ -120,6 +170,67 @@ pub async fn llm_chat(
     Ok(response)
 }
 
+pub async fn llm_chat_with_tools(
+    model: LlmModel,
+    messages: &Vec<LlmToolMessage>,
+    tools: &Vec<LlmTool>,
+    temperature: f32,
+    max_tokens: u32,
+    timeout: u64,
+    session_id: &Uuid,
+    user_id: &Uuid,
+    prompt_name: PromptName,
+) -> Result<LlmToolResponse> {
+    let start_time = Utc::now();
+
+    let response_result = match &model {
+        LlmModel::Anthropic(model) => {
+            anthropic_chat_with_tools_compiler(
+                model,
+                messages,
+                tools,
+                max_tokens,
+                temperature,
+                timeout,
+            )
+            .await
+        }
+        LlmModel::OpenAi(model) => {
+            openai_chat_with_tools_compiler(
+                model,
+                messages,
+                tools,
+                max_tokens,
+                temperature,
+                timeout,
+            )
+            .await
+        }
+    };
+
+    let response = match response_result {
+        Ok(response) => response,
+        Err(e) => return Err(anyhow!("LLM chat error: {}", e)),
+    };
+
+    let end_time = Utc::now();
+
+    send_langfuse_request(
+        session_id,
+        prompt_name,
+        None,
+        start_time,
+        end_time,
+        serde_json::to_string(&messages).unwrap(),
+        serde_json::to_string(&response).unwrap(),
+        user_id,
+        &model,
+    )
+    .await;
+
+    Ok(response)
+}
+
 pub async fn llm_chat_stream(
     model: LlmModel,
     messages: Vec<LlmMessage>,
@@ -381,3 +492,436 @@ async fn openai_chat_stream_compiler(
 
     Ok(stream)
 }
+
+async fn anthropic_chat_with_tools_compiler(
+    model: &AnthropicChatModel,
+    messages: &Vec<LlmToolMessage>,
+    tools: &Vec<LlmTool>,
+    max_tokens: u32,
+    temperature: f32,
+    timeout: u64,
+) -> Result<LlmToolResponse> {
+    let (system_message, anthropic_messages) = anthropic_tool_messages(messages);
+
+    let anthropic_tools = tools
+        .iter()
+        .map(|tool| AnthropicTool {
+            name: tool.name.clone(),
+            description: tool.description.clone(),
+            input_schema: tool.parameters.clone(),
+        })
+        .collect();
+
+    let response = match anthropic_chat_with_tools(
+        model,
+        system_message,
+        anthropic_messages,
+        anthropic_tools,
+        temperature,
+        max_tokens,
+        timeout,
+    )
+    .await
+    {
+        Ok(response) => response,
+        Err(e) => return Err(anyhow!("Anthropic chat error: {}", e)),
+    };
+
+    let mut text = Vec::new();
+    let mut tool_calls = Vec::new();
+
+    for block in response {
+        match block {
+            AnthropicToolContent::Text { text: content } => text.push(content),
+            AnthropicToolContent::ToolUse { id, name, input } => tool_calls.push(LlmToolCall {
+                id,
+                name,
+                arguments: input,
+            }),
+            AnthropicToolContent::ToolResult { .. } => (),
+        }
+    }
+
+    Ok(LlmToolResponse {
+        content: if text.is_empty() {
+            None
+        } else {
+            Some(text.join("\n"))
+        },
+        tool_calls,
+    })
+}
+
+/// Converts a tool conversation to Anthropic's format. The system prompt is passed separately and
+/// tool results are sent back as user messages.
+fn anthropic_tool_messages(
+    messages: &[LlmToolMessage],
+) -> (Option<String>, Vec<AnthropicToolChatMessage>) {
+    let mut system_message = None;
+    let mut anthropic_messages: Vec<AnthropicToolChatMessage> = Vec::new();
+
+    for message in messages {
+        let (role, content) = match message {
+            LlmToolMessage::Message(message) => {
+                let role = match message.role {
+                    LlmRole::System => {
+                        system_message = Some(message.content.clone());
+                        continue;
+                    }
+                    LlmRole::User => AnthropicChatRole::User,
+                    LlmRole::Assistant => AnthropicChatRole::Assistant,
+                };
+
+                (
+                    role,
+                    AnthropicToolContent::Text {
+                        text: message.content.clone(),
+                    },
+                )
+            }
+            LlmToolMessage::ToolCalls {
+                content,
+                tool_calls,
+            } => {
+                let mut blocks = Vec::new();
+
+                if let Some(content) = content {
+                    blocks.push(AnthropicToolContent::Text {
+                        text: content.clone(),
+                    });
+                }
+
+                for tool_call in tool_calls {
+                    blocks.push(AnthropicToolContent::ToolUse {
+                        id: tool_call.id.clone(),
+                        name: tool_call.name.clone(),
+                        input: tool_call.arguments.clone(),
+                    });
+                }
+
+                anthropic_messages.push(AnthropicToolChatMessage {
+                    role: AnthropicChatRole::Assistant,
+                    content: blocks,
+                });
+                continue;
+            }
+            LlmToolMessage::ToolResult {
+                tool_call_id,
+                content,
+                ..
+            } => (
+                AnthropicChatRole::User,
+                AnthropicToolContent::ToolResult {
+                    tool_use_id: tool_call_id.clone(),
+                    content: content.clone(),
+                },
+            ),
+        };
+
+        // Anthropic expects the results of parallel tool calls in a single user message.
+        if let (AnthropicToolContent::ToolResult { .. }, Some(last_message)) =
+            (&content, anthropic_messages.last_mut())
+        {
+            if matches!(last_message.role, AnthropicChatRole::User)
+                && matches!(
+                    last_message.content.last(),
+                    Some(AnthropicToolContent::ToolResult { .. })
+                )
+            {
+                last_message.content.push(content);
+                continue;
+            }
+        }
+
+        anthropic_messages.push(AnthropicToolChatMessage {
+            role,
+            content: vec![content],
+        });
+    }
+
+    (system_message, anthropic_messages)
+}
+
+async fn openai_chat_with_tools_compiler(
+    model: &OpenAiChatModel,
+    messages: &Vec<LlmToolMessage>,
+    tools: &Vec<LlmTool>,
+    max_tokens: u32,
+    temperature: f32,
+    timeout: u64,
+) -> Result<LlmToolResponse> {
+    let openai_messages = openai_tool_messages(messages);
+
+    let openai_tools = tools
+        .iter()
+        .map(|tool| OpenAiTool {
+            type_: "function".to_string(),
+            function: OpenAiFunction {
+                name: tool.name.clone(),
+                description: tool.description.clone(),
+                parameters: tool.parameters.clone(),
+            },
+        })
+        .collect();
+
+    let (content, openai_tool_calls) = match openai_chat_with_tools(
+        model,
+        openai_messages,
+        openai_tools,
+        temperature,
+        max_tokens,
+        timeout,
+    )
+    .await
+    {
+        Ok(response) => response,
+        Err(e) => return Err(anyhow!("OpenAI chat error: {}", e)),
+    };
+
+    let mut tool_calls = Vec::new();
+
+    for tool_call in openai_tool_calls {
+        let arguments = match serde_json::from_str::<Value>(&tool_call.function.arguments) {
+            Ok(arguments) => arguments,
+            Err(e) => {
+                return Err(anyhow!(
+                    "Unable to parse arguments for tool {}: {}",
+                    tool_call.function.name,
+                    e
+                ))
+            }
+        };
+
+        tool_calls.push(LlmToolCall {
+            id: tool_call.id,
+            name: tool_call.function.name,
+            arguments,
+        });
+    }
+
+    Ok(LlmToolResponse {
+        content,
+        tool_calls,
+    })
+}
+
+/// Converts a tool conversation to OpenAI's format, where tool results have their own role.
+fn openai_tool_messages(messages: &[LlmToolMessage]) -> Vec<OpenAiToolChatMessage> {
+    let mut openai_messages = Vec::new();
+
+    for message in messages {
+        let openai_message = match message {
+            LlmToolMessage::Message(message) => OpenAiToolChatMessage {
+                role: match message.role {
+                    LlmRole::System => OpenAiChatRole::System,
+                    LlmRole::User => OpenAiChatRole::User,
+                    LlmRole::Assistant => OpenAiChatRole::Assistant,
+                },
+                content: Some(message.content.clone()),
+                tool_calls: None,
+                tool_call_id: None,
+            },
+            LlmToolMessage::ToolCalls {
+                content,
+                tool_calls,
+            } => OpenAiToolChatMessage {
+                role: OpenAiChatRole::Assistant,
+                content: content.clone(),
+                tool_calls: Some(
+                    tool_calls
+                        .iter()
+                        .map(|tool_call| OpenAiToolCall {
+                            id: tool_call.id.clone(),
+                            type_: "function".to_string(),
+                            function: OpenAiToolCallFunction {
+                                name: tool_call.name.clone(),
+                                arguments: tool_call.arguments.to_string(),
+                            },
+                        })
+                        .collect(),
+                ),
+                tool_call_id: None,
+            },
+            LlmToolMessage::ToolResult {
+                tool_call_id,
+                content,
+                ..
+            } => OpenAiToolChatMessage {
+                role: OpenAiChatRole::Tool,
+                content: Some(content.clone()),
+                tool_calls: None,
+                tool_call_id: Some(tool_call_id.clone()),
+            },
+        };
+
+        openai_messages.push(openai_message);
+    }
+
+    openai_messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn tool_conversation() -> Vec<LlmToolMessage> {
+        vec![
+            LlmToolMessage::Message(LlmMessage::new(
+                "system".to_string(),
+                "You are an analyst.".to_string(),
+            )),
+            LlmToolMessage::Message(LlmMessage::new(
+                "user".to_string(),
+                "Revenue for ACME?".to_string(),
+            )),
+            LlmToolMessage::ToolCalls {
+                content: Some("Looking that up.".to_string()),
+                tool_calls: vec![
+                    LlmToolCall {
+                        id: "call_1".to_string(),
+                        name: "search_values".to_string(),
+                        arguments: json!({ "search_terms": ["ACME"] }),
+                    },
+                    LlmToolCall {
+                        id: "call_2".to_string(),
+                        name: "look_up_term".to_string(),
+                        arguments: json!({ "term": "revenue" }),
+                    },
+                ],
+            },
+            LlmToolMessage::ToolResult {
+                tool_call_id: "call_1".to_string(),
+                name: "search_values".to_string(),
+                content: "customers.name: ACME".to_string(),
+            },
+            LlmToolMessage::ToolResult {
+                tool_call_id: "call_2".to_string(),
+                name: "look_up_term".to_string(),
+                content: "revenue: sum of order totals".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_anthropic_tool_messages() {
+        let (system_message, messages) = anthropic_tool_messages(&tool_conversation());
+
+        assert_eq!(system_message.as_deref(), Some("You are an analyst."));
+
+        // Both tool results go back in one user message.
+        assert_eq!(
+            serde_json::to_value(&messages).unwrap(),
+            json!([
+                {
+                    "role": "user",
+                    "content": [{ "type": "text", "text": "Revenue for ACME?" }]
+                },
+                {
+                    "role": "assistant",
+                    "content": [
+                        { "type": "text", "text": "Looking that up." },
+                        {
+                            "type": "tool_use",
+                            "id": "call_1",
+                            "name": "search_values",
+                            "input": { "search_terms": ["ACME"] }
+                        },
+                        {
+                            "type": "tool_use",
+                            "id": "call_2",
+                            "name": "look_up_term",
+                            "input": { "term": "revenue" }
+                        }
+                    ]
+                },
+                {
+                    "role": "user",
+                    "content": [
+                        {
+                            "type": "tool_result",
+                            "tool_use_id": "call_1",
+                            "content": "customers.name: ACME"
+                        },
+                        {
+                            "type": "tool_result",
+                            "tool_use_id": "call_2",
+                            "content": "revenue: sum of order totals"
+                        }
+                    ]
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn test_anthropic_tool_messages_without_text() {
+        let (system_message, messages) = anthropic_tool_messages(&[LlmToolMessage::ToolCalls {
+            content: None,
+            tool_calls: vec![LlmToolCall {
+                id: "call_1".to_string(),
+                name: "generate_sql".to_string(),
+                arguments: json!({ "data_analyst_ticket": "revenue by month" }),
+            }],
+        }]);
+
+        assert!(system_message.is_none());
+        assert_eq!(
+            serde_json::to_value(&messages).unwrap(),
+            json!([{
+                "role": "assistant",
+                "content": [{
+                    "type": "tool_use",
+                    "id": "call_1",
+                    "name": "generate_sql",
+                    "input": { "data_analyst_ticket": "revenue by month" }
+                }]
+            }])
+        );
+    }
+
+    #[test]
+    fn test_openai_tool_messages() {
+        let messages = openai_tool_messages(&tool_conversation());
+
+        assert_eq!(
+            serde_json::to_value(&messages).unwrap(),
+            json!([
+                { "role": "system", "content": "You are an analyst." },
+                { "role": "user", "content": "Revenue for ACME?" },
+                {
+                    "role": "assistant",
+                    "content": "Looking that up.",
+                    "tool_calls": [
+                        {
+                            "id": "call_1",
+                            "type": "function",
+                            "function": {
+                                "name": "search_values",
+                                "arguments": "{\"search_terms\":[\"ACME\"]}"
+                            }
+                        },
+                        {
+                            "id": "call_2",
+                            "type": "function",
+                            "function": {
+                                "name": "look_up_term",
+                                "arguments": "{\"term\":\"revenue\"}"
+                            }
+                        }
+                    ]
+                },
+                {
+                    "role": "tool",
+                    "content": "customers.name: ACME",
+                    "tool_call_id": "call_1"
+                },
+                {
+                    "role": "tool",
+                    "content": "revenue: sum of order totals",
+                    "tool_call_id": "call_2"
+                }
+            ])
+        );
+    }
+}
@@ -1,4 +1,4 @@
-mod anthropic;
+pub mod anthropic;
 pub mod embedding_router;
 mod hugging_face;
 pub mod langfuse;
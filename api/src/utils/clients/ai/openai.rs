@@ -34,6 +34,7 @@ pub enum OpenAiChatRole {
     Developer,
     User,
     Assistant,
+    Tool,
 }
 
 #[derive(Serialize, Clone)]
@@ -226,6 +227,156 @@ pub async fn openai_chat(
     Ok(content)
 }
 
+#[derive(Serialize, Clone)]
+pub struct OpenAiFunction {
+    pub name: String,
+    pub description: String,
+    pub parameters: Value,
+}
+
+#[derive(Serialize, Clone)]
+pub struct OpenAiTool {
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub function: OpenAiFunction,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OpenAiToolCallFunction {
+    pub name: String,
+    // OpenAI returns the arguments as a JSON encoded string.
+    pub arguments: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OpenAiToolCall {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub function: OpenAiToolCallFunction,
+}
+
+#[derive(Serialize, Clone)]
+pub struct OpenAiToolChatMessage {
+    pub role: OpenAiChatRole,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<OpenAiToolCall>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct OpenAiToolChatRequest {
+    model: OpenAiChatModel,
+    messages: Vec<OpenAiToolChatMessage>,
+    tools: Vec<OpenAiTool>,
+    tool_choice: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reasoning_effort: Option<ReasoningEffort>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct OpenAiToolResponseMessage {
+    pub content: Option<String>,
+    pub tool_calls: Option<Vec<OpenAiToolCall>>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct OpenAiToolChoice {
+    pub message: OpenAiToolResponseMessage,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct OpenAiToolCompletionResponse {
+    pub choices: Vec<OpenAiToolChoice>,
+}
+
+/// Sends a chat request with tools the model can call. Returns the text content of the
+/// response along with any tool calls the model made.
+pub async fn openai_chat_with_tools(
+    model: &OpenAiChatModel,
+    messages: Vec<OpenAiToolChatMessage>,
+    tools: Vec<OpenAiTool>,
+    temperature: f32,
+    max_tokens: u32,
+    timeout: u64,
+) -> Result<(Option<String>, Vec<OpenAiToolCall>)> {
+    let (temperature, max_tokens, reasoning_effort) = if is_o3_model(model) {
+        (None, None, Some(ReasoningEffort::Low))
+    } else {
+        (Some(temperature), Some(max_tokens), None)
+    };
+
+    let chat_request = OpenAiToolChatRequest {
+        model: model.clone(),
+        messages,
+        tools,
+        tool_choice: "auto".to_string(),
+        temperature,
+        max_tokens,
+        reasoning_effort,
+    };
+
+    let client = reqwest::Client::new();
+
+    let headers = {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            reqwest::header::AUTHORIZATION,
+            format!("Bearer {}", OPENAI_API_KEY.to_string())
+                .parse()
+                .unwrap(),
+        );
+        headers
+    };
+
+    let response = match client
+        .post(OPENAI_CHAT_URL.to_string())
+        .headers(headers)
+        .json(&chat_request)
+        .timeout(Duration::from_secs(timeout))
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            tracing::error!("Unable to send request to OpenAI: {:?}", e);
+            let err = anyhow!("Unable to send request to OpenAI: {}", e);
+            send_sentry_error(&err.to_string(), None);
+            return Err(err);
+        }
+    };
+
+    let response_text = match response.text().await {
+        Ok(text) => text,
+        Err(e) => return Err(anyhow!("Unable to read response from OpenAI: {}", e)),
+    };
+
+    let completion_res = match serde_json::from_str::<OpenAiToolCompletionResponse>(&response_text)
+    {
+        Ok(res) => res,
+        Err(e) => {
+            tracing::error!("Unable to parse response from OpenAI: {:?}", e);
+            let err = anyhow!("Unable to parse response from OpenAI: {}", e);
+            send_sentry_error(&err.to_string(), None);
+            return Err(err);
+        }
+    };
+
+    let message = match completion_res.choices.into_iter().next() {
+        Some(choice) => choice.message,
+        None => return Err(anyhow!("No content returned from OpenAI")),
+    };
+
+    Ok((message.content, message.tool_calls.unwrap_or_default()))
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct OpenAiChatDelta {
     pub content: Option<String>,
@@ -1 +1,3 @@
 pub mod resend;
+pub mod sender;
+pub mod smtp;
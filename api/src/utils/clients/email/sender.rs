@@ -0,0 +1,120 @@
+use std::{env, future::Future};
+
+use anyhow::{anyhow, Result};
+use resend_rs::{types::CreateEmailBaseOptions, Resend};
+
+use super::smtp::SmtpSender;
+
+const DEFAULT_FROM: &str = "Buster <buster@mail.buster.so>";
+
+/// A single rendered email to one recipient.
+#[derive(Debug, Clone)]
+pub struct OutgoingEmail {
+    pub to: String,
+    pub subject: String,
+    pub html: String,
+    pub text: String,
+}
+
+pub trait EmailSender: Send + Sync {
+    fn send(&self, email: &OutgoingEmail) -> impl Future<Output = Result<()>> + Send;
+}
+
+pub struct ResendSender {
+    client: Resend,
+    from: String,
+}
+
+impl ResendSender {
+    pub fn new(api_key: &str, from: String) -> Self {
+        ResendSender {
+            client: Resend::new(api_key),
+            from,
+        }
+    }
+}
+
+impl EmailSender for ResendSender {
+    async fn send(&self, email: &OutgoingEmail) -> Result<()> {
+        let resend_email = CreateEmailBaseOptions::new(
+            self.from.clone(),
+            vec![email.to.clone()],
+            email.subject.clone(),
+        )
+        .with_html(&email.html)
+        .with_text(&email.text);
+
+        match self.client.emails.send(resend_email).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(anyhow!("Error sending email through Resend: {}", e)),
+        }
+    }
+}
+
+/// The email backend chosen by `EMAIL_BACKEND`: `resend` (the default) or `smtp`.
+pub enum EmailBackend {
+    Resend(ResendSender),
+    Smtp(SmtpSender),
+}
+
+impl EmailBackend {
+    pub fn from_env() -> Result<Self> {
+        let from = match env::var("EMAIL_FROM") {
+            Ok(from) if !from.is_empty() => from,
+            _ => DEFAULT_FROM.to_string(),
+        };
+        let backend = match env::var("EMAIL_BACKEND") {
+            Ok(backend) if !backend.is_empty() => backend,
+            _ => "resend".to_string(),
+        };
+
+        match backend.to_lowercase().as_str() {
+            "resend" => {
+                let api_key = match env::var("RESEND_API_KEY") {
+                    Ok(api_key) if !api_key.is_empty() => api_key,
+                    _ => return Err(anyhow!("RESEND_API_KEY must be set")),
+                };
+
+                Ok(EmailBackend::Resend(ResendSender::new(&api_key, from)))
+            }
+            "smtp" => {
+                let host = match env::var("SMTP_HOST") {
+                    Ok(host) if !host.is_empty() => host,
+                    _ => return Err(anyhow!("SMTP_HOST must be set")),
+                };
+
+                let port = match env::var("SMTP_PORT") {
+                    Ok(port) if !port.is_empty() => match port.parse::<u16>() {
+                        Ok(port) => port,
+                        Err(e) => return Err(anyhow!("Invalid SMTP_PORT: {}", e)),
+                    },
+                    _ => 587,
+                };
+
+                let credentials = match (env::var("SMTP_USERNAME"), env::var("SMTP_PASSWORD")) {
+                    (Ok(username), Ok(password)) if !username.is_empty() => {
+                        Some((username, password))
+                    }
+                    _ => None,
+                };
+
+                Ok(EmailBackend::Smtp(SmtpSender::new(
+                    host,
+                    port,
+                    credentials,
+                    from,
+                )?))
+            }
+            other => Err(anyhow!("Unsupported EMAIL_BACKEND: {}", other)),
+        }
+    }
+}
+
+impl EmailSender for EmailBackend {
+    async fn send(&self, email: &OutgoingEmail) -> Result<()> {
+        match self {
+            EmailBackend::Resend(sender) => sender.send(email).await,
+            EmailBackend::Smtp(sender) => sender.send(email).await,
+        }
+    }
+}
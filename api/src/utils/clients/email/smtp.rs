@@ -0,0 +1,164 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use lettre::{
+    message::{header::ContentType, Mailbox, MultiPart, SinglePart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+
+use super::sender::{EmailSender, OutgoingEmail};
+
+const SMTP_TIMEOUT: Duration = Duration::from_secs(30);
+const SMTPS_PORT: u16 = 465;
+
+/// Sends mail through an SMTP relay. The connection is always encrypted: port 465 uses implicit
+/// TLS and every other port has to support STARTTLS, so credentials never go out in plain text.
+pub struct SmtpSender {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpSender {
+    pub fn new(
+        host: String,
+        port: u16,
+        credentials: Option<(String, String)>,
+        from: String,
+    ) -> Result<Self> {
+        let builder = match port {
+            SMTPS_PORT => AsyncSmtpTransport::<Tokio1Executor>::relay(&host),
+            _ => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host),
+        };
+
+        let mut builder = match builder {
+            Ok(builder) => builder.port(port).timeout(Some(SMTP_TIMEOUT)),
+            Err(e) => return Err(anyhow!("Invalid SMTP host: {}", e)),
+        };
+
+        if let Some((username, password)) = credentials {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+
+        let from = match from.parse::<Mailbox>() {
+            Ok(from) => from,
+            Err(e) => return Err(anyhow!("Invalid EMAIL_FROM address: {}", e)),
+        };
+
+        Ok(SmtpSender {
+            transport: builder.build(),
+            from,
+        })
+    }
+
+    fn build_message(&self, email: &OutgoingEmail) -> Result<Message> {
+        let to = match email.to.parse::<Mailbox>() {
+            Ok(to) => to,
+            Err(e) => return Err(anyhow!("Invalid recipient address '{}': {}", email.to, e)),
+        };
+
+        // Subjects come from asset names, so line breaks are dropped rather than trusted to the
+        // header encoding.
+        let subject = email
+            .subject
+            .chars()
+            .filter(|c| *c != '\r' && *c != '\n')
+            .collect::<String>();
+
+        let body = MultiPart::alternative()
+            .singlepart(
+                SinglePart::builder()
+                    .header(ContentType::TEXT_PLAIN)
+                    .body(email.text.clone()),
+            )
+            .singlepart(
+                SinglePart::builder()
+                    .header(ContentType::TEXT_HTML)
+                    .body(email.html.clone()),
+            );
+
+        match Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(subject)
+            .multipart(body)
+        {
+            Ok(message) => Ok(message),
+            Err(e) => Err(anyhow!("Error building email: {}", e)),
+        }
+    }
+}
+
+impl EmailSender for SmtpSender {
+    async fn send(&self, email: &OutgoingEmail) -> Result<()> {
+        let message = self.build_message(email)?;
+
+        match self.transport.send(message).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(anyhow!("Error sending email over SMTP: {}", e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sender() -> SmtpSender {
+        SmtpSender::new(
+            "smtp.example.com".to_string(),
+            587,
+            Some(("reports".to_string(), "secret".to_string())),
+            "Buster <buster@mail.buster.so>".to_string(),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_build_smtp_message() {
+        let message = sender()
+            .build_message(&OutgoingEmail {
+                to: "ana@example.com".to_string(),
+                subject: "Weekly revenue".to_string(),
+                html: "<p>Revenue: 1,200</p>".to_string(),
+                text: "Revenue: 1,200".to_string(),
+            })
+            .unwrap();
+
+        let formatted = String::from_utf8(message.formatted()).unwrap();
+
+        assert!(formatted.contains("From: Buster <buster@mail.buster.so>\r\n"));
+        assert!(formatted.contains("To: ana@example.com\r\n"));
+        assert!(formatted.contains("Subject: Weekly revenue\r\n"));
+        assert!(formatted.contains("<p>Revenue: 1,200</p>"));
+        assert_eq!(
+            message.envelope().to(),
+            ["ana@example.com".parse().unwrap()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_smtp_headers_cannot_be_injected() {
+        let message = sender()
+            .build_message(&OutgoingEmail {
+                to: "ana@example.com".to_string(),
+                subject: "Revenue\r\nBcc: eve@example.com".to_string(),
+                html: String::new(),
+                text: String::new(),
+            })
+            .unwrap();
+
+        let formatted = String::from_utf8(message.formatted()).unwrap();
+        assert!(!formatted.contains("\r\nBcc:"));
+        assert_eq!(message.envelope().to().len(), 1);
+
+        assert!(sender()
+            .build_message(&OutgoingEmail {
+                to: "ana@example.com\r\nBcc: eve@example.com".to_string(),
+                subject: "Revenue".to_string(),
+                html: String::new(),
+                text: String::new(),
+            })
+            .is_err());
+    }
+}
@@ -0,0 +1,332 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ops::ControlFlow,
+};
+
+use anyhow::{anyhow, Result};
+use diesel::{ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+use serde_json::Value;
+use sqlparser::{
+    ast::{Ident, ObjectName, TableAlias, TableFactor, VisitMut, VisitorMut},
+    dialect::GenericDialect,
+    parser::Parser,
+};
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        lib::get_pg_pool,
+        schema::{dataset_columns, datasets},
+    },
+    utils::security::dataset_security::has_dataset_access,
+};
+
+use super::filters::get_dataset_table_names;
+
+/// Where a metric's SQL is pointed when its dataset is swapped for another with the same columns.
+#[derive(Debug, Clone)]
+pub struct DatasetRemap {
+    pub dataset_id: Uuid,
+    /// The names the source dataset's table can appear under in metric SQL.
+    pub source_table_names: Vec<String>,
+    /// The fully qualified table of the target dataset.
+    pub target_table: Vec<String>,
+}
+
+/// Checks a source to target dataset mapping and resolves the target tables. Every target has to
+/// be in the organization, readable by the user and have every column of its source dataset.
+pub async fn load_dataset_remaps(
+    user_id: &Uuid,
+    organization_id: &Uuid,
+    dataset_ids: &HashMap<Uuid, Uuid>,
+) -> Result<HashMap<Uuid, DatasetRemap>> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    let target_ids: Vec<Uuid> = dataset_ids.values().cloned().collect();
+    let source_ids: Vec<Uuid> = dataset_ids.keys().cloned().collect();
+
+    let targets = match datasets::table
+        .select((
+            datasets::id,
+            datasets::schema,
+            datasets::database_name,
+            datasets::database_identifier,
+        ))
+        .filter(datasets::id.eq_any(&target_ids))
+        .filter(datasets::organization_id.eq(organization_id))
+        .filter(datasets::deleted_at.is_null())
+        .load::<(Uuid, String, String, Option<String>)>(&mut conn)
+        .await
+    {
+        Ok(targets) => targets,
+        Err(e) => return Err(anyhow!("Error querying target datasets: {}", e)),
+    };
+
+    let columns = match dataset_columns::table
+        .select((dataset_columns::dataset_id, dataset_columns::name))
+        .filter(dataset_columns::dataset_id.eq_any(source_ids.iter().chain(target_ids.iter())))
+        .filter(dataset_columns::deleted_at.is_null())
+        .load::<(Uuid, String)>(&mut conn)
+        .await
+    {
+        Ok(columns) => columns,
+        Err(e) => return Err(anyhow!("Error querying dataset columns: {}", e)),
+    };
+
+    let mut columns_by_dataset: HashMap<Uuid, HashSet<String>> = HashMap::new();
+    for (dataset_id, name) in columns {
+        columns_by_dataset
+            .entry(dataset_id)
+            .or_default()
+            .insert(name.to_lowercase());
+    }
+
+    let source_table_names = get_dataset_table_names(&source_ids).await?;
+
+    let mut remaps = HashMap::new();
+
+    for (source_id, target_id) in dataset_ids {
+        let (_, schema, database_name, database_identifier) =
+            match targets.iter().find(|(id, ..)| id == target_id) {
+                Some(target) => target,
+                None => return Err(anyhow!("Dataset {} not found", target_id)),
+            };
+
+        if !has_dataset_access(user_id, target_id).await? {
+            return Err(anyhow!("Dataset {} not found", target_id));
+        }
+
+        let source_columns = columns_by_dataset
+            .get(source_id)
+            .cloned()
+            .unwrap_or_default();
+        let target_columns = columns_by_dataset
+            .get(target_id)
+            .cloned()
+            .unwrap_or_default();
+
+        let mut missing: Vec<&String> = source_columns.difference(&target_columns).collect();
+        if !missing.is_empty() {
+            missing.sort();
+            return Err(anyhow!(
+                "Dataset {} is missing columns of dataset {}: {}",
+                target_id,
+                source_id,
+                missing
+                    .iter()
+                    .map(|column| column.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(", ")
+            ));
+        }
+
+        let target_table = database_identifier
+            .iter()
+            .chain([schema, database_name])
+            .cloned()
+            .collect();
+
+        remaps.insert(
+            *source_id,
+            DatasetRemap {
+                dataset_id: *target_id,
+                source_table_names: source_table_names
+                    .get(source_id)
+                    .cloned()
+                    .unwrap_or_default(),
+                target_table,
+            },
+        );
+    }
+
+    Ok(remaps)
+}
+
+/// Points every read of the source dataset's table at the target table. Unaliased tables keep
+/// their old name as an alias so qualified column references still resolve.
+pub fn remap_dataset_sql(sql: &str, remap: &DatasetRemap) -> Result<String> {
+    let dialect = GenericDialect {};
+
+    let mut statements = match Parser::parse_sql(&dialect, sql) {
+        Ok(statements) => statements,
+        Err(e) => return Err(anyhow!("Unable to parse metric SQL: {}", e)),
+    };
+
+    let mut visitor = DatasetRemapVisitor {
+        remap,
+        remapped: false,
+    };
+
+    let _ = statements.visit(&mut visitor);
+
+    if !visitor.remapped {
+        return Err(anyhow!(
+            "Metric SQL does not read from the table of the dataset being replaced"
+        ));
+    }
+
+    Ok(statements
+        .iter()
+        .map(|statement| statement.to_string())
+        .collect::<Vec<String>>()
+        .join(";\n"))
+}
+
+struct DatasetRemapVisitor<'a> {
+    remap: &'a DatasetRemap,
+    remapped: bool,
+}
+
+impl VisitorMut for DatasetRemapVisitor<'_> {
+    type Break = ();
+
+    fn pre_visit_table_factor(
+        &mut self,
+        table_factor: &mut TableFactor,
+    ) -> ControlFlow<Self::Break> {
+        if let TableFactor::Table { name, alias, .. } = table_factor {
+            let table_name = match name.0.last() {
+                Some(table_name) => table_name.clone(),
+                None => return ControlFlow::Continue(()),
+            };
+
+            if self
+                .remap
+                .source_table_names
+                .iter()
+                .any(|candidate| candidate.eq_ignore_ascii_case(&table_name.value))
+            {
+                *name = ObjectName(
+                    self.remap
+                        .target_table
+                        .iter()
+                        .map(|part| Ident::new(part.as_str()))
+                        .collect(),
+                );
+
+                if alias.is_none() {
+                    *alias = Some(TableAlias {
+                        name: table_name,
+                        columns: vec![],
+                    });
+                }
+
+                self.remapped = true;
+            }
+        }
+
+        ControlFlow::Continue(())
+    }
+}
+
+/// Rewrites a copied dashboard config: layout items point at the copied threads and dashboard
+/// filter columns at the remapped datasets. Ids without a mapping are left as they are.
+pub fn remap_dashboard_config(
+    config: &Value,
+    thread_ids: &HashMap<Uuid, Uuid>,
+    dataset_ids: &HashMap<Uuid, Uuid>,
+) -> Value {
+    let mut config = config.clone();
+
+    let remap_id = |value: &mut Value, ids: &HashMap<Uuid, Uuid>| {
+        let new_id = value
+            .as_str()
+            .and_then(|id| Uuid::parse_str(id).ok())
+            .and_then(|id| ids.get(&id));
+
+        if let Some(new_id) = new_id {
+            *value = Value::String(new_id.to_string());
+        }
+    };
+
+    if let Some(rows) = config.get_mut("rows").and_then(Value::as_array_mut) {
+        for row in rows {
+            if let Some(items) = row.get_mut("items").and_then(Value::as_array_mut) {
+                for item in items {
+                    if let Some(id) = item.get_mut("id") {
+                        remap_id(id, thread_ids);
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(filters) = config.get_mut("filters").and_then(Value::as_array_mut) {
+        for filter in filters {
+            if let Some(columns) = filter.get_mut("columns").and_then(Value::as_array_mut) {
+                for column in columns {
+                    if let Some(dataset_id) = column.get_mut("dataset_id") {
+                        remap_id(dataset_id, dataset_ids);
+                    }
+                }
+            }
+        }
+    }
+
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_remap_dataset_sql() {
+        let remap = DatasetRemap {
+            dataset_id: Uuid::new_v4(),
+            source_table_names: vec!["Orders".to_string(), "orders".to_string()],
+            target_table: vec!["emea".to_string(), "orders".to_string()],
+        };
+
+        let sql = remap_dataset_sql(
+            "SELECT orders.region, sum(o.amount) FROM public.orders JOIN public.orders AS o ON o.id = orders.id GROUP BY orders.region",
+            &remap,
+        )
+        .unwrap();
+
+        assert_eq!(
+            sql,
+            "SELECT orders.region, sum(o.amount) FROM emea.orders AS orders JOIN emea.orders AS o ON o.id = orders.id GROUP BY orders.region"
+        );
+
+        assert!(remap_dataset_sql("SELECT * FROM customers", &remap).is_err());
+    }
+
+    #[test]
+    fn test_remap_dashboard_config() {
+        let (thread_id, new_thread_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let (dataset_id, new_dataset_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let other_thread_id = Uuid::new_v4();
+
+        let config = json!({
+            "rows": [{ "items": [{ "id": thread_id }, { "id": other_thread_id }], "columnSizes": [6, 6] }],
+            "filters": [{
+                "id": "region",
+                "type": "select",
+                "columns": [{ "dataset_id": dataset_id, "column": "region" }]
+            }]
+        });
+
+        let remapped = remap_dashboard_config(
+            &config,
+            &HashMap::from([(thread_id, new_thread_id)]),
+            &HashMap::from([(dataset_id, new_dataset_id)]),
+        );
+
+        assert_eq!(remapped["rows"][0]["items"][0]["id"], json!(new_thread_id));
+        assert_eq!(
+            remapped["rows"][0]["items"][1]["id"],
+            json!(other_thread_id)
+        );
+        assert_eq!(remapped["rows"][0]["columnSizes"], json!([6, 6]));
+        assert_eq!(
+            remapped["filters"][0]["columns"][0]["dataset_id"],
+            json!(new_dataset_id)
+        );
+    }
+}
@@ -0,0 +1,846 @@
+use std::{collections::HashMap, ops::ControlFlow};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use diesel::{ExpressionMethods, JoinOnDsl, QueryDsl};
+use diesel_async::RunQueryDsl;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use sqlparser::{
+    ast::{
+        BinaryOperator, Expr, Ident, ObjectName, Query, Select, SetExpr, TableFactor,
+        Value as SqlValue, VisitMut, VisitorMut,
+    },
+    dialect::GenericDialect,
+    parser::Parser,
+};
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        enums::DataSourceType,
+        lib::get_pg_pool,
+        schema::{data_sources, datasets},
+    },
+    utils::query_engine::sql_quoting::quote_string_literal,
+};
+
+/// A filter defined once on a dashboard and applied to every metric whose dataset it is mapped to.
+/// Filters live under `filters` in `dashboards.config`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DashboardFilter {
+    pub id: String,
+    pub label: Option<String>,
+    #[serde(rename = "type")]
+    pub type_: DashboardFilterType,
+    pub default: Option<Value>,
+    #[serde(default)]
+    pub columns: Vec<DashboardFilterColumn>,
+    /// In embedded views, the filter is locked to this attribute of the embed token's viewer.
+    #[serde(default)]
+    pub user_attribute: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DashboardFilterType {
+    DateRange,
+    Select,
+    Text,
+    Number,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DashboardFilterColumn {
+    pub dataset_id: Uuid,
+    pub column: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterCondition {
+    Range {
+        start: Option<Value>,
+        end: Option<Value>,
+    },
+    Equals(Value),
+    In(Vec<Value>),
+}
+
+/// A dashboard filter with the value it currently resolves to.
+#[derive(Debug, Clone)]
+pub struct ActiveFilter {
+    pub id: String,
+    pub columns: Vec<DashboardFilterColumn>,
+    pub condition: FilterCondition,
+}
+
+/// Reads the filter definitions from a dashboard config. A config without `filters` has none.
+pub fn parse_dashboard_filters(config: &Value) -> Result<Vec<DashboardFilter>> {
+    let filters = match config.get("filters") {
+        Some(Value::Null) | None => return Ok(Vec::new()),
+        Some(filters) => filters.clone(),
+    };
+
+    let filters: Vec<DashboardFilter> = match serde_json::from_value(filters) {
+        Ok(filters) => filters,
+        Err(e) => return Err(anyhow!("Invalid dashboard filters: {}", e)),
+    };
+
+    for filter in &filters {
+        for column in &filter.columns {
+            if !is_plain_identifier(&column.column) {
+                return Err(anyhow!(
+                    "Dashboard filter '{}' maps to an invalid column '{}'",
+                    filter.id,
+                    column.column
+                ));
+            }
+        }
+    }
+
+    Ok(filters)
+}
+
+/// Resolves each filter to the value requested by the client, falling back to its default.
+/// An explicit `null` clears a filter. Filters without a value are left out.
+pub fn resolve_active_filters(
+    filters: &[DashboardFilter],
+    values: Option<&HashMap<String, Value>>,
+) -> Result<Vec<ActiveFilter>> {
+    let mut active_filters = Vec::new();
+
+    for filter in filters {
+        let value = match values.and_then(|values| values.get(&filter.id)) {
+            Some(value) => value,
+            None => match &filter.default {
+                Some(default) => default,
+                None => continue,
+            },
+        };
+
+        if let Some(condition) = filter_condition(filter, value)? {
+            active_filters.push(ActiveFilter {
+                id: filter.id.clone(),
+                columns: filter.columns.clone(),
+                condition,
+            });
+        }
+    }
+
+    Ok(active_filters)
+}
+
+/// Builds the filter values for an embedded view. The viewer can only set the filters the token
+/// leaves `open`, and never to a value with a quote or backslash in it. Filters set in `locked`
+/// and filters bound to one of the viewer's `user_attributes` are locked to that value. Returns the
+/// values to resolve and the ids of the locked filters. A filter bound to an attribute the viewer
+/// doesn't have, or locked to no value, is an error rather than being left open.
+pub fn lock_filter_values(
+    filters: &[DashboardFilter],
+    requested: Option<&HashMap<String, Value>>,
+    locked: &HashMap<String, Value>,
+    open: &[String],
+    user_attributes: &HashMap<String, Value>,
+) -> Result<(HashMap<String, Value>, Vec<String>)> {
+    let mut values = HashMap::new();
+    let mut locked_ids = Vec::new();
+
+    for id in locked.keys() {
+        if !filters.iter().any(|filter| &filter.id == id) {
+            return Err(anyhow!("Dashboard has no filter '{}' to lock", id));
+        }
+    }
+
+    for id in open {
+        match filters.iter().find(|filter| &filter.id == id) {
+            Some(filter) if locked.contains_key(id) || filter.user_attribute.is_some() => {
+                return Err(anyhow!("Dashboard filter '{}' is locked", id))
+            }
+            Some(_) => (),
+            None => return Err(anyhow!("Dashboard has no filter '{}' to open", id)),
+        }
+    }
+
+    for (id, value) in requested.into_iter().flatten() {
+        if !open.contains(id) {
+            return Err(anyhow!("Dashboard filter '{}' can't be changed", id));
+        }
+
+        if has_escape_characters(value) {
+            return Err(anyhow!(
+                "Dashboard filter '{}' does not accept the value {}",
+                id,
+                value
+            ));
+        }
+
+        values.insert(id.clone(), value.clone());
+    }
+
+    for filter in filters {
+        let value = match (&filter.user_attribute, locked.get(&filter.id)) {
+            (_, Some(value)) => value.clone(),
+            (Some(attribute), None) => match user_attributes.get(attribute) {
+                Some(value) => value.clone(),
+                None => {
+                    return Err(anyhow!(
+                        "Dashboard filter '{}' requires the '{}' attribute",
+                        filter.id,
+                        attribute
+                    ))
+                }
+            },
+            (None, None) => continue,
+        };
+
+        if filter_condition(filter, &value)?.is_none() {
+            return Err(anyhow!(
+                "Locked dashboard filter '{}' has no value",
+                filter.id
+            ));
+        }
+
+        values.insert(filter.id.clone(), value);
+        locked_ids.push(filter.id.clone());
+    }
+
+    Ok((values, locked_ids))
+}
+
+// Viewer supplied values are rendered into SQL, so anything that could end a string literal is
+// refused outright rather than relying on escaping alone.
+fn has_escape_characters(value: &Value) -> bool {
+    match value {
+        Value::String(s) => s.contains('\\') || s.contains('\''),
+        Value::Array(values) => values.iter().any(has_escape_characters),
+        Value::Object(values) => values.values().any(has_escape_characters),
+        _ => false,
+    }
+}
+
+fn filter_condition(filter: &DashboardFilter, value: &Value) -> Result<Option<FilterCondition>> {
+    let condition = match (filter.type_, value) {
+        (_, Value::Null) => None,
+        (DashboardFilterType::DateRange, Value::Object(range)) => {
+            let bound = |key: &str| match range.get(key) {
+                Some(Value::String(s)) if s.is_empty() => Ok(None),
+                Some(Value::String(s)) if is_date_value(s) => Ok(Some(Value::String(s.clone()))),
+                Some(Value::Null) | None => Ok(None),
+                Some(other) => Err(anyhow!(
+                    "Dashboard filter '{}' has an invalid {} date: {}",
+                    filter.id,
+                    key,
+                    other
+                )),
+            };
+
+            match (bound("start")?, bound("end")?) {
+                (None, None) => None,
+                (start, end) => Some(FilterCondition::Range { start, end }),
+            }
+        }
+        (DashboardFilterType::Select, Value::Array(options)) => {
+            if options.iter().any(|option| !is_scalar(option)) {
+                return Err(anyhow!(
+                    "Dashboard filter '{}' has an invalid option list",
+                    filter.id
+                ));
+            }
+
+            match options.is_empty() {
+                true => None,
+                false => Some(FilterCondition::In(options.clone())),
+            }
+        }
+        (DashboardFilterType::Select, value) if is_scalar(value) => {
+            Some(FilterCondition::Equals(value.clone()))
+        }
+        (DashboardFilterType::Text, Value::String(s)) => match s.is_empty() {
+            true => None,
+            false => Some(FilterCondition::Equals(value.clone())),
+        },
+        (DashboardFilterType::Number, Value::Number(_)) => {
+            Some(FilterCondition::Equals(value.clone()))
+        }
+        (_, value) => {
+            return Err(anyhow!(
+                "Dashboard filter '{}' does not accept the value {}",
+                filter.id,
+                value
+            ))
+        }
+    };
+
+    Ok(condition)
+}
+
+/// A stable key for the set of applied filter values, so cached metric results for one filter
+/// state are never served for another. `None` when no filters are applied.
+pub fn filter_cache_key(active_filters: &[ActiveFilter]) -> Option<String> {
+    if active_filters.is_empty() {
+        return None;
+    }
+
+    let mut entries: Vec<(&String, &FilterCondition)> = active_filters
+        .iter()
+        .map(|filter| (&filter.id, &filter.condition))
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+
+    let canonical = serde_json::to_string(&entries).unwrap_or_default();
+
+    // The key is persisted with cached results, so it has to hash the same across releases.
+    let digest = Sha256::digest(canonical.as_bytes());
+
+    Some(format!("{:x}", digest)[..16].to_string())
+}
+
+/// Loads the table names each dataset can appear under in metric SQL.
+pub async fn get_dataset_table_names(dataset_ids: &[Uuid]) -> Result<HashMap<Uuid, Vec<String>>> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    let dataset_records = match datasets::table
+        .select((datasets::id, datasets::name, datasets::database_name))
+        .filter(datasets::id.eq_any(dataset_ids))
+        .load::<(Uuid, String, String)>(&mut conn)
+        .await
+    {
+        Ok(records) => records,
+        Err(e) => return Err(anyhow!("Error querying datasets: {}", e)),
+    };
+
+    Ok(dataset_records
+        .into_iter()
+        .map(|(id, name, database_name)| (id, vec![name, database_name]))
+        .collect())
+}
+
+/// Loads the type of data source each dataset's metrics run against, which decides how filter
+/// values are written into their SQL.
+pub async fn get_dataset_data_source_types(
+    dataset_ids: &[Uuid],
+) -> Result<HashMap<Uuid, DataSourceType>> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    let data_source_types = match datasets::table
+        .inner_join(data_sources::table.on(datasets::data_source_id.eq(data_sources::id)))
+        .select((datasets::id, data_sources::type_))
+        .filter(datasets::id.eq_any(dataset_ids))
+        .load::<(Uuid, DataSourceType)>(&mut conn)
+        .await
+    {
+        Ok(data_source_types) => data_source_types,
+        Err(e) => return Err(anyhow!("Error querying dataset data sources: {}", e)),
+    };
+
+    Ok(data_source_types.into_iter().collect())
+}
+
+/// Rewrites a metric's SQL so every `SELECT` reading the dataset's table is restricted by the
+/// filters mapped to that dataset. SQL is returned untouched when no filter applies to it.
+pub fn apply_dashboard_filters(
+    sql: &str,
+    dataset_id: &Uuid,
+    table_names: &[String],
+    data_source_type: &DataSourceType,
+    active_filters: &[ActiveFilter],
+) -> Result<String> {
+    let predicates: Vec<(&str, &FilterCondition)> = active_filters
+        .iter()
+        .flat_map(|filter| {
+            filter
+                .columns
+                .iter()
+                .filter(|column| &column.dataset_id == dataset_id)
+                .map(move |column| (column.column.as_str(), &filter.condition))
+        })
+        .collect();
+
+    if predicates.is_empty() {
+        return Ok(sql.to_string());
+    }
+
+    let dialect = GenericDialect {};
+
+    let mut statements = match Parser::parse_sql(&dialect, sql) {
+        Ok(statements) => statements,
+        Err(e) => return Err(anyhow!("Unable to parse metric SQL: {}", e)),
+    };
+
+    let mut visitor = DashboardFilterVisitor {
+        table_names,
+        data_source_type,
+        predicates: &predicates,
+        applied: false,
+        error: None,
+    };
+
+    let _ = statements.visit(&mut visitor);
+
+    if let Some(e) = visitor.error {
+        return Err(e);
+    }
+
+    if !visitor.applied {
+        return Err(anyhow!(
+            "Metric SQL does not read from the table the dashboard filters are mapped to"
+        ));
+    }
+
+    Ok(statements
+        .iter()
+        .map(|statement| statement.to_string())
+        .collect::<Vec<String>>()
+        .join(";\n"))
+}
+
+struct DashboardFilterVisitor<'a> {
+    table_names: &'a [String],
+    data_source_type: &'a DataSourceType,
+    predicates: &'a [(&'a str, &'a FilterCondition)],
+    applied: bool,
+    error: Option<anyhow::Error>,
+}
+
+impl VisitorMut for DashboardFilterVisitor<'_> {
+    type Break = ();
+
+    // Subqueries and CTEs are visited as their own queries, so each SELECT is filtered where the
+    // table is actually read.
+    fn pre_visit_query(&mut self, query: &mut Query) -> ControlFlow<Self::Break> {
+        match self.filter_set_expr(&mut query.body) {
+            Ok(_) => ControlFlow::Continue(()),
+            Err(e) => {
+                self.error = Some(e);
+                ControlFlow::Break(())
+            }
+        }
+    }
+}
+
+impl DashboardFilterVisitor<'_> {
+    fn filter_set_expr(&mut self, set_expr: &mut SetExpr) -> Result<()> {
+        match set_expr {
+            SetExpr::Select(select) => self.filter_select(select),
+            SetExpr::SetOperation { left, right, .. } => {
+                self.filter_set_expr(left)?;
+                self.filter_set_expr(right)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn filter_select(&mut self, select: &mut Select) -> Result<()> {
+        let mut qualifiers = Vec::new();
+
+        for table in &select.from {
+            let relations =
+                std::iter::once(&table.relation).chain(table.joins.iter().map(|j| &j.relation));
+
+            for relation in relations {
+                if let Some(qualifier) = self.matching_qualifier(relation) {
+                    qualifiers.push(qualifier);
+                }
+            }
+        }
+
+        for qualifier in qualifiers {
+            for (column, condition) in self.predicates {
+                let mut column_idents = qualifier.clone();
+                column_idents.push(Ident::new(*column));
+
+                let predicate = condition_expr(
+                    Expr::CompoundIdentifier(column_idents),
+                    condition,
+                    self.data_source_type,
+                )?;
+
+                select.selection = Some(match select.selection.take() {
+                    Some(existing) => Expr::BinaryOp {
+                        left: Box::new(nest(existing)),
+                        op: BinaryOperator::And,
+                        right: Box::new(predicate),
+                    },
+                    None => predicate,
+                });
+            }
+
+            self.applied = true;
+        }
+
+        Ok(())
+    }
+
+    fn matching_qualifier(&self, relation: &TableFactor) -> Option<Vec<Ident>> {
+        let (ObjectName(name), alias) = match relation {
+            TableFactor::Table { name, alias, .. } => (name, alias),
+            _ => return None,
+        };
+
+        let table_name = name.last()?;
+
+        if !self
+            .table_names
+            .iter()
+            .any(|candidate| candidate.eq_ignore_ascii_case(&table_name.value))
+        {
+            return None;
+        }
+
+        match alias {
+            Some(alias) => Some(vec![alias.name.clone()]),
+            None => Some(name.clone()),
+        }
+    }
+}
+
+fn nest(expr: Expr) -> Expr {
+    match expr {
+        Expr::BinaryOp {
+            op: BinaryOperator::Or,
+            ..
+        } => Expr::Nested(Box::new(expr)),
+        expr => expr,
+    }
+}
+
+fn condition_expr(
+    column: Expr,
+    condition: &FilterCondition,
+    data_source_type: &DataSourceType,
+) -> Result<Expr> {
+    let compare = |op: BinaryOperator, value: &Value| -> Result<Expr> {
+        Ok(Expr::BinaryOp {
+            left: Box::new(column.clone()),
+            op,
+            right: Box::new(Expr::Value(sql_literal(value, data_source_type)?)),
+        })
+    };
+
+    match condition {
+        FilterCondition::Equals(value) => compare(BinaryOperator::Eq, value),
+        FilterCondition::In(values) => Ok(Expr::InList {
+            expr: Box::new(column.clone()),
+            list: values
+                .iter()
+                .map(|value| sql_literal(value, data_source_type).map(Expr::Value))
+                .collect::<Result<Vec<Expr>>>()?,
+            negated: false,
+        }),
+        FilterCondition::Range { start, end } => {
+            let bounds = [
+                start
+                    .as_ref()
+                    .map(|start| compare(BinaryOperator::GtEq, start)),
+                end.as_ref().map(|end| compare(BinaryOperator::LtEq, end)),
+            ];
+
+            let mut range_expr: Option<Expr> = None;
+
+            for bound in bounds.into_iter().flatten() {
+                let bound = bound?;
+                range_expr = Some(match range_expr {
+                    Some(existing) => Expr::BinaryOp {
+                        left: Box::new(existing),
+                        op: BinaryOperator::And,
+                        right: Box::new(bound),
+                    },
+                    None => bound,
+                });
+            }
+
+            match range_expr {
+                Some(range_expr) => Ok(range_expr),
+                None => Err(anyhow!("Date range filter has no bounds")),
+            }
+        }
+    }
+}
+
+fn sql_literal(value: &Value, data_source_type: &DataSourceType) -> Result<SqlValue> {
+    match value {
+        // sqlparser only doubles quotes when printing a string, which doesn't stop a backslash from
+        // escaping the closing quote on warehouses that treat it as an escape character. The
+        // literal is written for the data source instead and printed as is.
+        Value::String(s) => Ok(SqlValue::Placeholder(quote_string_literal(s, data_source_type))),
+        Value::Number(n) => Ok(SqlValue::Number(n.to_string(), false)),
+        Value::Bool(b) => Ok(SqlValue::Boolean(*b)),
+        _ => Err(anyhow!("Unsupported filter value: {}", value)),
+    }
+}
+
+fn is_date_value(value: &str) -> bool {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok()
+        || DateTime::parse_from_rfc3339(value).is_ok()
+        || NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").is_ok()
+        || NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S").is_ok()
+}
+
+fn is_scalar(value: &Value) -> bool {
+    matches!(value, Value::String(_) | Value::Number(_) | Value::Bool(_))
+}
+
+fn is_plain_identifier(column: &str) -> bool {
+    let mut chars = column.chars();
+
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => (),
+        _ => return false,
+    }
+
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn applies_dashboard_filters_to_matching_tables() {
+        let orders_id = Uuid::new_v4();
+        let customers_id = Uuid::new_v4();
+
+        let config = json!({
+            "rows": [],
+            "filters": [
+                {
+                    "id": "period",
+                    "type": "date_range",
+                    "default": { "start": "2024-01-01", "end": "2024-03-31" },
+                    "columns": [{ "dataset_id": orders_id, "column": "created_at" }]
+                },
+                {
+                    "id": "region",
+                    "type": "select",
+                    "columns": [{ "dataset_id": customers_id, "column": "region" }]
+                }
+            ]
+        });
+
+        let filters = parse_dashboard_filters(&config).unwrap();
+
+        let values = HashMap::from([("region".to_string(), json!(["EMEA", "O'Hare"]))]);
+        let active_filters = resolve_active_filters(&filters, Some(&values)).unwrap();
+        assert_eq!(active_filters.len(), 2);
+
+        let sql = "SELECT c.region, SUM(o.amount) FROM public.orders o \
+                   JOIN customers c ON o.customer_id = c.id \
+                   WHERE o.status = 'paid' OR o.status = 'refunded' GROUP BY c.region";
+
+        let orders_sql = apply_dashboard_filters(
+            sql,
+            &orders_id,
+            &["orders".to_string()],
+            &DataSourceType::Postgres,
+            &active_filters,
+        )
+        .unwrap();
+        assert_eq!(
+            orders_sql,
+            "SELECT c.region, SUM(o.amount) FROM public.orders AS o \
+             JOIN customers AS c ON o.customer_id = c.id \
+             WHERE (o.status = 'paid' OR o.status = 'refunded') \
+             AND o.created_at >= '2024-01-01' AND o.created_at <= '2024-03-31' \
+             GROUP BY c.region"
+        );
+
+        let customers_sql = apply_dashboard_filters(
+            "SELECT region, COUNT(*) FROM (SELECT * FROM customers) AS sub GROUP BY region",
+            &customers_id,
+            &["customers".to_string()],
+            &DataSourceType::Postgres,
+            &active_filters,
+        )
+        .unwrap();
+        assert_eq!(
+            customers_sql,
+            "SELECT region, COUNT(*) FROM (SELECT * FROM customers \
+             WHERE customers.region IN ('EMEA', 'O''Hare')) AS sub GROUP BY region"
+        );
+
+        assert!(apply_dashboard_filters(
+            "SELECT * FROM invoices",
+            &orders_id,
+            &["orders".to_string()],
+            &DataSourceType::Postgres,
+            &active_filters,
+        )
+        .is_err());
+
+        let cleared = HashMap::from([("period".to_string(), Value::Null)]);
+        let cleared_filters = resolve_active_filters(&filters, Some(&cleared)).unwrap();
+        assert!(cleared_filters.is_empty());
+        assert_eq!(filter_cache_key(&cleared_filters), None);
+        assert_ne!(
+            filter_cache_key(&active_filters),
+            filter_cache_key(&resolve_active_filters(&filters, None).unwrap())
+        );
+    }
+
+    #[test]
+    fn writes_filter_values_for_the_data_source() {
+        let dataset_id = Uuid::new_v4();
+
+        let config = json!({
+            "filters": [
+                {
+                    "id": "customer",
+                    "type": "text",
+                    "columns": [{ "dataset_id": dataset_id, "column": "customer_id" }]
+                },
+                {
+                    "id": "period",
+                    "type": "date_range",
+                    "columns": [{ "dataset_id": dataset_id, "column": "created_at" }]
+                }
+            ]
+        });
+
+        let filters = parse_dashboard_filters(&config).unwrap();
+
+        let values = HashMap::from([("customer".to_string(), json!("\\' OR 1=1 --"))]);
+        let active_filters = resolve_active_filters(&filters, Some(&values)).unwrap();
+
+        let filtered_sql = |data_source_type: DataSourceType| {
+            apply_dashboard_filters(
+                "SELECT * FROM orders",
+                &dataset_id,
+                &["orders".to_string()],
+                &data_source_type,
+                &active_filters,
+            )
+            .unwrap()
+        };
+
+        assert_eq!(
+            filtered_sql(DataSourceType::Postgres),
+            "SELECT * FROM orders WHERE orders.customer_id = '\\'' OR 1=1 --'"
+        );
+        assert_eq!(
+            filtered_sql(DataSourceType::MySql),
+            "SELECT * FROM orders WHERE orders.customer_id = '\\\\\\' OR 1=1 --'"
+        );
+        assert_eq!(
+            filtered_sql(DataSourceType::BigQuery),
+            filtered_sql(DataSourceType::Databricks)
+        );
+
+        // Date range bounds have to be dates.
+        let values = HashMap::from([(
+            "period".to_string(),
+            json!({ "start": "2024-01-01' OR '1'='1" }),
+        )]);
+        assert!(resolve_active_filters(&filters, Some(&values)).is_err());
+        let values = HashMap::from([(
+            "period".to_string(),
+            json!({ "start": "2024-01-01", "end": "2024-03-31T23:59:59Z" }),
+        )]);
+        assert!(resolve_active_filters(&filters, Some(&values)).is_ok());
+    }
+
+    #[test]
+    fn filter_cache_key_is_stable() {
+        let active_filters = vec![ActiveFilter {
+            id: "region".to_string(),
+            columns: Vec::new(),
+            condition: FilterCondition::Equals(json!("EMEA")),
+        }];
+
+        assert_eq!(
+            filter_cache_key(&active_filters),
+            Some(
+                format!(
+                    "{:x}",
+                    Sha256::digest(r#"[["region",{"equals":"EMEA"}]]"#.as_bytes())
+                )[..16]
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn locks_embedded_filter_values() {
+        let dataset_id = Uuid::new_v4();
+
+        let config = json!({
+            "filters": [
+                {
+                    "id": "customer",
+                    "type": "select",
+                    "user_attribute": "customer_id",
+                    "columns": [{ "dataset_id": dataset_id, "column": "customer_id" }]
+                },
+                {
+                    "id": "region",
+                    "type": "select",
+                    "columns": [{ "dataset_id": dataset_id, "column": "region" }]
+                },
+                {
+                    "id": "period",
+                    "type": "date_range",
+                    "columns": [{ "dataset_id": dataset_id, "column": "created_at" }]
+                }
+            ]
+        });
+
+        let filters = parse_dashboard_filters(&config).unwrap();
+
+        let locked = HashMap::from([("region".to_string(), json!("EMEA"))]);
+        let open = vec!["period".to_string()];
+        let attributes = HashMap::from([("customer_id".to_string(), json!("acme"))]);
+
+        let requested = HashMap::from([("period".to_string(), json!({ "start": "2024-01-01" }))]);
+        let (values, mut locked_ids) =
+            lock_filter_values(&filters, Some(&requested), &locked, &open, &attributes).unwrap();
+        locked_ids.sort();
+
+        assert_eq!(values["customer"], json!("acme"));
+        assert_eq!(values["region"], json!("EMEA"));
+        assert_eq!(values["period"], json!({ "start": "2024-01-01" }));
+        assert_eq!(locked_ids, vec!["customer", "region"]);
+
+        // Only the filters the token leaves open can be set by the viewer.
+        let requested = HashMap::from([("customer".to_string(), json!("someone-else"))]);
+        assert!(
+            lock_filter_values(&filters, Some(&requested), &locked, &open, &attributes).is_err()
+        );
+        let unopened = HashMap::from([("period".to_string(), json!({ "start": "2024-01-01" }))]);
+        assert!(lock_filter_values(&filters, Some(&unopened), &locked, &[], &attributes).is_err());
+        // Open filters still can't be set to anything that could end a string literal.
+        let region_open = vec!["period".to_string(), "region".to_string()];
+        assert!(
+            lock_filter_values(&filters, None, &HashMap::new(), &region_open, &attributes).is_ok()
+        );
+        let escaped = HashMap::from([("region".to_string(), json!(["EMEA", "\\' OR 1=1 --"]))]);
+        assert!(lock_filter_values(
+            &filters,
+            Some(&escaped),
+            &HashMap::new(),
+            &region_open,
+            &attributes
+        )
+        .is_err());
+        // A filter can't be both locked and open.
+        assert!(lock_filter_values(&filters, None, &locked, &region_open, &attributes).is_err());
+        let customer_open = vec!["customer".to_string()];
+        assert!(lock_filter_values(&filters, None, &locked, &customer_open, &attributes).is_err());
+
+        // A viewer without the bound attribute sees nothing rather than every customer.
+        assert!(lock_filter_values(&filters, None, &locked, &open, &HashMap::new()).is_err());
+        // Locking a filter the dashboard doesn't have is rejected.
+        let unknown = HashMap::from([("segment".to_string(), json!("smb"))]);
+        assert!(lock_filter_values(&filters, None, &unknown, &open, &attributes).is_err());
+        // Neither can a locked filter be cleared.
+        let cleared = HashMap::from([("region".to_string(), Value::Null)]);
+        assert!(lock_filter_values(&filters, None, &cleared, &open, &attributes).is_err());
+    }
+}
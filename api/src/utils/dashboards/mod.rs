@@ -0,0 +1,3 @@
+pub mod duplication;
+pub mod filters;
+pub mod versions;
@@ -0,0 +1,528 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use diesel::{dsl::not, update, ExpressionMethods, NullableExpressionMethods, QueryDsl};
+use diesel::{JoinOnDsl, SelectableHelper};
+use diesel_async::RunQueryDsl;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use uuid::Uuid;
+
+use crate::database::{
+    lib::get_pg_pool,
+    models::{DashboardVersion, ThreadToDashboard},
+    schema::{dashboard_versions, dashboards, threads, threads_to_dashboards, users},
+};
+
+/// The parts of a dashboard that are versioned.
+#[derive(Debug, Clone, PartialEq)]
+struct DashboardSnapshot {
+    name: String,
+    description: Option<String>,
+    config: Value,
+    thread_ids: Vec<Uuid>,
+}
+
+impl From<&DashboardVersion> for DashboardSnapshot {
+    fn from(version: &DashboardVersion) -> Self {
+        DashboardSnapshot {
+            name: version.name.clone(),
+            description: version.description.clone(),
+            config: version.config.clone(),
+            thread_ids: version.thread_ids.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DashboardVersionAuthor {
+    pub id: Uuid,
+    pub name: Option<String>,
+    pub email: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DashboardVersionSummary {
+    pub id: Uuid,
+    pub version_number: i32,
+    pub name: String,
+    pub thread_count: usize,
+    pub created_by: Option<DashboardVersionAuthor>,
+    pub restored_from: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FieldChange<T> {
+    pub from: T,
+    pub to: T,
+}
+
+/// A change at one path of the dashboard config, e.g. `rows[0].items[1].id`. `None` means the
+/// path doesn't exist on that side.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ConfigChange {
+    pub path: String,
+    pub from: Option<Value>,
+    pub to: Option<Value>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DashboardVersionDiff {
+    pub from_version: i32,
+    pub to_version: i32,
+    pub name: Option<FieldChange<String>>,
+    pub description: Option<FieldChange<Option<String>>>,
+    pub threads_added: Vec<Uuid>,
+    pub threads_removed: Vec<Uuid>,
+    pub config_changes: Vec<ConfigChange>,
+}
+
+async fn get_dashboard_snapshot(dashboard_id: &Uuid) -> Result<(DashboardSnapshot, Uuid)> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Unable to get connection from pool: {}", e)),
+    };
+
+    let (name, description, config, updated_by) = match dashboards::table
+        .select((
+            dashboards::name,
+            dashboards::description,
+            dashboards::config,
+            dashboards::updated_by,
+        ))
+        .filter(dashboards::id.eq(dashboard_id))
+        .filter(dashboards::deleted_at.is_null())
+        .first::<(String, Option<String>, Value, Uuid)>(&mut conn)
+        .await
+    {
+        Ok(dashboard) => dashboard,
+        Err(e) => return Err(anyhow!("Unable to get dashboard: {}", e)),
+    };
+
+    let thread_ids = match threads_to_dashboards::table
+        .select(threads_to_dashboards::thread_id)
+        .filter(threads_to_dashboards::dashboard_id.eq(dashboard_id))
+        .filter(threads_to_dashboards::deleted_at.is_null())
+        .order(threads_to_dashboards::thread_id)
+        .load::<Uuid>(&mut conn)
+        .await
+    {
+        Ok(thread_ids) => thread_ids,
+        Err(e) => return Err(anyhow!("Unable to get dashboard threads: {}", e)),
+    };
+
+    Ok((
+        DashboardSnapshot {
+            name,
+            description,
+            config,
+            thread_ids,
+        },
+        updated_by,
+    ))
+}
+
+async fn get_latest_dashboard_version(dashboard_id: &Uuid) -> Result<Option<DashboardVersion>> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Unable to get connection from pool: {}", e)),
+    };
+
+    match dashboard_versions::table
+        .select(DashboardVersion::as_select())
+        .filter(dashboard_versions::dashboard_id.eq(dashboard_id))
+        .filter(dashboard_versions::deleted_at.is_null())
+        .order(dashboard_versions::version_number.desc())
+        .first::<DashboardVersion>(&mut conn)
+        .await
+    {
+        Ok(version) => Ok(Some(version)),
+        Err(diesel::NotFound) => Ok(None),
+        Err(e) => Err(anyhow!("Unable to get latest dashboard version: {}", e)),
+    }
+}
+
+/// Saves the dashboard's current name, description, config and threads as a new version, unless
+/// they match the latest version. Without an author, the version is attributed to whoever last
+/// updated the dashboard.
+pub async fn record_dashboard_version(
+    dashboard_id: &Uuid,
+    created_by: Option<Uuid>,
+    restored_from: Option<Uuid>,
+) -> Result<Option<DashboardVersion>> {
+    let (snapshot, updated_by) = get_dashboard_snapshot(dashboard_id).await?;
+
+    let latest = get_latest_dashboard_version(dashboard_id).await?;
+    if let Some(latest) = &latest {
+        if DashboardSnapshot::from(latest) == snapshot && restored_from.is_none() {
+            return Ok(None);
+        }
+    }
+
+    let version = DashboardVersion {
+        id: Uuid::new_v4(),
+        dashboard_id: *dashboard_id,
+        config: snapshot.config,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        deleted_at: None,
+        version_number: latest.map(|v| v.version_number + 1).unwrap_or(1),
+        name: snapshot.name,
+        description: snapshot.description,
+        thread_ids: snapshot.thread_ids,
+        created_by: Some(created_by.unwrap_or(updated_by)),
+        restored_from,
+    };
+
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Unable to get connection from pool: {}", e)),
+    };
+
+    match diesel::insert_into(dashboard_versions::table)
+        .values(&version)
+        .execute(&mut conn)
+        .await
+    {
+        Ok(_) => Ok(Some(version)),
+        Err(e) => Err(anyhow!("Unable to insert dashboard version: {}", e)),
+    }
+}
+
+/// Makes sure the dashboard's state before its first tracked change can be restored.
+pub async fn ensure_dashboard_has_version(dashboard_id: &Uuid) -> Result<()> {
+    if get_latest_dashboard_version(dashboard_id).await?.is_none() {
+        record_dashboard_version(dashboard_id, None, None).await?;
+    }
+
+    Ok(())
+}
+
+/// Lists a dashboard's versions, newest first.
+pub async fn list_dashboard_versions(dashboard_id: &Uuid) -> Result<Vec<DashboardVersionSummary>> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Unable to get connection from pool: {}", e)),
+    };
+
+    let versions = match dashboard_versions::table
+        .left_join(users::table.on(dashboard_versions::created_by.eq(users::id.nullable())))
+        .select((
+            DashboardVersion::as_select(),
+            users::name.nullable(),
+            users::email.nullable(),
+        ))
+        .filter(dashboard_versions::dashboard_id.eq(dashboard_id))
+        .filter(dashboard_versions::deleted_at.is_null())
+        .order(dashboard_versions::version_number.desc())
+        .load::<(DashboardVersion, Option<String>, Option<String>)>(&mut conn)
+        .await
+    {
+        Ok(versions) => versions,
+        Err(e) => return Err(anyhow!("Unable to get dashboard versions: {}", e)),
+    };
+
+    Ok(versions
+        .into_iter()
+        .map(
+            |(version, author_name, author_email)| DashboardVersionSummary {
+                id: version.id,
+                version_number: version.version_number,
+                name: version.name,
+                thread_count: version.thread_ids.len(),
+                created_by: match (version.created_by, author_email) {
+                    (Some(id), Some(email)) => Some(DashboardVersionAuthor {
+                        id,
+                        name: author_name,
+                        email,
+                    }),
+                    _ => None,
+                },
+                restored_from: version.restored_from,
+                created_at: version.created_at,
+            },
+        )
+        .collect())
+}
+
+pub async fn get_dashboard_version(
+    dashboard_id: &Uuid,
+    version_id: &Uuid,
+) -> Result<DashboardVersion> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Unable to get connection from pool: {}", e)),
+    };
+
+    match dashboard_versions::table
+        .select(DashboardVersion::as_select())
+        .filter(dashboard_versions::id.eq(version_id))
+        .filter(dashboard_versions::dashboard_id.eq(dashboard_id))
+        .filter(dashboard_versions::deleted_at.is_null())
+        .first::<DashboardVersion>(&mut conn)
+        .await
+    {
+        Ok(version) => Ok(version),
+        Err(diesel::NotFound) => Err(anyhow!("Dashboard version not found")),
+        Err(e) => Err(anyhow!("Unable to get dashboard version: {}", e)),
+    }
+}
+
+/// Describes what changed going from one version to another.
+pub fn diff_dashboard_versions(
+    from: &DashboardVersion,
+    to: &DashboardVersion,
+) -> DashboardVersionDiff {
+    let from_threads: BTreeSet<&Uuid> = from.thread_ids.iter().collect();
+    let to_threads: BTreeSet<&Uuid> = to.thread_ids.iter().collect();
+
+    let mut config_changes = Vec::new();
+    diff_config(
+        String::new(),
+        Some(&from.config),
+        Some(&to.config),
+        &mut config_changes,
+    );
+
+    DashboardVersionDiff {
+        from_version: from.version_number,
+        to_version: to.version_number,
+        name: (from.name != to.name).then(|| FieldChange {
+            from: from.name.clone(),
+            to: to.name.clone(),
+        }),
+        description: (from.description != to.description).then(|| FieldChange {
+            from: from.description.clone(),
+            to: to.description.clone(),
+        }),
+        threads_added: to_threads
+            .difference(&from_threads)
+            .map(|id| **id)
+            .collect(),
+        threads_removed: from_threads
+            .difference(&to_threads)
+            .map(|id| **id)
+            .collect(),
+        config_changes,
+    }
+}
+
+fn diff_config(
+    path: String,
+    from: Option<&Value>,
+    to: Option<&Value>,
+    changes: &mut Vec<ConfigChange>,
+) {
+    if from == to {
+        return;
+    }
+
+    match (from, to) {
+        (Some(Value::Object(from)), Some(Value::Object(to))) => {
+            let keys: BTreeSet<&String> = from.keys().chain(to.keys()).collect();
+            for key in keys {
+                let child = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                diff_config(child, from.get(key), to.get(key), changes);
+            }
+        }
+        (Some(Value::Array(from)), Some(Value::Array(to))) => {
+            for i in 0..from.len().max(to.len()) {
+                diff_config(format!("{}[{}]", path, i), from.get(i), to.get(i), changes);
+            }
+        }
+        _ => changes.push(ConfigChange {
+            path,
+            from: from.cloned(),
+            to: to.cloned(),
+        }),
+    }
+}
+
+/// Puts the dashboard back to a version and records the result as a new version. The current
+/// state is versioned first so the restore can itself be undone.
+pub async fn restore_dashboard_version(
+    dashboard_id: &Uuid,
+    version_id: &Uuid,
+    user_id: &Uuid,
+) -> Result<DashboardVersion> {
+    let version = get_dashboard_version(dashboard_id, version_id).await?;
+
+    record_dashboard_version(dashboard_id, None, None).await?;
+
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Unable to get connection from pool: {}", e)),
+    };
+
+    match update(dashboards::table)
+        .filter(dashboards::id.eq(dashboard_id))
+        .set((
+            dashboards::name.eq(&version.name),
+            dashboards::description.eq(&version.description),
+            dashboards::config.eq(&version.config),
+            dashboards::updated_by.eq(user_id),
+            dashboards::updated_at.eq(Utc::now()),
+        ))
+        .execute(&mut conn)
+        .await
+    {
+        Ok(_) => (),
+        Err(e) => return Err(anyhow!("Unable to restore dashboard: {}", e)),
+    };
+
+    // Threads deleted since the version was saved can't be brought back
+    let thread_ids = match threads::table
+        .select(threads::id)
+        .filter(threads::id.eq_any(&version.thread_ids))
+        .filter(threads::deleted_at.is_null())
+        .load::<Uuid>(&mut conn)
+        .await
+    {
+        Ok(thread_ids) => thread_ids,
+        Err(e) => return Err(anyhow!("Unable to get version threads: {}", e)),
+    };
+
+    if !thread_ids.is_empty() {
+        let thread_records: Vec<ThreadToDashboard> = thread_ids
+            .iter()
+            .map(|thread_id| ThreadToDashboard {
+                thread_id: *thread_id,
+                dashboard_id: *dashboard_id,
+                added_by: *user_id,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                deleted_at: None,
+            })
+            .collect();
+
+        match diesel::insert_into(threads_to_dashboards::table)
+            .values(&thread_records)
+            .on_conflict((
+                threads_to_dashboards::thread_id,
+                threads_to_dashboards::dashboard_id,
+            ))
+            .do_update()
+            .set((
+                threads_to_dashboards::updated_at.eq(Utc::now()),
+                threads_to_dashboards::deleted_at.eq(Option::<DateTime<Utc>>::None),
+            ))
+            .execute(&mut conn)
+            .await
+        {
+            Ok(_) => (),
+            Err(e) => return Err(anyhow!("Unable to restore dashboard threads: {}", e)),
+        };
+    }
+
+    match update(threads_to_dashboards::table)
+        .filter(threads_to_dashboards::dashboard_id.eq(dashboard_id))
+        .filter(not(threads_to_dashboards::thread_id.eq_any(&thread_ids)))
+        .filter(threads_to_dashboards::deleted_at.is_null())
+        .set(threads_to_dashboards::deleted_at.eq(Some(Utc::now())))
+        .execute(&mut conn)
+        .await
+    {
+        Ok(_) => (),
+        Err(e) => return Err(anyhow!("Unable to remove dashboard threads: {}", e)),
+    };
+
+    match diesel::sql_query(
+        "UPDATE asset_search
+        SET content = $1, updated_at = NOW()
+        WHERE asset_id = $2 AND asset_type = 'dashboard'",
+    )
+    .bind::<diesel::sql_types::Text, _>(&version.name)
+    .bind::<diesel::sql_types::Uuid, _>(dashboard_id)
+    .execute(&mut conn)
+    .await
+    {
+        Ok(_) => (),
+        Err(e) => return Err(anyhow!("Unable to update asset search: {}", e)),
+    };
+
+    match record_dashboard_version(dashboard_id, Some(*user_id), Some(version.id)).await? {
+        Some(restored) => Ok(restored),
+        None => Err(anyhow!("Unable to record the restored dashboard version")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn version(
+        version_number: i32,
+        name: &str,
+        config: Value,
+        thread_ids: Vec<Uuid>,
+    ) -> DashboardVersion {
+        DashboardVersion {
+            id: Uuid::new_v4(),
+            dashboard_id: Uuid::nil(),
+            config,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            deleted_at: None,
+            version_number,
+            name: name.to_string(),
+            description: None,
+            thread_ids,
+            created_by: None,
+            restored_from: None,
+        }
+    }
+
+    #[test]
+    fn test_diff_dashboard_versions() {
+        let kept = Uuid::new_v4();
+        let removed = Uuid::new_v4();
+        let added = Uuid::new_v4();
+
+        let from = version(
+            1,
+            "Sales",
+            json!({"rows": [{"items": [{"id": kept}, {"id": removed}], "columnSizes": [6, 6]}]}),
+            vec![kept, removed],
+        );
+        let to = version(
+            2,
+            "Sales overview",
+            json!({"rows": [{"items": [{"id": kept}, {"id": added}], "columnSizes": [6, 6]}], "theme": "dark"}),
+            vec![kept, added],
+        );
+
+        let diff = diff_dashboard_versions(&from, &to);
+
+        assert_eq!(
+            diff.name,
+            Some(FieldChange {
+                from: "Sales".to_string(),
+                to: "Sales overview".to_string()
+            })
+        );
+        assert_eq!(diff.description, None);
+        assert_eq!(diff.threads_added, vec![added]);
+        assert_eq!(diff.threads_removed, vec![removed]);
+        assert_eq!(
+            diff.config_changes,
+            vec![
+                ConfigChange {
+                    path: "rows[0].items[1].id".to_string(),
+                    from: Some(json!(removed)),
+                    to: Some(json!(added)),
+                },
+                ConfigChange {
+                    path: "theme".to_string(),
+                    from: None,
+                    to: Some(json!("dark")),
+                },
+            ]
+        );
+    }
+}
@@ -1,3 +1,4 @@
 pub mod column_management;
+pub mod prune;
 
 pub use column_management::*; 
\ No newline at end of file
@@ -0,0 +1,351 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use diesel::{BoolExpressionMethods, ExpressionMethods, QueryDsl};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::database::{
+    enums::AssetType,
+    lib::get_pg_pool,
+    schema::{
+        dashboards, dataset_columns, datasets, entity_relationship, messages, semantic_objects,
+        threads, threads_to_dashboards,
+    },
+};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DatasetReference {
+    pub id: Uuid,
+    pub name: Option<String>,
+    pub asset_type: AssetType,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PrunedDataset {
+    pub data_source_name: String,
+    pub name: String,
+    /// False for dry runs and for datasets kept because they are still referenced.
+    pub deleted: bool,
+    pub referenced_by: Vec<DatasetReference>,
+}
+
+/// Soft deletes the datasets of a data source that aren't in `keep`, along with their columns,
+/// semantic objects and relationships.
+///
+/// Datasets that threads or dashboards still query are only deleted with `force`; otherwise they
+/// are reported with their references and left in place. A dry run reports without writing.
+pub async fn prune_datasets(
+    data_source_id: &Uuid,
+    data_source_name: &str,
+    keep: &HashSet<String>,
+    dry_run: bool,
+    force: bool,
+) -> Result<Vec<PrunedDataset>> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Unable to get connection from pool: {}", e)),
+    };
+
+    let stale_datasets = match datasets::table
+        .filter(datasets::data_source_id.eq(data_source_id))
+        .filter(datasets::deleted_at.is_null())
+        .select((datasets::id, datasets::name))
+        .load::<(Uuid, String)>(&mut conn)
+        .await
+    {
+        Ok(datasets) => filter_stale_datasets(datasets, keep),
+        Err(e) => return Err(anyhow!("Unable to get datasets for data source: {}", e)),
+    };
+
+    if stale_datasets.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let stale_ids: Vec<Uuid> = stale_datasets.iter().map(|(id, _)| *id).collect();
+
+    let thread_references = match messages::table
+        .inner_join(threads::table)
+        .filter(messages::dataset_id.eq_any(&stale_ids))
+        .filter(messages::deleted_at.is_null())
+        .filter(threads::deleted_at.is_null())
+        .select((messages::dataset_id, threads::id, messages::title))
+        .load::<(Option<Uuid>, Uuid, Option<String>)>(&mut conn)
+        .await
+    {
+        Ok(references) => references,
+        Err(e) => return Err(anyhow!("Unable to get threads referencing datasets: {}", e)),
+    };
+
+    let thread_ids: HashSet<Uuid> = thread_references.iter().map(|(_, id, _)| *id).collect();
+
+    let dashboard_references = match threads_to_dashboards::table
+        .inner_join(dashboards::table)
+        .filter(threads_to_dashboards::thread_id.eq_any(&thread_ids))
+        .filter(threads_to_dashboards::deleted_at.is_null())
+        .filter(dashboards::deleted_at.is_null())
+        .select((
+            threads_to_dashboards::thread_id,
+            dashboards::id,
+            dashboards::name,
+        ))
+        .load::<(Uuid, Uuid, String)>(&mut conn)
+        .await
+    {
+        Ok(references) => references,
+        Err(e) => {
+            return Err(anyhow!(
+                "Unable to get dashboards referencing datasets: {}",
+                e
+            ))
+        }
+    };
+
+    let references = dataset_references(&thread_references, &dashboard_references);
+    let pruned = plan_prune(stale_datasets, references, data_source_name, dry_run, force);
+
+    let ids_to_delete: Vec<Uuid> = pruned
+        .iter()
+        .filter(|(_, dataset)| dataset.deleted)
+        .map(|(id, _)| *id)
+        .collect();
+
+    if !ids_to_delete.is_empty() {
+        tracing::info!(
+            "Pruning {} datasets from data source '{}'",
+            ids_to_delete.len(),
+            data_source_name
+        );
+
+        conn.transaction::<_, anyhow::Error, _>(|conn| {
+            async move { delete_datasets(conn, &ids_to_delete).await }.scope_boxed()
+        })
+        .await?;
+    }
+
+    Ok(pruned.into_iter().map(|(_, dataset)| dataset).collect())
+}
+
+/// The datasets of a data source that the deploy no longer declares.
+fn filter_stale_datasets(
+    datasets: Vec<(Uuid, String)>,
+    keep: &HashSet<String>,
+) -> Vec<(Uuid, String)> {
+    datasets
+        .into_iter()
+        .filter(|(_, name)| !keep.contains(name))
+        .collect()
+}
+
+/// The threads that query each dataset, and the dashboards those threads are on. Each thread and
+/// dashboard is listed once per dataset, however many messages point at it.
+fn dataset_references(
+    thread_references: &[(Option<Uuid>, Uuid, Option<String>)],
+    dashboard_references: &[(Uuid, Uuid, String)],
+) -> HashMap<Uuid, Vec<DatasetReference>> {
+    let mut references: HashMap<Uuid, Vec<DatasetReference>> = HashMap::new();
+    let mut seen: HashSet<(Uuid, Uuid)> = HashSet::new();
+
+    for (dataset_id, thread_id, title) in thread_references {
+        let dataset_id = match dataset_id {
+            Some(id) => *id,
+            None => continue,
+        };
+
+        if seen.insert((dataset_id, *thread_id)) {
+            references
+                .entry(dataset_id)
+                .or_default()
+                .push(DatasetReference {
+                    id: *thread_id,
+                    name: title.clone(),
+                    asset_type: AssetType::Thread,
+                });
+        }
+
+        for (_, dashboard_id, dashboard_name) in dashboard_references
+            .iter()
+            .filter(|(id, _, _)| id == thread_id)
+        {
+            if seen.insert((dataset_id, *dashboard_id)) {
+                references
+                    .entry(dataset_id)
+                    .or_default()
+                    .push(DatasetReference {
+                        id: *dashboard_id,
+                        name: Some(dashboard_name.clone()),
+                        asset_type: AssetType::Dashboard,
+                    });
+            }
+        }
+    }
+
+    references
+}
+
+/// Decides which stale datasets are deleted. Referenced ones are kept unless forced, and a dry run
+/// deletes nothing.
+fn plan_prune(
+    stale_datasets: Vec<(Uuid, String)>,
+    mut references: HashMap<Uuid, Vec<DatasetReference>>,
+    data_source_name: &str,
+    dry_run: bool,
+    force: bool,
+) -> Vec<(Uuid, PrunedDataset)> {
+    stale_datasets
+        .into_iter()
+        .map(|(id, name)| {
+            let referenced_by = references.remove(&id).unwrap_or_default();
+            let deleted = !dry_run && (force || referenced_by.is_empty());
+
+            (
+                id,
+                PrunedDataset {
+                    data_source_name: data_source_name.to_string(),
+                    name,
+                    deleted,
+                    referenced_by,
+                },
+            )
+        })
+        .collect()
+}
+
+async fn delete_datasets(conn: &mut AsyncPgConnection, ids: &[Uuid]) -> Result<()> {
+    let now = Utc::now();
+
+    if let Err(e) = diesel::update(datasets::table)
+        .filter(datasets::id.eq_any(ids))
+        .set((datasets::deleted_at.eq(now), datasets::trashed_at.eq(now)))
+        .execute(conn)
+        .await
+    {
+        return Err(anyhow!("Unable to delete datasets: {}", e));
+    }
+
+    if let Err(e) = diesel::update(dataset_columns::table)
+        .filter(dataset_columns::dataset_id.eq_any(ids))
+        .filter(dataset_columns::deleted_at.is_null())
+        .set(dataset_columns::deleted_at.eq(now))
+        .execute(conn)
+        .await
+    {
+        return Err(anyhow!("Unable to delete dataset columns: {}", e));
+    }
+
+    if let Err(e) = diesel::update(semantic_objects::table)
+        .filter(semantic_objects::dataset_id.eq_any(ids))
+        .filter(semantic_objects::deleted_at.is_null())
+        .set(semantic_objects::deleted_at.eq(now))
+        .execute(conn)
+        .await
+    {
+        return Err(anyhow!("Unable to delete semantic objects: {}", e));
+    }
+
+    // Relationships have no deleted_at and are rebuilt on every deploy.
+    if let Err(e) = diesel::delete(entity_relationship::table)
+        .filter(
+            entity_relationship::primary_dataset_id
+                .eq_any(ids)
+                .or(entity_relationship::foreign_dataset_id.eq_any(ids)),
+        )
+        .execute(conn)
+        .await
+    {
+        return Err(anyhow!("Unable to delete entity relationships: {}", e));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_stale_datasets() {
+        let orders = Uuid::new_v4();
+        let legacy = Uuid::new_v4();
+        let keep = HashSet::from(["orders".to_string(), "customers".to_string()]);
+
+        let stale = filter_stale_datasets(
+            vec![
+                (orders, "orders".to_string()),
+                (legacy, "legacy".to_string()),
+            ],
+            &keep,
+        );
+
+        assert_eq!(stale, vec![(legacy, "legacy".to_string())]);
+        assert!(filter_stale_datasets(vec![(orders, "orders".to_string())], &keep).is_empty());
+    }
+
+    #[test]
+    fn test_dataset_references_are_deduplicated() {
+        let dataset_id = Uuid::new_v4();
+        let thread_id = Uuid::new_v4();
+        let dashboard_id = Uuid::new_v4();
+
+        // Two messages of the same thread query the dataset, and the thread is on a dashboard.
+        let thread_references = vec![
+            (Some(dataset_id), thread_id, Some("Revenue".to_string())),
+            (
+                Some(dataset_id),
+                thread_id,
+                Some("Revenue by month".to_string()),
+            ),
+            (None, Uuid::new_v4(), None),
+        ];
+        let dashboard_references = vec![(thread_id, dashboard_id, "Finance".to_string())];
+
+        let references = dataset_references(&thread_references, &dashboard_references);
+
+        assert_eq!(references.len(), 1);
+
+        let referenced_by = &references[&dataset_id];
+        assert_eq!(referenced_by.len(), 2);
+        assert_eq!(referenced_by[0].id, thread_id);
+        assert_eq!(referenced_by[0].asset_type, AssetType::Thread);
+        assert_eq!(referenced_by[1].id, dashboard_id);
+        assert_eq!(referenced_by[1].asset_type, AssetType::Dashboard);
+    }
+
+    #[test]
+    fn test_referenced_datasets_are_kept_without_force() {
+        let referenced = Uuid::new_v4();
+        let unused = Uuid::new_v4();
+        let stale = vec![
+            (referenced, "referenced".to_string()),
+            (unused, "unused".to_string()),
+        ];
+        let references = dataset_references(&[(Some(referenced), Uuid::new_v4(), None)], &[]);
+
+        let pruned = plan_prune(stale.clone(), references.clone(), "warehouse", false, false);
+        assert!(!pruned[0].1.deleted);
+        assert_eq!(pruned[0].1.referenced_by.len(), 1);
+        assert!(pruned[1].1.deleted);
+
+        let pruned = plan_prune(stale, references, "warehouse", false, true);
+        assert!(pruned.iter().all(|(_, dataset)| dataset.deleted));
+    }
+
+    #[test]
+    fn test_dry_run_deletes_nothing() {
+        let stale = vec![
+            (Uuid::new_v4(), "referenced".to_string()),
+            (Uuid::new_v4(), "unused".to_string()),
+        ];
+        let references = dataset_references(&[(Some(stale[0].0), Uuid::new_v4(), None)], &[]);
+
+        for force in [false, true] {
+            let pruned = plan_prune(stale.clone(), references.clone(), "warehouse", true, force);
+
+            assert_eq!(pruned.len(), 2);
+            assert!(pruned.iter().all(|(_, dataset)| !dataset.deleted));
+        }
+    }
+}
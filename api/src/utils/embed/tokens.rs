@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use diesel::{update, ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+use jsonwebtoken::{
+    decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::{
+    database::{lib::get_pg_pool, models::User, schema::organizations},
+    utils::clients::supabase_vault::{create_secret, delete_secret, read_secret, update_secret},
+};
+
+pub const EMBED_AUDIENCE: &str = "embed";
+/// Embed tokens are meant to be minted per page view, so they never live longer than a day.
+pub const MAX_EMBED_TOKEN_SECONDS: i64 = 24 * 60 * 60;
+
+const EMBED_KEY_ID_PREFIX: &str = "embed:";
+const EMBED_SECRET_LENGTH: usize = 48;
+
+/// What an embed token grants: read-only access to one dashboard of the issuing organization, with
+/// some filters locked to fixed values or to the viewer's attributes and only the filters in
+/// `open_filters` left for the viewer to change.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EmbedClaims {
+    pub aud: String,
+    /// The organization whose embed secret signed the token.
+    pub iss: Uuid,
+    /// The dashboard the token can view.
+    pub sub: Uuid,
+    pub iat: i64,
+    pub exp: i64,
+    /// Dashboard filter values the viewer can't change, keyed by filter id.
+    #[serde(default)]
+    pub filters: HashMap<String, Value>,
+    /// Dashboard filters the viewer can set. Values for any other filter are refused.
+    #[serde(default)]
+    pub open_filters: Vec<String>,
+    /// Attributes of the viewer in the embedding application, e.g. `customer_id`. Dashboard filters
+    /// with a matching `user_attribute` are locked to them.
+    #[serde(default)]
+    pub user_attributes: HashMap<String, Value>,
+}
+
+/// Signs embed claims with the organization's secret. The organization goes in the `kid` header so
+/// the secret can be found before the signature is checked.
+pub fn sign_embed_token(secret: &str, claims: &EmbedClaims) -> Result<String> {
+    let header = Header {
+        kid: Some(format!("{}{}", EMBED_KEY_ID_PREFIX, claims.iss)),
+        ..Header::new(Algorithm::HS256)
+    };
+
+    match encode(&header, claims, &EncodingKey::from_secret(secret.as_bytes())) {
+        Ok(token) => Ok(token),
+        Err(e) => Err(anyhow!("Error signing embed token: {}", e)),
+    }
+}
+
+/// The organization an embed token claims to be from, or `None` for any other kind of token. The
+/// token is not verified.
+pub fn embed_token_organization(token: &str) -> Option<Uuid> {
+    let header = decode_header(token).ok()?;
+
+    header
+        .kid?
+        .strip_prefix(EMBED_KEY_ID_PREFIX)
+        .and_then(|id| Uuid::parse_str(id).ok())
+}
+
+/// Checks an embed token's signature, audience, expiry and issuer.
+pub fn verify_embed_token(token: &str, secret: &str, organization_id: &Uuid) -> Result<EmbedClaims> {
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.set_audience(&[EMBED_AUDIENCE]);
+    validation.set_issuer(&[organization_id.to_string()]);
+    validation.set_required_spec_claims(&["exp", "aud", "iss", "sub"]);
+
+    match decode::<EmbedClaims>(token, &DecodingKey::from_secret(secret.as_bytes()), &validation) {
+        Ok(token_data) => Ok(token_data.claims),
+        Err(e) => Err(anyhow!("Invalid embed token: {}", e)),
+    }
+}
+
+/// Verifies an embed token against the secret of the organization it names.
+pub async fn decode_embed_token(token: &str) -> Result<EmbedClaims> {
+    let organization_id = match embed_token_organization(token) {
+        Some(organization_id) => organization_id,
+        None => return Err(anyhow!("Not an embed token")),
+    };
+
+    let secret = match get_embed_secret(&organization_id).await? {
+        Some(secret) => secret,
+        None => return Err(anyhow!("Embedding is not enabled for this organization")),
+    };
+
+    verify_embed_token(token, &secret, &organization_id)
+}
+
+pub async fn get_embed_secret(organization_id: &Uuid) -> Result<Option<String>> {
+    let secret_id = match get_embed_secret_id(organization_id).await? {
+        Some(secret_id) => secret_id,
+        None => return Ok(None),
+    };
+
+    match read_secret(&secret_id).await {
+        Ok(secret) => Ok(Some(secret)),
+        Err(e) => Err(anyhow!("Error reading embed secret: {}", e)),
+    }
+}
+
+/// Generates a new embed secret for the organization, which invalidates every token signed with
+/// the old one.
+pub async fn rotate_embed_secret(organization_id: &Uuid) -> Result<String> {
+    let secret: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(EMBED_SECRET_LENGTH)
+        .map(char::from)
+        .collect();
+
+    match get_embed_secret_id(organization_id).await? {
+        Some(secret_id) => update_secret(&secret_id, &secret).await?,
+        None => {
+            let secret_id = create_secret(&secret).await?;
+            set_embed_secret_id(organization_id, Some(secret_id)).await?;
+        }
+    };
+
+    Ok(secret)
+}
+
+/// Removes the organization's embed secret. Embedding stays off until a new one is generated.
+pub async fn revoke_embed_secret(organization_id: &Uuid) -> Result<()> {
+    if let Some(secret_id) = get_embed_secret_id(organization_id).await? {
+        set_embed_secret_id(organization_id, None).await?;
+        delete_secret(&secret_id).await?;
+    }
+
+    Ok(())
+}
+
+/// The user an embedded WebSocket session runs as. It belongs to no organization and has no
+/// permissions of its own, so it can't reach anything outside the token's dashboard.
+pub fn embed_viewer(claims: &EmbedClaims) -> User {
+    User {
+        id: Uuid::new_v4(),
+        email: format!("{}@embed.invalid", claims.sub),
+        name: Some("Embedded viewer".to_string()),
+        config: json!({}),
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        attributes: json!({}),
+    }
+}
+
+async fn get_embed_secret_id(organization_id: &Uuid) -> Result<Option<Uuid>> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    match organizations::table
+        .select(organizations::embed_secret_id)
+        .filter(organizations::id.eq(organization_id))
+        .filter(organizations::deleted_at.is_null())
+        .first::<Option<Uuid>>(&mut conn)
+        .await
+    {
+        Ok(secret_id) => Ok(secret_id),
+        Err(diesel::NotFound) => Ok(None),
+        Err(e) => Err(anyhow!("Error getting embed secret: {}", e)),
+    }
+}
+
+async fn set_embed_secret_id(organization_id: &Uuid, secret_id: Option<Uuid>) -> Result<()> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    match update(organizations::table)
+        .filter(organizations::id.eq(organization_id))
+        .set((
+            organizations::embed_secret_id.eq(secret_id),
+            organizations::updated_at.eq(Utc::now()),
+        ))
+        .execute(&mut conn)
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!("Error updating embed secret: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims(organization_id: Uuid, expires_in: i64) -> EmbedClaims {
+        EmbedClaims {
+            aud: EMBED_AUDIENCE.to_string(),
+            iss: organization_id,
+            sub: Uuid::new_v4(),
+            iat: Utc::now().timestamp(),
+            exp: Utc::now().timestamp() + expires_in,
+            filters: HashMap::from([("region".to_string(), json!("EMEA"))]),
+            open_filters: vec!["period".to_string()],
+            user_attributes: HashMap::from([("customer_id".to_string(), json!("acme"))]),
+        }
+    }
+
+    #[test]
+    fn test_embed_token_round_trip() {
+        let organization_id = Uuid::new_v4();
+        let claims = claims(organization_id, 600);
+
+        let token = sign_embed_token("secret", &claims).unwrap();
+
+        assert_eq!(embed_token_organization(&token), Some(organization_id));
+
+        let verified = verify_embed_token(&token, "secret", &organization_id).unwrap();
+        assert_eq!(verified.sub, claims.sub);
+        assert_eq!(verified.filters["region"], json!("EMEA"));
+        assert_eq!(verified.open_filters, vec!["period"]);
+        assert_eq!(verified.user_attributes["customer_id"], json!("acme"));
+
+        assert!(verify_embed_token(&token, "other secret", &organization_id).is_err());
+    }
+
+    #[test]
+    fn test_expired_embed_token() {
+        let organization_id = Uuid::new_v4();
+        let expired = EmbedClaims {
+            iat: Utc::now().timestamp() - 7200,
+            ..claims(organization_id, -3600)
+        };
+
+        let token = sign_embed_token("secret", &expired).unwrap();
+        assert!(verify_embed_token(&token, "secret", &organization_id).is_err());
+    }
+
+    #[test]
+    fn test_embed_token_from_other_organization() {
+        let organization_id = Uuid::new_v4();
+        let other_organization_id = Uuid::new_v4();
+
+        let token = sign_embed_token("secret", &claims(organization_id, 600)).unwrap();
+
+        // Even with a shared secret, a token only verifies for the organization that issued it.
+        assert!(verify_embed_token(&token, "secret", &other_organization_id).is_err());
+        assert_eq!(embed_token_organization(&token), Some(organization_id));
+
+        // Nor is a token for the wrong audience accepted.
+        let other_audience = EmbedClaims {
+            aud: "api".to_string(),
+            ..claims(organization_id, 600)
+        };
+        let token = sign_embed_token("secret", &other_audience).unwrap();
+        assert!(verify_embed_token(&token, "secret", &organization_id).is_err());
+
+        assert_eq!(embed_token_organization("not a token"), None);
+    }
+}
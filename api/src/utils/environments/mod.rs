@@ -0,0 +1,58 @@
+pub mod promotion;
+
+use anyhow::{anyhow, Result};
+use diesel::{ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+use uuid::Uuid;
+
+use crate::database::{
+    lib::get_pg_pool,
+    schema::{dashboards, threads},
+};
+
+/// The environment of data sources, threads and dashboards that don't name one.
+pub const DEFAULT_ENV: &str = "dev";
+
+/// Dashboards are pinned to an environment, so only threads from that environment can be added
+/// to them.
+pub async fn check_threads_match_dashboard_env(
+    dashboard_id: &Uuid,
+    thread_ids: &[Uuid],
+) -> Result<()> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Unable to get connection from pool: {}", e)),
+    };
+
+    let dashboard_env = match dashboards::table
+        .filter(dashboards::id.eq(dashboard_id))
+        .select(dashboards::env)
+        .first::<String>(&mut conn)
+        .await
+    {
+        Ok(env) => env,
+        Err(e) => return Err(anyhow!("Unable to get dashboard environment: {}", e)),
+    };
+
+    let mismatched_envs = match threads::table
+        .filter(threads::id.eq_any(thread_ids))
+        .filter(threads::env.ne(&dashboard_env))
+        .select(threads::env)
+        .distinct()
+        .load::<String>(&mut conn)
+        .await
+    {
+        Ok(envs) => envs,
+        Err(e) => return Err(anyhow!("Unable to get thread environments: {}", e)),
+    };
+
+    if !mismatched_envs.is_empty() {
+        return Err(anyhow!(
+            "Threads from the {} environment can't be added to a dashboard in the {} environment",
+            mismatched_envs.join(", "),
+            dashboard_env
+        ));
+    }
+
+    Ok(())
+}
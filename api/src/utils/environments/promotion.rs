@@ -0,0 +1,647 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use diesel::{upsert::excluded, ExpressionMethods, JoinOnDsl, QueryDsl, SelectableHelper};
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::database::{
+    enums::Verification,
+    lib::get_pg_pool,
+    models::{Dataset, DatasetColumn, SemanticObject, TermToDataset},
+    schema::{data_sources, dataset_columns, datasets, semantic_objects, terms, terms_to_datasets},
+};
+
+/// Everything promotion copies for one dataset in one environment.
+#[derive(Debug, Clone)]
+pub struct DatasetSnapshot {
+    pub data_source_name: String,
+    pub dataset: Dataset,
+    pub columns: Vec<DatasetColumn>,
+    pub semantic_objects: Vec<SemanticObject>,
+    // (term id, term name)
+    pub terms: Vec<(Uuid, String)>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PromotionStatus {
+    Changed,
+    Unchanged,
+    // The dataset has to be deployed to the target environment before it can be promoted.
+    MissingInTarget,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PromotionChange {
+    pub field: String,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DatasetPromotion {
+    pub data_source_name: String,
+    pub dataset_name: String,
+    pub status: PromotionStatus,
+    pub changes: Vec<PromotionChange>,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PromotionPlan {
+    pub from_env: String,
+    pub to_env: String,
+    pub applied: bool,
+    pub datasets: Vec<DatasetPromotion>,
+}
+
+/// Copies dataset descriptions, column descriptions, semantic objects and term links from the
+/// datasets of one environment to the datasets with the same data source and name in another.
+///
+/// Only enabled, verified datasets are promoted, and only onto datasets that were already deployed
+/// to the target environment, since their SQL, schema and database are specific to it. With
+/// `preview` the plan is returned without writing anything.
+pub async fn promote_datasets(
+    organization_id: &Uuid,
+    user_id: &Uuid,
+    from_env: &String,
+    to_env: &String,
+    data_source_name: Option<&String>,
+    dataset_names: Option<&Vec<String>>,
+    preview: bool,
+) -> Result<PromotionPlan> {
+    if from_env == to_env {
+        return Err(anyhow!(
+            "The source and target environments must be different"
+        ));
+    }
+
+    let sources = load_env_snapshots(organization_id, from_env, data_source_name, dataset_names)
+        .await?
+        .into_iter()
+        .filter(|snapshot| {
+            snapshot.dataset.enabled && snapshot.dataset.verification == Verification::Verified
+        })
+        .collect::<Vec<DatasetSnapshot>>();
+
+    if sources.is_empty() {
+        return Err(anyhow!(
+            "No enabled, verified datasets found in the {} environment",
+            from_env
+        ));
+    }
+
+    let targets = load_env_snapshots(organization_id, to_env, data_source_name, dataset_names)
+        .await?
+        .into_iter()
+        .map(|snapshot| {
+            (
+                (
+                    snapshot.data_source_name.clone(),
+                    snapshot.dataset.name.clone(),
+                ),
+                snapshot,
+            )
+        })
+        .collect::<HashMap<(String, String), DatasetSnapshot>>();
+
+    let mut plan = PromotionPlan {
+        from_env: from_env.clone(),
+        to_env: to_env.clone(),
+        applied: !preview,
+        datasets: Vec::new(),
+    };
+
+    for source in &sources {
+        let key = (source.data_source_name.clone(), source.dataset.name.clone());
+
+        let target = match targets.get(&key) {
+            Some(target) => target,
+            None => {
+                plan.datasets.push(DatasetPromotion {
+                    data_source_name: source.data_source_name.clone(),
+                    dataset_name: source.dataset.name.clone(),
+                    status: PromotionStatus::MissingInTarget,
+                    changes: vec![],
+                    warnings: vec![format!(
+                        "Run `buster deploy --env {}` to create this dataset before promoting it",
+                        to_env
+                    )],
+                });
+                continue;
+            }
+        };
+
+        let changes = diff_snapshots(source, target);
+
+        let warnings = source
+            .columns
+            .iter()
+            .filter(|c| !target.columns.iter().any(|t| t.name == c.name))
+            .map(|c| {
+                format!(
+                    "Column '{}' doesn't exist in the {} environment and won't be promoted",
+                    c.name, to_env
+                )
+            })
+            .collect();
+
+        if !changes.is_empty() && !preview {
+            apply_promotion(user_id, source, target).await?;
+        }
+
+        plan.datasets.push(DatasetPromotion {
+            data_source_name: source.data_source_name.clone(),
+            dataset_name: source.dataset.name.clone(),
+            status: if changes.is_empty() {
+                PromotionStatus::Unchanged
+            } else {
+                PromotionStatus::Changed
+            },
+            changes,
+            warnings,
+        });
+    }
+
+    Ok(plan)
+}
+
+pub async fn load_env_snapshots(
+    organization_id: &Uuid,
+    env: &String,
+    data_source_name: Option<&String>,
+    dataset_names: Option<&Vec<String>>,
+) -> Result<Vec<DatasetSnapshot>> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Unable to get connection from pool: {}", e)),
+    };
+
+    let dataset_records = match datasets::table
+        .inner_join(data_sources::table.on(datasets::data_source_id.eq(data_sources::id)))
+        .filter(data_sources::organization_id.eq(organization_id))
+        .filter(data_sources::env.eq(env))
+        .filter(data_sources::deleted_at.is_null())
+        .filter(datasets::deleted_at.is_null())
+        .select((Dataset::as_select(), data_sources::name))
+        .load::<(Dataset, String)>(&mut conn)
+        .await
+    {
+        Ok(records) => records,
+        Err(e) => return Err(anyhow!("Unable to get datasets for {}: {}", env, e)),
+    };
+
+    let dataset_records = dataset_records
+        .into_iter()
+        .filter(|(_, name)| data_source_name.is_none_or(|filter| filter == name))
+        .filter(|(dataset, _)| dataset_names.is_none_or(|names| names.contains(&dataset.name)))
+        .collect::<Vec<(Dataset, String)>>();
+
+    let dataset_ids = dataset_records
+        .iter()
+        .map(|(dataset, _)| dataset.id)
+        .collect::<Vec<Uuid>>();
+
+    let columns = match dataset_columns::table
+        .filter(dataset_columns::dataset_id.eq_any(&dataset_ids))
+        .filter(dataset_columns::deleted_at.is_null())
+        .order(dataset_columns::name.asc())
+        .load::<DatasetColumn>(&mut conn)
+        .await
+    {
+        Ok(columns) => columns,
+        Err(e) => return Err(anyhow!("Unable to get dataset columns: {}", e)),
+    };
+
+    let objects = match semantic_objects::table
+        .filter(semantic_objects::dataset_id.eq_any(&dataset_ids))
+        .filter(semantic_objects::deleted_at.is_null())
+        .order(semantic_objects::name.asc())
+        .load::<SemanticObject>(&mut conn)
+        .await
+    {
+        Ok(objects) => objects,
+        Err(e) => return Err(anyhow!("Unable to get semantic objects: {}", e)),
+    };
+
+    let term_links = match terms_to_datasets::table
+        .inner_join(terms::table.on(terms_to_datasets::term_id.eq(terms::id)))
+        .filter(terms_to_datasets::dataset_id.eq_any(&dataset_ids))
+        .filter(terms_to_datasets::deleted_at.is_null())
+        .filter(terms::deleted_at.is_null())
+        .select((terms_to_datasets::dataset_id, terms::id, terms::name))
+        .load::<(Uuid, Uuid, String)>(&mut conn)
+        .await
+    {
+        Ok(links) => links,
+        Err(e) => return Err(anyhow!("Unable to get dataset terms: {}", e)),
+    };
+
+    Ok(dataset_records
+        .into_iter()
+        .map(|(dataset, data_source_name)| DatasetSnapshot {
+            data_source_name,
+            columns: columns
+                .iter()
+                .filter(|c| c.dataset_id == dataset.id)
+                .cloned()
+                .collect(),
+            semantic_objects: objects
+                .iter()
+                .filter(|o| o.dataset_id == dataset.id)
+                .cloned()
+                .collect(),
+            terms: term_links
+                .iter()
+                .filter(|(dataset_id, _, _)| *dataset_id == dataset.id)
+                .map(|(_, term_id, name)| (*term_id, name.clone()))
+                .collect(),
+            dataset,
+        })
+        .collect())
+}
+
+/// The changes promoting `source` would make to `target`.
+pub fn diff_snapshots(source: &DatasetSnapshot, target: &DatasetSnapshot) -> Vec<PromotionChange> {
+    let mut changes = Vec::new();
+
+    let mut compare = |field: String, from: Option<String>, to: Option<String>| {
+        if from != to {
+            changes.push(PromotionChange { field, from, to });
+        }
+    };
+
+    compare(
+        "when_to_use".to_string(),
+        target.dataset.when_to_use.clone(),
+        source.dataset.when_to_use.clone(),
+    );
+    compare(
+        "when_not_to_use".to_string(),
+        target.dataset.when_not_to_use.clone(),
+        source.dataset.when_not_to_use.clone(),
+    );
+
+    for column in &source.columns {
+        let target_column = match target.columns.iter().find(|c| c.name == column.name) {
+            Some(target_column) => target_column,
+            None => continue,
+        };
+
+        let column_fields = [
+            (
+                "description",
+                &target_column.description,
+                &column.description,
+            ),
+            (
+                "semantic_type",
+                &target_column.semantic_type,
+                &column.semantic_type,
+            ),
+            ("dim_type", &target_column.dim_type, &column.dim_type),
+            ("expr", &target_column.expr, &column.expr),
+        ];
+
+        for (name, from, to) in column_fields {
+            compare(
+                format!("columns.{}.{}", column.name, name),
+                from.clone(),
+                to.clone(),
+            );
+        }
+    }
+
+    let render = |objects: &[SemanticObject]| {
+        objects
+            .iter()
+            .map(|o| {
+                let definition = match &o.agg {
+                    Some(agg) => format!("{} {}({})", o.object_type, agg, o.expr),
+                    None => format!("{} {}", o.object_type, o.expr),
+                };
+                (o.name.clone(), definition)
+            })
+            .collect::<BTreeMap<String, String>>()
+    };
+
+    let source_objects = render(&source.semantic_objects);
+    let target_objects = render(&target.semantic_objects);
+
+    for (name, definition) in &source_objects {
+        compare(
+            format!("semantic_objects.{}", name),
+            target_objects.get(name).cloned(),
+            Some(definition.clone()),
+        );
+    }
+
+    for (name, definition) in &target_objects {
+        if !source_objects.contains_key(name) {
+            compare(
+                format!("semantic_objects.{}", name),
+                Some(definition.clone()),
+                None,
+            );
+        }
+    }
+
+    let term_names = |snapshot: &DatasetSnapshot| {
+        let mut names = snapshot
+            .terms
+            .iter()
+            .map(|(_, name)| name.clone())
+            .collect::<Vec<String>>();
+        names.sort();
+        names.join(", ")
+    };
+
+    compare(
+        "terms".to_string(),
+        Some(term_names(target)),
+        Some(term_names(source)),
+    );
+
+    changes
+}
+
+async fn apply_promotion(
+    user_id: &Uuid,
+    source: &DatasetSnapshot,
+    target: &DatasetSnapshot,
+) -> Result<()> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Unable to get connection from pool: {}", e)),
+    };
+
+    let now = Utc::now();
+    let target_id = target.dataset.id;
+
+    // Everything lands in one transaction, so a failure part-way leaves the target as it was.
+    conn.transaction::<_, anyhow::Error, _>(|conn| {
+        async move {
+            // The definition, model, yml file, schema and database stay as deployed, since they
+            // name the target environment's tables.
+            if let Err(e) = diesel::update(datasets::table)
+                .filter(datasets::id.eq(target_id))
+                .set((
+                    datasets::when_to_use.eq(&source.dataset.when_to_use),
+                    datasets::when_not_to_use.eq(&source.dataset.when_not_to_use),
+                    datasets::updated_by.eq(user_id),
+                    datasets::updated_at.eq(now),
+                ))
+                .execute(conn)
+                .await
+            {
+                return Err(anyhow!(
+                    "Unable to update dataset {}: {}",
+                    target.dataset.name,
+                    e
+                ));
+            }
+
+            for column in &source.columns {
+                if !target.columns.iter().any(|c| c.name == column.name) {
+                    continue;
+                }
+
+                if let Err(e) = diesel::update(dataset_columns::table)
+                    .filter(dataset_columns::dataset_id.eq(target_id))
+                    .filter(dataset_columns::name.eq(&column.name))
+                    .filter(dataset_columns::deleted_at.is_null())
+                    .set((
+                        dataset_columns::description.eq(&column.description),
+                        dataset_columns::semantic_type.eq(&column.semantic_type),
+                        dataset_columns::dim_type.eq(&column.dim_type),
+                        dataset_columns::expr.eq(&column.expr),
+                        dataset_columns::updated_at.eq(now),
+                    ))
+                    .execute(conn)
+                    .await
+                {
+                    return Err(anyhow!("Unable to update column {}: {}", column.name, e));
+                }
+            }
+
+            let objects_to_upsert = source
+                .semantic_objects
+                .iter()
+                .map(|o| SemanticObject {
+                    id: Uuid::new_v4(),
+                    dataset_id: target_id,
+                    name: o.name.clone(),
+                    object_type: o.object_type,
+                    expr: o.expr.clone(),
+                    agg: o.agg.clone(),
+                    description: o.description.clone(),
+                    created_at: now,
+                    updated_at: now,
+                    deleted_at: None,
+                })
+                .collect::<Vec<SemanticObject>>();
+
+            if !objects_to_upsert.is_empty() {
+                if let Err(e) = diesel::insert_into(semantic_objects::table)
+                    .values(&objects_to_upsert)
+                    .on_conflict((semantic_objects::dataset_id, semantic_objects::name))
+                    .do_update()
+                    .set((
+                        semantic_objects::object_type.eq(excluded(semantic_objects::object_type)),
+                        semantic_objects::expr.eq(excluded(semantic_objects::expr)),
+                        semantic_objects::agg.eq(excluded(semantic_objects::agg)),
+                        semantic_objects::description.eq(excluded(semantic_objects::description)),
+                        semantic_objects::updated_at.eq(now),
+                        semantic_objects::deleted_at.eq(None::<DateTime<Utc>>),
+                    ))
+                    .execute(conn)
+                    .await
+                {
+                    return Err(anyhow!("Unable to promote semantic objects: {}", e));
+                }
+            }
+
+            let source_object_names = source
+                .semantic_objects
+                .iter()
+                .map(|o| o.name.clone())
+                .collect::<Vec<String>>();
+
+            if let Err(e) = diesel::update(semantic_objects::table)
+                .filter(semantic_objects::dataset_id.eq(target_id))
+                .filter(semantic_objects::name.ne_all(&source_object_names))
+                .filter(semantic_objects::deleted_at.is_null())
+                .set(semantic_objects::deleted_at.eq(now))
+                .execute(conn)
+                .await
+            {
+                return Err(anyhow!("Unable to remove semantic objects: {}", e));
+            }
+
+            // Terms belong to the organization, so only their links to the dataset are promoted.
+            let term_links = source
+                .terms
+                .iter()
+                .map(|(term_id, _)| TermToDataset {
+                    term_id: *term_id,
+                    dataset_id: target_id,
+                    created_at: now,
+                    updated_at: now,
+                    deleted_at: None,
+                })
+                .collect::<Vec<TermToDataset>>();
+
+            if !term_links.is_empty() {
+                if let Err(e) = diesel::insert_into(terms_to_datasets::table)
+                    .values(&term_links)
+                    .on_conflict((terms_to_datasets::term_id, terms_to_datasets::dataset_id))
+                    .do_update()
+                    .set((
+                        terms_to_datasets::updated_at.eq(now),
+                        terms_to_datasets::deleted_at.eq(None::<DateTime<Utc>>),
+                    ))
+                    .execute(conn)
+                    .await
+                {
+                    return Err(anyhow!("Unable to promote terms: {}", e));
+                }
+            }
+
+            let source_term_ids = source
+                .terms
+                .iter()
+                .map(|(term_id, _)| *term_id)
+                .collect::<Vec<Uuid>>();
+
+            if let Err(e) = diesel::update(terms_to_datasets::table)
+                .filter(terms_to_datasets::dataset_id.eq(target_id))
+                .filter(terms_to_datasets::term_id.ne_all(&source_term_ids))
+                .filter(terms_to_datasets::deleted_at.is_null())
+                .set(terms_to_datasets::deleted_at.eq(now))
+                .execute(conn)
+                .await
+            {
+                return Err(anyhow!("Unable to remove terms: {}", e));
+            }
+
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::enums::{DatasetType, SemanticObjectType};
+
+    fn snapshot(when_to_use: &str, column_description: &str, agg: &str) -> DatasetSnapshot {
+        let dataset_id = Uuid::new_v4();
+        let now = Utc::now();
+
+        DatasetSnapshot {
+            data_source_name: "warehouse".to_string(),
+            dataset: Dataset {
+                id: dataset_id,
+                name: "orders".to_string(),
+                database_name: "orders".to_string(),
+                when_to_use: Some(when_to_use.to_string()),
+                when_not_to_use: None,
+                type_: DatasetType::View,
+                definition: "select * from orders".to_string(),
+                schema: "public".to_string(),
+                enabled: true,
+                imported: false,
+                data_source_id: Uuid::new_v4(),
+                organization_id: Uuid::new_v4(),
+                created_by: Uuid::new_v4(),
+                updated_by: Uuid::new_v4(),
+                created_at: now,
+                updated_at: now,
+                deleted_at: None,
+                model: None,
+                yml_file: None,
+                database_identifier: None,
+                verification: Verification::Verified,
+                trashed_at: None,
+            },
+            columns: vec![DatasetColumn {
+                id: Uuid::new_v4(),
+                dataset_id,
+                name: "amount".to_string(),
+                type_: "numeric".to_string(),
+                description: Some(column_description.to_string()),
+                nullable: true,
+                created_at: now,
+                updated_at: now,
+                deleted_at: None,
+                stored_values: None,
+                stored_values_status: None,
+                stored_values_error: None,
+                stored_values_count: None,
+                stored_values_last_synced: None,
+                semantic_type: Some("measure".to_string()),
+                dim_type: None,
+                expr: None,
+            }],
+            semantic_objects: vec![SemanticObject {
+                id: Uuid::new_v4(),
+                dataset_id,
+                name: "revenue".to_string(),
+                object_type: SemanticObjectType::Measure,
+                expr: "amount".to_string(),
+                agg: Some(agg.to_string()),
+                description: None,
+                created_at: now,
+                updated_at: now,
+                deleted_at: None,
+            }],
+            terms: vec![],
+        }
+    }
+
+    #[test]
+    fn test_identical_snapshots_have_no_changes() {
+        let source = snapshot("Order facts", "Order total", "sum");
+        let target = snapshot("Order facts", "Order total", "sum");
+
+        assert!(diff_snapshots(&source, &target).is_empty());
+    }
+
+    #[test]
+    fn test_diff_reports_changed_fields() {
+        let mut source = snapshot("Verified order facts", "Order total in USD", "sum");
+        source.terms = vec![(Uuid::new_v4(), "GMV".to_string())];
+        // The definition names each environment's own schema, so it isn't promoted.
+        source.dataset.definition = "select * from dev.orders".to_string();
+        let target = snapshot("Order facts", "Order total", "avg");
+
+        let changes = diff_snapshots(&source, &target);
+        let fields = changes
+            .iter()
+            .map(|c| c.field.as_str())
+            .collect::<Vec<&str>>();
+
+        assert_eq!(
+            fields,
+            vec![
+                "when_to_use",
+                "columns.amount.description",
+                "semantic_objects.revenue",
+                "terms"
+            ]
+        );
+        assert_eq!(
+            changes[2],
+            PromotionChange {
+                field: "semantic_objects.revenue".to_string(),
+                from: Some("measure avg(amount)".to_string()),
+                to: Some("measure sum(amount)".to_string()),
+            }
+        );
+    }
+}
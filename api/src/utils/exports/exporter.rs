@@ -0,0 +1,144 @@
+use std::{
+    env,
+    fs::File,
+    io::{self, Seek, SeekFrom},
+};
+
+use anyhow::Result;
+use axum::body::Bytes;
+use futures::stream::{BoxStream, StreamExt};
+use serde::Deserialize;
+use serde_json::Value;
+use tokio_util::io::ReaderStream;
+use uuid::Uuid;
+
+use crate::utils::query_engine::query_engine::limited_query_engine;
+
+use super::{
+    csv::csv_stream,
+    parquet::write_parquet,
+    table::ExportTable,
+    xlsx::{write_xlsx, XLSX_MAX_ROWS},
+};
+
+const DEFAULT_EXPORT_ROW_LIMIT: usize = 250_000;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Xlsx,
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Xlsx => {
+                "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+            }
+            ExportFormat::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Xlsx => "xlsx",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+
+    /// The most rows an export can hold: `EXPORT_ROW_LIMIT` (250,000 by default), and never more
+    /// than a worksheet can for XLSX.
+    pub fn row_limit(&self) -> usize {
+        let limit = match env::var("EXPORT_ROW_LIMIT") {
+            Ok(limit) => limit.parse::<usize>().unwrap_or(DEFAULT_EXPORT_ROW_LIMIT),
+            Err(_) => DEFAULT_EXPORT_ROW_LIMIT,
+        };
+
+        match self {
+            ExportFormat::Xlsx => limit.min(XLSX_MAX_ROWS),
+            _ => limit,
+        }
+    }
+}
+
+/// The chunks of an export file, produced as the response body is read.
+pub type ExportStream = BoxStream<'static, io::Result<Bytes>>;
+
+pub struct ExportFile {
+    pub file_name: String,
+    pub content_type: &'static str,
+    pub contents: ExportStream,
+    /// Whether rows past the export row limit were left out.
+    pub truncated: bool,
+}
+
+/// Re-runs a metric's query and writes its full result set, up to the export row limit, in the
+/// requested format with the metric's column label formats applied. Callers check access first.
+pub async fn export_metric(
+    name: &str,
+    dataset_id: &Uuid,
+    sql: &String,
+    chart_config: Option<&Value>,
+    format: ExportFormat,
+) -> Result<ExportFile> {
+    let row_limit = format.row_limit();
+
+    // One extra row tells us whether the result was cut off.
+    let mut rows = limited_query_engine(dataset_id, sql, row_limit as i64 + 1).await?;
+    let truncated = rows.len() > row_limit;
+    rows.truncate(row_limit);
+
+    let table = ExportTable::from_rows(rows, chart_config);
+
+    let contents = match format {
+        ExportFormat::Csv => csv_stream(table),
+        ExportFormat::Xlsx => {
+            let sheet_name = name.to_string();
+            file_stream(move |file| write_xlsx(&table, &sheet_name, file)).await?
+        }
+        ExportFormat::Parquet => file_stream(move |file| write_parquet(&table, file)).await?,
+    };
+
+    Ok(ExportFile {
+        file_name: format!("{}.{}", export_file_stem(name), format.extension()),
+        content_type: format.content_type(),
+        contents,
+        truncated,
+    })
+}
+
+// Binary formats can't be written a row at a time, so they go to a temporary file off the async
+// runtime and the response streams it back instead of holding the file in memory.
+async fn file_stream<F>(write: F) -> Result<ExportStream>
+where
+    F: FnOnce(&mut File) -> Result<()> + Send + 'static,
+{
+    let file = tokio::task::spawn_blocking(move || -> Result<File> {
+        let mut file = tempfile::tempfile()?;
+        write(&mut file)?;
+        file.seek(SeekFrom::Start(0))?;
+        Ok(file)
+    })
+    .await??;
+
+    Ok(ReaderStream::new(tokio::fs::File::from_std(file)).boxed())
+}
+
+// File names keep letters, numbers, spaces, dashes and underscores.
+fn export_file_stem(name: &str) -> String {
+    let stem: String = name
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, ' ' | '-' | '_'))
+        .take(100)
+        .collect();
+    let stem = stem.trim();
+
+    match stem.is_empty() {
+        true => "export".to_string(),
+        false => stem.to_string(),
+    }
+}
@@ -0,0 +1,5 @@
+pub mod csv;
+pub mod exporter;
+pub mod parquet;
+pub mod table;
+pub mod xlsx;
@@ -0,0 +1,218 @@
+use std::{io::Write, sync::Arc};
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use parquet::{
+    basic::{LogicalType, Repetition, TimeUnit, Type as PhysicalType},
+    data_type::{
+        BoolType, ByteArray, ByteArrayType, DataType as ParquetDataType, DoubleType, Int32Type,
+        Int64Type,
+    },
+    file::{
+        properties::WriterProperties,
+        writer::{SerializedColumnWriter, SerializedFileWriter},
+    },
+    format::MicroSeconds,
+    schema::types::Type,
+};
+
+use super::table::{ExportColumn, ExportColumnType, ExportTable, ExportValue};
+
+const ROW_GROUP_SIZE: usize = 100_000;
+
+/// Writes the table as a Parquet file. Every column is optional, so nulls round-trip, and dates
+/// and timestamps keep their logical types.
+pub fn write_parquet<W: Write + Send>(table: &ExportTable, writer: W) -> Result<()> {
+    let fields = table
+        .columns
+        .iter()
+        .map(|column| column_schema(column).map(Arc::new))
+        .collect::<Result<Vec<_>>>()?;
+    let schema = Type::group_type_builder("schema")
+        .with_fields(fields)
+        .build()?;
+
+    let mut writer = SerializedFileWriter::new(
+        writer,
+        Arc::new(schema),
+        Arc::new(WriterProperties::builder().build()),
+    )?;
+
+    for rows in table.rows.chunks(ROW_GROUP_SIZE) {
+        let mut row_group = writer.next_row_group()?;
+        let mut index = 0;
+
+        while let Some(mut column) = row_group.next_column()? {
+            match table.columns[index].column_type {
+                ExportColumnType::Bool => {
+                    write_column::<BoolType>(&mut column, rows, index, |value| match value {
+                        ExportValue::Bool(v) => Some(*v),
+                        _ => None,
+                    })?
+                }
+                ExportColumnType::Int => {
+                    write_column::<Int64Type>(&mut column, rows, index, |value| match value {
+                        ExportValue::Int(v) => Some(*v),
+                        _ => None,
+                    })?
+                }
+                ExportColumnType::Float => {
+                    write_column::<DoubleType>(&mut column, rows, index, |value| match value {
+                        ExportValue::Float(v) => Some(*v),
+                        _ => None,
+                    })?
+                }
+                ExportColumnType::Text => {
+                    write_column::<ByteArrayType>(&mut column, rows, index, |value| match value {
+                        ExportValue::Text(v) => Some(ByteArray::from(v.as_str())),
+                        _ => None,
+                    })?
+                }
+                ExportColumnType::Date => {
+                    write_column::<Int32Type>(&mut column, rows, index, |value| match value {
+                        ExportValue::Date(v) => Some(days_since_epoch(v)),
+                        _ => None,
+                    })?
+                }
+                ExportColumnType::Timestamp => {
+                    write_column::<Int64Type>(&mut column, rows, index, |value| match value {
+                        ExportValue::Timestamp(v) => Some(v.and_utc().timestamp_micros()),
+                        _ => None,
+                    })?
+                }
+            }
+
+            column.close()?;
+            index += 1;
+        }
+
+        row_group.close()?;
+    }
+
+    writer.close()?;
+    Ok(())
+}
+
+fn column_schema(column: &ExportColumn) -> Result<Type> {
+    let (physical_type, logical_type) = match column.column_type {
+        ExportColumnType::Bool => (PhysicalType::BOOLEAN, None),
+        ExportColumnType::Int => (PhysicalType::INT64, None),
+        ExportColumnType::Float => (PhysicalType::DOUBLE, None),
+        ExportColumnType::Text => (PhysicalType::BYTE_ARRAY, Some(LogicalType::String)),
+        ExportColumnType::Date => (PhysicalType::INT32, Some(LogicalType::Date)),
+        ExportColumnType::Timestamp => (
+            PhysicalType::INT64,
+            Some(LogicalType::Timestamp {
+                is_adjusted_to_u_t_c: false,
+                unit: TimeUnit::MICROS(MicroSeconds {}),
+            }),
+        ),
+    };
+
+    Ok(Type::primitive_type_builder(&column.header, physical_type)
+        .with_repetition(Repetition::OPTIONAL)
+        .with_logical_type(logical_type)
+        .build()?)
+}
+
+// Writes the non-null values of one column, with a definition level of 0 marking each null.
+fn write_column<T: ParquetDataType>(
+    column: &mut SerializedColumnWriter<'_>,
+    rows: &[Vec<ExportValue>],
+    index: usize,
+    value: impl Fn(&ExportValue) -> Option<T::T>,
+) -> Result<()> {
+    let mut values = Vec::with_capacity(rows.len());
+    let mut def_levels = Vec::with_capacity(rows.len());
+
+    for row in rows {
+        match value(&row[index]) {
+            Some(value) => {
+                values.push(value);
+                def_levels.push(1);
+            }
+            None => def_levels.push(0),
+        }
+    }
+
+    column
+        .typed::<T>()
+        .write_batch(&values, Some(&def_levels), None)?;
+    Ok(())
+}
+
+fn days_since_epoch(date: &NaiveDate) -> i32 {
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap_or_default();
+    (*date - epoch).num_days() as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use std::io::{Seek, SeekFrom};
+
+    #[test]
+    fn test_write_parquet_round_trip() {
+        let table = ExportTable {
+            columns: vec![
+                ExportColumn {
+                    header: "Region".to_string(),
+                    column_type: ExportColumnType::Text,
+                    format: None,
+                },
+                ExportColumn {
+                    header: "revenue".to_string(),
+                    column_type: ExportColumnType::Float,
+                    format: None,
+                },
+                ExportColumn {
+                    header: "day".to_string(),
+                    column_type: ExportColumnType::Date,
+                    format: None,
+                },
+            ],
+            rows: vec![
+                vec![
+                    ExportValue::Text("EMEA".to_string()),
+                    ExportValue::Float(12.5),
+                    ExportValue::Date(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()),
+                ],
+                vec![
+                    ExportValue::Text("APAC".to_string()),
+                    ExportValue::Null,
+                    ExportValue::Null,
+                ],
+            ],
+        };
+
+        let mut file = tempfile::tempfile().unwrap();
+        write_parquet(&table, &mut file).unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+
+        let reader = SerializedFileReader::new(file).unwrap();
+        let metadata = reader.metadata().file_metadata();
+        assert_eq!(metadata.num_rows(), 2);
+
+        let fields: Vec<&str> = metadata
+            .schema_descr()
+            .columns()
+            .iter()
+            .map(|column| column.name())
+            .collect();
+        assert_eq!(fields, vec!["Region", "revenue", "day"]);
+
+        let rows: Vec<String> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|row| row.unwrap().to_string())
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                "{Region: \"EMEA\", revenue: 12.5, day: 2024-01-01}",
+                "{Region: \"APAC\", revenue: null, day: null}",
+            ]
+        );
+    }
+}
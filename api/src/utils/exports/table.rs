@@ -0,0 +1,306 @@
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{NaiveDate, NaiveDateTime};
+use indexmap::IndexMap;
+use serde_json::Value;
+
+use crate::utils::{charting::types::ColumnLabelFormat, query_engine::data_types::DataType};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExportValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+    Date(NaiveDate),
+    Timestamp(NaiveDateTime),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportColumnType {
+    Bool,
+    Int,
+    Float,
+    Text,
+    Date,
+    Timestamp,
+}
+
+pub struct ExportColumn {
+    pub header: String,
+    pub column_type: ExportColumnType,
+    pub format: Option<ColumnLabelFormat>,
+}
+
+/// A query result ready to be written out, with one type per column.
+pub struct ExportTable {
+    pub columns: Vec<ExportColumn>,
+    pub rows: Vec<Vec<ExportValue>>,
+}
+
+impl ExportValue {
+    /// The value as it is written to text formats. Nulls have no text.
+    pub fn to_text(&self) -> Option<String> {
+        match self {
+            ExportValue::Null => None,
+            ExportValue::Bool(v) => Some(v.to_string()),
+            ExportValue::Int(v) => Some(v.to_string()),
+            ExportValue::Float(v) => Some(v.to_string()),
+            ExportValue::Text(v) => Some(v.clone()),
+            ExportValue::Date(v) => Some(v.format("%Y-%m-%d").to_string()),
+            ExportValue::Timestamp(v) => Some(v.format("%Y-%m-%d %H:%M:%S%.f").to_string()),
+        }
+    }
+
+    fn column_type(&self) -> Option<ExportColumnType> {
+        match self {
+            ExportValue::Null => None,
+            ExportValue::Bool(_) => Some(ExportColumnType::Bool),
+            ExportValue::Int(_) => Some(ExportColumnType::Int),
+            ExportValue::Float(_) => Some(ExportColumnType::Float),
+            ExportValue::Text(_) => Some(ExportColumnType::Text),
+            ExportValue::Date(_) => Some(ExportColumnType::Date),
+            ExportValue::Timestamp(_) => Some(ExportColumnType::Timestamp),
+        }
+    }
+
+    fn coerce(self, column_type: ExportColumnType) -> Self {
+        match (self, column_type) {
+            (ExportValue::Int(v), ExportColumnType::Float) => ExportValue::Float(v as f64),
+            (ExportValue::Date(v), ExportColumnType::Timestamp) => {
+                ExportValue::Timestamp(v.and_hms_opt(0, 0, 0).unwrap_or_default())
+            }
+            (value, ExportColumnType::Text) => match value.to_text() {
+                Some(text) => ExportValue::Text(text),
+                None => ExportValue::Null,
+            },
+            (value, _) => value,
+        }
+    }
+}
+
+impl ExportTable {
+    /// Builds an export from query rows, applying the metric's `columnLabelFormats`: display names
+    /// become headers, numbers are multiplied by their `multiplier` and nulls are replaced with
+    /// `replaceMissingDataWith`. Other display settings are left to the file format to apply.
+    pub fn from_rows(rows: Vec<IndexMap<String, DataType>>, chart_config: Option<&Value>) -> Self {
+        let column_ids: Vec<String> = match rows.first() {
+            Some(row) => row.keys().cloned().collect(),
+            None => Vec::new(),
+        };
+
+        let mut formats = column_label_formats(chart_config);
+
+        let mut values: Vec<Vec<ExportValue>> = rows
+            .into_iter()
+            .map(|mut row| {
+                column_ids
+                    .iter()
+                    .map(|id| match row.swap_remove(id) {
+                        Some(value) => export_value(value),
+                        None => ExportValue::Null,
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut columns = Vec::with_capacity(column_ids.len());
+        let mut headers = HashSet::new();
+
+        for (index, id) in column_ids.iter().enumerate() {
+            let format = formats.remove(id);
+
+            if let Some(format) = &format {
+                for row in values.iter_mut() {
+                    row[index] = apply_label_format(
+                        std::mem::replace(&mut row[index], ExportValue::Null),
+                        format,
+                    );
+                }
+            }
+
+            let column_type = infer_column_type(values.iter().map(|row| &row[index]));
+
+            for row in values.iter_mut() {
+                row[index] =
+                    std::mem::replace(&mut row[index], ExportValue::Null).coerce(column_type);
+            }
+
+            let header = match format.as_ref().and_then(|f| f.display_name.as_ref()) {
+                Some(display_name) if !display_name.trim().is_empty() => {
+                    display_name.trim().to_string()
+                }
+                _ => id.clone(),
+            };
+
+            columns.push(ExportColumn {
+                header: unique_header(header, &mut headers),
+                column_type,
+                format,
+            });
+        }
+
+        ExportTable {
+            columns,
+            rows: values,
+        }
+    }
+}
+
+fn column_label_formats(chart_config: Option<&Value>) -> HashMap<String, ColumnLabelFormat> {
+    chart_config
+        .and_then(|config| config.get("columnLabelFormats"))
+        .and_then(|formats| formats.as_object())
+        .into_iter()
+        .flatten()
+        .filter_map(|(column, format)| {
+            serde_json::from_value::<ColumnLabelFormat>(format.clone())
+                .ok()
+                .map(|format| (column.clone(), format))
+        })
+        .collect()
+}
+
+fn export_value(value: DataType) -> ExportValue {
+    let value = match value {
+        DataType::Bool(v) => v.map(ExportValue::Bool),
+        DataType::Int8(v) => v.map(ExportValue::Int),
+        DataType::Int4(v) => v.map(|v| ExportValue::Int(v as i64)),
+        DataType::Int2(v) => v.map(|v| ExportValue::Int(v as i64)),
+        DataType::Oid(v) => v.map(|v| ExportValue::Int(v as i64)),
+        DataType::Float4(v) => v.map(|v| ExportValue::Float(v as f64)),
+        DataType::Float8(v) => v.map(ExportValue::Float),
+        DataType::Decimal(v) => v
+            .and_then(|v| f64::from_str(&v.to_string()).ok())
+            .map(ExportValue::Float),
+        DataType::Text(v) | DataType::Char(v) | DataType::Unknown(v) => v.map(ExportValue::Text),
+        DataType::Uuid(v) => v.map(|v| ExportValue::Text(v.to_string())),
+        DataType::Bytea(v) => v.map(|v| ExportValue::Text(STANDARD.encode(v))),
+        DataType::Timestamp(v) => v.map(ExportValue::Timestamp),
+        DataType::Timestamptz(v) => v.map(|v| ExportValue::Timestamp(v.naive_utc())),
+        DataType::Date(v) => v.map(ExportValue::Date),
+        DataType::Time(v) => v.map(|v| ExportValue::Text(v.to_string())),
+        DataType::Json(v) => v.map(|v| match v {
+            Value::String(s) => ExportValue::Text(s),
+            v => ExportValue::Text(v.to_string()),
+        }),
+        DataType::Null => None,
+    };
+
+    match value {
+        Some(ExportValue::Float(v)) if !v.is_finite() => ExportValue::Null,
+        Some(value) => value,
+        None => ExportValue::Null,
+    }
+}
+
+fn apply_label_format(value: ExportValue, format: &ColumnLabelFormat) -> ExportValue {
+    let multiplier = format.multiplier.filter(|m| m.is_finite() && *m != 1.0);
+
+    match (value, multiplier) {
+        (ExportValue::Null, _) => match &format.replace_missing_data_with {
+            Some(Value::Number(n)) => match n.as_i64() {
+                Some(n) => ExportValue::Int(n),
+                None => n
+                    .as_f64()
+                    .map(ExportValue::Float)
+                    .unwrap_or(ExportValue::Null),
+            },
+            Some(Value::String(s)) => ExportValue::Text(s.clone()),
+            _ => ExportValue::Null,
+        },
+        (ExportValue::Int(v), Some(m)) => ExportValue::Float(v as f64 * m),
+        (ExportValue::Float(v), Some(m)) => ExportValue::Float(v * m),
+        (value, _) => value,
+    }
+}
+
+// Columns keep their type when every value agrees. Integers widen to floats and dates to
+// timestamps; any other mix is exported as text.
+fn infer_column_type<'a>(values: impl Iterator<Item = &'a ExportValue>) -> ExportColumnType {
+    let mut column_type = None;
+
+    for value_type in values.filter_map(|value| value.column_type()) {
+        column_type = Some(match (column_type, value_type) {
+            (None, value_type) => value_type,
+            (Some(current), value_type) if current == value_type => current,
+            (Some(ExportColumnType::Int), ExportColumnType::Float)
+            | (Some(ExportColumnType::Float), ExportColumnType::Int) => ExportColumnType::Float,
+            (Some(ExportColumnType::Date), ExportColumnType::Timestamp)
+            | (Some(ExportColumnType::Timestamp), ExportColumnType::Date) => {
+                ExportColumnType::Timestamp
+            }
+            _ => return ExportColumnType::Text,
+        });
+    }
+
+    column_type.unwrap_or(ExportColumnType::Text)
+}
+
+fn unique_header(header: String, headers: &mut HashSet<String>) -> String {
+    let mut unique = header.clone();
+    let mut suffix = 2;
+
+    while !headers.insert(unique.to_lowercase()) {
+        unique = format!("{} ({})", header, suffix);
+        suffix += 1;
+    }
+
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_export_table_applies_label_formats() {
+        let rows = vec![
+            IndexMap::from([
+                (
+                    "region".to_string(),
+                    DataType::Text(Some("EMEA".to_string())),
+                ),
+                ("conversion".to_string(), DataType::Float8(Some(0.25))),
+                ("orders".to_string(), DataType::Int8(Some(12))),
+            ]),
+            IndexMap::from([
+                (
+                    "region".to_string(),
+                    DataType::Text(Some("APAC".to_string())),
+                ),
+                ("conversion".to_string(), DataType::Float8(None)),
+                ("orders".to_string(), DataType::Float8(Some(7.5))),
+            ]),
+        ];
+
+        let chart_config = json!({
+            "columnLabelFormats": {
+                "region": { "style": "string", "displayName": "Region" },
+                "conversion": {
+                    "style": "percent",
+                    "displayName": "Region",
+                    "multiplier": 100,
+                    "replaceMissingDataWith": 0
+                }
+            }
+        });
+
+        let table = ExportTable::from_rows(rows, Some(&chart_config));
+
+        let headers: Vec<&str> = table.columns.iter().map(|c| c.header.as_str()).collect();
+        assert_eq!(headers, vec!["Region", "Region (2)", "orders"]);
+
+        assert_eq!(table.columns[1].column_type, ExportColumnType::Float);
+        assert_eq!(table.rows[0][1], ExportValue::Float(25.0));
+        assert_eq!(table.rows[1][1], ExportValue::Float(0.0));
+
+        // Integer and float values in one column widen to floats.
+        assert_eq!(table.columns[2].column_type, ExportColumnType::Float);
+        assert_eq!(table.rows[0][2], ExportValue::Float(12.0));
+    }
+}
@@ -0,0 +1,256 @@
+use std::io::{Seek, Write};
+
+use anyhow::{anyhow, Result};
+use rust_xlsxwriter::{Format, Workbook};
+
+use super::table::{ExportColumn, ExportColumnType, ExportTable, ExportValue};
+
+/// The most rows a worksheet can hold, less one for the header.
+pub const XLSX_MAX_ROWS: usize = 1_048_575;
+const XLSX_MAX_COLUMNS: usize = 16_384;
+const XLSX_MAX_CELL_LENGTH: usize = 32_767;
+
+/// Writes the table as a single-sheet workbook. Numeric columns get an Excel number format built
+/// from their label format, so currency, percent and separator settings show as they do in Buster
+/// while the cells keep their raw values.
+pub fn write_xlsx<W: Write + Seek + Send>(
+    table: &ExportTable,
+    sheet_name: &str,
+    writer: W,
+) -> Result<()> {
+    if table.rows.len() > XLSX_MAX_ROWS {
+        return Err(anyhow!(
+            "XLSX exports are limited to {} rows",
+            XLSX_MAX_ROWS
+        ));
+    }
+    if table.columns.len() > XLSX_MAX_COLUMNS {
+        return Err(anyhow!(
+            "XLSX exports are limited to {} columns",
+            XLSX_MAX_COLUMNS
+        ));
+    }
+
+    let header_format = Format::new().set_bold();
+    let column_formats: Vec<Option<Format>> = table
+        .columns
+        .iter()
+        .map(|column| number_format(column).map(|code| Format::new().set_num_format(code)))
+        .collect();
+
+    let mut workbook = Workbook::new();
+
+    // Rows are written in order, so the sheet can be flushed as it goes instead of held in memory.
+    let worksheet = workbook.add_worksheet_with_constant_memory();
+    worksheet.set_name(worksheet_name(sheet_name))?;
+    worksheet.set_freeze_panes(1, 0)?;
+
+    for (index, column) in table.columns.iter().enumerate() {
+        worksheet.write_string_with_format(
+            0,
+            index as u16,
+            cell_text(&column.header),
+            &header_format,
+        )?;
+    }
+
+    for (row_index, row) in table.rows.iter().enumerate() {
+        let row_number = row_index as u32 + 1;
+
+        for (index, value) in row.iter().enumerate() {
+            let column = index as u16;
+            let format = column_formats[index].as_ref();
+
+            match (value, format) {
+                (ExportValue::Null, _) => continue,
+                (ExportValue::Bool(v), _) => worksheet.write_boolean(row_number, column, *v)?,
+                (ExportValue::Int(v), Some(format)) => {
+                    worksheet.write_number_with_format(row_number, column, *v as f64, format)?
+                }
+                (ExportValue::Int(v), None) => {
+                    worksheet.write_number(row_number, column, *v as f64)?
+                }
+                (ExportValue::Float(v), Some(format)) => {
+                    worksheet.write_number_with_format(row_number, column, *v, format)?
+                }
+                (ExportValue::Float(v), None) => worksheet.write_number(row_number, column, *v)?,
+                (ExportValue::Date(v), Some(format)) => {
+                    worksheet.write_datetime_with_format(row_number, column, v, format)?
+                }
+                (ExportValue::Date(v), None) => worksheet.write_datetime(row_number, column, v)?,
+                (ExportValue::Timestamp(v), Some(format)) => {
+                    worksheet.write_datetime_with_format(row_number, column, v, format)?
+                }
+                (ExportValue::Timestamp(v), None) => {
+                    worksheet.write_datetime(row_number, column, v)?
+                }
+                (ExportValue::Text(v), _) => {
+                    worksheet.write_string(row_number, column, cell_text(v))?
+                }
+            };
+        }
+    }
+
+    workbook.save_to_writer(writer)?;
+    Ok(())
+}
+
+/// The Excel number format for a column, if it needs one.
+fn number_format(column: &ExportColumn) -> Option<String> {
+    match column.column_type {
+        ExportColumnType::Date => return Some("yyyy-mm-dd".to_string()),
+        ExportColumnType::Timestamp => return Some("yyyy-mm-dd hh:mm:ss".to_string()),
+        ExportColumnType::Int | ExportColumnType::Float => (),
+        _ => return None,
+    }
+
+    let format = column.format.as_ref()?;
+    let style = format.style.as_deref().unwrap_or("number");
+
+    let default_digits = match (style, column.column_type) {
+        ("currency", _) => 2,
+        (_, ExportColumnType::Int) => 0,
+        _ => 2,
+    };
+    let digits = format
+        .maximum_fraction_digits
+        .unwrap_or(default_digits)
+        .max(format.minimum_fraction_digits.unwrap_or(0))
+        .clamp(0, 10) as usize;
+
+    let grouped = match style {
+        "currency" => true,
+        "number" => format.number_separator_style.as_deref() == Some(","),
+        "percent" => false,
+        _ => return None,
+    };
+
+    let mut code = match grouped {
+        true => "#,##0".to_string(),
+        false => "0".to_string(),
+    };
+    if digits > 0 {
+        code.push('.');
+        code.push_str(&"0".repeat(digits));
+    }
+
+    match style {
+        "currency" => {
+            let symbol = match format.currency.as_deref().unwrap_or("USD") {
+                "USD" => "$".to_string(),
+                "EUR" => "€".to_string(),
+                "GBP" => "£".to_string(),
+                "JPY" => "¥".to_string(),
+                "INR" => "₹".to_string(),
+                other => format!("{} ", other),
+            };
+            Some(format!("{}{}", format_literal(&symbol), code))
+        }
+        _ => {
+            let prefix = format.prefix.as_deref().unwrap_or("");
+            let mut suffix = format.suffix.clone().unwrap_or_default();
+            if style == "percent" {
+                suffix.push('%');
+            }
+            Some(format!(
+                "{}{}{}",
+                format_literal(prefix),
+                code,
+                format_literal(&suffix)
+            ))
+        }
+    }
+}
+
+// Quoted text in a number format. A percent sign is quoted too, so Excel doesn't scale the value.
+fn format_literal(text: &str) -> String {
+    match text.is_empty() {
+        true => String::new(),
+        false => format!("\"{}\"", text.replace('"', "")),
+    }
+}
+
+// Sheet names are at most 31 characters and can't contain []:*?/\ or start or end with a quote.
+fn worksheet_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .filter(|c| !matches!(c, '[' | ']' | ':' | '*' | '?' | '/' | '\\'))
+        .take(31)
+        .collect();
+    let name = name.trim().trim_matches('\'');
+
+    match name.is_empty() {
+        true => "Export".to_string(),
+        false => name.to_string(),
+    }
+}
+
+// Strips characters XML can't hold and truncates to the longest text a cell accepts.
+fn cell_text(text: &str) -> String {
+    text.chars()
+        .filter(|c| {
+            matches!(c, '\t' | '\n' | '\r') || (*c >= ' ' && !matches!(c, '\u{FFFE}' | '\u{FFFF}'))
+        })
+        .take(XLSX_MAX_CELL_LENGTH)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::charting::types::ColumnLabelFormat;
+    use serde_json::json;
+
+    #[test]
+    fn test_xlsx_number_formats() {
+        let format: ColumnLabelFormat = serde_json::from_value(json!({
+            "style": "currency",
+            "currency": "EUR",
+            "minimumFractionDigits": 0,
+            "maximumFractionDigits": 0
+        }))
+        .unwrap();
+        let revenue = ExportColumn {
+            header: "Revenue".to_string(),
+            column_type: ExportColumnType::Float,
+            format: Some(format),
+        };
+        assert_eq!(number_format(&revenue).unwrap(), "\"€\"#,##0");
+
+        let format: ColumnLabelFormat = serde_json::from_value(json!({
+            "style": "percent",
+            "maximumFractionDigits": 1
+        }))
+        .unwrap();
+        let conversion = ExportColumn {
+            header: "Conversion".to_string(),
+            column_type: ExportColumnType::Float,
+            format: Some(format),
+        };
+        assert_eq!(number_format(&conversion).unwrap(), "0.0\"%\"");
+    }
+
+    #[test]
+    fn test_write_xlsx() {
+        let table = ExportTable {
+            columns: vec![ExportColumn {
+                header: "Day".to_string(),
+                column_type: ExportColumnType::Date,
+                format: None,
+            }],
+            rows: vec![
+                vec![ExportValue::Date(
+                    chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+                )],
+                vec![ExportValue::Null],
+            ],
+        };
+
+        let mut file = std::io::Cursor::new(Vec::new());
+        write_xlsx(&table, "Signups: [daily]", &mut file).unwrap();
+
+        // Workbooks are zip archives.
+        assert!(file.into_inner().starts_with(b"PK"));
+        assert_eq!(worksheet_name("Signups: [daily]"), "Signups daily");
+    }
+}
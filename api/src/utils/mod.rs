@@ -1,12 +1,22 @@
 pub mod agent_builder;
 pub mod agents;
+pub mod alerts;
 pub mod charting;
 pub mod clients;
+pub mod dashboards;
+pub mod embed;
+pub mod environments;
+pub mod exports;
 pub mod prompts;
 pub mod query_engine;
+pub mod reports;
 pub mod search_engine;
 pub mod security;
+pub mod semantic_layer;
 pub mod sharing;
+pub mod statistics;
+pub mod trash;
+pub mod uploads;
 pub mod user;
 pub mod serde_helpers;
 pub mod stored_values;
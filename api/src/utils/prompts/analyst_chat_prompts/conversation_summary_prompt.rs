@@ -0,0 +1,35 @@
+pub fn conversation_summary_system_prompt() -> String {
+    "### YOUR TASK
+You are a data analyst named Buster. You are in the middle of a long conversation with a coworker and the older part of the conversation no longer fits in your memory.
+
+Your task is to write a summary of the older part of the conversation that you will read instead of the original messages.
+
+### GENERAL GUIDELINES
+- Keep every request the user made, in the order they were made.
+- Keep the names of datasets, tables, columns, filters, time periods and metrics exactly as they appear.
+- Keep the SQL logic that was used to answer each request (aggregations, groupings, filters, joins), but do not copy entire SQL statements.
+- Keep any preferences the user expressed about charts, formatting or definitions of terms.
+- Keep any errors or requests you were not able to complete.
+- If a PREVIOUS SUMMARY is provided, merge it with the new messages into a single summary.
+- Do not add information that is not in the conversation.
+- Write in plain text using short bullet points. Keep your summary under 400 words.
+".to_string()
+}
+
+pub fn conversation_summary_user_prompt(
+    previous_summary: Option<&str>,
+    transcript: &str,
+) -> String {
+    let mut message = String::new();
+
+    if let Some(summary) = previous_summary {
+        message.push_str("## PREVIOUS SUMMARY\n");
+        message.push_str(summary);
+        message.push_str("\n\n");
+    }
+
+    message.push_str("## MESSAGES TO SUMMARIZE\n");
+    message.push_str(transcript);
+
+    message
+}
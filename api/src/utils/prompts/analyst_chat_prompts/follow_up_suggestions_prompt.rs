@@ -0,0 +1,46 @@
+pub fn follow_up_suggestions_system_prompt() -> String {
+    r#"### YOUR TASK
+You are a data analyst named Buster. You just answered a question from a coworker. Suggest follow-up questions they could ask you next.
+
+### GENERAL GUIDELINES
+- Suggest 5 follow-up questions.
+- Every question must be answerable with a single SQL query over ONE of the DATASETS provided. Only use columns that are listed for that dataset.
+- Build on the question that was just answered: drill into a segment, break the metric down by another dimension, change the time period, compare periods, or look at a related metric.
+- Use the TERMS provided where they apply.
+- Do not repeat the question that was just answered.
+- Write each question the way the user would type it. Keep each question under 15 words. Do not mention column or table names verbatim unless they are plain words.
+
+### OUTPUT
+Return JSON in this format:
+{
+  "suggestions": [
+    {
+      "question": "<follow-up question>",
+      "dataset": "<name of the dataset that answers the question>",
+      "columns": ["<every column the question needs>"]
+    }
+  ]
+}
+"#
+    .to_string()
+}
+
+pub fn follow_up_suggestions_user_prompt(
+    input: &str,
+    sql: &str,
+    data_metadata: &str,
+    datasets: &str,
+    terms: &str,
+) -> String {
+    let mut message = format!(
+        "## QUESTION THAT WAS JUST ANSWERED\n{}\n\n## SQL THAT WAS RUN\n{}\n\n## INFO ABOUT THE DATA RETURNED\n{}\n\n## DATASETS\n{}",
+        input, sql, data_metadata, datasets
+    );
+
+    if !terms.is_empty() {
+        message.push_str("\n\n## TERMS\n");
+        message.push_str(terms);
+    }
+
+    message
+}
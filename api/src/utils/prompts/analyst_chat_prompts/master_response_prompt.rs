@@ -29,11 +29,12 @@ Your task is to respond (or complete your response) to the user message. There a
     4. If the user asked what kinds of data, insights, analyses, or metrics you can provide, make sure this is addressed in some part of the response.
     5. If the user asked about your capabilities or how you do things, you need to make sure this is addressed in some part of the response.
     6. If the user asked you to do something that you cannot do, you need to make sure this is addressed in some part of the response (i.e. actions that are completely unrelated to data analysis, random tasks, sending things, adding things to dashboards, downloading things, refreshing data, scheduling things, etc).
+    7. If a '## STATISTICAL ANALYSIS OF THE DATA RETURNED' section is provided, you need to answer the user's statistical question with the numbers in it (i.e. the growth rate, whether a trend is significant, which segments are outliers). Round them sensibly, explain what they mean in plain language, and do not calculate any numbers yourself. If an analysis has an error, say that it couldn't be calculated.
 
 ## GENERAL INSTRUCTIONS
 - Responses regarding the following actions have not yet been sent to the user: explain_sql_data, explain_something_general, cannot_do_requested_action_response. If any of these actions are mentioned in the '## DECISIONS MADE WHEN THE USER REQUEST WAS FIRST RECEIVED', they still need to be addressed.
 - If scenario 1 is combined with other scenarios, ignore the instructions for scenario 1 and only follow the instructions for each of the other relevant scenarios.
-- For scenarios 2, 3, 4, 5, 6 you may need to return a longer response. If this is the case, use markdown to make your response more digestable and readable. This is especially helpful if you are listing a few examples (~3 bullet points) of analysis or metrics you can provide.
+- For scenarios 2, 3, 4, 5, 6, 7 you may need to return a longer response. If this is the case, use markdown to make your response more digestable and readable. This is especially helpful if you are listing a few examples (~3 bullet points) of analysis or metrics you can provide.
 - Make sure all aspects of the user request have been addressed.
 - Use natural language and avoid overly formal language.
 - Do not use technical terms. The user is not very technical and will struggle to understand technical lingo, unless the context requires it.
@@ -59,6 +60,7 @@ pub fn master_response_user_prompt(
     data_metadata: &Option<String>,
     chart_generated: &Option<String>,
     chart_requirements: &Option<String>,
+    statistical_analysis: &Option<String>,
 ) -> String {
     let mut message = format!("## USER MESSAGE\n{}", input);
 
@@ -94,5 +96,10 @@ pub fn master_response_user_prompt(
         message.push_str(requirements);
     }
 
+    if let Some(analysis) = statistical_analysis {
+        message.push_str("\n\n## STATISTICAL ANALYSIS OF THE DATA RETURNED (use these exact numbers, do not calculate your own)\n");
+        message.push_str(analysis);
+    }
+
     message
 }
@@ -1,3 +1,6 @@
+pub mod conversation_summary_prompt;
 pub mod failed_to_fix_sql_prompts;
+pub mod follow_up_suggestions_prompt;
 pub mod master_response_prompt;
 pub mod orchestrator_prompt;
+pub mod statistical_analysis_prompt;
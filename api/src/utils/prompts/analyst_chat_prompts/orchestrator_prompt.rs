@@ -1,4 +1,6 @@
-use serde_json::{json, Value};
+use serde_json::json;
+
+use crate::utils::clients::ai::llm_router::LlmTool;
 
 pub fn orchestrator_system_prompt() -> String {
     String::from(
@@ -15,132 +17,104 @@ pub fn orchestrator_system_prompt() -> String {
 - The modify_visualization action is not capable of filtering something, changing the underlying data, narrowing results, filtering or narrowing the visualization, drilling down, sorting data, grouping data, changing time periods (i.e. daily/weekly/monthly/quarterly/annually/etc), comparing time periods (i.e. compare this week to last week), or changing time frames (i.e. from a single time frame to "over time"). If any of these capabilities are in the user request, you need to use the generate_sql action to accomplish them (do not use the modify_visualization action for these kinds of requests).
 - The default time period for any request is the last year unless the user specifies otherwise.
 - If a data request is vague, you should still use generate_sql. The ticket description for a vague request should remain just as vague as the user request. For example, if the user asks for "our best employee", the ticket description should not include any recommendations for how to calculate the "best employee".
+- If the user asks a statistical question about data (significance of a trend, growth rates, outliers, correlations, period-over-period comparisons), use the generate_sql action to retrieve the data and the analyze_data action to compute the statistics over it.
 - A note about summaries: when a user request asks for a summary, knowing which action to use can be confusing. If the summary is referring to any kind of data, you should use generate_sql. The generate_sql action will include a summary (along with the data it returns) in it's final response to the user.
 
-## OUTPUT
-Output should follow the json schema specified.  Return in this format:
-{
-  "actions": [
-    {
-      "name": "<action_name>",
-      "data_analyst_ticket": "<data_analyst_ticket>"
-    },
-    ...
-  ]
-}
+## TOOLS
+- Each action is a tool. Call the tool for every action you select, with the `data_analyst_ticket` for that action. You can call multiple tools in one turn.
+- Before selecting actions, you can use the `search_values` tool to check whether a name, category or other value the user mentions exists in the data, and the `look_up_term` tool to get the definition of a business term the user mentions that you don't know. Use the results to write more precise tickets.
+- Do not call the same lookup tool with the same arguments more than once.
+- When you have called the tools for all of the actions you need, stop calling tools and reply with a short confirmation. If no action applies to the request, don't call any action tools.
 "#,
     )
 }
-pub fn orchestrator_prompt_schema() -> Value {
-    json!({
-      "name": "actions_selector",
-      "schema": {
-        "actions": {
-          "type": "array",
-          "items": {
-            "anyOf": [
-              {
-                "type": "object",
-                "description": "Use this action to generate or modify a SQL query. **Only one `generate_sql` action should be used per response, even if multiple data requests are present. Combine all data-related requests into a single action.** Use this action whenever a user is making any kind of data request, including historical queries, forecasts, what-if scenarios, or calculations. You can join and combine data from multiple datasets as needed. Even if the user's request is broad or lacks specific details, still use this action. Assume you can access and join any data the user asks for. Also use this action if the user requests sensitive information (e.g., passwords, credit cards). If the user asks for data in a unique format (e.g., dashboard, report), still use this action to provide relevant data. Use this action if the user wants to filter, modify underlying data, narrow results, drill down, sort data, group data, break down information, adjust time periods (e.g., daily, weekly, specified date range), or compare time periods (e.g., this week vs. last week). Never use this to format data. This action should be used for any modifiers or computations over column values.",
-                "properties": {
-                  "name": {
-                    "type": "string",
-                    "enum": [
-                      "generate_sql"
-                    ]
-                  },
-                  "data_analyst_ticket": {
-                    "type": "string",
-                    "description": "A brief description for the data analyst's ticket, explaining which parts of the user's request this action addresses. Copy the user's request exactly without adding instructions, thoughts, or assumptions. Write it as a command, not a question, typically starting with an imperative verb like 'Retrieve...', 'Provide...', 'Filter...', etc."
-                  }
-                }
-              },
-              {
-                "type": "object",
-                "description": "Use this action if the user specifically mentions how they would like to format, create, or modify a visualization or chart. This action can select or change the visualization type to supported charts like table visualizations, line charts, bar charts, histograms, pie charts, metric cards, or scatter plots. If any of these charts are mentioned, include this action in your output. This action can also edit the styling of these visualizations if specified by the user. However, this action cannot filter data, modify underlying data, narrow results, drill down, sort data, group data, change time periods, edit axis values, compare time periods, or adjust time frames. For these capabilities, use the `generate_sql` action instead and omit the `modify_visualization` action. This action can add a multipier to a column's values, but cannot do other aggregations or computations. For any of the other computations, use the `generate_sql` action instead and omit the `modify_visualization` action.",
-                "properties": {
-                  "name": {
-                    "type": "string",
-                    "enum": [
-                      "modify_visualization"
-                    ]
-                  },
-                  "data_analyst_ticket": {
-                    "type": "string",
-                    "description": "A brief description for the data analyst's ticket, specifying which parts of the user's request this action addresses. Rewrite the user's visualization request concisely, removing unnecessary details. Do not specify the data to be used in the visualization. For example, if a user requests 'Show me our total spend broken down by expense category and put it on a line chart', the ticket description should reference only the line chart."
-                  }
-                }
-              },
-              {
-                "type": "object",
-                "description": "Use this action when a user requests a chart or visualization not supported by the available chart types. Supported chart types are: table visualization, line chart (multi-axes, multi-line, single-line, area), bar chart (horizontal, vertical, stacked, grouped), histogram, pie/donut chart, metric card, combo chart, and scatter plot. Unsupported chart types include: heatmap, sankey, radial, treemap, sunburst, funnel, candlestick, waterfall, word cloud, and geographical maps. Don't worry about other stylistic details, just the core chart the user is asking for.",
-                "properties": {
-                  "name": {
-                    "type": "string",
-                    "enum": [
-                      "chart_requested_but_not_compatible"
-                    ]
-                  },
-                  "data_analyst_ticket": {
-                    "type": "string",
-                    "description": "A brief description for the data analyst's ticket, explaining which parts of the user's request this action addresses, and clarifying that the requested chart type is not supported."
-                  }
-                }
-              },
-              {
-                "type": "object",
-                "description": "Use this action when the user asks you to perform an action outside your capabilities and you need to inform them that you cannot do it. This includes requests like sending an email, writing a document, adding items to a dashboard, generating an entire dashboard, scheduling reports, or configuring data updates. If the user's request relates to querying data or modifying/creating a visualization, assume the data analyst can handle it and use the appropriate actions.",
-                "properties": {
-                  "name": {
-                    "type": "string",
-                    "enum": [
-                      "cannot_do_requested_action_response"
-                    ]
-                  },
-                  "data_analyst_ticket": {
-                    "type": "string",
-                    "description": "A brief description for the data analyst's ticket, explaining which parts of the user's request this action addresses and why it should be used. Do not generate a response for the user; the data analyst will provide one. If the user asks you to create a dashboard, use `generate_sql` to provide a relevant metric and select this action to explain that you cannot generate entire dashboards."
-                  }
-                }
-              },
-              {
+
+/// The tools the orchestrator can call. The action tools are recorded as the actions to take,
+/// the lookup tools are run while the orchestrator is deciding.
+pub fn orchestrator_tools() -> Vec<LlmTool> {
+    vec![
+        action_tool(
+            "generate_sql",
+            "Use this action to generate or modify a SQL query. **Only one `generate_sql` action should be used per response, even if multiple data requests are present. Combine all data-related requests into a single action.** Use this action whenever a user is making any kind of data request, including historical queries, forecasts, what-if scenarios, or calculations. You can join and combine data from multiple datasets as needed. Even if the user's request is broad or lacks specific details, still use this action. Assume you can access and join any data the user asks for. Also use this action if the user requests sensitive information (e.g., passwords, credit cards). If the user asks for data in a unique format (e.g., dashboard, report), still use this action to provide relevant data. Use this action if the user wants to filter, modify underlying data, narrow results, drill down, sort data, group data, break down information, adjust time periods (e.g., daily, weekly, specified date range), or compare time periods (e.g., this week vs. last week). Never use this to format data. This action should be used for any modifiers or computations over column values.",
+            "A brief description for the data analyst's ticket, explaining which parts of the user's request this action addresses. Copy the user's request exactly without adding instructions, thoughts, or assumptions. Write it as a command, not a question, typically starting with an imperative verb like 'Retrieve...', 'Provide...', 'Filter...', etc.",
+        ),
+        action_tool(
+            "modify_visualization",
+            "Use this action if the user specifically mentions how they would like to format, create, or modify a visualization or chart. This action can select or change the visualization type to supported charts like table visualizations, line charts, bar charts, histograms, pie charts, metric cards, or scatter plots. If any of these charts are mentioned, include this action in your output. This action can also edit the styling of these visualizations if specified by the user. However, this action cannot filter data, modify underlying data, narrow results, drill down, sort data, group data, change time periods, edit axis values, compare time periods, or adjust time frames. For these capabilities, use the `generate_sql` action instead and omit the `modify_visualization` action. This action can add a multipier to a column's values, but cannot do other aggregations or computations. For any of the other computations, use the `generate_sql` action instead and omit the `modify_visualization` action.",
+            "A brief description for the data analyst's ticket, specifying which parts of the user's request this action addresses. Rewrite the user's visualization request concisely, removing unnecessary details. Do not specify the data to be used in the visualization. For example, if a user requests 'Show me our total spend broken down by expense category and put it on a line chart', the ticket description should reference only the line chart.",
+        ),
+        action_tool(
+            "chart_requested_but_not_compatible",
+            "Use this action when a user requests a chart or visualization not supported by the available chart types. Supported chart types are: table visualization, line chart (multi-axes, multi-line, single-line, area), bar chart (horizontal, vertical, stacked, grouped), histogram, pie/donut chart, metric card, combo chart, and scatter plot. Unsupported chart types include: heatmap, sankey, radial, treemap, sunburst, funnel, candlestick, waterfall, word cloud, and geographical maps. Don't worry about other stylistic details, just the core chart the user is asking for.",
+            "A brief description for the data analyst's ticket, explaining which parts of the user's request this action addresses, and clarifying that the requested chart type is not supported.",
+        ),
+        action_tool(
+            "cannot_do_requested_action_response",
+            "Use this action when the user asks you to perform an action outside your capabilities and you need to inform them that you cannot do it. This includes requests like sending an email, writing a document, adding items to a dashboard, generating an entire dashboard, scheduling reports, or configuring data updates. If the user's request relates to querying data or modifying/creating a visualization, assume the data analyst can handle it and use the appropriate actions.",
+            "A brief description for the data analyst's ticket, explaining which parts of the user's request this action addresses and why it should be used. Do not generate a response for the user; the data analyst will provide one. If the user asks you to create a dashboard, use `generate_sql` to provide a relevant metric and select this action to explain that you cannot generate entire dashboards.",
+        ),
+        action_tool(
+            "explain_something_general",
+            "Use this action when a user asks you to explain what you can or cannot do (your capabilities). Use this action when a user asks you to explain what kinds of data, insights, or metrics you can provide (i.e. 'what insights can you offer about x?')",
+            "A brief description for the data analyst's ticket, summarizing the user's request without adding extra instructions, thoughts, or assumptions. This should briefly describe the user's request without adding additional instructions, thoughts, or assumptions. It shouldn't go into any details that aren't directly mentioned in the user request. Write it as a command, starting with an imperative verb like 'Explain...', etc.",
+        ),
+        action_tool(
+            "explain_sql_data",
+            "Use this action when the user is explicitly asking for an explanation of the data that will be or was returned by the SQL statement that will be or was created by the generate_sql action. This includes explaining how the data is calculated or details about the SQL query's results.",
+            "A brief description for the data analyst's ticket, summarizing the user's request without adding extra instructions, thoughts, or assumptions. This should briefly describe the user's request without adding additional instructions, thoughts, or assumptions. It shouldn't go into any details that aren't directly mentioned in the user request. Write it as a command, starting with an imperative verb like 'Explain...', etc.",
+        ),
+        action_tool(
+            "analyze_data",
+            "Use this action, together with the generate_sql action, when the user asks for a statistical answer about the data: whether a trend is significant, growth rates (i.e. YoY, MoM), moving averages, outliers or anomalies, correlations between two measures, or comparisons between periods. The statistics are computed over the results of the generate_sql action, so the generate_sql ticket must ask for the data the analysis needs (i.e. a time series for a trend, one row per segment for outliers). Do not use this action for simple totals, counts or averages, which the generate_sql action can calculate on its own.",
+            "A brief description for the data analyst's ticket, stating which statistical question the user asked. Copy the user's request without adding instructions, thoughts, or assumptions. Write it as a command, starting with an imperative verb like 'Calculate...', 'Test...', 'Find...', etc.",
+        ),
+        LlmTool {
+            name: "search_values".to_string(),
+            description: "Use this tool to search the values stored for the datasets' columns, such as names, categories, statuses or locations. Use it when the user mentions a specific value and you need to know whether it exists and how it is spelled in the data. Returns the matching values along with the column they belong to.".to_string(),
+            parameters: json!({
                 "type": "object",
-                "description": "Use this action when a user asks you to explain what you can or cannot do (your capabilities). Use this action when a user asks you to explain what kinds of data, insights, or metrics you can provide (i.e. 'what insights can you offer about x?')",
                 "properties": {
-                  "name": {
-                    "type": "string",
-                    "enum": [
-                      "explain_something_general"
-                    ]
-                  },
-                  "data_analyst_ticket": {
-                    "type": "string",
-                    "description": "A brief description for the data analyst's ticket, summarizing the user's request without adding extra instructions, thoughts, or assumptions. This should briefly describe the user's request without adding additional instructions, thoughts, or assumptions. It shouldn't go into any details that aren't directly mentioned in the user request. Write it as a command, starting with an imperative verb like 'Explain...', etc."
-                  }
-                }
-              },
-              {
+                    "search_terms": {
+                        "type": "array",
+                        "items": {
+                            "type": "string"
+                        },
+                        "description": "The values to search for, exactly as the user wrote them."
+                    }
+                },
+                "required": ["search_terms"]
+            }),
+        },
+        LlmTool {
+            name: "look_up_term".to_string(),
+            description: "Use this tool to look up the definition of a business term or metric the user mentions (i.e. 'churn', 'active customer', 'ARR'). Returns the definition and the SQL snippet used to calculate it, if the organization has defined the term.".to_string(),
+            parameters: json!({
                 "type": "object",
-                "description": "Use this action when the user is explicitly asking for an explanation of the data that will be or was returned by the SQL statement that will be or was created by the generate_sql action. This includes explaining how the data is calculated or details about the SQL query's results.",
                 "properties": {
-                  "name": {
-                    "type": "string",
-                    "enum": [
-                      "explain_sql_data"
-                    ]
-                  },
-                  "data_analyst_ticket": {
+                    "term": {
+                        "type": "string",
+                        "description": "The term to look up."
+                    }
+                },
+                "required": ["term"]
+            }),
+        },
+    ]
+}
+
+fn action_tool(name: &str, description: &str, ticket_description: &str) -> LlmTool {
+    LlmTool {
+        name: name.to_string(),
+        description: description.to_string(),
+        parameters: json!({
+            "type": "object",
+            "properties": {
+                "data_analyst_ticket": {
                     "type": "string",
-                    "description": "A brief description for the data analyst's ticket, summarizing the user's request without adding extra instructions, thoughts, or assumptions. This should briefly describe the user's request without adding additional instructions, thoughts, or assumptions. It shouldn't go into any details that aren't directly mentioned in the user request. Write it as a command, starting with an imperative verb like 'Explain...', etc."
-                  }
+                    "description": ticket_description
                 }
-              }
-            ]
-          }
-        },
-        "type": "object",
-        "properties": {},
-        "required": []
-      }
-    })
+            },
+            "required": ["data_analyst_ticket"]
+        }),
+    }
 }
@@ -8,6 +8,7 @@ Here are some general instructions:
 - Your task is to identify all datasets that could be useful when combined to answer the user's request
 - If the user requests advanced analysis like predictions, forecasts, correlation, impact analysis, etc., identify all datasets that could be combined for the analysis
 - Consider relationships between datasets and how they can be joined to provide comprehensive answers
+- Only combine datasets that are connected through the RELATIONSHIPS listed below, directly or through other datasets
         
 ### DATASET/MODEL INFORMATION
 {}"#,
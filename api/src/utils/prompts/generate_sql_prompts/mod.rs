@@ -1,4 +1,5 @@
 pub mod dataset_selector_prompt;
 pub mod multiple_datasets_prompt;
+pub mod semantic_query_prompt;
 pub mod sql_gen_prompt;
 pub mod sql_gen_thought_prompt;
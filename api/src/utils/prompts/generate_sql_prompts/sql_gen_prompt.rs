@@ -14,6 +14,7 @@ Generate a **single** {} query based on the provided analysis plan.
 - Do not include explanations or commentary
 - Do not suggest using other platforms or tools
 - Only join tables with explicit entity relationships
+- When a JOIN PLAN is provided, use exactly those joins and follow its warnings about repeated rows
 - Stay within the provided dataset
 
 # SQL REQUIREMENTS
@@ -36,6 +36,7 @@ End with:
 
 # CONSTRAINTS
 - Only join tables with explicit entity relationships
+- When a JOIN PLAN is provided, use exactly those joins and follow its warnings about repeated rows
 - Stay within the provided dataset
 - Prioritize data quality and accuracy
 - Follow user-specified visualization requirements if given
@@ -2,4 +2,5 @@ pub mod analyst_chat_prompts;
 pub mod custom_response_prompts;
 pub mod generate_sql_prompts;
 pub mod modify_visualization_prompts;
+pub mod prompt_registry;
 pub mod sql_evaluator_prompts;
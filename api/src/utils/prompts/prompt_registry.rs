@@ -0,0 +1,452 @@
+use std::{
+    collections::HashMap,
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use diesel::{BoolExpressionMethods, ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+use lazy_static::lazy_static;
+use uuid::Uuid;
+
+use crate::database::{lib::get_pg_pool, models::PromptTemplate, schema::prompt_templates};
+
+// Templates are cached per prompt and organization so agents don't hit the database on every call.
+const CACHE_TTL: Duration = Duration::from_secs(60);
+
+type TemplateCache = HashMap<(String, Uuid), (Instant, Vec<PromptTemplate>)>;
+
+lazy_static! {
+    static ref TEMPLATE_CACHE: RwLock<TemplateCache> = RwLock::new(HashMap::new());
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptSource {
+    Code,
+    Default,
+    Organization,
+    Experiment,
+}
+
+impl PromptSource {
+    fn as_str(&self) -> &str {
+        match self {
+            PromptSource::Code => "code",
+            PromptSource::Default => "default",
+            PromptSource::Organization => "organization",
+            PromptSource::Experiment => "experiment",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ResolvedPrompt {
+    pub name: String,
+    pub content: String,
+    pub source: PromptSource,
+    pub version: Option<i32>,
+    pub experiment_name: Option<String>,
+    pub variant: Option<String>,
+}
+
+impl ResolvedPrompt {
+    /// Tags attached to the Langfuse trace so outcomes can be compared across templates.
+    pub fn trace_tags(&self) -> Vec<String> {
+        let mut tags = vec![
+            format!("prompt:{}", self.name),
+            format!("prompt_source:{}", self.source.as_str()),
+        ];
+
+        if let Some(version) = self.version {
+            tags.push(format!("prompt_version:{}", version));
+        }
+
+        if let (Some(experiment_name), Some(variant)) = (&self.experiment_name, &self.variant) {
+            tags.push(format!("experiment:{}", experiment_name));
+            tags.push(format!("variant:{}", variant));
+        }
+
+        tags
+    }
+}
+
+/// Resolves the template for a prompt and renders it with the prompt function's inputs.
+///
+/// Resolution goes default → organization override → experiment variant. Templates use
+/// `{{variable}}` placeholders. When no template is registered, or the registry can't be read,
+/// `code_prompt` (the output of the existing prompt function) is used as is.
+pub async fn resolve_prompt(
+    name: &str,
+    organization_id: &Uuid,
+    bucket_key: &Uuid,
+    variables: &[(&str, &str)],
+    code_prompt: String,
+) -> ResolvedPrompt {
+    let templates = match get_prompt_templates(name, organization_id).await {
+        Ok(templates) => templates,
+        Err(e) => {
+            tracing::error!("Unable to load prompt templates for {}: {:?}", name, e);
+            vec![]
+        }
+    };
+
+    let (template, source) = match select_template(&templates, organization_id, bucket_key) {
+        Some(selected) => selected,
+        None => {
+            return ResolvedPrompt {
+                name: name.to_string(),
+                content: code_prompt,
+                source: PromptSource::Code,
+                version: None,
+                experiment_name: None,
+                variant: None,
+            }
+        }
+    };
+
+    ResolvedPrompt {
+        name: name.to_string(),
+        content: render_template(&template.template, variables),
+        source,
+        version: Some(template.version),
+        experiment_name: template.experiment_name.clone(),
+        variant: template.variant.clone(),
+    }
+}
+
+async fn get_prompt_templates(name: &str, organization_id: &Uuid) -> Result<Vec<PromptTemplate>> {
+    let cache_key = (name.to_string(), *organization_id);
+
+    if let Ok(cache) = TEMPLATE_CACHE.read() {
+        if let Some((fetched_at, templates)) = cache.get(&cache_key) {
+            if fetched_at.elapsed() < CACHE_TTL {
+                return Ok(templates.clone());
+            }
+        }
+    }
+
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Unable to get connection from pool: {}", e)),
+    };
+
+    let templates = match prompt_templates::table
+        .filter(prompt_templates::name.eq(name))
+        .filter(
+            prompt_templates::organization_id
+                .is_null()
+                .or(prompt_templates::organization_id.eq(organization_id)),
+        )
+        .filter(prompt_templates::active.eq(true))
+        .filter(prompt_templates::deleted_at.is_null())
+        .order(prompt_templates::version.desc())
+        .load::<PromptTemplate>(&mut conn)
+        .await
+    {
+        Ok(templates) => templates,
+        Err(e) => return Err(anyhow!("Error loading prompt templates: {}", e)),
+    };
+
+    if let Ok(mut cache) = TEMPLATE_CACHE.write() {
+        cache.insert(cache_key, (Instant::now(), templates.clone()));
+    }
+
+    Ok(templates)
+}
+
+fn select_template<'a>(
+    templates: &'a [PromptTemplate],
+    organization_id: &Uuid,
+    bucket_key: &Uuid,
+) -> Option<(&'a PromptTemplate, PromptSource)> {
+    // An organization's own experiment or template wins over anything global, so a global
+    // experiment never replaces a prompt the organization has customized.
+    if let Some(variant) = choose_variant(
+        &latest_variants(templates, Some(*organization_id)),
+        bucket_key,
+    ) {
+        return Some((variant, PromptSource::Experiment));
+    }
+
+    // Templates are ordered by version, so the first match is the latest version.
+    if let Some(organization_template) = templates
+        .iter()
+        .find(|t| t.organization_id == Some(*organization_id) && t.variant.is_none())
+    {
+        return Some((organization_template, PromptSource::Organization));
+    }
+
+    if let Some(variant) = choose_variant(&latest_variants(templates, None), bucket_key) {
+        return Some((variant, PromptSource::Experiment));
+    }
+
+    templates
+        .iter()
+        .find(|t| t.organization_id.is_none() && t.variant.is_none())
+        .map(|t| (t, PromptSource::Default))
+}
+
+// Only one experiment runs per prompt and scope at a time: the one with the newest variant. Each
+// of its variants is taken at its latest version.
+fn latest_variants(
+    templates: &[PromptTemplate],
+    organization_id: Option<Uuid>,
+) -> Vec<&PromptTemplate> {
+    let mut scoped_variants = templates
+        .iter()
+        .filter(|t| t.organization_id == organization_id && t.variant.is_some());
+
+    let experiment_name = match scoped_variants.next() {
+        Some(newest) => &newest.experiment_name,
+        None => return vec![],
+    };
+
+    let mut variants: Vec<&PromptTemplate> = vec![];
+
+    for template in templates {
+        if template.organization_id != organization_id
+            || template.variant.is_none()
+            || &template.experiment_name != experiment_name
+        {
+            continue;
+        }
+
+        if !variants.iter().any(|v| v.variant == template.variant) {
+            variants.push(template);
+        }
+    }
+
+    variants
+}
+
+fn choose_variant<'a>(
+    variants: &[&'a PromptTemplate],
+    bucket_key: &Uuid,
+) -> Option<&'a PromptTemplate> {
+    let total_weight: u64 = variants.iter().map(|v| v.weight.max(0) as u64).sum();
+
+    if total_weight == 0 {
+        return None;
+    }
+
+    // Every variant belongs to the same experiment, see `latest_variants`.
+    let experiment_name = variants[0].experiment_name.clone().unwrap_or_default();
+
+    // The same user always lands in the same variant for a given experiment.
+    let mut bucket = stable_hash(&format!("{}:{}", experiment_name, bucket_key)) % total_weight;
+
+    for variant in variants {
+        let weight = variant.weight.max(0) as u64;
+
+        if bucket < weight {
+            return Some(*variant);
+        }
+
+        bucket -= weight;
+    }
+
+    None
+}
+
+// FNV-1a, so bucketing doesn't change between releases the way `DefaultHasher` is allowed to.
+fn stable_hash(value: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+
+    for byte in value.as_bytes() {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    hash
+}
+
+// Placeholders are replaced in a single pass, so a value that itself contains `{{name}}` (a term
+// definition, say) is inserted as is rather than rendered again. Unknown placeholders are kept.
+fn render_template(template: &str, variables: &[(&str, &str)]) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+
+        let placeholder = &rest[start..];
+        let end = match placeholder.find("}}") {
+            Some(end) => end + 2,
+            None => {
+                rest = placeholder;
+                break;
+            }
+        };
+
+        let name = placeholder[2..end - 2].trim();
+
+        match variables.iter().find(|(variable, _)| *variable == name) {
+            Some((_, value)) => rendered.push_str(value),
+            None => rendered.push_str(&placeholder[..end]),
+        }
+
+        rest = &placeholder[end..];
+    }
+
+    // Whatever follows the last placeholder, including an unclosed `{{`.
+    rendered.push_str(rest);
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn template(
+        organization_id: Option<Uuid>,
+        version: i32,
+        variant: Option<&str>,
+        weight: i32,
+    ) -> PromptTemplate {
+        PromptTemplate {
+            id: Uuid::new_v4(),
+            name: "orchestrator".to_string(),
+            version,
+            template: format!("v{} {:?}", version, variant),
+            organization_id,
+            experiment_name: variant.map(|_| "tone".to_string()),
+            variant: variant.map(String::from),
+            weight,
+            active: true,
+            created_by: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn test_render_template() {
+        let rendered = render_template(
+            "Datasets:\n{{datasets}}\nUnknown: {{other}}",
+            &[("datasets", "orders")],
+        );
+
+        assert_eq!(rendered, "Datasets:\norders\nUnknown: {{other}}");
+
+        let rendered = render_template(
+            "Terms: {{terms}} / {{ datasets }} {{unclosed",
+            &[("terms", "ARR: {{datasets}}"), ("datasets", "orders")],
+        );
+
+        assert_eq!(rendered, "Terms: ARR: {{datasets}} / orders {{unclosed");
+    }
+
+    #[test]
+    fn test_organization_override_wins_over_default() {
+        let organization_id = Uuid::new_v4();
+        let templates = vec![
+            template(Some(organization_id), 2, None, 0),
+            template(None, 3, None, 0),
+            template(None, 1, None, 0),
+        ];
+
+        let (selected, source) =
+            select_template(&templates, &organization_id, &Uuid::new_v4()).unwrap();
+
+        assert_eq!(source, PromptSource::Organization);
+        assert_eq!(selected.version, 2);
+    }
+
+    #[test]
+    fn test_organization_override_wins_over_global_experiment() {
+        let organization_id = Uuid::new_v4();
+        let templates = vec![
+            template(None, 3, Some("a"), 50),
+            template(None, 3, Some("b"), 50),
+            template(Some(organization_id), 2, None, 0),
+            template(None, 1, None, 0),
+        ];
+
+        let (selected, source) =
+            select_template(&templates, &organization_id, &Uuid::new_v4()).unwrap();
+
+        assert_eq!(source, PromptSource::Organization);
+        assert_eq!(selected.organization_id, Some(organization_id));
+
+        // Organizations without an override are still part of the global experiment.
+        let (selected, source) =
+            select_template(&templates, &Uuid::new_v4(), &Uuid::new_v4()).unwrap();
+
+        assert_eq!(source, PromptSource::Experiment);
+        assert!(selected.variant.is_some());
+    }
+
+    #[test]
+    fn test_organization_experiment_wins_over_organization_override() {
+        let organization_id = Uuid::new_v4();
+        let templates = vec![
+            template(Some(organization_id), 3, Some("a"), 100),
+            template(Some(organization_id), 2, None, 0),
+            template(None, 1, None, 0),
+        ];
+
+        let (selected, source) =
+            select_template(&templates, &organization_id, &Uuid::new_v4()).unwrap();
+
+        assert_eq!(source, PromptSource::Experiment);
+        assert_eq!(selected.variant.as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn test_experiment_variant_is_stable_per_bucket() {
+        let organization_id = Uuid::new_v4();
+        let bucket_key = Uuid::new_v4();
+        let templates = vec![
+            template(None, 2, Some("a"), 50),
+            template(None, 2, Some("b"), 50),
+            template(None, 1, None, 0),
+        ];
+
+        let (first, source) = select_template(&templates, &organization_id, &bucket_key).unwrap();
+        let (second, _) = select_template(&templates, &organization_id, &bucket_key).unwrap();
+
+        assert_eq!(source, PromptSource::Experiment);
+        assert_eq!(first.variant, second.variant);
+    }
+
+    #[test]
+    fn test_only_newest_experiment_is_bucketed() {
+        let organization_id = Uuid::new_v4();
+        let mut old_experiment = template(None, 1, Some("c"), 100);
+        old_experiment.experiment_name = Some("length".to_string());
+
+        let templates = vec![
+            template(None, 3, Some("a"), 50),
+            template(None, 2, Some("b"), 50),
+            old_experiment,
+        ];
+
+        let variants = latest_variants(&templates, None);
+
+        assert_eq!(variants.len(), 2);
+        assert!(variants
+            .iter()
+            .all(|v| v.experiment_name.as_deref() == Some("tone")));
+
+        for _ in 0..20 {
+            let (selected, _) =
+                select_template(&templates, &organization_id, &Uuid::new_v4()).unwrap();
+            assert_ne!(selected.variant.as_deref(), Some("c"));
+        }
+    }
+
+    #[test]
+    fn test_zero_weight_experiment_falls_back_to_default() {
+        let organization_id = Uuid::new_v4();
+        let templates = vec![template(None, 2, Some("a"), 0), template(None, 1, None, 0)];
+
+        let (selected, source) =
+            select_template(&templates, &organization_id, &Uuid::new_v4()).unwrap();
+
+        assert_eq!(source, PromptSource::Default);
+        assert_eq!(selected.version, 1);
+    }
+}
@@ -8,7 +8,12 @@ use sqlx::FromRow;
 use uuid::Uuid;
 
 use crate::{
-    database::{enums::DatasetType, lib::get_pg_pool, models::Dataset, schema::datasets},
+    database::{
+        enums::{DatasetType, Verification},
+        lib::get_pg_pool,
+        models::Dataset,
+        schema::datasets,
+    },
     utils::{query_engine::credentials::Credential, user::user_info::get_user_organization_id},
 };
 
@@ -83,6 +88,8 @@ async fn create_datasets(
             yml_file: None,
             model: None,
             database_identifier: None,
+            verification: Verification::NotRequested,
+            trashed_at: None,
         })
         .collect::<Vec<Dataset>>();
 
@@ -1,10 +1,11 @@
 pub mod credentials;
-mod data_source_connections;
+pub mod data_source_connections;
 mod data_source_query_routes;
 pub mod data_types;
 pub mod import_dataset_columns;
 pub mod import_datasets;
 pub mod query_engine;
+pub mod sql_quoting;
 pub mod test_data_source_connections;
 mod utils;
 pub mod values_index;
@@ -29,6 +29,26 @@ pub async fn query_engine(
     Ok(results)
 }
 
+/// Runs a dataset query that stops reading after `limit` rows on data sources that stream their
+/// results. Callers still need to cap the rows for sources that return everything at once.
+pub async fn limited_query_engine(
+    dataset_id: &Uuid,
+    sql: &String,
+    limit: i64,
+) -> Result<Vec<IndexMap<String, DataType>>> {
+    let data_source = match DataSource::find_by_dataset_id(dataset_id).await? {
+        Some(data_source) => data_source,
+        None => return Err(anyhow::anyhow!("Data source not found")),
+    };
+
+    let results = match query_router(&data_source, sql, Some(limit), false).await {
+        Ok(results) => results,
+        Err(e) => return Err(e),
+    };
+
+    Ok(results)
+}
+
 pub async fn modeling_query_engine(
     data_source_id: &Uuid,
     sql: &String,
@@ -79,3 +99,18 @@ pub async fn modeling_query_engine(
 
     Ok(results)
 }
+
+/// Runs a read query against a data source that hasn't been loaded through a dataset, such as
+/// the probe queries used to validate a model before it is deployed.
+pub async fn data_source_query_engine(
+    data_source: &DataSource,
+    sql: &String,
+    limit: Option<i64>,
+) -> Result<Vec<IndexMap<String, DataType>>> {
+    let results = match query_router(data_source, sql, limit, false).await {
+        Ok(results) => results,
+        Err(e) => return Err(e),
+    };
+
+    Ok(results)
+}
@@ -0,0 +1,64 @@
+use crate::database::enums::DataSourceType;
+
+/// Writes a string literal for the data source's SQL dialect. Warehouses that treat a backslash
+/// as an escape character get backslashes, quotes and line breaks escaped with one, so a trailing
+/// backslash can't end the literal early and the literal stays on one line.
+pub fn quote_string_literal(value: &str, data_source_type: &DataSourceType) -> String {
+    let backslash_escapes = match data_source_type {
+        DataSourceType::Postgres | DataSourceType::Supabase | DataSourceType::SqlServer => false,
+        DataSourceType::BigQuery
+        | DataSourceType::Databricks
+        | DataSourceType::MySql
+        | DataSourceType::Mariadb
+        | DataSourceType::Redshift
+        | DataSourceType::Snowflake => true,
+    };
+
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('\'');
+
+    for c in value.chars() {
+        match (c, backslash_escapes) {
+            ('\'', false) => quoted.push_str("''"),
+            ('\'', true) => quoted.push_str("\\'"),
+            ('\\', true) => quoted.push_str("\\\\"),
+            ('\n', true) => quoted.push_str("\\n"),
+            ('\r', true) => quoted.push_str("\\r"),
+            (c, _) => quoted.push(c),
+        }
+    }
+
+    quoted.push('\'');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_string_literal() {
+        let value = "O'Hare \\' OR 1=1 --";
+
+        assert_eq!(
+            quote_string_literal(value, &DataSourceType::Postgres),
+            "'O''Hare \\'' OR 1=1 --'"
+        );
+        assert_eq!(
+            quote_string_literal(value, &DataSourceType::SqlServer),
+            quote_string_literal(value, &DataSourceType::Postgres)
+        );
+        assert_eq!(
+            quote_string_literal(value, &DataSourceType::MySql),
+            "'O\\'Hare \\\\\\' OR 1=1 --'"
+        );
+        assert_eq!(
+            quote_string_literal(value, &DataSourceType::BigQuery),
+            quote_string_literal(value, &DataSourceType::MySql)
+        );
+        assert_eq!(
+            quote_string_literal("a\nb", &DataSourceType::BigQuery),
+            "'a\\nb'"
+        );
+    }
+}
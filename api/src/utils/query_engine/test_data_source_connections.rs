@@ -1,7 +1,7 @@
 use crate::database::enums::DataSourceType;
 
 use super::{
-    credentials::Credential,
+    credentials::{Credential, PostgresCredentials},
     data_source_connections::{
         get_bigquery_client::get_bigquery_client, get_databricks_client::get_databricks_client,
         get_mysql_connection::get_mysql_connection,
@@ -77,8 +77,20 @@ pub async fn test_data_source_connection(
         }
         DataSourceType::Redshift => {
             // REDSHIFT just uses postgres credentials
-            let Credential::Postgres(credential) = credential else {
-                return Err(anyhow!("Invalid credential type: {:?}", credential));
+            let credential = match credential {
+                Credential::Postgres(credential) => credential.clone(),
+                Credential::Redshift(credential) => PostgresCredentials {
+                    host: credential.host.clone(),
+                    port: credential.port,
+                    username: credential.username.clone(),
+                    password: credential.password.clone(),
+                    database: credential.database.clone(),
+                    schemas: credential.schemas.clone(),
+                    jump_host: None,
+                    ssh_username: None,
+                    ssh_private_key: None,
+                },
+                _ => return Err(anyhow!("Invalid credential type: {:?}", credential)),
             };
 
             get_redshift_connection(&credential)
@@ -0,0 +1,176 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use chrono::{NaiveDateTime, TimeZone, Timelike, Utc};
+use cron::Schedule;
+
+const DAY_NAMES: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+/// A standard five-field cron expression: minute, hour, day of month, month and day of week.
+/// Supports `*`, lists, ranges, steps, month and day names, and the `@hourly`, `@daily`,
+/// `@weekly` and `@monthly` shorthands.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronSchedule {
+    // Like cron, when both day fields are restricted either one matching is enough, so the
+    // expression is split into one schedule per day field.
+    schedules: Vec<Schedule>,
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let expression = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            expression => expression,
+        };
+
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+
+        if fields.len() != 5 {
+            return Err(anyhow!(
+                "Cron expression '{}' must have five fields",
+                expression
+            ));
+        }
+
+        let (minute, hour, day_of_month, month) = (fields[0], fields[1], fields[2], fields[3]);
+        let day_of_week = day_of_week_names(fields[4])?;
+
+        // As in cron, a day field starting with `*` (including steps like `*/2`) doesn't restrict.
+        let day_fields = match (fields[2].starts_with('*'), fields[4].starts_with('*')) {
+            (false, false) => vec![(day_of_month, "*"), ("*", day_of_week.as_str())],
+            _ => vec![(day_of_month, day_of_week.as_str())],
+        };
+
+        let mut schedules = Vec::new();
+
+        for (day_of_month, day_of_week) in day_fields {
+            // The cron crate expects seconds first.
+            let expression = format!(
+                "0 {} {} {} {} {}",
+                minute, hour, day_of_month, month, day_of_week
+            );
+
+            match Schedule::from_str(&expression) {
+                Ok(schedule) => schedules.push(schedule),
+                Err(e) => {
+                    return Err(anyhow!(
+                        "Invalid cron expression '{}': {}",
+                        fields.join(" "),
+                        e
+                    ))
+                }
+            }
+        }
+
+        Ok(CronSchedule { schedules })
+    }
+
+    /// Whether the schedule fires in the minute of the given local time.
+    pub fn matches(&self, time: &NaiveDateTime) -> bool {
+        let minute = match time.with_second(0).and_then(|time| time.with_nanosecond(0)) {
+            Some(minute) => Utc.from_utc_datetime(&minute),
+            None => return false,
+        };
+
+        self.schedules
+            .iter()
+            .any(|schedule| schedule.includes(minute))
+    }
+}
+
+// The cron crate numbers days of the week from 1 (Sunday), where cron numbers them from 0 and also
+// accepts 7 for Sunday. Numbers are swapped for day names, which both read the same way.
+fn day_of_week_names(field: &str) -> Result<String> {
+    let day_name = |value: &str| -> Result<String> {
+        match value.parse::<usize>() {
+            Ok(day) if day <= 7 => Ok(DAY_NAMES[day % 7].to_string()),
+            Ok(_) => Err(anyhow!("Cron value {} is outside 0-7", value)),
+            Err(_) => Ok(value.to_string()),
+        }
+    };
+
+    let mut parts = Vec::new();
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, Some(step)),
+            None => (part, None),
+        };
+
+        let range = match range.split_once('-') {
+            // A range ending on 7 ends on Saturday and picks Sunday up separately.
+            Some((start, "7")) if step.is_none() => {
+                parts.push(DAY_NAMES[0].to_string());
+                format!("{}-{}", day_name(start)?, DAY_NAMES[6])
+            }
+            Some((start, end)) => format!("{}-{}", day_name(start)?, day_name(end)?),
+            None if range == "*" => range.to_string(),
+            None => day_name(range)?,
+        };
+
+        parts.push(match step {
+            Some(step) => format!("{}/{}", range, step),
+            None => range,
+        });
+    }
+
+    Ok(parts.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_cron_schedule_matches() {
+        // Weekdays at 9:00. 2025-02-17 is a Monday.
+        let weekdays = CronSchedule::parse("0 9 * * MON-FRI").unwrap();
+        assert!(weekdays.matches(&at(2025, 2, 17, 9, 0)));
+        assert!(!weekdays.matches(&at(2025, 2, 17, 9, 1)));
+        assert!(!weekdays.matches(&at(2025, 2, 16, 9, 0)));
+        assert_eq!(weekdays, CronSchedule::parse("0 9 * * 1-5").unwrap());
+
+        let every_quarter_hour = CronSchedule::parse("*/15 8-17 * * *").unwrap();
+        assert!(every_quarter_hour.matches(&at(2025, 2, 16, 17, 45)));
+        assert!(!every_quarter_hour.matches(&at(2025, 2, 16, 18, 0)));
+
+        // Day of month and day of week are ORed when both are set.
+        let first_or_sunday = CronSchedule::parse("30 6 1 * 7").unwrap();
+        assert!(first_or_sunday.matches(&at(2025, 3, 1, 6, 30)));
+        assert!(first_or_sunday.matches(&at(2025, 2, 16, 6, 30)));
+        assert!(!first_or_sunday.matches(&at(2025, 2, 17, 6, 30)));
+
+        // A stepped `*` doesn't restrict its day field, so both have to match.
+        let odd_days_on_mondays = CronSchedule::parse("0 9 */2 * 1").unwrap();
+        assert!(odd_days_on_mondays.matches(&at(2025, 2, 17, 9, 0)));
+        assert!(!odd_days_on_mondays.matches(&at(2025, 2, 24, 9, 0)));
+        assert!(!odd_days_on_mondays.matches(&at(2025, 2, 19, 9, 0)));
+
+        let weekends = CronSchedule::parse("0 10 * * 6-7").unwrap();
+        assert!(weekends.matches(&at(2025, 2, 15, 10, 0)));
+        assert!(weekends.matches(&at(2025, 2, 16, 10, 0)));
+        assert!(!weekends.matches(&at(2025, 2, 17, 10, 0)));
+
+        assert_eq!(
+            CronSchedule::parse("@weekly").unwrap(),
+            CronSchedule::parse("0 0 * * SUN").unwrap()
+        );
+
+        assert!(CronSchedule::parse("0 9 * *").is_err());
+        assert!(CronSchedule::parse("60 9 * * *").is_err());
+        assert!(CronSchedule::parse("0 9 * * 8").is_err());
+        assert!(CronSchedule::parse("0 9 * * 1-0").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+    }
+}
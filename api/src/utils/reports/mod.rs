@@ -0,0 +1,4 @@
+pub mod cron;
+pub mod render;
+pub mod scheduler;
+pub mod subscriptions;
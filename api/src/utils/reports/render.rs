@@ -0,0 +1,183 @@
+use html_escape::{encode_double_quoted_attribute as escape_attribute, encode_text as escape_html};
+use indexmap::IndexMap;
+use serde_json::Value;
+
+use crate::utils::query_engine::data_types::DataType;
+
+const REPORT_TEMPLATE: &str = include_str!("report_template.html");
+const MAX_TABLE_ROWS: usize = 10;
+const MAX_TABLE_COLUMNS: usize = 6;
+
+/// One metric in a report, with its rows or the reason they couldn't be fetched.
+pub struct ReportSection {
+    pub name: String,
+    pub result: Result<Vec<IndexMap<String, DataType>>, String>,
+}
+
+pub struct RenderedReport {
+    pub html: String,
+    pub text: String,
+}
+
+/// Renders a report as HTML and plain text. Single values are shown as a headline number, anything
+/// else as a small table with a note when rows or columns were cut off.
+pub fn render_report(
+    title: &str,
+    subtitle: &str,
+    link: &str,
+    sections: &[ReportSection],
+) -> RenderedReport {
+    let mut html_sections = String::new();
+    let mut text = format!("{}\n{}\n", title, subtitle);
+
+    for section in sections {
+        let (section_html, section_text) = match &section.result {
+            Ok(rows) => render_rows(rows),
+            Err(e) => (
+                format!(
+                    "<p style=\"margin:0; color:#b91c1c;\">{}</p>",
+                    escape_html(e)
+                ),
+                e.clone(),
+            ),
+        };
+
+        html_sections.push_str(&format!(
+            "<tr><td style=\"padding:16px 24px 0 24px;\"><h2 style=\"margin:0 0 8px 0; font-size:15px;\">{}</h2>{}</td></tr>",
+            escape_html(&section.name),
+            section_html
+        ));
+        text.push_str(&format!("\n{}\n{}\n", section.name, section_text));
+    }
+
+    if sections.is_empty() {
+        html_sections.push_str(
+            "<tr><td style=\"padding:16px 24px 0 24px;\"><p style=\"margin:0;\">There are no metrics in this report.</p></td></tr>",
+        );
+    }
+
+    text.push_str(&format!("\nView in Buster: {}\n", link));
+
+    let html = REPORT_TEMPLATE
+        .replace("{{title}}", &escape_html(title))
+        .replace("{{subtitle}}", &escape_html(subtitle))
+        .replace("{{content}}", &html_sections)
+        .replace("{{button_link}}", &escape_attribute(link))
+        .replace("{{button_text}}", "View in Buster");
+
+    RenderedReport { html, text }
+}
+
+/// Renders a short notification, such as an alert, in the same layout as reports.
+pub fn render_notification(title: &str, subtitle: &str, message: &str, link: &str) -> RenderedReport {
+    let content = format!(
+        "<tr><td style=\"padding:16px 24px 0 24px;\"><p style=\"margin:0; font-size:15px;\">{}</p></td></tr>",
+        escape_html(message)
+    );
+
+    let html = REPORT_TEMPLATE
+        .replace("{{title}}", &escape_html(title))
+        .replace("{{subtitle}}", &escape_html(subtitle))
+        .replace("{{content}}", &content)
+        .replace("{{button_link}}", &escape_attribute(link))
+        .replace("{{button_text}}", "View in Buster");
+
+    let text = format!(
+        "{}\n{}\n\n{}\n\nView in Buster: {}\n",
+        title, subtitle, message, link
+    );
+
+    RenderedReport { html, text }
+}
+
+fn render_rows(rows: &[IndexMap<String, DataType>]) -> (String, String) {
+    let columns: Vec<&String> = match rows.first() {
+        Some(row) => row.keys().collect(),
+        None => {
+            return (
+                "<p style=\"margin:0; color:#71717a;\">No results.</p>".to_string(),
+                "No results.".to_string(),
+            )
+        }
+    };
+
+    if rows.len() == 1 && columns.len() == 1 {
+        let value = format_cell(rows[0].get(columns[0]));
+        return (
+            format!(
+                "<p style=\"margin:0; font-size:28px; font-weight:bold;\">{}</p>",
+                escape_html(&value)
+            ),
+            value,
+        );
+    }
+
+    let shown_columns = &columns[..columns.len().min(MAX_TABLE_COLUMNS)];
+    let shown_rows = &rows[..rows.len().min(MAX_TABLE_ROWS)];
+
+    let cell_style = "padding:4px 8px; border-bottom:1px solid #e4e4e7; text-align:left;";
+
+    let mut html = String::from(
+        "<table cellpadding=\"0\" cellspacing=\"0\" border=\"0\" style=\"border-collapse:collapse; width:100%; font-size:13px;\"><tr>",
+    );
+    for column in shown_columns {
+        html.push_str(&format!(
+            "<th style=\"{}\">{}</th>",
+            cell_style,
+            escape_html(column.as_str())
+        ));
+    }
+    html.push_str("</tr>");
+
+    let mut text_lines = vec![shown_columns
+        .iter()
+        .map(|column| column.as_str())
+        .collect::<Vec<&str>>()
+        .join(" | ")];
+
+    for row in shown_rows {
+        let cells: Vec<String> = shown_columns
+            .iter()
+            .map(|column| format_cell(row.get(*column)))
+            .collect();
+
+        html.push_str("<tr>");
+        for cell in &cells {
+            html.push_str(&format!(
+                "<td style=\"{}\">{}</td>",
+                cell_style,
+                escape_html(cell)
+            ));
+        }
+        html.push_str("</tr>");
+
+        text_lines.push(cells.join(" | "));
+    }
+    html.push_str("</table>");
+
+    if rows.len() > shown_rows.len() || columns.len() > shown_columns.len() {
+        let note = format!(
+            "Showing {} of {} rows and {} of {} columns.",
+            shown_rows.len(),
+            rows.len(),
+            shown_columns.len(),
+            columns.len()
+        );
+        html.push_str(&format!(
+            "<p style=\"margin:4px 0 0 0; color:#71717a; font-size:12px;\">{}</p>",
+            note
+        ));
+        text_lines.push(note);
+    }
+
+    (html, text_lines.join("\n"))
+}
+
+fn format_cell(cell: Option<&DataType>) -> String {
+    match cell.map(serde_json::to_value) {
+        Some(Ok(Value::Null)) | None => String::new(),
+        Some(Ok(Value::String(s))) => s,
+        Some(Ok(value)) => value.to_string(),
+        Some(Err(_)) => String::new(),
+    }
+}
@@ -0,0 +1,346 @@
+use std::{env, sync::Arc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, DurationRound, NaiveDateTime, TimeDelta, Utc};
+use diesel::{
+    dsl::sql,
+    sql_types::{Timestamp, Timestamptz},
+    update, BoolExpressionMethods, ExpressionMethods, JoinOnDsl, QueryDsl, SelectableHelper,
+};
+use diesel_async::RunQueryDsl;
+use indexmap::IndexMap;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        enums::AssetType,
+        lib::get_pg_pool,
+        models::{Message, ReportSubscription, User},
+        schema::{messages, report_subscriptions, threads, users},
+    },
+    routes::ws::dashboards::dashboard_utils::get_dashboard_state_by_id,
+    utils::{
+        clients::{
+            email::sender::{EmailBackend, EmailSender, OutgoingEmail},
+            sentry_utils::send_sentry_error,
+        },
+        query_engine::{data_types::DataType, query_engine::query_engine},
+        security::{
+            dataset_security::has_dataset_access,
+            permission_flags::{has_permission_flag, PermissionFlag},
+        },
+    },
+};
+
+use super::{
+    cron::CronSchedule,
+    render::{render_report, ReportSection},
+    subscriptions::check_report_asset_access,
+};
+
+/// Starts the loop that sends scheduled reports. It wakes at the top of every minute and sends the
+/// subscriptions whose cron expression matches that minute in their time zone. Reports are skipped
+/// rather than caught up when the API was down at their scheduled time.
+pub fn start_report_scheduler() {
+    let sender = match EmailBackend::from_env() {
+        Ok(sender) => Arc::new(sender),
+        Err(e) => {
+            tracing::warn!("Scheduled reports are disabled: {}", e);
+            return;
+        }
+    };
+
+    tokio::spawn(async move {
+        loop {
+            let millis_into_minute = (Utc::now().timestamp_millis() % 60_000) as u64;
+            tokio::time::sleep(Duration::from_millis(60_000 - millis_into_minute)).await;
+
+            let minute = match Utc::now().duration_trunc(TimeDelta::minutes(1)) {
+                Ok(minute) => minute,
+                Err(e) => {
+                    tracing::error!("Error truncating report schedule time: {}", e);
+                    continue;
+                }
+            };
+
+            if let Err(e) = send_due_reports(&sender, minute).await {
+                tracing::error!("Error sending scheduled reports: {}", e);
+                send_sentry_error(&format!("Error sending scheduled reports: {}", e), None);
+            }
+        }
+    });
+}
+
+async fn send_due_reports(sender: &Arc<EmailBackend>, minute: DateTime<Utc>) -> Result<()> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    // Postgres resolves each subscription's local time so time zone rules stay in one place.
+    let subscriptions = match report_subscriptions::table
+        .select((
+            ReportSubscription::as_select(),
+            sql::<Timestamp>("timezone(report_subscriptions.timezone, ")
+                .bind::<Timestamptz, _>(minute)
+                .sql(")"),
+        ))
+        .filter(report_subscriptions::enabled.eq(true))
+        .filter(report_subscriptions::deleted_at.is_null())
+        .load::<(ReportSubscription, NaiveDateTime)>(&mut conn)
+        .await
+    {
+        Ok(subscriptions) => subscriptions,
+        Err(e) => return Err(anyhow!("Error querying report subscriptions: {}", e)),
+    };
+
+    for (subscription, local_time) in subscriptions {
+        match CronSchedule::parse(&subscription.cron) {
+            Ok(schedule) if schedule.matches(&local_time) => (),
+            Ok(_) => continue,
+            Err(e) => {
+                tracing::error!(
+                    "Invalid schedule on report subscription {}: {}",
+                    subscription.id,
+                    e
+                );
+                continue;
+            }
+        }
+
+        // Claiming the minute keeps a report from going out twice when several API processes run.
+        let claimed = match update(report_subscriptions::table)
+            .filter(report_subscriptions::id.eq(subscription.id))
+            .filter(
+                report_subscriptions::last_sent_at
+                    .is_null()
+                    .or(report_subscriptions::last_sent_at.lt(minute)),
+            )
+            .set(report_subscriptions::last_sent_at.eq(minute))
+            .execute(&mut conn)
+            .await
+        {
+            Ok(claimed) => claimed > 0,
+            Err(e) => {
+                tracing::error!("Error claiming report subscription: {}", e);
+                continue;
+            }
+        };
+
+        if !claimed {
+            continue;
+        }
+
+        let sender = Arc::clone(sender);
+        tokio::spawn(async move {
+            let result = send_report(&subscription, &local_time, sender.as_ref()).await;
+
+            if let Err(e) = &result {
+                tracing::error!("Error sending report {}: {}", subscription.id, e);
+                send_sentry_error(&e.to_string(), Some(&subscription.user_id));
+            }
+
+            if let Err(e) = record_report_result(&subscription.id, result.err()).await {
+                tracing::error!("Error recording report result: {}", e);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+async fn record_report_result(subscription_id: &Uuid, error: Option<anyhow::Error>) -> Result<()> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    match update(report_subscriptions::table)
+        .filter(report_subscriptions::id.eq(subscription_id))
+        .set(report_subscriptions::last_error.eq(error.map(|e| e.to_string())))
+        .execute(&mut conn)
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!("Error updating report subscription: {}", e)),
+    }
+}
+
+/// Builds a subscription's report as its subscriber and emails it to them. Everything runs with
+/// the subscriber's permissions, so a report only ever shows what they could see in the app.
+pub async fn send_report<S: EmailSender>(
+    subscription: &ReportSubscription,
+    local_time: &NaiveDateTime,
+    sender: &S,
+) -> Result<()> {
+    let user = get_report_user(&subscription.user_id).await?;
+
+    if !has_permission_flag(&user.id, PermissionFlag::EmailSlackEnabled).await? {
+        return Err(anyhow!("Email reports are not enabled for this user"));
+    }
+
+    check_report_asset_access(&user.id, &subscription.asset_id, &subscription.asset_type).await?;
+
+    let buster_url = env::var("BUSTER_URL").unwrap_or_default();
+
+    let (name, link, sections) = match subscription.asset_type {
+        AssetType::Dashboard => {
+            let (name, sections) = dashboard_sections(&user.id, &subscription.asset_id).await?;
+            let link = format!("{}/app/dashboards/{}", buster_url, subscription.asset_id);
+            (name, link, sections)
+        }
+        AssetType::Thread => {
+            let (name, section) = metric_section(&user.id, &subscription.asset_id).await?;
+            let link = format!("{}/app/metrics/{}", buster_url, subscription.asset_id);
+            (name, link, vec![section])
+        }
+        AssetType::Collection => {
+            return Err(anyhow!(
+                "Reports can only be sent for dashboards and metrics"
+            ))
+        }
+    };
+
+    let subtitle = format!(
+        "Scheduled report for {} ({})",
+        local_time.format("%B %-d, %Y %H:%M"),
+        subscription.timezone
+    );
+
+    let rendered = render_report(&name, &subtitle, &link, &sections);
+
+    sender
+        .send(&OutgoingEmail {
+            to: user.email,
+            subject: format!("{} report", name),
+            html: rendered.html,
+            text: rendered.text,
+        })
+        .await
+}
+
+async fn get_report_user(user_id: &Uuid) -> Result<User> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    match users::table
+        .filter(users::id.eq(user_id))
+        .select(User::as_select())
+        .first::<User>(&mut conn)
+        .await
+    {
+        Ok(user) => Ok(user),
+        Err(e) => Err(anyhow!("Error getting report user: {}", e)),
+    }
+}
+
+async fn dashboard_sections(
+    user_id: &Uuid,
+    dashboard_id: &Uuid,
+) -> Result<(String, Vec<ReportSection>)> {
+    let dashboard_state = get_dashboard_state_by_id(user_id, dashboard_id, None).await?;
+
+    let mut metrics = dashboard_state.metrics;
+    let layout_order = dashboard_metric_order(&dashboard_state.dashboard.config);
+    metrics.sort_by_key(|metric| {
+        layout_order
+            .iter()
+            .position(|id| id == &metric.id)
+            .unwrap_or(usize::MAX)
+    });
+
+    let mut sections = Vec::new();
+
+    for metric in metrics {
+        let result = match &metric.filter_error {
+            Some(_) => Err("Dashboard filters could not be applied to this metric.".to_string()),
+            None => run_report_metric(user_id, &metric.dataset_id, &metric.sql).await,
+        };
+
+        sections.push(ReportSection {
+            name: metric.name,
+            result,
+        });
+    }
+
+    Ok((dashboard_state.dashboard.name, sections))
+}
+
+// Metric ids in the order they are laid out on the dashboard.
+fn dashboard_metric_order(config: &Value) -> Vec<Uuid> {
+    config
+        .get("rows")
+        .and_then(|rows| rows.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|row| row.get("items").and_then(|items| items.as_array()))
+        .flatten()
+        .filter_map(|item| item.get("id").and_then(|id| id.as_str()))
+        .filter_map(|id| Uuid::parse_str(id).ok())
+        .collect()
+}
+
+async fn metric_section(user_id: &Uuid, thread_id: &Uuid) -> Result<(String, ReportSection)> {
+    let message = get_latest_metric_message(thread_id).await?;
+
+    let name = message.title.unwrap_or_else(|| "Metric".to_string());
+
+    let result = match (message.dataset_id, message.code) {
+        (Some(dataset_id), Some(sql)) => run_report_metric(user_id, &dataset_id, &sql).await,
+        _ => Err("This metric has no query to run.".to_string()),
+    };
+
+    Ok((name.clone(), ReportSection { name, result }))
+}
+
+/// The latest published message of a metric's thread, which holds its current SQL and dataset.
+pub async fn get_latest_metric_message(thread_id: &Uuid) -> Result<Message> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    match messages::table
+        .inner_join(threads::table.on(messages::thread_id.eq(threads::id)))
+        .select(messages::all_columns)
+        .filter(messages::thread_id.eq(thread_id))
+        .filter(messages::deleted_at.is_null())
+        .filter(messages::draft_session_id.is_null())
+        .filter(threads::deleted_at.is_null())
+        .order(messages::created_at.desc())
+        .first::<Message>(&mut conn)
+        .await
+    {
+        Ok(message) => Ok(message),
+        Err(e) => Err(anyhow!("Error getting metric message: {}", e)),
+    }
+}
+
+// Query failures are logged and shown as a short note so one broken metric doesn't stop the report.
+async fn run_report_metric(
+    user_id: &Uuid,
+    dataset_id: &Uuid,
+    sql: &str,
+) -> Result<Vec<IndexMap<String, DataType>>, String> {
+    match has_dataset_access(user_id, dataset_id).await {
+        Ok(true) => (),
+        Ok(false) => {
+            return Err("You don't have access to the data behind this metric.".to_string())
+        }
+        Err(e) => {
+            tracing::error!("Error checking dataset access for report: {}", e);
+            return Err("This metric could not be run.".to_string());
+        }
+    }
+
+    match query_engine(dataset_id, &sql.to_string()).await {
+        Ok(rows) => Ok(rows),
+        Err(e) => {
+            tracing::error!("Error running report metric: {}", e);
+            Err("This metric could not be run.".to_string())
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use diesel::{
+    dsl::{select, sql},
+    sql_types::{Bool, Text},
+};
+use diesel_async::RunQueryDsl;
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        enums::AssetType,
+        lib::get_pg_pool,
+    },
+    routes::ws::{
+        dashboards::dashboard_utils::get_user_dashboard_permission,
+        threads_and_messages::thread_utils::get_user_thread_permission,
+    },
+};
+
+use super::cron::CronSchedule;
+
+/// Checks the user can currently view the dashboard or metric a report is for.
+pub async fn check_report_asset_access(
+    user_id: &Uuid,
+    asset_id: &Uuid,
+    asset_type: &AssetType,
+) -> Result<()> {
+    let permission = match asset_type {
+        AssetType::Dashboard => get_user_dashboard_permission(user_id, asset_id).await?,
+        AssetType::Thread => {
+            get_user_thread_permission(Arc::new(*user_id), Arc::new(*asset_id)).await?
+        }
+        AssetType::Collection => {
+            return Err(anyhow!(
+                "Reports can only be scheduled for dashboards and metrics"
+            ))
+        }
+    };
+
+    match permission {
+        Some(_) => Ok(()),
+        None => Err(anyhow!("User does not have access to this asset")),
+    }
+}
+
+/// Validates a report schedule: the cron expression must parse and the time zone must be one
+/// Postgres knows, since the scheduler resolves local times there.
+pub async fn validate_report_schedule(cron: &str, timezone: &str) -> Result<()> {
+    CronSchedule::parse(cron)?;
+
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    let is_valid = match select(
+        sql::<Bool>("exists (select 1 from pg_timezone_names where name = ")
+            .bind::<Text, _>(timezone)
+            .sql(")"),
+    )
+    .get_result::<bool>(&mut conn)
+    .await
+    {
+        Ok(is_valid) => is_valid,
+        Err(e) => return Err(anyhow!("Error validating time zone: {}", e)),
+    };
+
+    match is_valid {
+        true => Ok(()),
+        false => Err(anyhow!("Unknown time zone '{}'", timezone)),
+    }
+}
@@ -0,0 +1,81 @@
+use anyhow::{anyhow, Result};
+use diesel::{
+    dsl::{exists, select},
+    pg::Pg,
+    sql_types::Bool,
+    BoolExpressionMethods, BoxableExpression, ExpressionMethods, QueryDsl,
+};
+use diesel_async::RunQueryDsl;
+use uuid::Uuid;
+
+use crate::database::{
+    lib::get_pg_pool,
+    schema::{teams, teams_to_users, users_to_organizations},
+};
+
+/// A feature switch stored on both organization memberships and teams.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PermissionFlag {
+    UploadCsv,
+    ExportAssets,
+    EmailSlackEnabled,
+}
+
+type OrganizationFlag = Box<dyn BoxableExpression<users_to_organizations::table, Pg, SqlType = Bool>>;
+type TeamFlag = Box<dyn BoxableExpression<teams::table, Pg, SqlType = Bool>>;
+
+impl PermissionFlag {
+    fn columns(&self) -> (OrganizationFlag, TeamFlag) {
+        match self {
+            PermissionFlag::UploadCsv => (
+                Box::new(users_to_organizations::upload_csv),
+                Box::new(teams::upload_csv),
+            ),
+            PermissionFlag::ExportAssets => (
+                Box::new(users_to_organizations::export_assets),
+                Box::new(teams::export_assets),
+            ),
+            PermissionFlag::EmailSlackEnabled => (
+                Box::new(users_to_organizations::email_slack_enabled),
+                Box::new(teams::email_slack_enabled),
+            ),
+        }
+    }
+}
+
+/// Whether the flag is set on the user's organization membership or on one of their teams.
+pub async fn has_permission_flag(user_id: &Uuid, flag: PermissionFlag) -> Result<bool> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    let (organization_flag, team_flag) = flag.columns();
+
+    let organization_enabled = users_to_organizations::table
+        .filter(users_to_organizations::user_id.eq(user_id))
+        .filter(users_to_organizations::deleted_at.is_null())
+        .filter(organization_flag)
+        .select(users_to_organizations::user_id)
+        .into_boxed();
+
+    let user_teams = teams_to_users::table
+        .filter(teams_to_users::user_id.eq(user_id))
+        .filter(teams_to_users::deleted_at.is_null())
+        .select(teams_to_users::team_id);
+
+    let team_enabled = teams::table
+        .filter(teams::id.eq_any(user_teams))
+        .filter(teams::deleted_at.is_null())
+        .filter(team_flag)
+        .select(teams::id)
+        .into_boxed();
+
+    match select(exists(organization_enabled).or(exists(team_enabled)))
+        .get_result::<bool>(&mut conn)
+        .await
+    {
+        Ok(enabled) => Ok(enabled),
+        Err(e) => Err(anyhow!("Error checking {:?} access: {}", flag, e)),
+    }
+}
@@ -0,0 +1,921 @@
+use std::{collections::HashMap, ops::ControlFlow};
+
+use anyhow::{anyhow, Result};
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlparser::{
+    ast::{Expr, Ident, VisitMut, VisitorMut},
+    dialect::GenericDialect,
+    parser::Parser,
+};
+use uuid::Uuid;
+
+use crate::{
+    database::enums::DataSourceType, utils::query_engine::sql_quoting::quote_string_literal,
+};
+
+use super::{
+    join_planner::{plan_joins, JoinPlan},
+    semantic_model::{SemanticDataset, SemanticModel},
+};
+
+// Aggregations that give the same result when a join repeats rows.
+const FAN_OUT_SAFE_AGGREGATIONS: [&str; 3] = ["count_distinct", "min", "max"];
+
+/// A request against the semantic layer. Fields are referenced by name, optionally qualified
+/// with the dataset name (`orders.revenue`) when the name exists in more than one dataset.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SemanticQuery {
+    // Measures or metrics.
+    #[serde(default)]
+    pub measures: Vec<String>,
+    #[serde(default)]
+    pub dimensions: Vec<String>,
+    #[serde(default)]
+    pub segments: Vec<String>,
+    #[serde(default)]
+    pub filters: Vec<SemanticFilter>,
+    pub time_dimension: Option<TimeDimension>,
+    #[serde(default)]
+    pub order_by: Vec<SemanticOrderBy>,
+    pub limit: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TimeDimension {
+    pub dimension: String,
+    pub grain: TimeGrain,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeGrain {
+    Day,
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
+
+impl TimeGrain {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TimeGrain::Day => "day",
+            TimeGrain::Week => "week",
+            TimeGrain::Month => "month",
+            TimeGrain::Quarter => "quarter",
+            TimeGrain::Year => "year",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SemanticFilter {
+    pub field: String,
+    pub operator: FilterOperator,
+    #[serde(default)]
+    pub value: Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterOperator {
+    Eq,
+    Neq,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    In,
+    NotIn,
+    Like,
+    IsNull,
+    IsNotNull,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SemanticOrderBy {
+    pub field: String,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+// A compiled select item: the dataset it reads from, its output alias and its SQL.
+struct CompiledField {
+    dataset_id: Uuid,
+    alias: String,
+    sql: String,
+}
+
+struct Compiler<'a> {
+    model: &'a SemanticModel,
+    dialect: DataSourceType,
+    qualify: bool,
+    // Datasets whose rows are repeated by the joins of the query.
+    fanned_out: Vec<Uuid>,
+}
+
+/// Compiles a semantic request into SQL for the data source's dialect.
+///
+/// Measures are aggregated with their `agg`, metrics are expanded from the measures they
+/// reference, segments and dimension filters go in the WHERE clause and measure filters in
+/// HAVING. Fields from other datasets are joined in along the join plan, and measures that the
+/// joins would overcount are rejected.
+pub fn compile_semantic_query(
+    model: &SemanticModel,
+    query: &SemanticQuery,
+    dialect: &DataSourceType,
+) -> Result<String> {
+    if query.measures.is_empty() && query.dimensions.is_empty() && query.time_dimension.is_none() {
+        return Err(anyhow!(
+            "The request must include at least one measure or dimension"
+        ));
+    }
+
+    // Resolve everything unqualified first to find out which datasets the query touches.
+    let unqualified = Compiler {
+        model,
+        dialect: *dialect,
+        qualify: false,
+        fanned_out: vec![],
+    };
+
+    let mut dataset_ids: Vec<Uuid> = Vec::new();
+    let mut add_dataset = |id: Uuid| {
+        if !dataset_ids.contains(&id) {
+            dataset_ids.push(id);
+        }
+    };
+
+    for name in &query.measures {
+        add_dataset(unqualified.measure(name)?.dataset_id);
+    }
+    for name in &query.dimensions {
+        add_dataset(unqualified.dimension(name)?.dataset_id);
+    }
+    if let Some(time_dimension) = &query.time_dimension {
+        add_dataset(unqualified.dimension(&time_dimension.dimension)?.dataset_id);
+    }
+    for name in &query.segments {
+        add_dataset(unqualified.segment(name)?.0);
+    }
+    for filter in &query.filters {
+        add_dataset(unqualified.filter(filter)?.0);
+    }
+
+    // The dataset of the first measure is the base, so its rows are only repeated by joins
+    // that can't be avoided.
+    let plan = plan_joins(model, &dataset_ids[0], &dataset_ids[1..])?;
+
+    let compiler = Compiler {
+        model,
+        dialect: *dialect,
+        qualify: !plan.steps.is_empty(),
+        fanned_out: plan.fanned_out_datasets(),
+    };
+
+    let from_clause = compiler.join_clause(&plan)?;
+
+    let mut group_by = Vec::new();
+    let mut select = Vec::new();
+
+    for name in &query.dimensions {
+        let dimension = compiler.dimension(name)?;
+        select.push(format!("{} AS {}", dimension.sql, dimension.alias));
+        group_by.push(dimension.sql);
+    }
+
+    let time_alias = match &query.time_dimension {
+        Some(time_dimension) => {
+            let dimension = compiler.dimension(&time_dimension.dimension)?;
+            let sql = truncate_to_grain(&compiler.dialect, &dimension.sql, time_dimension.grain);
+            let alias = format!("{}_{}", dimension.alias, time_dimension.grain.as_str());
+            select.push(format!("{} AS {}", sql, alias));
+            group_by.push(sql);
+            Some(alias)
+        }
+        None => None,
+    };
+
+    let mut measure_aliases = Vec::new();
+
+    for name in &query.measures {
+        let measure = compiler.measure(name)?;
+        select.push(format!("{} AS {}", measure.sql, measure.alias));
+        measure_aliases.push(measure.alias);
+    }
+
+    let mut where_conditions = Vec::new();
+    let mut having_conditions = Vec::new();
+
+    for name in &query.segments {
+        let (_, sql) = compiler.segment(name)?;
+        where_conditions.push(format!("({})", sql));
+    }
+
+    for filter in &query.filters {
+        let (_, condition, is_aggregate) = compiler.filter(filter)?;
+        if is_aggregate {
+            having_conditions.push(condition);
+        } else {
+            where_conditions.push(condition);
+        }
+    }
+
+    let order_by = if !query.order_by.is_empty() {
+        query
+            .order_by
+            .iter()
+            .map(|order| {
+                let alias = compiler.output_alias(&order.field, query)?;
+                Ok(format!(
+                    "{} {}",
+                    alias,
+                    if order.descending { "DESC" } else { "ASC" }
+                ))
+            })
+            .collect::<Result<Vec<String>>>()?
+    } else if let Some(time_alias) = &time_alias {
+        vec![format!("{} ASC", time_alias)]
+    } else if let Some(measure_alias) = measure_aliases.first() {
+        vec![format!("{} DESC", measure_alias)]
+    } else {
+        vec![]
+    };
+
+    let mut sql = String::from("SELECT ");
+
+    if let (DataSourceType::SqlServer, Some(limit)) = (&compiler.dialect, query.limit) {
+        sql.push_str(&format!("TOP {} ", limit));
+    }
+
+    sql.push_str(&select.join(",\n    "));
+    sql.push_str(&format!("\nFROM {}", from_clause));
+
+    if !where_conditions.is_empty() {
+        sql.push_str(&format!("\nWHERE {}", where_conditions.join("\n  AND ")));
+    }
+
+    // Grouping also deduplicates rows when only dimensions are requested.
+    if !group_by.is_empty() {
+        sql.push_str(&format!("\nGROUP BY {}", group_by.join(", ")));
+    }
+
+    if !having_conditions.is_empty() {
+        sql.push_str(&format!("\nHAVING {}", having_conditions.join("\n  AND ")));
+    }
+
+    if !order_by.is_empty() {
+        sql.push_str(&format!("\nORDER BY {}", order_by.join(", ")));
+    }
+
+    if let Some(limit) = query.limit {
+        if compiler.dialect != DataSourceType::SqlServer {
+            sql.push_str(&format!("\nLIMIT {}", limit));
+        }
+    }
+
+    Ok(sql)
+}
+
+/// Compiles a query that evaluates one field of a dataset without returning any rows, so the
+/// data source can check the field's expression without scanning the table.
+pub fn compile_field_probe(
+    dataset: &SemanticDataset,
+    name: &str,
+    dialect: &DataSourceType,
+) -> Result<String> {
+    let model = SemanticModel {
+        datasets: vec![dataset.clone()],
+        relationships: vec![],
+    };
+
+    let compiler = Compiler {
+        model: &model,
+        dialect: *dialect,
+        qualify: false,
+        fanned_out: vec![],
+    };
+
+    let (select, condition) = if dataset.measure(name).is_some() || dataset.metric(name).is_some() {
+        (compiler.measure(name)?.sql, "1 = 0".to_string())
+    } else if dataset.segment(name).is_some() {
+        let (_, sql) = compiler.segment(name)?;
+        ("1".to_string(), format!("({}) AND 1 = 0", sql))
+    } else {
+        (compiler.dimension(name)?.sql, "1 = 0".to_string())
+    };
+
+    Ok(format!(
+        "SELECT {} AS probe FROM {} WHERE {}",
+        select, dataset.table, condition
+    ))
+}
+
+impl<'a> Compiler<'a> {
+    // Splits `dataset.field` and returns the datasets that could hold the field.
+    fn candidates(&self, name: &str) -> Result<(Vec<&'a SemanticDataset>, String)> {
+        match name.split_once('.') {
+            Some((dataset_name, field)) => match self.model.dataset_by_name(dataset_name) {
+                Some(dataset) => Ok((vec![dataset], field.to_string())),
+                None => Err(anyhow!("Dataset '{}' not found", dataset_name)),
+            },
+            None => Ok((self.model.datasets.iter().collect(), name.to_string())),
+        }
+    }
+
+    fn measure(&self, name: &str) -> Result<CompiledField> {
+        let (datasets, field) = self.candidates(name)?;
+
+        for dataset in &datasets {
+            if dataset.measure(&field).is_some() || dataset.metric(&field).is_some() {
+                self.check_fan_out(dataset, &field)?;
+            }
+
+            if let Some(measure) = dataset.measure(&field) {
+                return Ok(CompiledField {
+                    dataset_id: dataset.id,
+                    alias: measure.name.clone(),
+                    sql: self.aggregate(dataset, &measure.expr, &measure.agg)?,
+                });
+            }
+
+            if let Some(metric) = dataset.metric(&field) {
+                return Ok(CompiledField {
+                    dataset_id: dataset.id,
+                    alias: metric.name.clone(),
+                    sql: self.expand_metric(dataset, &metric.expr)?,
+                });
+            }
+        }
+
+        Err(anyhow!("Measure or metric '{}' not found", name))
+    }
+
+    fn dimension(&self, name: &str) -> Result<CompiledField> {
+        let (datasets, field) = self.candidates(name)?;
+
+        for dataset in &datasets {
+            if let Some(dimension) = dataset.dimension(&field) {
+                return Ok(CompiledField {
+                    dataset_id: dataset.id,
+                    alias: dimension.name.clone(),
+                    sql: self.qualify(dataset, &dimension.expr)?,
+                });
+            }
+        }
+
+        Err(anyhow!("Dimension '{}' not found", name))
+    }
+
+    fn segment(&self, name: &str) -> Result<(Uuid, String)> {
+        let (datasets, field) = self.candidates(name)?;
+
+        for dataset in &datasets {
+            if let Some(segment) = dataset.segment(&field) {
+                return Ok((dataset.id, self.qualify(dataset, &segment.expr)?));
+            }
+        }
+
+        Err(anyhow!("Segment '{}' not found", name))
+    }
+
+    // Returns the dataset, the condition and whether it filters on an aggregate.
+    fn filter(&self, filter: &SemanticFilter) -> Result<(Uuid, String, bool)> {
+        let (field, is_aggregate) = match self.dimension(&filter.field) {
+            Ok(dimension) => (dimension, false),
+            Err(_) => match self.measure(&filter.field) {
+                Ok(measure) => (measure, true),
+                Err(_) => return Err(anyhow!("Filter field '{}' not found", filter.field)),
+            },
+        };
+
+        let condition = match filter.operator {
+            FilterOperator::IsNull => format!("{} IS NULL", field.sql),
+            FilterOperator::IsNotNull => format!("{} IS NOT NULL", field.sql),
+            // `= NULL` is never true, so comparing to null checks for it instead.
+            FilterOperator::Eq if filter.value.is_null() => format!("{} IS NULL", field.sql),
+            FilterOperator::Neq if filter.value.is_null() => format!("{} IS NOT NULL", field.sql),
+            FilterOperator::In | FilterOperator::NotIn => {
+                let values = match &filter.value {
+                    Value::Array(values) => values.clone(),
+                    value => vec![value.clone()],
+                };
+
+                if values.is_empty() {
+                    return Err(anyhow!("Filter on '{}' has no values", filter.field));
+                }
+
+                let values = values
+                    .iter()
+                    .map(|v| render_literal(&self.dialect, v))
+                    .collect::<Result<Vec<String>>>()?;
+
+                format!(
+                    "{} {} ({})",
+                    field.sql,
+                    if filter.operator == FilterOperator::In {
+                        "IN"
+                    } else {
+                        "NOT IN"
+                    },
+                    values.join(", ")
+                )
+            }
+            _ if filter.value.is_null() => {
+                return Err(anyhow!("Filter on '{}' can't compare to null", filter.field))
+            }
+            operator => {
+                let symbol = match operator {
+                    FilterOperator::Eq => "=",
+                    FilterOperator::Neq => "<>",
+                    FilterOperator::Gt => ">",
+                    FilterOperator::Gte => ">=",
+                    FilterOperator::Lt => "<",
+                    FilterOperator::Lte => "<=",
+                    _ => "LIKE",
+                };
+
+                format!(
+                    "{} {} {}",
+                    field.sql,
+                    symbol,
+                    render_literal(&self.dialect, &filter.value)?
+                )
+            }
+        };
+
+        Ok((field.dataset_id, condition, is_aggregate))
+    }
+
+    // ORDER BY references the output columns, so it uses the aliases from the select list.
+    fn output_alias(&self, name: &str, query: &SemanticQuery) -> Result<String> {
+        let field = name.split_once('.').map(|(_, f)| f).unwrap_or(name);
+
+        if let Some(time_dimension) = &query.time_dimension {
+            let time_field = time_dimension
+                .dimension
+                .split_once('.')
+                .map(|(_, f)| f)
+                .unwrap_or(&time_dimension.dimension);
+
+            if field.eq_ignore_ascii_case(time_field) {
+                let dimension = self.dimension(&time_dimension.dimension)?;
+                return Ok(format!(
+                    "{}_{}",
+                    dimension.alias,
+                    time_dimension.grain.as_str()
+                ));
+            }
+        }
+
+        let selected = query
+            .measures
+            .iter()
+            .chain(query.dimensions.iter())
+            .any(|selected| {
+                let selected = selected.split_once('.').map(|(_, f)| f).unwrap_or(selected);
+                selected.eq_ignore_ascii_case(field)
+            });
+
+        if !selected {
+            return Err(anyhow!("Cannot order by '{}', it is not selected", name));
+        }
+
+        match self.measure(name) {
+            Ok(measure) => Ok(measure.alias),
+            Err(_) => Ok(self.dimension(name)?.alias),
+        }
+    }
+
+    fn aggregate(
+        &self,
+        dataset: &SemanticDataset,
+        expr: &str,
+        agg: &Option<String>,
+    ) -> Result<String> {
+        let expr = self.qualify(dataset, expr)?;
+
+        // Measures without an agg already aggregate in their expr.
+        let agg = match agg {
+            Some(agg) => agg.to_lowercase(),
+            None => return Ok(expr),
+        };
+
+        let sql = match agg.as_str() {
+            "sum" => format!("SUM({})", expr),
+            "count" => format!("COUNT({})", expr),
+            "count_distinct" => format!("COUNT(DISTINCT {})", expr),
+            "avg" | "average" | "mean" => format!("AVG({})", expr),
+            "min" => format!("MIN({})", expr),
+            "max" => format!("MAX({})", expr),
+            "sum_boolean" => format!("SUM(CASE WHEN {} THEN 1 ELSE 0 END)", expr),
+            "median" => match self.dialect {
+                DataSourceType::Snowflake | DataSourceType::Databricks => {
+                    format!("MEDIAN({})", expr)
+                }
+                DataSourceType::Postgres | DataSourceType::Supabase | DataSourceType::Redshift => {
+                    format!("PERCENTILE_CONT(0.5) WITHIN GROUP (ORDER BY {})", expr)
+                }
+                _ => {
+                    return Err(anyhow!(
+                        "Aggregation 'median' is not supported for {}",
+                        self.dialect.to_string()
+                    ))
+                }
+            },
+            _ => return Err(anyhow!("Unsupported aggregation '{}'", agg)),
+        };
+
+        Ok(sql)
+    }
+
+    // Metrics are expressions over the measures of their dataset, e.g. `revenue / orders`.
+    fn expand_metric(&self, dataset: &SemanticDataset, expr: &str) -> Result<String> {
+        let mut measures = HashMap::new();
+
+        for measure in &dataset.measures {
+            measures.insert(
+                measure.name.to_lowercase(),
+                self.aggregate(dataset, &measure.expr, &measure.agg)?,
+            );
+        }
+
+        let identifier = Regex::new(r"'[^']*'|[A-Za-z_][A-Za-z0-9_]*").unwrap();
+
+        let expanded = identifier.replace_all(expr, |caps: &Captures| {
+            let token = &caps[0];
+            match measures.get(&token.to_lowercase()) {
+                Some(sql) => format!("({})", sql),
+                None => token.to_string(),
+            }
+        });
+
+        Ok(expanded.to_string())
+    }
+
+    fn qualify(&self, dataset: &SemanticDataset, expr: &str) -> Result<String> {
+        if !self.qualify {
+            return Ok(expr.to_string());
+        }
+
+        qualify_expr(expr, &table_alias(dataset), &dataset.columns)
+    }
+
+    fn check_fan_out(&self, dataset: &SemanticDataset, field: &str) -> Result<()> {
+        if !self.fanned_out.contains(&dataset.id) {
+            return Ok(());
+        }
+
+        let safe = match dataset.measure(field) {
+            Some(measure) => measure
+                .agg
+                .as_ref()
+                .map(|agg| FAN_OUT_SAFE_AGGREGATIONS.contains(&agg.to_lowercase().as_str()))
+                .unwrap_or(false),
+            // Metrics are built from measures that could be overcounted.
+            None => false,
+        };
+
+        if safe {
+            return Ok(());
+        }
+
+        Err(anyhow!(
+            "The joins in this request repeat rows of {}, so '{}' would be overcounted",
+            dataset.name,
+            field
+        ))
+    }
+
+    fn join_clause(&self, plan: &JoinPlan) -> Result<String> {
+        let base = match self.model.dataset(&plan.base_dataset_id) {
+            Some(dataset) => dataset,
+            None => return Err(anyhow!("Dataset {} not found", plan.base_dataset_id)),
+        };
+
+        if !self.qualify {
+            return Ok(base.table.clone());
+        }
+
+        let mut from_clause = format!("{} AS {}", base.table, table_alias(base));
+
+        for step in &plan.steps {
+            let (from, to) = match (
+                self.model.dataset(&step.from_dataset_id),
+                self.model.dataset(&step.to_dataset_id),
+            ) {
+                (Some(from), Some(to)) => (from, to),
+                _ => return Err(anyhow!("Dataset in join plan not found")),
+            };
+
+            from_clause.push_str(&format!(
+                "\nLEFT JOIN {} AS {} ON {}.{} = {}.{}",
+                to.table,
+                table_alias(to),
+                table_alias(from),
+                step.from_key,
+                table_alias(to),
+                step.to_key
+            ));
+        }
+
+        Ok(from_clause)
+    }
+}
+
+fn table_alias(dataset: &SemanticDataset) -> String {
+    dataset
+        .name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+struct QualifyColumnsVisitor<'a> {
+    alias: &'a str,
+    columns: &'a [String],
+}
+
+impl VisitorMut for QualifyColumnsVisitor<'_> {
+    type Break = ();
+
+    fn post_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<Self::Break> {
+        if let Expr::Identifier(ident) = expr {
+            if self
+                .columns
+                .iter()
+                .any(|c| c.eq_ignore_ascii_case(&ident.value))
+            {
+                *expr = Expr::CompoundIdentifier(vec![Ident::new(self.alias), ident.clone()]);
+            }
+        }
+
+        ControlFlow::Continue(())
+    }
+}
+
+// Prefixes bare column references with the table alias so joined datasets don't collide.
+fn qualify_expr(expr: &str, alias: &str, columns: &[String]) -> Result<String> {
+    let dialect = GenericDialect {};
+
+    let mut parsed = match Parser::new(&dialect).try_with_sql(expr) {
+        Ok(mut parser) => match parser.parse_expr() {
+            Ok(parsed) => parsed,
+            Err(e) => return Err(anyhow!("Unable to parse expression '{}': {}", expr, e)),
+        },
+        Err(e) => return Err(anyhow!("Unable to parse expression '{}': {}", expr, e)),
+    };
+
+    let mut visitor = QualifyColumnsVisitor { alias, columns };
+    let _ = parsed.visit(&mut visitor);
+
+    Ok(parsed.to_string())
+}
+
+fn truncate_to_grain(dialect: &DataSourceType, expr: &str, grain: TimeGrain) -> String {
+    let grain_name = grain.as_str();
+
+    match dialect {
+        DataSourceType::BigQuery => format!(
+            "TIMESTAMP_TRUNC(CAST({} AS TIMESTAMP), {})",
+            expr,
+            grain_name.to_uppercase()
+        ),
+        DataSourceType::MySql | DataSourceType::Mariadb => match grain {
+            TimeGrain::Day => format!("DATE({})", expr),
+            TimeGrain::Week => format!("DATE_SUB(DATE({0}), INTERVAL WEEKDAY({0}) DAY)", expr),
+            TimeGrain::Month => format!("DATE_FORMAT({}, '%Y-%m-01')", expr),
+            TimeGrain::Quarter => format!(
+                "MAKEDATE(YEAR({0}), 1) + INTERVAL QUARTER({0}) - 1 QUARTER",
+                expr
+            ),
+            TimeGrain::Year => format!("MAKEDATE(YEAR({}), 1)", expr),
+        },
+        DataSourceType::SqlServer => {
+            format!("DATEADD({0}, DATEDIFF({0}, 0, {1}), 0)", grain_name, expr)
+        }
+        DataSourceType::Snowflake | DataSourceType::Databricks => {
+            format!("DATE_TRUNC('{}', {})", grain_name.to_uppercase(), expr)
+        }
+        DataSourceType::Postgres | DataSourceType::Redshift | DataSourceType::Supabase => {
+            format!("DATE_TRUNC('{}', {})", grain_name, expr)
+        }
+    }
+}
+
+fn render_literal(dialect: &DataSourceType, value: &Value) -> Result<String> {
+    match value {
+        Value::String(s) => Ok(quote_string_literal(s, dialect)),
+        Value::Number(n) => Ok(n.to_string()),
+        Value::Bool(b) => Ok(match (dialect, b) {
+            (DataSourceType::SqlServer, true) => "1".to_string(),
+            (DataSourceType::SqlServer, false) => "0".to_string(),
+            (_, true) => "TRUE".to_string(),
+            (_, false) => "FALSE".to_string(),
+        }),
+        Value::Null => Ok("NULL".to_string()),
+        _ => Err(anyhow!("Unsupported filter value: {}", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::semantic_layer::semantic_model::{SemanticField, SemanticRelationship};
+
+    fn field(name: &str, expr: &str, agg: Option<&str>) -> SemanticField {
+        SemanticField {
+            name: name.to_string(),
+            expr: expr.to_string(),
+            agg: agg.map(String::from),
+            description: None,
+        }
+    }
+
+    fn model() -> SemanticModel {
+        let orders = SemanticDataset {
+            id: Uuid::new_v4(),
+            name: "orders".to_string(),
+            table: "public.orders".to_string(),
+            columns: vec![
+                "id".to_string(),
+                "customer_id".to_string(),
+                "amount".to_string(),
+                "status".to_string(),
+                "created_at".to_string(),
+            ],
+            dimensions: vec![],
+            measures: vec![
+                field("revenue", "amount", Some("sum")),
+                field("order_count", "id", Some("count_distinct")),
+            ],
+            metrics: vec![field("average_order_value", "revenue / order_count", None)],
+            segments: vec![field("completed", "status = 'completed'", None)],
+        };
+
+        let customers = SemanticDataset {
+            id: Uuid::new_v4(),
+            name: "customers".to_string(),
+            table: "public.customers".to_string(),
+            columns: vec!["id".to_string(), "region".to_string()],
+            dimensions: vec![],
+            measures: vec![],
+            metrics: vec![],
+            segments: vec![],
+        };
+
+        SemanticModel {
+            relationships: vec![SemanticRelationship {
+                primary_dataset_id: customers.id,
+                foreign_dataset_id: orders.id,
+                relationship_type: "one_to_many".to_string(),
+                primary_key: "id".to_string(),
+                foreign_key: "customer_id".to_string(),
+            }],
+            datasets: vec![orders, customers],
+        }
+    }
+
+    #[test]
+    fn test_compile_single_dataset_with_time_grain() {
+        let query = SemanticQuery {
+            measures: vec!["revenue".to_string()],
+            segments: vec!["completed".to_string()],
+            time_dimension: Some(TimeDimension {
+                dimension: "created_at".to_string(),
+                grain: TimeGrain::Month,
+            }),
+            ..Default::default()
+        };
+
+        let sql = compile_semantic_query(&model(), &query, &DataSourceType::Postgres).unwrap();
+
+        assert_eq!(
+            sql,
+            "SELECT DATE_TRUNC('month', created_at) AS created_at_month,\n    SUM(amount) AS revenue\nFROM public.orders\nWHERE (status = 'completed')\nGROUP BY DATE_TRUNC('month', created_at)\nORDER BY created_at_month ASC"
+        );
+    }
+
+    #[test]
+    fn test_compile_joins_related_dataset() {
+        let query = SemanticQuery {
+            measures: vec!["revenue".to_string()],
+            dimensions: vec!["region".to_string()],
+            limit: Some(10),
+            ..Default::default()
+        };
+
+        let sql = compile_semantic_query(&model(), &query, &DataSourceType::Snowflake).unwrap();
+
+        assert!(sql.contains("SUM(orders.amount) AS revenue"));
+        assert!(sql.contains("customers.region AS region"));
+        assert!(sql.contains(
+            "FROM public.orders AS orders\nLEFT JOIN public.customers AS customers ON orders.customer_id = customers.id"
+        ));
+        assert!(sql.ends_with("LIMIT 10"));
+    }
+
+    #[test]
+    fn test_compile_metric_and_having_filter() {
+        let query = SemanticQuery {
+            measures: vec!["average_order_value".to_string()],
+            dimensions: vec!["status".to_string()],
+            filters: vec![SemanticFilter {
+                field: "order_count".to_string(),
+                operator: FilterOperator::Gt,
+                value: serde_json::json!(100),
+            }],
+            limit: Some(5),
+            ..Default::default()
+        };
+
+        let sql = compile_semantic_query(&model(), &query, &DataSourceType::SqlServer).unwrap();
+
+        assert!(sql.starts_with("SELECT TOP 5 status AS status"));
+        assert!(sql.contains("(SUM(amount)) / (COUNT(DISTINCT id)) AS average_order_value"));
+        assert!(sql.contains("HAVING COUNT(DISTINCT id) > 100"));
+        assert!(!sql.contains("LIMIT"));
+    }
+
+    #[test]
+    fn test_compile_filter_values() {
+        let filter = |field: &str, operator: FilterOperator, value: Value| SemanticFilter {
+            field: field.to_string(),
+            operator,
+            value,
+        };
+
+        let query = SemanticQuery {
+            measures: vec!["revenue".to_string()],
+            filters: vec![
+                filter("status", FilterOperator::Eq, Value::Null),
+                filter("customer_id", FilterOperator::Neq, Value::Null),
+                filter("status", FilterOperator::Neq, serde_json::json!("\\' OR 1=1 --")),
+            ],
+            ..Default::default()
+        };
+
+        let sql = compile_semantic_query(&model(), &query, &DataSourceType::MySql).unwrap();
+
+        assert!(sql.contains("status IS NULL"));
+        assert!(sql.contains("customer_id IS NOT NULL"));
+        assert!(sql.contains("status <> '\\\\\\' OR 1=1 --'"));
+
+        let query = SemanticQuery {
+            measures: vec!["revenue".to_string()],
+            filters: vec![filter("amount", FilterOperator::Gt, Value::Null)],
+            ..Default::default()
+        };
+
+        assert!(compile_semantic_query(&model(), &query, &DataSourceType::MySql).is_err());
+    }
+
+    #[test]
+    fn test_compile_rejects_fanned_out_measure() {
+        let mut model = model();
+        model.datasets[1]
+            .measures
+            .push(field("customer_count", "id", Some("count")));
+
+        // Starting from customers, joining orders repeats every customer once per order.
+        let query = SemanticQuery {
+            measures: vec!["customer_count".to_string(), "revenue".to_string()],
+            ..Default::default()
+        };
+
+        let result = compile_semantic_query(&model, &query, &DataSourceType::Postgres);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_compile_field_probes() {
+        let model = model();
+        let orders = &model.datasets[0];
+
+        assert_eq!(
+            compile_field_probe(orders, "average_order_value", &DataSourceType::Postgres).unwrap(),
+            "SELECT (SUM(amount)) / (COUNT(DISTINCT id)) AS probe FROM public.orders WHERE 1 = 0"
+        );
+        assert_eq!(
+            compile_field_probe(orders, "completed", &DataSourceType::Postgres).unwrap(),
+            "SELECT 1 AS probe FROM public.orders WHERE (status = 'completed') AND 1 = 0"
+        );
+        assert!(compile_field_probe(orders, "profit", &DataSourceType::Postgres).is_err());
+    }
+
+    #[test]
+    fn test_compile_unknown_field_fails() {
+        let query = SemanticQuery {
+            measures: vec!["profit".to_string()],
+            ..Default::default()
+        };
+
+        let result = compile_semantic_query(&model(), &query, &DataSourceType::Postgres);
+
+        assert!(result.is_err());
+    }
+}
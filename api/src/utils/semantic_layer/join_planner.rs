@@ -0,0 +1,345 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
+};
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use uuid::Uuid;
+
+use super::semantic_model::SemanticModel;
+
+// Joins that repeat rows cost more so the planner routes around them when it can.
+const SAFE_JOIN_COST: u32 = 1;
+const FAN_OUT_JOIN_COST: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Cardinality {
+    OneToOne,
+    OneToMany,
+    ManyToOne,
+    ManyToMany,
+}
+
+impl Cardinality {
+    // Relationship types are stored from the primary dataset's side.
+    pub fn from_relationship_type(s: &str) -> Self {
+        match s {
+            "one_to_one" => Cardinality::OneToOne,
+            "many_to_one" => Cardinality::ManyToOne,
+            "many_to_many" => Cardinality::ManyToMany,
+            _ => Cardinality::OneToMany,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Cardinality::OneToOne => "one_to_one",
+            Cardinality::OneToMany => "one_to_many",
+            Cardinality::ManyToOne => "many_to_one",
+            Cardinality::ManyToMany => "many_to_many",
+        }
+    }
+
+    fn reverse(&self) -> Self {
+        match self {
+            Cardinality::OneToMany => Cardinality::ManyToOne,
+            Cardinality::ManyToOne => Cardinality::OneToMany,
+            cardinality => *cardinality,
+        }
+    }
+
+    /// Whether joining in this direction can repeat the rows already in the query.
+    pub fn fans_out(&self) -> bool {
+        matches!(self, Cardinality::OneToMany | Cardinality::ManyToMany)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JoinStep {
+    pub from_dataset_id: Uuid,
+    pub to_dataset_id: Uuid,
+    pub from_key: String,
+    pub to_key: String,
+    pub cardinality: Cardinality,
+}
+
+/// The joins needed to reach a set of datasets from a base dataset.
+#[derive(Debug, Clone, Serialize)]
+pub struct JoinPlan {
+    pub base_dataset_id: Uuid,
+    pub steps: Vec<JoinStep>,
+}
+
+impl JoinPlan {
+    pub fn dataset_ids(&self) -> Vec<Uuid> {
+        let mut dataset_ids = vec![self.base_dataset_id];
+        dataset_ids.extend(self.steps.iter().map(|s| s.to_dataset_id));
+        dataset_ids
+    }
+
+    /// Datasets whose rows are repeated by a one-to-many join elsewhere in the plan. Sums, counts
+    /// and averages over these datasets are overcounted unless they're aggregated before joining.
+    pub fn fanned_out_datasets(&self) -> Vec<Uuid> {
+        let mut fanned_out = HashSet::new();
+
+        for step in self.steps.iter().filter(|s| s.cardinality.fans_out()) {
+            let downstream = self.downstream_of(&step.to_dataset_id);
+
+            for dataset_id in self.dataset_ids() {
+                if !downstream.contains(&dataset_id) {
+                    fanned_out.insert(dataset_id);
+                }
+            }
+        }
+
+        self.dataset_ids()
+            .into_iter()
+            .filter(|id| fanned_out.contains(id))
+            .collect()
+    }
+
+    // The dataset and every dataset joined through it.
+    fn downstream_of(&self, dataset_id: &Uuid) -> HashSet<Uuid> {
+        let mut downstream = HashSet::from([*dataset_id]);
+
+        // Steps are ordered parent first, so a single pass reaches every descendant.
+        for step in &self.steps {
+            if downstream.contains(&step.from_dataset_id) {
+                downstream.insert(step.to_dataset_id);
+            }
+        }
+
+        downstream
+    }
+
+    /// Renders the plan for the SQL prompts.
+    pub fn to_prompt_string(&self, model: &SemanticModel) -> String {
+        let name = |id: &Uuid| {
+            model
+                .dataset(id)
+                .map(|d| d.name.clone())
+                .unwrap_or_else(|| id.to_string())
+        };
+
+        let mut plan = format!("## JOIN PLAN\nStart from {}\n", name(&self.base_dataset_id));
+
+        for step in &self.steps {
+            plan.push_str(&format!(
+                "- LEFT JOIN {to} ON {from}.{from_key} = {to}.{to_key} ({cardinality})\n",
+                to = name(&step.to_dataset_id),
+                from = name(&step.from_dataset_id),
+                from_key = step.from_key,
+                to_key = step.to_key,
+                cardinality = step.cardinality.as_str(),
+            ));
+        }
+
+        let fanned_out = self.fanned_out_datasets();
+
+        if !fanned_out.is_empty() {
+            plan.push_str(&format!(
+                "WARNING: these joins repeat the rows of {}. Aggregate their measures in a CTE before joining, or use COUNT(DISTINCT ...), MIN or MAX.\n",
+                fanned_out
+                    .iter()
+                    .map(name)
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ));
+        }
+
+        plan
+    }
+}
+
+/// Finds the joins that connect `targets` to `base` through the relationships of the model.
+///
+/// Each target is reached by the cheapest path, where joins that repeat rows cost more than
+/// joins that don't. Paths share their common prefix, so the plan is a tree rooted at `base`.
+pub fn plan_joins(model: &SemanticModel, base: &Uuid, targets: &[Uuid]) -> Result<JoinPlan> {
+    let mut edges: HashMap<Uuid, Vec<JoinStep>> = HashMap::new();
+
+    for relationship in &model.relationships {
+        let cardinality = Cardinality::from_relationship_type(&relationship.relationship_type);
+
+        edges
+            .entry(relationship.primary_dataset_id)
+            .or_default()
+            .push(JoinStep {
+                from_dataset_id: relationship.primary_dataset_id,
+                to_dataset_id: relationship.foreign_dataset_id,
+                from_key: relationship.primary_key.clone(),
+                to_key: relationship.foreign_key.clone(),
+                cardinality,
+            });
+
+        edges
+            .entry(relationship.foreign_dataset_id)
+            .or_default()
+            .push(JoinStep {
+                from_dataset_id: relationship.foreign_dataset_id,
+                to_dataset_id: relationship.primary_dataset_id,
+                from_key: relationship.foreign_key.clone(),
+                to_key: relationship.primary_key.clone(),
+                cardinality: cardinality.reverse(),
+            });
+    }
+
+    let mut costs: HashMap<Uuid, u32> = HashMap::from([(*base, 0)]);
+    let mut previous: HashMap<Uuid, JoinStep> = HashMap::new();
+    let mut queue = BinaryHeap::from([Reverse((0, *base))]);
+
+    while let Some(Reverse((cost, dataset_id))) = queue.pop() {
+        if cost > *costs.get(&dataset_id).unwrap_or(&u32::MAX) {
+            continue;
+        }
+
+        for step in edges.get(&dataset_id).into_iter().flatten() {
+            let step_cost = cost
+                + if step.cardinality.fans_out() {
+                    FAN_OUT_JOIN_COST
+                } else {
+                    SAFE_JOIN_COST
+                };
+
+            if step_cost < *costs.get(&step.to_dataset_id).unwrap_or(&u32::MAX) {
+                costs.insert(step.to_dataset_id, step_cost);
+                previous.insert(step.to_dataset_id, step.clone());
+                queue.push(Reverse((step_cost, step.to_dataset_id)));
+            }
+        }
+    }
+
+    let mut steps: Vec<JoinStep> = Vec::new();
+
+    for target in targets {
+        if target == base {
+            continue;
+        }
+
+        let mut path = Vec::new();
+        let mut current = *target;
+
+        while current != *base {
+            let step = match previous.get(&current) {
+                Some(step) => step,
+                None => {
+                    let name = |id: &Uuid| {
+                        model
+                            .dataset(id)
+                            .map(|d| d.name.clone())
+                            .unwrap_or_else(|| id.to_string())
+                    };
+
+                    return Err(anyhow!(
+                        "No relationship path between {} and {}",
+                        name(base),
+                        name(target)
+                    ));
+                }
+            };
+
+            path.push(step.clone());
+            current = step.from_dataset_id;
+        }
+
+        for step in path.into_iter().rev() {
+            if !steps.iter().any(|s| s.to_dataset_id == step.to_dataset_id) {
+                steps.push(step);
+            }
+        }
+    }
+
+    Ok(JoinPlan {
+        base_dataset_id: *base,
+        steps,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::semantic_layer::semantic_model::{SemanticDataset, SemanticRelationship};
+
+    fn dataset(name: &str) -> SemanticDataset {
+        SemanticDataset {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            table: format!("public.{}", name),
+            columns: vec![],
+            dimensions: vec![],
+            measures: vec![],
+            metrics: vec![],
+            segments: vec![],
+        }
+    }
+
+    fn relationship(
+        primary: &SemanticDataset,
+        foreign: &SemanticDataset,
+        foreign_key: &str,
+    ) -> SemanticRelationship {
+        SemanticRelationship {
+            primary_dataset_id: primary.id,
+            foreign_dataset_id: foreign.id,
+            relationship_type: "one_to_many".to_string(),
+            primary_key: "id".to_string(),
+            foreign_key: foreign_key.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_plan_joins_across_three_datasets() {
+        let regions = dataset("regions");
+        let customers = dataset("customers");
+        let orders = dataset("orders");
+
+        let model = SemanticModel {
+            relationships: vec![
+                relationship(&regions, &customers, "region_id"),
+                relationship(&customers, &orders, "customer_id"),
+            ],
+            datasets: vec![regions.clone(), customers.clone(), orders.clone()],
+        };
+
+        let plan = plan_joins(&model, &orders.id, &[regions.id]).unwrap();
+
+        assert_eq!(
+            plan.dataset_ids(),
+            vec![orders.id, customers.id, regions.id]
+        );
+        assert_eq!(plan.steps[0].from_key, "customer_id");
+        assert_eq!(plan.steps[0].cardinality, Cardinality::ManyToOne);
+        assert!(plan.fanned_out_datasets().is_empty());
+    }
+
+    #[test]
+    fn test_one_to_many_join_fans_out_base() {
+        let customers = dataset("customers");
+        let orders = dataset("orders");
+
+        let model = SemanticModel {
+            relationships: vec![relationship(&customers, &orders, "customer_id")],
+            datasets: vec![customers.clone(), orders.clone()],
+        };
+
+        let plan = plan_joins(&model, &customers.id, &[orders.id]).unwrap();
+
+        assert_eq!(plan.steps[0].cardinality, Cardinality::OneToMany);
+        assert_eq!(plan.fanned_out_datasets(), vec![customers.id]);
+    }
+
+    #[test]
+    fn test_unrelated_datasets_have_no_plan() {
+        let customers = dataset("customers");
+        let tickets = dataset("tickets");
+
+        let model = SemanticModel {
+            relationships: vec![],
+            datasets: vec![customers.clone(), tickets.clone()],
+        };
+
+        assert!(plan_joins(&model, &customers.id, &[tickets.id]).is_err());
+    }
+}
@@ -0,0 +1,3 @@
+pub mod compiler;
+pub mod join_planner;
+pub mod semantic_model;
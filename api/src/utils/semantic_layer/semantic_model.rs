@@ -0,0 +1,347 @@
+use anyhow::{anyhow, Result};
+use diesel::{ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::database::{
+    enums::SemanticObjectType,
+    lib::get_pg_pool,
+    models::{Dataset, DatasetColumn, EntityRelationship, SemanticObject},
+    schema::{dataset_columns, datasets, entity_relationship, semantic_objects},
+};
+
+/// The semantic definitions of a set of datasets and the relationships between them.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SemanticModel {
+    pub datasets: Vec<SemanticDataset>,
+    pub relationships: Vec<SemanticRelationship>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SemanticDataset {
+    pub id: Uuid,
+    pub name: String,
+    // Fully qualified table reference, as used in the dataset DDL.
+    pub table: String,
+    pub columns: Vec<String>,
+    pub dimensions: Vec<SemanticField>,
+    pub measures: Vec<SemanticField>,
+    pub metrics: Vec<SemanticField>,
+    pub segments: Vec<SemanticField>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SemanticField {
+    pub name: String,
+    pub expr: String,
+    pub agg: Option<String>,
+    pub description: Option<String>,
+}
+
+/// A join between the dataset holding the foreign key and the dataset it references.
+#[derive(Debug, Clone, Serialize)]
+pub struct SemanticRelationship {
+    pub primary_dataset_id: Uuid,
+    pub foreign_dataset_id: Uuid,
+    pub relationship_type: String,
+    pub primary_key: String,
+    pub foreign_key: String,
+}
+
+impl SemanticModel {
+    pub fn dataset(&self, id: &Uuid) -> Option<&SemanticDataset> {
+        self.datasets.iter().find(|d| &d.id == id)
+    }
+
+    pub fn dataset_by_name(&self, name: &str) -> Option<&SemanticDataset> {
+        self.datasets
+            .iter()
+            .find(|d| d.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn has_measures(&self) -> bool {
+        self.datasets
+            .iter()
+            .any(|d| !d.measures.is_empty() || !d.metrics.is_empty())
+    }
+}
+
+impl SemanticDataset {
+    /// Physical columns double as dimensions when the model doesn't define them.
+    pub fn dimension(&self, name: &str) -> Option<SemanticField> {
+        if let Some(dimension) = self
+            .dimensions
+            .iter()
+            .find(|d| d.name.eq_ignore_ascii_case(name))
+        {
+            return Some(dimension.clone());
+        }
+
+        self.columns
+            .iter()
+            .find(|c| c.eq_ignore_ascii_case(name))
+            .map(|column| SemanticField {
+                name: column.clone(),
+                expr: column.clone(),
+                agg: None,
+                description: None,
+            })
+    }
+
+    pub fn measure(&self, name: &str) -> Option<&SemanticField> {
+        self.measures
+            .iter()
+            .find(|m| m.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn metric(&self, name: &str) -> Option<&SemanticField> {
+        self.metrics
+            .iter()
+            .find(|m| m.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn segment(&self, name: &str) -> Option<&SemanticField> {
+        self.segments
+            .iter()
+            .find(|s| s.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Loads the semantic model for the datasets and the relationships between them. Relationships
+/// to datasets outside of `dataset_ids` are left out, so joins never leave the datasets the user
+/// has access to.
+pub async fn get_semantic_model(dataset_ids: &[Uuid]) -> Result<SemanticModel> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Unable to get connection from pool: {}", e)),
+    };
+
+    let relationships = match entity_relationship::table
+        .filter(entity_relationship::primary_dataset_id.eq_any(dataset_ids))
+        .filter(entity_relationship::foreign_dataset_id.eq_any(dataset_ids))
+        .load::<EntityRelationship>(&mut conn)
+        .await
+    {
+        Ok(relationships) => relationships,
+        Err(e) => return Err(anyhow!("Error loading entity relationships: {}", e)),
+    };
+
+    let datasets = match datasets::table
+        .filter(datasets::id.eq_any(dataset_ids))
+        .filter(datasets::deleted_at.is_null())
+        .load::<Dataset>(&mut conn)
+        .await
+    {
+        Ok(datasets) => datasets,
+        Err(e) => return Err(anyhow!("Error loading datasets: {}", e)),
+    };
+
+    let columns = match dataset_columns::table
+        .filter(dataset_columns::dataset_id.eq_any(dataset_ids))
+        .filter(dataset_columns::deleted_at.is_null())
+        .load::<DatasetColumn>(&mut conn)
+        .await
+    {
+        Ok(columns) => columns,
+        Err(e) => return Err(anyhow!("Error loading dataset columns: {}", e)),
+    };
+
+    let objects = match semantic_objects::table
+        .filter(semantic_objects::dataset_id.eq_any(dataset_ids))
+        .filter(semantic_objects::deleted_at.is_null())
+        .order(semantic_objects::name.asc())
+        .load::<SemanticObject>(&mut conn)
+        .await
+    {
+        Ok(objects) => objects,
+        Err(e) => return Err(anyhow!("Error loading semantic objects: {}", e)),
+    };
+
+    Ok(build_semantic_model(
+        datasets,
+        columns,
+        objects,
+        relationships,
+    ))
+}
+
+pub fn build_semantic_model(
+    datasets: Vec<Dataset>,
+    columns: Vec<DatasetColumn>,
+    objects: Vec<SemanticObject>,
+    relationships: Vec<EntityRelationship>,
+) -> SemanticModel {
+    let semantic_datasets = datasets
+        .into_iter()
+        .map(|dataset| {
+            let table = match &dataset.database_identifier {
+                Some(database) => {
+                    format!("{}.{}.{}", database, dataset.schema, dataset.database_name)
+                }
+                None => format!("{}.{}", dataset.schema, dataset.database_name),
+            };
+
+            let fields = |object_type: SemanticObjectType| {
+                objects
+                    .iter()
+                    .filter(|o| o.dataset_id == dataset.id && o.object_type == object_type)
+                    .map(|o| SemanticField {
+                        name: o.name.clone(),
+                        expr: o.expr.clone(),
+                        agg: o.agg.clone(),
+                        description: o.description.clone(),
+                    })
+                    .collect::<Vec<SemanticField>>()
+            };
+
+            // Dimensions on physical columns live in dataset_columns, possibly with an expr.
+            let mut dimensions = fields(SemanticObjectType::Dimension);
+
+            for column in columns.iter().filter(|c| c.dataset_id == dataset.id) {
+                if let Some(expr) = &column.expr {
+                    if column.semantic_type.as_deref() == Some("dimension") && expr != &column.name
+                    {
+                        dimensions.push(SemanticField {
+                            name: column.name.clone(),
+                            expr: expr.clone(),
+                            agg: None,
+                            description: column.description.clone(),
+                        });
+                    }
+                }
+            }
+
+            SemanticDataset {
+                id: dataset.id,
+                name: dataset.name.clone(),
+                table,
+                columns: columns
+                    .iter()
+                    .filter(|c| c.dataset_id == dataset.id)
+                    .map(|c| c.name.clone())
+                    .collect(),
+                dimensions,
+                measures: fields(SemanticObjectType::Measure),
+                metrics: fields(SemanticObjectType::Metric),
+                segments: fields(SemanticObjectType::Segment),
+            }
+        })
+        .collect::<Vec<SemanticDataset>>();
+
+    let relationships = relationships
+        .into_iter()
+        .filter_map(|relationship| {
+            // Relationships created before keys were recorded can't be compiled into a join.
+            let foreign_key = relationship.foreign_key?;
+
+            Some(SemanticRelationship {
+                primary_dataset_id: relationship.primary_dataset_id,
+                foreign_dataset_id: relationship.foreign_dataset_id,
+                relationship_type: relationship.relationship_type,
+                // Without a primary entity on the referenced model, assume the key has the same name.
+                primary_key: relationship.primary_key.unwrap_or(foreign_key.clone()),
+                foreign_key,
+            })
+        })
+        .collect();
+
+    SemanticModel {
+        datasets: semantic_datasets,
+        relationships,
+    }
+}
+
+impl SemanticModel {
+    /// Renders the semantic definitions for a prompt.
+    pub fn to_prompt_string(&self) -> String {
+        let mut description = String::new();
+
+        for dataset in &self.datasets {
+            description.push_str(&format!("## DATASET: {}\n", dataset.name));
+
+            let sections = [
+                ("Dimensions", &dataset.dimensions),
+                ("Measures", &dataset.measures),
+                ("Metrics", &dataset.metrics),
+                ("Segments", &dataset.segments),
+            ];
+
+            for (title, fields) in sections {
+                if fields.is_empty() {
+                    continue;
+                }
+
+                description.push_str(&format!("### {}\n", title));
+
+                for field in fields {
+                    description.push_str(&format!("- {}", field.name));
+
+                    if let Some(agg) = &field.agg {
+                        description.push_str(&format!(" ({} of {})", agg, field.expr));
+                    } else {
+                        description.push_str(&format!(" ({})", field.expr));
+                    }
+
+                    if let Some(field_description) = &field.description {
+                        description.push_str(&format!(": {}", field_description));
+                    }
+
+                    description.push('\n');
+                }
+            }
+
+            description.push_str(&format!("### Columns\n{}\n\n", dataset.columns.join(", ")));
+        }
+
+        description.push_str(&self.relationships_prompt_string());
+
+        description
+    }
+
+    /// Renders the relationships between the datasets for a prompt.
+    pub fn relationships_prompt_string(&self) -> String {
+        let mut description = String::new();
+
+        for relationship in &self.relationships {
+            let primary = self.dataset(&relationship.primary_dataset_id);
+            let foreign = self.dataset(&relationship.foreign_dataset_id);
+
+            if let (Some(primary), Some(foreign)) = (primary, foreign) {
+                description.push_str(&format!(
+                    "- {}.{} references {}.{} ({} {} to {})\n",
+                    foreign.name,
+                    relationship.foreign_key,
+                    primary.name,
+                    relationship.primary_key,
+                    relationship.relationship_type,
+                    primary.name,
+                    foreign.name
+                ));
+            }
+        }
+
+        description
+    }
+
+    /// The part of the model that covers the datasets.
+    pub fn subset(&self, dataset_ids: &[Uuid]) -> SemanticModel {
+        SemanticModel {
+            datasets: self
+                .datasets
+                .iter()
+                .filter(|d| dataset_ids.contains(&d.id))
+                .cloned()
+                .collect(),
+            relationships: self
+                .relationships
+                .iter()
+                .filter(|r| {
+                    dataset_ids.contains(&r.primary_dataset_id)
+                        && dataset_ids.contains(&r.foreign_dataset_id)
+                })
+                .cloned()
+                .collect(),
+        }
+    }
+}
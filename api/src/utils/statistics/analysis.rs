@@ -0,0 +1,1017 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Days, Duration, Months, NaiveDate, NaiveDateTime};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{cmp::Ordering, collections::BTreeMap, str::FromStr};
+
+use crate::utils::query_engine::data_types::DataType;
+
+// Series results are cut to the most recent points so they fit in a prompt.
+const MAX_SERIES_POINTS: usize = 50;
+
+const DEFAULT_OUTLIER_THRESHOLD: f64 = 2.0;
+
+const SIGNIFICANCE_LEVEL: f64 = 0.05;
+
+/// An analysis to run over query results. Column names refer to the columns of the results.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnalysisRequest {
+    GrowthRate {
+        column: String,
+        order_by: Option<String>,
+    },
+    MovingAverage {
+        column: String,
+        order_by: Option<String>,
+        window: usize,
+    },
+    Outliers {
+        column: String,
+        label_column: Option<String>,
+        threshold: Option<f64>,
+    },
+    Correlation {
+        x_column: String,
+        y_column: String,
+    },
+    LinearRegression {
+        x_column: String,
+        y_column: String,
+    },
+    PeriodOverPeriod {
+        date_column: String,
+        value_column: String,
+        period: Period,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Period {
+    Day,
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
+
+#[derive(Serialize, Debug)]
+struct SeriesPoint {
+    label: String,
+    value: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    change: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    change_pct: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    moving_average: Option<f64>,
+}
+
+#[derive(Serialize, Debug)]
+struct GrowthRateResult {
+    column: String,
+    first_value: f64,
+    last_value: f64,
+    total_change: f64,
+    total_change_pct: Option<f64>,
+    average_change_pct: Option<f64>,
+    compound_growth_pct: Option<f64>,
+    points: Vec<SeriesPoint>,
+}
+
+#[derive(Serialize, Debug)]
+struct MovingAverageResult {
+    column: String,
+    window: usize,
+    points: Vec<SeriesPoint>,
+}
+
+#[derive(Serialize, Debug)]
+struct Outlier {
+    label: String,
+    value: f64,
+    z_score: f64,
+}
+
+#[derive(Serialize, Debug)]
+struct OutliersResult {
+    column: String,
+    mean: f64,
+    std_dev: f64,
+    threshold: f64,
+    outliers: Vec<Outlier>,
+}
+
+#[derive(Serialize, Debug)]
+struct CorrelationResult {
+    x_column: String,
+    y_column: String,
+    n: usize,
+    pearson_r: f64,
+    r_squared: f64,
+    strength: String,
+}
+
+#[derive(Serialize, Debug)]
+struct LinearRegressionResult {
+    x_column: String,
+    y_column: String,
+    // Dates are converted to days so the slope is the change per day.
+    x_unit: String,
+    n: usize,
+    slope: f64,
+    intercept: f64,
+    r_squared: f64,
+    t_statistic: Option<f64>,
+    p_value: Option<f64>,
+    significant: Option<bool>,
+}
+
+#[derive(Serialize, Debug)]
+struct PeriodValue {
+    period_start: String,
+    value: f64,
+    change: Option<f64>,
+    change_pct: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    year_over_year_change_pct: Option<f64>,
+}
+
+#[derive(Serialize, Debug)]
+struct PeriodOverPeriodResult {
+    date_column: String,
+    value_column: String,
+    period: Period,
+    periods: Vec<PeriodValue>,
+}
+
+/// Runs an analysis over query results and returns the computed numbers as JSON.
+pub fn run_analysis(
+    data: &[IndexMap<String, DataType>],
+    request: &AnalysisRequest,
+) -> Result<Value> {
+    let result = match request {
+        AnalysisRequest::GrowthRate { column, order_by } => {
+            serde_json::to_value(growth_rate(data, column, order_by)?)
+        }
+        AnalysisRequest::MovingAverage {
+            column,
+            order_by,
+            window,
+        } => serde_json::to_value(moving_average(data, column, order_by, *window)?),
+        AnalysisRequest::Outliers {
+            column,
+            label_column,
+            threshold,
+        } => serde_json::to_value(outliers(
+            data,
+            column,
+            label_column,
+            threshold.unwrap_or(DEFAULT_OUTLIER_THRESHOLD),
+        )?),
+        AnalysisRequest::Correlation { x_column, y_column } => {
+            serde_json::to_value(correlation(data, x_column, y_column)?)
+        }
+        AnalysisRequest::LinearRegression { x_column, y_column } => {
+            serde_json::to_value(linear_regression(data, x_column, y_column)?)
+        }
+        AnalysisRequest::PeriodOverPeriod {
+            date_column,
+            value_column,
+            period,
+        } => serde_json::to_value(period_over_period(
+            data,
+            date_column,
+            value_column,
+            *period,
+        )?),
+    };
+
+    match result {
+        Ok(value) => Ok(value),
+        Err(e) => Err(anyhow!("Unable to serialize analysis result: {}", e)),
+    }
+}
+
+/// Converts rows that went through JSON (i.e. the `results` of the SQL agents) back into
+/// typed rows. Numbers become `Float8` or `Int8` and strings become `Text`.
+pub fn rows_from_json(rows: &[Value]) -> Vec<IndexMap<String, DataType>> {
+    rows.iter()
+        .filter_map(|row| row.as_object())
+        .map(|row| {
+            row.iter()
+                .map(|(key, value)| {
+                    let value = match value {
+                        Value::Null => DataType::Null,
+                        Value::Bool(b) => DataType::Bool(Some(*b)),
+                        Value::Number(n) => match n.as_i64() {
+                            Some(i) => DataType::Int8(Some(i)),
+                            None => DataType::Float8(n.as_f64()),
+                        },
+                        Value::String(s) => DataType::Text(Some(s.clone())),
+                        other => DataType::Json(Some(other.clone())),
+                    };
+
+                    (key.clone(), value)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn growth_rate(
+    data: &[IndexMap<String, DataType>],
+    column: &str,
+    order_by: &Option<String>,
+) -> Result<GrowthRateResult> {
+    let series = ordered_series(data, column, order_by)?;
+
+    if series.len() < 2 {
+        return Err(anyhow!(
+            "Growth rate needs at least two values in {}",
+            column
+        ));
+    }
+
+    let mut points = Vec::with_capacity(series.len());
+    let mut change_pcts = Vec::new();
+
+    for (i, (label, value)) in series.iter().enumerate() {
+        let (change, change_pct) = if i == 0 {
+            (None, None)
+        } else {
+            let previous = series[i - 1].1;
+            (Some(value - previous), pct_change(previous, *value))
+        };
+
+        if let Some(change_pct) = change_pct {
+            change_pcts.push(change_pct);
+        }
+
+        points.push(SeriesPoint {
+            label: label.clone(),
+            value: *value,
+            change,
+            change_pct,
+            moving_average: None,
+        });
+    }
+
+    let first_value = series[0].1;
+    let last_value = series[series.len() - 1].1;
+    let steps = (series.len() - 1) as f64;
+
+    let compound_growth_pct = if first_value > 0.0 && last_value > 0.0 {
+        Some(((last_value / first_value).powf(1.0 / steps) - 1.0) * 100.0)
+    } else {
+        None
+    };
+
+    Ok(GrowthRateResult {
+        column: column.to_string(),
+        first_value,
+        last_value,
+        total_change: last_value - first_value,
+        total_change_pct: pct_change(first_value, last_value),
+        average_change_pct: mean(&change_pcts),
+        compound_growth_pct,
+        points: last_points(points),
+    })
+}
+
+fn moving_average(
+    data: &[IndexMap<String, DataType>],
+    column: &str,
+    order_by: &Option<String>,
+    window: usize,
+) -> Result<MovingAverageResult> {
+    if window == 0 {
+        return Err(anyhow!("Moving average window must be greater than zero"));
+    }
+
+    let series = ordered_series(data, column, order_by)?;
+
+    let values = series.iter().map(|(_, v)| *v).collect::<Vec<f64>>();
+
+    let points = series
+        .iter()
+        .enumerate()
+        .map(|(i, (label, value))| SeriesPoint {
+            label: label.clone(),
+            value: *value,
+            change: None,
+            change_pct: None,
+            moving_average: if i + 1 >= window {
+                mean(&values[i + 1 - window..=i])
+            } else {
+                None
+            },
+        })
+        .collect();
+
+    Ok(MovingAverageResult {
+        column: column.to_string(),
+        window,
+        points: last_points(points),
+    })
+}
+
+fn outliers(
+    data: &[IndexMap<String, DataType>],
+    column: &str,
+    label_column: &Option<String>,
+    threshold: f64,
+) -> Result<OutliersResult> {
+    let series = labeled_values(data, column, label_column)?;
+    let values = series.iter().map(|(_, v)| *v).collect::<Vec<f64>>();
+
+    let (mean, std_dev) = match (mean(&values), std_dev(&values)) {
+        (Some(mean), Some(std_dev)) => (mean, std_dev),
+        _ => return Err(anyhow!("Outliers need at least two values in {}", column)),
+    };
+
+    let mut outliers = if std_dev == 0.0 {
+        vec![]
+    } else {
+        series
+            .into_iter()
+            .map(|(label, value)| Outlier {
+                label,
+                value,
+                z_score: (value - mean) / std_dev,
+            })
+            .filter(|outlier| outlier.z_score.abs() >= threshold)
+            .collect::<Vec<Outlier>>()
+    };
+
+    outliers.sort_by(|a, b| {
+        b.z_score
+            .abs()
+            .partial_cmp(&a.z_score.abs())
+            .unwrap_or(Ordering::Equal)
+    });
+    outliers.truncate(MAX_SERIES_POINTS);
+
+    Ok(OutliersResult {
+        column: column.to_string(),
+        mean,
+        std_dev,
+        threshold,
+        outliers,
+    })
+}
+
+fn correlation(
+    data: &[IndexMap<String, DataType>],
+    x_column: &str,
+    y_column: &str,
+) -> Result<CorrelationResult> {
+    let (xs, ys, _) = paired_values(data, x_column, y_column)?;
+
+    let pearson_r = match pearson(&xs, &ys) {
+        Some(r) => r,
+        None => {
+            return Err(anyhow!(
+                "Correlation is undefined when {} or {} is constant",
+                x_column,
+                y_column
+            ))
+        }
+    };
+
+    let strength = match pearson_r.abs() {
+        r if r >= 0.7 => "strong",
+        r if r >= 0.4 => "moderate",
+        r if r >= 0.2 => "weak",
+        _ => "none",
+    };
+
+    Ok(CorrelationResult {
+        x_column: x_column.to_string(),
+        y_column: y_column.to_string(),
+        n: xs.len(),
+        pearson_r,
+        r_squared: pearson_r * pearson_r,
+        strength: strength.to_string(),
+    })
+}
+
+fn linear_regression(
+    data: &[IndexMap<String, DataType>],
+    x_column: &str,
+    y_column: &str,
+) -> Result<LinearRegressionResult> {
+    let (xs, ys, x_is_date) = paired_values(data, x_column, y_column)?;
+    let n = xs.len() as f64;
+
+    let (x_mean, y_mean) = (mean(&xs).unwrap_or(0.0), mean(&ys).unwrap_or(0.0));
+
+    let sxx: f64 = xs.iter().map(|x| (x - x_mean).powi(2)).sum();
+    let sxy: f64 = xs
+        .iter()
+        .zip(&ys)
+        .map(|(x, y)| (x - x_mean) * (y - y_mean))
+        .sum();
+    let syy: f64 = ys.iter().map(|y| (y - y_mean).powi(2)).sum();
+
+    if sxx == 0.0 {
+        return Err(anyhow!(
+            "Regression is undefined when {} is constant",
+            x_column
+        ));
+    }
+
+    let slope = sxy / sxx;
+    let intercept = y_mean - slope * x_mean;
+
+    let ss_res: f64 = xs
+        .iter()
+        .zip(&ys)
+        .map(|(x, y)| (y - (intercept + slope * x)).powi(2))
+        .sum();
+
+    let r_squared = if syy == 0.0 { 1.0 } else { 1.0 - ss_res / syy };
+
+    // The t-test on the slope needs at least one degree of freedom left after fitting.
+    let (t_statistic, p_value) = if xs.len() > 2 {
+        let degrees_of_freedom = n - 2.0;
+        let standard_error = (ss_res / degrees_of_freedom / sxx).sqrt();
+
+        // A perfect fit leaves no error. A flat line is then no evidence of a trend, and any other
+        // slope is as significant as it gets.
+        if standard_error == 0.0 && slope == 0.0 {
+            (Some(0.0), Some(1.0))
+        } else if standard_error == 0.0 {
+            (None, Some(0.0))
+        } else {
+            let t = slope / standard_error;
+            (Some(t), Some(student_t_two_tailed_p(t, degrees_of_freedom)))
+        }
+    } else {
+        (None, None)
+    };
+
+    Ok(LinearRegressionResult {
+        x_column: x_column.to_string(),
+        y_column: y_column.to_string(),
+        x_unit: if x_is_date { "days" } else { "value" }.to_string(),
+        n: xs.len(),
+        slope,
+        intercept,
+        r_squared,
+        t_statistic,
+        p_value,
+        significant: p_value.map(|p| p < SIGNIFICANCE_LEVEL),
+    })
+}
+
+fn period_over_period(
+    data: &[IndexMap<String, DataType>],
+    date_column: &str,
+    value_column: &str,
+    period: Period,
+) -> Result<PeriodOverPeriodResult> {
+    let mut buckets: BTreeMap<NaiveDate, f64> = BTreeMap::new();
+
+    for row in data {
+        let date = match row.get(date_column).and_then(date_value) {
+            Some(date) => date,
+            None => continue,
+        };
+
+        let value = match row.get(value_column).and_then(numeric_value) {
+            Some(value) => value,
+            None => continue,
+        };
+
+        *buckets.entry(period_start(date, period)).or_insert(0.0) += value;
+    }
+
+    if buckets.is_empty() {
+        return Err(anyhow!(
+            "No rows have both a date in {} and a number in {}",
+            date_column,
+            value_column
+        ));
+    }
+
+    let mut periods = Vec::with_capacity(buckets.len());
+
+    for (start, value) in &buckets {
+        // Changes compare against the calendar period before, which has no value when no rows
+        // fall in it.
+        let previous = previous_period_start(*start, period).and_then(|p| buckets.get(&p));
+
+        let year_over_year_change_pct = match period {
+            Period::Month | Period::Quarter => start
+                .with_year(start.year() - 1)
+                .and_then(|last_year| buckets.get(&last_year))
+                .and_then(|last_year_value| pct_change(*last_year_value, *value)),
+            _ => None,
+        };
+
+        periods.push(PeriodValue {
+            period_start: start.to_string(),
+            value: *value,
+            change: previous.map(|p| value - p),
+            change_pct: previous.and_then(|p| pct_change(*p, *value)),
+            year_over_year_change_pct,
+        });
+    }
+
+    if periods.len() > MAX_SERIES_POINTS {
+        periods.drain(..periods.len() - MAX_SERIES_POINTS);
+    }
+
+    Ok(PeriodOverPeriodResult {
+        date_column: date_column.to_string(),
+        value_column: value_column.to_string(),
+        period,
+        periods,
+    })
+}
+
+/// Values of a column in the order of `order_by`, or in row order if no column is given.
+fn ordered_series(
+    data: &[IndexMap<String, DataType>],
+    column: &str,
+    order_by: &Option<String>,
+) -> Result<Vec<(String, f64)>> {
+    let mut rows = data
+        .iter()
+        .filter_map(|row| row.get(column).and_then(numeric_value).map(|v| (row, v)))
+        .collect::<Vec<(&IndexMap<String, DataType>, f64)>>();
+
+    if rows.is_empty() {
+        return Err(anyhow!("Column {} has no numeric values", column));
+    }
+
+    if let Some(order_by) = order_by {
+        rows.sort_by(|(a, _), (b, _)| compare_values(a.get(order_by), b.get(order_by)));
+    }
+
+    Ok(rows
+        .into_iter()
+        .enumerate()
+        .map(|(i, (row, value))| (row_label(row, order_by, i), value))
+        .collect())
+}
+
+fn labeled_values(
+    data: &[IndexMap<String, DataType>],
+    column: &str,
+    label_column: &Option<String>,
+) -> Result<Vec<(String, f64)>> {
+    let values = data
+        .iter()
+        .enumerate()
+        .filter_map(|(i, row)| {
+            row.get(column)
+                .and_then(numeric_value)
+                .map(|v| (row_label(row, label_column, i), v))
+        })
+        .collect::<Vec<(String, f64)>>();
+
+    if values.is_empty() {
+        return Err(anyhow!("Column {} has no numeric values", column));
+    }
+
+    Ok(values)
+}
+
+/// Pairs of x and y values from rows where both are present. Dates on the x axis are
+/// converted to days since the first date.
+fn paired_values(
+    data: &[IndexMap<String, DataType>],
+    x_column: &str,
+    y_column: &str,
+) -> Result<(Vec<f64>, Vec<f64>, bool)> {
+    let x_is_date = data
+        .iter()
+        .filter_map(|row| row.get(x_column))
+        .find(|value| !is_null(value))
+        .map(|value| numeric_value(value).is_none() && date_value(value).is_some())
+        .unwrap_or(false);
+
+    let mut xs = Vec::new();
+    let mut ys = Vec::new();
+
+    for row in data {
+        let x = match row.get(x_column) {
+            Some(value) if x_is_date => date_value(value).map(|d| days_since_epoch(d) as f64),
+            Some(value) => numeric_value(value),
+            None => None,
+        };
+
+        let y = row.get(y_column).and_then(numeric_value);
+
+        if let (Some(x), Some(y)) = (x, y) {
+            xs.push(x);
+            ys.push(y);
+        }
+    }
+
+    if xs.len() < 2 {
+        return Err(anyhow!(
+            "Need at least two rows with values in both {} and {}",
+            x_column,
+            y_column
+        ));
+    }
+
+    Ok((xs, ys, x_is_date))
+}
+
+fn row_label(
+    row: &IndexMap<String, DataType>,
+    label_column: &Option<String>,
+    index: usize,
+) -> String {
+    match label_column.as_ref().and_then(|column| row.get(column)) {
+        Some(value) => display_value(value),
+        None => format!("row {}", index + 1),
+    }
+}
+
+pub(crate) fn numeric_value(value: &DataType) -> Option<f64> {
+    match value {
+        DataType::Int8(Some(v)) => Some(*v as f64),
+        DataType::Int4(Some(v)) => Some(*v as f64),
+        DataType::Int2(Some(v)) => Some(*v as f64),
+        DataType::Oid(Some(v)) => Some(*v as f64),
+        DataType::Float4(Some(v)) => Some(*v as f64),
+        DataType::Float8(Some(v)) => Some(*v),
+        DataType::Decimal(Some(v)) => f64::from_str(&v.to_string()).ok(),
+        // Some warehouses return numerics as strings.
+        DataType::Text(Some(v)) | DataType::Char(Some(v)) | DataType::Unknown(Some(v)) => {
+            f64::from_str(v.trim()).ok()
+        }
+        _ => None,
+    }
+    .filter(|v| v.is_finite())
+}
+
+fn date_value(value: &DataType) -> Option<NaiveDate> {
+    match value {
+        DataType::Date(Some(v)) => Some(*v),
+        DataType::Timestamp(Some(v)) => Some(v.date()),
+        DataType::Timestamptz(Some(v)) => Some(v.date_naive()),
+        DataType::Text(Some(v)) | DataType::Char(Some(v)) | DataType::Unknown(Some(v)) => {
+            parse_date(v.trim())
+        }
+        _ => None,
+    }
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Some(date);
+    }
+
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(value) {
+        return Some(date_time.date_naive());
+    }
+
+    for format in [
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S%.f %Z",
+    ] {
+        if let Ok(date_time) = NaiveDateTime::parse_from_str(value, format) {
+            return Some(date_time.date());
+        }
+    }
+
+    None
+}
+
+fn display_value(value: &DataType) -> String {
+    match value {
+        DataType::Text(Some(v)) | DataType::Char(Some(v)) | DataType::Unknown(Some(v)) => v.clone(),
+        DataType::Date(Some(v)) => v.to_string(),
+        DataType::Timestamp(Some(v)) => v.to_string(),
+        DataType::Timestamptz(Some(v)) => v.to_string(),
+        DataType::Bool(Some(v)) => v.to_string(),
+        DataType::Uuid(Some(v)) => v.to_string(),
+        other => match numeric_value(other) {
+            Some(v) => v.to_string(),
+            None => "null".to_string(),
+        },
+    }
+}
+
+fn is_null(value: &DataType) -> bool {
+    matches!(serde_json::to_value(value), Ok(Value::Null))
+}
+
+fn compare_values(a: Option<&DataType>, b: Option<&DataType>) -> Ordering {
+    let (a, b) = match (a, b) {
+        (Some(a), Some(b)) => (a, b),
+        (Some(_), None) => return Ordering::Less,
+        (None, Some(_)) => return Ordering::Greater,
+        (None, None) => return Ordering::Equal,
+    };
+
+    if let (Some(a), Some(b)) = (date_value(a), date_value(b)) {
+        return a.cmp(&b);
+    }
+
+    if let (Some(a), Some(b)) = (numeric_value(a), numeric_value(b)) {
+        return a.partial_cmp(&b).unwrap_or(Ordering::Equal);
+    }
+
+    display_value(a).cmp(&display_value(b))
+}
+
+fn period_start(date: NaiveDate, period: Period) -> NaiveDate {
+    match period {
+        Period::Day => date,
+        Period::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+        Period::Month => NaiveDate::from_ymd_opt(date.year(), date.month(), 1).unwrap_or(date),
+        Period::Quarter => {
+            NaiveDate::from_ymd_opt(date.year(), (date.month() - 1) / 3 * 3 + 1, 1).unwrap_or(date)
+        }
+        Period::Year => NaiveDate::from_ymd_opt(date.year(), 1, 1).unwrap_or(date),
+    }
+}
+
+fn previous_period_start(start: NaiveDate, period: Period) -> Option<NaiveDate> {
+    match period {
+        Period::Day => start.checked_sub_days(Days::new(1)),
+        Period::Week => start.checked_sub_days(Days::new(7)),
+        Period::Month => start.checked_sub_months(Months::new(1)),
+        Period::Quarter => start.checked_sub_months(Months::new(3)),
+        Period::Year => start.checked_sub_months(Months::new(12)),
+    }
+}
+
+fn days_since_epoch(date: NaiveDate) -> i64 {
+    (date - NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()).num_days()
+}
+
+fn last_points(mut points: Vec<SeriesPoint>) -> Vec<SeriesPoint> {
+    if points.len() > MAX_SERIES_POINTS {
+        points.drain(..points.len() - MAX_SERIES_POINTS);
+    }
+
+    points
+}
+
+fn pct_change(from: f64, to: f64) -> Option<f64> {
+    if from == 0.0 {
+        None
+    } else {
+        Some((to - from) / from.abs() * 100.0)
+    }
+}
+
+pub(crate) fn mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    }
+}
+
+// Sample standard deviation.
+pub(crate) fn std_dev(values: &[f64]) -> Option<f64> {
+    if values.len() < 2 {
+        return None;
+    }
+
+    let mean = mean(values)?;
+    let variance =
+        values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64;
+
+    Some(variance.sqrt())
+}
+
+fn pearson(xs: &[f64], ys: &[f64]) -> Option<f64> {
+    let (x_mean, y_mean) = (mean(xs)?, mean(ys)?);
+
+    let mut sxy = 0.0;
+    let mut sxx = 0.0;
+    let mut syy = 0.0;
+
+    for (x, y) in xs.iter().zip(ys) {
+        sxy += (x - x_mean) * (y - y_mean);
+        sxx += (x - x_mean).powi(2);
+        syy += (y - y_mean).powi(2);
+    }
+
+    if sxx == 0.0 || syy == 0.0 {
+        None
+    } else {
+        Some(sxy / (sxx * syy).sqrt())
+    }
+}
+
+/// Two-tailed p-value of Student's t distribution, through the regularized incomplete beta
+/// function: p = I(df / (df + t²); df / 2, 1 / 2).
+fn student_t_two_tailed_p(t: f64, degrees_of_freedom: f64) -> f64 {
+    let x = degrees_of_freedom / (degrees_of_freedom + t * t);
+    regularized_incomplete_beta(x, degrees_of_freedom / 2.0, 0.5).clamp(0.0, 1.0)
+}
+
+fn regularized_incomplete_beta(x: f64, a: f64, b: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+
+    if x >= 1.0 {
+        return 1.0;
+    }
+
+    let ln_front = ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln();
+    let front = ln_front.exp();
+
+    // The continued fraction converges quickly only on one side of the mean.
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(x, a, b) / a
+    } else {
+        1.0 - front * beta_continued_fraction(1.0 - x, b, a) / b
+    }
+}
+
+// Lentz's method, as in Numerical Recipes.
+fn beta_continued_fraction(x: f64, a: f64, b: f64) -> f64 {
+    const MAX_ITERATIONS: usize = 200;
+    const EPSILON: f64 = 3e-14;
+    const TINY: f64 = 1e-300;
+
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+
+    if d.abs() < TINY {
+        d = TINY;
+    }
+
+    d = 1.0 / d;
+    let mut h = d;
+
+    for m in 1..=MAX_ITERATIONS {
+        let m = m as f64;
+        let m2 = 2.0 * m;
+
+        let aa = m * (b - m) * x / ((a + m2 - 1.0) * (a + m2));
+        d = 1.0 + aa * d;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = 1.0 + aa / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        h *= d * c;
+
+        let aa = -(a + m) * (a + b + m) * x / ((a + m2) * (a + m2 + 1.0));
+        d = 1.0 + aa * d;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = 1.0 + aa / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+
+        let delta = d * c;
+        h *= delta;
+
+        if (delta - 1.0).abs() < EPSILON {
+            break;
+        }
+    }
+
+    h
+}
+
+// Lanczos approximation.
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.18009172947146,
+        -86.50532032941677,
+        24.01409824083091,
+        -1.231739572450155,
+        0.1208650973866179e-2,
+        -0.5395239384953e-5,
+    ];
+
+    let mut y = x;
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+    let mut series = 1.000000000190015;
+
+    for coefficient in COEFFICIENTS {
+        y += 1.0;
+        series += coefficient / y;
+    }
+
+    -tmp + (2.5066282746310005 * series / x).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rows(values: Value) -> Vec<IndexMap<String, DataType>> {
+        rows_from_json(values.as_array().unwrap())
+    }
+
+    #[test]
+    fn test_growth_rate_orders_by_column() {
+        let data = rows(json!([
+            {"month": "2024-03-01", "revenue": 121},
+            {"month": "2024-01-01", "revenue": 100},
+            {"month": "2024-02-01", "revenue": 110},
+        ]));
+
+        let result = growth_rate(&data, "revenue", &Some("month".to_string())).unwrap();
+
+        assert_eq!(result.first_value, 100.0);
+        assert_eq!(result.last_value, 121.0);
+        assert!((result.total_change_pct.unwrap() - 21.0).abs() < 1e-9);
+        assert!((result.compound_growth_pct.unwrap() - 10.0).abs() < 1e-9);
+        assert_eq!(result.points[1].label, "2024-02-01");
+    }
+
+    #[test]
+    fn test_outliers_uses_z_score() {
+        let data = rows(json!([
+            {"region": "a", "sales": 10},
+            {"region": "b", "sales": 11},
+            {"region": "c", "sales": 9},
+            {"region": "d", "sales": 10},
+            {"region": "e", "sales": 10},
+            {"region": "f", "sales": 50},
+        ]));
+
+        let result = outliers(&data, "sales", &Some("region".to_string()), 2.0).unwrap();
+
+        assert_eq!(result.outliers.len(), 1);
+        assert_eq!(result.outliers[0].label, "f");
+    }
+
+    #[test]
+    fn test_linear_regression_on_dates() {
+        let data = rows(json!([
+            {"day": "2024-01-01", "users": 10.0},
+            {"day": "2024-01-02", "users": 12.1},
+            {"day": "2024-01-03", "users": 13.9},
+            {"day": "2024-01-04", "users": 16.0},
+            {"day": "2024-01-05", "users": 18.1},
+        ]));
+
+        let result = linear_regression(&data, "day", "users").unwrap();
+
+        assert_eq!(result.x_unit, "days");
+        assert!((result.slope - 2.0).abs() < 0.05);
+        assert!(result.significant.unwrap());
+    }
+
+    #[test]
+    fn test_linear_regression_on_constant_values() {
+        let data = rows(json!([
+            {"day": "2024-01-01", "users": 10},
+            {"day": "2024-01-02", "users": 10},
+            {"day": "2024-01-03", "users": 10},
+        ]));
+
+        let result = linear_regression(&data, "day", "users").unwrap();
+
+        assert_eq!(result.slope, 0.0);
+        assert_eq!(result.p_value, Some(1.0));
+        assert_eq!(result.significant, Some(false));
+    }
+
+    #[test]
+    fn test_student_t_p_value() {
+        // t = 2.228 is the 0.05 two-tailed critical value for 10 degrees of freedom.
+        assert!((student_t_two_tailed_p(2.228, 10.0) - 0.05).abs() < 1e-3);
+        assert!((student_t_two_tailed_p(0.0, 10.0) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_period_over_period_with_year_over_year() {
+        let data = rows(json!([
+            {"date": "2023-01-15", "orders": 10},
+            {"date": "2023-01-20", "orders": 10},
+            {"date": "2024-01-03", "orders": 30},
+            {"date": "2024-02-03", "orders": 15},
+        ]));
+
+        let result = period_over_period(&data, "date", "orders", Period::Month).unwrap();
+
+        assert_eq!(result.periods.len(), 3);
+        assert_eq!(result.periods[0].value, 20.0);
+        // December 2023 has no rows, so January 2024 has no month-over-month change.
+        assert_eq!(result.periods[1].change, None);
+        assert_eq!(result.periods[1].change_pct, None);
+        assert_eq!(result.periods[1].year_over_year_change_pct, Some(50.0));
+        assert_eq!(result.periods[2].change_pct, Some(-50.0));
+    }
+}
@@ -10,7 +10,7 @@ use uuid::Uuid;
 use crate::database::enums::StoredValuesStatus;
 use crate::database::{lib::get_pg_pool, schema::dataset_columns};
 use crate::utils::clients::ai::embedding_router::embedding_router;
-use diesel::sql_types::{Text, Uuid as SqlUuid, Array, Float4, Timestamptz, Integer};
+use diesel::sql_types::{Bool, Text, Uuid as SqlUuid, Array, Float4, Timestamptz, Integer};
 
 use super::query_engine::{data_types::DataType, query_engine::query_engine};
 
@@ -235,6 +235,57 @@ pub async fn search_stored_values(
     Ok(results.into_iter().map(|r| (r.value, r.column_name, r.column_id)).collect())
 }
 
+#[derive(Debug, QueryableByName)]
+struct SchemaExists {
+    #[diesel(sql_type = Bool)]
+    exists: bool,
+}
+
+/// Removes a dataset's stored values. Organizations that never stored any values have no schema,
+/// so there is nothing to remove.
+pub async fn delete_stored_values(organization_id: &Uuid, dataset_id: &Uuid) -> Result<()> {
+    let pool = get_pg_pool();
+    let mut conn = pool.get().await?;
+
+    let schema_name = organization_id.to_string().replace("-", "_");
+
+    let schema_exists: SchemaExists = diesel::sql_query(
+        "SELECT EXISTS (SELECT 1 FROM information_schema.schemata WHERE schema_name = $1) AS exists",
+    )
+    .bind::<Text, _>(format!("values_{}", schema_name))
+    .get_result(&mut conn)
+    .await?;
+
+    if !schema_exists.exists {
+        return Ok(());
+    }
+
+    let query = format!(
+        "DELETE FROM values_{}.values_v1 WHERE dataset_id = $1::uuid",
+        schema_name
+    );
+
+    diesel::sql_query(query)
+        .bind::<SqlUuid, _>(dataset_id)
+        .execute(&mut conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Drops the organization's stored values schema along with every value in it.
+pub async fn drop_stored_values_schema(organization_id: &Uuid) -> Result<()> {
+    let pool = get_pg_pool();
+    let mut conn = pool.get().await?;
+
+    let schema_name = organization_id.to_string().replace("-", "_");
+    let drop_schema_sql = format!("DROP SCHEMA IF EXISTS values_{} CASCADE", schema_name);
+
+    diesel::sql_query(drop_schema_sql).execute(&mut conn).await?;
+
+    Ok(())
+}
+
 pub struct StoredValueColumn {
     pub organization_id: Uuid,
     pub dataset_id: Uuid,
@@ -0,0 +1,569 @@
+use std::cmp::Reverse;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use diesel::{update, ExpressionMethods, JoinOnDsl, NullableExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        enums::{AssetPermissionRole, AssetType, IdentityType},
+        lib::get_pg_pool,
+        schema::{
+            asset_permissions, collections, dashboards, data_sources, datasets, messages, terms,
+            threads,
+        },
+    },
+    utils::clients::supabase_vault::read_secret,
+};
+
+use super::retention::{purge_at, purge_legacy_deletes, trash_retention_days};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum TrashAssetType {
+    Dashboard,
+    Thread,
+    Collection,
+    Dataset,
+    Term,
+    DataSource,
+}
+
+impl TrashAssetType {
+    pub const ALL: [TrashAssetType; 6] = [
+        TrashAssetType::Dashboard,
+        TrashAssetType::Thread,
+        TrashAssetType::Collection,
+        TrashAssetType::Dataset,
+        TrashAssetType::Term,
+        TrashAssetType::DataSource,
+    ];
+
+    /// The shareable asset type, for assets users hold permissions on. Datasets, terms and data
+    /// sources belong to the organization and only admins manage them.
+    pub fn shared_asset_type(self) -> Option<AssetType> {
+        match self {
+            TrashAssetType::Dashboard => Some(AssetType::Dashboard),
+            TrashAssetType::Thread => Some(AssetType::Thread),
+            TrashAssetType::Collection => Some(AssetType::Collection),
+            TrashAssetType::Dataset | TrashAssetType::Term | TrashAssetType::DataSource => None,
+        }
+    }
+
+    pub(super) fn label(self) -> &'static str {
+        match self {
+            TrashAssetType::Dashboard => "dashboards",
+            TrashAssetType::Thread => "threads",
+            TrashAssetType::Collection => "collections",
+            TrashAssetType::Dataset => "datasets",
+            TrashAssetType::Term => "terms",
+            TrashAssetType::DataSource => "data sources",
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TrashItem {
+    pub id: Uuid,
+    pub asset_type: TrashAssetType,
+    pub name: String,
+    pub deleted_at: DateTime<Utc>,
+    /// When the retention job permanently deletes the asset. Assets deleted before the trash
+    /// existed are kept until someone purges them, unless legacy deletes are opted in.
+    pub purge_at: Option<DateTime<Utc>>,
+}
+
+/// The deleted assets of the organization a user can see in the trash: the dashboards, threads and
+/// collections they could edit, or everything for admins.
+pub async fn list_trash_items(
+    user_id: &Uuid,
+    organization_id: &Uuid,
+    is_admin: bool,
+) -> Result<Vec<TrashItem>> {
+    let mut items = Vec::new();
+
+    for asset_type in TrashAssetType::ALL {
+        let ids = match (asset_type.shared_asset_type(), is_admin) {
+            (_, true) => None,
+            (Some(shared_asset_type), false) => {
+                Some(editable_asset_ids(user_id, shared_asset_type, None).await?)
+            }
+            (None, false) => continue,
+        };
+
+        items.extend(load_deleted_assets(asset_type, organization_id, ids).await?);
+    }
+
+    items.sort_by_key(|item| Reverse(item.deleted_at));
+
+    Ok(items)
+}
+
+/// A single deleted asset, if the user can see it in the trash.
+pub async fn get_trash_item(
+    user_id: &Uuid,
+    organization_id: &Uuid,
+    is_admin: bool,
+    asset_type: TrashAssetType,
+    id: &Uuid,
+) -> Result<Option<TrashItem>> {
+    let ids = match (asset_type.shared_asset_type(), is_admin) {
+        (_, true) => vec![*id],
+        (Some(shared_asset_type), false) => {
+            editable_asset_ids(user_id, shared_asset_type, Some(id)).await?
+        }
+        (None, false) => return Ok(None),
+    };
+
+    if ids.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(load_deleted_assets(asset_type, organization_id, Some(ids))
+        .await?
+        .into_iter()
+        .next())
+}
+
+/// Takes an asset out of the trash. Deleting never touched its permissions or collection
+/// membership, so those come back with it.
+pub async fn restore_trash_item(
+    user_id: &Uuid,
+    asset_type: TrashAssetType,
+    id: &Uuid,
+) -> Result<()> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    match asset_type {
+        TrashAssetType::DataSource => {
+            let secret_id = match data_sources::table
+                .select(data_sources::secret_id)
+                .filter(data_sources::id.eq(id))
+                .first::<Uuid>(&mut conn)
+                .await
+            {
+                Ok(secret_id) => secret_id,
+                Err(e) => return Err(anyhow!("Error getting data source: {}", e)),
+            };
+
+            let has_credentials = read_secret(&secret_id).await.is_ok();
+
+            if let Some(conflict) = restore_conflict(asset_type, has_credentials, false) {
+                return Err(anyhow!(conflict));
+            }
+        }
+        TrashAssetType::Dataset => {
+            let data_source_deleted_at = match datasets::table
+                .inner_join(data_sources::table.on(datasets::data_source_id.eq(data_sources::id)))
+                .select(data_sources::deleted_at)
+                .filter(datasets::id.eq(id))
+                .first::<Option<DateTime<Utc>>>(&mut conn)
+                .await
+            {
+                Ok(deleted_at) => deleted_at,
+                Err(e) => return Err(anyhow!("Error getting dataset data source: {}", e)),
+            };
+
+            if let Some(conflict) =
+                restore_conflict(asset_type, true, data_source_deleted_at.is_some())
+            {
+                return Err(anyhow!(conflict));
+            }
+        }
+        _ => (),
+    };
+
+    let restored = match asset_type {
+        TrashAssetType::Dashboard => {
+            update(dashboards::table)
+                .filter(dashboards::id.eq(id))
+                .filter(dashboards::deleted_at.is_not_null())
+                .set((
+                    dashboards::deleted_at.eq(None::<DateTime<Utc>>),
+                    dashboards::trashed_at.eq(None::<DateTime<Utc>>),
+                    dashboards::updated_at.eq(Utc::now()),
+                    dashboards::updated_by.eq(user_id),
+                ))
+                .execute(&mut conn)
+                .await
+        }
+        TrashAssetType::Thread => {
+            update(threads::table)
+                .filter(threads::id.eq(id))
+                .filter(threads::deleted_at.is_not_null())
+                .set((
+                    threads::deleted_at.eq(None::<DateTime<Utc>>),
+                    threads::trashed_at.eq(None::<DateTime<Utc>>),
+                    threads::updated_at.eq(Utc::now()),
+                    threads::updated_by.eq(user_id),
+                ))
+                .execute(&mut conn)
+                .await
+        }
+        TrashAssetType::Collection => {
+            update(collections::table)
+                .filter(collections::id.eq(id))
+                .filter(collections::deleted_at.is_not_null())
+                .set((
+                    collections::deleted_at.eq(None::<DateTime<Utc>>),
+                    collections::trashed_at.eq(None::<DateTime<Utc>>),
+                    collections::updated_at.eq(Utc::now()),
+                    collections::updated_by.eq(user_id),
+                ))
+                .execute(&mut conn)
+                .await
+        }
+        TrashAssetType::Dataset => {
+            update(datasets::table)
+                .filter(datasets::id.eq(id))
+                .filter(datasets::deleted_at.is_not_null())
+                .set((
+                    datasets::deleted_at.eq(None::<DateTime<Utc>>),
+                    datasets::trashed_at.eq(None::<DateTime<Utc>>),
+                    datasets::updated_at.eq(Utc::now()),
+                    datasets::updated_by.eq(user_id),
+                ))
+                .execute(&mut conn)
+                .await
+        }
+        TrashAssetType::Term => {
+            update(terms::table)
+                .filter(terms::id.eq(id))
+                .filter(terms::deleted_at.is_not_null())
+                .set((
+                    terms::deleted_at.eq(None::<DateTime<Utc>>),
+                    terms::trashed_at.eq(None::<DateTime<Utc>>),
+                    terms::updated_at.eq(Utc::now()),
+                    terms::updated_by.eq(user_id),
+                ))
+                .execute(&mut conn)
+                .await
+        }
+        TrashAssetType::DataSource => {
+            update(data_sources::table)
+                .filter(data_sources::id.eq(id))
+                .filter(data_sources::deleted_at.is_not_null())
+                .set((
+                    data_sources::deleted_at.eq(None::<DateTime<Utc>>),
+                    data_sources::trashed_at.eq(None::<DateTime<Utc>>),
+                    data_sources::updated_at.eq(Utc::now()),
+                    data_sources::updated_by.eq(user_id),
+                ))
+                .execute(&mut conn)
+                .await
+        }
+    };
+
+    match restored {
+        Ok(_) => (),
+        Err(e) => return Err(anyhow!("Error restoring {}: {}", asset_type.label(), e)),
+    };
+
+    if asset_type.shared_asset_type().is_some() {
+        let query = diesel::sql_query(
+            "UPDATE asset_search
+            SET deleted_at = NULL
+            WHERE asset_id = $1",
+        )
+        .bind::<diesel::sql_types::Uuid, _>(id);
+
+        if let Err(e) = query.execute(&mut conn).await {
+            return Err(anyhow!("Error restoring asset search: {}", e));
+        }
+    }
+
+    Ok(())
+}
+
+/// Why an asset can't come out of the trash, if it can't.
+fn restore_conflict(
+    asset_type: TrashAssetType,
+    has_credentials: bool,
+    data_source_deleted: bool,
+) -> Option<&'static str> {
+    match asset_type {
+        // Data sources deleted before the trash existed had their credentials removed right away.
+        TrashAssetType::DataSource if !has_credentials => {
+            Some("The data source's credentials no longer exist, add it again instead")
+        }
+        TrashAssetType::Dataset if data_source_deleted => {
+            Some("Restore the dataset's data source first")
+        }
+        _ => None,
+    }
+}
+
+async fn editable_asset_ids(
+    user_id: &Uuid,
+    asset_type: AssetType,
+    asset_id: Option<&Uuid>,
+) -> Result<Vec<Uuid>> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    let mut query = asset_permissions::table
+        .select(asset_permissions::asset_id)
+        .filter(asset_permissions::identity_id.eq(user_id))
+        .filter(asset_permissions::identity_type.eq(IdentityType::User))
+        .filter(asset_permissions::asset_type.eq(asset_type))
+        .filter(asset_permissions::role.ne(AssetPermissionRole::Viewer))
+        .filter(asset_permissions::deleted_at.is_null())
+        .into_boxed();
+
+    if let Some(asset_id) = asset_id {
+        query = query.filter(asset_permissions::asset_id.eq(asset_id));
+    }
+
+    match query.load::<Uuid>(&mut conn).await {
+        Ok(ids) => Ok(ids),
+        Err(e) => Err(anyhow!("Error getting asset permissions: {}", e)),
+    }
+}
+
+/// Deleted assets of one type in the organization, optionally limited to `ids`.
+async fn load_deleted_assets(
+    asset_type: TrashAssetType,
+    organization_id: &Uuid,
+    ids: Option<Vec<Uuid>>,
+) -> Result<Vec<TrashItem>> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    let records = match asset_type {
+        TrashAssetType::Dashboard => {
+            let mut query = dashboards::table
+                .select((
+                    dashboards::id,
+                    dashboards::name.nullable(),
+                    dashboards::deleted_at,
+                    dashboards::trashed_at,
+                ))
+                .filter(dashboards::organization_id.eq(organization_id))
+                .filter(dashboards::deleted_at.is_not_null())
+                .into_boxed();
+
+            if let Some(ids) = &ids {
+                query = query.filter(dashboards::id.eq_any(ids));
+            }
+
+            query
+                .load::<(
+                    Uuid,
+                    Option<String>,
+                    Option<DateTime<Utc>>,
+                    Option<DateTime<Utc>>,
+                )>(&mut conn)
+                .await
+        }
+        TrashAssetType::Thread => {
+            // Threads are named after the title of their current message.
+            let mut query = threads::table
+                .left_join(
+                    messages::table.on(threads::state_message_id.eq(messages::id.nullable())),
+                )
+                .select((
+                    threads::id,
+                    messages::title.nullable(),
+                    threads::deleted_at,
+                    threads::trashed_at,
+                ))
+                .filter(threads::organization_id.eq(organization_id))
+                .filter(threads::deleted_at.is_not_null())
+                .into_boxed();
+
+            if let Some(ids) = &ids {
+                query = query.filter(threads::id.eq_any(ids));
+            }
+
+            query
+                .load::<(
+                    Uuid,
+                    Option<String>,
+                    Option<DateTime<Utc>>,
+                    Option<DateTime<Utc>>,
+                )>(&mut conn)
+                .await
+        }
+        TrashAssetType::Collection => {
+            let mut query = collections::table
+                .select((
+                    collections::id,
+                    collections::name.nullable(),
+                    collections::deleted_at,
+                    collections::trashed_at,
+                ))
+                .filter(collections::organization_id.eq(organization_id))
+                .filter(collections::deleted_at.is_not_null())
+                .into_boxed();
+
+            if let Some(ids) = &ids {
+                query = query.filter(collections::id.eq_any(ids));
+            }
+
+            query
+                .load::<(
+                    Uuid,
+                    Option<String>,
+                    Option<DateTime<Utc>>,
+                    Option<DateTime<Utc>>,
+                )>(&mut conn)
+                .await
+        }
+        TrashAssetType::Dataset => {
+            let mut query = datasets::table
+                .select((
+                    datasets::id,
+                    datasets::name.nullable(),
+                    datasets::deleted_at,
+                    datasets::trashed_at,
+                ))
+                .filter(datasets::organization_id.eq(organization_id))
+                .filter(datasets::deleted_at.is_not_null())
+                .into_boxed();
+
+            if let Some(ids) = &ids {
+                query = query.filter(datasets::id.eq_any(ids));
+            }
+
+            query
+                .load::<(
+                    Uuid,
+                    Option<String>,
+                    Option<DateTime<Utc>>,
+                    Option<DateTime<Utc>>,
+                )>(&mut conn)
+                .await
+        }
+        TrashAssetType::Term => {
+            let mut query = terms::table
+                .select((
+                    terms::id,
+                    terms::name.nullable(),
+                    terms::deleted_at,
+                    terms::trashed_at,
+                ))
+                .filter(terms::organization_id.eq(organization_id))
+                .filter(terms::deleted_at.is_not_null())
+                .into_boxed();
+
+            if let Some(ids) = &ids {
+                query = query.filter(terms::id.eq_any(ids));
+            }
+
+            query
+                .load::<(
+                    Uuid,
+                    Option<String>,
+                    Option<DateTime<Utc>>,
+                    Option<DateTime<Utc>>,
+                )>(&mut conn)
+                .await
+        }
+        TrashAssetType::DataSource => {
+            let mut query = data_sources::table
+                .select((
+                    data_sources::id,
+                    data_sources::name.nullable(),
+                    data_sources::deleted_at,
+                    data_sources::trashed_at,
+                ))
+                .filter(data_sources::organization_id.eq(organization_id))
+                .filter(data_sources::deleted_at.is_not_null())
+                .into_boxed();
+
+            if let Some(ids) = &ids {
+                query = query.filter(data_sources::id.eq_any(ids));
+            }
+
+            query
+                .load::<(
+                    Uuid,
+                    Option<String>,
+                    Option<DateTime<Utc>>,
+                    Option<DateTime<Utc>>,
+                )>(&mut conn)
+                .await
+        }
+    };
+
+    let records = match records {
+        Ok(records) => records,
+        Err(e) => {
+            return Err(anyhow!(
+                "Error getting deleted {}: {}",
+                asset_type.label(),
+                e
+            ))
+        }
+    };
+
+    let retention_days = trash_retention_days();
+    let purge_legacy_deletes = purge_legacy_deletes();
+
+    Ok(records
+        .into_iter()
+        .filter_map(|(id, name, deleted_at, trashed_at)| {
+            let deleted_at = deleted_at?;
+
+            Some(TrashItem {
+                id,
+                asset_type,
+                name: name.unwrap_or_default(),
+                deleted_at,
+                purge_at: purge_at(trashed_at, deleted_at, retention_days, purge_legacy_deletes),
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restore_conflict() {
+        assert!(restore_conflict(TrashAssetType::DataSource, false, false).is_some());
+        assert!(restore_conflict(TrashAssetType::DataSource, true, false).is_none());
+        assert!(restore_conflict(TrashAssetType::Dataset, true, true).is_some());
+        assert!(restore_conflict(TrashAssetType::Dataset, true, false).is_none());
+
+        for asset_type in [
+            TrashAssetType::Dashboard,
+            TrashAssetType::Thread,
+            TrashAssetType::Collection,
+            TrashAssetType::Term,
+        ] {
+            assert!(restore_conflict(asset_type, true, false).is_none());
+        }
+    }
+
+    #[test]
+    fn test_restore_path_asset_types() {
+        let asset_type: TrashAssetType = serde_json::from_str("\"data_source\"").unwrap();
+        assert_eq!(asset_type, TrashAssetType::DataSource);
+
+        // Only dashboards, threads and collections carry permissions back with them.
+        let shared: Vec<TrashAssetType> = TrashAssetType::ALL
+            .into_iter()
+            .filter(|t| t.shared_asset_type().is_some())
+            .collect();
+        assert_eq!(
+            shared,
+            vec![
+                TrashAssetType::Dashboard,
+                TrashAssetType::Thread,
+                TrashAssetType::Collection
+            ]
+        );
+    }
+}
@@ -0,0 +1,3 @@
+pub mod items;
+pub mod purge;
+pub mod retention;
@@ -0,0 +1,420 @@
+use anyhow::{anyhow, Result};
+use diesel::{
+    delete, update, BoolExpressionMethods, ExpressionMethods, OptionalExtension, QueryDsl,
+};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        enums::AssetType,
+        lib::get_pg_pool,
+        schema::{
+            asset_permissions, collections, collections_to_assets, dashboards, data_sources,
+            dataset_columns, datasets, entity_relationship, messages, report_subscriptions, terms,
+            threads, user_favorites,
+        },
+    },
+    utils::{
+        clients::{sentry_utils::send_sentry_error, supabase_vault::delete_secret},
+        stored_values::{delete_stored_values, drop_stored_values_schema},
+    },
+};
+
+use super::items::TrashAssetType;
+
+/// What's left once an asset's rows are gone. Vault secrets and stored values can't be part of
+/// the transaction, so they are only removed after it commits.
+#[derive(Default)]
+struct PurgeCleanup {
+    secret_ids: Vec<Uuid>,
+    organization_id: Option<Uuid>,
+    dataset_ids: Vec<Uuid>,
+    drop_stored_values_schema: bool,
+}
+
+/// Permanently deletes an asset in the trash, with its vault secrets and whatever only existed for
+/// it. Returns false when the asset isn't in the trash.
+pub async fn purge_trash_item(asset_type: TrashAssetType, id: &Uuid) -> Result<bool> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    let id = *id;
+
+    let cleanup = conn
+        .transaction::<_, anyhow::Error, _>(|conn| {
+            async move {
+                match asset_type {
+                    TrashAssetType::Dashboard => purge_dashboard(conn, &id).await,
+                    TrashAssetType::Thread => purge_thread(conn, &id).await,
+                    TrashAssetType::Collection => purge_collection(conn, &id).await,
+                    TrashAssetType::Dataset => purge_dataset(conn, &id).await,
+                    TrashAssetType::Term => purge_term(conn, &id).await,
+                    TrashAssetType::DataSource => purge_data_source(conn, &id).await,
+                }
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    match cleanup {
+        Some(cleanup) => {
+            // The asset is gone either way, so failures are reported rather than returned.
+            if let Err(e) = run_cleanup(cleanup).await {
+                let message = format!(
+                    "Error cleaning up purged {} {}: {}",
+                    asset_type.label(),
+                    id,
+                    e
+                );
+                tracing::error!("{}", message);
+                send_sentry_error(&message, None);
+            }
+
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+async fn run_cleanup(cleanup: PurgeCleanup) -> Result<()> {
+    for secret_id in &cleanup.secret_ids {
+        delete_secret(secret_id).await?;
+    }
+
+    if let Some(organization_id) = &cleanup.organization_id {
+        for dataset_id in &cleanup.dataset_ids {
+            delete_stored_values(organization_id, dataset_id).await?;
+        }
+
+        if cleanup.drop_stored_values_schema {
+            drop_stored_values_schema(organization_id).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn purge_dashboard(conn: &mut AsyncPgConnection, id: &Uuid) -> Result<Option<PurgeCleanup>> {
+    let password_secret_id = match dashboards::table
+        .select(dashboards::password_secret_id)
+        .filter(dashboards::id.eq(id))
+        .filter(dashboards::deleted_at.is_not_null())
+        .first::<Option<Uuid>>(conn)
+        .await
+        .optional()
+    {
+        Ok(Some(password_secret_id)) => password_secret_id,
+        Ok(None) => return Ok(None),
+        Err(e) => return Err(anyhow!("Error getting dashboard: {}", e)),
+    };
+
+    delete_asset_references(conn, id, AssetType::Dashboard).await?;
+
+    // Thread links and versions cascade.
+    match delete(dashboards::table)
+        .filter(dashboards::id.eq(id))
+        .execute(conn)
+        .await
+    {
+        Ok(_) => Ok(Some(PurgeCleanup {
+            secret_ids: password_secret_id.into_iter().collect(),
+            ..Default::default()
+        })),
+        Err(e) => Err(anyhow!("Error purging dashboard: {}", e)),
+    }
+}
+
+async fn purge_thread(conn: &mut AsyncPgConnection, id: &Uuid) -> Result<Option<PurgeCleanup>> {
+    let password_secret_id = match threads::table
+        .select(threads::password_secret_id)
+        .filter(threads::id.eq(id))
+        .filter(threads::deleted_at.is_not_null())
+        .first::<Option<Uuid>>(conn)
+        .await
+        .optional()
+    {
+        Ok(Some(password_secret_id)) => password_secret_id,
+        Ok(None) => return Ok(None),
+        Err(e) => return Err(anyhow!("Error getting thread: {}", e)),
+    };
+
+    delete_asset_references(conn, id, AssetType::Thread).await?;
+
+    // Duplicates of the thread outlive it.
+    if let Err(e) = update(threads::table)
+        .filter(threads::parent_thread_id.eq(id))
+        .set(threads::parent_thread_id.eq(None::<Uuid>))
+        .execute(conn)
+        .await
+    {
+        return Err(anyhow!("Error detaching duplicated threads: {}", e));
+    }
+
+    // Messages, dashboard links and metric alerts cascade.
+    match delete(threads::table)
+        .filter(threads::id.eq(id))
+        .execute(conn)
+        .await
+    {
+        Ok(_) => Ok(Some(PurgeCleanup {
+            secret_ids: password_secret_id.into_iter().collect(),
+            ..Default::default()
+        })),
+        Err(e) => Err(anyhow!("Error purging thread: {}", e)),
+    }
+}
+
+async fn purge_collection(conn: &mut AsyncPgConnection, id: &Uuid) -> Result<Option<PurgeCleanup>> {
+    let password_secret_id = match collections::table
+        .select(collections::password_secret_id)
+        .filter(collections::id.eq(id))
+        .filter(collections::deleted_at.is_not_null())
+        .first::<Option<Uuid>>(conn)
+        .await
+        .optional()
+    {
+        Ok(Some(password_secret_id)) => password_secret_id,
+        Ok(None) => return Ok(None),
+        Err(e) => return Err(anyhow!("Error getting collection: {}", e)),
+    };
+
+    delete_asset_references(conn, id, AssetType::Collection).await?;
+
+    // The collection's assets stay, they just aren't in it anymore.
+    if let Err(e) = delete(collections_to_assets::table)
+        .filter(collections_to_assets::collection_id.eq(id))
+        .execute(conn)
+        .await
+    {
+        return Err(anyhow!("Error removing collection assets: {}", e));
+    }
+
+    match delete(collections::table)
+        .filter(collections::id.eq(id))
+        .execute(conn)
+        .await
+    {
+        Ok(_) => Ok(Some(PurgeCleanup {
+            secret_ids: password_secret_id.into_iter().collect(),
+            ..Default::default()
+        })),
+        Err(e) => Err(anyhow!("Error purging collection: {}", e)),
+    }
+}
+
+async fn purge_term(conn: &mut AsyncPgConnection, id: &Uuid) -> Result<Option<PurgeCleanup>> {
+    let deleted = match terms::table
+        .select(terms::id)
+        .filter(terms::id.eq(id))
+        .filter(terms::deleted_at.is_not_null())
+        .first::<Uuid>(conn)
+        .await
+        .optional()
+    {
+        Ok(deleted) => deleted.is_some(),
+        Err(e) => return Err(anyhow!("Error getting term: {}", e)),
+    };
+
+    if !deleted {
+        return Ok(None);
+    }
+
+    let query = diesel::sql_query("DELETE FROM terms_search WHERE term_id = $1")
+        .bind::<diesel::sql_types::Uuid, _>(id);
+
+    if let Err(e) = query.execute(conn).await {
+        return Err(anyhow!("Error removing term from search: {}", e));
+    }
+
+    // Dataset links cascade.
+    match delete(terms::table)
+        .filter(terms::id.eq(id))
+        .execute(conn)
+        .await
+    {
+        Ok(_) => Ok(Some(PurgeCleanup::default())),
+        Err(e) => Err(anyhow!("Error purging term: {}", e)),
+    }
+}
+
+async fn purge_dataset(conn: &mut AsyncPgConnection, id: &Uuid) -> Result<Option<PurgeCleanup>> {
+    let organization_id = match datasets::table
+        .select(datasets::organization_id)
+        .filter(datasets::id.eq(id))
+        .filter(datasets::deleted_at.is_not_null())
+        .first::<Uuid>(conn)
+        .await
+        .optional()
+    {
+        Ok(Some(organization_id)) => organization_id,
+        Ok(None) => return Ok(None),
+        Err(e) => return Err(anyhow!("Error getting dataset: {}", e)),
+    };
+
+    delete_dataset(conn, id).await?;
+
+    Ok(Some(PurgeCleanup {
+        organization_id: Some(organization_id),
+        dataset_ids: vec![*id],
+        ..Default::default()
+    }))
+}
+
+/// Data sources take their datasets with them, deleted or not, since those can't be queried
+/// anymore. The organization's stored values schema goes once it has no datasets left.
+async fn purge_data_source(
+    conn: &mut AsyncPgConnection,
+    id: &Uuid,
+) -> Result<Option<PurgeCleanup>> {
+    let (secret_id, organization_id) = match data_sources::table
+        .select((data_sources::secret_id, data_sources::organization_id))
+        .filter(data_sources::id.eq(id))
+        .filter(data_sources::deleted_at.is_not_null())
+        .first::<(Uuid, Uuid)>(conn)
+        .await
+        .optional()
+    {
+        Ok(Some(data_source)) => data_source,
+        Ok(None) => return Ok(None),
+        Err(e) => return Err(anyhow!("Error getting data source: {}", e)),
+    };
+
+    let dataset_ids = match datasets::table
+        .select(datasets::id)
+        .filter(datasets::data_source_id.eq(id))
+        .load::<Uuid>(conn)
+        .await
+    {
+        Ok(dataset_ids) => dataset_ids,
+        Err(e) => return Err(anyhow!("Error getting data source datasets: {}", e)),
+    };
+
+    for dataset_id in &dataset_ids {
+        delete_dataset(conn, dataset_id).await?;
+    }
+
+    if let Err(e) = delete(data_sources::table)
+        .filter(data_sources::id.eq(id))
+        .execute(conn)
+        .await
+    {
+        return Err(anyhow!("Error purging data source: {}", e));
+    }
+
+    let remaining_datasets = match datasets::table
+        .select(datasets::id)
+        .filter(datasets::organization_id.eq(organization_id))
+        .first::<Uuid>(conn)
+        .await
+        .optional()
+    {
+        Ok(remaining_datasets) => remaining_datasets,
+        Err(e) => return Err(anyhow!("Error getting organization datasets: {}", e)),
+    };
+
+    Ok(Some(PurgeCleanup {
+        secret_ids: vec![secret_id],
+        organization_id: Some(organization_id),
+        dataset_ids,
+        drop_stored_values_schema: remaining_datasets.is_none(),
+    }))
+}
+
+async fn delete_dataset(conn: &mut AsyncPgConnection, id: &Uuid) -> Result<()> {
+    // Messages would cascade with the dataset, so they are detached to keep thread history.
+    if let Err(e) = update(messages::table)
+        .filter(messages::dataset_id.eq(id))
+        .set(messages::dataset_id.eq(None::<Uuid>))
+        .execute(conn)
+        .await
+    {
+        return Err(anyhow!("Error detaching dataset messages: {}", e));
+    }
+
+    if let Err(e) = delete(dataset_columns::table)
+        .filter(dataset_columns::dataset_id.eq(id))
+        .execute(conn)
+        .await
+    {
+        return Err(anyhow!("Error deleting dataset columns: {}", e));
+    }
+
+    if let Err(e) = delete(entity_relationship::table)
+        .filter(
+            entity_relationship::primary_dataset_id
+                .eq(id)
+                .or(entity_relationship::foreign_dataset_id.eq(id)),
+        )
+        .execute(conn)
+        .await
+    {
+        return Err(anyhow!("Error deleting dataset relationships: {}", e));
+    }
+
+    // Permission group, dataset group, term and semantic layer links cascade.
+    match delete(datasets::table)
+        .filter(datasets::id.eq(id))
+        .execute(conn)
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!("Error purging dataset: {}", e)),
+    }
+}
+
+/// Rows that point at a dashboard, thread or collection without a foreign key.
+async fn delete_asset_references(
+    conn: &mut AsyncPgConnection,
+    asset_id: &Uuid,
+    asset_type: AssetType,
+) -> Result<()> {
+    if let Err(e) = delete(asset_permissions::table)
+        .filter(asset_permissions::asset_id.eq(asset_id))
+        .filter(asset_permissions::asset_type.eq(asset_type))
+        .execute(conn)
+        .await
+    {
+        return Err(anyhow!("Error deleting asset permissions: {}", e));
+    }
+
+    if let Err(e) = delete(collections_to_assets::table)
+        .filter(collections_to_assets::asset_id.eq(asset_id))
+        .filter(collections_to_assets::asset_type.eq(asset_type))
+        .execute(conn)
+        .await
+    {
+        return Err(anyhow!("Error deleting collection membership: {}", e));
+    }
+
+    if let Err(e) = delete(user_favorites::table)
+        .filter(user_favorites::asset_id.eq(asset_id))
+        .filter(user_favorites::asset_type.eq(asset_type))
+        .execute(conn)
+        .await
+    {
+        return Err(anyhow!("Error deleting favorites: {}", e));
+    }
+
+    if let Err(e) = delete(report_subscriptions::table)
+        .filter(report_subscriptions::asset_id.eq(asset_id))
+        .filter(report_subscriptions::asset_type.eq(asset_type))
+        .execute(conn)
+        .await
+    {
+        return Err(anyhow!("Error deleting report subscriptions: {}", e));
+    }
+
+    let query = diesel::sql_query("DELETE FROM asset_search WHERE asset_id = $1")
+        .bind::<diesel::sql_types::Uuid, _>(asset_id);
+
+    if let Err(e) = query.execute(conn).await {
+        return Err(anyhow!("Error deleting asset search: {}", e));
+    }
+
+    Ok(())
+}
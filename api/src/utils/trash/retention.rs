@@ -0,0 +1,454 @@
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, TimeDelta, Utc};
+use diesel::{BoolExpressionMethods, ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        lib::get_pg_pool,
+        schema::{collections, dashboards, data_sources, datasets, terms, threads},
+    },
+    utils::clients::sentry_utils::send_sentry_error,
+};
+
+use super::{items::TrashAssetType, purge::purge_trash_item};
+
+const DEFAULT_TRASH_RETENTION_DAYS: i64 = 30;
+const RETENTION_INTERVAL_SECONDS: u64 = 60 * 60;
+const MAX_PURGE_BACKOFF_HOURS: i64 = 7 * 24;
+
+/// How many days deleted assets stay in the trash, from `TRASH_RETENTION_DAYS`.
+pub fn trash_retention_days() -> i64 {
+    parse_retention_days(env::var("TRASH_RETENTION_DAYS").ok().as_deref())
+}
+
+fn parse_retention_days(days: Option<&str>) -> i64 {
+    days.and_then(|days| days.trim().parse::<i64>().ok())
+        .filter(|days| *days > 0)
+        .unwrap_or(DEFAULT_TRASH_RETENTION_DAYS)
+}
+
+/// Whether the retention job also purges assets deleted before the trash existed, from
+/// `TRASH_PURGE_LEGACY_DELETES`. Those rows have no `trashed_at` and are left alone by default.
+pub fn purge_legacy_deletes() -> bool {
+    parse_purge_legacy_deletes(env::var("TRASH_PURGE_LEGACY_DELETES").ok().as_deref())
+}
+
+fn parse_purge_legacy_deletes(value: Option<&str>) -> bool {
+    value
+        .and_then(|value| value.trim().parse::<bool>().ok())
+        .unwrap_or(false)
+}
+
+/// Assets trashed before the cutoff have been in the trash longer than the retention period.
+fn trash_cutoff(now: DateTime<Utc>, retention_days: i64) -> DateTime<Utc> {
+    now - TimeDelta::days(retention_days)
+}
+
+/// When the retention job permanently deletes a deleted asset, if it ever does.
+pub(super) fn purge_at(
+    trashed_at: Option<DateTime<Utc>>,
+    deleted_at: DateTime<Utc>,
+    retention_days: i64,
+    purge_legacy_deletes: bool,
+) -> Option<DateTime<Utc>> {
+    match (trashed_at, purge_legacy_deletes) {
+        (Some(trashed_at), _) => Some(trashed_at + TimeDelta::days(retention_days)),
+        (None, true) => Some(deleted_at + TimeDelta::days(retention_days)),
+        (None, false) => None,
+    }
+}
+
+/// How long to wait before retrying an asset that has failed to purge `failures` times in a row.
+/// Starts at the job interval and doubles up to a week.
+fn purge_backoff(failures: u32) -> TimeDelta {
+    let hours = 1_i64 << failures.saturating_sub(1).min(16);
+
+    TimeDelta::hours(hours.min(MAX_PURGE_BACKOFF_HOURS))
+}
+
+struct PurgeFailure {
+    failures: u32,
+    retry_at: DateTime<Utc>,
+}
+
+/// Assets the job failed to purge, so a broken row is retried with a growing delay instead of
+/// every hour.
+#[derive(Default)]
+struct PurgeFailures {
+    failures: HashMap<(TrashAssetType, Uuid), PurgeFailure>,
+}
+
+impl PurgeFailures {
+    fn should_attempt(&self, asset_type: TrashAssetType, id: &Uuid, now: DateTime<Utc>) -> bool {
+        match self.failures.get(&(asset_type, *id)) {
+            Some(failure) => failure.retry_at <= now,
+            None => true,
+        }
+    }
+
+    /// Records a failed purge and returns how many times in a row the asset has failed.
+    fn record_failure(&mut self, asset_type: TrashAssetType, id: &Uuid, now: DateTime<Utc>) -> u32 {
+        let failure = self
+            .failures
+            .entry((asset_type, *id))
+            .or_insert(PurgeFailure {
+                failures: 0,
+                retry_at: now,
+            });
+
+        failure.failures += 1;
+        failure.retry_at = now + purge_backoff(failure.failures);
+
+        failure.failures
+    }
+
+    fn record_success(&mut self, asset_type: TrashAssetType, id: &Uuid) {
+        self.failures.remove(&(asset_type, *id));
+    }
+
+    /// Forgets assets that are no longer waiting to be purged, e.g. because they were restored.
+    fn retain_expired(&mut self, expired: &HashSet<(TrashAssetType, Uuid)>) {
+        self.failures.retain(|key, _| expired.contains(key));
+    }
+}
+
+/// Starts the loop that empties the trash. Every hour it permanently deletes the assets that have
+/// been in the trash longer than the retention period.
+pub fn start_trash_retention_job() {
+    tokio::spawn(async move {
+        let mut failures = PurgeFailures::default();
+
+        loop {
+            tokio::time::sleep(Duration::from_secs(RETENTION_INTERVAL_SECONDS)).await;
+
+            let cutoff = trash_cutoff(Utc::now(), trash_retention_days());
+
+            if let Err(e) = purge_expired_trash(cutoff, purge_legacy_deletes(), &mut failures).await
+            {
+                tracing::error!("Error purging trash: {}", e);
+                send_sentry_error(&format!("Error purging trash: {}", e), None);
+            }
+        }
+    });
+}
+
+async fn purge_expired_trash(
+    cutoff: DateTime<Utc>,
+    purge_legacy_deletes: bool,
+    failures: &mut PurgeFailures,
+) -> Result<()> {
+    let mut expired = HashSet::new();
+
+    for asset_type in TrashAssetType::ALL {
+        for id in expired_trash_ids(asset_type, cutoff, purge_legacy_deletes).await? {
+            expired.insert((asset_type, id));
+
+            let now = Utc::now();
+
+            if !failures.should_attempt(asset_type, &id, now) {
+                continue;
+            }
+
+            // One asset failing to purge shouldn't hold up the rest. It is retried with a growing
+            // delay and only reported the first time it fails.
+            match purge_trash_item(asset_type, &id).await {
+                Ok(_) => failures.record_success(asset_type, &id),
+                Err(e) => {
+                    let attempts = failures.record_failure(asset_type, &id, now);
+                    let message = format!(
+                        "Error purging {} {} (attempt {}): {}",
+                        asset_type.label(),
+                        id,
+                        attempts,
+                        e
+                    );
+                    tracing::error!("{}", message);
+
+                    if attempts == 1 {
+                        send_sentry_error(&message, None);
+                    }
+                }
+            }
+        }
+    }
+
+    failures.retain_expired(&expired);
+
+    Ok(())
+}
+
+/// Deleted assets that have been in the trash since before the cutoff. Only rows the trash marked
+/// with `trashed_at` are considered, unless legacy deletes are opted in.
+async fn expired_trash_ids(
+    asset_type: TrashAssetType,
+    cutoff: DateTime<Utc>,
+    purge_legacy_deletes: bool,
+) -> Result<Vec<Uuid>> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    let ids = match asset_type {
+        TrashAssetType::Dashboard => {
+            let mut query = dashboards::table
+                .select(dashboards::id)
+                .filter(dashboards::deleted_at.is_not_null())
+                .into_boxed();
+
+            query = if purge_legacy_deletes {
+                query.filter(
+                    dashboards::trashed_at.lt(cutoff).or(dashboards::trashed_at
+                        .is_null()
+                        .and(dashboards::deleted_at.lt(cutoff))),
+                )
+            } else {
+                query.filter(dashboards::trashed_at.lt(cutoff))
+            };
+
+            query.load::<Uuid>(&mut conn).await
+        }
+        TrashAssetType::Thread => {
+            let mut query = threads::table
+                .select(threads::id)
+                .filter(threads::deleted_at.is_not_null())
+                .into_boxed();
+
+            query = if purge_legacy_deletes {
+                query.filter(
+                    threads::trashed_at.lt(cutoff).or(threads::trashed_at
+                        .is_null()
+                        .and(threads::deleted_at.lt(cutoff))),
+                )
+            } else {
+                query.filter(threads::trashed_at.lt(cutoff))
+            };
+
+            query.load::<Uuid>(&mut conn).await
+        }
+        TrashAssetType::Collection => {
+            let mut query = collections::table
+                .select(collections::id)
+                .filter(collections::deleted_at.is_not_null())
+                .into_boxed();
+
+            query = if purge_legacy_deletes {
+                query.filter(
+                    collections::trashed_at
+                        .lt(cutoff)
+                        .or(collections::trashed_at
+                            .is_null()
+                            .and(collections::deleted_at.lt(cutoff))),
+                )
+            } else {
+                query.filter(collections::trashed_at.lt(cutoff))
+            };
+
+            query.load::<Uuid>(&mut conn).await
+        }
+        TrashAssetType::Dataset => {
+            let mut query = datasets::table
+                .select(datasets::id)
+                .filter(datasets::deleted_at.is_not_null())
+                .into_boxed();
+
+            query = if purge_legacy_deletes {
+                query.filter(
+                    datasets::trashed_at.lt(cutoff).or(datasets::trashed_at
+                        .is_null()
+                        .and(datasets::deleted_at.lt(cutoff))),
+                )
+            } else {
+                query.filter(datasets::trashed_at.lt(cutoff))
+            };
+
+            query.load::<Uuid>(&mut conn).await
+        }
+        TrashAssetType::Term => {
+            let mut query = terms::table
+                .select(terms::id)
+                .filter(terms::deleted_at.is_not_null())
+                .into_boxed();
+
+            query = if purge_legacy_deletes {
+                query.filter(
+                    terms::trashed_at.lt(cutoff).or(terms::trashed_at
+                        .is_null()
+                        .and(terms::deleted_at.lt(cutoff))),
+                )
+            } else {
+                query.filter(terms::trashed_at.lt(cutoff))
+            };
+
+            query.load::<Uuid>(&mut conn).await
+        }
+        TrashAssetType::DataSource => {
+            let mut query = data_sources::table
+                .select(data_sources::id)
+                .filter(data_sources::deleted_at.is_not_null())
+                .into_boxed();
+
+            query = if purge_legacy_deletes {
+                query.filter(
+                    data_sources::trashed_at
+                        .lt(cutoff)
+                        .or(data_sources::trashed_at
+                            .is_null()
+                            .and(data_sources::deleted_at.lt(cutoff))),
+                )
+            } else {
+                query.filter(data_sources::trashed_at.lt(cutoff))
+            };
+
+            query.load::<Uuid>(&mut conn).await
+        }
+    };
+
+    match ids {
+        Ok(ids) => Ok(ids),
+        Err(e) => Err(anyhow!(
+            "Error getting expired {}: {}",
+            asset_type.label(),
+            e
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_retention_days() {
+        assert_eq!(parse_retention_days(Some("7")), 7);
+        assert_eq!(parse_retention_days(Some(" 90 ")), 90);
+        assert_eq!(
+            parse_retention_days(Some("0")),
+            DEFAULT_TRASH_RETENTION_DAYS
+        );
+        assert_eq!(
+            parse_retention_days(Some("-3")),
+            DEFAULT_TRASH_RETENTION_DAYS
+        );
+        assert_eq!(
+            parse_retention_days(Some("week")),
+            DEFAULT_TRASH_RETENTION_DAYS
+        );
+        assert_eq!(parse_retention_days(None), DEFAULT_TRASH_RETENTION_DAYS);
+    }
+
+    #[test]
+    fn test_trash_cutoff() {
+        let now = Utc::now();
+        let cutoff = trash_cutoff(now, 30);
+
+        assert_eq!(now - cutoff, TimeDelta::days(30));
+
+        // The job purges assets trashed strictly before the cutoff.
+        let expired = now - TimeDelta::days(31);
+        let kept = now - TimeDelta::days(29);
+
+        assert!(expired < cutoff);
+        assert!(kept >= cutoff);
+    }
+
+    #[test]
+    fn test_parse_purge_legacy_deletes() {
+        assert!(parse_purge_legacy_deletes(Some("true")));
+        assert!(!parse_purge_legacy_deletes(Some("false")));
+        assert!(!parse_purge_legacy_deletes(Some("yes")));
+        assert!(!parse_purge_legacy_deletes(None));
+    }
+
+    #[test]
+    fn test_legacy_deletes_are_not_purged_by_default() {
+        let deleted_at = Utc::now() - TimeDelta::days(400);
+        let trashed_at = Utc::now() - TimeDelta::days(2);
+
+        assert_eq!(purge_at(None, deleted_at, 30, false), None);
+        assert_eq!(
+            purge_at(None, deleted_at, 30, true),
+            Some(deleted_at + TimeDelta::days(30))
+        );
+
+        // The trash marker wins over the original deletion time.
+        assert_eq!(
+            purge_at(Some(trashed_at), deleted_at, 30, false),
+            Some(trashed_at + TimeDelta::days(30))
+        );
+        assert_eq!(
+            purge_at(Some(trashed_at), deleted_at, 30, true),
+            Some(trashed_at + TimeDelta::days(30))
+        );
+    }
+
+    #[test]
+    fn test_purge_backoff() {
+        assert_eq!(purge_backoff(1), TimeDelta::hours(1));
+        assert_eq!(purge_backoff(2), TimeDelta::hours(2));
+        assert_eq!(purge_backoff(5), TimeDelta::hours(16));
+        assert_eq!(purge_backoff(20), TimeDelta::hours(MAX_PURGE_BACKOFF_HOURS));
+        assert_eq!(
+            purge_backoff(200),
+            TimeDelta::hours(MAX_PURGE_BACKOFF_HOURS)
+        );
+    }
+
+    #[test]
+    fn test_failed_purges_back_off() {
+        let mut failures = PurgeFailures::default();
+        let id = Uuid::new_v4();
+        let now = Utc::now();
+
+        assert!(failures.should_attempt(TrashAssetType::Dataset, &id, now));
+
+        assert_eq!(
+            failures.record_failure(TrashAssetType::Dataset, &id, now),
+            1
+        );
+        assert!(!failures.should_attempt(TrashAssetType::Dataset, &id, now));
+        assert!(failures.should_attempt(TrashAssetType::Dataset, &id, now + TimeDelta::hours(1)));
+
+        let later = now + TimeDelta::hours(1);
+        assert_eq!(
+            failures.record_failure(TrashAssetType::Dataset, &id, later),
+            2
+        );
+        assert!(!failures.should_attempt(
+            TrashAssetType::Dataset,
+            &id,
+            later + TimeDelta::hours(1)
+        ));
+        assert!(failures.should_attempt(TrashAssetType::Dataset, &id, later + TimeDelta::hours(2)));
+
+        // Other assets with the same id are tracked separately.
+        assert!(failures.should_attempt(TrashAssetType::Term, &id, now));
+
+        failures.record_success(TrashAssetType::Dataset, &id);
+        assert!(failures.should_attempt(TrashAssetType::Dataset, &id, now));
+    }
+
+    #[test]
+    fn test_failures_are_forgotten_once_not_expired() {
+        let mut failures = PurgeFailures::default();
+        let restored = Uuid::new_v4();
+        let still_expired = Uuid::new_v4();
+        let now = Utc::now();
+
+        failures.record_failure(TrashAssetType::Dashboard, &restored, now);
+        failures.record_failure(TrashAssetType::Dashboard, &still_expired, now);
+
+        failures.retain_expired(&HashSet::from([(TrashAssetType::Dashboard, still_expired)]));
+
+        assert!(failures.should_attempt(TrashAssetType::Dashboard, &restored, now));
+        assert!(!failures.should_attempt(TrashAssetType::Dashboard, &still_expired, now));
+    }
+}
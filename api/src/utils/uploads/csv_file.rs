@@ -0,0 +1,278 @@
+use std::collections::HashSet;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+
+/// The most data rows an uploaded CSV can have.
+pub const UPLOAD_MAX_ROWS: usize = 1_000_000;
+const MAX_IDENTIFIER_LENGTH: usize = 60;
+
+const DATE_FORMATS: [&str; 3] = ["%Y-%m-%d", "%m/%d/%Y", "%Y/%m/%d"];
+const TIMESTAMP_FORMATS: [&str; 6] = [
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M",
+    "%m/%d/%Y %H:%M:%S",
+    "%m/%d/%Y %H:%M",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CsvColumnType {
+    Boolean,
+    Integer,
+    Float,
+    Date,
+    Timestamp,
+    Text,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CsvValue {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    Date(NaiveDate),
+    Timestamp(NaiveDateTime),
+    Text(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct CsvColumn {
+    /// The column name in the warehouse, made safe to use as an identifier.
+    pub name: String,
+    /// The header as it appeared in the file.
+    pub header: String,
+    pub column_type: CsvColumnType,
+    pub nullable: bool,
+}
+
+pub struct CsvUpload {
+    pub columns: Vec<CsvColumn>,
+    pub rows: Vec<Vec<CsvValue>>,
+}
+
+/// Reads a CSV with a header row and infers a type for each column: the narrowest of boolean,
+/// integer, float, date and timestamp that every non-empty value parses as, or text. Empty cells
+/// are nulls.
+pub fn parse_csv(contents: &[u8]) -> Result<CsvUpload> {
+    let contents = contents.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(contents);
+
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .from_reader(contents);
+
+    let headers: Vec<String> = match reader.headers() {
+        Ok(headers) => headers.iter().map(|h| h.trim().to_string()).collect(),
+        Err(e) => return Err(anyhow!("Unable to read the CSV header: {}", e)),
+    };
+
+    if headers.is_empty() || headers.iter().all(|h| h.is_empty()) {
+        return Err(anyhow!("The CSV has no header row"));
+    }
+
+    let mut raw_rows: Vec<Vec<Option<String>>> = Vec::new();
+
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => return Err(anyhow!("Unable to read the CSV: {}", e)),
+        };
+
+        if raw_rows.len() >= UPLOAD_MAX_ROWS {
+            return Err(anyhow!(
+                "CSV uploads are limited to {} rows",
+                UPLOAD_MAX_ROWS
+            ));
+        }
+
+        raw_rows.push(
+            record
+                .iter()
+                .map(|field| match field.trim() {
+                    "" => None,
+                    field => Some(field.to_string()),
+                })
+                .collect(),
+        );
+    }
+
+    let mut names = HashSet::new();
+    let mut columns = Vec::with_capacity(headers.len());
+
+    for (index, header) in headers.iter().enumerate() {
+        let values = raw_rows.iter().filter_map(|row| row[index].as_deref());
+
+        let mut name = sql_identifier(header);
+        if name.is_empty() {
+            name = format!("column_{}", index + 1);
+        }
+
+        columns.push(CsvColumn {
+            name: unique_identifier(name, &mut names),
+            header: header.clone(),
+            column_type: infer_column_type(values),
+            nullable: raw_rows.iter().any(|row| row[index].is_none()),
+        });
+    }
+
+    let rows = raw_rows
+        .into_iter()
+        .map(|row| {
+            row.into_iter()
+                .zip(&columns)
+                .map(|(value, column)| match value {
+                    Some(value) => {
+                        parse_value(&value, column.column_type).unwrap_or(CsvValue::Text(value))
+                    }
+                    None => CsvValue::Null,
+                })
+                .collect()
+        })
+        .collect();
+
+    Ok(CsvUpload { columns, rows })
+}
+
+/// Lowercases a name and replaces anything other than letters and digits with underscores, so it
+/// can be used unquoted as a table or column name.
+pub fn sql_identifier(name: &str) -> String {
+    let mut identifier = String::new();
+
+    for c in name.trim().chars().flat_map(|c| c.to_lowercase()) {
+        if c.is_ascii_alphanumeric() {
+            identifier.push(c);
+        } else if !identifier.is_empty() && !identifier.ends_with('_') {
+            identifier.push('_');
+        }
+    }
+
+    let identifier: String = identifier
+        .trim_end_matches('_')
+        .chars()
+        .take(MAX_IDENTIFIER_LENGTH)
+        .collect();
+
+    match identifier.starts_with(|c: char| c.is_ascii_digit()) {
+        true => format!("c_{}", identifier),
+        false => identifier,
+    }
+}
+
+fn unique_identifier(name: String, names: &mut HashSet<String>) -> String {
+    let mut unique = name.clone();
+    let mut suffix = 2;
+
+    while !names.insert(unique.clone()) {
+        unique = format!("{}_{}", name, suffix);
+        suffix += 1;
+    }
+
+    unique
+}
+
+fn infer_column_type<'a>(values: impl Iterator<Item = &'a str>) -> CsvColumnType {
+    let mut candidates = vec![
+        CsvColumnType::Boolean,
+        CsvColumnType::Integer,
+        CsvColumnType::Float,
+        CsvColumnType::Date,
+        CsvColumnType::Timestamp,
+    ];
+    let mut has_values = false;
+
+    for value in values {
+        has_values = true;
+        candidates.retain(|column_type| parse_value(value, *column_type).is_some());
+
+        if candidates.is_empty() {
+            return CsvColumnType::Text;
+        }
+    }
+
+    match has_values {
+        true => candidates[0],
+        false => CsvColumnType::Text,
+    }
+}
+
+fn parse_value(value: &str, column_type: CsvColumnType) -> Option<CsvValue> {
+    match column_type {
+        CsvColumnType::Boolean => match value.to_lowercase().as_str() {
+            "true" | "yes" => Some(CsvValue::Boolean(true)),
+            "false" | "no" => Some(CsvValue::Boolean(false)),
+            _ => None,
+        },
+        CsvColumnType::Integer => value.parse::<i64>().ok().map(CsvValue::Integer),
+        CsvColumnType::Float => value
+            .parse::<f64>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .map(CsvValue::Float),
+        CsvColumnType::Date => DATE_FORMATS
+            .iter()
+            .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+            .map(CsvValue::Date),
+        CsvColumnType::Timestamp => TIMESTAMP_FORMATS
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+            .or_else(|| {
+                DateTime::parse_from_rfc3339(value)
+                    .ok()
+                    .map(|v| v.naive_utc())
+            })
+            .or_else(|| {
+                DATE_FORMATS
+                    .iter()
+                    .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+                    .and_then(|v| v.and_hms_opt(0, 0, 0))
+            })
+            .map(CsvValue::Timestamp),
+        CsvColumnType::Text => Some(CsvValue::Text(value.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv_infers_column_types() {
+        let contents = "\u{feff}Account ID,Name,ARR ($),Churned,Signed,Last Seen,order\n\
+            1,Acme,1200,yes,2024-01-05,2024-03-01 10:00:00,1\n\
+            2,\"Globex, Inc\",980.50,no,2024-02-11,2024-03-02,\n\
+            3,,,,01/15/2024,2024-03-03T08:30:00Z,x\n";
+
+        let upload = parse_csv(contents.as_bytes()).unwrap();
+
+        let columns: Vec<(&str, CsvColumnType, bool)> = upload
+            .columns
+            .iter()
+            .map(|c| (c.name.as_str(), c.column_type, c.nullable))
+            .collect();
+        assert_eq!(
+            columns,
+            vec![
+                ("account_id", CsvColumnType::Integer, false),
+                ("name", CsvColumnType::Text, true),
+                ("arr", CsvColumnType::Float, true),
+                ("churned", CsvColumnType::Boolean, true),
+                ("signed", CsvColumnType::Date, false),
+                ("last_seen", CsvColumnType::Timestamp, false),
+                ("order", CsvColumnType::Text, true),
+            ]
+        );
+
+        assert_eq!(upload.rows[1][1], CsvValue::Text("Globex, Inc".to_string()));
+        assert_eq!(upload.rows[0][2], CsvValue::Float(1200.0));
+        assert_eq!(upload.rows[2][2], CsvValue::Null);
+        assert_eq!(
+            upload.rows[2][4],
+            CsvValue::Date(NaiveDate::from_ymd_opt(2024, 1, 15).unwrap())
+        );
+
+        assert_eq!(sql_identifier("2024 Revenue"), "c_2024_revenue");
+        assert!(parse_csv(b"a,b\n1,2,3\n").is_err());
+    }
+}
@@ -0,0 +1,3 @@
+pub mod csv_file;
+pub mod register;
+pub mod warehouse;
@@ -0,0 +1,296 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use diesel::{insert_into, BoolExpressionMethods, ExpressionMethods, QueryDsl};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+use uuid::Uuid;
+
+use crate::database::{
+    enums::{DatasetType, IdentityType, Verification},
+    lib::get_pg_pool,
+    models::{
+        DataSource, Dataset, DatasetColumn, DatasetToPermissionGroup, PermissionGroup,
+        PermissionGroupToIdentity,
+    },
+    schema::{
+        dataset_columns, datasets, datasets_to_permission_groups, permission_groups,
+        permission_groups_to_identities, teams_to_users,
+    },
+};
+
+use super::{
+    csv_file::CsvUpload,
+    warehouse::{warehouse_column_type, UploadTarget},
+};
+
+pub struct UploadDataset<'a> {
+    pub id: Uuid,
+    pub name: &'a str,
+    pub description: Option<String>,
+    pub data_source: &'a DataSource,
+    pub target: &'a UploadTarget,
+    pub upload: &'a CsvUpload,
+}
+
+/// Whether the user can grant a dataset to every permission group. Admins can grant it to any
+/// group in the organization, everyone else only to groups they belong to directly or through a
+/// team.
+pub async fn can_grant_permission_groups(
+    user_id: &Uuid,
+    organization_id: &Uuid,
+    permission_group_ids: &[Uuid],
+    is_admin: bool,
+) -> Result<bool> {
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    let mut query = permission_groups::table
+        .select(permission_groups::id)
+        .filter(permission_groups::id.eq_any(permission_group_ids))
+        .filter(permission_groups::organization_id.eq(organization_id))
+        .filter(permission_groups::deleted_at.is_null())
+        .into_boxed();
+
+    if !is_admin {
+        let user_teams = teams_to_users::table
+            .filter(teams_to_users::user_id.eq(user_id))
+            .filter(teams_to_users::deleted_at.is_null())
+            .select(teams_to_users::team_id);
+
+        let member_groups = permission_groups_to_identities::table
+            .filter(
+                permission_groups_to_identities::identity_id
+                    .eq(user_id)
+                    .or(permission_groups_to_identities::identity_id.eq_any(user_teams)),
+            )
+            .filter(permission_groups_to_identities::deleted_at.is_null())
+            .select(permission_groups_to_identities::permission_group_id);
+
+        query = query.filter(permission_groups::id.eq_any(member_groups));
+    }
+
+    let found_ids = match query.load::<Uuid>(&mut conn).await {
+        Ok(ids) => ids,
+        Err(e) => return Err(anyhow!("Error checking permission groups: {}", e)),
+    };
+
+    Ok(permission_group_ids.iter().all(|id| found_ids.contains(id)))
+}
+
+/// Registers an uploaded table as an enabled dataset with its columns, and grants it to the given
+/// permission groups, which callers check with `can_grant_permission_groups` first. Without any,
+/// a new `<name> upload` group is created holding just the uploader, so nobody else sees the data
+/// until it is shared.
+pub async fn register_upload(
+    user_id: &Uuid,
+    organization_id: &Uuid,
+    upload: UploadDataset<'_>,
+    permission_group_ids: Vec<Uuid>,
+) -> Result<(Dataset, Vec<DatasetColumn>)> {
+    let target = upload.target;
+    let qualified_table = match &target.database_identifier {
+        Some(database_identifier) => {
+            format!("{}.{}.{}", database_identifier, target.schema, target.table)
+        }
+        None => format!("{}.{}", target.schema, target.table),
+    };
+
+    let dataset = Dataset {
+        id: upload.id,
+        name: upload.name.to_string(),
+        database_name: target.table.clone(),
+        when_to_use: upload.description,
+        when_not_to_use: None,
+        type_: DatasetType::Table,
+        definition: format!("SELECT * FROM {}", qualified_table),
+        schema: target.schema.clone(),
+        enabled: true,
+        imported: false,
+        data_source_id: upload.data_source.id,
+        organization_id: *organization_id,
+        created_by: *user_id,
+        updated_by: *user_id,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        deleted_at: None,
+        model: None,
+        yml_file: None,
+        database_identifier: target.database_identifier.clone(),
+        verification: Verification::NotRequested,
+        trashed_at: None,
+    };
+
+    let mut columns = Vec::with_capacity(upload.upload.columns.len());
+
+    for column in &upload.upload.columns {
+        let description = match column.header != column.name {
+            true => Some(format!("Uploaded as \"{}\"", column.header)),
+            false => None,
+        };
+
+        columns.push(DatasetColumn {
+            id: Uuid::new_v4(),
+            dataset_id: dataset.id,
+            name: column.name.clone(),
+            type_: warehouse_column_type(&upload.data_source.type_, column.column_type)?
+                .to_string(),
+            description,
+            nullable: column.nullable,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            deleted_at: None,
+            stored_values: None,
+            stored_values_status: None,
+            stored_values_error: None,
+            stored_values_count: None,
+            stored_values_last_synced: None,
+            semantic_type: None,
+            dim_type: None,
+            expr: None,
+        });
+    }
+
+    let mut conn = match get_pg_pool().get().await {
+        Ok(conn) => conn,
+        Err(e) => return Err(anyhow!("Error getting pg connection: {}", e)),
+    };
+
+    // Any failure leaves nothing behind, so the caller can drop the warehouse table.
+    let (dataset_ref, columns_ref) = (&dataset, &columns);
+
+    conn.transaction::<_, anyhow::Error, _>(|conn| {
+        async move {
+            insert_upload(
+                conn,
+                user_id,
+                organization_id,
+                upload.name,
+                dataset_ref,
+                columns_ref,
+                permission_group_ids,
+            )
+            .await
+        }
+        .scope_boxed()
+    })
+    .await?;
+
+    drop(conn);
+
+    Ok((dataset, columns))
+}
+
+async fn insert_upload(
+    conn: &mut AsyncPgConnection,
+    user_id: &Uuid,
+    organization_id: &Uuid,
+    name: &str,
+    dataset: &Dataset,
+    columns: &[DatasetColumn],
+    permission_group_ids: Vec<Uuid>,
+) -> Result<()> {
+    if let Err(e) = insert_into(datasets::table)
+        .values(dataset)
+        .execute(conn)
+        .await
+    {
+        return Err(anyhow!("Error inserting upload dataset: {}", e));
+    }
+
+    if let Err(e) = insert_into(dataset_columns::table)
+        .values(columns)
+        .execute(conn)
+        .await
+    {
+        return Err(anyhow!("Error inserting upload dataset columns: {}", e));
+    }
+
+    let permission_group_ids = match permission_group_ids.is_empty() {
+        true => vec![create_upload_permission_group(conn, user_id, organization_id, name).await?],
+        false => permission_group_ids,
+    };
+
+    let datasets_to_permission_groups: Vec<DatasetToPermissionGroup> = permission_group_ids
+        .iter()
+        .map(|permission_group_id| DatasetToPermissionGroup {
+            dataset_id: dataset.id,
+            permission_group_id: *permission_group_id,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            deleted_at: None,
+        })
+        .collect();
+
+    match insert_into(datasets_to_permission_groups::table)
+        .values(&datasets_to_permission_groups)
+        .on_conflict((
+            datasets_to_permission_groups::dataset_id,
+            datasets_to_permission_groups::permission_group_id,
+        ))
+        .do_update()
+        .set((
+            datasets_to_permission_groups::deleted_at.eq(None::<chrono::DateTime<Utc>>),
+            datasets_to_permission_groups::updated_at.eq(Utc::now()),
+        ))
+        .execute(conn)
+        .await
+    {
+        Ok(_) => (),
+        Err(e) => {
+            return Err(anyhow!(
+                "Error adding upload dataset to permission groups: {}",
+                e
+            ))
+        }
+    }
+
+    Ok(())
+}
+
+async fn create_upload_permission_group(
+    conn: &mut AsyncPgConnection,
+    user_id: &Uuid,
+    organization_id: &Uuid,
+    name: &str,
+) -> Result<Uuid> {
+    let permission_group = PermissionGroup {
+        id: Uuid::new_v4(),
+        name: format!("{} upload", name),
+        organization_id: *organization_id,
+        created_by: *user_id,
+        updated_by: *user_id,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        deleted_at: None,
+    };
+
+    if let Err(e) = insert_into(permission_groups::table)
+        .values(&permission_group)
+        .execute(conn)
+        .await
+    {
+        return Err(anyhow!("Error creating upload permission group: {}", e));
+    }
+
+    if let Err(e) = insert_into(permission_groups_to_identities::table)
+        .values(&PermissionGroupToIdentity {
+            permission_group_id: permission_group.id,
+            identity_id: *user_id,
+            identity_type: IdentityType::User,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            deleted_at: None,
+            created_by: *user_id,
+            updated_by: *user_id,
+        })
+        .execute(conn)
+        .await
+    {
+        return Err(anyhow!("Error adding uploader to permission group: {}", e));
+    }
+
+    Ok(permission_group.id)
+}
@@ -0,0 +1,540 @@
+use std::env;
+
+use anyhow::{anyhow, Result};
+use gcp_bigquery_client::{model::query_request::QueryRequest, Client};
+use snowflake_api::SnowflakeApi;
+use sqlx::{Pool, Postgres, QueryBuilder};
+
+use crate::{
+    database::{enums::DataSourceType, models::DataSource},
+    utils::{
+        clients::supabase_vault::read_secret,
+        query_engine::{
+            sql_quoting::quote_string_literal,
+            credentials::{BigqueryCredentials, PostgresCredentials, SnowflakeCredentials},
+            data_source_connections::{
+                get_bigquery_client::get_bigquery_client,
+                get_postgres_connection::get_postgres_connection,
+                get_snowflake_client::get_snowflake_client, ssh_tunneling::kill_ssh_tunnel,
+            },
+        },
+    },
+};
+
+use super::csv_file::{sql_identifier, CsvColumn, CsvColumnType, CsvUpload, CsvValue};
+
+const DEFAULT_UPLOAD_SCHEMA: &str = "buster_uploads";
+const POSTGRES_MAX_PARAMETERS: usize = 65_535;
+const POSTGRES_MAX_BATCH_ROWS: usize = 1_000;
+// Snowflake and BigQuery cap statement length, so inserts are split well below it.
+const MAX_INSERT_STATEMENT_BYTES: usize = 512 * 1024;
+
+/// Where an upload was written in the data source.
+pub struct UploadTarget {
+    pub schema: String,
+    pub table: String,
+    /// The Snowflake database or BigQuery project the schema lives in.
+    pub database_identifier: Option<String>,
+}
+
+#[derive(Clone, Copy)]
+enum Dialect {
+    Postgres,
+    Snowflake,
+    BigQuery,
+}
+
+/// The schema uploads are written to, from `UPLOAD_SCHEMA` (`buster_uploads` by default).
+pub fn upload_schema() -> String {
+    let schema = match env::var("UPLOAD_SCHEMA") {
+        Ok(schema) => sql_identifier(&schema),
+        Err(_) => String::new(),
+    };
+
+    match schema.is_empty() {
+        true => DEFAULT_UPLOAD_SCHEMA.to_string(),
+        false => schema,
+    }
+}
+
+/// Whether CSVs can be uploaded into data sources of this type.
+pub fn supports_uploads(data_source_type: &DataSourceType) -> bool {
+    dialect(data_source_type).is_ok()
+}
+
+/// The warehouse type an upload column is created with, as recorded on its dataset column.
+pub fn warehouse_column_type(
+    data_source_type: &DataSourceType,
+    column_type: CsvColumnType,
+) -> Result<&'static str> {
+    Ok(column_type_name(dialect(data_source_type)?, column_type))
+}
+
+fn column_type_name(dialect: Dialect, column_type: CsvColumnType) -> &'static str {
+    match (dialect, column_type) {
+        (Dialect::Postgres, CsvColumnType::Boolean) => "boolean",
+        (Dialect::Postgres, CsvColumnType::Integer) => "bigint",
+        (Dialect::Postgres, CsvColumnType::Float) => "double precision",
+        (Dialect::Postgres, CsvColumnType::Date) => "date",
+        (Dialect::Postgres, CsvColumnType::Timestamp) => "timestamp",
+        (Dialect::Postgres, CsvColumnType::Text) => "text",
+        (Dialect::Snowflake, CsvColumnType::Boolean) => "BOOLEAN",
+        (Dialect::Snowflake, CsvColumnType::Integer) => "NUMBER(38,0)",
+        (Dialect::Snowflake, CsvColumnType::Float) => "FLOAT",
+        (Dialect::Snowflake, CsvColumnType::Date) => "DATE",
+        (Dialect::Snowflake, CsvColumnType::Timestamp) => "TIMESTAMP_NTZ",
+        (Dialect::Snowflake, CsvColumnType::Text) => "VARCHAR",
+        (Dialect::BigQuery, CsvColumnType::Boolean) => "BOOL",
+        (Dialect::BigQuery, CsvColumnType::Integer) => "INT64",
+        (Dialect::BigQuery, CsvColumnType::Float) => "FLOAT64",
+        (Dialect::BigQuery, CsvColumnType::Date) => "DATE",
+        (Dialect::BigQuery, CsvColumnType::Timestamp) => "DATETIME",
+        (Dialect::BigQuery, CsvColumnType::Text) => "STRING",
+    }
+}
+
+/// Creates `table` in the upload schema of the data source and loads the CSV into it. A table
+/// that was created but could not be filled is dropped again.
+pub async fn write_upload(
+    data_source: &DataSource,
+    table: &str,
+    upload: &CsvUpload,
+) -> Result<UploadTarget> {
+    let credentials = match read_secret(&data_source.secret_id).await {
+        Ok(credentials) => credentials,
+        Err(e) => return Err(anyhow!("Error reading data source credentials: {}", e)),
+    };
+
+    let schema = upload_schema();
+
+    let result = match dialect(&data_source.type_)? {
+        Dialect::Postgres => {
+            let credentials: PostgresCredentials = match serde_json::from_str(&credentials) {
+                Ok(credentials) => credentials,
+                Err(e) => return Err(anyhow!("Error deserializing Postgres credentials: {}", e)),
+            };
+
+            write_postgres(&credentials, &schema, table, upload)
+                .await
+                .map(|_| None)
+        }
+        Dialect::Snowflake => {
+            let credentials: SnowflakeCredentials = match serde_json::from_str(&credentials) {
+                Ok(credentials) => credentials,
+                Err(e) => return Err(anyhow!("Error deserializing Snowflake credentials: {}", e)),
+            };
+
+            write_snowflake(&credentials, &data_source.type_, &schema, table, upload)
+                .await
+                .map(Some)
+        }
+        Dialect::BigQuery => {
+            let credentials: BigqueryCredentials = match serde_json::from_str(&credentials) {
+                Ok(credentials) => credentials,
+                Err(e) => return Err(anyhow!("Error deserializing BigQuery credentials: {}", e)),
+            };
+
+            write_bigquery(&credentials, &data_source.type_, &schema, table, upload)
+                .await
+                .map(Some)
+        }
+    };
+
+    let target = UploadTarget {
+        schema,
+        table: table.to_string(),
+        database_identifier: None,
+    };
+
+    match result {
+        Ok(database_identifier) => Ok(UploadTarget {
+            database_identifier,
+            ..target
+        }),
+        Err(e) => {
+            if let Err(drop_error) = drop_upload(data_source, &target).await {
+                tracing::error!("Error dropping partially written upload: {}", drop_error);
+            }
+            Err(e)
+        }
+    }
+}
+
+/// Drops an uploaded table, if it exists.
+pub async fn drop_upload(data_source: &DataSource, target: &UploadTarget) -> Result<()> {
+    let credentials = match read_secret(&data_source.secret_id).await {
+        Ok(credentials) => credentials,
+        Err(e) => return Err(anyhow!("Error reading data source credentials: {}", e)),
+    };
+
+    match dialect(&data_source.type_)? {
+        Dialect::Postgres => {
+            let credentials: PostgresCredentials = serde_json::from_str(&credentials)?;
+            let (pool, ssh_tunnel, temp_files) = get_postgres_connection(&credentials).await?;
+
+            let result = sqlx::query(&format!(
+                "DROP TABLE IF EXISTS \"{}\".\"{}\"",
+                target.schema, target.table
+            ))
+            .execute(&pool)
+            .await;
+
+            if let (Some(mut ssh_tunnel), Some(temp_files)) = (ssh_tunnel, temp_files) {
+                let _ = kill_ssh_tunnel(&mut ssh_tunnel, temp_files).await;
+            };
+
+            match result {
+                Ok(_) => Ok(()),
+                Err(e) => Err(anyhow!("Error dropping upload table: {}", e)),
+            }
+        }
+        Dialect::Snowflake => {
+            let credentials: SnowflakeCredentials = serde_json::from_str(&credentials)?;
+            let database = snowflake_database(&credentials)?;
+            let client = get_snowflake_client(&credentials).await?;
+
+            snowflake_exec(
+                &client,
+                &format!(
+                    "DROP TABLE IF EXISTS {}.{}.{}",
+                    database, target.schema, target.table
+                ),
+            )
+            .await
+        }
+        Dialect::BigQuery => {
+            let credentials: BigqueryCredentials = serde_json::from_str(&credentials)?;
+            let (client, project_id) = get_bigquery_client(&credentials).await?;
+
+            bigquery_exec(
+                &client,
+                &project_id,
+                format!(
+                    "DROP TABLE IF EXISTS `{}.{}.{}`",
+                    project_id, target.schema, target.table
+                ),
+            )
+            .await
+        }
+    }
+}
+
+fn dialect(data_source_type: &DataSourceType) -> Result<Dialect> {
+    match data_source_type {
+        DataSourceType::Postgres | DataSourceType::Supabase => Ok(Dialect::Postgres),
+        DataSourceType::Snowflake => Ok(Dialect::Snowflake),
+        DataSourceType::BigQuery => Ok(Dialect::BigQuery),
+        data_source_type => Err(anyhow!(
+            "CSV uploads are not supported for {} data sources",
+            data_source_type.to_string()
+        )),
+    }
+}
+
+async fn write_postgres(
+    credentials: &PostgresCredentials,
+    schema: &str,
+    table: &str,
+    upload: &CsvUpload,
+) -> Result<()> {
+    let (pool, ssh_tunnel, temp_files) = get_postgres_connection(credentials).await?;
+
+    let result = insert_postgres(&pool, schema, table, upload).await;
+
+    if let (Some(mut ssh_tunnel), Some(temp_files)) = (ssh_tunnel, temp_files) {
+        let _ = kill_ssh_tunnel(&mut ssh_tunnel, temp_files).await;
+    };
+
+    result
+}
+
+// Postgres loads in one transaction with bound parameters, so a failed upload leaves nothing
+// behind.
+async fn insert_postgres(
+    pool: &Pool<Postgres>,
+    schema: &str,
+    table: &str,
+    upload: &CsvUpload,
+) -> Result<()> {
+    let qualified_table = format!("\"{}\".\"{}\"", schema, table);
+
+    let mut transaction = match pool.begin().await {
+        Ok(transaction) => transaction,
+        Err(e) => return Err(anyhow!("Error starting upload transaction: {}", e)),
+    };
+
+    let statements = [
+        format!("CREATE SCHEMA IF NOT EXISTS \"{}\"", schema),
+        create_table_statement(Dialect::Postgres, &qualified_table, &upload.columns),
+    ];
+
+    for statement in statements {
+        if let Err(e) = sqlx::query(&statement).execute(&mut *transaction).await {
+            return Err(anyhow!("Error creating upload table: {}", e));
+        }
+    }
+
+    let column_list = column_list(Dialect::Postgres, &upload.columns);
+    let batch_rows =
+        (POSTGRES_MAX_PARAMETERS / upload.columns.len().max(1)).clamp(1, POSTGRES_MAX_BATCH_ROWS);
+
+    for batch in upload.rows.chunks(batch_rows) {
+        let mut builder: QueryBuilder<Postgres> = QueryBuilder::new(format!(
+            "INSERT INTO {} ({}) ",
+            qualified_table, column_list
+        ));
+
+        builder.push_values(batch, |mut row_builder, row| {
+            for value in row {
+                match value {
+                    CsvValue::Null => {
+                        row_builder.push("NULL");
+                    }
+                    CsvValue::Boolean(v) => {
+                        row_builder.push_bind(*v);
+                    }
+                    CsvValue::Integer(v) => {
+                        row_builder.push_bind(*v);
+                    }
+                    CsvValue::Float(v) => {
+                        row_builder.push_bind(*v);
+                    }
+                    CsvValue::Date(v) => {
+                        row_builder.push_bind(*v);
+                    }
+                    CsvValue::Timestamp(v) => {
+                        row_builder.push_bind(*v);
+                    }
+                    CsvValue::Text(v) => {
+                        row_builder.push_bind(v.clone());
+                    }
+                }
+            }
+        });
+
+        if let Err(e) = builder.build().execute(&mut *transaction).await {
+            return Err(anyhow!("Error inserting upload rows: {}", e));
+        }
+    }
+
+    match transaction.commit().await {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!("Error committing upload: {}", e)),
+    }
+}
+
+async fn write_snowflake(
+    credentials: &SnowflakeCredentials,
+    data_source_type: &DataSourceType,
+    schema: &str,
+    table: &str,
+    upload: &CsvUpload,
+) -> Result<String> {
+    let database = snowflake_database(credentials)?;
+    let client = get_snowflake_client(credentials).await?;
+
+    let qualified_table = format!("{}.{}.{}", database, schema, table);
+
+    snowflake_exec(
+        &client,
+        &format!("CREATE SCHEMA IF NOT EXISTS {}.{}", database, schema),
+    )
+    .await?;
+    snowflake_exec(
+        &client,
+        &create_table_statement(Dialect::Snowflake, &qualified_table, &upload.columns),
+    )
+    .await?;
+
+    for statement in insert_statements(Dialect::Snowflake, data_source_type, &qualified_table, upload) {
+        snowflake_exec(&client, &statement).await?;
+    }
+
+    Ok(database)
+}
+
+fn snowflake_database(credentials: &SnowflakeCredentials) -> Result<String> {
+    match &credentials.database_id {
+        Some(database) if !database.is_empty() => Ok(database.clone()),
+        _ => Err(anyhow!(
+            "The Snowflake data source has no database to upload into"
+        )),
+    }
+}
+
+async fn snowflake_exec(client: &SnowflakeApi, sql: &str) -> Result<()> {
+    match client.exec(sql).await {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!("Error writing upload to Snowflake: {}", e)),
+    }
+}
+
+async fn write_bigquery(
+    credentials: &BigqueryCredentials,
+    data_source_type: &DataSourceType,
+    schema: &str,
+    table: &str,
+    upload: &CsvUpload,
+) -> Result<String> {
+    let (client, project_id) = get_bigquery_client(credentials).await?;
+
+    let qualified_table = format!("`{}.{}.{}`", project_id, schema, table);
+
+    bigquery_exec(
+        &client,
+        &project_id,
+        format!("CREATE SCHEMA IF NOT EXISTS `{}.{}`", project_id, schema),
+    )
+    .await?;
+    bigquery_exec(
+        &client,
+        &project_id,
+        create_table_statement(Dialect::BigQuery, &qualified_table, &upload.columns),
+    )
+    .await?;
+
+    for statement in insert_statements(Dialect::BigQuery, data_source_type, &qualified_table, upload) {
+        bigquery_exec(&client, &project_id, statement).await?;
+    }
+
+    Ok(project_id)
+}
+
+async fn bigquery_exec(client: &Client, project_id: &str, sql: String) -> Result<()> {
+    let mut query_request = QueryRequest::new(sql);
+    query_request.timeout_ms = Some(120000);
+
+    match client.job().query(project_id, query_request).await {
+        Ok(response) if response.job_complete.unwrap_or(false) => Ok(()),
+        Ok(_) => Err(anyhow!(
+            "BigQuery did not finish writing the upload in time"
+        )),
+        Err(e) => Err(anyhow!("Error writing upload to BigQuery: {}", e)),
+    }
+}
+
+fn create_table_statement(
+    dialect: Dialect,
+    qualified_table: &str,
+    columns: &[CsvColumn],
+) -> String {
+    let definitions = columns
+        .iter()
+        .map(|column| {
+            let column_type = column_type_name(dialect, column.column_type);
+            format!("{} {}", quote_column(dialect, &column.name), column_type)
+        })
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    format!("CREATE TABLE {} ({})", qualified_table, definitions)
+}
+
+// Column names come from the file's headers and may be reserved words, so they are always quoted.
+// Snowflake folds unquoted names to upper case, so its quoted names are upper case too.
+fn quote_column(dialect: Dialect, name: &str) -> String {
+    match dialect {
+        Dialect::Postgres => format!("\"{}\"", name),
+        Dialect::Snowflake => format!("\"{}\"", name.to_uppercase()),
+        Dialect::BigQuery => format!("`{}`", name),
+    }
+}
+
+fn column_list(dialect: Dialect, columns: &[CsvColumn]) -> String {
+    columns
+        .iter()
+        .map(|column| quote_column(dialect, &column.name))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+// Multi-row INSERT statements with literal values, each kept under the statement size limit.
+fn insert_statements(
+    dialect: Dialect,
+    data_source_type: &DataSourceType,
+    qualified_table: &str,
+    upload: &CsvUpload,
+) -> Vec<String> {
+    let prefix = format!(
+        "INSERT INTO {} ({}) VALUES ",
+        qualified_table,
+        column_list(dialect, &upload.columns)
+    );
+
+    let mut statements = Vec::new();
+    let mut statement = prefix.clone();
+    let mut row_count = 0;
+
+    for row in &upload.rows {
+        let values = row
+            .iter()
+            .map(|value| sql_literal(dialect, data_source_type, value))
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        if row_count > 0 && statement.len() + values.len() + 4 > MAX_INSERT_STATEMENT_BYTES {
+            statements.push(std::mem::replace(&mut statement, prefix.clone()));
+            row_count = 0;
+        }
+
+        if row_count > 0 {
+            statement.push_str(", ");
+        }
+        statement.push('(');
+        statement.push_str(&values);
+        statement.push(')');
+        row_count += 1;
+    }
+
+    if row_count > 0 {
+        statements.push(statement);
+    }
+
+    statements
+}
+
+fn sql_literal(dialect: Dialect, data_source_type: &DataSourceType, value: &CsvValue) -> String {
+    match (dialect, value) {
+        (_, CsvValue::Null) => "NULL".to_string(),
+        (_, CsvValue::Boolean(v)) => v.to_string().to_uppercase(),
+        (_, CsvValue::Integer(v)) => v.to_string(),
+        (_, CsvValue::Float(v)) => format!("{:e}", v),
+        (Dialect::BigQuery, CsvValue::Date(v)) => format!("DATE '{}'", v.format("%Y-%m-%d")),
+        (_, CsvValue::Date(v)) => format!("'{}'", v.format("%Y-%m-%d")),
+        (Dialect::BigQuery, CsvValue::Timestamp(v)) => {
+            format!("DATETIME '{}'", v.format("%Y-%m-%d %H:%M:%S%.6f"))
+        }
+        (_, CsvValue::Timestamp(v)) => format!("'{}'", v.format("%Y-%m-%d %H:%M:%S%.6f")),
+        (_, CsvValue::Text(v)) => quote_string_literal(v, data_source_type),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::uploads::csv_file::parse_csv;
+
+    #[test]
+    fn test_insert_statements() {
+        let upload = parse_csv(b"id,Order,signed\n1,it's,2024-01-05\n2,,2024-02-11\n").unwrap();
+
+        let statements = insert_statements(
+            Dialect::BigQuery,
+            &DataSourceType::BigQuery,
+            "`p.s.t`",
+            &upload,
+        );
+
+        assert_eq!(
+            statements,
+            vec!["INSERT INTO `p.s.t` (`id`, `order`, `signed`) VALUES \
+                 (1, 'it\\'s', DATE '2024-01-05'), (2, NULL, DATE '2024-02-11')"
+                .to_string()]
+        );
+
+        assert_eq!(
+            create_table_statement(Dialect::Snowflake, "db.s.t", &upload.columns),
+            "CREATE TABLE db.s.t (\"ID\" NUMBER(38,0), \"ORDER\" VARCHAR, \"SIGNED\" DATE)"
+        );
+    }
+}
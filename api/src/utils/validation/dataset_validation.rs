@@ -1,5 +1,6 @@
 use anyhow::Result;
 use tracing;
+use uuid::Uuid;
 
 use crate::{
     database::models::DataSource,
@@ -8,7 +9,9 @@ use crate::{
             credentials::get_data_source_credentials,
             import_dataset_columns::retrieve_dataset_columns_batch,
         },
+        semantic_layer::semantic_model::{SemanticDataset, SemanticField},
         validation::{
+            expression_validation::validate_expressions,
             types::{ValidationError, ValidationResult},
             type_mapping::{normalize_type, types_compatible},
         },
@@ -50,6 +53,11 @@ pub async fn validate_model(
         }
     };
 
+    let table = match &database {
+        Some(database) => format!("{}.{}.{}", database, schema, model_database_name),
+        None => format!("{}.{}", schema, model_database_name),
+    };
+
     // Collect all tables that need validation (including those referenced in relationships)
     let mut tables_to_validate = vec![(model_database_name.to_string(), schema.to_string())];
     
@@ -105,24 +113,29 @@ pub async fn validate_model(
         }
     }
 
-    // Validate expressions if provided
+    // Validate expressions if provided by compiling them against the data source
     if let Some(exprs) = expressions {
-        for (col_name, expr) in exprs {
-            // Check if expression references valid columns
-            let expr_cols: Vec<&str> = expr
-                .split_whitespace()
-                .filter(|word| !word.chars().all(|c| c.is_ascii_punctuation()))
-                .collect();
+        let dataset = SemanticDataset {
+            id: Uuid::new_v4(),
+            name: model_name.to_string(),
+            table,
+            columns: ds_columns.iter().map(|c| c.name.clone()).collect(),
+            dimensions: exprs
+                .iter()
+                .map(|(col_name, expr)| SemanticField {
+                    name: col_name.to_string(),
+                    expr: expr.to_string(),
+                    agg: None,
+                    description: None,
+                })
+                .collect(),
+            measures: vec![],
+            metrics: vec![],
+            segments: vec![],
+        };
 
-            for expr_col in expr_cols {
-                if !columns.iter().any(|(name, _)| *name == expr_col) {
-                    result.add_error(ValidationError::expression_error(
-                        col_name,
-                        expr,
-                        &format!("Referenced column '{}' not found in model definition", expr_col),
-                    ));
-                }
-            }
+        for error in validate_expressions(data_source, &dataset).await {
+            result.add_error(error);
         }
     }
 
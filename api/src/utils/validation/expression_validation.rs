@@ -0,0 +1,81 @@
+use futures::{stream, StreamExt};
+
+use crate::{
+    database::models::DataSource,
+    utils::{
+        query_engine::query_engine::data_source_query_engine,
+        semantic_layer::{
+            compiler::compile_field_probe,
+            semantic_model::{SemanticDataset, SemanticField},
+        },
+        validation::types::ValidationError,
+    },
+};
+
+// Each probe opens its own connection, so only a few run at once.
+const CONCURRENT_PROBES: usize = 4;
+
+/// Checks the dimensions, measures, metrics and segments of a dataset against the data source.
+///
+/// Every field is compiled into a probe query that selects it without returning rows, so typos
+/// and invalid aggregations fail here instead of when the field is first queried. Returns one
+/// expression error per failing field.
+pub async fn validate_expressions(
+    data_source: &DataSource,
+    dataset: &SemanticDataset,
+) -> Vec<ValidationError> {
+    // If the table itself can't be queried, every probe would fail for the same reason.
+    let table_probe = format!("SELECT 1 AS probe FROM {} WHERE 1 = 0", dataset.table);
+
+    if let Err(e) = data_source_query_engine(data_source, &table_probe, Some(1)).await {
+        return vec![ValidationError::data_source_error(format!(
+            "Unable to query {} to validate expressions: {}",
+            dataset.table, e
+        ))];
+    }
+
+    let fields = dataset
+        .dimensions
+        .iter()
+        .chain(dataset.measures.iter())
+        .chain(dataset.metrics.iter())
+        .chain(dataset.segments.iter());
+
+    let probes: Vec<_> = fields
+        .map(|field| probe_field(data_source, dataset, field))
+        .collect();
+
+    stream::iter(probes)
+        .buffered(CONCURRENT_PROBES)
+        .collect::<Vec<Option<ValidationError>>>()
+        .await
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+async fn probe_field(
+    data_source: &DataSource,
+    dataset: &SemanticDataset,
+    field: &SemanticField,
+) -> Option<ValidationError> {
+    let sql = match compile_field_probe(dataset, &field.name, &data_source.type_) {
+        Ok(sql) => sql,
+        Err(e) => {
+            return Some(ValidationError::expression_error(
+                &field.name,
+                &field.expr,
+                &e.to_string(),
+            ))
+        }
+    };
+
+    match data_source_query_engine(data_source, &sql, Some(1)).await {
+        Ok(_) => None,
+        Err(e) => Some(ValidationError::expression_error(
+            &field.name,
+            &field.expr,
+            &e.to_string(),
+        )),
+    }
+}
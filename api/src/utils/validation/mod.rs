@@ -1,4 +1,5 @@
 pub mod dataset_validation;
+pub mod expression_validation;
 pub mod types;
 pub mod type_mapping;
 
@@ -9,9 +9,9 @@ use colored::*;
 
 use crate::utils::{
     buster_credentials::get_and_validate_buster_credentials, BusterClient,
-    DeployDatasetsColumnsRequest, DeployDatasetsEntityRelationshipsRequest, DeployDatasetsRequest,
-    ValidationError, ValidationErrorType, ValidationResult, BusterConfig, ExclusionManager,
-    find_yml_files, ProgressTracker,
+    DeployDatasetsColumnsRequest, DeployDatasetsEntityRelationshipsRequest, DeployDatasetsOptions,
+    DeployDatasetsRequest, PrunedDataset, ValidationError, ValidationErrorType, ValidationResult, BusterConfig, ExclusionManager,
+    find_yml_files, manifest::DbtProject, yaml_diff_merger::has_conflict_markers, ProgressTracker,
 };
 
 // Use the unified BusterConfig from exclusion.rs instead
@@ -197,6 +197,32 @@ impl DeployProgress {
         println!("   Schema: {}", validation.schema);
     }
 
+    fn log_pruned(&self, pruned: &[PrunedDataset], dry_run: bool, force: bool) {
+        if pruned.is_empty() {
+            println!("\n🧹 No datasets to prune");
+            return;
+        }
+
+        println!("\n🧹 Datasets no longer in the project: {}", pruned.len());
+        for dataset in pruned {
+            println!(
+                "   - {} (Data Source: {}): {}",
+                dataset.name,
+                dataset.data_source_name,
+                pruned_status(dataset, dry_run, force)
+            );
+
+            for reference in &dataset.referenced_by {
+                println!(
+                    "     Used by {} {} ({})",
+                    reference.asset_type,
+                    reference.name.as_deref().unwrap_or("untitled"),
+                    reference.id
+                );
+            }
+        }
+    }
+
     fn log_excluded(&mut self, reason: &str) {
         self.excluded += 1;
         println!("⚠️  Skipping {} ({})", self.current_file, reason);
@@ -254,6 +280,12 @@ impl ProgressTracker for DeployProgress {
 impl ModelFile {
     fn new(yml_path: PathBuf, config: Option<BusterConfig>) -> Result<Self> {
         let yml_content = std::fs::read_to_string(&yml_path)?;
+        if has_conflict_markers(&yml_content) {
+            return Err(anyhow::anyhow!(
+                "{} has unresolved merge conflicts from buster generate",
+                yml_path.display()
+            ));
+        }
         let model: BusterModel = serde_yaml::from_str(&yml_content)?;
 
         Ok(Self {
@@ -264,6 +296,20 @@ impl ModelFile {
         })
     }
 
+    /// Fills in the schema and database dbt resolved for each model, unless the model sets its own.
+    fn apply_dbt_manifest(&mut self, project: &DbtProject) {
+        for model in &mut self.model.models {
+            if let Some(dbt_model) = project.model(&model.name) {
+                if model.schema.is_none() {
+                    model.schema = Some(dbt_model.schema.clone());
+                }
+                if model.database.is_none() {
+                    model.database = dbt_model.database.clone();
+                }
+            }
+        }
+    }
+
     fn check_excluded_tags(
         &self,
         sql_path: &Option<PathBuf>,
@@ -285,9 +331,12 @@ impl ModelFile {
             data_source_name: None,
             schema: None,
             database: None,
+            warehouse: None,
+            role: None,
             exclude_files: None,
             exclude_tags: Some(exclude_tags.to_vec()),
             model_paths: None,
+            environments: None,
         };
         
         let manager = ExclusionManager::new(&temp_config)?;
@@ -499,7 +548,12 @@ impl ModelFile {
         (data_source_name, schema, database)
     }
 
-    fn to_deploy_request(&self, model: &Model, sql_content: String) -> DeployDatasetsRequest {
+    fn to_deploy_request(
+        &self,
+        model: &Model,
+        sql_content: String,
+        env: &str,
+    ) -> DeployDatasetsRequest {
         let mut columns = Vec::new();
 
         // Convert dimensions to columns
@@ -606,7 +660,7 @@ impl ModelFile {
         let request = DeployDatasetsRequest {
             id: None,
             data_source_name,
-            env: "dev".to_string(),
+            env: env.to_string(),
             type_: "view".to_string(),
             name: model.name.clone(),
             model: model.model.clone(),
@@ -880,6 +934,49 @@ impl ModelFile {
     }
 }
 
+fn pruned_status(dataset: &PrunedDataset, dry_run: bool, force: bool) -> &'static str {
+    if dataset.deleted {
+        "removed"
+    } else if dry_run && (force || dataset.referenced_by.is_empty()) {
+        "would be removed"
+    } else {
+        "kept, still referenced (use --force to remove)"
+    }
+}
+
+/// Pruning treats the deployed models as the whole project, so it only runs on a project root.
+/// Deploying a subdirectory would delete every dataset defined outside of it.
+fn check_prune_path(target_path: &Path) -> Result<()> {
+    let target_path = target_path
+        .canonicalize()
+        .unwrap_or_else(|_| target_path.to_path_buf());
+
+    match find_nearest_buster_yml(&target_path) {
+        Some(buster_yml) if buster_yml.parent() == Some(target_path.as_path()) => Ok(()),
+        Some(buster_yml) => Err(anyhow::anyhow!(
+            "Refusing to prune from {}, which is inside the project at {}. Deploy the whole project to prune",
+            target_path.display(),
+            buster_yml.parent().unwrap_or(&target_path).display()
+        )),
+        None => Err(anyhow::anyhow!(
+            "Refusing to prune from {}, which has no buster.yml",
+            target_path.display()
+        )),
+    }
+}
+
+/// Pruning treats the deployed models as the whole project, so a model that failed to load
+/// would be deleted on the server.
+fn check_prune_failures(result: &DeployResult) -> Result<()> {
+    if result.failures.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "Refusing to prune while some models failed to load"
+        ))
+    }
+}
+
 // Add this function before the deploy function
 fn find_nearest_buster_yml(start_dir: &Path) -> Option<PathBuf> {
     let mut current_dir = start_dir.to_path_buf();
@@ -894,7 +991,14 @@ fn find_nearest_buster_yml(start_dir: &Path) -> Option<PathBuf> {
     }
 }
 
-pub async fn deploy(path: Option<&str>, dry_run: bool, recursive: bool) -> Result<()> {
+pub async fn deploy(
+    path: Option<&str>,
+    dry_run: bool,
+    recursive: bool,
+    env: &str,
+    prune: bool,
+    force: bool,
+) -> Result<()> {
     let target_path = PathBuf::from(path.unwrap_or("."));
     let mut progress = DeployProgress::new(0);
     let mut result = DeployResult::default();
@@ -917,8 +1021,13 @@ pub async fn deploy(path: Option<&str>, dry_run: bool, recursive: bool) -> Resul
         target_path
     };
 
-    // Only create client if not in dry-run mode
-    let client = if !dry_run {
+    if prune {
+        check_prune_path(&target_path)?;
+    }
+
+    // Only create client if not in dry-run mode. Pruning needs the server even for a dry run,
+    // since only it knows which datasets would be removed.
+    let client = if !dry_run || prune {
         // Create API client without explicit auth check
         let creds = get_and_validate_buster_credentials().await?;
         Some(BusterClient::new(creds.url, creds.api_key)?)
@@ -933,6 +1042,8 @@ pub async fn deploy(path: Option<&str>, dry_run: bool, recursive: bool) -> Resul
     let config = match ModelFile::get_config(&target_path) {
         Ok(Some(config)) => {
             println!("✅ Found buster.yml configuration");
+            let config = config.for_env(env)?;
+            println!("   - Environment: {}", env);
             if let Some(ds) = &config.data_source_name {
                 println!("   - Default data source: {}", ds);
             }
@@ -954,6 +1065,19 @@ pub async fn deploy(path: Option<&str>, dry_run: bool, recursive: bool) -> Resul
         }
     };
 
+    // A compiled dbt project resolves each model's schema, database and tags
+    let dbt_project = match DbtProject::load(&target_path) {
+        Ok(Some(project)) => {
+            println!("✅ Found dbt manifest at {}", project.manifest_path.display());
+            Some(project)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            println!("⚠️  Error reading dbt manifest: {}", e);
+            None
+        }
+    };
+
     // Find all .yml files
     progress.status = "Discovering model files...".to_string();
     progress.log_progress();
@@ -1022,7 +1146,7 @@ pub async fn deploy(path: Option<&str>, dry_run: bool, recursive: bool) -> Resul
         progress.log_progress();
 
         // Load and validate model
-        let model_file = match ModelFile::new(yml_path.clone(), config.clone()) {
+        let mut model_file = match ModelFile::new(yml_path.clone(), config.clone()) {
             Ok(mf) => mf,
             Err(e) => {
                 progress.log_error(&format!("Failed to load model: {}", e));
@@ -1035,8 +1159,30 @@ pub async fn deploy(path: Option<&str>, dry_run: bool, recursive: bool) -> Resul
             }
         };
 
-        // Check for excluded tags
-        if let Some(ref cfg) = config {
+        // Check for excluded tags, which dbt knows per model without scanning the SQL
+        if let Some(project) = &dbt_project {
+            model_file.apply_dbt_manifest(project);
+            model_file.model.models.retain(|model| {
+                let tags = project
+                    .model(&model.name)
+                    .map(|m| m.tags.as_slice())
+                    .unwrap_or_default();
+                match exclusion_manager.should_exclude_tags(tags) {
+                    (true, tag) => {
+                        progress.log_excluded(&format!(
+                            "Skipping model {} due to excluded tag: {}",
+                            model.name,
+                            tag.unwrap_or_default()
+                        ));
+                        false
+                    }
+                    _ => true,
+                }
+            });
+            if model_file.model.models.is_empty() {
+                continue;
+            }
+        } else if let Some(ref cfg) = config {
             if let Some(ref exclude_tags) = cfg.exclude_tags {
                 if !exclude_tags.is_empty() {
                     match model_file.check_excluded_tags(&model_file.sql_path, exclude_tags) {
@@ -1123,15 +1269,22 @@ pub async fn deploy(path: Option<&str>, dry_run: bool, recursive: bool) -> Resul
             });
 
             // Create deploy request
-            deploy_requests.push(model_file.to_deploy_request(model, sql_content));
+            deploy_requests.push(model_file.to_deploy_request(model, sql_content, env));
         }
 
         progress.log_success();
     }
 
+    if prune {
+        if let Err(e) = check_prune_failures(&result) {
+            progress.log_summary(&result);
+            return Err(e);
+        }
+    }
+
     // Deploy to API if we have valid models and not in dry-run mode
     if !deploy_requests.is_empty() {
-        if dry_run {
+        if dry_run && !prune {
             println!("\n🔍 Dry run mode - validation successful!");
             println!("\n📦 Would deploy {} models:", deploy_requests.len());
             for request in &deploy_requests {
@@ -1152,8 +1305,8 @@ pub async fn deploy(path: Option<&str>, dry_run: bool, recursive: bool) -> Resul
             return Ok(());
         }
 
-        let client =
-            client.expect("BusterClient should be initialized for non-dry-run deployments");
+        let client = client
+            .expect("BusterClient should be initialized for non-dry-run or pruning deployments");
         progress.status = "Deploying models to Buster...".to_string();
         progress.log_progress();
 
@@ -1178,7 +1331,13 @@ pub async fn deploy(path: Option<&str>, dry_run: bool, recursive: bool) -> Resul
             }
         }
 
-        match client.deploy_datasets(deploy_requests).await {
+        let options = DeployDatasetsOptions {
+            prune,
+            dry_run,
+            force,
+        };
+
+        match client.deploy_datasets(deploy_requests, &options).await {
             Ok(response) => {
                 let mut has_validation_errors = false;
 
@@ -1215,12 +1374,16 @@ pub async fn deploy(path: Option<&str>, dry_run: bool, recursive: bool) -> Resul
                     }
                 }
 
+                if prune {
+                    progress.log_pruned(&response.pruned, dry_run, force);
+                }
+
                 if has_validation_errors {
                     println!("\n❌ Deployment failed due to validation errors!");
                     println!("\n💡 Troubleshooting:");
                     println!("1. Check data source:");
                     println!("   - Verify '{}' exists in Buster", data_source_name);
-                    println!("   - Confirm it has env='dev'");
+                    println!("   - Confirm it has env='{}'", env);
                     println!("   - Check your access permissions");
                     println!("2. Check model definitions:");
                     println!("   - Validate SQL syntax");
@@ -1233,6 +1396,11 @@ pub async fn deploy(path: Option<&str>, dry_run: bool, recursive: bool) -> Resul
                     ));
                 }
 
+                if dry_run {
+                    println!("\n🔍 Dry run mode - validation successful, nothing was changed!");
+                    return Ok(());
+                }
+
                 println!("\n✅ All models deployed successfully!");
             }
             Err(e) => {
@@ -1241,7 +1409,7 @@ pub async fn deploy(path: Option<&str>, dry_run: bool, recursive: bool) -> Resul
                 println!("\n💡 Troubleshooting:");
                 println!("1. Check data source:");
                 println!("   - Verify '{}' exists in Buster", data_source_name);
-                println!("   - Confirm it has env='dev'");
+                println!("   - Confirm it has env='{}'", env);
                 println!("   - Check your access permissions");
                 println!("2. Check model definitions:");
                 println!("   - Validate SQL syntax");
@@ -1330,6 +1498,7 @@ fn find_yml_files_recursively(dir: &Path, config: Option<&BusterConfig>, progres
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::DatasetReference;
     use anyhow::Result;
     use std::fs;
     use tempfile::TempDir;
@@ -1386,7 +1555,7 @@ mod tests {
         create_test_yaml(temp_dir.path(), "test_model.yml", model_yml).await?;
 
         // Test dry run
-        let result = deploy(Some(temp_dir.path().to_str().unwrap()), true, false).await;
+        let result = deploy(Some(temp_dir.path().to_str().unwrap()), true, false, "dev", false, false).await;
         assert!(result.is_ok());
 
         Ok(())
@@ -1430,7 +1599,7 @@ mod tests {
         create_test_yaml(temp_dir.path(), "test_model.yml", model_yml).await?;
 
         // Test dry run
-        let result = deploy(Some(temp_dir.path().to_str().unwrap()), true, false).await;
+        let result = deploy(Some(temp_dir.path().to_str().unwrap()), true, false, "dev", false, false).await;
         assert!(result.is_ok());
 
         Ok(())
@@ -1474,7 +1643,7 @@ mod tests {
         create_test_yaml(temp_dir.path(), "test_model.yml", model_yml).await?;
 
         // Test dry run - should fail due to data source mismatch
-        let result = deploy(Some(temp_dir.path().to_str().unwrap()), true, false).await;
+        let result = deploy(Some(temp_dir.path().to_str().unwrap()), true, false, "dev", false, false).await;
         assert!(result.is_err());
 
         Ok(())
@@ -1509,7 +1678,7 @@ mod tests {
         create_test_yaml(temp_dir.path(), "test_model.yml", model_yml).await?;
 
         // Test dry run - should fail due to missing project
-        let result = deploy(Some(temp_dir.path().to_str().unwrap()), true, false).await;
+        let result = deploy(Some(temp_dir.path().to_str().unwrap()), true, false, "dev", false, false).await;
         assert!(result.is_err());
 
         Ok(())
@@ -1557,7 +1726,7 @@ mod tests {
         }
 
         // Test dry run
-        let result = deploy(Some(temp_dir.path().to_str().unwrap()), true, false).await;
+        let result = deploy(Some(temp_dir.path().to_str().unwrap()), true, false, "dev", false, false).await;
         assert!(result.is_ok());
 
         Ok(())
@@ -1579,7 +1748,7 @@ mod tests {
         create_test_yaml(temp_dir.path(), "invalid_model.yml", invalid_yml).await?;
 
         // Test dry run - should fail due to invalid YAML
-        let result = deploy(Some(temp_dir.path().to_str().unwrap()), true, false).await;
+        let result = deploy(Some(temp_dir.path().to_str().unwrap()), true, false, "dev", false, false).await;
         assert!(result.is_err());
 
         Ok(())
@@ -1631,7 +1800,7 @@ mod tests {
         create_test_yaml(temp_dir.path(), "test_model.yml", model_yml).await?;
 
         // Test dry run - should succeed because actual_model exists
-        let result = deploy(Some(temp_dir.path().to_str().unwrap()), true, false).await;
+        let result = deploy(Some(temp_dir.path().to_str().unwrap()), true, false, "dev", false, false).await;
         assert!(result.is_ok());
 
         Ok(())
@@ -1666,10 +1835,93 @@ mod tests {
         create_test_yaml(temp_dir.path(), "test_model.yml", model_yml).await?;
 
         // Test dry run - should fail because referenced model doesn't exist
-        let result = deploy(Some(temp_dir.path().to_str().unwrap()), true, false).await;
+        let result = deploy(Some(temp_dir.path().to_str().unwrap()), true, false, "dev", false, false).await;
         assert!(result.is_err());
 
         Ok(())
     }
-}
 
+    fn pruned_dataset(deleted: bool, references: usize) -> PrunedDataset {
+        PrunedDataset {
+            data_source_name: "test_source".to_string(),
+            name: "old_model".to_string(),
+            deleted,
+            referenced_by: (0..references)
+                .map(|_| DatasetReference {
+                    id: uuid::Uuid::new_v4(),
+                    name: Some("Revenue".to_string()),
+                    asset_type: "thread".to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_pruned_status() {
+        assert_eq!(
+            pruned_status(&pruned_dataset(true, 0), false, false),
+            "removed"
+        );
+        assert_eq!(
+            pruned_status(&pruned_dataset(true, 2), false, true),
+            "removed"
+        );
+
+        // A dry run reports what a real run with the same flags would do.
+        assert_eq!(
+            pruned_status(&pruned_dataset(false, 0), true, false),
+            "would be removed"
+        );
+        assert_eq!(
+            pruned_status(&pruned_dataset(false, 1), true, true),
+            "would be removed"
+        );
+        assert_eq!(
+            pruned_status(&pruned_dataset(false, 1), true, false),
+            "kept, still referenced (use --force to remove)"
+        );
+        assert_eq!(
+            pruned_status(&pruned_dataset(false, 1), false, false),
+            "kept, still referenced (use --force to remove)"
+        );
+
+        let progress = DeployProgress::new(0);
+        progress.log_pruned(&[pruned_dataset(false, 1)], true, false);
+        progress.log_pruned(&[], false, false);
+    }
+
+    #[test]
+    fn test_check_prune_failures() {
+        let mut result = DeployResult::default();
+        assert!(check_prune_failures(&result).is_ok());
+
+        result.failures.push((
+            "broken.yml".to_string(),
+            "broken".to_string(),
+            vec!["invalid yaml".to_string()],
+        ));
+        assert!(check_prune_failures(&result).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_check_prune_path() -> Result<()> {
+        let temp_dir = setup_test_dir().await?;
+        let buster_yml = "data_source_name: \"test_source\"";
+        create_test_yaml(temp_dir.path(), "buster.yml", buster_yml).await?;
+
+        let models_dir = temp_dir.path().join("models");
+        fs::create_dir_all(&models_dir)?;
+
+        assert!(check_prune_path(temp_dir.path()).is_ok());
+        assert!(check_prune_path(&models_dir).is_err());
+
+        // A nested project is its own root.
+        create_test_yaml(&models_dir, "buster.yml", buster_yml).await?;
+        assert!(check_prune_path(&models_dir).is_ok());
+
+        let outside = setup_test_dir().await?;
+        assert!(check_prune_path(outside.path()).is_err());
+
+        Ok(())
+    }
+}
@@ -1,7 +1,10 @@
 use crate::utils::{
-    buster_credentials::get_and_validate_buster_credentials, file_finder::find_sql_files,
-    yaml_diff_merger::YamlDiffMerger, BusterClient, BusterConfig, ExclusionManager,
-    GenerateApiRequest, ProgressTracker,
+    buster_credentials::get_and_validate_buster_credentials,
+    file_finder::find_sql_files,
+    manifest::{DbtModel, DbtProject},
+    yaml_diff_merger::{ConflictResolution, YamlDiffMerger},
+    BusterClient, BusterConfig, ExclusionManager, GenerateApiRequest, GenerateApiResponse,
+    ProgressTracker,
 };
 use anyhow::Result;
 use colored::*;
@@ -22,6 +25,8 @@ pub struct GenerateCommand {
     database: Option<String>,
     config: BusterConfig,
     maintain_directory_structure: bool,
+    project_root: PathBuf,
+    conflict_resolution: ConflictResolution,
 }
 
 #[derive(Debug)]
@@ -29,6 +34,7 @@ struct ModelName {
     name: String,
     source_file: PathBuf,
     is_from_alias: bool,
+    dbt_model: Option<DbtModel>, // Set when the model was read from a dbt manifest
 }
 
 #[derive(Debug)]
@@ -196,19 +202,24 @@ impl GenerateCommand {
             data_source_name: data_source_name.clone(),
             schema: schema.clone(),
             database: database.clone(),
+            warehouse: None,
+            role: None,
             exclude_files: None,
             exclude_tags: None,
             model_paths: None,
+            environments: None,
         };
 
         Self {
             source_path,
+            project_root: destination_path.clone(),
             destination_path,
             data_source_name,
             schema,
             database,
             config,
             maintain_directory_structure: true, // Default to maintaining directory structure
+            conflict_resolution: ConflictResolution::default(),
         }
     }
 
@@ -233,9 +244,27 @@ impl GenerateCommand {
             database: self.database.clone(),
             config, // Use the loaded config
             maintain_directory_structure: self.maintain_directory_structure,
+            project_root: self.project_root.clone(),
+            conflict_resolution: self.conflict_resolution,
         };
 
-        let model_names = cmd.process_sql_files(&mut progress).await?;
+        // A compiled dbt project already knows its models, so prefer its manifest over
+        // guessing from SQL files.
+        let dbt_project = match DbtProject::load(&self.source_path)? {
+            Some(project) => Some(project),
+            None => DbtProject::load(&self.destination_path)?,
+        };
+
+        let model_names = match &dbt_project {
+            Some(project) => {
+                println!("✅ Found dbt manifest at {}", project.manifest_path.display());
+                if project.has_catalog {
+                    println!("✅ Found dbt catalog, using it for relation names and column comments");
+                }
+                cmd.process_dbt_models(project, &mut progress)?
+            }
+            None => cmd.process_sql_files(&mut progress).await?,
+        };
 
         // Print results
         println!("\n✅ Successfully processed all files");
@@ -244,7 +273,9 @@ impl GenerateCommand {
             println!(
                 "  - {} ({})",
                 model.name,
-                if model.is_from_alias {
+                if model.dbt_model.is_some() {
+                    "from dbt manifest"
+                } else if model.is_from_alias {
                     "from alias"
                 } else {
                     "from filename"
@@ -259,110 +290,170 @@ impl GenerateCommand {
         let creds = get_and_validate_buster_credentials().await?;
         let client = BusterClient::new(creds.url, creds.api_key)?;
 
-        // Prepare API request
-        let request = GenerateApiRequest {
-            data_source_name: cmd
-                .config
-                .data_source_name
-                .expect("data_source_name is required"),
-            schema: cmd.config.schema.expect("schema is required"),
-            database: cmd.config.database,
-            model_names: model_names.iter().map(|m| m.name.clone()).collect(),
-        };
+        let data_source_name = cmd
+            .config
+            .data_source_name
+            .clone()
+            .expect("data_source_name is required");
+        let default_schema = cmd.config.schema.clone().expect("schema is required");
+        let default_database = cmd.config.database.clone();
+
+        // dbt models can live in different schemas and databases, so the API is called once
+        // for each location.
+        let mut model_groups: HashMap<(String, Option<String>), Vec<String>> = HashMap::new();
+        for model in &model_names {
+            let location = match &model.dbt_model {
+                Some(dbt_model) => (
+                    dbt_model.schema.clone(),
+                    dbt_model.database.clone().or_else(|| default_database.clone()),
+                ),
+                None => (default_schema.clone(), default_database.clone()),
+            };
+            model_groups
+                .entry(location)
+                .or_default()
+                .push(model.name.clone());
+        }
 
-        // Make API call
+        // Make API calls
         progress.status = "Generating YAML files...".to_string();
         progress.log_progress();
 
-        match client.generate_datasets(request).await {
-            Ok(response) => {
-                // Process each model's YAML
-                for (model_name, yml_content) in response.yml_contents {
-                    // Find the source file for this model
-                    let source_file = model_names
-                        .iter()
-                        .find(|m| m.name == model_name)
-                        .map(|m| m.source_file.clone())
-                        .unwrap_or_else(|| {
-                            self.destination_path.join(format!("{}.sql", model_name))
-                        });
+        let mut response = GenerateApiResponse {
+            yml_contents: HashMap::new(),
+            errors: HashMap::new(),
+        };
+
+        for ((schema, database), names) in model_groups {
+            let request = GenerateApiRequest {
+                data_source_name: data_source_name.clone(),
+                schema,
+                database,
+                model_names: names,
+            };
 
-                    // Determine output path based on source file
-                    let file_path = self.get_output_path(&model_name, &source_file);
+            match client.generate_datasets(request).await {
+                Ok(group_response) => {
+                    response.yml_contents.extend(group_response.yml_contents);
+                    response.errors.extend(group_response.errors);
+                }
+                Err(e) => {
+                    progress.log_error(&format!("API call failed: {}", e));
+                    return Err(anyhow::anyhow!("Failed to generate YAML files: {}", e));
+                }
+            }
+        }
 
-                    // Create parent directories if they don't exist
-                    if let Some(parent) = file_path.parent() {
-                        fs::create_dir_all(parent)?;
+        // Process each model's YAML
+        for (model_name, yml_content) in response.yml_contents {
+            let model = model_names.iter().find(|m| m.name == model_name);
+
+            // Find the source file for this model
+            let source_file = model
+                .map(|m| m.source_file.clone())
+                .unwrap_or_else(|| {
+                    self.destination_path.join(format!("{}.sql", model_name))
+                });
+
+            // Descriptions, entities and locations from dbt replace the generated ones
+            let yml_content = match model.and_then(|m| m.dbt_model.as_ref()) {
+                Some(dbt_model) => match dbt_model.seed_yaml(
+                    &yml_content,
+                    Some(&default_schema),
+                    default_database.as_deref(),
+                ) {
+                    Ok(seeded) => seeded,
+                    Err(e) => {
+                        progress.log_warning(&format!(
+                            "Failed to apply dbt metadata to {}: {}",
+                            model_name, e
+                        ));
+                        yml_content
                     }
+                },
+                None => yml_content,
+            };
 
-                    if file_path.exists() {
-                        // Use YAML diff merger for existing files
-                        let merger = YamlDiffMerger::new(file_path.clone(), yml_content);
-
-                        match merger.compute_diff() {
-                            Ok(diff_result) => {
-                                // Preview changes
-                                println!("\nProcessing model: {}", model_name);
-                                merger.preview_changes(&diff_result);
-
-                                // Apply changes
-                                match merger.apply_changes(&diff_result) {
-                                    Ok(_) => {
-                                        progress.log_success();
-                                        println!("✅ Updated {}", file_path.display());
-                                    }
-                                    Err(e) => {
-                                        progress.log_error(&format!(
-                                            "Failed to update {}: {}",
-                                            file_path.display(),
-                                            e
-                                        ));
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                progress.log_error(&format!(
-                                    "Failed to compute diff for {}: {}",
-                                    file_path.display(),
-                                    e
-                                ));
-                            }
-                        }
-                    } else {
-                        // Create new file for models that don't exist yet
-                        match fs::write(&file_path, yml_content) {
+            // Determine output path based on source file
+            let file_path = self.get_output_path(&model_name, &source_file);
+
+            // Create parent directories if they don't exist
+            if let Some(parent) = file_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            if file_path.exists() {
+                // Use YAML diff merger for existing files
+                let merger = YamlDiffMerger::new(file_path.clone(), yml_content)
+                    .with_base_snapshot(&self.project_root)
+                    .with_conflict_resolution(self.conflict_resolution);
+
+                match merger.compute_diff() {
+                    Ok(diff_result) => {
+                        // Preview changes
+                        println!("\nProcessing model: {}", model_name);
+                        merger.preview_changes(&diff_result);
+
+                        // Apply changes
+                        match merger.apply_changes(&diff_result) {
                             Ok(_) => {
                                 progress.log_success();
-                                println!("✅ Created new file {}", file_path.display());
+                                println!("✅ Updated {}", file_path.display());
                             }
                             Err(e) => {
                                 progress.log_error(&format!(
-                                    "Failed to write {}: {}",
+                                    "Failed to update {}: {}",
                                     file_path.display(),
                                     e
                                 ));
                             }
                         }
                     }
+                    Err(e) => {
+                        progress.log_error(&format!(
+                            "Failed to compute diff for {}: {}",
+                            file_path.display(),
+                            e
+                        ));
+                    }
                 }
-
-                // Report any errors
-                if !response.errors.is_empty() {
-                    println!("\n⚠️  Some models had errors:");
-                    for (model_name, error) in response.errors {
-                        println!("❌ {}: {}", model_name, error.message);
-                        if let Some(error_type) = error.error_type {
-                            println!("   Error type: {}", error_type);
-                        }
-                        if let Some(context) = error.context {
-                            println!("   Context: {}", context);
+            } else {
+                // Create new file for models that don't exist yet
+                match fs::write(&file_path, &yml_content) {
+                    Ok(_) => {
+                        progress.log_success();
+                        println!("✅ Created new file {}", file_path.display());
+
+                        // Later runs merge against what was generated
+                        if let Err(e) = YamlDiffMerger::new(file_path.clone(), yml_content)
+                            .with_base_snapshot(&self.project_root)
+                            .save_base_snapshot()
+                        {
+                            progress.log_warning(&format!("Failed to save base snapshot: {}", e));
                         }
                     }
+                    Err(e) => {
+                        progress.log_error(&format!(
+                            "Failed to write {}: {}",
+                            file_path.display(),
+                            e
+                        ));
+                    }
                 }
             }
-            Err(e) => {
-                progress.log_error(&format!("API call failed: {}", e));
-                return Err(anyhow::anyhow!("Failed to generate YAML files: {}", e));
+        }
+
+        // Report any errors
+        if !response.errors.is_empty() {
+            println!("\n⚠️  Some models had errors:");
+            for (model_name, error) in response.errors {
+                println!("❌ {}: {}", model_name, error.message);
+                if let Some(error_type) = error.error_type {
+                    println!("   Error type: {}", error_type);
+                }
+                if let Some(context) = error.context {
+                    println!("   Context: {}", context);
+                }
             }
         }
 
@@ -461,9 +552,12 @@ impl GenerateCommand {
                 data_source_name: Some(data_source_name),
                 schema: Some(schema),
                 database,
+                warehouse: None,
+                role: None,
                 exclude_files: None,
                 exclude_tags: None,
                 model_paths,
+                environments: None,
             };
 
             // Write the config to file
@@ -475,6 +569,65 @@ impl GenerateCommand {
         }
     }
 
+    fn process_dbt_models(
+        &self,
+        project: &DbtProject,
+        progress: &mut GenerateProgress,
+    ) -> Result<Vec<ModelName>> {
+        let mut names = Vec::new();
+        let exclusion_manager = ExclusionManager::new(&self.config)?;
+
+        progress.total_files = project.models.len();
+        progress.status = format!("Found {} models in dbt manifest", project.models.len());
+        progress.log_progress();
+
+        for model in &project.models {
+            progress.processed += 1;
+
+            let relative_path = model.original_file_path.to_string_lossy().into_owned();
+            progress.current_file = relative_path.clone();
+
+            // Patterns are matched against the path in the dbt project and tags come from dbt
+            let (excluded_by_pattern, pattern) =
+                exclusion_manager.should_exclude_file(&model.original_file_path, Path::new(""));
+            if excluded_by_pattern {
+                progress.log_excluded_file(&relative_path, &pattern.unwrap_or_default());
+                continue;
+            }
+
+            let (excluded_by_tags, tag) = exclusion_manager.should_exclude_tags(&model.tags);
+            if excluded_by_tags {
+                progress.log_excluded_tag(&relative_path, &tag.unwrap_or_default());
+                continue;
+            }
+
+            progress.log_info(&format!(
+                "Found model: {} ({}{})",
+                model.name,
+                model
+                    .database
+                    .as_ref()
+                    .map(|d| format!("{}.", d))
+                    .unwrap_or_default(),
+                model.schema
+            ));
+
+            let is_from_alias = model.original_file_path.file_stem().and_then(|s| s.to_str())
+                != Some(model.name.as_str());
+
+            names.push(ModelName {
+                name: model.name.clone(),
+                source_file: project.root.join(&model.original_file_path),
+                is_from_alias,
+                dbt_model: Some(model.clone()),
+            });
+        }
+
+        progress.log_summary();
+
+        Ok(names)
+    }
+
     async fn process_sql_files(&self, progress: &mut GenerateProgress) -> Result<Vec<ModelName>> {
         let mut names = Vec::new();
         let mut seen_names: HashMap<String, PathBuf> = HashMap::new();
@@ -669,6 +822,7 @@ impl GenerateCommand {
                 name: alias,
                 source_file: path.clone(),
                 is_from_alias: true,
+                dbt_model: None,
             })
         } else {
             // Use filename without extension
@@ -685,6 +839,7 @@ impl GenerateCommand {
                 name,
                 source_file: path.clone(),
                 is_from_alias: false,
+                dbt_model: None,
             })
         }
     }
@@ -748,6 +903,7 @@ pub async fn generate(
     schema: Option<String>,
     database: Option<String>,
     flat_structure: bool,
+    interactive: bool,
 ) -> Result<()> {
     let source = PathBuf::from(source_path.unwrap_or("."));
     let destination = PathBuf::from(destination_path.unwrap_or("."));
@@ -783,5 +939,13 @@ pub async fn generate(
     // Set directory structure preference
     cmd.maintain_directory_structure = !flat_structure;
 
+    // Base snapshots live with the project, next to buster.yml
+    cmd.project_root = buster_yml_path.unwrap().parent().unwrap().to_path_buf();
+    cmd.conflict_resolution = if interactive {
+        ConflictResolution::Interactive
+    } else {
+        ConflictResolution::Markers
+    };
+
     cmd.execute().await
 }
@@ -0,0 +1,139 @@
+use crate::utils::{
+    manifest::DbtProject, metricflow::import_metricflow as convert_metricflow,
+    yaml_diff_merger::YamlDiffMerger,
+};
+use anyhow::{anyhow, Result};
+use colored::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn find_nearest_buster_yml(start_dir: &Path) -> Option<PathBuf> {
+    let mut current_dir = start_dir.to_path_buf();
+    loop {
+        let buster_yml = current_dir.join("buster.yml");
+        if buster_yml.exists() {
+            return Some(buster_yml);
+        }
+        if !current_dir.pop() {
+            return None;
+        }
+    }
+}
+
+/// Converts the MetricFlow semantic models and metrics under `path` into Buster model files.
+///
+/// Existing model files are merged rather than overwritten, so the import can be re-run after
+/// the MetricFlow definitions change without losing local edits.
+pub async fn import_metricflow(path: Option<&str>, destination_path: Option<&str>) -> Result<()> {
+    let source = PathBuf::from(path.unwrap_or("."));
+
+    // Default to the project root, the same place `buster generate` writes to.
+    let destination = match destination_path {
+        Some(destination) => PathBuf::from(destination),
+        None => match find_nearest_buster_yml(&std::env::current_dir()?) {
+            Some(buster_yml) => buster_yml.parent().unwrap().to_path_buf(),
+            None => PathBuf::from("."),
+        },
+    };
+
+    // Model refs resolve to relation names through the manifest when there is one.
+    let dbt_project = match DbtProject::load(&source) {
+        Ok(project) => project,
+        Err(e) => {
+            println!("{}", format!("⚠️  Ignoring dbt manifest: {}", e).yellow());
+            None
+        }
+    };
+
+    let import = convert_metricflow(&source, dbt_project.as_ref())?;
+    if import.models.is_empty() {
+        return Err(anyhow!(
+            "No MetricFlow semantic models found in {}",
+            source.display()
+        ));
+    }
+
+    println!(
+        "Converting {} MetricFlow semantic models",
+        import.models.len()
+    );
+
+    fs::create_dir_all(&destination)?;
+    let mut written = 0;
+    let mut failed = 0;
+
+    for model in &import.models {
+        let model_name = &model.models[0].name;
+
+        let yml_content = match serde_yaml::to_string(model) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("❌ Failed to serialize {}: {}", model_name, e);
+                failed += 1;
+                continue;
+            }
+        };
+
+        let file_path = destination.join(format!("{}.yml", model_name));
+
+        if file_path.exists() {
+            let merger = YamlDiffMerger::new(file_path.clone(), yml_content)
+                .with_base_snapshot(&destination);
+
+            match merger.compute_diff() {
+                Ok(diff_result) => {
+                    println!("\nProcessing model: {}", model_name);
+                    merger.preview_changes(&diff_result);
+
+                    match merger.apply_changes(&diff_result) {
+                        Ok(_) => {
+                            written += 1;
+                            println!("✅ Updated {}", file_path.display());
+                        }
+                        Err(e) => {
+                            failed += 1;
+                            eprintln!("❌ Failed to update {}: {}", file_path.display(), e);
+                        }
+                    }
+                }
+                Err(e) => {
+                    failed += 1;
+                    eprintln!(
+                        "❌ Failed to compute diff for {}: {}",
+                        file_path.display(),
+                        e
+                    );
+                }
+            }
+        } else {
+            match fs::write(&file_path, &yml_content) {
+                Ok(_) => {
+                    written += 1;
+                    println!("✅ Created new file {}", file_path.display());
+
+                    if let Err(e) = YamlDiffMerger::new(file_path.clone(), yml_content)
+                        .with_base_snapshot(&destination)
+                        .save_base_snapshot()
+                    {
+                        println!("⚠️  Failed to save base snapshot: {}", e);
+                    }
+                }
+                Err(e) => {
+                    failed += 1;
+                    eprintln!("❌ Failed to write {}: {}", file_path.display(), e);
+                }
+            }
+        }
+    }
+
+    if !import.untranslated.is_empty() {
+        println!("\n⚠️  Some MetricFlow definitions couldn't be translated:");
+        for item in &import.untranslated {
+            println!("  - {}", item);
+        }
+    }
+
+    println!("\n📊 Imported {} models ({} failed)", written, failed);
+
+    Ok(())
+}
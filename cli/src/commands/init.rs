@@ -3,25 +3,30 @@ use colored::*;
 use indicatif::{ProgressBar, ProgressStyle};
 use inquire::{validator::Validation, Confirm, Password, Select, Text};
 use regex::Regex;
-use serde::{Deserialize, Serialize};
 use serde_yaml;
-use std::error::Error;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use crate::utils::{
     buster_credentials::get_and_validate_buster_credentials,
-    profiles::{BigqueryCredentials, Credential, PostgresCredentials},
+    profiles::{
+        get_dbt_profile_target, BigqueryCredentials, Credential, DatabricksCredentials, DbtTarget,
+        MySqlCredentials, PostgresCredentials, SnowflakeCredentials, SqlServerCredentials,
+    },
     BusterClient, BusterConfig, PostDataSourcesRequest,
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 enum DatabaseType {
     Redshift,
     Postgres,
     BigQuery,
     Snowflake,
+    Databricks,
+    MySql,
+    SqlServer,
+    Supabase,
 }
 
 impl std::fmt::Display for DatabaseType {
@@ -31,18 +36,38 @@ impl std::fmt::Display for DatabaseType {
             DatabaseType::Postgres => write!(f, "Postgres"),
             DatabaseType::BigQuery => write!(f, "BigQuery"),
             DatabaseType::Snowflake => write!(f, "Snowflake"),
+            DatabaseType::Databricks => write!(f, "Databricks"),
+            DatabaseType::MySql => write!(f, "MySQL / MariaDB"),
+            DatabaseType::SqlServer => write!(f, "SQL Server"),
+            DatabaseType::Supabase => write!(f, "Supabase"),
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct RedshiftCredentials {
-    pub host: String,
-    pub port: u16,
-    pub username: String,
-    pub password: String,
-    pub database: Option<String>,
-    pub schemas: Option<Vec<String>>,
+impl DatabaseType {
+    /// The database type a dbt target's adapter connects to.
+    fn from_dbt_target(target: &DbtTarget) -> Option<Self> {
+        match target.adapter.as_str() {
+            "redshift" => Some(DatabaseType::Redshift),
+            "postgres" => {
+                let is_supabase = target
+                    .get(&["host"])
+                    .map(|host| host.contains("supabase"))
+                    .unwrap_or(false);
+                if is_supabase {
+                    Some(DatabaseType::Supabase)
+                } else {
+                    Some(DatabaseType::Postgres)
+                }
+            }
+            "bigquery" => Some(DatabaseType::BigQuery),
+            "snowflake" => Some(DatabaseType::Snowflake),
+            "databricks" => Some(DatabaseType::Databricks),
+            "mysql" | "mariadb" => Some(DatabaseType::MySql),
+            "sqlserver" => Some(DatabaseType::SqlServer),
+            _ => None,
+        }
+    }
 }
 
 pub async fn init(destination_path: Option<&str>) -> Result<()> {
@@ -82,15 +107,7 @@ pub async fn init(destination_path: Option<&str>) -> Result<()> {
     };
 
     // Check for Buster credentials with progress indicator
-    let spinner = ProgressBar::new_spinner();
-    spinner.set_style(
-        ProgressStyle::default_spinner()
-            .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ ")
-            .template("{spinner:.green} {msg}")
-            .unwrap(),
-    );
-    spinner.set_message("Checking for Buster credentials...");
-    spinner.enable_steady_tick(Duration::from_millis(100));
+    let spinner = new_spinner("Checking for Buster credentials...");
 
     let buster_creds = match get_and_validate_buster_credentials().await {
         Ok(creds) => {
@@ -104,35 +121,82 @@ pub async fn init(destination_path: Option<&str>) -> Result<()> {
         }
     };
 
+    // Use the dbt project's connection, when there is one, for the defaults
+    let dbt_target = match get_dbt_profile_target(&dest_path).await {
+        Ok(target) => target,
+        Err(e) => {
+            println!(
+                "{}",
+                format!("⚠️  Couldn't read your dbt profile: {}", e).yellow()
+            );
+            None
+        }
+    };
+    let detected = dbt_target
+        .as_ref()
+        .and_then(|target| DatabaseType::from_dbt_target(target).map(|db_type| (target, db_type)));
+
+    if let Some((target, db_type)) = &detected {
+        println!(
+            "Found dbt profile '{}' (target '{}', {}). Its connection details will be used as defaults.",
+            target.profile.cyan(),
+            target.target.cyan(),
+            db_type
+        );
+    }
+
     // Select database type
     let db_types = vec![
         DatabaseType::Redshift,
         DatabaseType::Postgres,
         DatabaseType::BigQuery,
         DatabaseType::Snowflake,
+        DatabaseType::Databricks,
+        DatabaseType::MySql,
+        DatabaseType::SqlServer,
+        DatabaseType::Supabase,
     ];
 
-    let db_type = Select::new("Select your database type:", db_types).prompt()?;
+    let starting_cursor = detected
+        .as_ref()
+        .and_then(|(_, db_type)| db_types.iter().position(|t| t == db_type))
+        .unwrap_or(0);
+
+    let db_type = Select::new("Select your database type:", db_types)
+        .with_starting_cursor(starting_cursor)
+        .prompt()?;
 
     println!("You selected: {}", db_type.to_string().cyan());
 
+    // Only prefill from the dbt profile when it's for the selected database
+    let prefill = detected
+        .filter(|(_, detected_type)| *detected_type == db_type)
+        .map(|(target, _)| target);
+
     match db_type {
         DatabaseType::Redshift => {
-            setup_redshift(buster_creds.url, buster_creds.api_key, &config_path, should_create_config).await
+            setup_redshift(buster_creds.url, buster_creds.api_key, &config_path, should_create_config, prefill).await
         }
         DatabaseType::Postgres => {
-            setup_postgres(buster_creds.url, buster_creds.api_key, &config_path, should_create_config).await
+            setup_postgres(buster_creds.url, buster_creds.api_key, &config_path, should_create_config, prefill).await
         }
         DatabaseType::BigQuery => {
-            setup_bigquery(buster_creds.url, buster_creds.api_key, &config_path, should_create_config).await
+            setup_bigquery(buster_creds.url, buster_creds.api_key, &config_path, should_create_config, prefill).await
         }
-        _ => {
-            println!(
-                "{}",
-                format!("{} support is coming soon!", db_type).yellow()
-            );
-            println!("Currently, only Redshift, Postgres, and BigQuery are supported.");
-            Err(anyhow::anyhow!("Database type not yet implemented"))
+        DatabaseType::Snowflake => {
+            setup_snowflake(buster_creds.url, buster_creds.api_key, &config_path, should_create_config, prefill).await
+        }
+        DatabaseType::Databricks => {
+            setup_databricks(buster_creds.url, buster_creds.api_key, &config_path, should_create_config, prefill).await
+        }
+        DatabaseType::MySql => {
+            setup_mysql(buster_creds.url, buster_creds.api_key, &config_path, should_create_config, prefill).await
+        }
+        DatabaseType::SqlServer => {
+            setup_sql_server(buster_creds.url, buster_creds.api_key, &config_path, should_create_config, prefill).await
+        }
+        DatabaseType::Supabase => {
+            setup_supabase(buster_creds.url, buster_creds.api_key, &config_path, should_create_config, prefill).await
         }
     }
 }
@@ -142,92 +206,36 @@ async fn setup_redshift(
     buster_api_key: String,
     config_path: &Path,
     should_create_config: bool,
+    prefill: Option<&DbtTarget>,
 ) -> Result<()> {
     println!("{}", "Setting up Redshift connection...".bold().green());
 
-    // Collect name (with validation)
-    let name_regex = Regex::new(r"^[a-zA-Z0-9_-]+$")?;
-    let name = Text::new("Enter a unique name for this data source:")
-        .with_help_message("Only alphanumeric characters, dash (-) and underscore (_) allowed")
-        .with_validator(move |input: &str| {
-            if input.trim().is_empty() {
-                return Ok(Validation::Invalid("Name cannot be empty".into()));
-            }
-            if name_regex.is_match(input) {
-                Ok(Validation::Valid)
-            } else {
-                Ok(Validation::Invalid(
-                    "Name must contain only alphanumeric characters, dash (-) or underscore (_)"
-                        .into(),
-                ))
-            }
-        })
-        .prompt()?;
-
-    // Collect host
-    let host = Text::new("Enter the Redshift host:")
-        .with_help_message("Example: my-cluster.abc123xyz789.us-west-2.redshift.amazonaws.com")
-        .with_validator(|input: &str| {
-            if input.trim().is_empty() {
-                return Ok(Validation::Invalid("Host cannot be empty".into()));
-            }
-            Ok(Validation::Valid)
-        })
-        .prompt()?;
-
-    // Collect port (with validation)
-    let port_str = Text::new("Enter the Redshift port:")
-        .with_default("5439")
-        .with_help_message("Default Redshift port is 5439")
-        .with_validator(|input: &str| match input.parse::<u16>() {
-            Ok(_) => Ok(Validation::Valid),
-            Err(_) => Ok(Validation::Invalid(
-                "Port must be a valid number between 1 and 65535".into(),
-            )),
-        })
-        .prompt()?;
-    let port = port_str.parse::<u16>()?;
-
-    // Collect username
-    let username = Text::new("Enter the Redshift username:")
-        .with_validator(|input: &str| {
-            if input.trim().is_empty() {
-                return Ok(Validation::Invalid("Username cannot be empty".into()));
-            }
-            Ok(Validation::Valid)
-        })
-        .prompt()?;
-
-    // Collect password (masked)
-    let password = Password::new("Enter the Redshift password:")
-        .with_validator(|input: &str| {
-            if input.trim().is_empty() {
-                return Ok(Validation::Invalid("Password cannot be empty".into()));
-            }
-            Ok(Validation::Valid)
-        })
-        .without_confirmation()
-        .prompt()?;
-
-    // Collect database (optional)
-    let database = Text::new("Enter the Redshift database (optional):")
-        .with_help_message("Leave blank to access all available databases")
-        .prompt()?;
-    let database = if database.trim().is_empty() {
-        None
-    } else {
-        Some(database)
-    };
-
-    // Collect schema (optional)
-    let schema = Text::new("Enter the Redshift schema (optional):")
-        .with_help_message("Leave blank to access all available schemas")
-        .prompt()?;
-    let schema = if schema.trim().is_empty() {
-        None
-    } else {
-        Some(schema)
-    };
+    let name = prompt_data_source_name(prefill)?;
+
+    let host = prompt_required(
+        "Host",
+        "Enter the Redshift host:",
+        "Example: my-cluster.abc123xyz789.us-west-2.redshift.amazonaws.com",
+        prefill.and_then(|t| t.get(&["host"])),
+    )?;
+    let port = prompt_port("Enter the Redshift port:", 5439, prefill)?;
+    let username = prompt_required(
+        "Username",
+        "Enter the Redshift username:",
+        "",
+        prefill.and_then(|t| t.get(&["user", "username"])),
+    )?;
+    let password = prompt_password("Enter the Redshift password:", prefill)?;
+    let database = prompt_optional(
+        "Enter the Redshift database (optional):",
+        "Leave blank to access all available databases",
+        prefill.and_then(|t| t.get(&["dbname", "database"])),
+    )?;
+    let schema = prompt_optional(
+        "Enter the Redshift schema (optional):",
+        "Leave blank to access all available schemas",
+        prefill.and_then(|t| t.get(&["schema"])),
+    )?;
 
     // Show summary and confirm
     println!("\n{}", "Connection Summary:".bold());
@@ -236,113 +244,33 @@ async fn setup_redshift(
     println!("Port: {}", port.to_string().cyan());
     println!("Username: {}", username.cyan());
     println!("Password: {}", "********".cyan());
+    print_optional("Database", &database, "All databases (null)");
+    print_optional("Schema", &schema, "All schemas (null)");
 
-    // Display database and schema with clear indication if they're empty
-    if let Some(db) = &database {
-        println!("Database: {}", db.cyan());
-    } else {
-        println!("Database: {}", "All databases (null)".cyan());
-    }
-
-    if let Some(sch) = &schema {
-        println!("Schema: {}", sch.cyan());
-    } else {
-        println!("Schema: {}", "All schemas (null)".cyan());
-    }
-
-    let confirm = Confirm::new("Do you want to create this data source?")
-        .with_default(true)
-        .prompt()?;
-
-    if !confirm {
-        println!("{}", "Data source creation cancelled.".yellow());
+    if !confirm_create()? {
         return Ok(());
     }
 
-    // Create credentials
-    let redshift_creds = RedshiftCredentials {
-        host,
-        port,
-        username,
-        password,
-        database: database.clone(),
-        schemas: schema.as_ref().map(|s| vec![s.clone()]),
-    };
-
-    // Create API request
-    // Note: PostgresCredentials requires String for database and schema, not Option<String>
+    // PostgresCredentials requires String for database and schema, not Option<String>
     // We use empty strings to represent null/all databases or schemas
     let request = PostDataSourcesRequest {
         name: name.clone(),
         env: "dev".to_string(), // Default to dev environment
         credential: Credential::Redshift(PostgresCredentials {
-            host: redshift_creds.host,
-            port: redshift_creds.port,
-            username: redshift_creds.username,
-            password: redshift_creds.password,
-            database: redshift_creds.database.clone().unwrap_or_default(),
-            schema: redshift_creds
-                .schemas
-                .clone()
-                .and_then(|s| s.first().cloned())
-                .unwrap_or_default(),
+            host,
+            port,
+            username,
+            password,
+            database: database.clone().unwrap_or_default(),
+            schema: schema.clone().unwrap_or_default(),
             jump_host: None,
             ssh_username: None,
             ssh_private_key: None,
         }),
     };
 
-    // Send to API with progress indicator
-    let spinner = ProgressBar::new_spinner();
-    spinner.set_style(
-        ProgressStyle::default_spinner()
-            .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ ")
-            .template("{spinner:.green} {msg}")
-            .unwrap(),
-    );
-    spinner.set_message("Sending credentials to Buster API...");
-    spinner.enable_steady_tick(Duration::from_millis(100));
-
-    let client = BusterClient::new(buster_url, buster_api_key)?;
-
-    match client.post_data_sources(vec![request]).await {
-        Ok(_) => {
-            spinner.finish_with_message(
-                "✓ Data source created successfully!"
-                    .green()
-                    .bold()
-                    .to_string(),
-            );
-            println!(
-                "\nData source '{}' is now available for use with Buster.",
-                name.cyan()
-            );
-
-            // Only create buster.yml if we should create/overwrite the config
-            if should_create_config {
-                // Create a copy of the values we need for the config file
-                let db_copy = database.clone();
-                let schema_copy = schema.clone();
-
-                // Create buster.yml file
-                create_buster_config_file(
-                    config_path,
-                    &name,
-                    db_copy.as_deref(),
-                    schema_copy.as_deref(),
-                )?;
-            }
-
-            println!("You can now use this data source with other Buster commands.");
-            Ok(())
-        }
-        Err(e) => {
-            spinner.finish_with_message("✗ Failed to create data source".red().bold().to_string());
-            println!("\nError: {}", e);
-            println!("Please check your credentials and try again.");
-            Err(anyhow::anyhow!("Failed to create data source: {}", e))
-        }
-    }
+    let config = new_buster_config(&name, database, schema);
+    create_data_source(buster_url, buster_api_key, request, config_path, should_create_config, config).await
 }
 
 async fn setup_postgres(
@@ -350,93 +278,39 @@ async fn setup_postgres(
     buster_api_key: String,
     config_path: &Path,
     should_create_config: bool,
+    prefill: Option<&DbtTarget>,
 ) -> Result<()> {
     println!("{}", "Setting up PostgreSQL connection...".bold().green());
 
-    // Collect name (with validation)
-    let name_regex = Regex::new(r"^[a-zA-Z0-9_-]+$")?;
-    let name = Text::new("Enter a unique name for this data source:")
-        .with_help_message("Only alphanumeric characters, dash (-) and underscore (_) allowed")
-        .with_validator(move |input: &str| {
-            if input.trim().is_empty() {
-                return Ok(Validation::Invalid("Name cannot be empty".into()));
-            }
-            if name_regex.is_match(input) {
-                Ok(Validation::Valid)
-            } else {
-                Ok(Validation::Invalid(
-                    "Name must contain only alphanumeric characters, dash (-) or underscore (_)"
-                        .into(),
-                ))
-            }
-        })
-        .prompt()?;
-
-    // Collect host
-    let host = Text::new("Enter the PostgreSQL host:")
-        .with_help_message("Example: localhost or db.example.com")
-        .with_validator(|input: &str| {
-            if input.trim().is_empty() {
-                return Ok(Validation::Invalid("Host cannot be empty".into()));
-            }
-            Ok(Validation::Valid)
-        })
-        .prompt()?;
-
-    // Collect port (with validation)
-    let port_str = Text::new("Enter the PostgreSQL port:")
-        .with_default("5432") // Default Postgres port is 5432
-        .with_help_message("Default PostgreSQL port is 5432")
-        .with_validator(|input: &str| match input.parse::<u16>() {
-            Ok(_) => Ok(Validation::Valid),
-            Err(_) => Ok(Validation::Invalid(
-                "Port must be a valid number between 1 and 65535".into(),
-            )),
-        })
-        .prompt()?;
-    let port = port_str.parse::<u16>()?;
-
-    // Collect username
-    let username = Text::new("Enter the PostgreSQL username:")
-        .with_validator(|input: &str| {
-            if input.trim().is_empty() {
-                return Ok(Validation::Invalid("Username cannot be empty".into()));
-            }
-            Ok(Validation::Valid)
-        })
-        .prompt()?;
-
-    // Collect password (masked)
-    let password = Password::new("Enter the PostgreSQL password:")
-        .with_validator(|input: &str| {
-            if input.trim().is_empty() {
-                return Ok(Validation::Invalid("Password cannot be empty".into()));
-            }
-            Ok(Validation::Valid)
-        })
-        .without_confirmation()
-        .prompt()?;
-
-    // Collect database (optional)
-    let database = Text::new("Enter the PostgreSQL database name (optional):")
-        .with_help_message("Leave blank to access all available databases")
-        .prompt()?;
-    let database = if database.trim().is_empty() {
-        None
-    } else {
-        Some(database)
-    };
-
-    // Collect schema (optional)
-    let schema = Text::new("Enter the PostgreSQL schema (optional):")
-        .with_help_message("Leave blank to access all available schemas")
-        .with_default("public") // Default Postgres schema is usually 'public'
-        .prompt()?;
-    let schema = if schema.trim().is_empty() {
-        None
-    } else {
-        Some(schema)
-    };
+    let name = prompt_data_source_name(prefill)?;
+
+    let host = prompt_required(
+        "Host",
+        "Enter the PostgreSQL host:",
+        "Example: localhost or db.example.com",
+        prefill.and_then(|t| t.get(&["host"])),
+    )?;
+    let port = prompt_port("Enter the PostgreSQL port:", 5432, prefill)?;
+    let username = prompt_required(
+        "Username",
+        "Enter the PostgreSQL username:",
+        "",
+        prefill.and_then(|t| t.get(&["user", "username"])),
+    )?;
+    let password = prompt_password("Enter the PostgreSQL password:", prefill)?;
+    let database = prompt_optional(
+        "Enter the PostgreSQL database name (optional):",
+        "Leave blank to access all available databases",
+        prefill.and_then(|t| t.get(&["dbname", "database"])),
+    )?;
+    let schema = prompt_optional(
+        "Enter the PostgreSQL schema (optional):",
+        "Leave blank to access all available schemas",
+        // Default Postgres schema is usually 'public'
+        prefill
+            .and_then(|t| t.get(&["schema"]))
+            .or_else(|| Some("public".to_string())),
+    )?;
 
     // Show summary and confirm
     println!("\n{}", "Connection Summary:".bold());
@@ -445,30 +319,13 @@ async fn setup_postgres(
     println!("Port: {}", port.to_string().cyan());
     println!("Username: {}", username.cyan());
     println!("Password: {}", "********".cyan());
+    print_optional("Database", &database, "All databases (null)");
+    print_optional("Schema", &schema, "All schemas (null)");
 
-    // Display database and schema with clear indication if they're empty
-    if let Some(db) = &database {
-        println!("Database: {}", db.cyan());
-    } else {
-        println!("Database: {}", "All databases (null)".cyan());
-    }
-
-    if let Some(sch) = &schema {
-        println!("Schema: {}", sch.cyan());
-    } else {
-        println!("Schema: {}", "All schemas (null)".cyan());
-    }
-
-    let confirm = Confirm::new("Do you want to create this data source?")
-        .with_default(true)
-        .prompt()?;
-
-    if !confirm {
-        println!("{}", "Data source creation cancelled.".yellow());
+    if !confirm_create()? {
         return Ok(());
     }
 
-    // Create API request
     let request = PostDataSourcesRequest {
         name: name.clone(),
         env: "dev".to_string(), // Default to dev environment
@@ -485,57 +342,8 @@ async fn setup_postgres(
         }),
     };
 
-    // Send to API with progress indicator
-    let spinner = ProgressBar::new_spinner();
-    spinner.set_style(
-        ProgressStyle::default_spinner()
-            .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ ")
-            .template("{spinner:.green} {msg}")
-            .unwrap(),
-    );
-    spinner.set_message("Sending credentials to Buster API...");
-    spinner.enable_steady_tick(Duration::from_millis(100));
-
-    let client = BusterClient::new(buster_url, buster_api_key)?;
-
-    match client.post_data_sources(vec![request]).await {
-        Ok(_) => {
-            spinner.finish_with_message(
-                "✓ Data source created successfully!"
-                    .green()
-                    .bold()
-                    .to_string(),
-            );
-            println!(
-                "\nData source '{}' is now available for use with Buster.",
-                name.cyan()
-            );
-
-            // Only create buster.yml if we should create/overwrite the config
-            if should_create_config {
-                // Create a copy of the values we need for the config file
-                let db_copy = database.clone();
-                let schema_copy = schema.clone();
-
-                // Create buster.yml file
-                create_buster_config_file(
-                    config_path,
-                    &name,
-                    db_copy.as_deref(),
-                    schema_copy.as_deref(),
-                )?;
-            }
-
-            println!("You can now use this data source with other Buster commands.");
-            Ok(())
-        }
-        Err(e) => {
-            spinner.finish_with_message("✗ Failed to create data source".red().bold().to_string());
-            println!("\nError: {}", e);
-            println!("Please check your credentials and try again.");
-            Err(anyhow::anyhow!("Failed to create data source: {}", e))
-        }
-    }
+    let config = new_buster_config(&name, database, schema);
+    create_data_source(buster_url, buster_api_key, request, config_path, should_create_config, config).await
 }
 
 async fn setup_bigquery(
@@ -543,48 +351,23 @@ async fn setup_bigquery(
     buster_api_key: String,
     config_path: &Path,
     should_create_config: bool,
+    prefill: Option<&DbtTarget>,
 ) -> Result<()> {
     println!("{}", "Setting up BigQuery connection...".bold().green());
 
-    // Collect name (with validation)
-    let name_regex = Regex::new(r"^[a-zA-Z0-9_-]+$")?;
-    let name = Text::new("Enter a unique name for this data source:")
-        .with_help_message("Only alphanumeric characters, dash (-) and underscore (_) allowed")
-        .with_validator(move |input: &str| {
-            if input.trim().is_empty() {
-                return Ok(Validation::Invalid("Name cannot be empty".into()));
-            }
-            if name_regex.is_match(input) {
-                Ok(Validation::Valid)
-            } else {
-                Ok(Validation::Invalid(
-                    "Name must contain only alphanumeric characters, dash (-) or underscore (_)"
-                        .into(),
-                ))
-            }
-        })
-        .prompt()?;
-
-    // Collect project ID
-    let project_id = Text::new("Enter the Google Cloud project ID:")
-        .with_help_message("Example: my-project-123456")
-        .with_validator(|input: &str| {
-            if input.trim().is_empty() {
-                return Ok(Validation::Invalid("Project ID cannot be empty".into()));
-            }
-            Ok(Validation::Valid)
-        })
-        .prompt()?;
+    let name = prompt_data_source_name(prefill)?;
 
-    // Collect dataset ID (optional)
-    let dataset_id = Text::new("Enter the BigQuery dataset ID (optional):")
-        .with_help_message("Leave blank to access all available datasets")
-        .prompt()?;
-    let dataset_id = if dataset_id.trim().is_empty() {
-        None
-    } else {
-        Some(dataset_id)
-    };
+    let project_id = prompt_required(
+        "Project ID",
+        "Enter the Google Cloud project ID:",
+        "Example: my-project-123456",
+        prefill.and_then(|t| t.get(&["project", "database"])),
+    )?;
+    let dataset_id = prompt_optional(
+        "Enter the BigQuery dataset ID (optional):",
+        "Leave blank to access all available datasets",
+        prefill.and_then(|t| t.get(&["dataset", "schema"])),
+    )?;
 
     // Collect credentials JSON
     println!(
@@ -595,7 +378,8 @@ async fn setup_bigquery(
         "You can create one in the Google Cloud Console under IAM & Admin > Service Accounts."
     );
 
-    let credentials_path = Text::new("Enter the path to your credentials JSON file:")
+    let keyfile = prefill.and_then(|t| t.get(&["keyfile"]));
+    let mut credentials_prompt = Text::new("Enter the path to your credentials JSON file:")
         .with_help_message("Example: /path/to/credentials.json")
         .with_validator(|input: &str| {
             let path = Path::new(input);
@@ -606,8 +390,11 @@ async fn setup_bigquery(
                 return Ok(Validation::Invalid("Path is not a file".into()));
             }
             Ok(Validation::Valid)
-        })
-        .prompt()?;
+        });
+    if let Some(keyfile) = &keyfile {
+        credentials_prompt = credentials_prompt.with_default(keyfile);
+    }
+    let credentials_path = credentials_prompt.prompt()?;
 
     // Read credentials file
     let credentials_content = match fs::read_to_string(&credentials_path) {
@@ -629,26 +416,13 @@ async fn setup_bigquery(
     println!("\n{}", "Connection Summary:".bold());
     println!("Name: {}", name.cyan());
     println!("Project ID: {}", project_id.cyan());
-
-    // Display dataset ID with clear indication if it's empty
-    if let Some(ds) = &dataset_id {
-        println!("Dataset ID: {}", ds.cyan());
-    } else {
-        println!("Dataset ID: {}", "All datasets (null)".cyan());
-    }
-
+    print_optional("Dataset ID", &dataset_id, "All datasets (null)");
     println!("Credentials: {}", credentials_path.cyan());
 
-    let confirm = Confirm::new("Do you want to create this data source?")
-        .with_default(true)
-        .prompt()?;
-
-    if !confirm {
-        println!("{}", "Data source creation cancelled.".yellow());
+    if !confirm_create()? {
         return Ok(());
     }
 
-    // Create API request
     let request = PostDataSourcesRequest {
         name: name.clone(),
         env: "dev".to_string(), // Default to dev environment
@@ -659,7 +433,390 @@ async fn setup_bigquery(
         }),
     };
 
-    // Send to API with progress indicator
+    // Project ID maps to database, dataset ID maps to schema
+    let config = new_buster_config(&name, Some(project_id), dataset_id);
+    create_data_source(buster_url, buster_api_key, request, config_path, should_create_config, config).await
+}
+
+async fn setup_snowflake(
+    buster_url: String,
+    buster_api_key: String,
+    config_path: &Path,
+    should_create_config: bool,
+    prefill: Option<&DbtTarget>,
+) -> Result<()> {
+    println!("{}", "Setting up Snowflake connection...".bold().green());
+
+    let name = prompt_data_source_name(prefill)?;
+
+    let account_id = prompt_required(
+        "Account identifier",
+        "Enter the Snowflake account identifier:",
+        "Example: ab12345.us-east-1 or myorg-myaccount",
+        prefill.and_then(|t| t.get(&["account"])),
+    )?;
+    let warehouse = prompt_required(
+        "Warehouse",
+        "Enter the Snowflake warehouse:",
+        "The warehouse Buster runs queries on. Example: COMPUTE_WH",
+        prefill.and_then(|t| t.get(&["warehouse"])),
+    )?;
+    let username = prompt_required(
+        "Username",
+        "Enter the Snowflake username:",
+        "",
+        prefill.and_then(|t| t.get(&["user", "username"])),
+    )?;
+    let password = prompt_password("Enter the Snowflake password:", prefill)?;
+    let role = prompt_optional(
+        "Enter the Snowflake role (optional):",
+        "Leave blank to use the user's default role",
+        prefill.and_then(|t| t.get(&["role"])),
+    )?;
+    let database = prompt_required(
+        "Database",
+        "Enter the Snowflake database:",
+        "Example: ANALYTICS",
+        prefill.and_then(|t| t.get(&["database"])),
+    )?;
+    let schema = prompt_optional(
+        "Enter the Snowflake schema (optional):",
+        "Leave blank to access all available schemas",
+        prefill.and_then(|t| t.get(&["schema"])),
+    )?;
+
+    // Show summary and confirm
+    println!("\n{}", "Connection Summary:".bold());
+    println!("Name: {}", name.cyan());
+    println!("Account: {}", account_id.cyan());
+    println!("Warehouse: {}", warehouse.cyan());
+    println!("Username: {}", username.cyan());
+    println!("Password: {}", "********".cyan());
+    print_optional("Role", &role, "Default role (null)");
+    println!("Database: {}", database.cyan());
+    print_optional("Schema", &schema, "All schemas (null)");
+
+    if !confirm_create()? {
+        return Ok(());
+    }
+
+    let request = PostDataSourcesRequest {
+        name: name.clone(),
+        env: "dev".to_string(), // Default to dev environment
+        credential: Credential::Snowflake(SnowflakeCredentials {
+            account_id,
+            warehouse_id: warehouse.clone(),
+            database_id: database.clone(),
+            username,
+            password,
+            role: role.clone(),
+            schemas: schema.as_ref().map(|s| vec![s.clone()]),
+        }),
+    };
+
+    let mut config = new_buster_config(&name, Some(database), schema);
+    config.warehouse = Some(warehouse);
+    config.role = role;
+    create_data_source(buster_url, buster_api_key, request, config_path, should_create_config, config).await
+}
+
+async fn setup_databricks(
+    buster_url: String,
+    buster_api_key: String,
+    config_path: &Path,
+    should_create_config: bool,
+    prefill: Option<&DbtTarget>,
+) -> Result<()> {
+    println!("{}", "Setting up Databricks connection...".bold().green());
+
+    let name = prompt_data_source_name(prefill)?;
+
+    let host = prompt_required(
+        "Host",
+        "Enter the Databricks workspace host:",
+        "Example: dbc-a1b2345c-d6e7.cloud.databricks.com",
+        prefill.and_then(|t| t.get(&["host"])),
+    )?;
+    // dbt connects through the warehouse's HTTP path, which ends in its ID
+    let warehouse_id = prompt_required(
+        "Warehouse ID",
+        "Enter the SQL warehouse ID:",
+        "The last part of the warehouse's HTTP path, e.g. /sql/1.0/warehouses/<id>",
+        prefill
+            .and_then(|t| t.get(&["http_path"]))
+            .and_then(|path| path.rsplit('/').next().map(String::from)),
+    )?;
+    let api_key = match prefill.and_then(|t| t.get(&["token"])) {
+        Some(token) if use_profile_secret("access token")? => token,
+        _ => Password::new("Enter a Databricks personal access token:")
+            .with_validator(|input: &str| {
+                if input.trim().is_empty() {
+                    return Ok(Validation::Invalid("Access token cannot be empty".into()));
+                }
+                Ok(Validation::Valid)
+            })
+            .without_confirmation()
+            .prompt()?,
+    };
+    let catalog = prompt_required(
+        "Catalog",
+        "Enter the Unity Catalog catalog:",
+        "Example: main or hive_metastore",
+        prefill.and_then(|t| t.get(&["catalog"])),
+    )?;
+    let schema = prompt_optional(
+        "Enter the Databricks schema (optional):",
+        "Leave blank to access all available schemas",
+        prefill.and_then(|t| t.get(&["schema"])),
+    )?;
+
+    // Show summary and confirm
+    println!("\n{}", "Connection Summary:".bold());
+    println!("Name: {}", name.cyan());
+    println!("Host: {}", host.cyan());
+    println!("Warehouse ID: {}", warehouse_id.cyan());
+    println!("Access token: {}", "********".cyan());
+    println!("Catalog: {}", catalog.cyan());
+    print_optional("Schema", &schema, "All schemas (null)");
+
+    if !confirm_create()? {
+        return Ok(());
+    }
+
+    let request = PostDataSourcesRequest {
+        name: name.clone(),
+        env: "dev".to_string(), // Default to dev environment
+        credential: Credential::Databricks(DatabricksCredentials {
+            host,
+            api_key,
+            warehouse_id,
+            catalog_name: catalog.clone(),
+            schemas: schema.as_ref().map(|s| vec![s.clone()]),
+        }),
+    };
+
+    // Catalog maps to database
+    let config = new_buster_config(&name, Some(catalog), schema);
+    create_data_source(buster_url, buster_api_key, request, config_path, should_create_config, config).await
+}
+
+async fn setup_mysql(
+    buster_url: String,
+    buster_api_key: String,
+    config_path: &Path,
+    should_create_config: bool,
+    prefill: Option<&DbtTarget>,
+) -> Result<()> {
+    println!("{}", "Setting up MySQL / MariaDB connection...".bold().green());
+
+    let name = prompt_data_source_name(prefill)?;
+
+    let host = prompt_required(
+        "Host",
+        "Enter the MySQL host:",
+        "Example: localhost or db.example.com",
+        prefill.and_then(|t| t.get(&["server", "host"])),
+    )?;
+    let port = prompt_port("Enter the MySQL port:", 3306, prefill)?;
+    let username = prompt_required(
+        "Username",
+        "Enter the MySQL username:",
+        "",
+        prefill.and_then(|t| t.get(&["username", "user"])),
+    )?;
+    let password = prompt_password("Enter the MySQL password:", prefill)?;
+    // MySQL databases play the role of schemas
+    let database = prompt_optional(
+        "Enter the MySQL database (optional):",
+        "Leave blank to access all available databases",
+        prefill.and_then(|t| t.get(&["schema", "database"])),
+    )?;
+
+    // Show summary and confirm
+    println!("\n{}", "Connection Summary:".bold());
+    println!("Name: {}", name.cyan());
+    println!("Host: {}", host.cyan());
+    println!("Port: {}", port.to_string().cyan());
+    println!("Username: {}", username.cyan());
+    println!("Password: {}", "********".cyan());
+    print_optional("Database", &database, "All databases (null)");
+
+    if !confirm_create()? {
+        return Ok(());
+    }
+
+    let request = PostDataSourcesRequest {
+        name: name.clone(),
+        env: "dev".to_string(), // Default to dev environment
+        credential: Credential::MySQL(MySqlCredentials {
+            host,
+            port,
+            username,
+            password,
+            jump_host: None,
+            ssh_username: None,
+            ssh_private_key: None,
+            databases: database.as_ref().map(|d| vec![d.clone()]),
+        }),
+    };
+
+    // The MySQL database maps to schema
+    let config = new_buster_config(&name, None, database);
+    create_data_source(buster_url, buster_api_key, request, config_path, should_create_config, config).await
+}
+
+async fn setup_sql_server(
+    buster_url: String,
+    buster_api_key: String,
+    config_path: &Path,
+    should_create_config: bool,
+    prefill: Option<&DbtTarget>,
+) -> Result<()> {
+    println!("{}", "Setting up SQL Server connection...".bold().green());
+
+    let name = prompt_data_source_name(prefill)?;
+
+    let host = prompt_required(
+        "Host",
+        "Enter the SQL Server host:",
+        "Example: myserver.database.windows.net",
+        prefill.and_then(|t| t.get(&["server", "host"])),
+    )?;
+    let port = prompt_port("Enter the SQL Server port:", 1433, prefill)?;
+    let username = prompt_required(
+        "Username",
+        "Enter the SQL Server username:",
+        "",
+        prefill.and_then(|t| t.get(&["user", "username", "UID"])),
+    )?;
+    let password = prompt_password("Enter the SQL Server password:", prefill)?;
+    let database = prompt_required(
+        "Database",
+        "Enter the SQL Server database:",
+        "",
+        prefill.and_then(|t| t.get(&["database"])),
+    )?;
+    let schema = prompt_optional(
+        "Enter the SQL Server schema (optional):",
+        "Leave blank to access all available schemas",
+        prefill
+            .and_then(|t| t.get(&["schema"]))
+            .or_else(|| Some("dbo".to_string())),
+    )?;
+
+    // Show summary and confirm
+    println!("\n{}", "Connection Summary:".bold());
+    println!("Name: {}", name.cyan());
+    println!("Host: {}", host.cyan());
+    println!("Port: {}", port.to_string().cyan());
+    println!("Username: {}", username.cyan());
+    println!("Password: {}", "********".cyan());
+    println!("Database: {}", database.cyan());
+    print_optional("Schema", &schema, "All schemas (null)");
+
+    if !confirm_create()? {
+        return Ok(());
+    }
+
+    let request = PostDataSourcesRequest {
+        name: name.clone(),
+        env: "dev".to_string(), // Default to dev environment
+        credential: Credential::SqlServer(SqlServerCredentials {
+            host,
+            port,
+            username,
+            password,
+            database: database.clone(),
+            jump_host: None,
+            ssh_username: None,
+            ssh_private_key: None,
+            schemas: schema.as_ref().map(|s| vec![s.clone()]),
+        }),
+    };
+
+    let config = new_buster_config(&name, Some(database), schema);
+    create_data_source(buster_url, buster_api_key, request, config_path, should_create_config, config).await
+}
+
+async fn setup_supabase(
+    buster_url: String,
+    buster_api_key: String,
+    config_path: &Path,
+    should_create_config: bool,
+    prefill: Option<&DbtTarget>,
+) -> Result<()> {
+    println!("{}", "Setting up Supabase connection...".bold().green());
+
+    let name = prompt_data_source_name(prefill)?;
+
+    let host = prompt_required(
+        "Host",
+        "Enter the Supabase database host:",
+        "Found under Project Settings > Database. Example: db.abcdefghijklmnop.supabase.co",
+        prefill.and_then(|t| t.get(&["host"])),
+    )?;
+    let port = prompt_port("Enter the Supabase database port:", 5432, prefill)?;
+    let username = prompt_required(
+        "Username",
+        "Enter the Supabase database user:",
+        "",
+        prefill
+            .and_then(|t| t.get(&["user", "username"]))
+            .or_else(|| Some("postgres".to_string())),
+    )?;
+    let password = prompt_password("Enter the Supabase database password:", prefill)?;
+    let database = prompt_required(
+        "Database",
+        "Enter the Supabase database name:",
+        "",
+        prefill
+            .and_then(|t| t.get(&["dbname", "database"]))
+            .or_else(|| Some("postgres".to_string())),
+    )?;
+    let schema = prompt_optional(
+        "Enter the schema (optional):",
+        "Leave blank to access all available schemas",
+        prefill
+            .and_then(|t| t.get(&["schema"]))
+            .or_else(|| Some("public".to_string())),
+    )?;
+
+    // Show summary and confirm
+    println!("\n{}", "Connection Summary:".bold());
+    println!("Name: {}", name.cyan());
+    println!("Host: {}", host.cyan());
+    println!("Port: {}", port.to_string().cyan());
+    println!("Username: {}", username.cyan());
+    println!("Password: {}", "********".cyan());
+    println!("Database: {}", database.cyan());
+    print_optional("Schema", &schema, "All schemas (null)");
+
+    if !confirm_create()? {
+        return Ok(());
+    }
+
+    // Supabase databases are Postgres, so they connect with Postgres credentials
+    let request = PostDataSourcesRequest {
+        name: name.clone(),
+        env: "dev".to_string(), // Default to dev environment
+        credential: Credential::Postgres(PostgresCredentials {
+            host,
+            port,
+            username,
+            password,
+            database: database.clone(),
+            schema: schema.clone().unwrap_or_default(),
+            jump_host: None,
+            ssh_username: None,
+            ssh_private_key: None,
+        }),
+    };
+
+    let config = new_buster_config(&name, Some(database), schema);
+    create_data_source(buster_url, buster_api_key, request, config_path, should_create_config, config).await
+}
+
+fn new_spinner(message: &str) -> ProgressBar {
     let spinner = ProgressBar::new_spinner();
     spinner.set_style(
         ProgressStyle::default_spinner()
@@ -667,10 +824,194 @@ async fn setup_bigquery(
             .template("{spinner:.green} {msg}")
             .unwrap(),
     );
-    spinner.set_message("Sending credentials to Buster API...");
+    spinner.set_message(message.to_string());
     spinner.enable_steady_tick(Duration::from_millis(100));
+    spinner
+}
+
+// Collect name (with validation), defaulting to the dbt profile's name
+fn prompt_data_source_name(prefill: Option<&DbtTarget>) -> Result<String> {
+    let name_regex = Regex::new(r"^[a-zA-Z0-9_-]+$")?;
+    let default = prefill
+        .map(|t| t.profile.clone())
+        .filter(|profile| name_regex.is_match(profile));
+
+    let mut prompt = Text::new("Enter a unique name for this data source:")
+        .with_help_message("Only alphanumeric characters, dash (-) and underscore (_) allowed")
+        .with_validator(move |input: &str| {
+            if input.trim().is_empty() {
+                return Ok(Validation::Invalid("Name cannot be empty".into()));
+            }
+            if name_regex.is_match(input) {
+                Ok(Validation::Valid)
+            } else {
+                Ok(Validation::Invalid(
+                    "Name must contain only alphanumeric characters, dash (-) or underscore (_)"
+                        .into(),
+                ))
+            }
+        });
+    if let Some(default) = &default {
+        prompt = prompt.with_default(default);
+    }
+
+    Ok(prompt.prompt()?)
+}
+
+fn prompt_required(
+    field: &str,
+    message: &str,
+    help: &str,
+    default: Option<String>,
+) -> Result<String> {
+    let empty_message = format!("{} cannot be empty", field);
+    let mut prompt = Text::new(message).with_validator(move |input: &str| {
+        if input.trim().is_empty() {
+            return Ok(Validation::Invalid(empty_message.clone().into()));
+        }
+        Ok(Validation::Valid)
+    });
+    if !help.is_empty() {
+        prompt = prompt.with_help_message(help);
+    }
+    if let Some(default) = &default {
+        prompt = prompt.with_default(default);
+    }
 
+    Ok(prompt.prompt()?)
+}
+
+// Blank input means "all", so it comes back as None
+fn prompt_optional(message: &str, help: &str, default: Option<String>) -> Result<Option<String>> {
+    let mut prompt = Text::new(message).with_help_message(help);
+    if let Some(default) = &default {
+        prompt = prompt.with_default(default);
+    }
+
+    let value = prompt.prompt()?;
+    if value.trim().is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(value))
+    }
+}
+
+fn prompt_port(message: &str, default_port: u16, prefill: Option<&DbtTarget>) -> Result<u16> {
+    let default = prefill
+        .and_then(|t| t.get(&["port"]))
+        .unwrap_or_else(|| default_port.to_string());
+    let help = format!("Default port is {}", default_port);
+
+    let port_str = Text::new(message)
+        .with_default(&default)
+        .with_help_message(&help)
+        .with_validator(|input: &str| match input.parse::<u16>() {
+            Ok(_) => Ok(Validation::Valid),
+            Err(_) => Ok(Validation::Invalid(
+                "Port must be a valid number between 1 and 65535".into(),
+            )),
+        })
+        .prompt()?;
+
+    Ok(port_str.parse::<u16>()?)
+}
+
+// Collect password (masked), unless the dbt profile has one the user wants to reuse
+fn prompt_password(message: &str, prefill: Option<&DbtTarget>) -> Result<String> {
+    if let Some(password) = prefill.and_then(|t| t.get(&["password", "pass"])) {
+        if use_profile_secret("password")? {
+            return Ok(password);
+        }
+    }
+
+    Ok(Password::new(message)
+        .with_validator(|input: &str| {
+            if input.trim().is_empty() {
+                return Ok(Validation::Invalid("Password cannot be empty".into()));
+            }
+            Ok(Validation::Valid)
+        })
+        .without_confirmation()
+        .prompt()?)
+}
+
+fn use_profile_secret(secret: &str) -> Result<bool> {
+    Ok(
+        Confirm::new(&format!("Use the {} from your dbt profile?", secret))
+            .with_default(true)
+            .prompt()?,
+    )
+}
+
+// Display optional values with clear indication if they're empty
+fn print_optional(label: &str, value: &Option<String>, empty: &str) {
+    match value {
+        Some(value) => println!("{}: {}", label, value.cyan()),
+        None => println!("{}: {}", label, empty.cyan()),
+    }
+}
+
+fn confirm_create() -> Result<bool> {
+    let confirm = Confirm::new("Do you want to create this data source?")
+        .with_default(true)
+        .prompt()?;
+
+    if !confirm {
+        println!("{}", "Data source creation cancelled.".yellow());
+    }
+
+    Ok(confirm)
+}
+
+fn new_buster_config(
+    data_source_name: &str,
+    database: Option<String>,
+    schema: Option<String>,
+) -> BusterConfig {
+    BusterConfig {
+        data_source_name: Some(data_source_name.to_string()),
+        schema,
+        database,
+        warehouse: None,
+        role: None,
+        exclude_files: None,
+        exclude_tags: None,
+        model_paths: None,
+        environments: None,
+    }
+}
+
+/// Checks the connection through the Buster server, then saves the data source and writes
+/// buster.yml. Nothing is saved when the server can't connect.
+async fn create_data_source(
+    buster_url: String,
+    buster_api_key: String,
+    request: PostDataSourcesRequest,
+    config_path: &Path,
+    should_create_config: bool,
+    config: BusterConfig,
+) -> Result<()> {
     let client = BusterClient::new(buster_url, buster_api_key)?;
+    let name = request.name.clone();
+
+    let spinner = new_spinner("Testing connection...");
+
+    if let Err(e) = client.test_data_source(&request.credential).await {
+        spinner.finish_with_message(
+            "✗ Buster couldn't connect to the data source"
+                .red()
+                .bold()
+                .to_string(),
+        );
+        println!("\nError: {}", e);
+        println!("Please check your credentials and try again.");
+        return Err(anyhow::anyhow!("Connection test failed: {}", e));
+    }
+
+    spinner.finish_with_message("✓ Connection successful".green().to_string());
+
+    // Send to API with progress indicator
+    let spinner = new_spinner("Sending credentials to Buster API...");
 
     match client.post_data_sources(vec![request]).await {
         Ok(_) => {
@@ -687,13 +1028,7 @@ async fn setup_bigquery(
 
             // Only create buster.yml if we should create/overwrite the config
             if should_create_config {
-                // Create buster.yml file
-                create_buster_config_file(
-                    config_path,
-                    &name,
-                    Some(&project_id),     // Project ID maps to database
-                    dataset_id.as_deref(), // Dataset ID maps to schema
-                )?;
+                create_buster_config_file(config_path, config)?;
             }
 
             println!("You can now use this data source with other Buster commands.");
@@ -709,19 +1044,14 @@ async fn setup_bigquery(
 }
 
 // Helper function to create buster.yml file
-fn create_buster_config_file(
-    path: &Path,
-    data_source_name: &str,
-    database: Option<&str>,
-    schema: Option<&str>,
-) -> Result<()> {
+fn create_buster_config_file(path: &Path, mut config: BusterConfig) -> Result<()> {
     // Prompt for model paths (optional)
     let model_paths_input = Text::new("Enter paths to your SQL models (optional, comma-separated):")
         .with_help_message("Leave blank to use current directory, or specify paths like './models,./analytics/models'")
         .prompt()?;
-    
+
     // Process the comma-separated input into a vector if not empty
-    let model_paths = if model_paths_input.trim().is_empty() {
+    config.model_paths = if model_paths_input.trim().is_empty() {
         None
     } else {
         Some(
@@ -733,15 +1063,6 @@ fn create_buster_config_file(
         )
     };
 
-    let config = BusterConfig {
-        data_source_name: Some(data_source_name.to_string()),
-        schema: schema.map(String::from),
-        database: database.map(String::from),
-        exclude_files: None,
-        exclude_tags: None,
-        model_paths,
-    };
-
     let yaml = serde_yaml::to_string(&config)?;
     fs::write(path, yaml)?;
 
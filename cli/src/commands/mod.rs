@@ -1,12 +1,16 @@
 pub mod auth;
 mod deploy;
 mod generate;
+mod import_metricflow;
 mod init;
+mod pull;
 pub mod version;
 pub mod update;
 
 pub use auth::{auth, auth_with_args, AuthArgs};
 pub use deploy::deploy;
 pub use generate::{GenerateCommand, generate};
+pub use import_metricflow::import_metricflow;
 pub use init::init;
+pub use pull::pull;
 pub use update::UpdateCommand;
@@ -0,0 +1,419 @@
+use crate::utils::{
+    buster_credentials::get_and_validate_buster_credentials,
+    find_yml_files,
+    yaml_diff_merger::{Model, YamlDiffMerger, YamlFile},
+    BusterClient, BusterConfig, ExclusionManager, ExportDatasetsRequest, ExportedDataset,
+    ProgressReporter,
+};
+use anyhow::{anyhow, Result};
+use colored::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+fn find_nearest_buster_yml(start_dir: &Path) -> Option<PathBuf> {
+    let mut current_dir = start_dir.to_path_buf();
+    loop {
+        let buster_yml = current_dir.join("buster.yml");
+        if buster_yml.exists() {
+            return Some(buster_yml);
+        }
+        if !current_dir.pop() {
+            return None;
+        }
+    }
+}
+
+/// A model with the server's edits applied, and the fields where both sides changed.
+#[derive(Debug)]
+struct PulledModel {
+    model: Model,
+    conflicts: Vec<String>,
+}
+
+/// Three-way merge of one field. `base` is the value from the last deploy, when known.
+///
+/// Server-side edits win when the local file still has the deployed value. When both sides have
+/// changed it, the local value is kept and the field is reported.
+fn merge_field<T: PartialEq + Clone>(
+    label: String,
+    local: T,
+    base: Option<T>,
+    server: T,
+    conflicts: &mut Vec<String>,
+) -> T {
+    if local == server {
+        return local;
+    }
+
+    match base {
+        Some(base) if base == local => server,
+        Some(base) if base == server => local,
+        _ => {
+            conflicts.push(label);
+            local
+        }
+    }
+}
+
+/// Applies the dataset and column metadata edited in the app to a local model.
+fn pull_model(local: &Model, dataset: &ExportedDataset) -> PulledModel {
+    let mut model = local.clone();
+    let mut conflicts = Vec::new();
+
+    // The model file as it was deployed is the common ancestor of both sides.
+    let base = dataset
+        .yml_file
+        .as_ref()
+        .and_then(|yml| serde_yaml::from_str::<YamlFile>(yml).ok())
+        .and_then(|file| file.models.into_iter().find(|m| m.name == local.name));
+
+    let columns: HashMap<String, _> = dataset
+        .columns
+        .iter()
+        .map(|c| (c.name.to_lowercase(), c))
+        .collect();
+
+    if let Some(description) = &dataset.description {
+        model.description = merge_field(
+            format!("{}.description", local.name),
+            model.description,
+            base.as_ref().map(|b| b.description.clone()),
+            Some(description.clone()),
+            &mut conflicts,
+        );
+    }
+
+    for dim in model.dimensions.iter_mut() {
+        let column = match columns.get(&dim.name.to_lowercase()) {
+            Some(column) => column,
+            None => continue,
+        };
+        let base_dim = base.as_ref().and_then(|b| {
+            b.dimensions
+                .iter()
+                .find(|d| d.name.eq_ignore_ascii_case(&dim.name))
+        });
+
+        if let Some(description) = &column.description {
+            dim.description = merge_field(
+                format!("{}.{}.description", local.name, dim.name),
+                dim.description.clone(),
+                base_dim.map(|d| d.description.clone()),
+                Some(description.clone()),
+                &mut conflicts,
+            );
+        }
+
+        let searchable = merge_field(
+            format!("{}.{}.searchable", local.name, dim.name),
+            dim.searchable.unwrap_or(false),
+            base_dim.map(|d| d.searchable.unwrap_or(false)),
+            column.stored_values,
+            &mut conflicts,
+        );
+        dim.searchable = Some(searchable);
+    }
+
+    for measure in model.measures.iter_mut() {
+        let description = match columns
+            .get(&measure.name.to_lowercase())
+            .and_then(|c| c.description.as_ref())
+        {
+            Some(description) => description,
+            None => continue,
+        };
+        let base_measure = base.as_ref().and_then(|b| {
+            b.measures
+                .iter()
+                .find(|m| m.name.eq_ignore_ascii_case(&measure.name))
+        });
+
+        measure.description = merge_field(
+            format!("{}.{}.description", local.name, measure.name),
+            measure.description.clone(),
+            base_measure.map(|m| m.description.clone()),
+            Some(description.clone()),
+            &mut conflicts,
+        );
+    }
+
+    for metric in model.metrics.iter_mut() {
+        let description = match columns
+            .get(&metric.name.to_lowercase())
+            .and_then(|c| c.description.as_ref())
+        {
+            Some(description) => description,
+            None => continue,
+        };
+        let base_metric = base.as_ref().and_then(|b| {
+            b.metrics
+                .iter()
+                .find(|m| m.name.eq_ignore_ascii_case(&metric.name))
+        });
+
+        metric.description = merge_field(
+            format!("{}.{}.description", local.name, metric.name),
+            metric.description.clone(),
+            base_metric.map(|m| m.description.clone()),
+            Some(description.clone()),
+            &mut conflicts,
+        );
+    }
+
+    PulledModel { model, conflicts }
+}
+
+/// Pulls descriptions and stored-values settings edited in the app back into local model files.
+pub async fn pull(path: Option<&str>, env: &str, dry_run: bool) -> Result<()> {
+    let buster_yml_path = match find_nearest_buster_yml(&std::env::current_dir()?) {
+        Some(path) => path,
+        None => {
+            println!("❌ No buster.yml found in the current directory or any parent directories.");
+            println!(
+                "To create a new Buster project, run: {}",
+                "buster init".cyan()
+            );
+            return Err(anyhow!(
+                "No buster.yml found. Run 'buster init' to create a new project."
+            ));
+        }
+    };
+
+    let target_path = match path {
+        Some(path) => PathBuf::from(path),
+        None => buster_yml_path.parent().unwrap().to_path_buf(),
+    };
+
+    let config = match BusterConfig::load_from_dir(buster_yml_path.parent().unwrap())? {
+        Some(config) => Some(config.for_env(env)?),
+        None => None,
+    };
+    let exclusion_manager = match &config {
+        Some(config) => ExclusionManager::new(config)?,
+        None => ExclusionManager::empty(),
+    };
+
+    let yml_files = find_yml_files(
+        &target_path,
+        true,
+        &exclusion_manager,
+        None::<&mut ProgressReporter>,
+    )?;
+
+    // (file, model, data source the model deploys to)
+    let mut local_models = Vec::new();
+    for yml_path in yml_files {
+        let content = std::fs::read_to_string(&yml_path)?;
+        let file: YamlFile = match serde_yaml::from_str(&content) {
+            Ok(file) => file,
+            Err(e) => {
+                println!(
+                    "⚠️  Skipping {}: not a model file ({})",
+                    yml_path.display(),
+                    e
+                );
+                continue;
+            }
+        };
+
+        // The merger updates the first model of a file, which is how generate writes them.
+        if file.models.len() != 1 {
+            println!(
+                "⚠️  Skipping {}: pull only supports one model per file",
+                yml_path.display()
+            );
+            continue;
+        }
+
+        let model = file.models[0].clone();
+        let data_source_name = model
+            .extra
+            .get("data_source_name")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .or_else(|| config.as_ref().and_then(|c| c.data_source_name.clone()));
+
+        match data_source_name {
+            Some(data_source_name) => local_models.push((yml_path, model, data_source_name)),
+            None => println!(
+                "⚠️  Skipping {}: no data_source_name in the model or buster.yml",
+                yml_path.display()
+            ),
+        }
+    }
+
+    if local_models.is_empty() {
+        println!("No model files found in {}", target_path.display());
+        return Ok(());
+    }
+
+    let creds = get_and_validate_buster_credentials().await?;
+    let client = BusterClient::new(creds.url, creds.api_key)?;
+
+    let datasets = client
+        .export_datasets(ExportDatasetsRequest {
+            env: env.to_string(),
+            data_source_name: None,
+            dataset_names: Some(
+                local_models
+                    .iter()
+                    .map(|(_, m, _)| m.name.clone())
+                    .collect(),
+            ),
+        })
+        .await?;
+
+    let datasets: HashMap<(String, String), ExportedDataset> = datasets
+        .into_iter()
+        .map(|d| ((d.data_source_name.clone(), d.name.clone()), d))
+        .collect();
+
+    let mut updated = 0;
+    let mut not_deployed = Vec::new();
+    let mut conflicts = Vec::new();
+
+    for (yml_path, model, data_source_name) in &local_models {
+        let dataset = match datasets.get(&(data_source_name.clone(), model.name.clone())) {
+            Some(dataset) => dataset,
+            None => {
+                not_deployed.push(model.name.clone());
+                continue;
+            }
+        };
+
+        let pulled = pull_model(model, dataset);
+        conflicts.extend(pulled.conflicts);
+
+        let new_content = serde_yaml::to_string(&YamlFile {
+            models: vec![pulled.model],
+        })?;
+        let merger = YamlDiffMerger::new(yml_path.clone(), new_content)
+            .with_updated_fields(&["description", "searchable"]);
+
+        let diff_result = match merger.compute_diff() {
+            Ok(diff_result) => diff_result,
+            Err(e) => {
+                println!(
+                    "❌ Failed to compute diff for {}: {}",
+                    yml_path.display(),
+                    e
+                );
+                continue;
+            }
+        };
+
+        if !diff_result.has_updated_fields() {
+            continue;
+        }
+
+        println!("\nProcessing model: {}", model.name);
+        merger.preview_changes(&diff_result);
+
+        if dry_run {
+            continue;
+        }
+
+        match merger.apply_changes(&diff_result) {
+            Ok(_) => {
+                updated += 1;
+                println!("✅ Updated {}", yml_path.display());
+            }
+            Err(e) => println!("❌ Failed to update {}: {}", yml_path.display(), e),
+        }
+    }
+
+    if !not_deployed.is_empty() {
+        println!("\nℹ️  Not deployed to {}: {}", env, not_deployed.join(", "));
+    }
+
+    if !conflicts.is_empty() {
+        println!(
+            "\n{}",
+            "⚠️  Changed both locally and in Buster since the last deploy (kept the local value):"
+                .yellow()
+        );
+        for conflict in &conflicts {
+            println!("  - {}", conflict);
+        }
+    }
+
+    if dry_run {
+        println!("\nDry run: no files were changed");
+    } else if updated == 0 {
+        println!("\n✅ Local models are up to date");
+    } else {
+        println!("\n✅ Updated {} model files", updated);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::ExportedColumn;
+    use uuid::Uuid;
+
+    fn dataset(description: &str, columns: Vec<ExportedColumn>, yml_file: &str) -> ExportedDataset {
+        ExportedDataset {
+            id: Uuid::new_v4(),
+            name: "orders".to_string(),
+            data_source_name: "warehouse".to_string(),
+            env: "dev".to_string(),
+            schema: "public".to_string(),
+            database: None,
+            description: Some(description.to_string()),
+            yml_file: Some(yml_file.to_string()),
+            columns,
+        }
+    }
+
+    fn column(name: &str, description: &str, stored_values: bool) -> ExportedColumn {
+        ExportedColumn {
+            name: name.to_string(),
+            description: Some(description.to_string()),
+            semantic_type: Some("dimension".to_string()),
+            expr: Some(name.to_string()),
+            type_: "text".to_string(),
+            stored_values,
+        }
+    }
+
+    const DEPLOYED: &str = r#"
+models:
+  - name: orders
+    description: Orders
+    dimensions:
+      - name: status
+        description: Order status
+        searchable: false
+      - name: region
+        description: Region
+"#;
+
+    #[test]
+    fn test_pull_model_applies_server_edits_and_reports_conflicts() {
+        let mut local: YamlFile = serde_yaml::from_str(DEPLOYED).unwrap();
+        // Edited locally since the deploy
+        local.models[0].dimensions[1].description = Some("Sales region".to_string());
+
+        let dataset = dataset(
+            "All orders",
+            vec![
+                column("status", "Order status", true),
+                column("region", "Shipping region", false),
+            ],
+            DEPLOYED,
+        );
+
+        let pulled = pull_model(&local.models[0], &dataset);
+
+        assert_eq!(pulled.model.description.as_deref(), Some("All orders"));
+        assert_eq!(pulled.model.dimensions[0].searchable, Some(true));
+        assert_eq!(
+            pulled.model.dimensions[1].description.as_deref(),
+            Some("Sales region")
+        );
+        assert_eq!(pulled.conflicts, vec!["orders.region.description"]);
+    }
+}
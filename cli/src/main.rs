@@ -62,6 +62,9 @@ pub enum Commands {
         /// Output YML files in a flat structure instead of maintaining directory hierarchy
         #[arg(long, default_value_t = false)]
         flat_structure: bool,
+        /// Resolve conflicts with local edits interactively instead of writing conflict markers
+        #[arg(long, default_value_t = false)]
+        interactive: bool,
     },
     Deploy {
         #[arg(long)]
@@ -71,6 +74,35 @@ pub enum Commands {
         /// Recursively search for model files in subdirectories
         #[arg(long, default_value_t = true)]
         recursive: bool,
+        /// Environment to deploy to, using its overrides from the `environments` section of buster.yml
+        #[arg(long, default_value = "dev")]
+        env: String,
+        /// Remove datasets from the server that are no longer defined in the project
+        #[arg(long, default_value_t = false)]
+        prune: bool,
+        /// With --prune, also remove datasets that threads or dashboards still reference
+        #[arg(long, default_value_t = false, requires = "prune")]
+        force: bool,
+    },
+    /// Pull descriptions and settings edited in Buster back into local model files
+    Pull {
+        #[arg(long)]
+        path: Option<String>,
+        /// Environment to pull from
+        #[arg(long, default_value = "dev")]
+        env: String,
+        /// Preview the changes without writing any files
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+    },
+    /// Convert dbt Semantic Layer (MetricFlow) definitions into Buster model files
+    ImportMetricflow {
+        /// Directory to search for semantic_models and metrics YAML (defaults to current directory)
+        #[arg(long)]
+        path: Option<String>,
+        /// Directory to write model files to (defaults to the directory containing buster.yml)
+        #[arg(long)]
+        destination_path: Option<String>,
     },
 }
 
@@ -135,6 +167,7 @@ async fn main() {
             schema,
             database,
             flat_structure,
+            interactive,
         } => {
             commands::generate(
                 source_path.as_deref(),
@@ -143,6 +176,7 @@ async fn main() {
                 schema,
                 database,
                 flat_structure,
+                interactive,
             )
             .await
         }
@@ -150,7 +184,17 @@ async fn main() {
             path,
             dry_run,
             recursive,
-        } => deploy(path.as_deref(), dry_run, recursive).await,
+            env,
+            prune,
+            force,
+        } => deploy(path.as_deref(), dry_run, recursive, &env, prune, force).await,
+        Commands::Pull { path, env, dry_run } => {
+            commands::pull(path.as_deref(), &env, dry_run).await
+        }
+        Commands::ImportMetricflow {
+            path,
+            destination_path,
+        } => commands::import_metricflow(path.as_deref(), destination_path.as_deref()).await,
     };
 
     if let Err(e) = result {
@@ -5,9 +5,12 @@ use reqwest::{
 };
 use std::error::Error as StdError;
 
+use crate::utils::profiles::Credential;
+
 use super::{
     PostDataSourcesRequest, DeployDatasetsRequest, ValidateApiKeyRequest, ValidateApiKeyResponse,
-    DeployDatasetsResponse, GenerateApiRequest, GenerateApiResponse,
+    DeployDatasetsOptions, DeployDatasetsResponse, ExportDatasetsRequest, ExportedDataset,
+    GenerateApiRequest, GenerateApiResponse,
 };
 
 pub struct BusterClient {
@@ -123,13 +126,46 @@ impl BusterClient {
         }
     }
 
-    pub async fn deploy_datasets(&self, req_body: Vec<DeployDatasetsRequest>) -> Result<DeployDatasetsResponse> {
+    /// Asks the server to connect with the credentials without saving them.
+    pub async fn test_data_source(&self, credential: &Credential) -> Result<()> {
+        let headers = self.build_headers()?;
+
+        match self
+            .client
+            .post(format!("{}/api/v1/data_sources/test", self.base_url))
+            .headers(headers)
+            .json(credential)
+            .send()
+            .await
+        {
+            Ok(res) => {
+                if !res.status().is_success() {
+                    return Err(anyhow::anyhow!(
+                        "POST /api/v1/data_sources/test failed: {}",
+                        res.text().await?
+                    ));
+                }
+                Ok(())
+            }
+            Err(e) => Err(anyhow::anyhow!(
+                "POST /api/v1/data_sources/test failed: {}",
+                e
+            )),
+        }
+    }
+
+    pub async fn deploy_datasets(
+        &self,
+        req_body: Vec<DeployDatasetsRequest>,
+        options: &DeployDatasetsOptions,
+    ) -> Result<DeployDatasetsResponse> {
         let headers = self.build_headers()?;
 
         match self
             .client
             .post(format!("{}/api/v1/datasets/deploy", self.base_url))
             .headers(headers)
+            .query(options)
             .json(&req_body)
             .send()
             .await
@@ -147,6 +183,33 @@ impl BusterClient {
         }
     }
 
+    pub async fn export_datasets(
+        &self,
+        req_body: ExportDatasetsRequest,
+    ) -> Result<Vec<ExportedDataset>> {
+        let headers = self.build_headers()?;
+
+        match self
+            .client
+            .post(format!("{}/api/v1/datasets/export", self.base_url))
+            .headers(headers)
+            .json(&req_body)
+            .send()
+            .await
+        {
+            Ok(res) => {
+                if !res.status().is_success() {
+                    return Err(anyhow::anyhow!(
+                        "POST /api/v1/datasets/export failed: {}",
+                        res.text().await?
+                    ));
+                }
+                Ok(res.json().await?)
+            }
+            Err(e) => Err(anyhow::anyhow!("POST /api/v1/datasets/export failed: {}", e)),
+        }
+    }
+
     pub async fn generate_datasets(&self, req_body: GenerateApiRequest) -> Result<GenerateApiResponse> {
         let headers = self.build_headers()?;
 
@@ -92,9 +92,64 @@ pub enum ValidationErrorType {
     DataSourceMismatch,
 }
 
+#[derive(Debug, Default, Serialize)]
+pub struct DeployDatasetsOptions {
+    pub prune: bool,
+    pub dry_run: bool,
+    pub force: bool,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct DeployDatasetsResponse {
     pub results: Vec<ValidationResult>,
+    #[serde(default)]
+    pub pruned: Vec<PrunedDataset>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PrunedDataset {
+    pub data_source_name: String,
+    pub name: String,
+    pub deleted: bool,
+    pub referenced_by: Vec<DatasetReference>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DatasetReference {
+    pub id: Uuid,
+    pub name: Option<String>,
+    pub asset_type: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportDatasetsRequest {
+    pub env: String,
+    pub data_source_name: Option<String>,
+    pub dataset_names: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportedDataset {
+    pub id: Uuid,
+    pub name: String,
+    pub data_source_name: String,
+    pub env: String,
+    pub schema: String,
+    pub database: Option<String>,
+    pub description: Option<String>,
+    pub yml_file: Option<String>,
+    pub columns: Vec<ExportedColumn>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportedColumn {
+    pub name: String,
+    pub description: Option<String>,
+    pub semantic_type: Option<String>,
+    pub expr: Option<String>,
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub stored_values: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -0,0 +1,693 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use serde_yaml::{Mapping, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// A dbt model as resolved by the last `dbt compile`/`dbt run`, read from `target/manifest.json`.
+#[derive(Debug, Clone)]
+pub struct DbtModel {
+    pub unique_id: String,
+    /// The relation name, which is the alias when the model sets one.
+    pub name: String,
+    pub database: Option<String>,
+    pub schema: String,
+    pub description: String,
+    /// Path of the model's SQL file, relative to the dbt project root.
+    pub original_file_path: PathBuf,
+    pub tags: Vec<String>,
+    pub columns: Vec<DbtColumn>,
+    pub entities: Vec<DbtEntity>,
+    /// Relation names of the models, seeds and snapshots this model `ref`s.
+    pub depends_on: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DbtColumn {
+    pub name: String,
+    pub description: String,
+    /// The warehouse type, from the catalog when there is one and otherwise the documented
+    /// `data_type`.
+    pub data_type: Option<String>,
+}
+
+/// An entity derived from a dbt test: `unique` makes a primary entity and `relationships` a
+/// foreign one named after the referenced model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DbtEntity {
+    pub name: String,
+    pub expr: String,
+    pub entity_type: String,
+    pub description: String,
+}
+
+#[derive(Debug)]
+pub struct DbtProject {
+    pub root: PathBuf,
+    pub manifest_path: PathBuf,
+    pub has_catalog: bool,
+    pub models: Vec<DbtModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DbtProjectFile {
+    #[serde(rename = "target-path")]
+    target_path: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    metadata: ManifestMetadata,
+    nodes: HashMap<String, ManifestNode>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ManifestMetadata {
+    project_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ManifestNode {
+    unique_id: String,
+    resource_type: String,
+    package_name: String,
+    name: String,
+    alias: Option<String>,
+    database: Option<String>,
+    schema: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    original_file_path: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    columns: HashMap<String, ManifestColumn>,
+    #[serde(default)]
+    depends_on: DependsOn,
+    test_metadata: Option<TestMetadata>,
+    attached_node: Option<String>,
+    column_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ManifestColumn {
+    name: String,
+    #[serde(default)]
+    description: String,
+    data_type: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct DependsOn {
+    #[serde(default)]
+    nodes: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TestMetadata {
+    name: String,
+    #[serde(default)]
+    kwargs: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Catalog {
+    #[serde(default)]
+    nodes: HashMap<String, CatalogNode>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CatalogNode {
+    metadata: CatalogMetadata,
+    #[serde(default)]
+    columns: HashMap<String, CatalogColumn>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CatalogMetadata {
+    schema: String,
+    name: String,
+    database: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CatalogColumn {
+    name: String,
+    index: Option<i64>,
+    #[serde(rename = "type")]
+    data_type: Option<String>,
+    comment: Option<String>,
+}
+
+impl DbtProject {
+    /// Finds the dbt project containing `dir` and loads its artifacts.
+    ///
+    /// Returns `None` when there is no `dbt_project.yml` above `dir` or the project hasn't been
+    /// compiled yet, so callers can fall back to scanning SQL files.
+    pub fn load(dir: &Path) -> Result<Option<Self>> {
+        let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        let root = match dir.ancestors().find(|d| d.join("dbt_project.yml").exists()) {
+            Some(root) => root.to_path_buf(),
+            None => return Ok(None),
+        };
+
+        let project_content = std::fs::read_to_string(root.join("dbt_project.yml"))
+            .map_err(|e| anyhow!("Failed to read dbt_project.yml: {}", e))?;
+        let project: DbtProjectFile = serde_yaml::from_str(&project_content)
+            .map_err(|e| anyhow!("Failed to parse dbt_project.yml: {}", e))?;
+        let target_dir = root.join(project.target_path.as_deref().unwrap_or("target"));
+
+        let manifest_path = target_dir.join("manifest.json");
+        if !manifest_path.exists() {
+            return Ok(None);
+        }
+
+        let manifest = std::fs::read_to_string(&manifest_path)
+            .map_err(|e| anyhow!("Failed to read {}: {}", manifest_path.display(), e))?;
+
+        let catalog_path = target_dir.join("catalog.json");
+        let catalog = if catalog_path.exists() {
+            Some(
+                std::fs::read_to_string(&catalog_path)
+                    .map_err(|e| anyhow!("Failed to read {}: {}", catalog_path.display(), e))?,
+            )
+        } else {
+            None
+        };
+
+        let models = Self::parse_models(&manifest, catalog.as_deref())?;
+
+        Ok(Some(Self {
+            root,
+            manifest_path,
+            has_catalog: catalog.is_some(),
+            models,
+        }))
+    }
+
+    /// Builds the root project's models from the contents of `manifest.json` and, when
+    /// available, `catalog.json`. Models from installed packages are left out.
+    pub fn parse_models(manifest: &str, catalog: Option<&str>) -> Result<Vec<DbtModel>> {
+        let manifest: Manifest = serde_json::from_str(manifest)
+            .map_err(|e| anyhow!("Failed to parse dbt manifest: {}", e))?;
+        let mut catalog: Catalog = match catalog {
+            Some(catalog) => serde_json::from_str(catalog)
+                .map_err(|e| anyhow!("Failed to parse dbt catalog: {}", e))?,
+            None => Catalog {
+                nodes: HashMap::new(),
+            },
+        };
+
+        let is_root_model = |node: &ManifestNode| {
+            node.resource_type == "model"
+                && manifest
+                    .metadata
+                    .project_name
+                    .as_ref()
+                    .is_none_or(|project| &node.package_name == project)
+        };
+
+        let mut models: HashMap<&String, DbtModel> = HashMap::new();
+        for node in manifest.nodes.values().filter(|node| is_root_model(node)) {
+            let catalog_node = catalog.nodes.remove(&node.unique_id);
+
+            // The catalog reflects what was actually built, so it wins over the manifest.
+            let (name, schema, database) = match &catalog_node {
+                Some(c) => (
+                    c.metadata.name.clone(),
+                    c.metadata.schema.clone(),
+                    c.metadata.database.clone(),
+                ),
+                None => (
+                    node.alias.clone().unwrap_or_else(|| node.name.clone()),
+                    node.schema.clone(),
+                    node.database.clone(),
+                ),
+            };
+
+            let mut columns: Vec<DbtColumn> = node
+                .columns
+                .values()
+                .map(|c| DbtColumn {
+                    name: c.name.clone(),
+                    description: c.description.clone(),
+                    data_type: c.data_type.clone(),
+                })
+                .collect();
+            columns.sort_by(|a, b| a.name.cmp(&b.name));
+
+            // Columns only documented in the database still carry their comments, and the built
+            // column's type wins over the documented one.
+            if let Some(catalog_node) = catalog_node {
+                let mut catalog_columns: Vec<CatalogColumn> =
+                    catalog_node.columns.into_values().collect();
+                catalog_columns.sort_by_key(|c| c.index);
+
+                for catalog_column in catalog_columns {
+                    let comment = catalog_column.comment.unwrap_or_default();
+                    match columns
+                        .iter_mut()
+                        .find(|c| c.name.eq_ignore_ascii_case(&catalog_column.name))
+                    {
+                        Some(column) => {
+                            if column.description.is_empty() {
+                                column.description = comment;
+                            }
+                            if catalog_column.data_type.is_some() {
+                                column.data_type = catalog_column.data_type;
+                            }
+                        }
+                        None => columns.push(DbtColumn {
+                            name: catalog_column.name.to_lowercase(),
+                            description: comment,
+                            data_type: catalog_column.data_type,
+                        }),
+                    }
+                }
+            }
+
+            models.insert(
+                &node.unique_id,
+                DbtModel {
+                    unique_id: node.unique_id.clone(),
+                    name,
+                    database,
+                    schema,
+                    description: node.description.clone(),
+                    original_file_path: PathBuf::from(&node.original_file_path),
+                    tags: node.tags.clone(),
+                    columns,
+                    entities: vec![],
+                    depends_on: vec![],
+                },
+            );
+        }
+
+        let relation_names: HashMap<String, String> = models
+            .values()
+            .map(|m| (m.unique_id.clone(), m.name.clone()))
+            .collect();
+
+        // Models from packages, seeds and snapshots aren't loaded, so they keep their dbt name.
+        for node in manifest.nodes.values() {
+            let model = match models.get_mut(&node.unique_id) {
+                Some(model) => model,
+                None => continue,
+            };
+
+            let mut depends_on: Vec<String> = node
+                .depends_on
+                .nodes
+                .iter()
+                .filter(|id| {
+                    ["model.", "seed.", "snapshot."]
+                        .iter()
+                        .any(|prefix| id.starts_with(prefix))
+                })
+                .filter_map(|id| {
+                    relation_names
+                        .get(id)
+                        .cloned()
+                        .or_else(|| id.rsplit('.').next().map(String::from))
+                })
+                .collect();
+            depends_on.sort();
+            depends_on.dedup();
+
+            model.depends_on = depends_on;
+        }
+
+        let mut tests: Vec<&ManifestNode> = manifest
+            .nodes
+            .values()
+            .filter(|node| node.resource_type == "test" && node.test_metadata.is_some())
+            .collect();
+        tests.sort_by(|a, b| a.unique_id.cmp(&b.unique_id));
+
+        let mut primary_candidates: HashMap<String, Vec<(String, bool)>> = HashMap::new();
+        let mut not_null_columns: Vec<(String, String)> = Vec::new();
+
+        for test in tests {
+            let test_metadata = test.test_metadata.as_ref().unwrap();
+            let model_id = match test.attached_node.clone().or_else(|| {
+                (test.depends_on.nodes.len() == 1).then(|| test.depends_on.nodes[0].clone())
+            }) {
+                Some(id) => id,
+                None => continue,
+            };
+            let column = match test.column_name.clone().or_else(|| {
+                test_metadata
+                    .kwargs
+                    .get("column_name")
+                    .and_then(|v| v.as_str())
+                    .map(String::from)
+            }) {
+                Some(column) => column,
+                None => continue,
+            };
+
+            match test_metadata.name.as_str() {
+                "unique" => primary_candidates
+                    .entry(model_id)
+                    .or_default()
+                    .push((column, false)),
+                "not_null" => not_null_columns.push((model_id, column)),
+                "relationships" => {
+                    // The model on the other side of the ref is the dependency that isn't the
+                    // model under test.
+                    let target = test
+                        .depends_on
+                        .nodes
+                        .iter()
+                        .find(|id| **id != model_id)
+                        .and_then(|id| relation_names.get(id));
+                    let field = test_metadata.kwargs.get("field").and_then(|v| v.as_str());
+
+                    if let (Some(model), Some(target), Some(field)) =
+                        (models.get_mut(&model_id), target, field)
+                    {
+                        model.entities.push(DbtEntity {
+                            name: target.clone(),
+                            expr: column,
+                            entity_type: "foreign".to_string(),
+                            description: format!("References {}.{}", target, field),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // A model gets one primary entity, preferring a unique column that is also not null.
+        for (model_id, mut candidates) in primary_candidates {
+            let model = match models.get_mut(&model_id) {
+                Some(model) => model,
+                None => continue,
+            };
+
+            for (column, not_null) in candidates.iter_mut() {
+                *not_null = not_null_columns.contains(&(model_id.clone(), column.clone()));
+            }
+            let (column, _) = candidates
+                .iter()
+                .find(|(_, not_null)| *not_null)
+                .unwrap_or(&candidates[0]);
+
+            let description = model
+                .columns
+                .iter()
+                .find(|c| &c.name == column && !c.description.is_empty())
+                .map(|c| c.description.clone())
+                .unwrap_or_else(|| format!("Primary key of {}", model.name));
+
+            model.entities.insert(
+                0,
+                DbtEntity {
+                    name: model.name.clone(),
+                    expr: column.clone(),
+                    entity_type: "primary".to_string(),
+                    description,
+                },
+            );
+        }
+
+        let mut models: Vec<DbtModel> = models.into_values().collect();
+        models.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(models)
+    }
+
+    /// Looks a model up by its relation name, falling back to the dbt model name.
+    pub fn model(&self, name: &str) -> Option<&DbtModel> {
+        self.models
+            .iter()
+            .find(|m| m.name.eq_ignore_ascii_case(name))
+            .or_else(|| {
+                self.models
+                    .iter()
+                    .find(|m| m.unique_id.rsplit('.').next() == Some(name))
+            })
+    }
+}
+
+impl DbtModel {
+    /// Fills a generated Buster model YAML with what dbt already knows: the model and column
+    /// descriptions and types, the entities from its tests, the models it `ref`s and, when they
+    /// differ from buster.yml, its schema and database.
+    pub fn seed_yaml(
+        &self,
+        yml_content: &str,
+        default_schema: Option<&str>,
+        default_database: Option<&str>,
+    ) -> Result<String> {
+        let mut doc: Value = serde_yaml::from_str(yml_content)
+            .map_err(|e| anyhow!("Failed to parse generated YAML for {}: {}", self.name, e))?;
+
+        let model = match doc
+            .get_mut("models")
+            .and_then(|models| models.as_sequence_mut())
+            .and_then(|models| models.first_mut())
+            .and_then(|model| model.as_mapping_mut())
+        {
+            Some(model) => model,
+            None => return Err(anyhow!("Generated YAML for {} has no model", self.name)),
+        };
+
+        if !self.description.is_empty() {
+            model.insert("description".into(), self.description.clone().into());
+        }
+        if default_schema != Some(self.schema.as_str()) {
+            model.insert("schema".into(), self.schema.clone().into());
+        }
+        if let Some(database) = &self.database {
+            if default_database != Some(database.as_str()) {
+                model.insert("database".into(), database.clone().into());
+            }
+        }
+
+        for section in ["dimensions", "measures"] {
+            let fields = match model.get_mut(section).and_then(|f| f.as_sequence_mut()) {
+                Some(fields) => fields,
+                None => continue,
+            };
+
+            for field in fields.iter_mut().filter_map(|f| f.as_mapping_mut()) {
+                let name = field
+                    .get("name")
+                    .and_then(|n| n.as_str())
+                    .unwrap_or_default();
+                let column = match self
+                    .columns
+                    .iter()
+                    .find(|c| c.name.eq_ignore_ascii_case(name))
+                {
+                    Some(column) => column.clone(),
+                    None => continue,
+                };
+
+                if !column.description.is_empty() {
+                    field.insert("description".into(), column.description.into());
+                }
+                if let Some(data_type) = column.data_type {
+                    field.insert("type".into(), data_type.into());
+                }
+            }
+        }
+
+        if !self.entities.is_empty() {
+            let entities: Vec<Value> = self
+                .entities
+                .iter()
+                .map(|entity| {
+                    let mut mapping = Mapping::new();
+                    mapping.insert("name".into(), entity.name.clone().into());
+                    mapping.insert("expr".into(), entity.expr.clone().into());
+                    mapping.insert("type".into(), entity.entity_type.clone().into());
+                    mapping.insert("description".into(), entity.description.clone().into());
+                    Value::Mapping(mapping)
+                })
+                .collect();
+            model.insert("entities".into(), Value::Sequence(entities));
+        }
+
+        if !self.depends_on.is_empty() {
+            let depends_on: Vec<Value> = self
+                .depends_on
+                .iter()
+                .map(|name| name.clone().into())
+                .collect();
+            model.insert("depends_on".into(), Value::Sequence(depends_on));
+        }
+
+        Ok(serde_yaml::to_string(&doc)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"{
+        "metadata": {"project_name": "shop"},
+        "nodes": {
+            "model.shop.customers": {
+                "unique_id": "model.shop.customers",
+                "resource_type": "model",
+                "package_name": "shop",
+                "name": "customers",
+                "alias": "dim_customers",
+                "database": "analytics",
+                "schema": "marts",
+                "description": "One row per customer",
+                "original_file_path": "models/marts/customers.sql",
+                "tags": ["core"],
+                "columns": {
+                    "customer_id": {"name": "customer_id", "description": "Customer key", "data_type": "varchar"}
+                },
+                "depends_on": {"nodes": []}
+            },
+            "model.shop.orders": {
+                "unique_id": "model.shop.orders",
+                "resource_type": "model",
+                "package_name": "shop",
+                "name": "orders",
+                "alias": null,
+                "database": "analytics",
+                "schema": "marts",
+                "description": "",
+                "original_file_path": "models/marts/orders.sql",
+                "tags": [],
+                "columns": {},
+                "depends_on": {"nodes": ["model.shop.customers", "seed.shop.order_statuses", "source.shop.raw.orders"]}
+            },
+            "model.audit.audit_log": {
+                "unique_id": "model.audit.audit_log",
+                "resource_type": "model",
+                "package_name": "audit",
+                "name": "audit_log",
+                "schema": "audit",
+                "original_file_path": "models/audit_log.sql"
+            },
+            "test.shop.unique_customers_customer_id.1": {
+                "unique_id": "test.shop.unique_customers_customer_id.1",
+                "resource_type": "test",
+                "package_name": "shop",
+                "name": "unique_customers_customer_id",
+                "schema": "marts",
+                "attached_node": "model.shop.customers",
+                "column_name": "customer_id",
+                "test_metadata": {"name": "unique", "kwargs": {"column_name": "customer_id"}},
+                "depends_on": {"nodes": ["model.shop.customers"]}
+            },
+            "test.shop.relationships_orders_customer_id.2": {
+                "unique_id": "test.shop.relationships_orders_customer_id.2",
+                "resource_type": "test",
+                "package_name": "shop",
+                "name": "relationships_orders_customer_id",
+                "schema": "marts",
+                "attached_node": "model.shop.orders",
+                "column_name": "customer_id",
+                "test_metadata": {
+                    "name": "relationships",
+                    "kwargs": {"column_name": "customer_id", "to": "ref('customers')", "field": "customer_id"}
+                },
+                "depends_on": {"nodes": ["model.shop.customers", "model.shop.orders"]}
+            }
+        }
+    }"#;
+
+    const CATALOG: &str = r#"{
+        "nodes": {
+            "model.shop.orders": {
+                "metadata": {"schema": "marts", "name": "orders", "database": "analytics"},
+                "columns": {
+                    "ORDER_ID": {"name": "ORDER_ID", "index": 1, "type": "NUMBER", "comment": "Order key"},
+                    "CUSTOMER_ID": {"name": "CUSTOMER_ID", "index": 2, "type": "NUMBER", "comment": null}
+                }
+            }
+        }
+    }"#;
+
+    #[test]
+    fn test_parse_models_from_manifest_and_catalog() {
+        let models = DbtProject::parse_models(MANIFEST, Some(CATALOG)).unwrap();
+
+        let names: Vec<&str> = models.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["dim_customers", "orders"]);
+
+        let customers = &models[0];
+        assert_eq!(customers.schema, "marts");
+        assert_eq!(customers.database.as_deref(), Some("analytics"));
+        assert_eq!(customers.tags, vec!["core".to_string()]);
+        assert_eq!(customers.columns[0].data_type.as_deref(), Some("varchar"));
+        assert!(customers.depends_on.is_empty());
+        assert_eq!(
+            customers.entities,
+            vec![DbtEntity {
+                name: "dim_customers".to_string(),
+                expr: "customer_id".to_string(),
+                entity_type: "primary".to_string(),
+                description: "Customer key".to_string(),
+            }]
+        );
+
+        let orders = &models[1];
+        assert_eq!(orders.columns.len(), 2);
+        assert_eq!(orders.columns[0].name, "order_id");
+        assert_eq!(orders.columns[0].description, "Order key");
+        assert_eq!(orders.columns[0].data_type.as_deref(), Some("NUMBER"));
+        assert_eq!(orders.depends_on, vec!["dim_customers", "order_statuses"]);
+        assert_eq!(orders.entities.len(), 1);
+        assert_eq!(orders.entities[0].name, "dim_customers");
+        assert_eq!(orders.entities[0].entity_type, "foreign");
+        assert_eq!(orders.entities[0].expr, "customer_id");
+    }
+
+    #[test]
+    fn test_seed_yaml_with_dbt_metadata() {
+        let models = DbtProject::parse_models(MANIFEST, None).unwrap();
+        let generated = r#"
+models:
+  - name: dim_customers
+    description: Generated model for dim_customers
+    dimensions:
+      - name: customer_id
+        expr: customer_id
+        type: string
+        description: An identifier
+    measures: []
+"#;
+
+        let seeded = models[0]
+            .seed_yaml(generated, Some("public"), Some("analytics"))
+            .unwrap();
+        let doc: Value = serde_yaml::from_str(&seeded).unwrap();
+        let model = &doc["models"][0];
+
+        assert_eq!(model["description"], "One row per customer");
+        assert_eq!(model["schema"], "marts");
+        assert!(model.get("database").is_none());
+        assert_eq!(model["dimensions"][0]["description"], "Customer key");
+        assert_eq!(model["dimensions"][0]["type"], "varchar");
+        assert!(model.get("depends_on").is_none());
+        assert_eq!(model["entities"][0]["type"], "primary");
+        assert_eq!(model["entities"][0]["expr"], "customer_id");
+
+        let generated = r#"
+models:
+  - name: orders
+    description: Generated model for orders
+    dimensions: []
+    measures: []
+"#;
+
+        let seeded = models[1]
+            .seed_yaml(generated, Some("marts"), Some("analytics"))
+            .unwrap();
+        let doc: Value = serde_yaml::from_str(&seeded).unwrap();
+
+        assert_eq!(doc["models"][0]["depends_on"][0], "dim_customers");
+        assert_eq!(doc["models"][0]["depends_on"][1], "order_statuses");
+    }
+}
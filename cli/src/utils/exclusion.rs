@@ -3,6 +3,7 @@ use glob::Pattern;
 use lazy_static::lazy_static;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 use globwalk;
@@ -13,14 +14,66 @@ pub struct BusterConfig {
     pub data_source_name: Option<String>,
     #[serde(alias = "dataset_id")]     // BigQuery alias for schema
     pub schema: Option<String>,        // For SQL DBs: schema, For BigQuery: dataset ID
-    #[serde(alias = "project_id")]     // BigQuery alias for database
-    pub database: Option<String>,      // For SQL DBs: database, For BigQuery: project ID
+    #[serde(alias = "project_id", alias = "catalog")] // BigQuery and Databricks aliases for database
+    pub database: Option<String>,      // For SQL DBs: database, For BigQuery: project ID, For Databricks: catalog
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warehouse: Option<String>,     // Snowflake warehouse the data source runs queries on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,          // Snowflake role the data source connects as
     pub exclude_files: Option<Vec<String>>,
     pub exclude_tags: Option<Vec<String>>,
     pub model_paths: Option<Vec<String>>,  // Paths to SQL model files/directories
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environments: Option<HashMap<String, EnvironmentConfig>>, // Per-environment overrides, keyed by env name
+}
+
+/// Overrides applied on top of the top-level buster.yml settings when deploying to an environment
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct EnvironmentConfig {
+    pub data_source_name: Option<String>,
+    #[serde(alias = "dataset_id")]
+    pub schema: Option<String>,
+    #[serde(alias = "project_id")]
+    pub database: Option<String>,
 }
 
 impl BusterConfig {
+    /// Resolves the configuration for an environment.
+    /// Without an `environments` section every environment uses the top-level settings.
+    /// With one, the environment must be listed and its settings override the top-level ones.
+    pub fn for_env(&self, env: &str) -> Result<Self> {
+        let environments = match &self.environments {
+            Some(environments) => environments,
+            None => return Ok(self.clone()),
+        };
+
+        let env_config = match environments.get(env) {
+            Some(env_config) => env_config,
+            None => {
+                let mut available: Vec<&String> = environments.keys().collect();
+                available.sort();
+                return Err(anyhow!(
+                    "Environment '{}' is not defined in buster.yml. Available environments: {}",
+                    env,
+                    available.iter().map(|e| e.as_str()).collect::<Vec<_>>().join(", ")
+                ));
+            }
+        };
+
+        let mut config = self.clone();
+        if env_config.data_source_name.is_some() {
+            config.data_source_name = env_config.data_source_name.clone();
+        }
+        if env_config.schema.is_some() {
+            config.schema = env_config.schema.clone();
+        }
+        if env_config.database.is_some() {
+            config.database = env_config.database.clone();
+        }
+
+        Ok(config)
+    }
+
     /// Validates all exclude patterns to ensure they are valid glob patterns
     pub fn validate_exclude_patterns(&self) -> Result<()> {
         if let Some(patterns) = &self.exclude_files {
@@ -257,20 +310,25 @@ impl ExclusionManager {
             // Split the tags string and trim each tag
             let tags: Vec<String> = tags_str
                 .split(',')
-                .map(|tag| tag.trim().trim_matches('"').trim_matches('\'').to_lowercase())
+                .map(|tag| tag.trim().trim_matches('"').trim_matches('\'').to_string())
                 .collect();
             
-            // Check if any excluded tag is in the content's tags
-            for exclude_tag in &self.exclude_tags {
-                let exclude_tag_lower = exclude_tag.to_lowercase();
-                if tags.contains(&exclude_tag_lower) {
-                    return (true, Some(exclude_tag.clone()));
-                }
-            }
+            return self.should_exclude_tags(&tags);
         }
         
         (false, None)
     }
+
+    /// Check if any of a model's tags are excluded, for tags that are already known (e.g. from a dbt manifest)
+    pub fn should_exclude_tags(&self, tags: &[String]) -> (bool, Option<String>) {
+        for exclude_tag in &self.exclude_tags {
+            if tags.iter().any(|tag| tag.eq_ignore_ascii_case(exclude_tag)) {
+                return (true, Some(exclude_tag.clone()));
+            }
+        }
+
+        (false, None)
+    }
 }
 
 /// A progress reporter for file processing with exclusion support
@@ -5,7 +5,7 @@ use tokio::fs;
 
 use crate::utils::{
     BusterClient, DeployDatasetsColumnsRequest, DeployDatasetsEntityRelationshipsRequest,
-    DeployDatasetsRequest,
+    DeployDatasetsOptions, DeployDatasetsRequest,
 };
 
 use super::{
@@ -278,7 +278,9 @@ pub async fn upload_model_files(
 
     let buster = BusterClient::new(buster_creds.url, buster_creds.api_key)?;
 
-    if let Err(e) = buster.deploy_datasets(post_datasets_req_body).await {
+    if let Err(e) = buster
+        .deploy_datasets(post_datasets_req_body, &DeployDatasetsOptions::default())
+        .await {
         return Err(anyhow::anyhow!(
             "Failed to upload model files to Buster: {}",
             e
@@ -1,8 +1,10 @@
 use anyhow::Result;
 use dirs::home_dir;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use serde_yaml::Value;
+use serde_yaml::{Mapping, Value};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use tokio::fs;
 
 use crate::utils::{BusterClient, PostDataSourcesRequest};
@@ -213,3 +215,143 @@ pub async fn get_project_profile() -> Result<(String, Profile)> {
 
     Ok((project_config.profile, profile.clone()))
 }
+
+/// A target from dbt's `profiles.yml`. The fields are kept as raw YAML because each adapter names
+/// its connection settings differently.
+#[derive(Debug, Clone)]
+pub struct DbtTarget {
+    pub profile: String,
+    pub target: String,
+    /// The dbt adapter, e.g. `postgres` or `snowflake`.
+    pub adapter: String,
+    fields: Mapping,
+}
+
+impl DbtTarget {
+    /// Returns the first of `keys` the target sets, with `{{ env_var(...) }}` resolved.
+    pub fn get(&self, keys: &[&str]) -> Option<String> {
+        keys.iter().find_map(|key| {
+            let value = match self.fields.get(*key)? {
+                Value::String(s) => resolve_env_var(s)?,
+                Value::Number(n) => n.to_string(),
+                Value::Bool(b) => b.to_string(),
+                _ => return None,
+            };
+            if value.trim().is_empty() {
+                None
+            } else {
+                Some(value)
+            }
+        })
+    }
+}
+
+/// Resolves a value written as `{{ env_var('NAME') }}` or `{{ env_var('NAME', 'default') }}`.
+/// Other templated values can't be resolved outside of dbt and are ignored.
+fn resolve_env_var(value: &str) -> Option<String> {
+    if !value.contains("{{") {
+        return Some(value.to_string());
+    }
+
+    let env_var = Regex::new(
+        r#"^\{\{\s*env_var\(\s*['"]([^'"]+)['"]\s*(?:,\s*['"]([^'"]*)['"]\s*)?\)\s*\}\}$"#,
+    )
+    .ok()?;
+    let captures = env_var.captures(value.trim())?;
+
+    std::env::var(&captures[1])
+        .ok()
+        .or_else(|| captures.get(2).map(|default| default.as_str().to_string()))
+}
+
+fn dbt_profiles_path() -> PathBuf {
+    match std::env::var("DBT_PROFILES_DIR") {
+        Ok(dir) => PathBuf::from(dir).join("profiles.yml"),
+        Err(_) => home_dir().unwrap_or_default().join(".dbt").join("profiles.yml"),
+    }
+}
+
+/// Finds the `profiles.yml` target the dbt project in `project_dir` runs against by default.
+///
+/// Returns `None` when there is no dbt project or profile to read from.
+pub async fn get_dbt_profile_target(project_dir: &Path) -> Result<Option<DbtTarget>> {
+    let project_path = project_dir.join("dbt_project.yml");
+    if !fs::try_exists(&project_path).await? {
+        return Ok(None);
+    }
+
+    let project: Value = serde_yaml::from_str(&fs::read_to_string(&project_path).await?)?;
+    let profile_name = match project.get("profile").and_then(|p| p.as_str()) {
+        Some(name) => name.to_string(),
+        None => return Ok(None),
+    };
+
+    let profiles_path = dbt_profiles_path();
+    if !fs::try_exists(&profiles_path).await? {
+        return Ok(None);
+    }
+
+    let profiles: Value = serde_yaml::from_str(&fs::read_to_string(&profiles_path).await?)?;
+    Ok(find_dbt_target(&profiles, &profile_name))
+}
+
+fn find_dbt_target(profiles: &Value, profile_name: &str) -> Option<DbtTarget> {
+    let profile = profiles.get(profile_name)?;
+    let target = profile
+        .get("target")
+        .and_then(|t| t.as_str())
+        .and_then(resolve_env_var)
+        .unwrap_or_else(|| "dev".to_string());
+    let fields = profile.get("outputs")?.get(&target)?.as_mapping()?.clone();
+    let adapter = fields.get("type")?.as_str()?.to_lowercase();
+
+    Some(DbtTarget {
+        profile: profile_name.to_string(),
+        target,
+        adapter,
+        fields,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_dbt_target_reads_default_target() {
+        std::env::set_var("BUSTER_TEST_SNOWFLAKE_PASSWORD", "hunter2");
+        let profiles: Value = serde_yaml::from_str(
+            r#"
+jaffle_shop:
+  target: prod
+  outputs:
+    dev:
+      type: postgres
+      host: localhost
+    prod:
+      type: snowflake
+      account: ab12345.us-east-1
+      user: analytics
+      password: "{{ env_var('BUSTER_TEST_SNOWFLAKE_PASSWORD') }}"
+      role: "{{ env_var('BUSTER_TEST_UNSET_ROLE', 'transformer') }}"
+      warehouse: "{{ env_var('BUSTER_TEST_UNSET_WAREHOUSE') }}"
+      threads: 4
+"#,
+        )
+        .unwrap();
+
+        let target = find_dbt_target(&profiles, "jaffle_shop").unwrap();
+
+        assert_eq!(target.target, "prod");
+        assert_eq!(target.adapter, "snowflake");
+        assert_eq!(
+            target.get(&["username", "user"]).as_deref(),
+            Some("analytics")
+        );
+        assert_eq!(target.get(&["password"]).as_deref(), Some("hunter2"));
+        assert_eq!(target.get(&["role"]).as_deref(), Some("transformer"));
+        assert_eq!(target.get(&["warehouse"]), None);
+        assert_eq!(target.get(&["threads"]).as_deref(), Some("4"));
+        assert!(find_dbt_target(&profiles, "missing").is_none());
+    }
+}
@@ -0,0 +1,695 @@
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use serde_yaml::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use walkdir::WalkDir;
+
+use super::manifest::DbtProject;
+use super::yaml_diff_merger::{Dimension, Entity, Measure, Metric, Model, YamlFile};
+
+/// Buster models converted from MetricFlow definitions, with everything that couldn't be
+/// translated.
+#[derive(Debug, Default)]
+pub struct MetricFlowImport {
+    pub models: Vec<YamlFile>,
+    pub untranslated: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct MetricFlowFile {
+    #[serde(default)]
+    semantic_models: Vec<SemanticModel>,
+    #[serde(default)]
+    metrics: Vec<MetricFlowMetric>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SemanticModel {
+    name: String,
+    description: Option<String>,
+    model: String,
+    #[serde(default)]
+    entities: Vec<MetricFlowEntity>,
+    #[serde(default)]
+    dimensions: Vec<MetricFlowDimension>,
+    #[serde(default)]
+    measures: Vec<MetricFlowMeasure>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetricFlowEntity {
+    name: String,
+    #[serde(rename = "type")]
+    entity_type: String,
+    expr: Option<Value>,
+    description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetricFlowDimension {
+    name: String,
+    #[serde(rename = "type")]
+    dimension_type: String,
+    expr: Option<Value>,
+    description: Option<String>,
+    type_params: Option<DimensionTypeParams>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DimensionTypeParams {
+    time_granularity: Option<String>,
+    validity_params: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetricFlowMeasure {
+    name: String,
+    agg: String,
+    expr: Option<Value>,
+    description: Option<String>,
+    agg_params: Option<Value>,
+    non_additive_dimension: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetricFlowMetric {
+    name: String,
+    #[serde(rename = "type")]
+    metric_type: String,
+    description: Option<String>,
+    label: Option<String>,
+    #[serde(default)]
+    type_params: MetricTypeParams,
+    filter: Option<Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct MetricTypeParams {
+    measure: Option<MetricInput>,
+    numerator: Option<MetricInput>,
+    denominator: Option<MetricInput>,
+    expr: Option<String>,
+    metrics: Option<Vec<MetricInput>>,
+}
+
+/// Measures and metrics can be referenced by name or with extra options.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum MetricInput {
+    Name(String),
+    Detailed(Box<DetailedMetricInput>),
+}
+
+#[derive(Debug, Deserialize)]
+struct DetailedMetricInput {
+    name: String,
+    alias: Option<String>,
+    filter: Option<Value>,
+    offset_window: Option<Value>,
+    offset_to_grain: Option<Value>,
+}
+
+impl MetricInput {
+    fn name(&self) -> &str {
+        match self {
+            MetricInput::Name(name) => name,
+            MetricInput::Detailed(input) => &input.name,
+        }
+    }
+
+    fn alias(&self) -> &str {
+        match self {
+            MetricInput::Detailed(input) => input.alias.as_deref().unwrap_or(&input.name),
+            MetricInput::Name(name) => name,
+        }
+    }
+
+    // Options that change the input's meaning in ways a Buster expression can't.
+    fn unsupported_option(&self) -> Option<&'static str> {
+        let input = match self {
+            MetricInput::Detailed(input) => input,
+            MetricInput::Name(_) => return None,
+        };
+
+        if input.filter.is_some() {
+            Some("filter")
+        } else if input.offset_window.is_some() {
+            Some("offset_window")
+        } else if input.offset_to_grain.is_some() {
+            Some("offset_to_grain")
+        } else {
+            None
+        }
+    }
+}
+
+/// Reads every `semantic_models` and `metrics` definition in the YAML files under `path`.
+pub fn import_metricflow(
+    path: &Path,
+    dbt_project: Option<&DbtProject>,
+) -> Result<MetricFlowImport> {
+    let mut definitions = MetricFlowFile::default();
+
+    for entry in WalkDir::new(path)
+        .follow_links(true)
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
+        let file_path = entry.path();
+        let is_yaml = matches!(
+            file_path.extension().and_then(|ext| ext.to_str()),
+            Some("yml") | Some("yaml")
+        );
+        if !is_yaml {
+            continue;
+        }
+
+        let content = std::fs::read_to_string(file_path)
+            .map_err(|e| anyhow!("Failed to read {}: {}", file_path.display(), e))?;
+
+        // Plenty of YAML in a dbt project isn't MetricFlow, and not all of it parses on its own.
+        let doc: Value = match serde_yaml::from_str(&content) {
+            Ok(doc) => doc,
+            Err(_) => continue,
+        };
+        if doc.get("semantic_models").is_none() && doc.get("metrics").is_none() {
+            continue;
+        }
+
+        let file: MetricFlowFile = serde_yaml::from_value(doc).map_err(|e| {
+            anyhow!(
+                "Failed to parse MetricFlow definitions in {}: {}",
+                file_path.display(),
+                e
+            )
+        })?;
+        definitions.semantic_models.extend(file.semantic_models);
+        definitions.metrics.extend(file.metrics);
+    }
+
+    Ok(convert(definitions, dbt_project))
+}
+
+fn convert(definitions: MetricFlowFile, dbt_project: Option<&DbtProject>) -> MetricFlowImport {
+    let mut import = MetricFlowImport::default();
+
+    // Semantic models point at dbt models, and Buster models are named after the relation.
+    let model_names: Vec<String> = definitions
+        .semantic_models
+        .iter()
+        .map(|sm| {
+            let dbt_name = ref_name(&sm.model).unwrap_or_else(|| sm.name.clone());
+            dbt_project
+                .and_then(|project| project.model(&dbt_name))
+                .map(|model| model.name.clone())
+                .unwrap_or(dbt_name)
+        })
+        .collect();
+
+    // Foreign entities join to the semantic model that declares the same entity as its key.
+    let mut entity_owners: HashMap<&str, &str> = HashMap::new();
+    for (sm, model_name) in definitions.semantic_models.iter().zip(&model_names) {
+        for entity in &sm.entities {
+            if matches!(
+                entity.entity_type.as_str(),
+                "primary" | "unique" | "natural"
+            ) {
+                entity_owners.insert(&entity.name, model_name);
+            }
+        }
+    }
+
+    let mut measure_owners: HashMap<&str, usize> = HashMap::new();
+    let mut models = Vec::new();
+
+    for (index, (sm, model_name)) in definitions
+        .semantic_models
+        .iter()
+        .zip(&model_names)
+        .enumerate()
+    {
+        let mut model =
+            Model {
+                name: model_name.clone(),
+                description: Some(sm.description.clone().unwrap_or_else(|| {
+                    format!("Imported from MetricFlow semantic model {}", sm.name)
+                })),
+                entities: vec![],
+                dimensions: vec![],
+                measures: vec![],
+                metrics: vec![],
+                extra: HashMap::new(),
+            };
+
+        for entity in &sm.entities {
+            let expr = value_to_expr(entity.expr.as_ref()).unwrap_or_else(|| entity.name.clone());
+            let description = entity.description.clone().unwrap_or_default();
+
+            match entity.entity_type.as_str() {
+                "primary" | "unique" | "natural" => {
+                    model
+                        .entities
+                        .push(new_entity(model_name, expr, "primary", description))
+                }
+                "foreign" => match entity_owners.get(entity.name.as_str()) {
+                    Some(owner) => {
+                        model
+                            .entities
+                            .push(new_entity(owner, expr, "foreign", description))
+                    }
+                    None => import.untranslated.push(format!(
+                        "{}: foreign entity '{}' has no semantic model with it as a key",
+                        sm.name, entity.name
+                    )),
+                },
+                other => import.untranslated.push(format!(
+                    "{}: entity '{}' has unsupported type '{}'",
+                    sm.name, entity.name, other
+                )),
+            }
+        }
+
+        for dimension in &sm.dimensions {
+            let type_params = dimension.type_params.as_ref();
+            if type_params
+                .and_then(|p| p.validity_params.as_ref())
+                .is_some()
+            {
+                import.untranslated.push(format!(
+                    "{}: dimension '{}' uses validity_params, which Buster doesn't support",
+                    sm.name, dimension.name
+                ));
+                continue;
+            }
+
+            let dimension_type = match dimension.dimension_type.as_str() {
+                "categorical" => "string",
+                // Buster truncates to coarser grains at query time, so only sub-day data needs a timestamp.
+                "time" => match type_params.and_then(|p| p.time_granularity.as_deref()) {
+                    Some("nanosecond") | Some("microsecond") | Some("millisecond")
+                    | Some("second") | Some("minute") | Some("hour") => "timestamp",
+                    _ => "date",
+                },
+                other => {
+                    import.untranslated.push(format!(
+                        "{}: dimension '{}' has unsupported type '{}'",
+                        sm.name, dimension.name, other
+                    ));
+                    continue;
+                }
+            };
+
+            model.dimensions.push(Dimension {
+                name: dimension.name.clone(),
+                description: Some(dimension.description.clone().unwrap_or_default()),
+                type_: Some(dimension_type.to_string()),
+                expr: Some(
+                    value_to_expr(dimension.expr.as_ref())
+                        .unwrap_or_else(|| dimension.name.clone()),
+                ),
+                semantic_type: None,
+                searchable: None,
+                extra: HashMap::new(),
+            });
+        }
+
+        for measure in &sm.measures {
+            if measure.non_additive_dimension.is_some() {
+                import.untranslated.push(format!(
+                    "{}: measure '{}' is semi-additive (non_additive_dimension), which Buster doesn't support",
+                    sm.name, measure.name
+                ));
+                continue;
+            }
+
+            let agg = match measure.agg.as_str() {
+                "sum" | "min" | "max" | "count" | "count_distinct" | "sum_boolean" | "median" => {
+                    measure.agg.clone()
+                }
+                "average" => "avg".to_string(),
+                other => {
+                    let detail = if measure.agg_params.is_some() {
+                        " with agg_params"
+                    } else {
+                        ""
+                    };
+                    import.untranslated.push(format!(
+                        "{}: measure '{}' uses unsupported aggregation '{}'{}",
+                        sm.name, measure.name, other, detail
+                    ));
+                    continue;
+                }
+            };
+
+            measure_owners.insert(&measure.name, index);
+            model.measures.push(Measure {
+                name: measure.name.clone(),
+                description: Some(measure.description.clone().unwrap_or_default()),
+                expr: value_to_expr(measure.expr.as_ref()).unwrap_or_else(|| measure.name.clone()),
+                agg: Some(agg),
+                extra: HashMap::new(),
+            });
+        }
+
+        models.push(model);
+    }
+
+    // Buster metrics are expressions over the measures of a single model, so every MetricFlow
+    // metric is expanded down to measures and placed on the model that owns them.
+    let metrics: HashMap<&str, &MetricFlowMetric> = definitions
+        .metrics
+        .iter()
+        .map(|m| (m.name.as_str(), m))
+        .collect();
+
+    for metric in &definitions.metrics {
+        let mut visiting = HashSet::new();
+        let (owner, expr) =
+            match resolve_metric(&metric.name, &metrics, &measure_owners, &mut visiting) {
+                Ok(resolved) => resolved,
+                Err(reason) => {
+                    import
+                        .untranslated
+                        .push(format!("metric '{}': {}", metric.name, reason));
+                    continue;
+                }
+            };
+
+        let model = &mut models[owner];
+
+        // A simple metric over a measure of the same name is already queryable as that measure.
+        if model.measures.iter().any(|m| m.name == metric.name) {
+            if expr != metric.name {
+                import.untranslated.push(format!(
+                    "metric '{}': a measure of {} already has this name",
+                    metric.name, model.name
+                ));
+            }
+            continue;
+        }
+
+        model.metrics.push(Metric {
+            name: metric.name.clone(),
+            expr,
+            description: Some(
+                metric
+                    .description
+                    .clone()
+                    .or_else(|| metric.label.clone())
+                    .unwrap_or_default(),
+            ),
+            extra: HashMap::new(),
+        });
+    }
+
+    import.models = models
+        .into_iter()
+        .map(|model| YamlFile {
+            models: vec![model],
+        })
+        .collect();
+    import
+}
+
+/// Expands a metric into an expression over measures, returning the index of the semantic model
+/// those measures belong to.
+fn resolve_metric(
+    name: &str,
+    metrics: &HashMap<&str, &MetricFlowMetric>,
+    measure_owners: &HashMap<&str, usize>,
+    visiting: &mut HashSet<String>,
+) -> Result<(usize, String), String> {
+    let metric = match metrics.get(name) {
+        Some(metric) => metric,
+        None => return Err(format!("references unknown metric '{}'", name)),
+    };
+    if !visiting.insert(name.to_string()) {
+        return Err(format!("metric '{}' references itself", name));
+    }
+    if metric.filter.is_some() {
+        return Err("metric filters aren't supported".to_string());
+    }
+
+    let params = &metric.type_params;
+    let resolved = match metric.metric_type.as_str() {
+        "simple" => {
+            let measure = params
+                .measure
+                .as_ref()
+                .ok_or("simple metric has no measure")?;
+            if let Some(option) = measure.unsupported_option() {
+                return Err(format!(
+                    "measure input uses unsupported option '{}'",
+                    option
+                ));
+            }
+            match measure_owners.get(measure.name()) {
+                Some(owner) => (*owner, measure.name().to_string()),
+                None => return Err(format!("measure '{}' wasn't imported", measure.name())),
+            }
+        }
+        "ratio" => {
+            let (numerator, denominator) = match (&params.numerator, &params.denominator) {
+                (Some(numerator), Some(denominator)) => (numerator, denominator),
+                _ => return Err("ratio metric needs a numerator and a denominator".to_string()),
+            };
+            for input in [numerator, denominator] {
+                if let Some(option) = input.unsupported_option() {
+                    return Err(format!(
+                        "input '{}' uses unsupported option '{}'",
+                        input.name(),
+                        option
+                    ));
+                }
+            }
+
+            let (owner, numerator) =
+                resolve_metric(numerator.name(), metrics, measure_owners, visiting)?;
+            let (denominator_owner, denominator) =
+                resolve_metric(denominator.name(), metrics, measure_owners, visiting)?;
+            if owner != denominator_owner {
+                return Err(
+                    "numerator and denominator come from different semantic models".to_string(),
+                );
+            }
+
+            (
+                owner,
+                format!("{} / NULLIF({}, 0)", parenthesize(&numerator), denominator),
+            )
+        }
+        "derived" => {
+            let expr = params.expr.as_ref().ok_or("derived metric has no expr")?;
+            let inputs = params
+                .metrics
+                .as_ref()
+                .ok_or("derived metric has no input metrics")?;
+
+            let mut owner = None;
+            let mut expansions = HashMap::new();
+            for input in inputs {
+                if let Some(option) = input.unsupported_option() {
+                    return Err(format!(
+                        "input '{}' uses unsupported option '{}'",
+                        input.name(),
+                        option
+                    ));
+                }
+
+                let (input_owner, input_expr) =
+                    resolve_metric(input.name(), metrics, measure_owners, visiting)?;
+                if owner.is_some_and(|owner| owner != input_owner) {
+                    return Err("input metrics come from different semantic models".to_string());
+                }
+                owner = Some(input_owner);
+                expansions.insert(input.alias().to_lowercase(), parenthesize(&input_expr));
+            }
+
+            match owner {
+                Some(owner) => (owner, substitute_identifiers(expr, &expansions)),
+                None => return Err("derived metric has no input metrics".to_string()),
+            }
+        }
+        other => return Err(format!("{} metrics aren't supported", other)),
+    };
+
+    visiting.remove(name);
+    Ok(resolved)
+}
+
+fn new_entity(name: &str, expr: String, entity_type: &str, description: String) -> Entity {
+    Entity {
+        name: name.to_string(),
+        ref_: None,
+        expr,
+        entity_type: entity_type.to_string(),
+        description,
+        project_path: None,
+        extra: HashMap::new(),
+    }
+}
+
+// Expressions can be written as numbers, e.g. `expr: 1` for counting rows.
+fn value_to_expr(value: Option<&Value>) -> Option<String> {
+    match value? {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn ref_name(model: &str) -> Option<String> {
+    lazy_static! {
+        static ref REF_RE: Regex = Regex::new(r#"ref\(\s*['"]([^'"]+)['"]\s*\)"#).unwrap();
+    }
+
+    REF_RE.captures(model).map(|cap| cap[1].to_string())
+}
+
+fn parenthesize(expr: &str) -> String {
+    lazy_static! {
+        static ref IDENTIFIER_RE: Regex = Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$").unwrap();
+    }
+
+    if IDENTIFIER_RE.is_match(expr) {
+        expr.to_string()
+    } else {
+        format!("({})", expr)
+    }
+}
+
+fn substitute_identifiers(expr: &str, expansions: &HashMap<String, String>) -> String {
+    lazy_static! {
+        static ref TOKEN_RE: Regex = Regex::new(r"'[^']*'|[A-Za-z_][A-Za-z0-9_]*").unwrap();
+    }
+
+    TOKEN_RE
+        .replace_all(expr, |caps: &Captures| {
+            let token = &caps[0];
+            expansions
+                .get(&token.to_lowercase())
+                .cloned()
+                .unwrap_or_else(|| token.to_string())
+        })
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFINITIONS: &str = r#"
+semantic_models:
+  - name: orders
+    description: Order facts
+    model: ref('fct_orders')
+    entities:
+      - name: order
+        type: primary
+        expr: order_id
+      - name: customer
+        type: foreign
+        expr: customer_id
+    dimensions:
+      - name: ordered_at
+        type: time
+        type_params:
+          time_granularity: day
+      - name: status
+        type: categorical
+    measures:
+      - name: order_total
+        agg: sum
+        expr: amount
+      - name: order_count
+        agg: sum
+        expr: 1
+      - name: p90_order_total
+        agg: percentile
+        expr: amount
+        agg_params:
+          percentile: 0.9
+  - name: customers
+    model: ref('dim_customers')
+    entities:
+      - name: customer
+        type: primary
+        expr: customer_id
+metrics:
+  - name: order_total
+    type: simple
+    type_params:
+      measure: order_total
+  - name: revenue
+    type: simple
+    label: Revenue
+    type_params:
+      measure: order_total
+  - name: average_order_value
+    type: ratio
+    type_params:
+      numerator: revenue
+      denominator: order_count_metric
+  - name: order_count_metric
+    type: simple
+    type_params:
+      measure: order_count
+  - name: revenue_per_order_pct
+    type: derived
+    type_params:
+      expr: aov * 100
+      metrics:
+        - name: average_order_value
+          alias: aov
+  - name: cumulative_revenue
+    type: cumulative
+    type_params:
+      measure: order_total
+"#;
+
+    #[test]
+    fn test_convert_metricflow_definitions() {
+        let definitions: MetricFlowFile = serde_yaml::from_str(DEFINITIONS).unwrap();
+        let import = convert(definitions, None);
+
+        assert_eq!(import.models.len(), 2);
+        let orders = &import.models[0].models[0];
+        assert_eq!(orders.name, "fct_orders");
+
+        assert_eq!(orders.entities.len(), 2);
+        assert_eq!(orders.entities[0].entity_type, "primary");
+        assert_eq!(orders.entities[1].name, "dim_customers");
+        assert_eq!(orders.entities[1].expr, "customer_id");
+
+        assert_eq!(orders.dimensions[0].type_.as_deref(), Some("date"));
+        assert_eq!(orders.dimensions[1].type_.as_deref(), Some("string"));
+
+        let measures: Vec<&str> = orders.measures.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(measures, vec!["order_total", "order_count"]);
+        assert_eq!(orders.measures[1].expr, "1");
+
+        let metrics: HashMap<&str, &str> = orders
+            .metrics
+            .iter()
+            .map(|m| (m.name.as_str(), m.expr.as_str()))
+            .collect();
+        assert_eq!(metrics.get("order_total"), None);
+        assert_eq!(metrics["revenue"], "order_total");
+        assert_eq!(
+            metrics["average_order_value"],
+            "order_total / NULLIF(order_count, 0)"
+        );
+        assert_eq!(
+            metrics["revenue_per_order_pct"],
+            "(order_total / NULLIF(order_count, 0)) * 100"
+        );
+
+        assert_eq!(import.untranslated.len(), 2);
+        assert!(import.untranslated[0].contains("p90_order_total"));
+        assert!(import.untranslated[1].contains("cumulative_revenue"));
+    }
+}
@@ -12,4 +12,5 @@ pub use formatting::*;
 pub use exclusion::*;
 pub use file_finder::*;
 
+pub mod metricflow;
 pub mod yaml_diff_merger;
@@ -1,10 +1,14 @@
-use std::path::PathBuf;
-use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::collections::{HashMap, HashSet};
 use serde::{Serialize, Deserialize};
 use serde_yaml::{Value, Mapping};
 use anyhow::{Result, Context};
 use std::fs;
 use colored::*;
+use inquire::Select;
+
+/// Where base snapshots are kept, relative to the project root.
+pub const STATE_DIR: &str = ".buster/state";
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct YamlFile {
@@ -22,6 +26,8 @@ pub struct Model {
     pub dimensions: Vec<Dimension>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub measures: Vec<Measure>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub metrics: Vec<Metric>,
     #[serde(flatten)]
     pub extra: HashMap<String, Value>,
 }
@@ -70,11 +76,75 @@ pub struct Measure {
     pub extra: HashMap<String, Value>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Metric {
+    pub name: String,
+    pub expr: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
 #[derive(Debug)]
 pub struct YamlDiffMerger {
     existing_yaml: PathBuf,
     new_content: String,
     backup_path: PathBuf,
+    updated_fields: Vec<String>,
+    base_path: Option<PathBuf>,
+    conflict_resolution: ConflictResolution,
+}
+
+/// How fields changed both locally and upstream are written when changes are applied.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ConflictResolution {
+    /// Write both versions between git-style conflict markers.
+    #[default]
+    Markers,
+    /// Ask which version to keep for each conflict.
+    Interactive,
+}
+
+/// A field, or a whole element when `field` is `None`, changed both locally and upstream since
+/// the base snapshot. `None` values mean the field or element is absent on that side.
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub path: String,
+    pub field: Option<String>,
+    pub local: Option<Value>,
+    pub base: Option<Value>,
+    pub generated: Option<Value>,
+}
+
+impl Conflict {
+    fn label(&self) -> String {
+        match &self.field {
+            Some(field) => format!("{}.{}", self.path, field),
+            None => self.path.clone(),
+        }
+    }
+}
+
+/// Collects conflicts during a merge and decides what value each one gets.
+struct MergeState {
+    /// `None` when only computing the diff, which keeps the local values.
+    resolution: Option<ConflictResolution>,
+    conflicts: Vec<Conflict>,
+}
+
+impl MergeState {
+    fn resolve(&mut self, conflict: Conflict) -> Result<Option<Value>> {
+        let value = match self.resolution {
+            None => conflict.local.clone(),
+            Some(ConflictResolution::Markers) => {
+                Some(Value::String(conflict_placeholder(self.conflicts.len())))
+            }
+            Some(ConflictResolution::Interactive) => prompt_conflict(&conflict)?,
+        };
+        self.conflicts.push(conflict);
+        Ok(value)
+    }
 }
 
 #[derive(Debug)]
@@ -88,6 +158,11 @@ pub struct ModelDiff {
     added_entities: Vec<Entity>,
     removed_entities: Vec<String>,
     preserved_entities: Vec<Entity>,
+    added_metrics: Vec<Metric>,
+    removed_metrics: Vec<String>,
+    preserved_metrics: Vec<Metric>,
+    updated_fields: Vec<String>,
+    conflicts: Vec<Conflict>,
 }
 
 #[derive(Debug)]
@@ -95,15 +170,21 @@ pub struct DiffStats {
     total_dimensions: usize,
     total_measures: usize,
     total_entities: usize,
+    total_metrics: usize,
     added_dimensions: usize,
     added_measures: usize,
     added_entities: usize,
+    added_metrics: usize,
     removed_dimensions: usize,
     removed_measures: usize,
     removed_entities: usize,
+    removed_metrics: usize,
     preserved_dimensions: usize,
     preserved_measures: usize,
     preserved_entities: usize,
+    preserved_metrics: usize,
+    updated_fields: usize,
+    conflicts: usize,
 }
 
 #[derive(Debug)]
@@ -112,6 +193,12 @@ pub struct DiffResult {
     statistics: DiffStats,
 }
 
+impl DiffResult {
+    pub fn has_updated_fields(&self) -> bool {
+        !self.changes.updated_fields.is_empty()
+    }
+}
+
 impl YamlDiffMerger {
     pub fn new(existing_yaml: PathBuf, new_content: String) -> Self {
         let backup_path = existing_yaml.with_extension("yml.bak");
@@ -119,97 +206,311 @@ impl YamlDiffMerger {
             existing_yaml,
             new_content,
             backup_path,
+            updated_fields: Vec::new(),
+            base_path: None,
+            conflict_resolution: ConflictResolution::default(),
+        }
+    }
+
+    /// Merges three ways against the snapshot of the content last generated for this file,
+    /// kept under `.buster/state` in `project_root`. Applying changes updates the snapshot.
+    pub fn with_base_snapshot(mut self, project_root: &Path) -> Self {
+        let relative = match self.existing_yaml.strip_prefix(project_root) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => PathBuf::from(self.existing_yaml.file_name().unwrap_or_default()),
+        };
+        self.base_path = Some(project_root.join(STATE_DIR).join(relative));
+        self
+    }
+
+    pub fn with_conflict_resolution(mut self, conflict_resolution: ConflictResolution) -> Self {
+        self.conflict_resolution = conflict_resolution;
+        self
+    }
+
+    /// Records the new content as the base for the next merge. Needed when a file is written
+    /// without merging, e.g. when it's created.
+    pub fn save_base_snapshot(&self) -> Result<()> {
+        if let Some(base_path) = &self.base_path {
+            if let Some(parent) = base_path.parent() {
+                fs::create_dir_all(parent).context("Failed to create state directory")?;
+            }
+            fs::write(base_path, &self.new_content).context("Failed to write base snapshot")?;
+        }
+        Ok(())
+    }
+
+    fn load_base_model(&self) -> Result<Option<Value>> {
+        let base_path = match &self.base_path {
+            Some(base_path) if base_path.exists() => base_path,
+            _ => return Ok(None),
+        };
+
+        let content = fs::read_to_string(base_path).context("Failed to read base snapshot")?;
+        let base: Value = serde_yaml::from_str(&content).context("Failed to parse base snapshot")?;
+        Ok(base
+            .get("models")
+            .and_then(|models| models.get(0))
+            .cloned())
+    }
+
+    /// Overwrites `fields` on the model and on elements that already exist with the values from
+    /// the new content, instead of keeping the existing element as-is. A field missing from the
+    /// new content is removed.
+    pub fn with_updated_fields(mut self, fields: &[&str]) -> Self {
+        self.updated_fields = fields.iter().map(|f| f.to_string()).collect();
+        self
+    }
+
+    fn update_fields(&self, existing: &mut Value, new: &Value) {
+        if let Value::Mapping(map) = existing {
+            for field in &self.updated_fields {
+                let key = Value::String(field.clone());
+                match new.get(field.as_str()) {
+                    Some(value) if !value.is_null() => {
+                        map.insert(key, value.clone());
+                    }
+                    _ => {
+                        map.remove(&key);
+                    }
+                }
+            }
         }
     }
 
+    fn changed_fields(&self, existing: &Value, new: &Value, label: &str) -> Vec<String> {
+        self.updated_fields
+            .iter()
+            .filter(|field| {
+                let existing = existing.get(field.as_str()).filter(|v| !v.is_null());
+                let new = new.get(field.as_str()).filter(|v| !v.is_null());
+                existing != new
+            })
+            .map(|field| format!("{}.{}", label, field))
+            .collect()
+    }
+
     fn parse_yaml_preserving_style(content: &str) -> Result<Value> {
         serde_yaml::from_str(content).context("Failed to parse YAML content")
     }
 
-    fn update_model_preserving_style(&self, existing_model: &mut Value, new_model: &Model) -> Result<()> {
+    fn update_model_preserving_style(
+        &self,
+        existing_model: &mut Value,
+        new_model: &Model,
+        base_model: Option<&Value>,
+        state: &mut MergeState,
+    ) -> Result<()> {
+        let new_value = serde_yaml::to_value(new_model)?;
+        *existing_model = self.merge_element(
+            existing_model,
+            &new_value,
+            base_model,
+            &new_model.name,
+            &["name", "dimensions", "measures", "entities", "metrics"],
+            state,
+        )?;
+
         if let Value::Mapping(map) = existing_model {
-            // Update dimensions while preserving style
-            if let Some(existing_dims) = map.get_mut("dimensions") {
-                if let Value::Sequence(dims) = existing_dims {
-                    // Create a map of existing dimensions by name (case insensitive)
-                    let mut dim_map: HashMap<String, &Value> = HashMap::new();
-                    for dim in dims.iter() {
-                        if let Some(name) = dim.get("name").and_then(|n| n.as_str()) {
-                            dim_map.insert(name.to_lowercase(), dim);
-                        }
-                    }
+            for section in ["dimensions", "measures", "entities", "metrics"] {
+                self.merge_section(map, section, &new_value, base_model, &new_model.name, state)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Merges one list of named elements, e.g. `dimensions`, matching elements by name (case
+    /// insensitive).
+    ///
+    /// Without a base snapshot the new content decides which elements exist and existing ones are
+    /// kept as they are. With one, elements added or removed locally stay that way, and elements
+    /// both sides have are merged field by field.
+    fn merge_section(
+        &self,
+        map: &mut Mapping,
+        section: &str,
+        new_model: &Value,
+        base_model: Option<&Value>,
+        model_name: &str,
+        state: &mut MergeState,
+    ) -> Result<()> {
+        let key = Value::String(section.to_string());
+        let existing_items = match map.get(&key) {
+            Some(Value::Sequence(items)) => items.clone(),
+            Some(_) => return Ok(()),
+            None => Vec::new(),
+        };
+        let new_items = section_items(new_model, section);
+
+        let existing_by_name: HashMap<String, &Value> = existing_items
+            .iter()
+            .map(|item| (item_name(item).to_lowercase(), item))
+            .collect();
+        let new_by_name: HashMap<String, &Value> = new_items
+            .iter()
+            .map(|item| (item_name(item).to_lowercase(), item))
+            .collect();
 
-                    // Update dimensions while preserving order and style
-                    let mut new_dims = Vec::new();
-                    for dim in &new_model.dimensions {
-                        if let Some(&existing_dim) = dim_map.get(&dim.name.to_lowercase()) {
-                            // Preserve existing dimension's style and casing
-                            new_dims.push(existing_dim.clone());
-                        } else {
-                            // Add new dimension
-                            new_dims.push(serde_yaml::to_value(dim)?);
+        let mut merged = Vec::new();
+        match base_model {
+            None => {
+                // Follow the new content, preserving the style and casing of existing elements
+                for item in &new_items {
+                    match existing_by_name.get(&item_name(item).to_lowercase()) {
+                        Some(&existing) => {
+                            let mut existing = existing.clone();
+                            self.update_fields(&mut existing, item);
+                            merged.push(existing);
                         }
+                        None => merged.push(item.clone()),
                     }
-                    *dims = new_dims;
                 }
             }
+            Some(base_model) => {
+                let base_items = section_items(base_model, section);
+                let base_by_name: HashMap<String, &Value> = base_items
+                    .iter()
+                    .map(|item| (item_name(item).to_lowercase(), item))
+                    .collect();
 
-            // Update measures while preserving style
-            if let Some(existing_measures) = map.get_mut("measures") {
-                if let Value::Sequence(measures) = existing_measures {
-                    // Create a map of existing measures by name (case insensitive)
-                    let mut measure_map: HashMap<String, &Value> = HashMap::new();
-                    for measure in measures.iter() {
-                        if let Some(name) = measure.get("name").and_then(|n| n.as_str()) {
-                            measure_map.insert(name.to_lowercase(), measure);
+                // Keep the file's order
+                for existing in &existing_items {
+                    let name = item_name(existing);
+                    let label = format!("{}.{}", model_name, name);
+                    let lookup = name.to_lowercase();
+
+                    match (new_by_name.get(&lookup), base_by_name.get(&lookup)) {
+                        (Some(&new), base) => merged.push(self.merge_element(
+                            existing,
+                            new,
+                            base.copied(),
+                            &label,
+                            &["name"],
+                            state,
+                        )?),
+                        // Removed upstream; only a conflict if it was edited locally
+                        (None, Some(&base)) => {
+                            if existing != base {
+                                let resolved = state.resolve(Conflict {
+                                    path: label,
+                                    field: None,
+                                    local: Some(existing.clone()),
+                                    base: Some(base.clone()),
+                                    generated: None,
+                                })?;
+                                merged.extend(resolved);
+                            }
                         }
+                        // Added locally
+                        (None, None) => merged.push(existing.clone()),
                     }
+                }
 
-                    // Update measures while preserving order and style
-                    let mut new_measures = Vec::new();
-                    for measure in &new_model.measures {
-                        if let Some(&existing_measure) = measure_map.get(&measure.name.to_lowercase()) {
-                            // Preserve existing measure's style and casing
-                            new_measures.push(existing_measure.clone());
-                        } else {
-                            // Add new measure
-                            new_measures.push(serde_yaml::to_value(measure)?);
-                        }
+                // Elements removed locally aren't brought back
+                for item in &new_items {
+                    let lookup = item_name(item).to_lowercase();
+                    if !existing_by_name.contains_key(&lookup) && !base_by_name.contains_key(&lookup) {
+                        merged.push(item.clone());
                     }
-                    *measures = new_measures;
                 }
             }
+        }
 
-            // Update entities while preserving style
-            if let Some(existing_entities) = map.get_mut("entities") {
-                if let Value::Sequence(entities) = existing_entities {
-                    // Create a map of existing entities by name (case insensitive)
-                    let mut entity_map: HashMap<String, &Value> = HashMap::new();
-                    for entity in entities.iter() {
-                        if let Some(name) = entity.get("name").and_then(|n| n.as_str()) {
-                            entity_map.insert(name.to_lowercase(), entity);
-                        }
+        if merged.is_empty() && !map.contains_key(&key) {
+            return Ok(());
+        }
+        map.insert(key, Value::Sequence(merged));
+        Ok(())
+    }
+
+    /// Merges the fields of one element, or of the model itself, skipping `skip_fields`.
+    ///
+    /// Without a base snapshot the existing element is kept, apart from `updated_fields`. With
+    /// one, fields changed only upstream are updated, fields changed only locally are kept, and
+    /// fields changed on both sides are conflicts.
+    fn merge_element(
+        &self,
+        existing: &Value,
+        new: &Value,
+        base: Option<&Value>,
+        label: &str,
+        skip_fields: &[&str],
+        state: &mut MergeState,
+    ) -> Result<Value> {
+        let mut merged = existing.clone();
+        let base = match base {
+            Some(base) => base,
+            None => {
+                self.update_fields(&mut merged, new);
+                return Ok(merged);
+            }
+        };
+
+        let mut fields: Vec<Value> = Vec::new();
+        for value in [existing, new, base] {
+            if let Value::Mapping(map) = value {
+                for key in map.keys() {
+                    if !fields.contains(key) {
+                        fields.push(key.clone());
                     }
+                }
+            }
+        }
 
-                    // Update entities while preserving order and style
-                    let mut new_entities = Vec::new();
-                    for entity in &new_model.entities {
-                        if let Some(&existing_entity) = entity_map.get(&entity.name.to_lowercase()) {
-                            // Preserve existing entity's style and casing
-                            new_entities.push(existing_entity.clone());
-                        } else {
-                            // Add new entity
-                            new_entities.push(serde_yaml::to_value(entity)?);
-                        }
+        if let Value::Mapping(map) = &mut merged {
+            for key in fields {
+                let field = match key.as_str() {
+                    Some(field) if !skip_fields.contains(&field) => field.to_string(),
+                    _ => continue,
+                };
+                let local = existing.get(&key);
+                let generated = new.get(&key);
+                let base = base.get(&key);
+
+                let value = if local == generated || generated == base {
+                    continue;
+                } else if local == base {
+                    generated.cloned()
+                } else {
+                    state.resolve(Conflict {
+                        path: label.to_string(),
+                        field: Some(field),
+                        local: local.cloned(),
+                        base: base.cloned(),
+                        generated: generated.cloned(),
+                    })?
+                };
+
+                match value {
+                    Some(value) => {
+                        map.insert(key, value);
+                    }
+                    None => {
+                        map.remove(&key);
                     }
-                    *entities = new_entities;
                 }
-            } else if !new_model.entities.is_empty() {
-                // If there's no entities section but we have new entities, add them
-                map.insert(
-                    Value::String("entities".to_string()),
-                    serde_yaml::to_value(&new_model.entities)?,
-                );
+            }
+        }
+
+        Ok(merged)
+    }
+
+    /// Applies the new content to a parsed document, merging its first model.
+    fn merge_document(&self, document: &mut Value, state: &mut MergeState) -> Result<()> {
+        let new_yaml: YamlFile = serde_yaml::from_str(&self.new_content)
+            .context("Failed to parse new YAML content")?;
+        let base_model = self.load_base_model()?;
+
+        if let Value::Mapping(map) = document {
+            if let Some(Value::Sequence(models)) = map.get_mut("models") {
+                if !models.is_empty() && !new_yaml.models.is_empty() {
+                    self.update_model_preserving_style(
+                        &mut models[0],
+                        &new_yaml.models[0],
+                        base_model.as_ref(),
+                        state,
+                    )?;
+                }
             }
         }
         Ok(())
@@ -219,6 +520,13 @@ impl YamlDiffMerger {
         // Read and parse existing YAML
         let existing_content = fs::read_to_string(&self.existing_yaml)
             .context(format!("Failed to read file: {}", self.existing_yaml.display()))?;
+
+        if has_conflict_markers(&existing_content) {
+            return Err(anyhow::anyhow!(
+                "File {} has unresolved merge conflicts. Resolve them before merging again.",
+                self.existing_yaml.display()
+            ));
+        }
         
         let existing_yaml: YamlFile = match serde_yaml::from_str(&existing_content) {
             Ok(yaml) => yaml,
@@ -283,6 +591,29 @@ impl YamlDiffMerger {
             .map(|m| (m.name.to_lowercase(), m)).collect();
         let new_entities: HashMap<_, _> = new_model.entities.iter()
             .map(|e| (e.name.to_lowercase(), e)).collect();
+        let existing_metrics: HashMap<_, _> = existing_model.metrics.iter()
+            .map(|m| (m.name.to_lowercase(), m)).collect();
+        let new_metrics: HashMap<_, _> = new_model.metrics.iter()
+            .map(|m| (m.name.to_lowercase(), m)).collect();
+
+        // With a base snapshot, elements missing on one side may have been added or removed locally
+        let base_model: Option<Model> = self
+            .load_base_model()?
+            .and_then(|model| serde_yaml::from_value(model).ok());
+        let base_dims: Option<HashSet<String>> = base_model.as_ref()
+            .map(|m| m.dimensions.iter().map(|d| d.name.to_lowercase()).collect());
+        let base_measures: Option<HashSet<String>> = base_model.as_ref()
+            .map(|m| m.measures.iter().map(|d| d.name.to_lowercase()).collect());
+        let base_entities: Option<HashSet<String>> = base_model.as_ref()
+            .map(|m| m.entities.iter().map(|d| d.name.to_lowercase()).collect());
+        let base_metrics: Option<HashSet<String>> = base_model.as_ref()
+            .map(|m| m.metrics.iter().map(|d| d.name.to_lowercase()).collect());
+        let removed_locally = |base: &Option<HashSet<String>>, name: &String| {
+            base.as_ref().is_some_and(|b| b.contains(name))
+        };
+        let added_locally = |base: &Option<HashSet<String>>, name: &String| {
+            base.as_ref().is_some_and(|b| !b.contains(name))
+        };
 
         let mut changes = ModelDiff {
             added_dimensions: Vec::new(),
@@ -294,19 +625,28 @@ impl YamlDiffMerger {
             added_entities: Vec::new(),
             removed_entities: Vec::new(),
             preserved_entities: Vec::new(),
+            added_metrics: Vec::new(),
+            removed_metrics: Vec::new(),
+            preserved_metrics: Vec::new(),
+            updated_fields: Vec::new(),
+            conflicts: Vec::new(),
         };
 
         // Process dimensions
         for (name, dim) in &new_dims {
             if existing_dims.contains_key(name) {
                 changes.preserved_dimensions.push(existing_dims[name].clone());
-            } else {
+            } else if !removed_locally(&base_dims, name) {
                 changes.added_dimensions.push((*dim).clone());
             }
         }
         for (name, dim) in existing_dims.iter() {
             if !new_dims.contains_key(name) {
-                changes.removed_dimensions.push(dim.name.clone());
+                if added_locally(&base_dims, name) {
+                    changes.preserved_dimensions.push((*dim).clone());
+                } else {
+                    changes.removed_dimensions.push(dim.name.clone());
+                }
             }
         }
 
@@ -314,13 +654,17 @@ impl YamlDiffMerger {
         for (name, measure) in &new_measures {
             if existing_measures.contains_key(name) {
                 changes.preserved_measures.push(existing_measures[name].clone());
-            } else {
+            } else if !removed_locally(&base_measures, name) {
                 changes.added_measures.push((*measure).clone());
             }
         }
         for (name, measure) in existing_measures.iter() {
             if !new_measures.contains_key(name) {
-                changes.removed_measures.push(measure.name.clone());
+                if added_locally(&base_measures, name) {
+                    changes.preserved_measures.push((*measure).clone());
+                } else {
+                    changes.removed_measures.push(measure.name.clone());
+                }
             }
         }
 
@@ -328,29 +672,102 @@ impl YamlDiffMerger {
         for (name, entity) in &new_entities {
             if existing_entities.contains_key(name) {
                 changes.preserved_entities.push(existing_entities[name].clone());
-            } else {
+            } else if !removed_locally(&base_entities, name) {
                 changes.added_entities.push((*entity).clone());
             }
         }
         for (name, entity) in existing_entities.iter() {
             if !new_entities.contains_key(name) {
-                changes.removed_entities.push(entity.name.clone());
+                if added_locally(&base_entities, name) {
+                    changes.preserved_entities.push((*entity).clone());
+                } else {
+                    changes.removed_entities.push(entity.name.clone());
+                }
+            }
+        }
+
+        // Process metrics
+        for (name, metric) in &new_metrics {
+            if existing_metrics.contains_key(name) {
+                changes.preserved_metrics.push(existing_metrics[name].clone());
+            } else if !removed_locally(&base_metrics, name) {
+                changes.added_metrics.push((*metric).clone());
+            }
+        }
+        for (name, metric) in existing_metrics.iter() {
+            if !new_metrics.contains_key(name) {
+                if added_locally(&base_metrics, name) {
+                    changes.preserved_metrics.push((*metric).clone());
+                } else {
+                    changes.removed_metrics.push(metric.name.clone());
+                }
+            }
+        }
+
+        // Fields the caller asked to overwrite on the model and the elements both sides have
+        if !self.updated_fields.is_empty() {
+            changes.updated_fields.extend(self.changed_fields(
+                &serde_yaml::to_value(existing_model)?,
+                &serde_yaml::to_value(new_model)?,
+                &existing_model.name,
+            ));
+            for dim in &new_model.dimensions {
+                if let Some(existing) = existing_dims.get(&dim.name.to_lowercase()) {
+                    changes.updated_fields.extend(self.changed_fields(
+                        &serde_yaml::to_value(existing)?,
+                        &serde_yaml::to_value(dim)?,
+                        &format!("{}.{}", existing_model.name, dim.name),
+                    ));
+                }
+            }
+            for measure in &new_model.measures {
+                if let Some(existing) = existing_measures.get(&measure.name.to_lowercase()) {
+                    changes.updated_fields.extend(self.changed_fields(
+                        &serde_yaml::to_value(existing)?,
+                        &serde_yaml::to_value(measure)?,
+                        &format!("{}.{}", existing_model.name, measure.name),
+                    ));
+                }
+            }
+            for metric in &new_model.metrics {
+                if let Some(existing) = existing_metrics.get(&metric.name.to_lowercase()) {
+                    changes.updated_fields.extend(self.changed_fields(
+                        &serde_yaml::to_value(existing)?,
+                        &serde_yaml::to_value(metric)?,
+                        &format!("{}.{}", existing_model.name, metric.name),
+                    ));
+                }
             }
         }
 
+        // Conflicts are found by merging a copy of the file, which keeps the local values
+        let mut document = Self::parse_yaml_preserving_style(&existing_content)?;
+        let mut state = MergeState {
+            resolution: None,
+            conflicts: Vec::new(),
+        };
+        self.merge_document(&mut document, &mut state)?;
+        changes.conflicts = state.conflicts;
+
         let statistics = DiffStats {
             total_dimensions: existing_dims.len(),
             total_measures: existing_measures.len(),
             total_entities: existing_model.entities.len(),
+            total_metrics: existing_metrics.len(),
             added_dimensions: changes.added_dimensions.len(),
             added_measures: changes.added_measures.len(),
             added_entities: changes.added_entities.len(),
+            added_metrics: changes.added_metrics.len(),
             removed_dimensions: changes.removed_dimensions.len(),
             removed_measures: changes.removed_measures.len(),
             removed_entities: changes.removed_entities.len(),
+            removed_metrics: changes.removed_metrics.len(),
             preserved_dimensions: changes.preserved_dimensions.len(),
             preserved_measures: changes.preserved_measures.len(),
             preserved_entities: changes.preserved_entities.len(),
+            preserved_metrics: changes.preserved_metrics.len(),
+            updated_fields: changes.updated_fields.len(),
+            conflicts: changes.conflicts.len(),
         };
 
         Ok(DiffResult { changes, statistics })
@@ -381,6 +798,13 @@ impl YamlDiffMerger {
             }
         }
 
+        if !diff_result.changes.added_metrics.is_empty() {
+            println!("\nNew metrics to be added:");
+            for metric in &diff_result.changes.added_metrics {
+                println!("  + {}", metric.name.green());
+            }
+        }
+
         if !diff_result.changes.removed_dimensions.is_empty() {
             println!("\nDimensions to be removed:");
             for name in &diff_result.changes.removed_dimensions {
@@ -402,6 +826,13 @@ impl YamlDiffMerger {
             }
         }
 
+        if !diff_result.changes.removed_metrics.is_empty() {
+            println!("\nMetrics to be removed:");
+            for name in &diff_result.changes.removed_metrics {
+                println!("  - {}", name.red());
+            }
+        }
+
         if !diff_result.changes.preserved_dimensions.is_empty() {
             println!("\nPreserved dimensions (keeping existing configuration):");
             for dim in &diff_result.changes.preserved_dimensions {
@@ -423,6 +854,27 @@ impl YamlDiffMerger {
             }
         }
 
+        if !diff_result.changes.preserved_metrics.is_empty() {
+            println!("\nPreserved metrics (keeping existing configuration):");
+            for metric in &diff_result.changes.preserved_metrics {
+                println!("  • {}", metric.name.yellow());
+            }
+        }
+
+        if !diff_result.changes.updated_fields.is_empty() {
+            println!("\nFields to be updated:");
+            for field in &diff_result.changes.updated_fields {
+                println!("  ~ {}", field.cyan());
+            }
+        }
+
+        if !diff_result.changes.conflicts.is_empty() {
+            println!("\nConflicts (changed both locally and in the generated content):");
+            for conflict in &diff_result.changes.conflicts {
+                println!("  ! {}", conflict.label().red());
+            }
+        }
+
         println!("\nStatistics:");
         println!("  Dimensions:");
         println!("    Total: {}", diff_result.statistics.total_dimensions);
@@ -439,6 +891,17 @@ impl YamlDiffMerger {
         println!("    Added: {}", diff_result.statistics.added_entities);
         println!("    Removed: {}", diff_result.statistics.removed_entities);
         println!("    Preserved: {}", diff_result.statistics.preserved_entities);
+        println!("  Metrics:");
+        println!("    Total: {}", diff_result.statistics.total_metrics);
+        println!("    Added: {}", diff_result.statistics.added_metrics);
+        println!("    Removed: {}", diff_result.statistics.removed_metrics);
+        println!("    Preserved: {}", diff_result.statistics.preserved_metrics);
+        if diff_result.statistics.updated_fields > 0 {
+            println!("  Updated fields: {}", diff_result.statistics.updated_fields);
+        }
+        if diff_result.statistics.conflicts > 0 {
+            println!("  Conflicts: {}", diff_result.statistics.conflicts);
+        }
     }
 
     pub fn apply_changes(&self, diff_result: &DiffResult) -> Result<()> {
@@ -451,23 +914,28 @@ impl YamlDiffMerger {
             .context("Failed to read existing YAML file")?;
         let mut existing_yaml = Self::parse_yaml_preserving_style(&existing_content)?;
 
-        // Parse new content
-        let new_yaml: YamlFile = serde_yaml::from_str(&self.new_content)
-            .context("Failed to parse new YAML content")?;
-
         // Update the existing YAML while preserving style
-        if let Value::Mapping(map) = &mut existing_yaml {
-            if let Some(Value::Sequence(models)) = map.get_mut("models") {
-                if !models.is_empty() {
-                    // Update the first model
-                    self.update_model_preserving_style(&mut models[0], &new_yaml.models[0])?;
-                }
-            }
-        }
+        let mut state = MergeState {
+            resolution: Some(self.conflict_resolution),
+            conflicts: Vec::new(),
+        };
+        self.merge_document(&mut existing_yaml, &mut state)?;
 
         // Write to temporary file using the original style
         let temp_path = self.existing_yaml.with_extension("yml.tmp");
-        let yaml_str = serde_yaml::to_string(&existing_yaml)?;
+        let mut yaml_str = serde_yaml::to_string(&existing_yaml)?;
+        if self.conflict_resolution == ConflictResolution::Markers && !state.conflicts.is_empty() {
+            yaml_str = write_conflict_markers(&yaml_str, &state.conflicts)?;
+            println!(
+                "{}",
+                format!(
+                    "⚠️  {} conflicts in {} are marked with <<<<<<< and >>>>>>>. Resolve them before deploying.",
+                    state.conflicts.len(),
+                    self.existing_yaml.display()
+                )
+                .yellow()
+            );
+        }
         fs::write(&temp_path, yaml_str)
             .context("Failed to write temporary file")?;
 
@@ -479,11 +947,279 @@ impl YamlDiffMerger {
         fs::remove_file(&self.backup_path)
             .context("Failed to remove backup file")?;
 
+        // The generated content is the base for the next merge, whatever was kept locally
+        self.save_base_snapshot()?;
+
         Ok(())
     }
 }
 
+fn section_items(model: &Value, section: &str) -> Vec<Value> {
+    match model.get(section) {
+        Some(Value::Sequence(items)) => items.clone(),
+        _ => Vec::new(),
+    }
+}
+
+fn item_name(item: &Value) -> String {
+    item.get("name")
+        .and_then(|n| n.as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn conflict_placeholder(index: usize) -> String {
+    format!("__BUSTER_CONFLICT_{}__", index)
+}
+
+/// Whether a file still has conflict markers written by a merge.
+pub fn has_conflict_markers(content: &str) -> bool {
+    content
+        .lines()
+        .any(|line| line.starts_with("<<<<<<< ") || line.starts_with(">>>>>>> "))
+}
+
+fn prompt_conflict(conflict: &Conflict) -> Result<Option<Value>> {
+    let describe = |value: &Option<Value>| match value {
+        Some(value) => serde_yaml::to_string(value)
+            .unwrap_or_default()
+            .trim()
+            .replace('\n', " "),
+        None => "(removed)".to_string(),
+    };
+
+    let keep_local = format!("Keep local: {}", describe(&conflict.local));
+    let use_generated = format!("Use generated: {}", describe(&conflict.generated));
+    let choice = Select::new(
+        &format!(
+            "Conflict in {} (last generated: {})",
+            conflict.label(),
+            describe(&conflict.base)
+        ),
+        vec![keep_local.clone(), use_generated],
+    )
+    .prompt()?;
+
+    if choice == keep_local {
+        Ok(conflict.local.clone())
+    } else {
+        Ok(conflict.generated.clone())
+    }
+}
+
+/// Replaces each conflict placeholder with both versions between git-style markers.
+fn write_conflict_markers(yaml: &str, conflicts: &[Conflict]) -> Result<String> {
+    let mut output = String::new();
+
+    for line in yaml.lines() {
+        let conflict = conflicts
+            .iter()
+            .enumerate()
+            .find(|(index, _)| line.contains(&conflict_placeholder(*index)));
+
+        let (index, conflict) = match conflict {
+            Some(conflict) => conflict,
+            None => {
+                output.push_str(line);
+                output.push('\n');
+                continue;
+            }
+        };
+
+        // Everything before the field, or before the element for whole-element conflicts,
+        // e.g. the indentation and the `- ` of a list item.
+        let prefix = match &conflict.field {
+            Some(_) => {
+                let trimmed = line.trim_start();
+                let mut start = line.len() - trimmed.len();
+                if trimmed.starts_with("- ") {
+                    start += 2;
+                }
+                &line[..start]
+            }
+            None => &line[..line.find(&conflict_placeholder(index)).unwrap_or(0)],
+        };
+
+        output.push_str("<<<<<<< local\n");
+        push_conflict_side(&mut output, prefix, conflict.field.as_deref(), conflict.local.as_ref())?;
+        output.push_str("=======\n");
+        push_conflict_side(&mut output, prefix, conflict.field.as_deref(), conflict.generated.as_ref())?;
+        output.push_str(">>>>>>> generated\n");
+    }
+
+    Ok(output)
+}
+
+fn push_conflict_side(
+    output: &mut String,
+    prefix: &str,
+    field: Option<&str>,
+    value: Option<&Value>,
+) -> Result<()> {
+    let value = match value {
+        Some(value) => value,
+        None => return Ok(()),
+    };
+
+    let text = match field {
+        Some(field) => {
+            let mut mapping = Mapping::new();
+            mapping.insert(Value::String(field.to_string()), value.clone());
+            serde_yaml::to_string(&mapping)?
+        }
+        None => serde_yaml::to_string(value)?,
+    };
+
+    let indent = " ".repeat(prefix.chars().count());
+    for (i, line) in text.lines().enumerate() {
+        output.push_str(if i == 0 { prefix } else { &indent });
+        output.push_str(line);
+        output.push('\n');
+    }
+    Ok(())
+}
+
 // Add helper function at module level
 fn should_skip_searchable(b: &Option<bool>) -> bool {
     b.is_none() || !b.unwrap()
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const BASE: &str = r#"
+models:
+  - name: orders
+    description: Orders
+    dimensions:
+      - name: status
+        description: Status
+        type: string
+      - name: region
+        description: Region
+        type: string
+      - name: legacy
+        description: Legacy
+        type: string
+"#;
+
+    const LOCAL: &str = r#"
+models:
+  - name: orders
+    description: Orders
+    dimensions:
+      - name: status
+        description: Order status
+        type: string
+      - name: region
+        description: Sales region
+        type: string
+      - name: notes
+        description: Added by hand
+        type: string
+"#;
+
+    const GENERATED: &str = r#"
+models:
+  - name: orders
+    description: Orders
+    dimensions:
+      - name: status
+        description: Status
+        type: text
+      - name: region
+        description: Customer region
+        type: string
+      - name: legacy
+        description: Legacy
+        type: string
+      - name: created_at
+        description: Created at
+        type: timestamp
+"#;
+
+    fn setup(with_base: bool) -> (TempDir, PathBuf) {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("orders.yml");
+        fs::write(&path, LOCAL).unwrap();
+        if with_base {
+            let state = dir.path().join(STATE_DIR);
+            fs::create_dir_all(&state).unwrap();
+            fs::write(state.join("orders.yml"), BASE).unwrap();
+        }
+        (dir, path)
+    }
+
+    #[test]
+    fn test_three_way_merge_keeps_local_edits_and_marks_conflicts() {
+        let (dir, path) = setup(true);
+        let merger = YamlDiffMerger::new(path.clone(), GENERATED.to_string())
+            .with_base_snapshot(dir.path());
+
+        let diff = merger.compute_diff().unwrap();
+        assert_eq!(diff.statistics.conflicts, 1);
+        assert_eq!(diff.changes.conflicts[0].label(), "orders.region.description");
+        assert_eq!(diff.statistics.added_dimensions, 1);
+        assert_eq!(diff.statistics.removed_dimensions, 0);
+
+        merger.apply_changes(&diff).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(has_conflict_markers(&content));
+        assert!(content.contains("description: Sales region"));
+        assert!(content.contains("description: Customer region"));
+
+        // Keeping the local side of the conflict leaves a valid file
+        let mut resolved = Vec::new();
+        let mut in_generated_side = false;
+        for line in content.lines() {
+            if line.starts_with("=======") {
+                in_generated_side = true;
+            } else if line.starts_with(">>>>>>>") {
+                in_generated_side = false;
+            } else if !line.starts_with("<<<<<<<") && !in_generated_side {
+                resolved.push(line);
+            }
+        }
+        let resolved = resolved.join("\n");
+        let merged: YamlFile = serde_yaml::from_str(&resolved).unwrap();
+        let dims: HashMap<&str, &Dimension> = merged.models[0]
+            .dimensions
+            .iter()
+            .map(|d| (d.name.as_str(), d))
+            .collect();
+
+        assert_eq!(dims["status"].description.as_deref(), Some("Order status"));
+        assert_eq!(dims["status"].type_.as_deref(), Some("text"));
+        assert_eq!(dims["region"].description.as_deref(), Some("Sales region"));
+        assert!(dims.contains_key("notes"));
+        assert!(dims.contains_key("created_at"));
+        assert!(!dims.contains_key("legacy"));
+
+        // The generated content becomes the next base
+        let snapshot = fs::read_to_string(dir.path().join(STATE_DIR).join("orders.yml")).unwrap();
+        assert_eq!(snapshot, GENERATED);
+    }
+
+    #[test]
+    fn test_merge_without_base_follows_generated_elements() {
+        let (_dir, path) = setup(false);
+        let merger = YamlDiffMerger::new(path.clone(), GENERATED.to_string());
+
+        let diff = merger.compute_diff().unwrap();
+        assert_eq!(diff.statistics.conflicts, 0);
+        merger.apply_changes(&diff).unwrap();
+
+        let merged: YamlFile = serde_yaml::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let names: Vec<&str> = merged.models[0]
+            .dimensions
+            .iter()
+            .map(|d| d.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["status", "region", "legacy", "created_at"]);
+        assert_eq!(
+            merged.models[0].dimensions[0].description.as_deref(),
+            Some("Order status")
+        );
+    }
+}